@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Batched radar cube publish benchmark
+//!
+//! Publishes radar cube sized messages through a [`BatchSink`] to a second
+//! Zenoh session over TCP loopback at batch sizes of 1, 2 and 4 cubes, the
+//! `--cube-batch` values worth comparing.  Each iteration publishes four
+//! cubes so the time per element is the cost of a cube at that batch size.
+//! The subscriber discards the samples in its callback.
+//!
+//! ```bash
+//! cargo bench --bench cube_publish
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use radarpub::{
+    latency::BatchMetrics,
+    sink::{BatchSink, Sink},
+};
+use std::{
+    hint::black_box,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use zenoh::{
+    bytes::{Encoding, ZBytes},
+    qos::CongestionControl,
+    Config, Session,
+};
+
+/// Cubes published per iteration, a whole number of every batch size.
+const CUBES: usize = 4;
+
+/// Size of a serialized [2, 256, 4, 128] complex i16 radar cube.
+const CUBE_BYTES: usize = 2 * 256 * 4 * 128 * 2 * 2;
+
+fn local_config(listen: &[String], connect: &[String]) -> Config {
+    let mut config = Config::default();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+        .insert_json5("listen/endpoints", &serde_json::to_string(listen).unwrap())
+        .unwrap();
+    config
+        .insert_json5(
+            "connect/endpoints",
+            &serde_json::to_string(connect).unwrap(),
+        )
+        .unwrap();
+    config
+}
+
+/// A publishing session connected over TCP loopback to a listening session.
+async fn sessions() -> (Session, Session) {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let endpoint = vec![format!("tcp/127.0.0.1:{}", port)];
+    let listener = zenoh::open(local_config(&endpoint, &[])).await.unwrap();
+    let publisher = zenoh::open(local_config(&[], &endpoint)).await.unwrap();
+    (publisher, listener)
+}
+
+fn bench_cube_publish(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let (session, listener) = rt.block_on(sessions());
+    let _subscriber = rt
+        .block_on(
+            listener
+                .declare_subscriber("rt/radar/cube")
+                .callback(|sample| drop(black_box(sample))),
+        )
+        .unwrap();
+    // Let the subscription reach the publishing session.
+    std::thread::sleep(Duration::from_millis(500));
+
+    let payload = ZBytes::from(vec![0x55u8; CUBE_BYTES]);
+    let encoding = Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/RadarCube");
+
+    let mut group = c.benchmark_group("cube_publish");
+    group.throughput(Throughput::Elements(CUBES as u64));
+    for batch in [1, 2, 4] {
+        let publisher = rt
+            .block_on(
+                session
+                    .declare_publisher("rt/radar/cube")
+                    .congestion_control(CongestionControl::Block),
+            )
+            .unwrap();
+        let metrics = Arc::new(Mutex::new(BatchMetrics::new()));
+        let sink = BatchSink::new(publisher, batch, Duration::from_secs(1), metrics);
+
+        group.bench_with_input(BenchmarkId::from_parameter(batch), &batch, |b, _| {
+            b.iter(|| {
+                rt.block_on(async {
+                    for _ in 0..CUBES {
+                        sink.publish(payload.clone(), encoding.clone())
+                            .await
+                            .unwrap();
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cube_publish);
+criterion_main!(benches);
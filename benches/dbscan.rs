@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! DBSCAN neighbourhood search benchmark
+//!
+//! Clusters windows of 300, 600 and 1200 radar targets, six frames of 50 to
+//! 200 targets, on a single thread.  Run once without and once with the
+//! kdtree feature to compare the brute force and k-d tree neighbourhoods.
+//!
+//! The threads group clusters a dense frame of 3000 targets with 1, 2, 4 and
+//! one thread per core (0), which only differ with the rayon feature.
+//!
+//! ```bash
+//! cargo bench --bench dbscan
+//! cargo bench --bench dbscan --features kdtree
+//! cargo bench --bench dbscan --features rayon -- threads
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use radarpub::clustering::ClusteringBuilder;
+
+/// Targets spread over groups of nearby points and background clutter,
+/// generated with a fixed seed.
+fn targets(n: usize) -> Vec<[f32; 4]> {
+    let mut seed = 0x2545_f491_u32;
+    let mut random = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f32 / u32::MAX as f32
+    };
+
+    (0..n)
+        .map(|i| match i % 4 {
+            // Clutter within the 100 m field of view.
+            0 => [random() * 100.0, random() * 100.0 - 50.0, 0.0, 0.0],
+            // Objects of about 2 m on a 10 m grid.
+            _ => {
+                let object = (i / 4) % 40;
+                let x = (object % 8) as f32 * 10.0 + random() * 2.0;
+                let y = (object / 8) as f32 * 10.0 - 25.0 + random() * 2.0;
+                [x, y, 0.0, 0.0]
+            }
+        })
+        .collect()
+}
+
+fn bench_dbscan(c: &mut Criterion) {
+    let clustering = ClusteringBuilder::new()
+        .eps(1.0)
+        .point_limit(5)
+        .threads(1)
+        .build();
+
+    let mut group = c.benchmark_group("dbscan");
+    for n in [300, 600, 1200] {
+        let points = targets(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &points, |b, points| {
+            b.iter(|| clustering.eps_sweep(points, &[1.0]))
+        });
+    }
+    group.finish();
+}
+
+fn bench_threads(c: &mut Criterion) {
+    let points = targets(3000);
+
+    let mut group = c.benchmark_group("threads");
+    group.throughput(Throughput::Elements(points.len() as u64));
+    for threads in [1, 2, 4, 0] {
+        let mut clustering = ClusteringBuilder::new()
+            .eps(0.5)
+            .point_limit(3)
+            .threads(threads)
+            .build();
+        let mut timestamp = 0;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threads),
+            &points,
+            |b, points| {
+                b.iter(|| {
+                    timestamp += 55_000_000;
+                    clustering.cluster(points.clone(), timestamp)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_dbscan, bench_threads);
+criterion_main!(benches);
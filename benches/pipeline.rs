@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Radar frame processing pipeline benchmark
+//!
+//! Measures the stages between a decoded CAN frame and the Zenoh put for a
+//! full frame of 256 targets: the spherical to Cartesian transform, packing
+//! the targets into a PointCloud2 and its CDR serialisation, for both the
+//! targets and the clusters point layouts.  The cube path is covered by
+//! `RadarCubeReader::read` of a single FRAME_DATA packet.
+//!
+//! The point packing follows the default layout of `format_targets` and
+//! `format_clusters` in the radarpub binary, which benchmarks cannot link,
+//! so changes to those layouts should be mirrored here.  Save a baseline
+//! before a change and compare against it afterwards.
+//!
+//! ```bash
+//! cargo bench --bench pipeline -- --save-baseline main
+//! cargo bench --bench pipeline -- --baseline main
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use edgefirst_schemas::{builtin_interfaces::Time, sensor_msgs, serde_cdr, std_msgs};
+use radarpub::{
+    can::Target,
+    common::transform_xyz,
+    eth::{CubeHeader, DebugHeader, PortHeader, RadarCubeReader, SMS_PACKET_SIZE},
+};
+use std::hint::black_box;
+
+/// Targets in a full radar frame.
+const TARGETS: usize = 256;
+
+/// sensor_msgs/PointField FLOAT32 datatype.
+const FLOAT32: u8 = 7;
+
+/// A full frame of targets spread over the field of view.
+fn frame() -> Vec<Target> {
+    (0..TARGETS)
+        .map(|i| Target {
+            range: 1.0 + (i % 64) as f64 * 1.5,
+            azimuth: (i % 32) as f64 * 3.0 - 48.0,
+            elevation: (i % 8) as f64 - 4.0,
+            speed: (i % 16) as f64 * 0.5 - 4.0,
+            rcs: (i % 20) as f64 - 10.0,
+            power: -80.0 + (i % 40) as f64,
+            noise: -100.0,
+            accuracy: 0,
+        })
+        .collect()
+}
+
+/// Pack the targets as FLOAT32 x, y, z, speed, power, rcs points followed
+/// by the `extra` fields, the cluster id for the clusters.
+fn point_cloud(targets: &[Target], extra: &[&str]) -> sensor_msgs::PointCloud2 {
+    let names = ["x", "y", "z", "speed", "power", "rcs"];
+    let fields: Vec<_> = names
+        .iter()
+        .chain(extra)
+        .enumerate()
+        .map(|(i, name)| sensor_msgs::PointField {
+            name: name.to_string(),
+            offset: 4 * i as u32,
+            datatype: FLOAT32,
+            count: 1,
+        })
+        .collect();
+    let point_step = 4 * fields.len() as u32;
+
+    let mut data = Vec::with_capacity(point_step as usize * targets.len());
+    for (i, target) in targets.iter().enumerate() {
+        let [x, y, z] = transform_xyz(
+            target.range as f32,
+            target.azimuth as f32,
+            target.elevation as f32,
+            false,
+        );
+        for elem in [
+            x,
+            y,
+            z,
+            target.speed as f32,
+            target.power as f32,
+            target.rcs as f32,
+        ] {
+            data.extend_from_slice(&elem.to_ne_bytes());
+        }
+        for _ in extra {
+            data.extend_from_slice(&((i % 12) as f32).to_ne_bytes());
+        }
+    }
+
+    sensor_msgs::PointCloud2 {
+        header: std_msgs::Header {
+            stamp: Time { sec: 0, nanosec: 0 },
+            frame_id: String::from("radar"),
+        },
+        height: 1,
+        width: targets.len() as u32,
+        fields,
+        is_bigendian: false,
+        point_step,
+        row_step: point_step * targets.len() as u32,
+        data,
+        is_dense: true,
+    }
+}
+
+/// SMS transport packet with the debug header of the frame.
+fn packet(counter: u16, flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut payload = 1u32.to_be_bytes().to_vec();
+    payload.extend_from_slice(&[flags, 0, 0, 0]);
+    payload.extend_from_slice(body);
+
+    let mut packet = vec![0x7E, 1, 14];
+    packet.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[5, 0, 0, 0, 0x01]);
+    packet.extend_from_slice(&counter.to_be_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(&payload);
+    packet
+}
+
+/// Start of a frame with a [1, 256, 4, 128] cube, larger than a single data
+/// packet so every packet is copied into the cube.
+fn start_of_frame() -> Vec<u8> {
+    let mut body = 5u32.to_be_bytes().to_vec();
+    body.resize(PortHeader::LEN, 0);
+    // Big-endian payload.
+    body[20] = 1;
+    let mut cube = vec![0; CubeHeader::LEN];
+    cube[24..26].copy_from_slice(&256i16.to_be_bytes());
+    cube[28..30].copy_from_slice(&128i16.to_be_bytes());
+    cube[30] = 4;
+    cube[31] = 1;
+    body.extend_from_slice(&cube);
+    packet(0, DebugHeader::START_OF_FRAME, &body)
+}
+
+fn bench_targets(c: &mut Criterion) {
+    let targets = frame();
+    let mut group = c.benchmark_group("targets");
+    group.throughput(Throughput::Elements(TARGETS as u64));
+
+    group.bench_function("transform_xyz", |b| {
+        b.iter(|| {
+            for t in black_box(&targets) {
+                black_box(transform_xyz(
+                    t.range as f32,
+                    t.azimuth as f32,
+                    t.elevation as f32,
+                    false,
+                ));
+            }
+        })
+    });
+
+    group.bench_function("format", |b| {
+        b.iter(|| black_box(point_cloud(black_box(&targets), &[])))
+    });
+
+    for (name, extra) in [
+        ("targets_cdr", &[][..]),
+        ("clusters_cdr", &["cluster_id"][..]),
+    ] {
+        let msg = point_cloud(&targets, extra);
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(serde_cdr::serialize(black_box(&msg)).unwrap()))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_cube(c: &mut Criterion) {
+    let start = start_of_frame();
+    // A full size data packet, the payload is a whole number of elements.
+    let data_len = (SMS_PACKET_SIZE - 14 - DebugHeader::LEN) / 4 * 4;
+    let data = packet(1, DebugHeader::FRAME_DATA, &vec![0x55; data_len]);
+
+    let mut group = c.benchmark_group("cube");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("frame_data", |b| {
+        // The reader is dropped outside of the measurement.
+        b.iter_batched_ref(
+            || {
+                let mut reader = RadarCubeReader::new();
+                reader.read(&start).unwrap();
+                reader
+            },
+            |reader| black_box(reader.read(black_box(&data)).unwrap()),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_targets, bench_cube);
+criterion_main!(benches);
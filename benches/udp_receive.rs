@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Port 5 cube packet receive benchmark
+//!
+//! Sends batches of radar cube sized packets over loopback and reads them
+//! back with the recvmmsg reader and, with the io-uring feature, the io_uring
+//! reader with and without SQPOLL.  Both sides run on the benchmark thread so
+//! the send cost is included equally in every variant.
+//!
+//! ```bash
+//! cargo bench --bench udp_receive --features io-uring
+//! ```
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+#[cfg(target_os = "linux")]
+fn bench_port5(c: &mut Criterion) {
+    use criterion::Throughput;
+    use radarpub::{common::set_socket_bufsize, eth::SMS_PACKET_SIZE, net::MmsgReader};
+    use std::{io::ErrorKind, net::UdpSocket, os::fd::AsRawFd};
+
+    const BATCH: usize = 64;
+
+    fn sockets(nonblocking: bool) -> (UdpSocket, UdpSocket) {
+        let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        set_socket_bufsize("bench", &rx, 4 * 1024 * 1024);
+        rx.set_nonblocking(nonblocking).unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        tx.connect(rx.local_addr().unwrap()).unwrap();
+        (tx, rx)
+    }
+
+    fn send_batch(tx: &UdpSocket, packet: &[u8]) {
+        for _ in 0..BATCH {
+            tx.send(packet).unwrap();
+        }
+    }
+
+    let packet = vec![0x7E; SMS_PACKET_SIZE];
+    let mut group = c.benchmark_group("port5");
+    group.throughput(Throughput::Elements(BATCH as u64));
+
+    let (tx, rx) = sockets(true);
+    let mut reader = MmsgReader::new(BATCH);
+    group.bench_function("recvmmsg", |b| {
+        b.iter(|| {
+            send_batch(&tx, &packet);
+            let mut received = 0;
+            while received < BATCH {
+                match reader.read(rx.as_raw_fd()) {
+                    Ok(packets) => received += packets.len() / SMS_PACKET_SIZE,
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => (),
+                    Err(err) => panic!("recvmmsg error: {:?}", err),
+                }
+            }
+        })
+    });
+
+    #[cfg(feature = "io-uring")]
+    for (name, sqpoll) in [("io_uring", false), ("io_uring_sqpoll", true)] {
+        use radarpub::net::UringReader;
+
+        let (tx, rx) = sockets(false);
+        let mut reader = match UringReader::new(rx.as_raw_fd(), BATCH, sqpoll) {
+            Ok(reader) => reader,
+            Err(err) => {
+                eprintln!("skipping {}: {:?}", name, err);
+                continue;
+            }
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                send_batch(&tx, &packet);
+                let mut received = 0;
+                while received < BATCH {
+                    received += reader.read().unwrap().len() / SMS_PACKET_SIZE;
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bench_port5(_c: &mut Criterion) {}
+
+criterion_group!(benches, bench_port5);
+criterion_main!(benches);
@@ -7,25 +7,30 @@
 //! radar sensor and visualize the data using Rerun. It supports:
 //! - Live CAN interface reading for target data
 //! - Live UDP interface reading for radar cube data
-//! - PCAP file replay for offline analysis
+//! - PCAP file replay for offline analysis, optionally with the capture
+//!   timing
 //! - Numpy export for post-processing
 
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use futures::{stream, Stream, StreamExt};
 use log::{debug, error, trace};
-use ndarray::{s, Array2};
-use ndarray_npy::write_npy;
-use num::complex::Complex32;
 use rerun::RecordingStream;
 use std::{fs::File, net::Ipv4Addr, thread};
+use tokio::time::Instant;
 
 // Import from radarpub library
 use radarpub::{
-    eth::{RadarCube, RadarCubeReader, SMSError, TransportHeaderSlice, SMS_PACKET_SIZE},
+    dump::NpzExporter,
+    eth::{RadarCubeReader, SMSError, TransportHeaderSlice, SMS_PACKET_SIZE},
     net,
+    viewer::{
+        CubeSlice, CubeSlicer, PowerNormaliser, RateLimiter, DEFAULT_COLORMAP_EMA_ALPHA,
+        DEFAULT_CUBE_RATE, RADAR_TIMELINE,
+    },
 };
 
 #[cfg(feature = "can")]
-use radarpub::can;
+use radarpub::{can, common::transform_xyz};
 
 #[derive(Parser, Debug, Clone)]
 #[command(
@@ -50,14 +55,46 @@ struct Args {
     #[arg(short, long)]
     port: Option<u16>,
 
-    /// Save Numpy files to this directory, one per frame
+    /// Save Numpy archives with the cube and its metadata to this directory,
+    /// one per exported frame
     #[arg(short, long)]
     numpy: Option<String>,
 
+    /// Export only every Nth frame with --numpy
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    numpy_every: u64,
+
+    /// Maximum rate in Hz of the cube tensor logs, the bin properties and
+    /// stats are still logged for every cube. Defaults to 5 Hz for a viewer
+    /// and to every cube when recording to a file, 0 logs every cube.
+    #[arg(long, value_name = "HZ")]
+    rerun_cube_rate: Option<f64>,
+
+    /// Sequence and rx antenna of the displayed cube slice
+    #[arg(long, value_name = "SEQ,RX", default_value = "0,0")]
+    rerun_slice: CubeSlice,
+
+    /// Smoothing factor from 0 to 1 of the target power range the colormap
+    /// is normalised over, lower values keep the target colours steadier
+    /// and 1 normalises every frame over its own range.
+    #[arg(long, value_name = "ALPHA", default_value_t = DEFAULT_COLORMAP_EMA_ALPHA)]
+    colormap_ema_alpha: f64,
+
+    /// Mirror the y axis of the targets like radarpub --mirror, for sensors
+    /// mounted upside-down
+    #[arg(long)]
+    mirror: bool,
+
     /// Read from a PCAP file instead of a live interface
     #[arg()]
     pcap: Option<String>,
 
+    /// Replay the PCAP file with the capture timing of its packets through
+    /// the live cube loop, sped up by this factor. Without it the PCAP file
+    /// is read as fast as possible.
+    #[arg(long, value_name = "FACTOR", requires = "pcap")]
+    replay_speed: Option<f64>,
+
     /// Enable radar data cube streaming (UDP)
     #[arg(long)]
     cube: bool,
@@ -66,11 +103,50 @@ struct Args {
     #[cfg(feature = "can")]
     #[arg(long)]
     device: Option<String>,
+
+    /// CAN identifier of the radar frame header, in decimal or 0x prefixed
+    /// hexadecimal, the targets follow it
+    #[cfg(feature = "can")]
+    #[arg(long, default_value = "0x400", value_parser = can::parse_can_id_base)]
+    can_id_base: u32,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
     let args = Args::parse();
+    if args
+        .replay_speed
+        .is_some_and(|speed| speed.is_nan() || speed <= 0.0)
+    {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "the replay speed must be greater than 0",
+            )
+            .exit();
+    }
+    if !(args.colormap_ema_alpha > 0.0 && args.colormap_ema_alpha <= 1.0) {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "the colormap EMA alpha must be greater than 0 and at most 1",
+            )
+            .exit();
+    }
+
+    let mut exporter = args
+        .numpy
+        .as_ref()
+        .map(|dir| NpzExporter::new(dir, args.numpy_every))
+        .transpose()?;
+
+    // Recordings keep every cube unless a rate is given.
+    let cube_rate = args.rerun_cube_rate.unwrap_or(match args.record {
+        Some(_) => 0.0,
+        None => DEFAULT_CUBE_RATE,
+    });
+    let mut limiter = RateLimiter::new(cube_rate);
+    let mut slicer = CubeSlicer::new(args.rerun_slice);
 
     // Initialize Rerun recording stream
     let rr = if let Some(addr) = args.connect {
@@ -88,14 +164,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Handle different data sources
-    if let Some(pcap) = args.pcap {
+    if let (Some(pcap), Some(speed)) = (&args.pcap, args.replay_speed) {
+        // Timed PCAP replay
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(udp_loop_pcap(
+                &rr,
+                pcap,
+                speed,
+                &mut exporter,
+                &mut limiter,
+                &mut slicer,
+            ))?;
+    } else if let Some(pcap) = args.pcap {
         // Offline PCAP replay
-        pcap_loop(&rr, &pcap, &args.numpy)?;
+        pcap_loop(&rr, &pcap, &mut exporter, &mut limiter, &mut slicer)?;
     } else {
         // Live radar data
         #[cfg(feature = "can")]
         if let Some(device) = args.device {
             let rr2 = rr.clone();
+            let ema_alpha = args.colormap_ema_alpha;
+            let mirror = args.mirror;
+            let ids = can::CanIds::new(args.can_id_base).expect("checked by its parser");
 
             if args.cube {
                 // Cube data only
@@ -107,7 +199,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .enable_all()
                                 .build()
                                 .unwrap()
-                                .block_on(udp_loop(&rr, &args.numpy))
+                                .block_on(async {
+                                    udp_loop(
+                                        udp_packets()?,
+                                        &rr,
+                                        &mut exporter,
+                                        &mut limiter,
+                                        &mut slicer,
+                                    )
+                                    .await
+                                })
                                 .unwrap();
                         })?;
                 cube_thread.join().unwrap();
@@ -121,7 +222,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .enable_all()
                                 .build()
                                 .unwrap()
-                                .block_on(can_loop(&rr2, Some(device)));
+                                .block_on(can_loop(&rr2, Some(device), ids, ema_alpha, mirror));
                         })?;
                 can_thread.join().unwrap();
             }
@@ -138,7 +239,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .enable_all()
                         .build()
                         .unwrap()
-                        .block_on(udp_loop(&rr, &args.numpy))
+                        .block_on(async {
+                            udp_loop(
+                                udp_packets()?,
+                                &rr,
+                                &mut exporter,
+                                &mut limiter,
+                                &mut slicer,
+                            )
+                            .await
+                        })
                         .unwrap();
                 })?;
             cube_thread.join().unwrap();
@@ -151,49 +261,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// Format radar cube for visualization
-///
-/// Extracts a 2D slice from the 4D radar cube for display and optionally saves
-/// to Numpy format
-fn format_cube(
-    cube: &RadarCube,
-    numpy: &Option<String>,
-) -> Result<Array2<i16>, Box<dyn std::error::Error>> {
-    if let Some(numpy) = numpy {
-        // Numpy requires complex arrays to be either f32 or f64
-        let npdata = cube.data.mapv(|x| Complex32::new(x.re as f32, x.im as f32));
-        write_npy(
-            format!("{}/cube_{}.npy", numpy, cube.frame_counter),
-            &npdata,
-        )?;
-    }
-
-    // The radar cube shape is (sequence, range, rx antenna, doppler, complex).
-    // For display purposes, take the first sequence, first rx antenna, and the real
-    // portion
-    let data = cube.data.slice(s![1, .., 0, ..]);
-
-    // Convert to absolute values (Rerun cannot handle complex numbers)
-    let data = data.mapv(|x| x.re.abs());
-
-    trace!(
-        "format_cube shape {:?} -> {:?}",
-        cube.data.shape(),
-        data.shape()
-    );
-
-    Ok(data)
-}
-
-/// Main loop for live UDP radar cube data
-async fn udp_loop(
-    rr: &Option<RecordingStream>,
-    numpy: &Option<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(numpy) = numpy {
-        std::fs::create_dir_all(numpy)?;
-    }
-
+/// Packets received on the radar cube UDP ports 5 and 63
+fn udp_packets() -> Result<impl Stream<Item = Vec<u8>>, std::io::Error> {
     let (tx5, rx) = kanal::bounded_async(128);
     let tx63 = tx5.clone();
 
@@ -205,7 +274,7 @@ async fn udp_loop(
                 .enable_all()
                 .build()
                 .unwrap()
-                .block_on(net::port5(tx5));
+                .block_on(net::port5(tx5, false, 10));
         })?;
 
     thread::Builder::new()
@@ -218,24 +287,90 @@ async fn udp_loop(
                 .block_on(net::port63(tx63));
         })?;
 
-    let mut reader = RadarCubeReader::default();
-
-    loop {
-        let msg = match rx.recv().await {
-            Ok(msg) => msg,
+    Ok(stream::unfold(rx, |rx| async move {
+        match rx.recv().await {
+            Ok(msg) => Some((msg, rx)),
             Err(e) => {
                 error!("recv error: {:?}", e);
-                continue;
+                None
+            }
+        }
+    }))
+}
+
+/// UDP payloads of the radar cube packets in a PCAP file, each delayed to
+/// its capture time relative to the first packet divided by the replay
+/// speed.  Packets without a capture timestamp are not delayed.
+fn pcap_packets(path: &str, speed: f64) -> Result<impl Stream<Item = Vec<u8>>, std::io::Error> {
+    let capture = pcarp::Capture::new(File::open(path)?);
+    let origin: Option<(std::time::SystemTime, Instant)> = None;
+
+    Ok(stream::unfold(
+        (capture, origin),
+        move |(mut capture, mut origin)| async move {
+            loop {
+                let packet = match capture.next()? {
+                    Ok(packet) => packet,
+                    Err(err) => {
+                        error!("pcap error: {:?}", err);
+                        return None;
+                    }
+                };
+                let payload = match etherparse::SlicedPacket::from_ethernet(&packet.data) {
+                    Ok(etherparse::SlicedPacket {
+                        transport: Some(etherparse::TransportSlice::Udp(udp)),
+                        ..
+                    }) if TransportHeaderSlice::from_slice(udp.payload()).is_ok() => {
+                        udp.payload().to_vec()
+                    }
+                    Ok(_) => continue,
+                    Err(err) => {
+                        error!("Err {:?}", err);
+                        continue;
+                    }
+                };
+
+                if let Some(timestamp) = packet.timestamp {
+                    let (first, start) = *origin.get_or_insert((timestamp, Instant::now()));
+                    let offset = timestamp.duration_since(first).unwrap_or_default();
+                    tokio::time::sleep_until(start + offset.div_f64(speed)).await;
+                }
+
+                return Some((payload, (capture, origin)));
             }
-        };
+        },
+    ))
+}
 
-        let n_msg = msg.len() / SMS_PACKET_SIZE;
+/// PCAP file replay through the [`udp_loop`] with the capture timing
+async fn udp_loop_pcap(
+    rr: &Option<RecordingStream>,
+    path: &str,
+    speed: f64,
+    exporter: &mut Option<NpzExporter>,
+    limiter: &mut RateLimiter,
+    slicer: &mut CubeSlicer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    udp_loop(pcap_packets(path, speed)?, rr, exporter, limiter, slicer).await
+}
 
-        for i in 0..n_msg {
-            let start = i * SMS_PACKET_SIZE;
-            let end = start + SMS_PACKET_SIZE;
+/// Main loop for UDP radar cube data, live from the radar ports or replayed
+/// from a PCAP file.  Returns once the packet source is exhausted.
+async fn udp_loop(
+    packets: impl Stream<Item = Vec<u8>>,
+    rr: &Option<RecordingStream>,
+    exporter: &mut Option<NpzExporter>,
+    limiter: &mut RateLimiter,
+    slicer: &mut CubeSlicer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut packets = std::pin::pin!(packets);
+    let mut reader = RadarCubeReader::default();
 
-            match reader.read(&msg[start..end]) {
+    while let Some(msg) = packets.next().await {
+        // The live ports batch whole SMS packets, a PCAP payload is a
+        // single packet.
+        for packet in msg.chunks(SMS_PACKET_SIZE) {
+            match reader.read(packet) {
                 Ok(Some(cubemsg)) => {
                     let badcount = cubemsg
                         .data
@@ -257,11 +392,22 @@ async fn udp_loop(
                         error!("dropped {} packets", cubemsg.packets_skipped);
                     }
 
-                    let cube = format_cube(&cubemsg, numpy)?;
+                    if let Some(exporter) = exporter {
+                        exporter.export(&cubemsg)?;
+                    }
 
                     if let Some(rr) = rr {
-                        let tensor = rerun::Tensor::try_from(cube)?;
-                        rr.log("cube", &tensor)?;
+                        rr.set_time(
+                            RADAR_TIMELINE,
+                            rerun::TimeCell::from_timestamp_nanos_since_epoch(
+                                cubemsg.timestamp as i64 * 1000,
+                            ),
+                        );
+                        if limiter.ready(cubemsg.timestamp) {
+                            let cube = slicer.format_cube(&cubemsg.data);
+                            let tensor = rerun::Tensor::try_from(cube)?;
+                            rr.log("cube", &tensor)?;
+                        }
 
                         rr.log(
                             "cube/speed_per_bin",
@@ -303,18 +449,18 @@ async fn udp_loop(
             }
         }
     }
+
+    Ok(())
 }
 
 /// PCAP file replay loop
 fn pcap_loop(
     rr: &Option<RecordingStream>,
     path: &String,
-    numpy: &Option<String>,
+    exporter: &mut Option<NpzExporter>,
+    limiter: &mut RateLimiter,
+    slicer: &mut CubeSlicer,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(numpy) = numpy {
-        std::fs::create_dir_all(numpy)?;
-    }
-
     let file = File::open(path)?;
     let mut reader = RadarCubeReader::default();
 
@@ -326,11 +472,22 @@ fn pcap_loop(
                     if TransportHeaderSlice::from_slice(udp.payload()).is_ok() {
                         match reader.read(udp.payload()) {
                             Ok(Some(cubemsg)) => {
-                                let cube = format_cube(&cubemsg, numpy)?;
+                                if let Some(exporter) = exporter {
+                                    exporter.export(&cubemsg)?;
+                                }
 
                                 if let Some(rr) = rr {
-                                    let tensor = rerun::Tensor::try_from(cube)?;
-                                    rr.log("cube", &tensor)?;
+                                    rr.set_time(
+                                        RADAR_TIMELINE,
+                                        rerun::TimeCell::from_timestamp_nanos_since_epoch(
+                                            cubemsg.timestamp as i64 * 1000,
+                                        ),
+                                    );
+                                    if limiter.ready(cubemsg.timestamp) {
+                                        let cube = slicer.format_cube(&cubemsg.data);
+                                        let tensor = rerun::Tensor::try_from(cube)?;
+                                        rr.log("cube", &tensor)?;
+                                    }
                                 }
                             }
                             Ok(None) => (),
@@ -350,7 +507,13 @@ fn pcap_loop(
 
 /// Live CAN target data loop
 #[cfg(feature = "can")]
-async fn can_loop(rr: &Option<RecordingStream>, device: Option<String>) {
+async fn can_loop(
+    rr: &Option<RecordingStream>,
+    device: Option<String>,
+    ids: can::CanIds,
+    ema_alpha: f64,
+    mirror: bool,
+) {
     use rerun::Points3D;
     use tokio::task::yield_now;
 
@@ -363,30 +526,32 @@ async fn can_loop(rr: &Option<RecordingStream>, device: Option<String>) {
 
     debug!("opening CAN interface {}", iface);
     let sock = socketcan::tokio::CanSocket::open(&iface).unwrap();
+    let mut normaliser = PowerNormaliser::new(ema_alpha);
 
     loop {
-        match can::read_message(&sock).await {
+        match can::read_message(&sock, &ids).await {
             Err(err) => println!("Error: {:?}", err),
             Ok(msg) => {
                 trace!("radar CAN header {:?}", msg.header);
+                let targets = &msg.targets[..msg.header.n_targets];
+                normaliser.update(targets.iter().map(|tgt| tgt.power));
 
                 if let Some(rr) = rr {
+                    let colors = targets
+                        .iter()
+                        .map(|tgt| colormap_viridis_srgb(normaliser.normalize(tgt.power)));
                     rr.log(
                         "radar/targets",
-                        &Points3D::new((0..msg.header.n_targets).map(|idx| {
-                            let tgt = &msg.targets[idx];
+                        &Points3D::new(targets.iter().map(|tgt| {
                             transform_xyz(
                                 tgt.range as f32,
                                 tgt.azimuth as f32,
                                 tgt.elevation as f32,
-                                false,
+                                mirror,
                             )
                         }))
                         .with_radii([0.5])
-                        .with_colors(
-                            msg.targets
-                                .map(|tgt| colormap_viridis_srgb(tgt.power as f32)),
-                        ),
+                        .with_colors(colors),
                     )
                     .unwrap()
                 }
@@ -395,23 +560,6 @@ async fn can_loop(rr: &Option<RecordingStream>, device: Option<String>) {
     }
 }
 
-/// Convert spherical coordinates to Cartesian XYZ
-#[cfg(feature = "can")]
-fn transform_xyz(range: f32, azimuth: f32, elevation: f32, mirror: bool) -> [f32; 3] {
-    use core::f32::consts::PI;
-
-    let azi = azimuth / 180.0 * PI;
-    let ele = elevation / 180.0 * PI;
-    let x = range * ele.cos() * azi.cos();
-    let y = range * ele.cos() * azi.sin();
-    let z = range * ele.sin();
-    if mirror {
-        [x, -y, z]
-    } else {
-        [x, y, z]
-    }
-}
-
 /// Viridis colormap for power visualization
 #[cfg(feature = "can")]
 fn colormap_viridis_srgb(t: f32) -> [u8; 4] {
@@ -425,7 +573,7 @@ fn colormap_viridis_srgb(t: f32) -> [u8; 4] {
     const C5: Vec3A = Vec3A::new(4.776_385, -13.745_146, -65.353_035);
     const C6: Vec3A = Vec3A::new(-5.435_456, 4.645_852_6, 26.312_435);
 
-    debug_assert!((0.0..=1.0).contains(&t));
+    let t = t.clamp(0.0, 1.0);
 
     let c = C0 + t * (C1 + t * (C2 + t * (C3 + t * (C4 + t * (C5 + t * C6)))));
 
@@ -10,15 +10,31 @@
 //! - RadarCube 4D tensor data
 //! - TF transform frames
 //!
+//! The point layouts published by radarpub on `/rt/radar/schema` are checked
+//! against the fields the viewer reads, a mismatch is logged as a warning.
+//!
 //! This is the recommended approach for integrating with EdgeFirst Studio and
 //! the broader perception pipeline.
 
 use clap::Parser;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use radarpub::{
+    common::normalize,
+    eth::cube_from_msg,
+    schema::{layout_mismatches, parse_schema, FLOAT32, SCHEMA_VERSION},
+};
 use rerun::RecordingStream;
 use std::net::Ipv4Addr;
 use zenoh::Config;
 
+/// Fields of the point clouds read by the viewer, a track_id is optional.
+const EXPECTED_FIELDS: &[(&str, u8)] = &[
+    ("x", FLOAT32),
+    ("y", FLOAT32),
+    ("z", FLOAT32),
+    ("power", FLOAT32),
+];
+
 #[derive(Parser, Debug, Clone)]
 #[command(
     author,
@@ -174,6 +190,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
     }
 
+    // Check the point layouts, radarpub republishes the schema every second
+    // so only changes are reported.
+    info!("Subscribing to /rt/radar/schema");
+    let schema_sub = session
+        .declare_subscriber("/rt/radar/schema")
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        let mut last = Vec::new();
+        loop {
+            match schema_sub.recv_async().await {
+                Ok(sample) => {
+                    let payload = sample.payload().to_bytes();
+                    if payload[..] != last[..] {
+                        check_schema(&payload);
+                        last = payload.to_vec();
+                    }
+                }
+                Err(e) => {
+                    error!("Subscriber error: {:?}", e);
+                    break;
+                }
+            }
+        }
+    });
+
     // Subscribe to TF transforms
     info!("Subscribing to /tf_static");
     let rr_clone = rr.clone();
@@ -203,6 +245,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Warn about the point cloud topics whose published layout differs from
+/// the fields read by the viewer.
+fn check_schema(payload: &[u8]) {
+    let schema = serde_json::from_slice::<serde_json::Value>(payload)
+        .ok()
+        .and_then(|schema| parse_schema(&schema));
+    let (version, topics) = match schema {
+        Some(schema) => schema,
+        None => {
+            warn!("Unreadable radar schema, point layouts are not checked");
+            return;
+        }
+    };
+    if version != SCHEMA_VERSION {
+        warn!(
+            "Radar schema version {} differs from the viewer's {}, update the viewer",
+            version, SCHEMA_VERSION
+        );
+    }
+
+    for (topic, layout) in topics {
+        let mismatches = layout_mismatches(&layout, EXPECTED_FIELDS);
+        match mismatches.is_empty() {
+            true => info!(
+                "{} point layout: {} bytes per point",
+                topic, layout.point_step
+            ),
+            false => warn!(
+                "{} point layout differs from the viewer, the points may be misread: {}",
+                topic,
+                mismatches.join(", ")
+            ),
+        }
+    }
+}
+
 /// Handle PointCloud2 messages (targets or clusters)
 fn handle_pointcloud(
     rr: &RecordingStream,
@@ -258,15 +336,8 @@ fn handle_radar_cube(
         cube.cube.len()
     );
 
-    // Convert cube data to tensor for visualization
-    let data = ndarray::Array::from_shape_vec(
-        cube.shape.iter().map(|&x| x as usize).collect::<Vec<_>>(),
-        cube.cube
-            .iter()
-            .map(|x| x.unsigned_abs())
-            .collect::<Vec<_>>(),
-    )?;
-
+    // Visualize the magnitude of the complex samples
+    let data = cube_from_msg(&cube)?.magnitude();
     let tensor = rerun::Tensor::try_from(data)?.with_dim_names(["SEQ", "RANGE", "RX", "DOPPLER"]);
 
     rr.log("radar/cube", &tensor)?;
@@ -333,7 +404,7 @@ fn parse_pointcloud2(
     let mut y_offset = None;
     let mut z_offset = None;
     let mut intensity_offset = None;
-    let mut track_id_offset = None;
+    let mut track_id_field = None;
 
     for field in &msg.fields {
         match field.name.as_str() {
@@ -341,7 +412,7 @@ fn parse_pointcloud2(
             "y" => y_offset = Some(field.offset as usize),
             "z" => z_offset = Some(field.offset as usize),
             "intensity" | "power" => intensity_offset = Some(field.offset as usize),
-            "track_id" | "id" => track_id_offset = Some(field.offset as usize),
+            "track_id" | "id" => track_id_field = Some((field.offset as usize, field.datatype)),
             _ => {}
         }
     }
@@ -361,8 +432,14 @@ fn parse_pointcloud2(
         let intensity = intensity_offset
             .map(|off| f32::from_le_bytes(point_data[off..off + 4].try_into().unwrap_or([0; 4])));
 
-        let track_id = track_id_offset
-            .map(|off| u32::from_le_bytes(point_data[off..off + 4].try_into().unwrap_or([0; 4])));
+        // radarpub packs the track ids as FLOAT32 like its other fields.
+        let track_id = track_id_field.map(|(off, datatype)| {
+            let bytes = point_data[off..off + 4].try_into().unwrap_or([0; 4]);
+            match datatype == FLOAT32 {
+                true => f32::from_le_bytes(bytes) as u32,
+                false => u32::from_le_bytes(bytes),
+            }
+        });
 
         points.push(Point {
             x,
@@ -387,11 +464,15 @@ fn extract_colors(points: &[Point]) -> Option<Vec<[u8; 4]>> {
                 .collect(),
         )
     } else if points.iter().any(|p| p.intensity.is_some()) {
-        // Otherwise use intensity
+        // Otherwise use the intensity normalized over the frame
+        let intensities: Vec<_> = points
+            .iter()
+            .map(|p| p.intensity.unwrap_or(f32::NAN))
+            .collect();
         Some(
-            points
-                .iter()
-                .map(|p| intensity_to_color(p.intensity.unwrap_or(0.0)))
+            normalize(&intensities)
+                .into_iter()
+                .map(colormap_viridis_srgb)
                 .collect(),
         )
     } else {
@@ -406,13 +487,6 @@ fn track_id_to_color(id: u32) -> [u8; 4] {
     hsv_to_rgb(hue, 0.8, 0.9)
 }
 
-/// Convert intensity to color using a colormap
-fn intensity_to_color(intensity: f32) -> [u8; 4] {
-    // Normalize intensity to 0-1 range (assuming typical range)
-    let t = (intensity / 100.0).clamp(0.0, 1.0);
-    colormap_viridis_srgb(t)
-}
-
 /// HSV to RGB conversion
 fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [u8; 4] {
     let c = v * s;
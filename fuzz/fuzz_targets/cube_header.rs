@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use radarpub::eth::{CubeHeader, CubeHeaderSlice};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(cube) = CubeHeaderSlice::from_slice(data) {
+        let header = cube.to_header();
+        let payload = cube.payload();
+        assert_eq!(
+            payload.len() + CubeHeader::LEN + cube.padding_bytes(),
+            data.len()
+        );
+        assert_eq!(header.range_gates, cube.range_gates());
+        assert_eq!(header.doppler_bins, cube.doppler_bins());
+    }
+});
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use radarpub::eth::RadarCubeReader;
+
+// The input is a sequence of packets, each prefixed by its big endian u16
+// length, fed in order to a single reader so the frame assembly state is
+// exercised across packets.
+fuzz_target!(|data: &[u8]| {
+    let mut reader = RadarCubeReader::new();
+    let mut rest = data;
+
+    while rest.len() >= 2 {
+        let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        let packet = &rest[2..(2 + len).min(rest.len())];
+        rest = &rest[2 + packet.len()..];
+
+        if let Ok(Some(cube)) = reader.read(packet) {
+            assert_eq!(cube.data.len(), cube.data.shape().iter().product::<usize>());
+        }
+    }
+});
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use radarpub::eth::TransportHeaderSlice;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(transport) = TransportHeaderSlice::from_slice(data) {
+        let _ = transport.to_header();
+        assert!(transport.len() + transport.payload().len() <= data.len());
+
+        if let Ok(port) = transport.port_header() {
+            let _ = port.to_header();
+            let _ = port.payload();
+        }
+        if let Ok(debug) = transport.debug_header() {
+            let _ = debug.to_header();
+            let _ = debug.payload();
+        }
+        if let Ok(cube) = transport.cube_header() {
+            let _ = cube.to_header();
+            let _ = cube.payload();
+        }
+        if let Ok(bin_properties) = transport.bin_properties() {
+            let _ = bin_properties.to_header();
+        }
+    }
+});
@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::bev::decay_factor;
+
+/// Long horizon detection heatmap in the radar frame.
+///
+/// Every target increments the count of the cell it falls in and the counts
+/// decay exponentially over time, so with a small decay rate the heatmap
+/// shows where detections occurred over the last minutes to hours.  The grid
+/// is allocated once from the extent and resolution, targets outside of the
+/// extent are ignored and counted.
+///
+/// The grid is stored row-major with row 0 being the furthest forward (+x)
+/// and column 0 being the furthest left (+y), the same orientation as the
+/// bird's-eye-view raster.
+#[derive(Debug, Clone)]
+pub struct Heatmap {
+    /// Extent in meters as [x_min, x_max, y_min, y_max]
+    extent: [f32; 4],
+
+    /// Cell size in meters
+    resolution: f32,
+
+    /// Exponential decay rate per second
+    decay: f32,
+
+    /// Publish interval in nanoseconds
+    interval: u64,
+
+    /// Number of cells along x
+    rows: usize,
+
+    /// Number of cells along y
+    cols: usize,
+
+    /// Decayed detection counts
+    grid: Vec<f32>,
+
+    /// Targets which fell outside of the extent
+    out_of_range: u64,
+
+    /// Timestamp of the last decay step in nanoseconds
+    timestamp: Option<u64>,
+
+    /// Timestamp of the last publication in nanoseconds
+    published: Option<u64>,
+}
+
+impl Heatmap {
+    /// Create a new heatmap.
+    ///
+    /// # Arguments
+    /// * `extent` - Radar frame extent in meters as [x_min, x_max, y_min, y_max]
+    /// * `resolution` - Cell size in meters
+    /// * `decay` - Exponential decay rate per second (0 disables decay)
+    /// * `interval` - Publish interval in seconds
+    ///
+    /// # Returns
+    /// Empty heatmap
+    pub fn new(extent: &[f32], resolution: f32, decay: f32, interval: f32) -> Self {
+        let extent = [extent[0], extent[1], extent[2], extent[3]];
+        let rows = ((extent[1] - extent[0]) / resolution).ceil().max(1.0) as usize;
+        let cols = ((extent[3] - extent[2]) / resolution).ceil().max(1.0) as usize;
+
+        Heatmap {
+            extent,
+            resolution,
+            decay,
+            interval: (interval.max(0.0) as f64 * 1e9) as u64,
+            rows,
+            cols,
+            grid: vec![0.0; rows * cols],
+            out_of_range: 0,
+            timestamp: None,
+            published: None,
+        }
+    }
+
+    /// Returns the number of cells along x (image height).
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of cells along y (image width).
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the number of targets ignored for falling outside the extent.
+    pub fn out_of_range(&self) -> u64 {
+        self.out_of_range
+    }
+
+    /// Map a radar frame coordinate to a (row, column) cell index.
+    ///
+    /// # Returns
+    /// The cell index or None if the point falls outside the extent.
+    pub fn cell(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let [x_min, x_max, y_min, y_max] = self.extent;
+        if !(x_min..=x_max).contains(&x) || !(y_min..=y_max).contains(&y) {
+            return None;
+        }
+
+        let row = ((x_max - x) / self.resolution) as usize;
+        let col = ((y_max - y) / self.resolution) as usize;
+        Some((row.min(self.rows - 1), col.min(self.cols - 1)))
+    }
+
+    /// Decay every cell according to the time elapsed since the previous
+    /// call.  The first call only records the timestamp.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Current time in nanoseconds
+    pub fn decay(&mut self, timestamp: u64) {
+        if let Some(previous) = self.timestamp {
+            let dt = timestamp.saturating_sub(previous) as f32 * 1e-9;
+            let factor = decay_factor(self.decay, dt);
+            self.grid.iter_mut().for_each(|cell| *cell *= factor);
+        }
+        self.timestamp = Some(timestamp);
+    }
+
+    /// Count the radar frame points in their cells, points outside of the
+    /// extent are only counted as out of range.
+    ///
+    /// # Arguments
+    /// * `points` - Iterator of radar frame [x, y] coordinates
+    pub fn accumulate<I: IntoIterator<Item = [f32; 2]>>(&mut self, points: I) {
+        for [x, y] in points {
+            match self.cell(x, y) {
+                Some((row, col)) => self.grid[row * self.cols + col] += 1.0,
+                None => self.out_of_range += 1,
+            }
+        }
+    }
+
+    /// Returns the decayed count of the cell at (row, column).
+    #[allow(dead_code)]
+    pub fn value(&self, row: usize, col: usize) -> f32 {
+        self.grid[row * self.cols + col]
+    }
+
+    /// Returns true once per publish interval, the first call is always due.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Current time in nanoseconds
+    pub fn publish_due(&mut self, timestamp: u64) -> bool {
+        match self.published {
+            Some(published) if timestamp.saturating_sub(published) < self.interval => false,
+            _ => {
+                self.published = Some(timestamp);
+                true
+            }
+        }
+    }
+
+    /// Encode the heatmap as a row-major mono8 image normalized to the
+    /// hottest cell.
+    pub fn to_mono8(&self) -> Vec<u8> {
+        let max = self.grid.iter().cloned().fold(0.0, f32::max);
+        if max <= 0.0 {
+            return vec![0; self.grid.len()];
+        }
+
+        self.grid
+            .iter()
+            .map(|cell| (cell / max * 255.0).round() as u8)
+            .collect()
+    }
+
+    /// Encode the mono8 image as a grayscale PNG file.
+    pub fn to_png(&self) -> Vec<u8> {
+        encode_png(self.cols, self.rows, &self.to_mono8())
+    }
+}
+
+/// Encode an 8-bit grayscale image as an uncompressed PNG.
+fn encode_png(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8-bit grayscale, deflate, adaptive filtering, no interlace.
+    ihdr.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    // Every scanline starts with filter type 0 (none).
+    let mut raw = Vec::with_capacity((width + 1) * height);
+    for row in pixels.chunks(width) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib stream of stored deflate blocks.
+    let mut idat = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        idat.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        idat.push(blocks.peek().is_none() as u8);
+        idat.extend_from_slice(&len.to_le_bytes());
+        idat.extend_from_slice(&(!len).to_le_bytes());
+        idat.extend_from_slice(block);
+    }
+    idat.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    png_chunk(&mut png, b"IHDR", &ihdr);
+    png_chunk(&mut png, b"IDAT", &idat);
+    png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600 * 1_000_000_000;
+
+    fn heatmap() -> Heatmap {
+        Heatmap::new(&[0.0, 10.0, -5.0, 5.0], 1.0, 0.0, 10.0)
+    }
+
+    #[test]
+    fn test_binning() {
+        let mut heatmap = heatmap();
+        assert_eq!(heatmap.rows(), 10);
+        assert_eq!(heatmap.cols(), 10);
+
+        // Far forward and left is the top-left corner.
+        assert_eq!(heatmap.cell(9.9, 4.9), Some((0, 0)));
+        assert_eq!(heatmap.cell(0.1, -4.9), Some((9, 9)));
+        // The extent is inclusive on both ends.
+        assert_eq!(heatmap.cell(0.0, -5.0), Some((9, 9)));
+        assert_eq!(heatmap.cell(10.0, 5.0), Some((0, 0)));
+        assert_eq!(heatmap.cell(-0.1, 0.0), None);
+        assert_eq!(heatmap.cell(5.0, f32::NAN), None);
+
+        heatmap.accumulate([
+            [9.5, 4.5],
+            [9.2, 4.1],
+            [0.5, -4.5],
+            [20.0, 0.0],
+            [5.0, -6.0],
+        ]);
+        assert_eq!(heatmap.value(0, 0), 2.0);
+        assert_eq!(heatmap.value(9, 9), 1.0);
+        assert_eq!(heatmap.out_of_range(), 2);
+
+        let image = heatmap.to_mono8();
+        assert_eq!(image.len(), 100);
+        assert_eq!(image[0], 255);
+        assert_eq!(image[99], 128);
+    }
+
+    #[test]
+    fn test_decay_hours() {
+        // Half-life of one hour.
+        let rate = std::f32::consts::LN_2 / 3600.0;
+        let mut heatmap = Heatmap::new(&[0.0, 10.0, -5.0, 5.0], 1.0, rate, 10.0);
+        heatmap.accumulate([[5.5, 0.5]; 100]);
+
+        // Decay once per radar frame second over two simulated hours.
+        for t in (0..=2 * HOUR).step_by(1_000_000_000) {
+            heatmap.decay(t);
+        }
+        assert!((heatmap.value(4, 4) - 25.0).abs() < 0.1);
+
+        // A long gap decays in a single step.
+        heatmap.decay(4 * HOUR);
+        assert!((heatmap.value(4, 4) - 6.25).abs() < 0.05);
+
+        let mut heatmap = heatmap();
+        heatmap.accumulate([[5.5, 0.5]]);
+        heatmap.decay(0);
+        heatmap.decay(10 * HOUR);
+        assert_eq!(heatmap.value(4, 4), 1.0);
+    }
+
+    #[test]
+    fn test_publish_cadence() {
+        let mut heatmap = heatmap();
+        let second = 1_000_000_000;
+
+        let due: Vec<_> = (0..35)
+            .map(|t| t * second)
+            .filter(|t| heatmap.publish_due(*t))
+            .collect();
+        assert_eq!(due, vec![0, 10 * second, 20 * second, 30 * second]);
+    }
+
+    #[test]
+    fn test_png() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+
+        let png = encode_png(3, 2, &[0, 128, 255, 255, 128, 0]);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &3u32.to_be_bytes());
+        assert_eq!(&png[20..24], &2u32.to_be_bytes());
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        // Stored block with both scanlines and their filter bytes.
+        let idat = &png[41..];
+        assert_eq!(&idat[..3], &[0x78, 0x01, 0x01]);
+        assert_eq!(&idat[3..5], &8u16.to_le_bytes());
+        assert_eq!(&idat[7..15], &[0, 0, 128, 255, 0, 255, 128, 0]);
+    }
+}
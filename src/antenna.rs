@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+/// Azimuth dependent power correction for the antenna gain pattern.
+///
+/// The DRVEGRD antenna gain drops away from boresight, so a target at ±60°
+/// reports less power than the same target straight ahead.  The correction
+/// table holds the gain in dB to add to the measured power at evenly spaced
+/// azimuths centered on boresight, `gains_db[i]` applies at
+/// `(i - (len - 1) / 2) * azimuth_step_deg` degrees.  Azimuths between
+/// entries are linearly interpolated and azimuths beyond the table use the
+/// outermost entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AntennaPatternCorrection {
+    /// Power correction in dB per azimuth step
+    gains_db: Vec<f32>,
+
+    /// Azimuth between table entries in degrees
+    azimuth_step_deg: f32,
+}
+
+impl AntennaPatternCorrection {
+    /// Create a correction from a table centered on boresight.
+    ///
+    /// # Arguments
+    /// * `gains_db` - Power correction in dB, must not be empty
+    /// * `azimuth_step_deg` - Azimuth between table entries in degrees
+    pub fn new(gains_db: Vec<f32>, azimuth_step_deg: f32) -> Self {
+        assert!(!gains_db.is_empty(), "empty antenna gain table");
+        AntennaPatternCorrection {
+            gains_db,
+            azimuth_step_deg,
+        }
+    }
+
+    /// Parse the gain table in the CSV format `azimuth_deg,gain_db`.
+    ///
+    /// An optional header line is skipped.  The azimuths must be ascending,
+    /// evenly spaced and symmetric around boresight.
+    ///
+    /// # Errors
+    /// Returns an `InvalidData` error for malformed rows or a table which is
+    /// empty, unevenly spaced or not centered on boresight
+    pub fn from_csv<R: BufRead>(reader: R) -> Result<Self, io::Error> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut rows: Vec<(f32, f32)> = Vec::new();
+
+        for (lineno, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<_> = line.split(',').map(str::trim).collect();
+            if lineno == 0 && fields[0].parse::<f32>().is_err() {
+                continue;
+            }
+
+            match fields[..] {
+                [azimuth, gain] => match (azimuth.parse(), gain.parse()) {
+                    (Ok(azimuth), Ok(gain)) => rows.push((azimuth, gain)),
+                    _ => return Err(invalid(format!("line {}: invalid number", lineno + 1))),
+                },
+                _ => return Err(invalid(format!("line {}: expected 2 fields", lineno + 1))),
+            }
+        }
+
+        let (first, last) = match (rows.first(), rows.last()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            _ => return Err(invalid("empty antenna gain table".to_string())),
+        };
+
+        let step = match rows.len() {
+            1 => 1.0,
+            n => (last - first) / (n - 1) as f32,
+        };
+        if step <= 0.0 || (first + last).abs() > 1e-3 {
+            return Err(invalid(format!(
+                "azimuths {}..{} not ascending and centered on boresight",
+                first, last
+            )));
+        }
+        for (i, (azimuth, _)) in rows.iter().enumerate() {
+            if (first + i as f32 * step - azimuth).abs() > 1e-3 {
+                return Err(invalid(format!(
+                    "azimuth {} not evenly spaced by {}",
+                    azimuth, step
+                )));
+            }
+        }
+
+        Ok(Self::new(rows.iter().map(|row| row.1).collect(), step))
+    }
+
+    /// Load the gain table from a CSV file, see
+    /// [`AntennaPatternCorrection::from_csv`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        Self::from_csv(BufReader::new(File::open(path)?))
+    }
+
+    /// Returns the interpolated gain correction in dB at the azimuth.
+    pub fn gain_db(&self, azimuth_deg: f64) -> f64 {
+        let last = self.gains_db.len() - 1;
+        let center = last as f64 / 2.0;
+        let position =
+            (azimuth_deg / self.azimuth_step_deg as f64 + center).clamp(0.0, last as f64);
+
+        let i = (position.floor() as usize).min(last.saturating_sub(1));
+        let j = (i + 1).min(last);
+        let t = position - i as f64;
+        self.gains_db[i] as f64 * (1.0 - t) + self.gains_db[j] as f64 * t
+    }
+
+    /// Correct the measured power of a target for the antenna gain.
+    ///
+    /// # Arguments
+    /// * `power_db` - Measured target power in dB
+    /// * `azimuth_deg` - Target azimuth in degrees
+    ///
+    /// # Returns
+    /// Power with the interpolated gain correction added
+    pub fn correct_power(&self, power_db: f64, azimuth_deg: f64) -> f64 {
+        power_db + self.gain_db(azimuth_deg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "azimuth_deg,gain_db\n-60,6\n-30,1.5\n0,0\n30,1.5\n60,6\n";
+
+    #[test]
+    fn test_correct_power() {
+        let correction = AntennaPatternCorrection::from_csv(TABLE.as_bytes()).unwrap();
+        assert_eq!(
+            correction,
+            AntennaPatternCorrection::new(vec![6.0, 1.5, 0.0, 1.5, 6.0], 30.0)
+        );
+
+        assert_eq!(correction.correct_power(-80.0, 0.0), -80.0);
+        assert_eq!(correction.correct_power(-80.0, 30.0), -78.5);
+        assert_eq!(correction.correct_power(-80.0, -60.0), -74.0);
+        assert_eq!(correction.correct_power(-80.0, 45.0), -76.25);
+        assert_eq!(correction.correct_power(-80.0, -15.0), -79.25);
+        // Beyond the table the outermost entry applies.
+        assert_eq!(correction.correct_power(-80.0, 75.0), -74.0);
+        assert_eq!(correction.correct_power(-80.0, -90.0), -74.0);
+
+        let flat = AntennaPatternCorrection::new(vec![2.0], 1.0);
+        assert_eq!(flat.correct_power(-80.0, 40.0), -78.0);
+    }
+
+    #[test]
+    fn test_invalid_table() {
+        for table in [
+            "",
+            "azimuth_deg,gain_db\n",
+            "-60,6\n0,0\n30,1.5\n",
+            "0,0\n30,1.5\n60,6\n",
+            "-30,1.5\n0\n30,1.5\n",
+            "-30,1.5\n0,x\n30,1.5\n",
+            "30,1.5\n0,0\n-30,1.5\n",
+        ] {
+            let err = AntennaPatternCorrection::from_csv(table.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", table);
+        }
+    }
+}
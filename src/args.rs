@@ -1,8 +1,17 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
-use std::{fmt, io};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use crate::{
+    blockage::BlockageSettings,
+    can::{parse_can_id, parse_can_id_base, CanIds, CAN_LOCK_DIR},
+    common::CpuList,
+};
 use clap::{Parser, ValueEnum};
 use serde_json::json;
 use tracing::level_filters::LevelFilter;
@@ -15,6 +24,9 @@ pub enum Error {
     InvalidFrequencySweep(u32),
     InvalidRangeToggle(u32),
     InvalidDetectionSensitivity(u32),
+    ClientWithoutEndpoints,
+    TlsListenWithoutCertificate(String),
+    ZenohConfig(&'static str, String),
 }
 
 impl std::error::Error for Error {}
@@ -37,6 +49,16 @@ impl fmt::Display for Error {
             Error::InvalidDetectionSensitivity(value) => {
                 write!(f, "invalid detection sensitivity: {}", value)
             }
+            Error::ClientWithoutEndpoints => write!(
+                f,
+                "zenoh client mode needs a router to connect to, set connect or endpoints_file"
+            ),
+            Error::TlsListenWithoutCertificate(endpoint) => write!(
+                f,
+                "listening on {} needs a certificate, set tls_cert and tls_key",
+                endpoint
+            ),
+            Error::ZenohConfig(key, err) => write!(f, "invalid zenoh config {}: {}", key, err),
         }
     }
 }
@@ -183,6 +205,55 @@ impl fmt::Display for DetectionSensitivity {
     }
 }
 
+/// The source of the points accumulated into the bird's-eye-view raster.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum BevSource {
+    /// Only points assigned to a cluster.
+    Clusters,
+    /// All targets in the clustering window.
+    Targets,
+}
+
+/// Grouping of the targets in the clustering window.
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum ClusteringAlgorithm {
+    /// DBSCAN followed by the tracker.
+    Dbscan,
+    /// Every target in cluster 1, the tracker follows the frame bounding box.
+    Single,
+    /// No clustering task.
+    None,
+}
+
+/// Destination of the assembled radar cubes.
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum CubeSink {
+    /// Publish on the cube_topic.
+    Zenoh,
+    /// One cube_{frame_counter}.npy file per frame in cube_sink_path.
+    Npy,
+    /// Length prefixed CDR RadarCube records appended to cube_sink_path.
+    Raw,
+}
+
+/// Span of the clustering window.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindowLimit {
+    /// Most frames in the window
+    Frames(usize),
+    /// Oldest frame age relative to the newest frame
+    Duration(Duration),
+}
+
+impl fmt::Display for WindowLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowLimit::Frames(frames) => write!(f, "{} frames", frames),
+            WindowLimit::Duration(duration) => write!(f, "{} ms", duration.as_millis()),
+        }
+    }
+}
+
 /// Command-line arguments for EdgeFirst Radar Publisher.
 ///
 /// This structure defines all configuration options for the radar node,
@@ -222,24 +293,322 @@ pub struct Args {
     #[arg(long, env = "DETECTION_SENSITIVITY", default_value = "medium")]
     pub detection_sensitivity: DetectionSensitivity,
 
+    /// Read the radar parameters at startup instead of writing the values
+    /// above, for sensors configured by another system. Differences from the
+    /// requested values are logged as a warning.
+    #[arg(long, env = "READ_ONLY_PARAMS")]
+    pub read_only_params: bool,
+
+    /// Save the radar parameters to the sensor's non-volatile memory once the
+    /// writes are confirmed. The current values are read first and the save
+    /// is skipped when nothing changed.
+    #[arg(
+        long,
+        env = "SAVE_PARAMS_TO_SENSOR",
+        conflicts_with = "read_only_params"
+    )]
+    pub save_params_to_sensor: bool,
+
+    /// CAN frames other than the instruction response, such as buffered
+    /// targets, read before a parameter or status request gives up.
+    #[arg(long, env = "UAT_MAX_RETRIES", default_value = "100")]
+    pub uat_max_retries: usize,
+
+    /// Milliseconds to wait for each frame of an instruction response before
+    /// a parameter or status request times out.
+    #[arg(long, env = "UAT_TIMEOUT_MS", default_value = "1000")]
+    pub uat_timeout_ms: u64,
+
+    /// CAN identifier of the radar frame header, in decimal or 0x prefixed
+    /// hexadecimal. The targets and the instruction identifiers are shifted
+    /// along with it for radars configured with other or 29-bit extended
+    /// identifiers, such as several radars sharing a bus.
+    #[arg(
+        long,
+        env = "CAN_ID_BASE",
+        default_value = "0x400",
+        value_parser = parse_can_id_base
+    )]
+    pub can_id_base: u32,
+
+    /// CAN identifier of the instruction requests, by default 0x3FB shifted
+    /// along with can_id_base.
+    #[arg(long, env = "CAN_INSTRUCTION_ID", value_parser = parse_can_id)]
+    pub can_instruction_id: Option<u32>,
+
+    /// Seconds the radar must report a blockage continuously before the
+    /// blockage alarm is raised on the device topic.
+    #[arg(
+        long,
+        env = "BLOCKAGE_ALARM_SEC",
+        default_value = "10",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub blockage_alarm_sec: u64,
+
+    /// Detect a radome blockage from the target statistics, for firmware
+    /// which does not report it, and publish the state on the blockage
+    /// topic.
+    #[arg(long, env = "BLOCKAGE_DETECT")]
+    pub blockage_detect: bool,
+
+    /// Fraction of the learned target count and SNR below which the frames
+    /// are suspected to be blocked.
+    #[arg(long, env = "BLOCKAGE_FRACTION", default_value = "0.3")]
+    pub blockage_fraction: f64,
+
+    /// Seconds the frames must stay below the blockage_fraction before a
+    /// blockage is reported.
+    #[arg(
+        long,
+        env = "BLOCKAGE_HOLD_SEC",
+        default_value = "60",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub blockage_hold_sec: u64,
+
+    /// Hours of frames averaged into the blockage baseline.
+    #[arg(long, env = "BLOCKAGE_BASELINE_HOURS", default_value = "4")]
+    pub blockage_baseline_hours: f64,
+
+    /// File keeping the learned blockage baseline across restarts.
+    #[arg(long, env = "BLOCKAGE_STATE_FILE")]
+    pub blockage_state_file: Option<PathBuf>,
+
     /// Enable streaming the low-level radar data cube on the cube_topic.
     #[arg(long, env = "CUBE", default_value = "false")]
     pub cube: bool,
 
+    /// Destination of the radar cubes, npy and raw write the cubes to
+    /// cube_sink_path instead of publishing them. Requires cube.
+    #[arg(long, env = "CUBE_SINK", default_value = "zenoh")]
+    pub cube_sink: CubeSink,
+
+    /// Directory for the npy cube sink or file for the raw cube sink.
+    #[arg(long, env = "CUBE_SINK_PATH", default_value = "cubes")]
+    pub cube_sink_path: PathBuf,
+
+    /// Rotate the raw cube sink file once it reaches this size in MiB, the
+    /// file is renamed with a timestamp suffix and a new file started.
+    /// Unlimited when unset.
+    #[arg(long, env = "LOG_MAX_SIZE_MB")]
+    pub log_max_size_mb: Option<f64>,
+
+    /// Rotated files kept by log_max_size_mb, older files are deleted.
+    #[arg(long, env = "LOG_MAX_FILES", default_value = "5")]
+    pub log_max_files: usize,
+
+    /// Largest radar cube in bytes, start of frame packets describing a
+    /// larger cube are rejected instead of allocating it.
+    #[arg(long, env = "MAX_CUBE_BYTES", default_value = "67108864")]
+    pub max_cube_bytes: usize,
+
+    /// Most cube packet bytes waiting for the cube task, the oldest packets
+    /// are dropped when a stalled cube task lets the channel exceed it.
+    #[arg(long, env = "CUBE_CHANNEL_MAX_BYTES", default_value = "8388608")]
+    pub cube_channel_max_bytes: usize,
+
+    /// Radar cubes published together as one batch, 1 publishes every cube
+    /// as soon as it is complete.
+    #[arg(
+        long,
+        env = "CUBE_BATCH",
+        default_value = "1",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub cube_batch: usize,
+
+    /// Milliseconds a partial cube batch waits for more cubes before it is
+    /// published.
+    #[arg(long, env = "CUBE_BATCH_TIMEOUT_MS", default_value = "100")]
+    pub cube_batch_timeout_ms: u64,
+
+    /// Poll the io_uring submission queue of the cube receiver from a kernel
+    /// thread (IORING_SETUP_SQPOLL), trading a busy CPU core for fewer
+    /// system calls. Falls back to recvmmsg if the ring cannot be set up.
+    #[cfg(feature = "io-uring")]
+    #[arg(long, env = "URING_SQPOLL")]
+    pub uring_sqpoll: bool,
+
+    /// SCHED_FIFO real-time priority (1-99) of the CAN stream and clustering
+    /// threads, 0 keeps the normal scheduler. Requires CAP_SYS_NICE, without
+    /// it the threads run at normal priority.
+    #[arg(
+        long,
+        env = "RT_PRIORITY_CAN",
+        default_value = "0",
+        value_parser = clap::value_parser!(i32).range(0..=99)
+    )]
+    pub rt_priority_can: i32,
+
+    /// SCHED_FIFO real-time priority (1-99) of the UDP port and cube
+    /// threads, 0 keeps the normal scheduler. Requires CAP_SYS_NICE, without
+    /// it the threads run at normal priority.
+    #[arg(
+        long,
+        env = "RT_PRIORITY_UDP",
+        default_value = "10",
+        value_parser = clap::value_parser!(i32).range(0..=99)
+    )]
+    pub rt_priority_udp: i32,
+
+    /// CPU cores in cpulist format, for example 2-3, the cube thread and
+    /// the UDP port threads it spawns are pinned to. Unpinned when unset.
+    #[arg(long, env = "CPU_AFFINITY_CUBE", value_name = "CPUS")]
+    pub cpu_affinity_cube: Option<CpuList>,
+
+    /// CPU cores in cpulist format the CAN stream thread is pinned to.
+    /// Unpinned when unset.
+    #[arg(long, env = "CPU_AFFINITY_CAN", value_name = "CPUS")]
+    pub cpu_affinity_can: Option<CpuList>,
+
+    /// CPU cores in cpulist format the clustering thread is pinned to.
+    /// Unpinned when unset.
+    #[arg(long, env = "CPU_AFFINITY_CLUSTER", value_name = "CPUS")]
+    pub cpu_affinity_cluster: Option<CpuList>,
+
+    /// Comma separated steering angles in degrees, publishes a phase shift
+    /// beamformed range-Doppler map per angle on the beamformed_topic.
+    /// Requires cube.
+    #[arg(long, env = "BEAMFORMING_ANGLES", value_delimiter = ',')]
+    pub beamforming_angles: Vec<f32>,
+
+    /// Serve sensor status, parameter and command requests on the
+    /// control_topic queryable, used by drvegrdctl --via-zenoh. Parameter
+    /// changes are reflected in the published radar info.
+    #[arg(long, env = "CONTROL", default_value = "false")]
+    pub control: bool,
+
+    /// Serve a JSON dump of every sensor parameter and status field on the
+    /// param_dump_topic queryable for field diagnostics. Queries are answered
+    /// with an error reply if the sensor does not respond within 2 seconds.
+    #[arg(long, env = "PARAM_DUMP_QUERYABLE", default_value = "false")]
+    pub param_dump_queryable: bool,
+
+    /// Publish every message received on the echo_request topic back on the
+    /// echo_reply topic, used by drvegrdctl --zenoh-test. Runs independently
+    /// of the radar.
+    #[arg(long, env = "ECHO_SERVER")]
+    pub echo_server: bool,
+
+    /// Check the CAN, UDP and Zenoh connectivity, print a JSON summary and
+    /// exit with status 0 only if every check passed. The CAN check reads the
+    /// serial number, the UDP check waits for a cube packet when cube is
+    /// enabled and the Zenoh check receives its own message on a scratch
+    /// key.
+    #[arg(long)]
+    pub self_test: bool,
+
+    /// Seconds each self test check waits for its subsystem.
+    #[arg(long, default_value = "5")]
+    pub self_test_timeout: u64,
+
+    /// Decode the radar frames, and the cubes when cube is enabled, without
+    /// a Zenoh session and print a summary of each on stdout instead of
+    /// publishing it. The messages are still formatted and serialized, the
+    /// radar parameters are not written.
+    #[arg(long, conflicts_with = "self_test")]
+    pub dry_run: bool,
+
+    /// Subscribe to the heartbeat topic of an external supervisor and shut
+    /// down when no message arrives within watchdog_timeout_sec, any message
+    /// counts as a heartbeat.
+    #[arg(long, env = "WATCHDOG_SUBSCRIBE")]
+    pub watchdog_subscribe: Option<String>,
+
+    /// Seconds without a heartbeat on watchdog_subscribe before shutting down
+    #[arg(
+        long,
+        env = "WATCHDOG_TIMEOUT_SEC",
+        default_value = "5",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub watchdog_timeout_sec: u64,
+
     /// Enable radar target clustering task.
     #[arg(long, env = "CLUSTERING", default_value = "false")]
     pub clustering: bool,
 
-    /// Clustering window size in frames (one frame is 55ms).
+    /// Clustering algorithm, dbscan and single enable the clustering task and
+    /// none disables it.  Takes precedence over clustering when both are set.
+    #[arg(long, env = "CLUSTERING_MODE")]
+    pub clustering_mode: Option<ClusteringAlgorithm>,
+
+    /// Clustering window size in frames, the time span follows the radar
+    /// cycle duration of the sweep settings.
     #[arg(long, env = "WINDOW_SIZE", default_value = "6")]
     pub window_size: usize,
 
+    /// Clustering window span in milliseconds instead of window_size, frames
+    /// received longer than this before the newest frame are evicted.
+    #[arg(
+        long,
+        env = "WINDOW_DURATION",
+        value_name = "MS",
+        conflicts_with = "window_size",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub window_duration: Option<u64>,
+
+    /// Most targets held by the clustering window, the oldest frames are
+    /// evicted before the window is full when exceeded. Unlimited when
+    /// unset.
+    #[arg(long, env = "WINDOW_MAX_POINTS")]
+    pub window_max_points: Option<usize>,
+
     /// Clustering DBSCAN distance limit (euclidean distance)
     #[arg(long, env = "CLUSTERING_EPS", default_value = "1")]
     pub clustering_eps: f64,
 
+    /// Multiplier of clustering_eps for frames of the long frequency sweep,
+    /// with range toggling the eps follows the sweep of the newest frame.
+    #[arg(long, env = "EPS_SCALE_LONG", default_value = "1.0")]
+    pub eps_scale_long: f64,
+
+    /// Multiplier of clustering_eps for frames of the medium frequency sweep.
+    #[arg(long, env = "EPS_SCALE_MEDIUM", default_value = "1.0")]
+    pub eps_scale_medium: f64,
+
+    /// Multiplier of clustering_eps for frames of the short frequency sweep.
+    #[arg(long, env = "EPS_SCALE_SHORT", default_value = "1.0")]
+    pub eps_scale_short: f64,
+
+    /// Multiplier of clustering_eps for frames of the ultra-short frequency
+    /// sweep.
+    #[arg(long, env = "EPS_SCALE_ULTRA_SHORT", default_value = "1.0")]
+    pub eps_scale_ultra_short: f64,
+
+    /// First eps of the clustering eps sweep.  The sweep runs DBSCAN for
+    /// every eps on each clustering window and publishes the Davies-Bouldin
+    /// indices on the cluster_quality_sweep_topic, it is a tuning mode and
+    /// cannot be combined with clustering.
+    #[arg(
+        long,
+        env = "CLUSTERING_EPS_SWEEP_START",
+        requires_all = ["clustering_eps_sweep_end", "clustering_eps_sweep_steps"]
+    )]
+    pub clustering_eps_sweep_start: Option<f64>,
+
+    /// Last eps of the clustering eps sweep.
+    #[arg(
+        long,
+        env = "CLUSTERING_EPS_SWEEP_END",
+        requires = "clustering_eps_sweep_start"
+    )]
+    pub clustering_eps_sweep_end: Option<f64>,
+
+    /// Number of evenly spaced eps values of the clustering eps sweep.
+    #[arg(
+        long,
+        env = "CLUSTERING_EPS_SWEEP_STEPS",
+        requires = "clustering_eps_sweep_start",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub clustering_eps_sweep_steps: Option<u64>,
+
     /// Clustering DBSCAN parameter scaling. Parameter order is x, y, z, speed.
-    /// Set the appropriate axis to 0 to ignore that axis
+    /// Set the appropriate axis to 0 to ignore that axis, a z above 0
+    /// separates objects stacked above each other
     #[arg(
         long,
         env = "CLUSTERING_PARAM_SCALE",
@@ -253,14 +622,372 @@ pub struct Args {
     #[arg(long, env = "CLUSTERING_POINT_LIMIT", default_value = "5")]
     pub clustering_point_limit: usize,
 
+    /// Clustering worker threads, 0 uses one thread per CPU core. Requires
+    /// the rayon feature, otherwise clustering is single threaded.
+    #[arg(long, env = "CLUSTERING_THREADS", default_value = "0")]
+    pub clustering_threads: usize,
+
+    /// Split the azimuth span into this many sectors clustered in parallel,
+    /// clusters touching across a sector boundary are merged. 1 clusters all
+    /// targets in a single pass.
+    #[arg(long, env = "CLUSTERING_PARALLEL_SECTORS", default_value = "1")]
+    pub clustering_parallel_sectors: usize,
+
+    /// Cluster approaching, stationary and receding targets separately so
+    /// objects moving in opposite directions are never merged.
+    #[arg(long, env = "CLUSTERING_VELOCITY_GATED")]
+    pub clustering_velocity_gated: bool,
+
+    /// Radial speed in m/s separating the stationary velocity band from the
+    /// approaching and receding bands. Requires clustering_velocity_gated.
+    #[arg(
+        long,
+        env = "CLUSTERING_VELOCITY_SEPARATION_MPS",
+        default_value = "0.5"
+    )]
+    pub clustering_velocity_separation_mps: f32,
+
+    /// Warn when the mean velocity of a cluster changes faster than this
+    /// acceleration in m/s² between cycles, which hints at DBSCAN merging
+    /// objects into one cluster. The default inf disables the check.
+    #[arg(long, env = "CLUSTER_MAX_ACCELERATION_MPS2", default_value = "inf")]
+    pub cluster_max_acceleration_mps2: f32,
+
+    /// Merge cluster boxes overlapping a kept box by more than this IoU
+    /// before tracking (non-maximum suppression). 1.0 disables suppression.
+    #[arg(long, env = "CLUSTERING_NMS_IOU", default_value = "1.0")]
+    pub clustering_nms_iou: f32,
+
+    /// Minimum number of points in a cluster for it to be tracked. Smaller
+    /// clusters are reported as noise. 1 tracks every cluster.
+    #[arg(long, env = "TRACK_MIN_CLUSTER_SIZE", default_value = "1")]
+    pub track_min_cluster_size: usize,
+
+    /// Associations before the cluster id of a new track is published, the
+    /// points of an unconfirmed track are noise. 1 publishes every track on
+    /// its first frame.
+    #[arg(
+        long,
+        env = "TRACK_MIN_HITS",
+        default_value = "1",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub track_min_hits: u64,
+
+    /// Consecutive missed frames a confirmed track may coast on its
+    /// prediction before it is dropped. Unset keeps tracks for the track
+    /// lifespan.
+    #[arg(long, env = "TRACK_MAX_COAST")]
+    pub track_max_coast: Option<usize>,
+
+    /// Publish the points of unconfirmed tracks with the negated cluster id
+    /// they will keep once confirmed, instead of 0.
+    #[arg(long, env = "TRACK_PROVISIONAL_IDS")]
+    pub track_provisional_ids: bool,
+
+    /// Seconds the cluster id of an ended track is held back before it is
+    /// assigned to a new track.
+    #[arg(long, env = "CLUSTER_ID_COOLDOWN", default_value = "5")]
+    pub cluster_id_cooldown: f32,
+
+    /// Assign monotonically increasing cluster ids which wrap around after
+    /// cluster_id_max instead of recycling the ids of ended tracks.
+    #[arg(long, env = "NO_ID_RECYCLING")]
+    pub no_id_recycling: bool,
+
+    /// Largest cluster id before the ids wrap around to 1 with
+    /// no_id_recycling.
+    #[arg(
+        long,
+        env = "CLUSTER_ID_MAX",
+        default_value = "65535",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub cluster_id_max: u64,
+
+    /// Annotate the raw targets point cloud with the track_id of the tracked
+    /// cluster each target falls inside (0 for none) along with the
+    /// track_age in radar cycles. The association is taken from the previous
+    /// clustering cycle so it is at least one frame late. Requires clustering.
+    #[arg(
+        long,
+        visible_alias = "targets-include-track-id",
+        env = "ANNOTATE_TARGETS",
+        requires = "clustering"
+    )]
+    pub annotate_targets: bool,
+
+    /// Enable the bird's-eye-view occupancy raster published on the
+    /// bev_topic at the clustering rate. Requires clustering.
+    #[arg(long, env = "BEV", requires = "clustering")]
+    pub bev: bool,
+
+    /// Points accumulated into the bird's-eye-view raster.
+    #[arg(long, env = "BEV_SOURCE", default_value = "clusters")]
+    pub bev_source: BevSource,
+
+    /// Bird's-eye-view raster side length in meters, centered on base_link.
+    #[arg(long, env = "BEV_SIZE", default_value = "40")]
+    pub bev_size: f32,
+
+    /// Bird's-eye-view raster resolution in meters per cell.
+    #[arg(long, env = "BEV_RESOLUTION", default_value = "0.2")]
+    pub bev_resolution: f32,
+
+    /// Bird's-eye-view exponential decay rate per second. Higher values fade
+    /// stale detections faster, 0 disables decay.
+    #[arg(long, env = "BEV_DECAY", default_value = "2")]
+    pub bev_decay: f32,
+
+    /// Publish the tracked clusters as edgefirst_msgs/Detect boxes on the
+    /// detect_topic for fusion with camera detections. Requires clustering.
+    #[arg(long, env = "DETECT", requires = "clustering")]
+    pub detect: bool,
+
+    /// Space separated labels of the Detect boxes. A single label is used
+    /// for every box, three labels name the approaching, stationary and
+    /// receding boxes split at clustering_velocity_separation_mps.
+    #[arg(
+        long,
+        env = "DETECT_LABELS",
+        default_value = "object",
+        value_delimiter = ' ',
+        num_args = 1..=3
+    )]
+    pub detect_labels: Vec<String>,
+
+    /// Classify the tracked clusters as pedestrian, bicycle, vehicle or
+    /// unknown from their speed, extent, point count and mean RCS. The class
+    /// labels the Detect boxes instead of detect_labels. Requires clustering.
+    #[arg(long, env = "CLASSIFY", requires = "clustering")]
+    pub classify: bool,
+
+    /// Fewest cluster points for a class other than unknown.
+    #[arg(long, env = "CLASSIFIER_MIN_POINTS", default_value = "3")]
+    pub classifier_min_points: usize,
+
+    /// Largest pedestrian radial speed in m/s.
+    #[arg(long, env = "CLASSIFIER_PEDESTRIAN_MAX_SPEED", default_value = "3.0")]
+    pub classifier_pedestrian_max_speed: f32,
+
+    /// Largest pedestrian box extent in meters.
+    #[arg(long, env = "CLASSIFIER_PEDESTRIAN_MAX_EXTENT", default_value = "1.2")]
+    pub classifier_pedestrian_max_extent: f32,
+
+    /// Largest pedestrian mean RCS in dBsm.
+    #[arg(
+        long,
+        env = "CLASSIFIER_PEDESTRIAN_MAX_RCS",
+        default_value = "-3.0",
+        allow_negative_numbers = true
+    )]
+    pub classifier_pedestrian_max_rcs: f32,
+
+    /// Radial speed in m/s from which a cluster is a vehicle.
+    #[arg(long, env = "CLASSIFIER_VEHICLE_MIN_SPEED", default_value = "12.0")]
+    pub classifier_vehicle_min_speed: f32,
+
+    /// Box extent in meters from which a cluster is a vehicle.
+    #[arg(long, env = "CLASSIFIER_VEHICLE_MIN_EXTENT", default_value = "2.5")]
+    pub classifier_vehicle_min_extent: f32,
+
+    /// Mean RCS in dBsm from which a cluster is a vehicle.
+    #[arg(
+        long,
+        env = "CLASSIFIER_VEHICLE_MIN_RCS",
+        default_value = "5.0",
+        allow_negative_numbers = true
+    )]
+    pub classifier_vehicle_min_rcs: f32,
+
+    /// Consecutive cycles a different class has to be observed before a
+    /// classified track changes class, 1 disables the hysteresis.
+    #[arg(long, env = "CLASSIFIER_HYSTERESIS", default_value = "3")]
+    pub classifier_hysteresis: u32,
+
+    /// Record the history of the confirmed tracks and append a summary of
+    /// every completed track to this file, CSV for a .csv path and JSON
+    /// lines otherwise. The completed tracks are written when the
+    /// track_log_topic is queried and at shutdown, including the tracks
+    /// still live. Requires clustering.
+    #[arg(long, env = "TRACK_LOG")]
+    pub track_log: Option<PathBuf>,
+
+    /// Publish the JSON summary of every track as it completes on the
+    /// track_events_topic. Requires clustering.
+    #[arg(long, env = "TRACK_EVENTS", requires = "clustering")]
+    pub track_events: bool,
+
+    /// Clustering cycles with points between the path points of a track in
+    /// the track log and events.
+    #[arg(
+        long,
+        env = "TRACK_HISTORY_DECIMATION",
+        default_value = "10",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub track_history_decimation: usize,
+
+    /// Largest number of path points of a track, a longer path is thinned
+    /// to every other point.
+    #[arg(long, env = "TRACK_HISTORY_MAX_POINTS", default_value = "256")]
+    pub track_history_max_points: usize,
+
+    /// Largest number of completed tracks held until the track log is
+    /// written, the oldest are dropped first.
+    #[arg(long, env = "TRACK_HISTORY_MAX_TRACKS", default_value = "10000")]
+    pub track_history_max_tracks: usize,
+
+    /// File keeping the tracks across restarts. The tracks are saved at
+    /// shutdown and restored by the first clustering cycle, keeping their
+    /// cluster ids, unless saved more than tracker_state_max_age seconds
+    /// earlier. Requires clustering.
+    #[arg(long, env = "TRACKER_STATE_FILE")]
+    pub tracker_state_file: Option<PathBuf>,
+
+    /// Seconds after which a saved tracker state is discarded, the objects
+    /// have moved too far to continue their tracks.
+    #[arg(long, env = "TRACKER_STATE_MAX_AGE", default_value = "5.0")]
+    pub tracker_state_max_age: f32,
+
+    /// Keep the last 10 target frames and publish them as JSON on the
+    /// debug_dump_topic when the cluster count drops to 0 from 3 or more
+    /// clusters or jumps by more than 10. Requires clustering.
+    #[arg(long, env = "ENABLE_CLUSTER_DEBUG_DUMP", requires = "clustering")]
+    pub enable_cluster_debug_dump: bool,
+
+    /// Enable the long horizon detection heatmap published on the
+    /// heatmap_topic every heatmap_interval seconds.
+    #[arg(long, env = "HEATMAP")]
+    pub heatmap: bool,
+
+    /// Heatmap extent in the radar frame in meters. Parameter order is
+    /// x_min, x_max, y_min, y_max. Targets outside the extent are ignored.
+    #[arg(
+        long,
+        env = "HEATMAP_EXTENT",
+        default_value = "0 100 -50 50",
+        value_delimiter = ' ',
+        num_args = 4,
+        allow_negative_numbers = true
+    )]
+    pub heatmap_extent: Vec<f32>,
+
+    /// Heatmap resolution in meters per cell.
+    #[arg(long, env = "HEATMAP_RESOLUTION", default_value = "0.5")]
+    pub heatmap_resolution: f32,
+
+    /// Heatmap exponential decay rate per second, the default halves the
+    /// detection counts roughly every hour. 0 disables decay.
+    #[arg(long, env = "HEATMAP_DECAY", default_value = "0.0002")]
+    pub heatmap_decay: f32,
+
+    /// Heatmap publish interval in seconds.
+    #[arg(long, env = "HEATMAP_INTERVAL", default_value = "10")]
+    pub heatmap_interval: f32,
+
+    /// Also write the heatmap to this PNG file on every publish, for
+    /// debugging.
+    #[arg(long, env = "HEATMAP_PNG")]
+    pub heatmap_png: Option<PathBuf>,
+
     /// Mirror the radar data
     #[arg(long, env = "MIRROR")]
     pub mirror: bool,
 
+    /// Remove the ground clutter found by a RANSAC plane fit from the
+    /// targets before they are published and clustered. The fitted plane is
+    /// published on the ground_plane_topic.
+    #[arg(long, env = "REMOVE_GROUND")]
+    pub remove_ground: bool,
+
+    /// Candidate planes evaluated per frame by the ground plane fit.
+    #[arg(long, env = "GROUND_ITERATIONS", default_value = "100")]
+    pub ground_iterations: usize,
+
+    /// Maximum distance in meters of a ground target to the fitted plane.
+    #[arg(long, env = "GROUND_THRESHOLD", default_value = "0.15")]
+    pub ground_threshold: f64,
+
+    /// Include a per-target confidence field (derived from SNR) in the
+    /// targets and clusters point clouds, at offset 24 after the rcs in both
+    /// which moves the cluster_id to offset 28.
+    #[arg(long, env = "INCLUDE_CONFIDENCE")]
+    pub include_confidence: bool,
+
+    /// Include the per-target range accuracy (0 high to 3 low) from the CAN
+    /// data_1 message in the targets point cloud as a FLOAT32 field.  Always
+    /// 0 on firmware which does not report it.
+    #[arg(long, env = "INCLUDE_ACCURACY")]
+    pub include_accuracy: bool,
+
+    /// Unfold the ambiguous target speeds of range toggled frames against
+    /// the previous frame of the other sweep and add them as a FLOAT32
+    /// speed_unfolded field to the targets point cloud. Targets which cannot
+    /// be unfolded keep their raw speed. Requires range_toggle.
+    #[arg(long, env = "DOPPLER_UNFOLD")]
+    pub doppler_unfold: bool,
+
+    /// Publish the targets as an organised point cloud of
+    /// organised_cloud_range_bins rows by organised_cloud_azimuth_bins
+    /// columns over the organised_cloud_extent, for ROS 2 algorithms which
+    /// require an organised cloud. Unoccupied cells are NaN, the strongest
+    /// target is kept when several share a cell and targets outside the
+    /// extent are dropped.
+    #[arg(long, env = "ORGANISED_CLOUD")]
+    pub organised_cloud: bool,
+
+    /// Range rows of the organised targets cloud.
+    #[arg(
+        long,
+        env = "ORGANISED_CLOUD_RANGE_BINS",
+        default_value = "128",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub organised_cloud_range_bins: u32,
+
+    /// Azimuth columns of the organised targets cloud.
+    #[arg(
+        long,
+        env = "ORGANISED_CLOUD_AZIMUTH_BINS",
+        default_value = "64",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub organised_cloud_azimuth_bins: u32,
+
+    /// Organised targets cloud extent in meters and degrees. Parameter order
+    /// is range_min, range_max, azimuth_min, azimuth_max.
+    #[arg(
+        long,
+        env = "ORGANISED_CLOUD_EXTENT",
+        default_value = "0 100 -60 60",
+        value_delimiter = ' ',
+        num_args = 4,
+        allow_negative_numbers = true
+    )]
+    pub organised_cloud_extent: Vec<f32>,
+
+    /// Add a UINT8 point_role field to the clusters point cloud with the
+    /// DBSCAN role of each point, 0 for noise, 1 for border and 2 for core
+    /// points.
+    #[arg(long, env = "CLUSTER_ROLES")]
+    pub cluster_roles: bool,
+
+    /// Correct the published target power for the antenna gain pattern
+    /// using the azimuth_deg,gain_db table in this CSV file.
+    #[arg(long, env = "ANTENNA_PATTERN_FILE")]
+    pub antenna_pattern_file: Option<PathBuf>,
+
     /// CAN device connected to radar
     #[arg(long, default_value = "can0")]
     pub can: String,
 
+    /// Directory of the CAN interface lock, which keeps drvegrdctl from
+    /// sending instructions while radarpub streams.  Empty disables the
+    /// lock.
+    #[arg(long, env = "CAN_LOCK_DIR", default_value = CAN_LOCK_DIR)]
+    pub can_lock_dir: PathBuf,
+
     /// Radar frame transform vector from base_link (x y z in meters)
     #[arg(
         long,
@@ -289,18 +1016,98 @@ pub struct Args {
     #[arg(long, env = "RADAR_FRAME_ID", default_value = "radar")]
     pub radar_frame_id: String,
 
+    /// Prefix of every topic, the first level of the key expressions.
+    #[arg(long, env = "TOPIC_PREFIX", default_value = "rt")]
+    pub topic_prefix: String,
+
+    /// Node name between the topic prefix and the topic names so several
+    /// radars can share a Zenoh network, topics are published as
+    /// {topic_prefix}/{node_name}/{topic}. Topic names containing a '/' are
+    /// used as given.
+    #[arg(long, env = "NODE_NAME", default_value = "radar")]
+    pub node_name: String,
+
     /// Radar targets topic name
-    #[arg(long, default_value = "rt/radar/targets")]
+    #[arg(long, default_value = "targets")]
     pub targets_topic: String,
 
     /// Radar clusters topic name
-    #[arg(long, default_value = "rt/radar/clusters")]
+    #[arg(long, default_value = "clusters")]
     pub clusters_topic: String,
 
+    /// Cluster quality (Davies-Bouldin index) topic name
+    #[arg(long, default_value = "cluster_quality")]
+    pub cluster_quality_topic: String,
+
+    /// Davies-Bouldin index per eps of the clustering eps sweep topic name
+    #[arg(long, default_value = "cluster_quality_sweep")]
+    pub cluster_quality_sweep_topic: String,
+
     /// Radar data cube topic name
-    #[arg(long, default_value = "rt/radar/cube")]
+    #[arg(long, default_value = "cube")]
     pub cube_topic: String,
 
+    /// Radar bird's-eye-view raster topic name
+    #[arg(long, default_value = "bev")]
+    pub bev_topic: String,
+
+    /// Radar Detect boxes topic name
+    #[arg(long, default_value = "detect")]
+    pub detect_topic: String,
+
+    /// Clustering debug dump topic name
+    #[arg(long, default_value = "debug_dump")]
+    pub debug_dump_topic: String,
+
+    /// Radar control queryable key expression
+    #[arg(long, default_value = "control")]
+    pub control_topic: String,
+
+    /// Radar parameter dump queryable key expression
+    #[arg(long, default_value = "dump_params")]
+    pub param_dump_topic: String,
+
+    /// Completed track events topic name
+    #[arg(long, default_value = "track_events")]
+    pub track_events_topic: String,
+
+    /// Track log write queryable key expression
+    #[arg(long, default_value = "track_log")]
+    pub track_log_topic: String,
+
+    /// Radar detection heatmap topic name
+    #[arg(long, default_value = "heatmap")]
+    pub heatmap_topic: String,
+
+    /// Radar cube reader lifetime statistics topic name
+    #[arg(long, default_value = "cube_stats")]
+    pub cube_stats_topic: String,
+
+    /// Beamformed range-Doppler maps topic name
+    #[arg(long, default_value = "beamformed")]
+    pub beamformed_topic: String,
+
+    /// Radar publish latency histogram topic name
+    #[arg(long, default_value = "latency_histogram")]
+    pub latency_topic: String,
+
+    /// Ground plane topic name
+    #[arg(long, default_value = "ground_plane")]
+    pub ground_plane_topic: String,
+
+    /// Thread CPU utilisation topic name
+    #[arg(long, default_value = "cpu_usage")]
+    pub cpu_usage_topic: String,
+
+    /// Serve Prometheus metrics over HTTP on this address (e.g. 0.0.0.0:9100)
+    #[arg(long, env = "METRICS")]
+    pub metrics: Option<String>,
+
+    /// Publish the CPU utilisation of every radarpub thread every second on
+    /// the cpu_usage_topic and warn about threads above 80% of a core.
+    #[arg(long, env = "CPU_MONITOR")]
+    pub cpu_monitor: bool,
+
     /// Application log level
     #[arg(long, env = "RUST_LOG", default_value = "info")]
     pub rust_log: LevelFilter,
@@ -317,6 +1124,12 @@ pub struct Args {
     #[arg(long, env = "CONNECT")]
     connect: Vec<String>,
 
+    /// File of Zenoh endpoints to connect to, one per line, used instead of
+    /// connect.  The file is polled and the publishers reconnect when it
+    /// changes.
+    #[arg(long, env = "ENDPOINTS_FILE")]
+    pub endpoints_file: Option<PathBuf>,
+
     /// Zenoh endpoints to listen on (can specify multiple)
     #[arg(long, env = "LISTEN")]
     listen: Vec<String>,
@@ -324,38 +1137,279 @@ pub struct Args {
     /// Disable Zenoh multicast peer discovery
     #[arg(long, env = "NO_MULTICAST_SCOUTING")]
     no_multicast_scouting: bool,
+
+    /// Network interface of the Zenoh multicast scouting, such as eth0 or lo.
+    /// By default Zenoh picks the interface.
+    #[arg(long, env = "SCOUT_INTERFACE")]
+    scout_interface: Option<String>,
+
+    /// Certificate authority used to verify the TLS and QUIC peers
+    #[arg(long, env = "TLS_CA")]
+    tls_ca: Option<PathBuf>,
+
+    /// Certificate presented on the TLS and QUIC endpoints, required to
+    /// listen on them
+    #[arg(long, env = "TLS_CERT", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// Private key of the tls_cert certificate
+    #[arg(long, env = "TLS_KEY", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
 }
 
-impl From<Args> for Config {
-    fn from(args: Args) -> Self {
-        let mut config = Config::default();
+impl Args {
+    /// Returns the key expression of a node topic, names containing a '/'
+    /// are already complete and returned unchanged.
+    pub fn topic(&self, name: &str) -> String {
+        node_topic(&self.topic_prefix, &self.node_name, name)
+    }
+
+    /// Returns the key expression of a topic shared by every node directly
+    /// under the topic prefix, such as tf_static.
+    pub fn global_topic(&self, name: &str) -> String {
+        join_topic(&[&self.topic_prefix, name])
+    }
+
+    /// Range rows, azimuth columns and extent of the organised targets
+    /// cloud, None for an unorganised cloud.
+    pub fn organised_grid(&self) -> Option<(u32, u32, &[f32])> {
+        self.organised_cloud.then_some((
+            self.organised_cloud_range_bins,
+            self.organised_cloud_azimuth_bins,
+            &self.organised_cloud_extent,
+        ))
+    }
+
+    /// Clustering window of window_duration when set, otherwise of
+    /// window_size.
+    pub fn window_limit(&self) -> WindowLimit {
+        match self.window_duration {
+            Some(ms) => WindowLimit::Duration(Duration::from_millis(ms)),
+            None => WindowLimit::Frames(self.window_size),
+        }
+    }
+
+    /// Parameters of the radome blockage detector.
+    pub fn blockage_settings(&self) -> BlockageSettings {
+        BlockageSettings {
+            fraction: self.blockage_fraction,
+            hold: Duration::from_secs(self.blockage_hold_sec),
+            baseline_window: Duration::from_secs_f64(self.blockage_baseline_hours * 3600.0),
+        }
+    }
+
+    /// Path of the blockage state file, None if unset or empty.
+    pub fn blockage_state_file(&self) -> Option<&Path> {
+        self.blockage_state_file
+            .as_deref()
+            .filter(|path| !path.as_os_str().is_empty())
+    }
 
-        config
-            .insert_json5("mode", &json!(args.mode).to_string())
-            .unwrap();
+    /// The tracker state file, None when unset or empty.
+    pub fn tracker_state_file(&self) -> Option<&Path> {
+        self.tracker_state_file
+            .as_deref()
+            .filter(|path| !path.as_os_str().is_empty())
+    }
+
+    /// Directory of the CAN interface lock, None if empty.
+    pub fn can_lock_dir(&self) -> Option<&Path> {
+        Some(self.can_lock_dir.as_path()).filter(|path| !path.as_os_str().is_empty())
+    }
+
+    /// CAN identifiers of the radar from can_id_base and
+    /// can_instruction_id.
+    pub fn can_ids(&self) -> CanIds {
+        let ids = CanIds::new(self.can_id_base).expect("can_id_base is checked by its parser");
+        match self.can_instruction_id {
+            Some(id) => ids.with_instruction(id),
+            None => ids,
+        }
+    }
+
+    /// The track log path, None when unset or empty.
+    pub fn track_log_path(&self) -> Option<&Path> {
+        self.track_log
+            .as_deref()
+            .filter(|path| !path.as_os_str().is_empty())
+    }
+
+    /// Expand every topic argument with [`Args::topic`].
+    pub fn resolve_topics(&mut self) {
+        let (prefix, node_name) = (&self.topic_prefix, &self.node_name);
+        for topic in [
+            &mut self.targets_topic,
+            &mut self.clusters_topic,
+            &mut self.cluster_quality_topic,
+            &mut self.cluster_quality_sweep_topic,
+            &mut self.cube_topic,
+            &mut self.bev_topic,
+            &mut self.detect_topic,
+            &mut self.debug_dump_topic,
+            &mut self.control_topic,
+            &mut self.param_dump_topic,
+            &mut self.track_events_topic,
+            &mut self.track_log_topic,
+            &mut self.heatmap_topic,
+            &mut self.cube_stats_topic,
+            &mut self.beamformed_topic,
+            &mut self.latency_topic,
+            &mut self.ground_plane_topic,
+            &mut self.cpu_usage_topic,
+        ] {
+            *topic = node_topic(prefix, node_name, topic);
+        }
+    }
+
+    /// DBSCAN eps of a frame with the frequency sweep reported in its CAN
+    /// header, an unknown sweep uses clustering_eps unscaled.
+    pub fn sweep_eps(&self, frequency_sweep: u8) -> f64 {
+        let scale = match FrequencySweep::try_from(frequency_sweep as u32) {
+            Ok(FrequencySweep::Long) => self.eps_scale_long,
+            Ok(FrequencySweep::Medium) => self.eps_scale_medium,
+            Ok(FrequencySweep::Short) => self.eps_scale_short,
+            Ok(FrequencySweep::UltraShort) => self.eps_scale_ultra_short,
+            Err(_) => 1.0,
+        };
+        self.clustering_eps * scale
+    }
+
+    /// Evenly spaced eps values of the clustering eps sweep from start to end,
+    /// None unless the sweep is configured.
+    pub fn eps_sweep(&self) -> Option<Vec<f64>> {
+        let (start, end, steps) = match (
+            self.clustering_eps_sweep_start,
+            self.clustering_eps_sweep_end,
+            self.clustering_eps_sweep_steps,
+        ) {
+            (Some(start), Some(end), Some(steps)) => (start, end, steps as usize),
+            _ => return None,
+        };
+        let step = match steps {
+            1 => 0.0,
+            _ => (end - start) / (steps - 1) as f64,
+        };
+        Some((0..steps).map(|i| start + step * i as f64).collect())
+    }
+
+    /// Reconcile clustering with clustering_mode so either can come from a
+    /// config file, afterwards clustering_mode is always set.
+    pub fn resolve_clustering(&mut self) {
+        let mode = match (self.clustering_mode, self.clustering) {
+            (Some(mode), _) => mode,
+            (None, true) => ClusteringAlgorithm::Dbscan,
+            (None, false) => ClusteringAlgorithm::None,
+        };
+        self.clustering_mode = Some(mode);
+        self.clustering = mode != ClusteringAlgorithm::None;
+    }
+}
+
+fn node_topic(prefix: &str, node_name: &str, name: &str) -> String {
+    match name.contains('/') {
+        true => name.to_string(),
+        false => join_topic(&[prefix, node_name, name]),
+    }
+}
+
+/// Join the non-empty levels of a key expression.
+fn join_topic(levels: &[&str]) -> String {
+    levels
+        .iter()
+        .map(|level| level.trim_matches('/'))
+        .filter(|level| !level.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns true for the TLS and QUIC endpoints.
+fn is_secure_endpoint(endpoint: &str) -> bool {
+    endpoint.starts_with("tls/") || endpoint.starts_with("quic/")
+}
+
+/// Insert the value at the key of the Zenoh config, the error names the key.
+fn insert(config: &mut Config, key: &'static str, value: serde_json::Value) -> Result<(), Error> {
+    config
+        .insert_json5(key, &value.to_string())
+        .map_err(|err| Error::ZenohConfig(key, err.to_string()))
+}
+
+impl TryFrom<Args> for Config {
+    type Error = Error;
+
+    fn try_from(args: Args) -> Result<Self, Error> {
+        // A client without endpoints would wait for a router forever.
+        let endpoints_file = args
+            .endpoints_file
+            .as_ref()
+            .filter(|path| !path.as_os_str().is_empty());
+        if args.mode == WhatAmI::Client && args.connect.is_empty() && endpoints_file.is_none() {
+            return Err(Error::ClientWithoutEndpoints);
+        }
+
+        // The secure endpoints cannot listen without a certificate.
+        let certificate = args.tls_cert.as_ref().zip(args.tls_key.as_ref());
+        if certificate.is_none() {
+            if let Some(endpoint) = args.listen.iter().find(|e| is_secure_endpoint(e)) {
+                return Err(Error::TlsListenWithoutCertificate(endpoint.clone()));
+            }
+        }
+
+        let mut config = Config::default();
+        insert(&mut config, "mode", json!(args.mode))?;
 
         if !args.connect.is_empty() {
-            config
-                .insert_json5("connect/endpoints", &json!(args.connect).to_string())
-                .unwrap();
+            insert(&mut config, "connect/endpoints", json!(args.connect))?;
         }
 
         if !args.listen.is_empty() {
-            config
-                .insert_json5("listen/endpoints", &json!(args.listen).to_string())
-                .unwrap();
+            insert(&mut config, "listen/endpoints", json!(args.listen))?;
         }
 
         if args.no_multicast_scouting {
-            config
-                .insert_json5("scouting/multicast/enabled", &json!(false).to_string())
-                .unwrap();
+            insert(&mut config, "scouting/multicast/enabled", json!(false))?;
+        }
+
+        if let Some(interface) = args.scout_interface.filter(|i| !i.is_empty()) {
+            insert(
+                &mut config,
+                "scouting/multicast/interface",
+                json!(interface),
+            )?;
+        }
+
+        // QUIC shares the TLS settings of Zenoh.
+        if let Some(ca) = &args.tls_ca {
+            insert(
+                &mut config,
+                "transport/link/tls/root_ca_certificate",
+                json!(ca),
+            )?;
         }
 
-        config
-            .insert_json5("scouting/multicast/interface", &json!("lo").to_string())
-            .unwrap();
+        if let Some((cert, key)) = certificate {
+            insert(
+                &mut config,
+                "transport/link/tls/listen_certificate",
+                json!(cert),
+            )?;
+            insert(
+                &mut config,
+                "transport/link/tls/listen_private_key",
+                json!(key),
+            )?;
+            insert(
+                &mut config,
+                "transport/link/tls/connect_certificate",
+                json!(cert),
+            )?;
+            insert(
+                &mut config,
+                "transport/link/tls/connect_private_key",
+                json!(key),
+            )?;
+        }
 
-        config
+        Ok(config)
     }
 }
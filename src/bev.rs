@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use nalgebra::{Isometry3, Point3, Quaternion, Translation3, UnitQuaternion};
+
+/// Largest number of cells along a side of the BEV grid and the heatmap,
+/// radarpub rejects a size and resolution beyond it at startup.
+pub const MAX_CELLS: usize = 4096;
+
+/// Bird's-eye-view occupancy raster centered on base_link.
+///
+/// Points are transformed from the radar frame into base_link and
+/// accumulated into a square grid of `size` meters with `resolution` meters
+/// per cell.  Every cell decays exponentially over time so that stale
+/// detections fade out of the raster.
+///
+/// The raster is stored row-major with row 0 being the furthest forward
+/// (+x) and column 0 being the furthest left (+y), which matches the usual
+/// top-down image orientation.
+#[derive(Debug, Clone)]
+pub struct BevGrid {
+    /// Side length of the grid in meters
+    size: f32,
+
+    /// Cell size in meters
+    resolution: f32,
+
+    /// Exponential decay rate per second
+    decay: f32,
+
+    /// Number of cells along each side of the grid
+    cells: usize,
+
+    /// Transform from the radar frame to base_link
+    transform: Isometry3<f32>,
+
+    /// Cell occupancy values in the range [0, 1]
+    grid: Vec<f32>,
+
+    /// Timestamp of the last decay step in nanoseconds
+    timestamp: Option<u64>,
+}
+
+impl BevGrid {
+    /// Create a new BEV grid.
+    ///
+    /// # Arguments
+    /// * `size` - Side length of the square grid in meters
+    /// * `resolution` - Cell size in meters
+    /// * `decay` - Exponential decay rate per second (0 disables decay)
+    /// * `translation` - Radar translation from base_link (x y z in meters)
+    /// * `rotation` - Radar rotation from base_link as a quaternion (x y z w)
+    ///
+    /// # Returns
+    /// Empty BEV grid
+    pub fn new(
+        size: f32,
+        resolution: f32,
+        decay: f32,
+        translation: &[f64],
+        rotation: &[f64],
+    ) -> Self {
+        let cells = (size / resolution).ceil().max(1.0) as usize;
+        let translation = Translation3::new(
+            translation[0] as f32,
+            translation[1] as f32,
+            translation[2] as f32,
+        );
+        let rotation = UnitQuaternion::from_quaternion(Quaternion::new(
+            rotation[3] as f32,
+            rotation[0] as f32,
+            rotation[1] as f32,
+            rotation[2] as f32,
+        ));
+
+        BevGrid {
+            size,
+            resolution,
+            decay,
+            cells,
+            transform: Isometry3::from_parts(translation, rotation),
+            grid: vec![0.0; cells * cells],
+            timestamp: None,
+        }
+    }
+
+    /// Returns the number of cells along each side of the grid.
+    pub fn cells(&self) -> usize {
+        self.cells
+    }
+
+    /// Map a base_link coordinate to a (row, column) cell index.
+    ///
+    /// # Returns
+    /// The cell index or None if the point falls outside the grid.
+    pub fn cell(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let half = self.size / 2.0;
+        let row = ((half - x) / self.resolution).floor();
+        let col = ((half - y) / self.resolution).floor();
+
+        if !row.is_finite() || !col.is_finite() {
+            return None;
+        }
+
+        if row < 0.0 || col < 0.0 || row >= self.cells as f32 || col >= self.cells as f32 {
+            return None;
+        }
+
+        Some((row as usize, col as usize))
+    }
+
+    /// Decay every cell according to the time elapsed since the previous
+    /// call.  The first call only records the timestamp.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Current time in nanoseconds
+    pub fn decay(&mut self, timestamp: u64) {
+        if let Some(previous) = self.timestamp {
+            let dt = timestamp.saturating_sub(previous) as f32 * 1e-9;
+            let factor = decay_factor(self.decay, dt);
+            self.grid.iter_mut().for_each(|cell| *cell *= factor);
+        }
+        self.timestamp = Some(timestamp);
+    }
+
+    /// Mark the cells containing the provided radar frame points as occupied.
+    /// Points falling outside the grid are ignored.
+    ///
+    /// # Arguments
+    /// * `points` - Iterator of radar frame [x, y, z] coordinates
+    pub fn accumulate<I: IntoIterator<Item = [f32; 3]>>(&mut self, points: I) {
+        for [x, y, z] in points {
+            let p = self.transform * Point3::new(x, y, z);
+            if let Some((row, col)) = self.cell(p.x, p.y) {
+                self.grid[row * self.cells + col] = 1.0;
+            }
+        }
+    }
+
+    /// Returns the occupancy value of the cell at (row, column).
+    #[allow(dead_code)]
+    pub fn value(&self, row: usize, col: usize) -> f32 {
+        self.grid[row * self.cells + col]
+    }
+
+    /// Encode the grid as a row-major mono8 image.
+    pub fn to_mono8(&self) -> Vec<u8> {
+        self.grid
+            .iter()
+            .map(|cell| (cell.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect()
+    }
+}
+
+/// Multiplicative decay applied to a cell after `dt` seconds with the given
+/// exponential decay `rate` per second.
+pub fn decay_factor(rate: f32, dt: f32) -> f32 {
+    (-rate * dt).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> BevGrid {
+        BevGrid::new(10.0, 1.0, 1.0, &[0.0, 0.0, 0.0], &[0.0, 0.0, 0.0, 1.0])
+    }
+
+    #[test]
+    fn test_cell_mapping() {
+        let grid = grid();
+        assert_eq!(grid.cells(), 10);
+
+        // Far forward and left is the top-left corner.
+        assert_eq!(grid.cell(4.9, 4.9), Some((0, 0)));
+        // Far backward and right is the bottom-right corner.
+        assert_eq!(grid.cell(-4.9, -4.9), Some((9, 9)));
+        // The origin sits in the center of the grid.
+        assert_eq!(grid.cell(0.1, 0.1), Some((4, 4)));
+        assert_eq!(grid.cell(-0.1, -0.1), Some((5, 5)));
+    }
+
+    #[test]
+    fn test_cell_clipping() {
+        let grid = grid();
+        assert_eq!(grid.cell(5.0, 0.0), Some((0, 5)));
+        assert_eq!(grid.cell(5.1, 0.0), None);
+        assert_eq!(grid.cell(-5.0, 0.0), None);
+        assert_eq!(grid.cell(0.0, 5.1), None);
+        assert_eq!(grid.cell(0.0, -5.0), None);
+        assert_eq!(grid.cell(f32::NAN, 0.0), None);
+    }
+
+    #[test]
+    fn test_accumulate_transform() {
+        let mut grid = BevGrid::new(10.0, 1.0, 1.0, &[2.0, 0.0, 0.0], &[0.0, 0.0, 0.0, 1.0]);
+        grid.accumulate([[0.5, 0.5, 0.0], [100.0, 0.0, 0.0]]);
+
+        // The radar is 2m ahead of base_link so the point lands at x=2.5.
+        assert_eq!(grid.value(2, 4), 1.0);
+        assert_eq!(grid.to_mono8().iter().filter(|v| **v == 255).count(), 1);
+    }
+
+    #[test]
+    fn test_decay() {
+        assert_eq!(decay_factor(1.0, 0.0), 1.0);
+        assert!((decay_factor(1.0, 1.0) - (-1.0f32).exp()).abs() < 1e-6);
+        assert_eq!(decay_factor(0.0, 10.0), 1.0);
+
+        let mut grid = grid();
+        grid.accumulate([[0.5, 0.5, 0.0]]);
+        grid.decay(0);
+        assert_eq!(grid.value(4, 4), 1.0);
+        grid.decay(500_000_000);
+        assert!((grid.value(4, 4) - (-0.5f32).exp()).abs() < 1e-6);
+        grid.decay(1_500_000_000);
+        assert!((grid.value(4, 4) - (-1.5f32).exp()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,366 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Radome blockage detection from target statistics.
+//!
+//! Mud, snow or ice on the radome attenuates every reflection, so the radar
+//! reports fewer targets and the remaining ones closer to the noise floor.
+//! The [`BlockageDetector`] compares a short-term average of the target
+//! count and the signal to noise ratio of every frame with a baseline
+//! learned over hours, and reports a blockage once both stay below a
+//! fraction of the baseline for the hold time.  The baseline is only
+//! learned from unblocked frames and is saved to a state file so a restart
+//! does not start the learning over.
+
+use crate::can::Target;
+use serde_json::{json, Value};
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Time constant of the short-term average of the frame statistics.
+pub const SHORT_TERM_WINDOW: Duration = Duration::from_secs(30);
+
+/// Learning time before the baseline is trusted to detect a blockage.
+pub const BASELINE_WARMUP: Duration = Duration::from_secs(600);
+
+/// Statistics of the targets of a radar frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    /// Number of targets
+    pub n_targets: f64,
+    /// Mean target power above the mean target noise in dB, 0 without
+    /// targets
+    pub snr: f64,
+}
+
+impl FrameStats {
+    /// Statistics of the targets of a frame.
+    pub fn from_targets(targets: &[Target]) -> Self {
+        if targets.is_empty() {
+            return FrameStats::default();
+        }
+        let n = targets.len() as f64;
+        let power = targets.iter().map(|t| t.power).sum::<f64>() / n;
+        let noise = targets.iter().map(|t| t.noise).sum::<f64>() / n;
+        FrameStats {
+            n_targets: n,
+            snr: (power - noise).max(0.0),
+        }
+    }
+
+    /// Exponential moving average step towards `sample` with weight `alpha`.
+    fn blend(&mut self, sample: &FrameStats, alpha: f64) {
+        self.n_targets += alpha * (sample.n_targets - self.n_targets);
+        self.snr += alpha * (sample.snr - self.snr);
+    }
+}
+
+/// Long-term frame statistics of the unblocked radar.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Baseline {
+    /// Average frame statistics
+    pub stats: FrameStats,
+    /// Seconds of frames learned
+    pub learned_sec: f64,
+}
+
+impl Baseline {
+    /// JSON object of the baseline, the content of the state file.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "n_targets": self.stats.n_targets,
+            "snr": self.stats.snr,
+            "learned_sec": self.learned_sec,
+        })
+    }
+
+    /// Parse a [`Baseline::to_json`] object, None if malformed.
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let baseline = Baseline {
+            stats: FrameStats {
+                n_targets: value["n_targets"].as_f64()?,
+                snr: value["snr"].as_f64()?,
+            },
+            learned_sec: value["learned_sec"].as_f64()?,
+        };
+        let valid = [
+            baseline.stats.n_targets,
+            baseline.stats.snr,
+            baseline.learned_sec,
+        ]
+        .iter()
+        .all(|v| v.is_finite() && *v >= 0.0);
+        valid.then_some(baseline)
+    }
+}
+
+/// Parameters of the [`BlockageDetector`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockageSettings {
+    /// Fraction of the baseline below which a frame is suspect
+    pub fraction: f64,
+    /// Time the frames must stay suspect before reporting a blockage
+    pub hold: Duration,
+    /// Time constant of the baseline average
+    pub baseline_window: Duration,
+}
+
+/// Radome blockage detector fed with the statistics of every frame.
+#[derive(Debug, Clone)]
+pub struct BlockageDetector {
+    settings: BlockageSettings,
+    baseline: Baseline,
+    short: Option<FrameStats>,
+    last: Option<Instant>,
+    suspect_since: Option<Instant>,
+    blocked: bool,
+}
+
+impl BlockageDetector {
+    /// Create a detector continuing from a saved baseline, if any.
+    pub fn new(settings: BlockageSettings, baseline: Option<Baseline>) -> Self {
+        BlockageDetector {
+            settings,
+            baseline: baseline.unwrap_or_default(),
+            short: None,
+            last: None,
+            suspect_since: None,
+            blocked: false,
+        }
+    }
+
+    /// Create a detector continuing from the baseline saved at `path`, a
+    /// missing or malformed file starts the learning over.
+    pub fn load(settings: BlockageSettings, path: &Path) -> Self {
+        let baseline = match fs::read_to_string(path) {
+            Ok(text) => {
+                let baseline = serde_json::from_str(&text)
+                    .ok()
+                    .and_then(|value| Baseline::from_json(&value));
+                if baseline.is_none() {
+                    warn!("ignoring malformed blockage state {}", path.display());
+                }
+                baseline
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                warn!("blockage state {} error: {}", path.display(), err);
+                None
+            }
+        };
+        BlockageDetector::new(settings, baseline)
+    }
+
+    /// Save the baseline to `path`, replacing the previous state at once so
+    /// a crash cannot leave a truncated file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, self.baseline.to_json().to_string())?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Returns the learned baseline.
+    pub fn baseline(&self) -> &Baseline {
+        &self.baseline
+    }
+
+    /// Returns true while a blockage is reported.
+    pub fn blocked(&self) -> bool {
+        self.blocked
+    }
+
+    /// Update the detector with the statistics of a frame received at
+    /// `now`.
+    ///
+    /// # Returns
+    /// True while a blockage is reported
+    pub fn update(&mut self, stats: &FrameStats, now: Instant) -> bool {
+        // A gap in the frames counts as a single short-term window.
+        let dt = self
+            .last
+            .replace(now)
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f64())
+            .min(SHORT_TERM_WINDOW.as_secs_f64());
+
+        let short = self.short.get_or_insert(*stats);
+        short.blend(stats, 1.0 - (-dt / SHORT_TERM_WINDOW.as_secs_f64()).exp());
+        let short = *short;
+
+        let baseline = &self.baseline.stats;
+        let fraction = self.settings.fraction;
+        let suspect = self.baseline.learned_sec >= BASELINE_WARMUP.as_secs_f64()
+            && short.n_targets < fraction * baseline.n_targets
+            && short.snr < fraction * baseline.snr;
+
+        match suspect {
+            true => {
+                let since = *self.suspect_since.get_or_insert(now);
+                if !self.blocked && now.duration_since(since) >= self.settings.hold {
+                    warn!(
+                        "radome blockage suspected: {:.1} targets at {:.1} dB SNR, baseline {:.1} \
+                         targets at {:.1} dB",
+                        short.n_targets, short.snr, baseline.n_targets, baseline.snr
+                    );
+                    self.blocked = true;
+                }
+            }
+            false => {
+                if self.blocked {
+                    info!(
+                        "radome blockage cleared: {:.1} targets at {:.1} dB SNR",
+                        short.n_targets, short.snr
+                    );
+                }
+                self.suspect_since = None;
+                self.blocked = false;
+                self.learn(stats, dt);
+            }
+        }
+        self.blocked
+    }
+
+    /// Learn the baseline from an unblocked frame, a plain average until
+    /// the baseline window has been learned.
+    fn learn(&mut self, stats: &FrameStats, dt: f64) {
+        let learned = self.baseline.learned_sec + dt;
+        let alpha = match learned > 0.0 {
+            true => (dt / learned)
+                .max(1.0 - (-dt / self.settings.baseline_window.as_secs_f64().max(1.0)).exp()),
+            false => 1.0,
+        };
+        self.baseline.stats.blend(stats, alpha);
+        self.baseline.learned_sec = learned;
+    }
+
+    /// JSON object of the detector state published on the blockage topic.
+    pub fn to_json(&self) -> Value {
+        let short = self.short.unwrap_or_default();
+        json!({
+            "blocked": self.blocked,
+            "n_targets": short.n_targets,
+            "snr": short.snr,
+            "baseline": self.baseline.to_json(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> BlockageSettings {
+        BlockageSettings {
+            fraction: 0.3,
+            hold: Duration::from_secs(60),
+            baseline_window: Duration::from_secs(4 * 3600),
+        }
+    }
+
+    fn stats(n_targets: f64, snr: f64) -> FrameStats {
+        FrameStats { n_targets, snr }
+    }
+
+    /// Feed one frame a second from `start` for `secs` seconds, returning
+    /// the first second at which a blockage was reported.
+    fn feed(
+        detector: &mut BlockageDetector,
+        start: Instant,
+        secs: std::ops::Range<u64>,
+        frame: impl Fn(u64) -> FrameStats,
+    ) -> Option<u64> {
+        let mut first = None;
+        for s in secs {
+            let blocked = detector.update(&frame(s), start + Duration::from_secs(s));
+            if blocked && first.is_none() {
+                first = Some(s);
+            }
+        }
+        first
+    }
+
+    #[test]
+    fn test_frame_stats() {
+        let target = |power, noise| Target {
+            power,
+            noise,
+            ..Default::default()
+        };
+        let s = FrameStats::from_targets(&[target(-70.0, -95.0), target(-80.0, -95.0)]);
+        assert_eq!(s, stats(2.0, 20.0));
+        assert_eq!(FrameStats::from_targets(&[]), stats(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_gradual_blockage() {
+        let mut detector = BlockageDetector::new(settings(), None);
+        let start = Instant::now();
+
+        // Two hours of a busy scene, with an empty scene during the warmup
+        // which is not reported.
+        let busy = |s: u64| stats(40.0 + (s % 7) as f64, 18.0 + (s % 3) as f64);
+        assert_eq!(
+            feed(&mut detector, start, 0..300, |_| stats(0.0, 0.0)),
+            None
+        );
+        assert_eq!(feed(&mut detector, start, 300..7200, busy), None);
+        let baseline = detector.baseline();
+        assert_eq!(baseline.learned_sec, 7199.0);
+        assert!(baseline.stats.n_targets > 35.0);
+
+        // The radome fouls over ten minutes down to a tenth of the targets
+        // and the SNR.
+        let fouling = |s: u64| {
+            let t = ((s - 7200) as f64 / 600.0).min(1.0);
+            let scale = 1.0 - 0.9 * t;
+            stats(43.0 * scale, 19.0 * scale)
+        };
+        let blocked = feed(&mut detector, start, 7200..9000, fouling).unwrap();
+        // Both averages fall below 30% of the baseline about 8 minutes in,
+        // the alarm follows after the minute of hold time.
+        assert!((7200 + 480..7200 + 660).contains(&blocked), "{}", blocked);
+        // The baseline did not learn the blockage.
+        assert!(detector.baseline().stats.n_targets > 30.0);
+
+        // Cleaning the radome clears the alarm within the short-term window.
+        feed(&mut detector, start, 9000..9120, busy);
+        assert!(!detector.blocked());
+
+        // A dark scene with strong reflections is not a blockage.
+        assert_eq!(
+            feed(&mut detector, start, 9120..10000, |_| stats(3.0, 19.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_blockage_restart() {
+        let path = std::env::temp_dir().join(format!("radarpub-blockage-{}", std::process::id()));
+        let start = Instant::now();
+
+        let mut detector = BlockageDetector::load(settings(), &path);
+        assert_eq!(detector.baseline(), &Baseline::default());
+        feed(&mut detector, start, 0..3600, |_| stats(40.0, 18.0));
+        detector.save(&path).unwrap();
+
+        // The restarted detector keeps the baseline and detects a blockage
+        // without a new warmup.
+        let mut restarted = BlockageDetector::load(settings(), &path);
+        assert_eq!(restarted.baseline(), detector.baseline());
+        let start = start + Duration::from_secs(7200);
+        let blocked = feed(&mut restarted, start, 0..300, |_| stats(2.0, 3.0)).unwrap();
+        assert!((60..150).contains(&blocked), "{}", blocked);
+
+        // A fresh detector would still be learning.
+        let mut fresh = BlockageDetector::new(settings(), None);
+        assert_eq!(feed(&mut fresh, start, 0..300, |_| stats(2.0, 3.0)), None);
+
+        fs::write(&path, "{\"n_targets\": -1}").unwrap();
+        let detector = BlockageDetector::load(settings(), &path);
+        assert_eq!(detector.baseline(), &Baseline::default());
+        fs::remove_file(&path).unwrap();
+    }
+}
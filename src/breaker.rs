@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::sink::Sink;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use zenoh::bytes::{Encoding, ZBytes};
+
+/// State of a [`PublishBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Messages are published normally.
+    Closed,
+
+    /// The session is considered dead, messages are skipped and only a
+    /// periodic probe message is published.
+    Open,
+}
+
+/// Publish counters of a [`PublishBreaker`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PublishCounters {
+    /// Messages published successfully
+    pub published: u64,
+
+    /// Messages which failed to publish, including failed probes
+    pub failed: u64,
+
+    /// Messages skipped while the breaker was open
+    pub skipped: u64,
+
+    /// Number of times the breaker opened
+    pub trips: u64,
+}
+
+/// Circuit breaker for a single publisher.
+///
+/// When the Zenoh session dies every put fails, yet the messages are still
+/// serialized at the full radar rate.  After `threshold` consecutive
+/// failures the breaker opens and [`PublishBreaker::ready`] returns false so
+/// the caller skips the serialization entirely.  Once per probe interval a
+/// single message is let through as a probe, the first successful probe
+/// closes the breaker and publishing resumes.
+#[derive(Debug, Clone)]
+pub struct PublishBreaker {
+    /// Topic used in the log messages
+    topic: String,
+
+    /// Consecutive failures before the breaker opens
+    threshold: u32,
+
+    /// Time between probes while open
+    probe_interval: Duration,
+
+    /// Current state
+    state: BreakerState,
+
+    /// Consecutive failed puts
+    consecutive_failures: u32,
+
+    /// Time of the last publish attempt
+    last_attempt: Option<Instant>,
+
+    /// Lifetime counters
+    counters: PublishCounters,
+}
+
+impl PublishBreaker {
+    /// Create a closed breaker.
+    ///
+    /// # Arguments
+    /// * `topic` - Topic of the publisher, used for logging
+    /// * `threshold` - Consecutive failures before the breaker opens
+    /// * `probe_interval` - Time between probes while the breaker is open
+    pub fn new(topic: &str, threshold: u32, probe_interval: Duration) -> Self {
+        PublishBreaker {
+            topic: topic.to_string(),
+            threshold: threshold.max(1),
+            probe_interval,
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            last_attempt: None,
+            counters: PublishCounters::default(),
+        }
+    }
+
+    /// Returns the current state.
+    #[allow(dead_code)]
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    /// Returns the lifetime publish counters.
+    pub fn counters(&self) -> PublishCounters {
+        self.counters
+    }
+
+    /// Returns true if the next message should be serialized and published,
+    /// either because the breaker is closed or because a probe is due.
+    /// Otherwise the message is counted as skipped.
+    pub fn ready(&mut self) -> bool {
+        self.ready_at(Instant::now())
+    }
+
+    fn ready_at(&mut self, now: Instant) -> bool {
+        if let (BreakerState::Open, Some(last)) = (self.state, self.last_attempt) {
+            if now.duration_since(last) < self.probe_interval {
+                self.counters.skipped += 1;
+                return false;
+            }
+        }
+
+        self.last_attempt = Some(now);
+        true
+    }
+
+    /// Publish a message and record the outcome.
+    ///
+    /// # Errors
+    /// Returns the publisher error, which is already counted by the breaker
+    pub async fn publish<S: Sink>(
+        &mut self,
+        sink: &S,
+        payload: ZBytes,
+        encoding: Encoding,
+    ) -> Result<(), zenoh::Error> {
+        let result = sink.publish(payload, encoding).await;
+        self.record(result.is_ok());
+        result
+    }
+
+    /// Record the outcome of a put, opening or closing the breaker.
+    pub fn record(&mut self, success: bool) {
+        if success {
+            self.counters.published += 1;
+            if self.state == BreakerState::Open {
+                info!(
+                    "{} publishing resumed after {} skipped messages, counters {:?}",
+                    self.topic, self.counters.skipped, self.counters
+                );
+                self.state = BreakerState::Closed;
+            }
+            self.consecutive_failures = 0;
+            return;
+        }
+
+        self.counters.failed += 1;
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.state == BreakerState::Closed && self.consecutive_failures >= self.threshold {
+            warn!(
+                "{} failed {} consecutive publishes, pausing and probing every {:?}",
+                self.topic, self.consecutive_failures, self.probe_interval
+            );
+            self.state = BreakerState::Open;
+            self.counters.trips += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        cell::Cell,
+        future::Future,
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
+    /// Publisher which fails while the session is down.
+    #[derive(Default)]
+    struct FlakyPublisher {
+        down: AtomicBool,
+        puts: AtomicUsize,
+    }
+
+    impl Sink for FlakyPublisher {
+        fn publish(
+            &self,
+            _payload: ZBytes,
+            _encoding: Encoding,
+        ) -> impl Future<Output = Result<(), zenoh::Error>> + Send {
+            self.puts.fetch_add(1, Ordering::Relaxed);
+            let down = self.down.load(Ordering::Relaxed);
+            async move {
+                match down {
+                    true => Err("session closed".into()),
+                    false => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_breaker_opens_probes_and_closes() {
+        let publisher = FlakyPublisher::default();
+        let mut breaker = PublishBreaker::new("rt/test", 3, Duration::from_secs(1));
+        let start = Instant::now();
+        let serialized = Cell::new(0);
+
+        // Publish one message per 100ms of simulated time.
+        let step = |breaker: &mut PublishBreaker, ms: u64| {
+            if breaker.ready_at(start + Duration::from_millis(ms)) {
+                serialized.set(serialized.get() + 1);
+                let _ = block_on(breaker.publish(
+                    &publisher,
+                    ZBytes::from("msg"),
+                    Encoding::TEXT_PLAIN,
+                ));
+            }
+        };
+
+        step(&mut breaker, 0);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        publisher.down.store(true, Ordering::Relaxed);
+        for ms in (100..=300).step_by(100) {
+            step(&mut breaker, ms);
+        }
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(breaker.counters().trips, 1);
+        assert_eq!(breaker.counters().failed, 3);
+
+        // While open only one probe per second reaches the publisher.
+        let puts = publisher.puts.load(Ordering::Relaxed);
+        for ms in (400..=2300).step_by(100) {
+            step(&mut breaker, ms);
+        }
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(publisher.puts.load(Ordering::Relaxed) - puts, 2);
+        assert_eq!(breaker.counters().skipped, 18);
+        assert_eq!(breaker.counters().trips, 1);
+        assert_eq!(serialized.get(), 6);
+
+        // The first successful probe closes the breaker.
+        publisher.down.store(false, Ordering::Relaxed);
+        for ms in (2400..=3500).step_by(100) {
+            step(&mut breaker, ms);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        let counters = breaker.counters();
+        assert_eq!(counters.failed, 5);
+        assert!(counters.published > 1);
+        assert_eq!(counters.published + counters.failed + counters.skipped, 36);
+    }
+
+    #[test]
+    fn test_breaker_tolerates_sporadic_failures() {
+        let mut breaker = PublishBreaker::new("rt/test", 3, Duration::from_secs(1));
+        for _ in 0..10 {
+            breaker.record(false);
+            breaker.record(false);
+            breaker.record(true);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(breaker.counters().trips, 0);
+        assert_eq!(breaker.counters().failed, 20);
+        assert!(breaker.ready());
+    }
+}
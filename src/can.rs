@@ -2,9 +2,20 @@
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
 use crc16::{State, CCITT_FALSE};
-use log::{debug, trace};
-use socketcan::{tokio::CanSocket, CanFrame, EmbeddedFrame, Id as CanId, StandardId};
-use std::{fmt, io};
+use edgefirst_schemas::builtin_interfaces::Time;
+use log::{debug, info, trace, warn};
+use socketcan::{tokio::CanSocket, CanFrame, EmbeddedFrame, ExtendedId, Id as CanId, StandardId};
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File, OpenOptions},
+    future::Future,
+    io::{self, BufRead, Write},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 #[allow(unused)]
 /// DRVEGRD protocol error types.
@@ -22,13 +33,21 @@ pub enum Error {
     /// No CAN socket available
     NoSocket,
     /// Response ID does not match request
-    InvalidResponseId(u16),
+    InvalidResponseId(u32),
     /// Unsupported UAT protocol version
     UATProtocolUnsupported(u16),
     /// CRC check failed
     UATCRCError,
     /// UAT protocol error code
-    UATError(u16),
+    UATError(UatErrorCode),
+    /// Sensor rejected saving the parameters to non-volatile memory
+    SaveRejected(UatErrorCode),
+    /// No instruction response frame within the read timeout
+    ResponseTimeout(Duration),
+    /// Another client sent an instruction while awaiting a response
+    ConcurrentInstruction,
+    /// The CAN interface is locked by radarpub, with its process id
+    InterfaceLocked(String, Option<u32>),
 }
 
 impl std::error::Error for Error {}
@@ -51,11 +70,127 @@ impl fmt::Display for Error {
                 write!(f, "UAT protocol version {} unsupported", ver)
             }
             Error::UATCRCError => write!(f, "UAT CRC error"),
-            Error::UATError(err) => write!(f, "UAT error: {}", err),
+            Error::UATError(err) => write!(f, "UAT error {}: {}", err.code(), err),
+            Error::SaveRejected(err) => write!(
+                f,
+                "sensor rejected saving parameters with UAT error {} ({}), some firmware versions limit how often parameters can be saved",
+                err.code(),
+                err
+            ),
+            Error::ResponseTimeout(timeout) => {
+                write!(f, "no instruction response within {:?}", timeout)
+            }
+            Error::ConcurrentInstruction => write!(
+                f,
+                "another client sent an instruction to the sensor, is radarpub using this interface?"
+            ),
+            Error::InterfaceLocked(iface, Some(pid)) => {
+                write!(f, "{} is in use by radarpub (pid {})", iface, pid)
+            }
+            Error::InterfaceLocked(iface, None) => write!(f, "{} is in use by radarpub", iface),
+        }
+    }
+}
+
+/// Result code of a failed UATv4 instruction response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UatErrorCode {
+    /// The sensor could not execute the instruction
+    Failed,
+    /// Unknown section, parameter or command identifier
+    InvalidParameter,
+    /// Value outside of the range of the parameter
+    OutOfRange,
+    /// Parameter cannot be written
+    WriteProtected,
+    /// Sensor busy with a previous instruction
+    Busy,
+    /// A code missing from the table above
+    Unknown(u16),
+}
+
+impl UatErrorCode {
+    /// Every known code, in numeric order.
+    pub const KNOWN: [UatErrorCode; 5] = [
+        UatErrorCode::Failed,
+        UatErrorCode::InvalidParameter,
+        UatErrorCode::OutOfRange,
+        UatErrorCode::WriteProtected,
+        UatErrorCode::Busy,
+    ];
+
+    /// The numeric result code of the response.
+    pub fn code(&self) -> u16 {
+        match self {
+            UatErrorCode::Failed => 1,
+            UatErrorCode::InvalidParameter => 2,
+            UatErrorCode::OutOfRange => 3,
+            UatErrorCode::WriteProtected => 4,
+            UatErrorCode::Busy => 5,
+            UatErrorCode::Unknown(code) => *code,
+        }
+    }
+
+    /// Short snake case name of the code, used as the key of the error
+    /// counters.
+    pub fn name(&self) -> String {
+        match self {
+            UatErrorCode::Failed => "failed".to_string(),
+            UatErrorCode::InvalidParameter => "invalid_parameter".to_string(),
+            UatErrorCode::OutOfRange => "out_of_range".to_string(),
+            UatErrorCode::WriteProtected => "write_protected".to_string(),
+            UatErrorCode::Busy => "busy".to_string(),
+            UatErrorCode::Unknown(code) => format!("unknown_{}", code),
+        }
+    }
+}
+
+impl From<u16> for UatErrorCode {
+    fn from(code: u16) -> Self {
+        UatErrorCode::KNOWN
+            .into_iter()
+            .find(|known| known.code() == code)
+            .unwrap_or(UatErrorCode::Unknown(code))
+    }
+}
+
+impl fmt::Display for UatErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UatErrorCode::Failed => write!(f, "instruction failed"),
+            UatErrorCode::InvalidParameter => write!(f, "invalid parameter"),
+            UatErrorCode::OutOfRange => write!(f, "value out of range"),
+            UatErrorCode::WriteProtected => write!(f, "parameter is write protected"),
+            UatErrorCode::Busy => write!(f, "sensor busy"),
+            UatErrorCode::Unknown(_) => write!(f, "unknown error"),
         }
     }
 }
 
+/// Failed instruction responses of the process by result code.
+static UAT_ERRORS: Mutex<Vec<(u16, u64)>> = Mutex::new(Vec::new());
+
+/// Count a failed instruction response.
+fn count_uat_error(code: UatErrorCode) {
+    let mut errors = UAT_ERRORS.lock().unwrap();
+    match errors.iter_mut().find(|(known, _)| *known == code.code()) {
+        Some((_, count)) => *count += 1,
+        None => errors.push((code.code(), 1)),
+    }
+}
+
+/// Returns the failed instruction responses received by the process for
+/// every result code seen, in numeric order.
+#[allow(dead_code)]
+pub fn uat_error_counts() -> Vec<(UatErrorCode, u64)> {
+    let mut errors = UAT_ERRORS.lock().unwrap().clone();
+    errors.sort_unstable();
+    errors
+        .into_iter()
+        .map(|(code, count)| (UatErrorCode::from(code), count))
+        .collect()
+}
+
 /// Raw CAN message packet from DRVEGRD radar.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Packet {
@@ -65,6 +200,736 @@ pub struct Packet {
     pub data: u64,
 }
 
+/// Source of CAN packets.
+///
+/// Implemented for the SocketCAN [`CanSocket`] as well as [`CanInjector`]
+/// which allows synthetic frames to be injected for testing without CAN
+/// hardware.
+pub trait CanReader: Send + Sync {
+    /// Read the next data packet from the CAN bus.
+    fn read_frame(&self) -> impl Future<Output = Result<Packet, Error>> + Send;
+
+    /// Discard the packets already queued for this reader, called before an
+    /// instruction is sent so a late response cannot be taken as its reply.
+    ///
+    /// # Returns
+    /// The number of discarded packets
+    fn discard_pending(&self) -> usize {
+        0
+    }
+}
+
+/// Sink for CAN packets.
+pub trait CanWriter: Send + Sync {
+    /// Write a data packet to the CAN bus.
+    fn write_frame(&self, packet: Packet) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// Blocking source of CAN packets, for tools without an async runtime.
+///
+/// Implemented for the blocking SocketCAN [`socketcan::CanSocket`] as well
+/// as [`CanInjector`], the frames are parsed by the same code as those of a
+/// [`CanReader`].
+pub trait BlockingCanReader {
+    /// Read the next data packet from the CAN bus, waiting at most `timeout`
+    /// or forever for None.
+    ///
+    /// # Errors
+    /// Returns an [`Error::Io`] of kind [`io::ErrorKind::TimedOut`] if no
+    /// packet arrived in time
+    fn read_frame_blocking(&self, timeout: Option<Duration>) -> Result<Packet, Error>;
+}
+
+/// Blocking sink for CAN packets.
+pub trait BlockingCanWriter {
+    /// Write a data packet to the CAN bus.
+    fn write_frame_blocking(&self, packet: Packet) -> Result<(), Error>;
+}
+
+/// Packet of a received data frame.
+fn data_packet(frame: CanFrame) -> Packet {
+    match frame {
+        CanFrame::Data(frame) => {
+            let id = match frame.id() {
+                CanId::Standard(id) => id.as_raw() as u32,
+                CanId::Extended(id) => id.as_raw(),
+            };
+            Packet {
+                id,
+                data: load_data(frame.data()),
+            }
+        }
+        CanFrame::Remote(frame) => panic!("Unexpected remote frame: {:?}", frame),
+        CanFrame::Error(frame) => panic!("Unexpected error frame: {:?}", frame),
+    }
+}
+
+/// Data frame carrying a packet.
+fn packet_frame(packet: Packet) -> Result<CanFrame, Error> {
+    // Identifiers beyond the 11-bit range are sent as extended frames.
+    let id = match packet.id <= MAX_STANDARD_ID {
+        true => StandardId::new(packet.id as u16).map(CanId::Standard),
+        false => ExtendedId::new(packet.id).map(CanId::Extended),
+    }
+    .ok_or(Error::InvalidHeader(format!(
+        "invalid can id 0x{:X}",
+        packet.id
+    )))?;
+    Ok(CanFrame::new(id, &packet.data.to_le_bytes()).unwrap())
+}
+
+impl CanReader for CanSocket {
+    async fn read_frame(&self) -> Result<Packet, Error> {
+        Ok(data_packet(CanSocket::read_frame(self).await?))
+    }
+}
+
+impl CanWriter for CanSocket {
+    async fn write_frame(&self, packet: Packet) -> Result<(), Error> {
+        CanSocket::write_frame(self, packet_frame(packet)?).await?;
+        Ok(())
+    }
+}
+
+impl BlockingCanReader for socketcan::CanSocket {
+    fn read_frame_blocking(&self, timeout: Option<Duration>) -> Result<Packet, Error> {
+        let frame = match timeout {
+            Some(timeout) => socketcan::Socket::read_frame_timeout(self, timeout),
+            None => socketcan::Socket::read_frame(self),
+        };
+        match frame {
+            Ok(frame) => Ok(data_packet(frame)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                Err(Error::Io(io::ErrorKind::TimedOut.into()))
+            }
+            Err(err) => Err(Error::Io(err)),
+        }
+    }
+}
+
+impl BlockingCanWriter for socketcan::CanSocket {
+    fn write_frame_blocking(&self, packet: Packet) -> Result<(), Error> {
+        socketcan::Socket::write_frame(self, &packet_frame(packet)?)?;
+        Ok(())
+    }
+}
+
+/// In-memory CAN bus for hardware-in-loop and unit testing.
+///
+/// Packets pushed into the injector are returned in order by
+/// [`CanReader::read_frame`], which yields to the runtime while the queue is
+/// empty.  Packets written through [`CanWriter::write_frame`] are recorded
+/// and can be inspected with [`CanInjector::sent`].  Sensor replies queued
+/// with [`CanInjector::reply`] are only returned once an instruction was
+/// written.  Clones share the same queues so a test can keep a handle while
+/// the code under test owns another.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct CanInjector {
+    queue: Arc<Mutex<VecDeque<Packet>>>,
+    sent: Arc<Mutex<Vec<Packet>>>,
+    replies: Arc<Mutex<VecDeque<Vec<Packet>>>>,
+    written: Arc<Mutex<usize>>,
+}
+
+#[allow(dead_code)]
+impl CanInjector {
+    /// Create a new empty injector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a packet to be returned by a future read.
+    pub fn push(&self, packet: Packet) {
+        self.queue.lock().unwrap().push_back(packet);
+    }
+
+    /// Queue a sequence of packets to be returned by future reads.
+    pub fn extend<I: IntoIterator<Item = Packet>>(&self, packets: I) {
+        self.queue.lock().unwrap().extend(packets);
+    }
+
+    /// Queue the reply of the sensor to an instruction, it is returned by
+    /// future reads once the next three frame instruction has been written.
+    pub fn reply<I: IntoIterator<Item = Packet>>(&self, packets: I) {
+        self.replies
+            .lock()
+            .unwrap()
+            .push_back(packets.into_iter().collect());
+    }
+
+    /// Record a written packet and release the next reply once a complete
+    /// instruction was written.
+    fn record(&self, packet: Packet) {
+        self.sent.lock().unwrap().push(packet);
+        let mut written = self.written.lock().unwrap();
+        *written += 1;
+        if *written % 3 == 0 {
+            if let Some(reply) = self.replies.lock().unwrap().pop_front() {
+                self.extend(reply);
+            }
+        }
+    }
+
+    /// Returns the number of packets waiting to be read.
+    pub fn pending(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    /// Drain and return the packets written to the injector.
+    pub fn sent(&self) -> Vec<Packet> {
+        std::mem::take(&mut *self.sent.lock().unwrap())
+    }
+
+    /// Create an injector preloaded with every frame of a candump log.
+    ///
+    /// # Arguments
+    /// * `reader` - Log in the `candump -L` format, see [`parse_candump`]
+    ///
+    /// # Errors
+    /// Returns Error if the log cannot be read or contains an invalid frame
+    pub fn from_candump<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let can = Self::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            can.push(parse_candump(line)?);
+        }
+        Ok(can)
+    }
+}
+
+/// Parse a single line of a `candump -L` log such as
+/// `(1700000000.000000) can0 400#5B8382323B80880C`.
+///
+/// The timestamp and interface are ignored.  Frames carry at most 8 data
+/// bytes which are zero padded into the packet data.
+///
+/// # Errors
+/// Returns Error if the line is not a valid candump data frame
+#[allow(dead_code)]
+pub fn parse_candump(line: &str) -> Result<Packet, Error> {
+    let invalid = || {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid candump line: {}", line),
+        ))
+    };
+
+    let frame = line.split_whitespace().last().ok_or_else(invalid)?;
+    let (id, data) = frame.split_once('#').ok_or_else(invalid)?;
+    let id = u32::from_str_radix(id, 16).map_err(|_| invalid())?;
+
+    if !data.is_ascii() || data.len() > 16 || data.len() % 2 != 0 {
+        return Err(invalid());
+    }
+
+    let mut bytes = [0u8; 8];
+    for (i, byte) in bytes.iter_mut().enumerate().take(data.len() / 2) {
+        *byte = u8::from_str_radix(&data[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+
+    Ok(Packet {
+        id,
+        data: u64::from_le_bytes(bytes),
+    })
+}
+
+impl CanReader for CanInjector {
+    async fn read_frame(&self) -> Result<Packet, Error> {
+        loop {
+            if let Some(packet) = self.queue.lock().unwrap().pop_front() {
+                return Ok(packet);
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+impl CanWriter for CanInjector {
+    async fn write_frame(&self, packet: Packet) -> Result<(), Error> {
+        self.record(packet);
+        Ok(())
+    }
+}
+
+impl BlockingCanReader for CanInjector {
+    // The injector is filled before it is read, an empty queue times out at
+    // once whatever the timeout so a test cannot hang.
+    fn read_frame_blocking(&self, _timeout: Option<Duration>) -> Result<Packet, Error> {
+        self.queue
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| Error::Io(io::ErrorKind::TimedOut.into()))
+    }
+}
+
+impl BlockingCanWriter for CanInjector {
+    fn write_frame_blocking(&self, packet: Packet) -> Result<(), Error> {
+        self.record(packet);
+        Ok(())
+    }
+}
+
+/// Default CAN identifier of the frame header, the targets follow it.
+pub const TARGET_ID_BASE: u32 = 0x400;
+
+/// Default CAN identifier of UATv4 instruction requests.
+pub const INSTRUCTION_ID: u32 = 0x3FB;
+
+/// Default CAN identifier of UATv4 instruction responses.
+pub const RESPONSE_ID: u32 = 0x700;
+
+/// Default CAN identifier of the periodic sensor status broadcast.
+pub const STATUS_ID: u32 = 0x600;
+
+/// Bit offset of the sensor temperature in the status frame, 8 bits.
+pub const STATUS_TEMPERATURE_SHIFT: u32 = 0;
+
+/// Temperature in °C of a raw status temperature of 0, 1 °C per bit.
+pub const STATUS_TEMPERATURE_OFFSET: f32 = -50.0;
+
+/// Bit offset of the supply voltage in the status frame, 12 bits.
+pub const STATUS_VOLTAGE_SHIFT: u32 = 8;
+
+/// Supply voltage in volts per bit of the status frame.
+pub const STATUS_VOLTAGE_SCALE: f32 = 0.01;
+
+/// Bit of the status frame set while the radome is blocked.
+pub const STATUS_BLOCKAGE_BIT: u32 = 20;
+
+/// Largest 11-bit standard CAN identifier.
+pub const MAX_STANDARD_ID: u32 = 0x7FF;
+
+/// Largest 29-bit extended CAN identifier.
+pub const MAX_EXTENDED_ID: u32 = 0x1FFF_FFFF;
+
+/// Parse a CAN identifier in decimal or 0x prefixed hexadecimal.
+pub fn parse_can_id(value: &str) -> Result<u32, String> {
+    let id = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|e| e.to_string())?;
+    match id <= MAX_EXTENDED_ID {
+        true => Ok(id),
+        false => Err(format!("0x{:X} is beyond the 29-bit CAN identifiers", id)),
+    }
+}
+
+/// Parse a frame header CAN identifier which leaves room for the shifted
+/// instruction and target identifiers.
+pub fn parse_can_id_base(value: &str) -> Result<u32, String> {
+    let base = parse_can_id(value)?;
+    match CanIds::new(base) {
+        Some(_) => Ok(base),
+        None => Err(format!(
+            "0x{:X} shifts the instruction or target identifiers out of the 29-bit range",
+            base
+        )),
+    }
+}
+
+/// CAN identifiers used by a radar.
+///
+/// Sensors sharing a bus are configured with different identifiers, often in
+/// the extended range, so every identifier is shifted by the offset of the
+/// frame header `base` from its default [`TARGET_ID_BASE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanIds {
+    /// Frame header, the targets use `base + 1` to `base + n_targets`
+    pub base: u32,
+    /// Instruction requests
+    pub instruction: u32,
+    /// Instruction responses
+    pub response: u32,
+    /// Sensor status broadcasts
+    pub status: u32,
+}
+
+impl CanIds {
+    /// Identifiers shifted to the frame header `base`, None if any of them
+    /// falls outside of the 29-bit extended range.
+    pub fn new(base: u32) -> Option<Self> {
+        let shift = |id: u32| {
+            id.checked_add(base)?
+                .checked_sub(TARGET_ID_BASE)
+                .filter(|id| *id <= MAX_EXTENDED_ID)
+        };
+        // The last of the 256 targets must fit as well.
+        shift(TARGET_ID_BASE + 256)?;
+        Some(CanIds {
+            base,
+            instruction: shift(INSTRUCTION_ID)?,
+            response: shift(RESPONSE_ID)?,
+            status: shift(STATUS_ID)?,
+        })
+    }
+
+    /// Returns true for the frames broadcast by the radar, the frame header,
+    /// the targets and the sensor status.
+    pub fn is_radar_frame(&self, id: u32) -> bool {
+        (self.base..=self.base + 256).contains(&id) || id == self.status
+    }
+
+    /// Use the `instruction` identifier for requests instead of the shifted
+    /// default.
+    pub fn with_instruction(self, instruction: u32) -> Self {
+        CanIds {
+            instruction,
+            ..self
+        }
+    }
+}
+
+impl Default for CanIds {
+    fn default() -> Self {
+        CanIds {
+            base: TARGET_ID_BASE,
+            instruction: INSTRUCTION_ID,
+            response: RESPONSE_ID,
+            status: STATUS_ID,
+        }
+    }
+}
+
+/// Default directory of the CAN interface lock files.
+pub const CAN_LOCK_DIR: &str = "/run/radarpub";
+
+/// Advisory lock of a CAN interface, held by radarpub while it streams from
+/// the interface so drvegrdctl does not send instructions whose responses
+/// radarpub would read.  The lock is released when dropped or when the
+/// process exits.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct CanLock {
+    _file: File,
+}
+
+#[allow(dead_code)]
+impl CanLock {
+    /// Path of the lock file of the interface.
+    pub fn path(dir: &Path, iface: &str) -> PathBuf {
+        dir.join(format!("{}.lock", iface))
+    }
+
+    /// Lock the interface, creating the lock directory if needed.
+    ///
+    /// # Errors
+    /// Returns [`Error::InterfaceLocked`] if another process holds the lock,
+    /// otherwise Error if the lock file cannot be written
+    pub fn acquire(dir: &Path, iface: &str) -> Result<Self, Error> {
+        fs::create_dir_all(dir)?;
+        let path = CanLock::path(dir, iface);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let err = io::Error::last_os_error();
+            return match err.kind() {
+                io::ErrorKind::WouldBlock => Err(Error::InterfaceLocked(
+                    iface.to_string(),
+                    CanLock::holder(dir, iface),
+                )),
+                _ => Err(err.into()),
+            };
+        }
+
+        file.set_len(0)?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(CanLock { _file: file })
+    }
+
+    /// Returns true if a process holds the lock of the interface.
+    pub fn is_locked(dir: &Path, iface: &str) -> io::Result<bool> {
+        let file = match File::open(CanLock::path(dir, iface)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+        // The shared lock is released when the file is closed.
+        match unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_SH | libc::LOCK_NB) } {
+            0 => Ok(false),
+            _ => {
+                let err = io::Error::last_os_error();
+                match err.kind() {
+                    io::ErrorKind::WouldBlock => Ok(true),
+                    _ => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Returns the process id written to the lock file of the interface.
+    pub fn holder(dir: &Path, iface: &str) -> Option<u32> {
+        fs::read_to_string(CanLock::path(dir, iface))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
+/// Limits of the wait for a UATv4 instruction response, a busy radar on a
+/// loaded bus may need more retries or a longer timeout than the defaults,
+/// and the identifiers of the exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvResponseSettings {
+    /// Frames other than responses, such as buffered targets, read before
+    /// giving up on the response
+    pub max_retries: usize,
+    /// Time to wait for each frame of the response
+    pub read_timeout: Duration,
+    /// Identifiers of the requests and responses
+    pub ids: CanIds,
+}
+
+impl Default for RecvResponseSettings {
+    fn default() -> Self {
+        RecvResponseSettings {
+            max_retries: 100,
+            read_timeout: Duration::from_secs(1),
+            ids: CanIds::default(),
+        }
+    }
+}
+
+/// One half of a CAN bus split by [`split_responses`].  Reads return the
+/// packets routed to this half while writes go directly to the shared bus.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct CanChannel<C> {
+    can: Arc<C>,
+    rx: kanal::AsyncReceiver<Packet>,
+}
+
+impl<C: CanReader + CanWriter> CanReader for CanChannel<C> {
+    async fn read_frame(&self) -> Result<Packet, Error> {
+        self.rx.recv().await.map_err(|_| Error::NoSocket)
+    }
+
+    fn discard_pending(&self) -> usize {
+        let mut discarded = 0;
+        while let Ok(Some(packet)) = self.rx.try_recv() {
+            debug!("discarding stale packet {:?}", packet);
+            discarded += 1;
+        }
+        discarded
+    }
+}
+
+impl<C: CanReader + CanWriter> CanWriter for CanChannel<C> {
+    async fn write_frame(&self, packet: Packet) -> Result<(), Error> {
+        self.can.write_frame(packet).await
+    }
+}
+
+/// Delay before the split reader retries after `errors` consecutive read
+/// errors, doubling from 20 ms up to one second so a failed bus does not spin.
+fn read_error_backoff(errors: u32) -> Duration {
+    Duration::from_millis((10u64 << errors.min(7)).min(1000))
+}
+
+/// Split the CAN bus so instruction responses can be awaited while another
+/// task streams radar frames from the same bus.
+///
+/// A background task reads every packet and routes instruction responses,
+/// identified by `response_id`, to the second channel and all other packets
+/// to the first.  Responses which nobody is waiting for are dropped once the
+/// response queue is full so they cannot stall the frame stream, those still
+/// queued are discarded before the next instruction.  Read errors are logged
+/// and retried with a backoff, the task only exits once the frames channel is
+/// dropped.  Must be called within a tokio runtime.
+///
+/// # Returns
+/// The (frames, responses) channels
+#[allow(dead_code)]
+pub fn split_responses<C>(can: C, response_id: u32) -> (CanChannel<C>, CanChannel<C>)
+where
+    C: CanReader + CanWriter + 'static,
+{
+    let can = Arc::new(can);
+    let (frames_tx, frames_rx) = kanal::bounded_async(1024);
+    let (responses_tx, responses_rx) = kanal::bounded_async(64);
+
+    let reader = can.clone();
+    tokio::spawn(async move {
+        let mut errors = 0u32;
+        loop {
+            let packet = match reader.read_frame().await {
+                Ok(packet) => {
+                    errors = 0;
+                    packet
+                }
+                Err(err) => {
+                    if frames_tx.is_disconnected() {
+                        break;
+                    }
+                    errors = errors.saturating_add(1);
+                    warn!("can split read error ({} in a row): {}", errors, err);
+                    tokio::time::sleep(read_error_backoff(errors)).await;
+                    continue;
+                }
+            };
+
+            if packet.id == response_id {
+                if let Ok(false) = responses_tx.try_send(packet) {
+                    debug!("dropping unexpected response {:?}", packet);
+                }
+            } else if frames_tx.send(packet).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    (
+        CanChannel {
+            can: can.clone(),
+            rx: frames_rx,
+        },
+        CanChannel {
+            can,
+            rx: responses_rx,
+        },
+    )
+}
+
+/// Split the sensor status broadcasts, identified by `status_id`, from the
+/// CAN bus.  Like [`split_responses`] a background task reads every packet,
+/// the status frames are decoded and sent to the receiver and all other
+/// packets to the returned channel.  Status updates are dropped while the
+/// receiver lags behind.  Read errors are logged and retried with a backoff,
+/// the task only exits once the frames channel is dropped.  Must be called
+/// within a tokio runtime.
+///
+/// # Returns
+/// The frames channel and the status receiver
+#[allow(dead_code)]
+pub fn split_status<C>(
+    can: C,
+    status_id: u32,
+) -> (CanChannel<C>, kanal::AsyncReceiver<SensorStatus>)
+where
+    C: CanReader + CanWriter + 'static,
+{
+    let can = Arc::new(can);
+    let (frames_tx, frames_rx) = kanal::bounded_async(1024);
+    let (status_tx, status_rx) = kanal::bounded_async(16);
+
+    let reader = can.clone();
+    tokio::spawn(async move {
+        let mut errors = 0u32;
+        loop {
+            let packet = match reader.read_frame().await {
+                Ok(packet) => {
+                    errors = 0;
+                    packet
+                }
+                Err(err) => {
+                    if frames_tx.is_disconnected() {
+                        break;
+                    }
+                    errors = errors.saturating_add(1);
+                    warn!("can status read error ({} in a row): {}", errors, err);
+                    tokio::time::sleep(read_error_backoff(errors)).await;
+                    continue;
+                }
+            };
+
+            if packet.id == status_id {
+                if let Ok(false) = status_tx.try_send(read_status_frame(packet.data)) {
+                    trace!("dropping sensor status {:?}", packet);
+                }
+            } else if frames_tx.send(packet).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    (CanChannel { can, rx: frames_rx }, status_rx)
+}
+
+/// Sensor health broadcast periodically by the radar.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorStatus {
+    /// Sensor temperature in °C
+    pub temperature: f32,
+    /// Supply voltage in volts
+    pub supply_voltage: f32,
+    /// Radome blockage detected
+    pub blocked: bool,
+}
+
+/// Decode the data of a status frame, see the `STATUS_*` bit layout.
+#[allow(dead_code)]
+pub fn read_status_frame(data: u64) -> SensorStatus {
+    let temperature = ((data >> STATUS_TEMPERATURE_SHIFT) & 0xFF) as f32;
+    let supply_voltage = ((data >> STATUS_VOLTAGE_SHIFT) & 0xFFF) as f32;
+    SensorStatus {
+        temperature: temperature + STATUS_TEMPERATURE_OFFSET,
+        supply_voltage: supply_voltage * STATUS_VOLTAGE_SCALE,
+        blocked: (data >> STATUS_BLOCKAGE_BIT) & 1 == 1,
+    }
+}
+
+/// Raises an alarm once the blockage bit of the sensor status has been set
+/// for the hold time, a brief blockage such as a splash of water is
+/// ignored.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BlockageAlarm {
+    hold: Duration,
+    since: Option<Instant>,
+    raised: bool,
+}
+
+#[allow(dead_code)]
+impl BlockageAlarm {
+    /// Create an alarm raised after `hold` of continuous blockage.
+    pub fn new(hold: Duration) -> Self {
+        BlockageAlarm {
+            hold,
+            since: None,
+            raised: false,
+        }
+    }
+
+    /// Update the alarm with a status received at `now`.
+    ///
+    /// # Returns
+    /// True while the alarm is raised
+    pub fn update(&mut self, status: &SensorStatus, now: Instant) -> bool {
+        match status.blocked {
+            true => {
+                let since = *self.since.get_or_insert(now);
+                if !self.raised && now.duration_since(since) >= self.hold {
+                    warn!(
+                        "radar blocked for {:?}, check the radome for obstructions",
+                        now.duration_since(since)
+                    );
+                    self.raised = true;
+                }
+            }
+            false => {
+                if self.raised {
+                    info!("radar blockage cleared");
+                }
+                self.since = None;
+                self.raised = false;
+            }
+        }
+        self.raised
+    }
+}
+
 /// Complete radar frame containing header and target list.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Frame {
@@ -85,6 +950,20 @@ impl fmt::Display for Frame {
     }
 }
 
+/// Targets of a radar frame with the frame metadata, as passed from the CAN
+/// stream to the clustering.
+#[derive(Debug, Clone)]
+pub struct TargetFrame {
+    /// Frame header, `n_targets` counts the targets before ground removal
+    pub header: Header,
+    /// Time the frame was received from the CAN bus
+    pub received_at: Instant,
+    /// Host timestamp of the frame reception
+    pub stamp: Time,
+    /// Targets of the frame
+    pub targets: Vec<Target>,
+}
+
 /// Radar frame header with timing and configuration data.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Header {
@@ -123,6 +1002,26 @@ pub struct Target {
     pub power: f64,
     /// Noise level in dBm
     pub noise: f64,
+    /// Range accuracy indicator from 0 (high) to 3 (low), always 0 on
+    /// firmware without [`DeviceInfo::reports_accuracy`]
+    pub accuracy: u8,
+}
+
+/// Compute a confidence score in the range [0, 1] for a radar target.
+///
+/// The score is derived from the signal-to-noise ratio (`power - noise` in dB)
+/// using a logistic function centered on the empirical noise floor such that
+/// an SNR of 20 dB maps to a confidence of 0.5.
+///
+/// # Arguments
+/// * `t` - Radar target
+///
+/// # Returns
+/// Confidence score clipped to [0, 1]
+pub fn target_confidence(t: &Target) -> f32 {
+    let snr = (t.power - t.noise) as f32;
+    let confidence = 1.0 / (1.0 + (-(snr - 20.0) / 5.0).exp());
+    confidence.clamp(0.0, 1.0)
 }
 
 #[allow(unused)]
@@ -547,90 +1446,220 @@ fn message_crc(
     crc.get()
 }
 
-/// Sends a prepared instruction request to the SmartMicro using the UATv4
-/// protocol.
-// Send instruction message to sensor (write command/parameter).
-// Used by drvegrdctl for sensor configuration.
+/// Build the three frames of an instruction request, with the CRC of the
+/// header computed over the messages.
 // See: DRVEGRD Communication Protocol Specification v4.2, Section 5.1
 #[allow(dead_code)]
-async fn send_instruction(
-    sock: &CanSocket,
-    header: InstructionHeader,
-    message1: InstructionMessage1,
-    message2: InstructionMessage2,
-) -> Result<(), Error> {
-    let mut header = header; // mutable copy of the header for crc updates
-    header.crc = message_crc(&header, &message1, &message2);
+fn instruction(
+    id: u32,
+    uat_id: u16,
+    message_type: MessageType,
+    parnum: u16,
+    value: u32,
+) -> [Packet; 3] {
+    let mut header = InstructionHeader {
+        crc: 0,
+        instructions: 1,
+        device_id: 0,
+        protocol_version: 4,
+        message_index: 0,
+        uat_id,
+    };
 
-    let id = StandardId::new(0x3FB).unwrap();
-    let header_frame = CanFrame::new(id, &<[u8; 8]>::from(&header)).unwrap();
-    let message1_frame = CanFrame::new(id, &<[u8; 8]>::from(&message1)).unwrap();
-    let message2_frame = CanFrame::new(id, &<[u8; 8]>::from(&message2)).unwrap();
+    let message1 = InstructionMessage1 {
+        dim0: 0,
+        dim1: 0,
+        parnum,
+        message_type: message_type as u8,
+        message_index: 1,
+        uat_id,
+    };
 
-    sock.write_frame(header_frame).await?;
-    sock.write_frame(message1_frame).await?;
-    sock.write_frame(message2_frame).await?;
+    let message2 = InstructionMessage2 {
+        value,
+        format: 0,
+        message_index: 2,
+        uat_id,
+    };
 
-    Ok(())
+    header.crc = message_crc(&header, &message1, &message2);
+
+    [
+        Packet {
+            id,
+            data: u64::from_le_bytes(<[u8; 8]>::from(&header)),
+        },
+        Packet {
+            id,
+            data: u64::from_le_bytes(<[u8; 8]>::from(&message1)),
+        },
+        Packet {
+            id,
+            data: u64::from_le_bytes(<[u8; 8]>::from(&message2)),
+        },
+    ]
 }
 
-/// Receives an instruction response from the SmartMicro using the UATv4
+/// Sends a prepared instruction request to the SmartMicro using the UATv4
 /// protocol.
-// Receive and parse response message from sensor.
-// Used by drvegrdctl for reading sensor state and diagnostics.
+// Send instruction message to sensor (write command/parameter).
+// Used by drvegrdctl for sensor configuration.
 #[allow(dead_code)]
-async fn recv_response(sock: &CanSocket) -> Result<u32, Error> {
-    let mut header = Packet { id: 0, data: 0 };
-
-    // Retry loop in case we receive a buffered target frame before the response.
-    for _ in 0..100 {
-        header = read_frame(sock).await?;
-        if header.id == 0x700 {
-            break;
-        }
+async fn send_instruction(
+    sock: &(impl CanReader + CanWriter),
+    packets: [Packet; 3],
+) -> Result<(), Error> {
+    let stale = sock.discard_pending();
+    if stale > 0 {
+        warn!("discarded {} stale response frames", stale);
     }
-
-    if header.id != 0x700 {
-        return Err(Error::InvalidResponseId(header.id as u16));
+    for packet in packets {
+        sock.write_frame(packet).await?;
     }
+    Ok(())
+}
 
-    let header = ResponseHeader::from(header.data);
-    trace!("{:?}", header);
+/// Instruction response assembled from its frames, the protocol checks
+/// shared by [`recv_response`] and [`recv_response_blocking`].
+#[allow(dead_code)]
+struct ResponseAssembler<'a> {
+    settings: &'a RecvResponseSettings,
+    retries: usize,
+    header: Option<ResponseHeader>,
+    messages: Vec<u64>,
+}
 
-    if header.protocol_version == 2 {
-        return Err(Error::UATCRCError);
-    } else if header.protocol_version != 5 {
-        return Err(Error::UATProtocolUnsupported(header.protocol_version));
+#[allow(dead_code)]
+impl<'a> ResponseAssembler<'a> {
+    fn new(settings: &'a RecvResponseSettings) -> Self {
+        ResponseAssembler {
+            settings,
+            retries: 0,
+            header: None,
+            messages: Vec::with_capacity(3),
+        }
     }
 
-    let message1 = read_frame(sock).await?;
-    if message1.id != 0x700 {
-        return Err(Error::InvalidResponseId(message1.id as u16));
-    }
-    let message1 = ResponseMessage1::from(message1.data);
-    trace!("{:?}", message1);
+    /// Add the next frame read from the bus.
+    ///
+    /// # Returns
+    /// The response value once the last frame of the response is added
+    fn push(&mut self, frame: Packet) -> Result<Option<u32>, Error> {
+        let ids = &self.settings.ids;
+        if self.header.is_none() {
+            // Retry in case we receive a buffered target frame before the
+            // response.  Only the radar broadcasts are expected on the bus,
+            // an instruction is another client talking to the sensor whose
+            // responses would be mixed up with ours.
+            if frame.id == ids.instruction {
+                return Err(Error::ConcurrentInstruction);
+            } else if frame.id != ids.response {
+                if !ids.is_radar_frame(frame.id) {
+                    return Err(Error::InvalidResponseId(frame.id));
+                }
+                self.retries += 1;
+                if self.retries >= self.settings.max_retries {
+                    return Err(Error::InvalidResponseId(frame.id));
+                }
+                return Ok(None);
+            }
+
+            let header = ResponseHeader::from(frame.data);
+            trace!("{:?}", header);
+            if header.protocol_version == 2 {
+                return Err(Error::UATCRCError);
+            } else if header.protocol_version != 5 {
+                return Err(Error::UATProtocolUnsupported(header.protocol_version));
+            }
+            self.header = Some(header);
+            return Ok(None);
+        }
+
+        if frame.id != ids.response {
+            return Err(Error::InvalidResponseId(frame.id));
+        }
+        self.messages.push(frame.data);
+        if self.messages.len() < 3 {
+            return Ok(None);
+        }
+
+        let message1 = ResponseMessage1::from(self.messages[0]);
+        trace!("{:?}", message1);
+        let message2 = ResponseMessage2::from(self.messages[1]);
+        trace!("{:?}", message2);
+        let message3 = ResponseMessage3::from(self.messages[2]);
+        trace!("{:?}", message3);
+
+        if message2.result != 0 {
+            let code = UatErrorCode::from(message2.result as u16);
+            count_uat_error(code);
+            return Err(Error::UATError(code));
+        }
 
-    let message2 = read_frame(sock).await?;
-    if message2.id != 0x700 {
-        return Err(Error::InvalidResponseId(message2.id as u16));
+        debug!("response 1: {:?} 2: {:?}", message1, message2);
+
+        Ok(Some(message2.value))
     }
-    let message2 = ResponseMessage2::from(message2.data);
-    trace!("{:?}", message2);
+}
 
-    let message3 = read_frame(sock).await?;
-    if message3.id != 0x700 {
-        return Err(Error::InvalidResponseId(message3.id as u16));
+/// Receives an instruction response from the SmartMicro using the UATv4
+/// protocol.
+// Receive and parse response message from sensor.
+// Used by drvegrdctl for reading sensor state and diagnostics.
+#[allow(dead_code)]
+async fn recv_response(
+    sock: &impl CanReader,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
+    let mut response = ResponseAssembler::new(settings);
+    loop {
+        if let Some(value) = response.push(read_response_frame(sock, settings).await?)? {
+            return Ok(value);
+        }
     }
-    let message3 = ResponseMessage3::from(message3.data);
-    trace!("{:?}", message3);
+}
 
-    if message2.result != 0 {
-        return Err(Error::UATError(message2.result as u16));
+/// Read the next frame of an instruction response within the read timeout.
+async fn read_response_frame(
+    sock: &impl CanReader,
+    settings: &RecvResponseSettings,
+) -> Result<Packet, Error> {
+    match tokio::time::timeout(settings.read_timeout, read_frame(sock)).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::ResponseTimeout(settings.read_timeout)),
     }
+}
 
-    debug!("response 1: {:?} 2: {:?}", message1, message2);
+/// Blocking [`send_instruction`].
+#[allow(dead_code)]
+fn send_instruction_blocking(
+    sock: &impl BlockingCanWriter,
+    packets: [Packet; 3],
+) -> Result<(), Error> {
+    for packet in packets {
+        sock.write_frame_blocking(packet)?;
+    }
+    Ok(())
+}
 
-    Ok(message2.value)
+/// Blocking [`recv_response`], each frame is read within the read timeout.
+#[allow(dead_code)]
+fn recv_response_blocking(
+    sock: &impl BlockingCanReader,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
+    let mut response = ResponseAssembler::new(settings);
+    loop {
+        let frame = match sock.read_frame_blocking(Some(settings.read_timeout)) {
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::TimedOut => {
+                return Err(Error::ResponseTimeout(settings.read_timeout))
+            }
+            result => result?,
+        };
+        if let Some(value) = response.push(frame)? {
+            return Ok(value);
+        }
+    }
 }
 
 /// Send command to sensor and await response.
@@ -639,6 +1668,7 @@ async fn recv_response(sock: &CanSocket) -> Result<u32, Error> {
 /// * `sock` - Active CAN socket connection
 /// * `command` - Command to execute
 /// * `value` - Command parameter value
+/// * `settings` - Retries and timeout of the response
 ///
 /// # Returns
 /// Response value from sensor
@@ -649,36 +1679,23 @@ async fn recv_response(sock: &CanSocket) -> Result<u32, Error> {
 /// Public API for drvegrdctl binary.
 /// See: DRVEGRD Communication Protocol Specification v4.2, Section 5.1
 #[allow(dead_code)]
-pub async fn send_command(sock: &CanSocket, command: Command, value: u32) -> Result<u32, Error> {
+pub async fn send_command(
+    sock: &(impl CanReader + CanWriter),
+    command: Command,
+    value: u32,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
     debug!("send_command {:?} {}", command, value);
 
-    let header = InstructionHeader {
-        crc: 0,
-        instructions: 1,
-        device_id: 0,
-        protocol_version: 4,
-        message_index: 0,
-        uat_id: 1000,
-    };
-
-    let message1 = InstructionMessage1 {
-        dim0: 0,
-        dim1: 0,
-        parnum: command as u16,
-        message_type: MessageType::Command as u8,
-        message_index: 1,
-        uat_id: 1000,
-    };
-
-    let message2 = InstructionMessage2 {
+    let packets = instruction(
+        settings.ids.instruction,
+        1000,
+        MessageType::Command,
+        command as u16,
         value,
-        format: 0,
-        message_index: 2,
-        uat_id: 1000,
-    };
-
-    send_instruction(sock, header, message1, message2).await?;
-    recv_response(sock).await
+    );
+    send_instruction(sock, packets).await?;
+    recv_response(sock, settings).await
 }
 
 /// Write parameter value to sensor.
@@ -687,6 +1704,7 @@ pub async fn send_command(sock: &CanSocket, command: Command, value: u32) -> Res
 /// * `sock` - Active CAN socket connection
 /// * `param` - Parameter to write
 /// * `value` - New parameter value
+/// * `settings` - Retries and timeout of the response
 ///
 /// # Returns
 /// Confirmation value from sensor
@@ -697,36 +1715,23 @@ pub async fn send_command(sock: &CanSocket, command: Command, value: u32) -> Res
 /// Public API for drvegrdctl binary.
 /// See: DRVEGRD Communication Protocol Specification v4.2, Section 4.1
 #[allow(dead_code)]
-pub async fn write_parameter(sock: &CanSocket, param: Parameter, value: u32) -> Result<u32, Error> {
+pub async fn write_parameter(
+    sock: &(impl CanReader + CanWriter),
+    param: Parameter,
+    value: u32,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
     debug!("write_parameter {:?} {}", param, value);
 
-    let header = InstructionHeader {
-        crc: 0,
-        instructions: 1,
-        device_id: 0,
-        protocol_version: 4,
-        message_index: 0,
-        uat_id: 2010,
-    };
-
-    let message1 = InstructionMessage1 {
-        dim0: 0,
-        dim1: 0,
-        parnum: param as u16,
-        message_type: MessageType::ParameterWrite as u8,
-        message_index: 1,
-        uat_id: 2010,
-    };
-
-    let message2 = InstructionMessage2 {
+    let packets = instruction(
+        settings.ids.instruction,
+        2010,
+        MessageType::ParameterWrite,
+        param as u16,
         value,
-        format: 0,
-        message_index: 2,
-        uat_id: 2010,
-    };
-
-    send_instruction(sock, header, message1, message2).await?;
-    recv_response(sock).await
+    );
+    send_instruction(sock, packets).await?;
+    recv_response(sock, settings).await
 }
 
 /// Read parameter value from sensor.
@@ -734,6 +1739,7 @@ pub async fn write_parameter(sock: &CanSocket, param: Parameter, value: u32) ->
 /// # Arguments
 /// * `sock` - Active CAN socket connection
 /// * `param` - Parameter to read
+/// * `settings` - Retries and timeout of the response
 ///
 /// # Returns
 /// Current parameter value
@@ -744,36 +1750,22 @@ pub async fn write_parameter(sock: &CanSocket, param: Parameter, value: u32) ->
 /// Public API for drvegrdctl binary.
 /// See: DRVEGRD Communication Protocol Specification v4.2, Section 4.1
 #[allow(dead_code)]
-pub async fn read_parameter(sock: &CanSocket, param: Parameter) -> Result<u32, Error> {
+pub async fn read_parameter(
+    sock: &(impl CanReader + CanWriter),
+    param: Parameter,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
     debug!("read_parameter {:?}", param);
 
-    let header = InstructionHeader {
-        crc: 0,
-        instructions: 1,
-        device_id: 0,
-        protocol_version: 4,
-        message_index: 0,
-        uat_id: 2010,
-    };
-
-    let message1 = InstructionMessage1 {
-        dim0: 0,
-        dim1: 0,
-        parnum: param as u16,
-        message_type: MessageType::ParameterRead as u8,
-        message_index: 1,
-        uat_id: 2010,
-    };
-
-    let message2 = InstructionMessage2 {
-        value: 0,
-        format: 0,
-        message_index: 2,
-        uat_id: 2010,
-    };
-
-    send_instruction(sock, header, message1, message2).await?;
-    recv_response(sock).await
+    let packets = instruction(
+        settings.ids.instruction,
+        2010,
+        MessageType::ParameterRead,
+        param as u16,
+        0,
+    );
+    send_instruction(sock, packets).await?;
+    recv_response(sock, settings).await
 }
 
 /// Read status field from sensor.
@@ -781,6 +1773,7 @@ pub async fn read_parameter(sock: &CanSocket, param: Parameter) -> Result<u32, E
 /// # Arguments
 /// * `sock` - Active CAN socket connection
 /// * `status` - Status field to read
+/// * `settings` - Retries and timeout of the response
 ///
 /// # Returns
 /// Current status value
@@ -791,36 +1784,280 @@ pub async fn read_parameter(sock: &CanSocket, param: Parameter) -> Result<u32, E
 /// Public API for drvegrdctl binary.
 /// See: DRVEGRD Communication Protocol Specification v4.2, Section 5.2
 #[allow(dead_code)]
-pub async fn read_status(sock: &CanSocket, status: Status) -> Result<u32, Error> {
+pub async fn read_status(
+    sock: &(impl CanReader + CanWriter),
+    status: Status,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
     debug!("read_status");
 
-    let header = InstructionHeader {
-        crc: 0,
-        instructions: 1,
-        device_id: 0,
-        protocol_version: 4,
-        message_index: 0,
-        uat_id: 2012,
-    };
+    let packets = instruction(
+        settings.ids.instruction,
+        2012,
+        MessageType::StatusRequest,
+        status as u16,
+        0,
+    );
+    send_instruction(sock, packets).await?;
+    recv_response(sock, settings).await
+}
 
-    let message1 = InstructionMessage1 {
-        dim0: 0,
-        dim1: 0,
-        parnum: status as u16,
-        message_type: MessageType::StatusRequest as u8,
-        message_index: 1,
-        uat_id: 2012,
-    };
+/// Blocking [`send_command`] for tools without an async runtime.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+#[allow(dead_code)]
+pub fn send_command_blocking(
+    sock: &(impl BlockingCanReader + BlockingCanWriter),
+    command: Command,
+    value: u32,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
+    debug!("send_command {:?} {}", command, value);
+    let packets = instruction(
+        settings.ids.instruction,
+        1000,
+        MessageType::Command,
+        command as u16,
+        value,
+    );
+    send_instruction_blocking(sock, packets)?;
+    recv_response_blocking(sock, settings)
+}
 
-    let message2 = InstructionMessage2 {
-        value: 0,
-        format: 0,
-        message_index: 2,
-        uat_id: 2012,
-    };
+/// Blocking [`write_parameter`] for tools without an async runtime.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+#[allow(dead_code)]
+pub fn write_parameter_blocking(
+    sock: &(impl BlockingCanReader + BlockingCanWriter),
+    param: Parameter,
+    value: u32,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
+    debug!("write_parameter {:?} {}", param, value);
+    let packets = instruction(
+        settings.ids.instruction,
+        2010,
+        MessageType::ParameterWrite,
+        param as u16,
+        value,
+    );
+    send_instruction_blocking(sock, packets)?;
+    recv_response_blocking(sock, settings)
+}
+
+/// Blocking [`read_parameter`] for tools without an async runtime.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+#[allow(dead_code)]
+pub fn read_parameter_blocking(
+    sock: &(impl BlockingCanReader + BlockingCanWriter),
+    param: Parameter,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
+    debug!("read_parameter {:?}", param);
+    let packets = instruction(
+        settings.ids.instruction,
+        2010,
+        MessageType::ParameterRead,
+        param as u16,
+        0,
+    );
+    send_instruction_blocking(sock, packets)?;
+    recv_response_blocking(sock, settings)
+}
+
+/// Blocking [`read_status`] for tools without an async runtime.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+#[allow(dead_code)]
+pub fn read_status_blocking(
+    sock: &(impl BlockingCanReader + BlockingCanWriter),
+    status: Status,
+    settings: &RecvResponseSettings,
+) -> Result<u32, Error> {
+    debug!("read_status");
+    let packets = instruction(
+        settings.ids.instruction,
+        2012,
+        MessageType::StatusRequest,
+        status as u16,
+        0,
+    );
+    send_instruction_blocking(sock, packets)?;
+    recv_response_blocking(sock, settings)
+}
+
+/// First firmware version populating the target accuracy bits of data_1 as
+/// `[major, minor, patch]`.
+pub const ACCURACY_FIRMWARE_VERSION: [u32; 3] = [2, 4, 0];
+
+/// Sensor identification read from the status fields.
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Software generation number
+    pub software_generation: u32,
+    /// Major version number
+    pub major_version: u32,
+    /// Minor version number
+    pub minor_version: u32,
+    /// Patch version number
+    pub patch_version: u32,
+    /// Sensor serial number
+    pub serial_number: u32,
+}
+
+#[allow(dead_code)]
+impl DeviceInfo {
+    /// Returns the firmware version as `major.minor.patch`.
+    pub fn version(&self) -> String {
+        format!(
+            "{}.{}.{}",
+            self.major_version, self.minor_version, self.patch_version
+        )
+    }
+
+    /// Returns true if the firmware reports the target accuracy, older
+    /// firmware leaves those bits of data_1 undefined.
+    pub fn reports_accuracy(&self) -> bool {
+        [self.major_version, self.minor_version, self.patch_version] >= ACCURACY_FIRMWARE_VERSION
+    }
+}
+
+/// Read the sensor identification status fields.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+#[allow(dead_code)]
+pub async fn read_device_info(
+    sock: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+) -> Result<DeviceInfo, Error> {
+    Ok(DeviceInfo {
+        software_generation: read_status(sock, Status::SoftwareGeneration, settings).await?,
+        major_version: read_status(sock, Status::MajorVersion, settings).await?,
+        minor_version: read_status(sock, Status::MinorVersion, settings).await?,
+        patch_version: read_status(sock, Status::PatchVersion, settings).await?,
+        serial_number: read_status(sock, Status::SerialNumber, settings).await?,
+    })
+}
+
+/// Radar parameter values configured at startup, either as requested or as
+/// confirmed by the sensor.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RadarParameters {
+    /// Center frequency setting
+    pub center_frequency: u32,
+    /// Frequency sweep setting
+    pub frequency_sweep: u32,
+    /// Range toggle mode
+    pub range_toggle: u32,
+    /// Detection sensitivity setting
+    pub detection_sensitivity: u32,
+}
+
+#[allow(dead_code)]
+impl RadarParameters {
+    /// Update the value of a parameter, parameters which are not part of
+    /// the startup configuration are ignored.
+    pub fn set(&mut self, param: Parameter, value: u32) {
+        match param {
+            Parameter::CenterFrequency => self.center_frequency = value,
+            Parameter::FrequencySweep => self.frequency_sweep = value,
+            Parameter::RangeToggle => self.range_toggle = value,
+            Parameter::DetectionSensitivity => self.detection_sensitivity = value,
+            Parameter::TxAntenna | Parameter::EnableTargetList => (),
+        }
+    }
+}
 
-    send_instruction(sock, header, message1, message2).await?;
-    recv_response(sock).await
+/// Write every radar parameter and collect the values confirmed by the
+/// sensor.  The sensor may adjust a requested value, for example clamping it
+/// to the supported range, in which case the confirmed value differs from the
+/// requested one.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+#[allow(dead_code)]
+pub async fn write_parameters(
+    sock: &(impl CanReader + CanWriter),
+    requested: &RadarParameters,
+    settings: &RecvResponseSettings,
+) -> Result<RadarParameters, Error> {
+    Ok(RadarParameters {
+        center_frequency: write_parameter(
+            sock,
+            Parameter::CenterFrequency,
+            requested.center_frequency,
+            settings,
+        )
+        .await?,
+        frequency_sweep: write_parameter(
+            sock,
+            Parameter::FrequencySweep,
+            requested.frequency_sweep,
+            settings,
+        )
+        .await?,
+        range_toggle: write_parameter(
+            sock,
+            Parameter::RangeToggle,
+            requested.range_toggle,
+            settings,
+        )
+        .await?,
+        detection_sensitivity: write_parameter(
+            sock,
+            Parameter::DetectionSensitivity,
+            requested.detection_sensitivity,
+            settings,
+        )
+        .await?,
+    })
+}
+
+/// Read every radar parameter without changing the sensor configuration, for
+/// sensors configured by another system.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+#[allow(dead_code)]
+pub async fn read_parameters(
+    sock: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+) -> Result<RadarParameters, Error> {
+    Ok(RadarParameters {
+        center_frequency: read_parameter(sock, Parameter::CenterFrequency, settings).await?,
+        frequency_sweep: read_parameter(sock, Parameter::FrequencySweep, settings).await?,
+        range_toggle: read_parameter(sock, Parameter::RangeToggle, settings).await?,
+        detection_sensitivity: read_parameter(sock, Parameter::DetectionSensitivity, settings)
+            .await?,
+    })
+}
+
+/// Save the current sensor parameters to non-volatile memory so they
+/// persist across power cycles.
+///
+/// # Errors
+/// Returns [`Error::SaveRejected`] if the sensor reports an error for the
+/// save command, some firmware versions rate limit writes to non-volatile
+/// memory.  Otherwise returns Error if CAN communication fails.
+#[allow(dead_code)]
+pub async fn save_parameters(
+    sock: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+) -> Result<(), Error> {
+    match send_command(sock, Command::SaveParameters, 0, settings).await {
+        Ok(_) => Ok(()),
+        Err(Error::UATError(result)) => Err(Error::SaveRejected(result)),
+        Err(err) => Err(err),
+    }
 }
 
 /// The read_message function is a state machine that reads a frame from the
@@ -828,52 +2065,94 @@ pub async fn read_status(sock: &CanSocket, status: Status) -> Result<u32, Error>
 /// been read.  The function will throw away any incomplete frames, returning
 /// the first full frame it encounters.
 ///
-/// The CAN reader is a function that returns a future that resolves to a Packet
-/// struct which represents a CAN ID and data. This allows the function to be
-/// used with a variety of CAN readers.
-///
-/// The reader function is called with a user argument which should be used
-/// to pass a state argument to the reader, such as a CAN socket.
-pub async fn read_message(sock: &CanSocket) -> Result<Frame, Error> {
-    // Read packets until we find the starting header packet
-    let pkt = loop {
-        let pkt = read_frame(sock).await?;
-        if (pkt.id == 0x400) && ((pkt.data >> 62) & 3) == 0 {
-            break pkt;
+/// The CAN reader is any [`CanReader`] which resolves to a Packet struct
+/// representing a CAN ID and data. This allows the function to be used with
+/// a SocketCAN [`CanSocket`] or a [`CanInjector`] for testing.  The header
+/// is expected on `ids.base` and the targets on the identifiers after it.
+pub async fn read_message(sock: &impl CanReader, ids: &CanIds) -> Result<Frame, Error> {
+    let mut frame = FrameAssembler::new(ids);
+    loop {
+        if let Some(frame) = frame.push(read_frame(sock).await?)? {
+            return Ok(frame);
         }
-    };
+    }
+}
 
-    let header = read_header_0(pkt.data, None)?;
-    let header = read_header_1(read_frame(sock).await?.data, Some(header))?;
-    let header = read_header_2(read_frame(sock).await?.data, Some(header))?;
+/// Blocking [`read_message`] for tools without an async runtime, waiting
+/// for the packets without a timeout.
+#[allow(dead_code)]
+pub fn read_message_blocking(sock: &impl BlockingCanReader, ids: &CanIds) -> Result<Frame, Error> {
+    let mut frame = FrameAssembler::new(ids);
+    loop {
+        if let Some(frame) = frame.push(sock.read_frame_blocking(None)?)? {
+            return Ok(frame);
+        }
+    }
+}
 
-    let mut targets = [Target::default(); 256];
+/// Radar frame assembled from its packets, the parsing shared by
+/// [`read_message`] and [`read_message_blocking`].
+struct FrameAssembler<'a> {
+    ids: &'a CanIds,
+    header: Option<Header>,
+    /// Packets of the frame added, from the first header packet
+    packets: u32,
+    targets: [Target; 256],
+}
 
-    for i in 0..header.n_targets as u32 {
-        let pkt = read_frame(sock).await?;
-        if 0x401 + i != pkt.id {
-            Err(Error::OutOfSequence(format!(
-                "expected target {} but got {}",
-                0x401 + i,
-                pkt.id
-            )))?;
-        }
-        let target = read_data_0(pkt.data, None);
-
-        let pkt = read_frame(sock).await?;
-        if 0x401 + i != pkt.id {
-            Err(Error::OutOfSequence(format!(
-                "expected target {} but got {}",
-                0x401 + i,
-                pkt.id
-            )))?;
+impl<'a> FrameAssembler<'a> {
+    fn new(ids: &'a CanIds) -> Self {
+        FrameAssembler {
+            ids,
+            header: None,
+            packets: 0,
+            targets: [Target::default(); 256],
         }
-        let target = read_data_1(pkt.data, Some(target));
-
-        targets[i as usize] = target;
     }
 
-    Ok(Frame { header, targets })
+    /// Add the next packet read from the bus.  The packets before the first
+    /// header packet are skipped.
+    ///
+    /// # Returns
+    /// The frame once the last target is added
+    fn push(&mut self, pkt: Packet) -> Result<Option<Frame>, Error> {
+        let header = match (self.header, self.packets) {
+            (None, _) => {
+                if pkt.id != self.ids.base || ((pkt.data >> 62) & 3) != 0 {
+                    return Ok(None);
+                }
+                read_header_0(pkt.data, None)?
+            }
+            (Some(header), 1) => read_header_1(pkt.data, Some(header))?,
+            (Some(header), 2) => read_header_2(pkt.data, Some(header))?,
+            (Some(header), n) => {
+                let i = (n - 3) / 2;
+                if self.ids.base + 1 + i != pkt.id {
+                    return Err(Error::OutOfSequence(format!(
+                        "expected target {} but got {}",
+                        self.ids.base + 1 + i,
+                        pkt.id
+                    )));
+                }
+                let target = &mut self.targets[i as usize];
+                *target = match (n - 3) % 2 {
+                    0 => read_data_0(pkt.data, None),
+                    _ => read_data_1(pkt.data, Some(*target)),
+                };
+                header
+            }
+        };
+        self.header = Some(header);
+        self.packets += 1;
+
+        match self.packets == 3 + 2 * header.n_targets as u32 {
+            true => Ok(Some(Frame {
+                header,
+                targets: self.targets,
+            })),
+            false => Ok(None),
+        }
+    }
 }
 
 /// Parse radar frame header from CAN data payload.
@@ -890,6 +2169,24 @@ pub async fn read_message(sock: &CanSocket) -> Result<Frame, Error> {
 ///
 /// Alternative header parsing function for testing/debugging.
 /// Kept for protocol documentation and future use.
+///
+/// # Examples
+///
+/// The first of the three header frames carries the cycle counter and the
+/// number of targets which follow the header.
+///
+/// ```
+/// use radarpub::can::{load_data, read_header};
+///
+/// let data = load_data(&[0x5b, 0x83, 0x82, 0x32, 0x3b, 0x80, 0x88, 0x0c]);
+/// let header = read_header(data, None).unwrap();
+/// assert_eq!(header.cycle_counter, 7759109);
+/// assert_eq!(header.n_targets, 17);
+/// assert_eq!(header.cycle_duration, 54.976);
+///
+/// // Header frame types 0 to 2 are valid, 3 is not.
+/// assert!(read_header(0xC000_0000_0000_0000, None).is_err());
+/// ```
 #[allow(dead_code)]
 pub fn read_header(data: u64, hdr: Option<Header>) -> Result<Header, Error> {
     match (data >> 62) & 3 {
@@ -1014,6 +2311,26 @@ fn read_header_2(data: u64, hdr: Option<Header>) -> Result<Header, Error> {
 ///
 /// Alternative target data parsing function for testing/debugging.
 /// Kept for protocol documentation and future use.
+///
+/// # Examples
+///
+/// A target is sent as two frames, the first with the range, azimuth and
+/// speed and the second with the elevation, RCS, power and noise.  Passing
+/// the partial target of the first frame completes it with the second.
+///
+/// ```
+/// use radarpub::can::{load_data, read_data};
+///
+/// let first = load_data(&[0x62, 0xC1, 0x40, 0x55, 0x03, 0xD8, 0x0D, 0x00]);
+/// let second = load_data(&[0x6D, 0x0A, 0x7D, 0x01, 0x60, 0xCB, 0x01, 0x00]);
+///
+/// let target = read_data(first, None);
+/// assert_eq!((target.range, target.azimuth), (7.08, -27.2));
+///
+/// let target = read_data(second, Some(target));
+/// assert_eq!((target.range, target.elevation), (7.08, 3.68));
+/// assert_eq!((target.rcs, target.power, target.noise), (-4.2, 133.0, 95.0));
+/// ```
 #[allow(dead_code)]
 pub fn read_data(data: u64, tgt: Option<Target>) -> Target {
     match data & 1 != 0 {
@@ -1036,6 +2353,7 @@ fn read_data_0(data: u64, tgt: Option<Target>) -> Target {
             rcs: tgt.rcs,
             power: tgt.power,
             noise: tgt.noise,
+            accuracy: tgt.accuracy,
         },
         None => Target {
             range: range as f64 * 0.04,
@@ -1045,6 +2363,7 @@ fn read_data_0(data: u64, tgt: Option<Target>) -> Target {
             rcs: 0.0,
             power: 0.0,
             noise: 0.0,
+            accuracy: 0,
         },
     }
 }
@@ -1053,6 +2372,8 @@ fn read_data_1(data: u64, tgt: Option<Target>) -> Target {
     let rcs = ((data >> 1) & 0xFF) as i32 - 75;
     let power = ((data >> 9) & 0xFF) as i32;
     let noise = ((data >> 17) & 0xFF) as i32;
+    // Bits 25-26 of the reserved range, unpopulated by older firmware.
+    let accuracy = ((data >> 25) & 0x3) as u8;
     let elevation = ((data >> 37) & 0x3FF) as i32 - 511;
 
     match tgt {
@@ -1064,6 +2385,7 @@ fn read_data_1(data: u64, tgt: Option<Target>) -> Target {
             rcs: rcs as f64 * 0.2,
             power: power as f64,
             noise: noise as f64 * 0.5,
+            accuracy,
         },
         None => Target {
             range: 0.0,
@@ -1073,40 +2395,33 @@ fn read_data_1(data: u64, tgt: Option<Target>) -> Target {
             rcs: rcs as f64 * 0.2,
             power: power as f64,
             noise: noise as f64 * 0.5,
+            accuracy,
         },
     }
 }
 
-fn load_data(data: &[u8]) -> u64 {
-    u64::from_le_bytes(data[0..8].try_into().unwrap())
+/// Load the data bytes of a radar CAN frame as the little endian u64 parsed
+/// by [`read_header`] and [`read_data`].  Frames with fewer than 8 data bytes
+/// are zero padded, bytes beyond the first 8 are ignored.
+pub fn load_data(data: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    let len = data.len().min(8);
+    bytes[..len].copy_from_slice(&data[..len]);
+    u64::from_le_bytes(bytes)
 }
 
 /// Read next CAN frame from socket.
 ///
 /// # Arguments
-/// * `can` - Active CAN socket
+/// * `can` - Active CAN reader
 ///
 /// # Returns
 /// Next Packet from CAN bus
 ///
 /// # Errors
 /// Returns Error if socket read fails
-pub async fn read_frame(can: &CanSocket) -> Result<Packet, Error> {
-    match can.read_frame().await {
-        Ok(CanFrame::Data(frame)) => {
-            let id = match frame.id() {
-                CanId::Standard(id) => id.as_raw() as u32,
-                CanId::Extended(id) => id.as_raw(),
-            };
-            Ok(Packet {
-                id,
-                data: load_data(frame.data()),
-            })
-        }
-        Ok(CanFrame::Remote(frame)) => panic!("Unexpected remote frame: {:?}", frame),
-        Ok(CanFrame::Error(frame)) => panic!("Unexpected error frame: {:?}", frame),
-        Err(err) => Err(Error::Io(err)),
-    }
+pub async fn read_frame(can: &impl CanReader) -> Result<Packet, Error> {
+    can.read_frame().await
 }
 
 #[cfg(test)]
@@ -1200,7 +2515,8 @@ mod tests {
                 speed: 0.0,
                 rcs: 0.0,
                 power: 0.0,
-                noise: 0.0
+                noise: 0.0,
+                accuracy: 0,
             }
         );
 
@@ -1218,10 +2534,737 @@ mod tests {
                 rcs: -4.2,
                 power: 133.0,
                 noise: 95.0,
+                accuracy: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_accuracy() {
+        let data1 = load_data(&[0x6D, 0x0A, 0x7D, 0x01, 0x60, 0xCB, 0x01, 0x00]);
+        let target = read_data(data1, None);
+        for accuracy in 0..4u8 {
+            let parsed = read_data(data1 | (accuracy as u64) << 25, None);
+            assert_eq!(parsed, Target { accuracy, ..target });
+        }
+
+        let device = |major, minor, patch| DeviceInfo {
+            major_version: major,
+            minor_version: minor,
+            patch_version: patch,
+            ..Default::default()
+        };
+        let [major, minor, patch] = ACCURACY_FIRMWARE_VERSION;
+        assert!(device(major, minor, patch).reports_accuracy());
+        assert!(device(major + 1, 0, 0).reports_accuracy());
+        assert!(!device(major, minor - 1, 99).reports_accuracy());
+        assert!(!DeviceInfo::default().reports_accuracy());
+    }
+
+    #[test]
+    fn test_target_confidence() {
+        let target = Target {
+            power: 60.0,
+            noise: 40.0,
+            ..Default::default()
+        };
+        assert!((target_confidence(&target) - 0.5).abs() < 1e-6);
+
+        let strong = Target {
+            power: 133.0,
+            noise: 47.5,
+            ..Default::default()
+        };
+        assert!(target_confidence(&strong) > 0.99);
+
+        let weak = Target {
+            power: 10.0,
+            noise: 47.5,
+            ..Default::default()
+        };
+        assert!(target_confidence(&weak) < 0.01);
+        assert!(target_confidence(&weak) >= 0.0);
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// Injector whose first `failures` reads return an error.
+    #[derive(Clone, Default)]
+    struct FailingCan {
+        can: CanInjector,
+        failures: Arc<Mutex<usize>>,
+    }
+
+    impl CanReader for FailingCan {
+        async fn read_frame(&self) -> Result<Packet, Error> {
+            {
+                let mut failures = self.failures.lock().unwrap();
+                if *failures > 0 {
+                    *failures -= 1;
+                    return Err(Error::Io(io::ErrorKind::Other.into()));
+                }
+            }
+            self.can.read_frame().await
+        }
+    }
+
+    impl CanWriter for FailingCan {
+        async fn write_frame(&self, packet: Packet) -> Result<(), Error> {
+            self.can.write_frame(packet).await
+        }
+    }
+
+    #[test]
+    fn test_injector_read_message() {
+        let can = CanInjector::new();
+        can.extend([
+            // Stray target packet from a previous frame which must be skipped.
+            Packet { id: 0x401, data: 0 },
+            // Header 0 with cycle_counter 42 and a single target.
+            Packet {
+                id: 0x400,
+                data: (1 << 47) | (42 << 15) | 100,
+            },
+            Packet {
+                id: 0x400,
+                data: 1 << 62,
+            },
+            Packet {
+                id: 0x400,
+                data: 2 << 62,
+            },
+            Packet {
+                id: 0x401,
+                data: load_data(&[0x62, 0xC1, 0x40, 0x55, 0x03, 0xD8, 0x0D, 0x00]),
+            },
+            Packet {
+                id: 0x401,
+                data: load_data(&[0x6D, 0x0A, 0x7D, 0x01, 0x60, 0xCB, 0x01, 0x00]),
+            },
+        ]);
+
+        let frame = block_on(read_message(&can, &CanIds::default())).unwrap();
+        assert_eq!(can.pending(), 0);
+        assert_eq!(frame.header.cycle_counter, 42);
+        assert_eq!(frame.header.n_targets, 1);
+        assert_eq!(
+            frame.targets[0],
+            Target {
+                range: 7.08,
+                azimuth: -27.2,
+                elevation: 3.68,
+                speed: 0.0,
+                rcs: -4.2,
+                power: 133.0,
+                noise: 95.0,
+                accuracy: 0,
             }
         );
     }
 
+    #[test]
+    fn test_injector_out_of_sequence() {
+        let can = CanInjector::new();
+        can.extend([
+            Packet {
+                id: 0x400,
+                data: 1 << 47,
+            },
+            Packet {
+                id: 0x400,
+                data: 1 << 62,
+            },
+            Packet {
+                id: 0x400,
+                data: 2 << 62,
+            },
+            Packet { id: 0x402, data: 0 },
+        ]);
+
+        assert!(matches!(
+            block_on(read_message(&can, &CanIds::default())),
+            Err(Error::OutOfSequence(_))
+        ));
+    }
+
+    #[test]
+    fn test_injector_write_parameter() {
+        let can = CanInjector::new();
+        can.extend([
+            // Response header with UAT protocol version 5.
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 5, 0, 0, 1, 0, 0]),
+            },
+            Packet { id: 0x700, data: 0 },
+            // Response message 2 with result 0 and value 2.
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 2, 0, 2, 0, 0, 0]),
+            },
+            Packet { id: 0x700, data: 0 },
+        ]);
+
+        let value = block_on(write_parameter(
+            &can,
+            Parameter::FrequencySweep,
+            2,
+            &RecvResponseSettings::default(),
+        ))
+        .unwrap();
+        assert_eq!(value, 2);
+
+        let sent = can.sent();
+        assert_eq!(sent.len(), 3);
+        assert!(sent.iter().all(|pkt| pkt.id == 0x3FB));
+        let header = sent[0].data.to_le_bytes();
+        assert_eq!(u16::from_le_bytes([header[0], header[1]]), 2010);
+    }
+
+    #[test]
+    fn test_can_id_base() {
+        assert_eq!(CanIds::new(TARGET_ID_BASE), Some(CanIds::default()));
+        // The instruction identifier would be negative, the last target or
+        // the response beyond 29 bits.
+        assert_eq!(CanIds::new(4), None);
+        assert_eq!(CanIds::new(MAX_EXTENDED_ID - 255), None);
+        assert_eq!(CanIds::new(MAX_EXTENDED_ID - 0x300 + 1), None);
+        assert_eq!(CanIds::new(u32::MAX), None);
+
+        let ids = CanIds::new(0x18FF_0000).unwrap();
+        assert_eq!(ids.instruction, 0x18FE_FFFB);
+        assert_eq!(ids.response, 0x18FF_0300);
+        assert_eq!(ids.with_instruction(0x18FF_1000).instruction, 0x18FF_1000);
+
+        // A frame on the shifted identifiers, the default ones are ignored.
+        let can = CanInjector::new();
+        can.extend([
+            Packet {
+                id: 0x400,
+                data: 1 << 47,
+            },
+            Packet {
+                id: ids.base,
+                data: (1 << 47) | (7 << 15),
+            },
+            Packet {
+                id: ids.base,
+                data: 1 << 62,
+            },
+            Packet {
+                id: ids.base,
+                data: 2 << 62,
+            },
+            Packet {
+                id: ids.base + 1,
+                data: 0,
+            },
+            Packet {
+                id: ids.base + 1,
+                data: 0,
+            },
+        ]);
+        let frame = block_on(read_message(&can, &ids)).unwrap();
+        assert_eq!(can.pending(), 0);
+        assert_eq!(frame.header.cycle_counter, 7);
+        assert_eq!(frame.header.n_targets, 1);
+
+        // Instructions go out on the shifted identifier and only the shifted
+        // responses are accepted.
+        let settings = RecvResponseSettings {
+            ids,
+            ..Default::default()
+        };
+        let can = CanInjector::new();
+        can.extend(response(1));
+        can.extend(response(42).map(|pkt| Packet {
+            id: ids.response,
+            ..pkt
+        }));
+        let value = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap();
+        assert_eq!(value, 42);
+        assert!(can.sent().iter().all(|pkt| pkt.id == ids.instruction));
+    }
+
+    /// Response to an instruction with result 0 and the given value.
+    fn response(value: u32) -> [Packet; 4] {
+        let value = value.to_le_bytes();
+        [
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 5, 0, 0, 1, 0, 0]),
+            },
+            Packet { id: 0x700, data: 0 },
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 2, 0, value[0], value[1], value[2], value[3]]),
+            },
+            Packet { id: 0x700, data: 0 },
+        ]
+    }
+
+    #[test]
+    fn test_write_parameters_clamped() {
+        let requested = RadarParameters {
+            center_frequency: 2,
+            frequency_sweep: 3,
+            range_toggle: 0,
+            detection_sensitivity: 9,
+        };
+
+        // The sensor rejects ultra-short range with the high center frequency
+        // and clamps the sensitivity.
+        let can = CanInjector::new();
+        for value in [2, 2, 0, 2] {
+            can.extend(response(value));
+        }
+
+        let confirmed = block_on(write_parameters(
+            &can,
+            &requested,
+            &RecvResponseSettings::default(),
+        ))
+        .unwrap();
+        assert_eq!(can.pending(), 0);
+        assert_eq!(can.sent().len(), 12);
+        assert_eq!(
+            confirmed,
+            RadarParameters {
+                center_frequency: 2,
+                frequency_sweep: 2,
+                range_toggle: 0,
+                detection_sensitivity: 2,
+            }
+        );
+        assert_ne!(confirmed, requested);
+    }
+
+    #[test]
+    fn test_split_responses_late_response() {
+        let can = CanInjector::new();
+        block_on(async {
+            let (_frames, responses) = split_responses(can.clone(), RESPONSE_ID);
+            let settings = RecvResponseSettings::default();
+
+            // A response which arrived after its instruction gave up must not
+            // be taken as the reply to the next instruction.
+            can.extend(response(1));
+            while can.pending() > 0 {
+                tokio::task::yield_now().await;
+            }
+            can.reply(response(2));
+            let value = read_parameter(&responses, Parameter::RangeToggle, &settings).await;
+            assert_eq!(value.unwrap(), 2);
+            assert_eq!(can.sent().len(), 3);
+        });
+        assert_eq!(read_error_backoff(1), Duration::from_millis(20));
+        assert_eq!(read_error_backoff(100), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_read_parameters() {
+        let can = CanInjector::new();
+        for value in [0, 1, 3, 2] {
+            can.extend(response(value));
+        }
+
+        let parameters = block_on(read_parameters(&can, &RecvResponseSettings::default())).unwrap();
+        assert_eq!(
+            parameters,
+            RadarParameters {
+                center_frequency: 0,
+                frequency_sweep: 1,
+                range_toggle: 3,
+                detection_sensitivity: 2,
+            }
+        );
+
+        // Every instruction is a parameter read.
+        let sent = can.sent();
+        assert_eq!(sent.len(), 12);
+        for message1 in sent.iter().skip(1).step_by(3) {
+            assert_eq!(
+                message1.data.to_le_bytes()[3],
+                MessageType::ParameterRead as u8
+            );
+        }
+    }
+
+    #[test]
+    fn test_save_parameters() {
+        let can = CanInjector::new();
+        can.extend(response(0));
+        block_on(save_parameters(&can, &RecvResponseSettings::default())).unwrap();
+
+        let sent = can.sent();
+        assert_eq!(sent.len(), 3);
+        let message1 = sent[1].data.to_le_bytes();
+        assert_eq!(u16::from_le_bytes([message1[0], message1[1]]), 1000);
+        assert_eq!(message1[3], MessageType::Command as u8);
+        assert_eq!(
+            u16::from_le_bytes([message1[4], message1[5]]),
+            Command::SaveParameters as u16
+        );
+
+        // A non-zero result in the response rejects the save.
+        let mut rejected = response(0);
+        rejected[2].data |= 7 << 24;
+        can.extend(rejected);
+        let err = block_on(save_parameters(&can, &RecvResponseSettings::default())).unwrap_err();
+        assert!(matches!(err, Error::SaveRejected(UatErrorCode::Unknown(7))));
+    }
+
+    #[test]
+    fn test_status_frame() {
+        // 75 °C, 12.00 V and blocked.
+        let blocked = parse_candump("(1700000000.000000) can0 600#7DB0140000000000").unwrap();
+        assert_eq!(blocked.id, STATUS_ID);
+        let status = read_status_frame(blocked.data);
+        assert_eq!(status.temperature, 75.0);
+        assert!((status.supply_voltage - 12.0).abs() < 1e-4);
+        assert!(status.blocked);
+
+        // -10 °C, 13.80 V and clear.
+        let clear = parse_candump("(1700000000.050000) can0 600#2864050000000000").unwrap();
+        let status = read_status_frame(clear.data);
+        assert_eq!(status.temperature, -10.0);
+        assert!((status.supply_voltage - 13.8).abs() < 1e-4);
+        assert!(!status.blocked);
+
+        // The status frames are split from the rest of the bus.
+        let can = CanInjector::new();
+        can.extend([blocked, Packet { id: 0x400, data: 0 }, clear]);
+        block_on(async {
+            let (frames, statuses) = split_status(can, CanIds::default().status);
+            assert_eq!(frames.read_frame().await.unwrap().id, 0x400);
+            assert!(statuses.recv().await.unwrap().blocked);
+            assert!(!statuses.recv().await.unwrap().blocked);
+        });
+        assert_eq!(CanIds::new(0x18FF_0000).unwrap().status, 0x18FF_0200);
+    }
+
+    #[test]
+    fn test_status_frame_short() {
+        // A status frame with a DLC of 3 is zero padded instead of panicking.
+        let id = StandardId::new(STATUS_ID as u16).unwrap();
+        let frame = CanFrame::new(id, &[0x7D, 0xB0, 0x14]).unwrap();
+        let packet = data_packet(frame);
+        assert_eq!(packet.id, STATUS_ID);
+        assert_eq!(packet.data, 0x14B07D);
+        let status = read_status_frame(packet.data);
+        assert_eq!(status.temperature, 75.0);
+        assert!(status.blocked);
+    }
+
+    #[test]
+    fn test_split_status_read_error() {
+        // The reader keeps going after bus errors instead of ending the stream.
+        let can = FailingCan::default();
+        *can.failures.lock().unwrap() = 3;
+        can.can.extend([
+            Packet { id: 0x400, data: 0 },
+            Packet {
+                id: STATUS_ID,
+                data: 0,
+            },
+            Packet { id: 0x401, data: 0 },
+        ]);
+        block_on(async {
+            let (frames, statuses) = split_status(can, STATUS_ID);
+            assert_eq!(frames.read_frame().await.unwrap().id, 0x400);
+            assert!(statuses.recv().await.is_ok());
+            assert_eq!(frames.read_frame().await.unwrap().id, 0x401);
+        });
+    }
+
+    #[test]
+    fn test_can_lock() {
+        let dir = std::env::temp_dir().join(format!("radarpub-canlock-{}", std::process::id()));
+        assert!(!CanLock::is_locked(&dir, "vcan0").unwrap());
+
+        let lock = CanLock::acquire(&dir, "vcan0").unwrap();
+        assert!(CanLock::is_locked(&dir, "vcan0").unwrap());
+        assert!(!CanLock::is_locked(&dir, "vcan1").unwrap());
+        assert_eq!(CanLock::holder(&dir, "vcan0"), Some(std::process::id()));
+
+        // A second radarpub on the interface is refused.
+        let err = CanLock::acquire(&dir, "vcan0").unwrap_err();
+        assert!(
+            matches!(&err, Error::InterfaceLocked(iface, Some(pid)) if iface == "vcan0" && *pid == std::process::id())
+        );
+        assert!(err.to_string().starts_with("vcan0 is in use by radarpub"));
+
+        // The stale file of a stopped radarpub is not a lock.
+        drop(lock);
+        assert!(!CanLock::is_locked(&dir, "vcan0").unwrap());
+        let lock = CanLock::acquire(&dir, "vcan0").unwrap();
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recv_response_skip() {
+        let settings = RecvResponseSettings::default();
+
+        // The radar broadcasts are skipped.
+        let can = CanInjector::new();
+        can.extend([
+            Packet { id: 0x400, data: 0 },
+            Packet { id: 0x4FF, data: 0 },
+            Packet {
+                id: STATUS_ID,
+                data: 0,
+            },
+        ]);
+        can.extend(response(42));
+        let value = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap();
+        assert_eq!(value, 42);
+
+        // An instruction of another client is reported.
+        let can = CanInjector::new();
+        can.push(Packet {
+            id: INSTRUCTION_ID,
+            data: 0,
+        });
+        can.extend(response(42));
+        let err = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap_err();
+        assert!(matches!(err, Error::ConcurrentInstruction));
+
+        // As is any other frame, it is not retried.
+        let can = CanInjector::new();
+        can.push(Packet { id: 0x123, data: 0 });
+        can.extend(response(42));
+        let err = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap_err();
+        assert!(matches!(err, Error::InvalidResponseId(0x123)));
+
+        let ids = CanIds::default();
+        assert!(ids.is_radar_frame(0x400) && ids.is_radar_frame(0x500));
+        assert!(!ids.is_radar_frame(0x501) && !ids.is_radar_frame(RESPONSE_ID));
+    }
+
+    #[test]
+    fn test_blockage_alarm() {
+        let blocked = SensorStatus {
+            temperature: 40.0,
+            supply_voltage: 12.0,
+            blocked: true,
+        };
+        let clear = SensorStatus {
+            blocked: false,
+            ..blocked
+        };
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut alarm = BlockageAlarm::new(Duration::from_secs(2));
+        assert!(!alarm.update(&blocked, at(0)));
+        assert!(!alarm.update(&blocked, at(1900)));
+        assert!(alarm.update(&blocked, at(2000)));
+        assert!(alarm.update(&blocked, at(5000)));
+
+        // A clear status resets the hold time.
+        assert!(!alarm.update(&clear, at(5100)));
+        assert!(!alarm.update(&blocked, at(5200)));
+        assert!(!alarm.update(&blocked, at(7100)));
+        assert!(alarm.update(&blocked, at(7200)));
+    }
+
+    #[test]
+    fn test_uat_error_code() {
+        for (i, code) in UatErrorCode::KNOWN.iter().enumerate() {
+            assert_eq!(code.code(), i as u16 + 1);
+            assert_eq!(UatErrorCode::from(code.code()), *code);
+        }
+        assert_eq!(UatErrorCode::from(3), UatErrorCode::OutOfRange);
+        assert_eq!(UatErrorCode::OutOfRange.name(), "out_of_range");
+        assert_eq!(UatErrorCode::Busy.to_string(), "sensor busy");
+
+        // Codes missing from the table keep their number.
+        assert_eq!(UatErrorCode::from(42), UatErrorCode::Unknown(42));
+        assert_eq!(UatErrorCode::from(42).code(), 42);
+        assert_eq!(UatErrorCode::Unknown(42).name(), "unknown_42");
+        assert_eq!(
+            Error::UATError(UatErrorCode::WriteProtected).to_string(),
+            "UAT error 4: parameter is write protected"
+        );
+
+        // Failed responses are counted by code, other tests may add to the
+        // process wide counters concurrently.
+        let busy = |counts: Vec<(UatErrorCode, u64)>| {
+            counts
+                .into_iter()
+                .find(|(code, _)| *code == UatErrorCode::Busy)
+                .map_or(0, |(_, count)| count)
+        };
+        let before = busy(uat_error_counts());
+        let can = CanInjector::new();
+        for _ in 0..2 {
+            let mut rejected = response(0);
+            rejected[2].data |= 5 << 24;
+            can.extend(rejected);
+            let err = block_on(read_status(
+                &can,
+                Status::SerialNumber,
+                &RecvResponseSettings::default(),
+            ))
+            .unwrap_err();
+            assert!(matches!(err, Error::UATError(UatErrorCode::Busy)));
+        }
+        assert!(busy(uat_error_counts()) >= before + 2);
+        let counts = uat_error_counts();
+        assert!(counts.windows(2).all(|w| w[0].0.code() < w[1].0.code()));
+    }
+
+    #[test]
+    fn test_recv_response_settings() {
+        let settings = RecvResponseSettings {
+            max_retries: 3,
+            read_timeout: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let targets = [Packet { id: 0x400, data: 0 }; 3];
+
+        // Giving up after the third target frame.
+        let can = CanInjector::new();
+        can.extend(targets);
+        can.extend(response(42));
+        let err = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap_err();
+        assert!(matches!(err, Error::InvalidResponseId(0x400)));
+
+        // One more retry reaches the response.
+        let can = CanInjector::new();
+        can.extend(targets);
+        can.extend(response(42));
+        let settings = RecvResponseSettings {
+            max_retries: 4,
+            ..settings
+        };
+        let value = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap();
+        assert_eq!(value, 42);
+
+        // A silent sensor times out instead of blocking.
+        let can = CanInjector::new();
+        let err = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap_err();
+        assert!(matches!(err, Error::ResponseTimeout(timeout) if timeout == settings.read_timeout));
+
+        // As does a response cut short.
+        let can = CanInjector::new();
+        can.extend(response(42)[..2].iter().copied());
+        let err = block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap_err();
+        assert!(matches!(err, Error::ResponseTimeout(_)));
+    }
+
+    #[test]
+    fn test_blocking() {
+        // A stray target, a frame with two targets and the start of the next.
+        let mut packets = vec![
+            Packet { id: 0x402, data: 0 },
+            Packet {
+                id: 0x400,
+                data: (2 << 47) | (42 << 15) | 100,
+            },
+            Packet {
+                id: 0x400,
+                data: 1 << 62,
+            },
+            Packet {
+                id: 0x400,
+                data: 2 << 62,
+            },
+        ];
+        for id in [0x401, 0x401, 0x402, 0x402] {
+            packets.push(Packet {
+                id,
+                data: load_data(&[0x62, 0xC1, 0x40, 0x55, 0x03, 0xD8, 0x0D, id as u8]),
+            });
+        }
+        packets.push(Packet {
+            id: 0x400,
+            data: 1 << 47,
+        });
+
+        // The blocking reads parse the same frames as the async reads.
+        let ids = CanIds::default();
+        let (can, blocking) = (CanInjector::new(), CanInjector::new());
+        can.extend(packets.iter().copied());
+        blocking.extend(packets.iter().copied());
+        let frame = read_message_blocking(&blocking, &ids).unwrap();
+        assert_eq!(frame, block_on(read_message(&can, &ids)).unwrap());
+        assert_eq!(frame.header.cycle_counter, 42);
+        assert_eq!(frame.header.n_targets, 2);
+        assert_eq!(blocking.pending(), 1);
+        // An empty injector times out instead of blocking.
+        let err = read_message_blocking(&blocking, &ids).unwrap_err();
+        assert!(matches!(err, Error::Io(err) if err.kind() == io::ErrorKind::TimedOut));
+
+        let settings = RecvResponseSettings::default();
+        let (can, blocking) = (CanInjector::new(), CanInjector::new());
+        for can in [&can, &blocking] {
+            can.push(Packet { id: 0x401, data: 0 });
+            can.extend(response(42));
+        }
+        let value = read_status_blocking(&blocking, Status::SerialNumber, &settings).unwrap();
+        assert_eq!(value, 42);
+        block_on(read_status(&can, Status::SerialNumber, &settings)).unwrap();
+        assert_eq!(blocking.sent(), can.sent());
+
+        blocking.extend(response(2));
+        let value =
+            write_parameter_blocking(&blocking, Parameter::FrequencySweep, 2, &settings).unwrap();
+        assert_eq!(value, 2);
+        let header = blocking.sent()[0].data.to_le_bytes();
+        assert_eq!(u16::from_le_bytes([header[0], header[1]]), 2010);
+
+        let err =
+            read_parameter_blocking(&blocking, Parameter::RangeToggle, &settings).unwrap_err();
+        assert!(matches!(err, Error::ResponseTimeout(timeout) if timeout == settings.read_timeout));
+
+        blocking.push(Packet {
+            id: INSTRUCTION_ID,
+            data: 0,
+        });
+        let err = send_command_blocking(&blocking, Command::SensorReset, 0, &settings).unwrap_err();
+        assert!(matches!(err, Error::ConcurrentInstruction));
+    }
+
+    #[test]
+    fn test_read_device_info() {
+        let can = CanInjector::new();
+        for value in [3, 1, 6, 2, 123456] {
+            can.extend(response(value));
+        }
+
+        let info = block_on(read_device_info(&can, &RecvResponseSettings::default())).unwrap();
+        assert_eq!(info.software_generation, 3);
+        assert_eq!(info.version(), "1.6.2");
+        assert_eq!(info.serial_number, 123456);
+    }
+
+    #[test]
+    fn test_parse_candump() {
+        let pkt = parse_candump("(1700000000.000000) can0 400#5B8382323B80880C").unwrap();
+        assert_eq!(pkt.id, 0x400);
+        assert_eq!(
+            pkt.data,
+            load_data(&[0x5b, 0x83, 0x82, 0x32, 0x3b, 0x80, 0x88, 0x0c])
+        );
+
+        let pkt = parse_candump("(0.1) vcan0 700#0102").unwrap();
+        assert_eq!(pkt.id, 0x700);
+        assert_eq!(pkt.data, 0x0201);
+
+        assert!(parse_candump("(0.1) can0 400").is_err());
+        assert!(parse_candump("(0.1) can0 40G#00").is_err());
+        assert!(parse_candump("(0.1) can0 400#0").is_err());
+        assert!(parse_candump("(0.1) can0 400#000000000000000000").is_err());
+    }
+
     #[test]
     fn test_crc() {
         // From Smart Micro Systems User Application Note UATv4 Section 7.1
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use clap::Parser;
+use log::info;
+use radarpub::{
+    clustering::{davies_bouldin_index, ClusteringBuilder},
+    eval::{cluster_frames, parse_csv},
+};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+const HEADER: &str = "frame_counter,range,azimuth,elevation,speed,rcs,power,noise";
+
+/// Run the radar target clustering offline on targets recorded as CSV.
+///
+/// The input uses the format
+/// `frame_counter,range,azimuth,elevation,speed,rcs,power,noise` and the
+/// output repeats every input row, grouped by frame, with a cluster_id column
+/// appended.
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Input CSV file with recorded targets
+    #[arg()]
+    input: PathBuf,
+
+    /// Output CSV file
+    #[arg()]
+    output: PathBuf,
+
+    /// Clustering DBSCAN distance limit (euclidean distance)
+    #[arg(long, default_value = "1")]
+    clustering_eps: f64,
+
+    /// Clustering DBSCAN parameter scaling. Parameter order is x, y, z, speed.
+    /// Set the appropriate axis to 0 to ignore that axis
+    #[arg(long, default_value = "1 1 0 0", value_delimiter = ' ', num_args = 4)]
+    clustering_param_scale: Vec<f32>,
+
+    /// Clustering DBSCAN point limit. Minimum 3
+    #[arg(long, default_value = "5")]
+    clustering_point_limit: usize,
+
+    /// Clustering worker threads, 0 uses one thread per CPU core
+    #[arg(long, default_value = "0")]
+    clustering_threads: usize,
+}
+
+fn run(args: &Args) -> Result<(), std::io::Error> {
+    let frames = parse_csv(BufReader::new(File::open(&args.input)?))?;
+    info!("read {} frames from {:?}", frames.len(), args.input);
+
+    let params = ClusteringBuilder::new()
+        .eps(args.clustering_eps)
+        .param_scale(&args.clustering_param_scale)
+        .point_limit(args.clustering_point_limit)
+        .threads(args.clustering_threads);
+    let clusters = cluster_frames(&frames, &params);
+
+    let quality: Vec<_> = clusters
+        .iter()
+        .map(|points| {
+            let (points, labels): (Vec<_>, Vec<_>) = points
+                .iter()
+                .map(|p| ([p[0], p[1], p[2], p[3]], p[4] as usize))
+                .unzip();
+            davies_bouldin_index(&points, &labels)
+        })
+        .filter(|index| *index > 0.0)
+        .collect();
+    if !quality.is_empty() {
+        info!(
+            "mean davies-bouldin index {:.3} over {} frames with at least two clusters",
+            quality.iter().sum::<f32>() / quality.len() as f32,
+            quality.len()
+        );
+    }
+
+    let mut out = BufWriter::new(File::create(&args.output)?);
+    writeln!(out, "{},cluster_id", HEADER)?;
+    for (frame, points) in frames.iter().zip(clusters.iter()) {
+        for (line, point) in frame.lines.iter().zip(points.iter()) {
+            writeln!(out, "{},{}", line, point[4] as usize)?;
+        }
+    }
+    out.flush()?;
+
+    info!("wrote clusters to {:?}", args.output);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {}", args.input.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
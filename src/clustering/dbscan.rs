@@ -0,0 +1,345 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use super::pool::Pool;
+#[cfg(feature = "kdtree")]
+use kiddo::{immutable::float::kdtree::ImmutableKdTree, SquaredEuclidean};
+
+/// DBSCAN point classification.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Classification {
+    /// Point with at least `min_points` neighbours within `eps`
+    Core(usize),
+    /// Point within `eps` of a core point but without enough neighbours
+    Edge(usize),
+    /// Point which does not belong to any cluster
+    Noise,
+}
+
+/// Density based spatial clustering.
+///
+/// Neighbourhoods are computed up front for every point, which is the
+/// expensive part of the algorithm and is spread across the rayon pool when
+/// one is provided.  Region growth then runs sequentially in point order so
+/// the resulting labels are identical regardless of the number of threads.
+///
+/// With the `kdtree` feature the neighbourhoods are found with a k-d tree
+/// built once per run and queried in O(log N) per point, without it they
+/// fall back to a brute force O(N²) search with identical neighbourhoods and
+/// labels.
+#[derive(Debug, Clone)]
+pub struct Dbscan {
+    eps: f64,
+    min_points: usize,
+}
+
+impl Dbscan {
+    pub fn new(eps: f64, min_points: usize) -> Self {
+        Dbscan { eps, min_points }
+    }
+
+    /// Cluster the points, returning one classification per point.  Cluster
+    /// indices start at 0 and are assigned in order of the first core point
+    /// of each cluster.
+    pub fn run<const N: usize>(&self, points: &[[f32; N]], pool: &Pool) -> Vec<Classification> {
+        let neighbours = self.neighbours(points, pool);
+        self.expand(&neighbours)
+    }
+
+    /// Indices of the points within eps of every point, the point included,
+    /// in ascending order.
+    #[cfg(not(feature = "kdtree"))]
+    fn neighbours<const N: usize>(&self, points: &[[f32; N]], pool: &Pool) -> Vec<Vec<usize>> {
+        pool.map(points, |p| self.range_query(p, points))
+    }
+
+    /// Indices of the points within eps of every point, the point included,
+    /// in ascending order.
+    #[cfg(feature = "kdtree")]
+    fn neighbours<const N: usize>(&self, points: &[[f32; N]], pool: &Pool) -> Vec<Vec<usize>> {
+        if points.is_empty() {
+            return Vec::new();
+        }
+
+        let tree: ImmutableKdTree<f32, u32, N, 32> = ImmutableKdTree::new_from_slice(points);
+        // The f32 squared radius is padded so rounding never drops a
+        // neighbour, the f64 distance check then matches the brute force
+        // search exactly.
+        let radius = (self.eps * self.eps * (1.0 + 1e-4)) as f32 + f32::EPSILON;
+        pool.map(points, |p| {
+            let mut indices: Vec<usize> = tree
+                .within_unsorted::<SquaredEuclidean>(p, radius)
+                .into_iter()
+                .map(|neighbour| neighbour.item as usize)
+                .filter(|&i| distance(p, &points[i]) < self.eps)
+                .collect();
+            indices.sort_unstable();
+            indices
+        })
+    }
+
+    /// Cluster the points independently per azimuth sector.
+    ///
+    /// The azimuth span of the points is split into `sectors` equal sectors
+    /// which are clustered in parallel on the pool, each with a fresh DBSCAN
+    /// so no state is shared between sectors.  Clusters of adjacent sectors
+    /// are then merged when any of their points are within `eps` of each
+    /// other.  A point which only reaches the point limit with neighbours
+    /// across a sector boundary is not a core point, so the result can differ
+    /// slightly from [`Dbscan::run`] at the boundaries.  Cluster
+    /// indices are assigned in order of the first point of each merged
+    /// cluster.
+    pub fn run_sectors<const N: usize>(
+        &self,
+        points: &[[f32; N]],
+        azimuths: &[f32],
+        sectors: usize,
+        pool: &Pool,
+    ) -> Vec<Classification> {
+        let (min, max) = azimuths
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &a| {
+                (min.min(a), max.max(a))
+            });
+        if sectors <= 1 || max <= min {
+            return self.run(points, pool);
+        }
+
+        let width = (max - min) / sectors as f32;
+        let mut members = vec![Vec::new(); sectors];
+        for (i, &azimuth) in azimuths.iter().enumerate() {
+            let sector = (((azimuth - min) / width) as usize).min(sectors - 1);
+            members[sector].push(i);
+        }
+
+        // The sectors are the parallel work items, each one runs serially.
+        let serial = Pool::default();
+        let results = pool.map(&members, |indices| {
+            let subset: Vec<_> = indices.iter().map(|&i| points[i]).collect();
+            self.run(&subset, &serial)
+        });
+
+        let mut classes = vec![Classification::Noise; points.len()];
+        let mut offset = 0;
+        for (indices, result) in members.iter().zip(results) {
+            let mut count = 0;
+            for (&i, class) in indices.iter().zip(result) {
+                classes[i] = match class {
+                    Classification::Core(c) => Classification::Core(c + offset),
+                    Classification::Edge(c) => Classification::Edge(c + offset),
+                    Classification::Noise => Classification::Noise,
+                };
+                if let Some(c) = class.cluster() {
+                    count = count.max(c + 1);
+                }
+            }
+            offset += count;
+        }
+
+        let mut parent: Vec<usize> = (0..offset).collect();
+        for pair in members.windows(2) {
+            let clustered = |indices: &[usize]| -> Vec<(usize, usize)> {
+                indices
+                    .iter()
+                    .filter_map(|&i| classes[i].cluster().map(|c| (i, c)))
+                    .collect()
+            };
+            let (left, right) = (clustered(&pair[0][..]), clustered(&pair[1][..]));
+            for &(i, a) in &left {
+                for &(j, b) in &right {
+                    let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+                    if a != b && distance(&points[i], &points[j]) < self.eps {
+                        parent[a.max(b)] = a.min(b);
+                    }
+                }
+            }
+        }
+
+        let mut relabel = vec![None; offset];
+        let mut next = 0;
+        for class in classes.iter_mut() {
+            if let Classification::Core(c) | Classification::Edge(c) = class {
+                let root = find(&mut parent, *c);
+                *c = *relabel[root].get_or_insert_with(|| {
+                    next += 1;
+                    next - 1
+                });
+            }
+        }
+        classes
+    }
+
+    /// Brute force O(N) search for the points within eps of `sample`, the
+    /// fallback without the `kdtree` feature and the reference of its tests.
+    #[cfg(any(not(feature = "kdtree"), test))]
+    fn range_query<const N: usize>(&self, sample: &[f32; N], points: &[[f32; N]]) -> Vec<usize> {
+        points
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| distance(sample, p) < self.eps)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn expand(&self, neighbours: &[Vec<usize>]) -> Vec<Classification> {
+        let mut classes = vec![Classification::Noise; neighbours.len()];
+        let mut visited = vec![false; neighbours.len()];
+        let mut cluster = 0;
+
+        for idx in 0..neighbours.len() {
+            if visited[idx] {
+                continue;
+            }
+            visited[idx] = true;
+
+            if neighbours[idx].len() < self.min_points {
+                continue;
+            }
+
+            let mut queue = neighbours[idx].clone();
+            while let Some(ind) = queue.pop() {
+                if neighbours[ind].len() < self.min_points {
+                    continue;
+                }
+
+                classes[ind] = Classification::Core(cluster);
+                for &n in &neighbours[ind] {
+                    if classes[n] == Classification::Noise {
+                        classes[n] = Classification::Edge(cluster);
+                    }
+                    if visited[n] {
+                        continue;
+                    }
+                    visited[n] = true;
+                    queue.push(n);
+                }
+            }
+
+            cluster += 1;
+        }
+
+        classes
+    }
+}
+
+impl Classification {
+    /// Cluster index of a core or edge point.
+    pub fn cluster(&self) -> Option<usize> {
+        match self {
+            Classification::Core(c) | Classification::Edge(c) => Some(*c),
+            Classification::Noise => None,
+        }
+    }
+}
+
+/// Union-find root of a merged cluster.
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn distance<const N: usize>(a: &[f32; N], b: &[f32; N]) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(dbscan: &Dbscan, points: &[[f32; 2]]) -> Vec<Classification> {
+        dbscan.run(points, &Pool::default())
+    }
+
+    #[test]
+    fn test_dbscan() {
+        let points = [
+            [0.0, 0.0],
+            [0.5, 0.0],
+            [0.0, 0.5],
+            [0.5, 0.5],
+            [1.4, 0.5],
+            [10.0, 10.0],
+            [10.5, 10.0],
+            [10.0, 10.5],
+            [20.0, 20.0],
+        ];
+        let classes = run(&Dbscan::new(1.0, 3), &points);
+
+        assert_eq!(classes[0], Classification::Core(0));
+        assert_eq!(classes[3], Classification::Core(0));
+        // Only within eps of a single core point.
+        assert_eq!(classes[4], Classification::Edge(0));
+        assert_eq!(classes[5], Classification::Core(1));
+        assert_eq!(classes[7], Classification::Core(1));
+        assert_eq!(classes[8], Classification::Noise);
+    }
+
+    #[test]
+    fn test_dbscan_sectors() {
+        // A cluster straddling the boundary of two sectors, and one cluster
+        // within each sector.
+        let points = [
+            [10.0, -5.0],
+            [10.3, -5.0],
+            [10.0, -5.3],
+            [10.0, -0.6],
+            [10.0, -0.2],
+            [10.0, 0.2],
+            [10.0, 0.6],
+            [10.0, 5.0],
+            [10.3, 5.0],
+            [10.0, 5.3],
+            [30.0, 0.1],
+        ];
+        let azimuths: Vec<_> = points.iter().map(|p| p[1].atan2(p[0])).collect();
+        let dbscan = Dbscan::new(0.5, 2);
+        let sectors = dbscan.run_sectors(&points, &azimuths, 2, &Pool::default());
+
+        // The straddling cluster is merged back together.
+        assert_eq!(sectors[3].cluster(), sectors[6].cluster());
+        assert_eq!(sectors, run(&dbscan, &points));
+        let clusters: std::collections::HashSet<_> =
+            sectors.iter().filter_map(|c| c.cluster()).collect();
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(sectors[10], Classification::Noise);
+
+        // Without an azimuth span there is a single sector.
+        assert_eq!(
+            dbscan.run_sectors(&points, &[0.0; 11], 4, &Pool::default()),
+            run(&dbscan, &points)
+        );
+    }
+
+    #[test]
+    fn test_dbscan_neighbours() {
+        // A grid with points exactly eps apart, which are not neighbours, and
+        // flat axes like the unscaled z and speed of the clustering.
+        let mut points = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                points.push([i as f32 * 0.5, j as f32 * 0.25 + i as f32 * 0.01, 0.0, 0.0]);
+            }
+        }
+        let dbscan = Dbscan::new(0.5, 3);
+        let neighbours = dbscan.neighbours(&points, &Pool::default());
+        for (p, neighbours) in points.iter().zip(&neighbours) {
+            assert_eq!(*neighbours, dbscan.range_query(p, &points));
+        }
+    }
+
+    #[test]
+    fn test_dbscan_empty() {
+        assert!(run(&Dbscan::new(1.0, 3), &[]).is_empty());
+        assert_eq!(
+            run(&Dbscan::new(1.0, 3), &[[0.0, 0.0]]),
+            vec![Classification::Noise]
+        );
+    }
+}
@@ -85,6 +85,11 @@ where
         }
     }
 
+    /// State transition applied by [`Self::predict`].
+    pub fn motion_matrix(&self) -> &OMatrix<R, U8, U8> {
+        &self.motion_matrix
+    }
+
     pub fn predict(&mut self) {
         let height = self.mean[3];
         let diag = [
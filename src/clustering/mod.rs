@@ -1,14 +1,856 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
 
-use dbscan::{Classification, Model};
-use tracker::{ByteTrack, TrackSettings, VAALBox};
+use dbscan::{Classification, Dbscan};
+use kalman::ConstantVelocityXYAHModel2;
+use nalgebra::SMatrix;
+use pool::Pool;
+use serde_json::{json, Value};
+use tracker::{iou, non_maximum_suppression, ByteTrack, TrackSettings, Tracklet, VAALBox};
 use uuid::Uuid;
 
+mod dbscan;
 mod kalman;
+mod pool;
 mod tracker;
+
+/// Snapshot of the tracked cluster boxes at the end of a clustering cycle.
+///
+/// The clustering task runs behind the CAN reader, so an association is
+/// always applied to a later radar frame than the one which produced it.
+/// The [`TrackAssociation::age`] reports how many radar cycles late the
+/// annotation is, normally 1.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackAssociation {
+    /// Radar cycle counter of the frame which completed the clustering cycle
+    pub cycle_counter: u32,
+
+    /// Predicted [xmin, ymin, xmax, ymax] box and cluster id for each track
+    pub boxes: Vec<([f32; 4], usize)>,
+}
+
+impl TrackAssociation {
+    /// Returns the cluster id of the first tracked box containing the point,
+    /// or 0 if the point is not inside any tracked box.  The point must be
+    /// in the same scaled clustering coordinates given to
+    /// [`Clustering::cluster`].
+    pub fn track_id(&self, x: f32, y: f32) -> usize {
+        self.boxes
+            .iter()
+            .find(|([xmin, ymin, xmax, ymax], _)| {
+                x >= *xmin && x <= *xmax && y >= *ymin && y <= *ymax
+            })
+            .map(|(_, id)| *id)
+            .unwrap_or(0)
+    }
+
+    /// Number of radar cycles between the association and the given frame.
+    pub fn age(&self, cycle_counter: u32) -> u32 {
+        cycle_counter.wrapping_sub(self.cycle_counter)
+    }
+}
+
+/// Number of radar frames missed between two clustered frames.
+///
+/// Jitter below 1.5 radar cycles is not a missed frame, longer gaps are
+/// rounded to the nearest number of cycles.
+///
+/// # Arguments
+/// * `gap_ns` - Time between the clustered frames in nanoseconds
+/// * `cycle_ns` - Expected radar cycle duration in nanoseconds
+pub fn missed_frames(gap_ns: u64, cycle_ns: u64) -> usize {
+    if cycle_ns == 0 || gap_ns.saturating_mul(2) <= cycle_ns.saturating_mul(3) {
+        return 0;
+    }
+    ((gap_ns + cycle_ns / 2) / cycle_ns).saturating_sub(1) as usize
+}
+
+/// Smooth a complete track history with a Rauch-Tung-Striebel smoother.
+///
+/// The forward pass runs the tracker's Kalman filter over the measurements,
+/// the backward pass then corrects each filtered state with the smoothed
+/// state of the following measurement, so every point is estimated from
+/// the whole track rather than only its past.  Meant for offline processing
+/// of recorded sessions such as a PCAP replay, the live tracker cannot look
+/// ahead.  Gaps longer than the shortest interval of the history are
+/// predicted over the missed frames as with
+/// [`Clustering::advance_missing_frames`].
+///
+/// # Arguments
+/// * `trajectory` - Timestamp in nanoseconds and [x, y, aspect, height]
+///   measurement of the track box, ordered by time
+/// * `update_factor` - Kalman update factor of the tracker, see
+///   `track_update`
+///
+/// # Returns
+/// The smoothed [x, y, aspect, height] of each measurement
+#[allow(dead_code)]
+pub fn smooth_trajectory(trajectory: &[(u64, [f32; 4])], update_factor: f32) -> Vec<[f32; 4]> {
+    let Some((_, first)) = trajectory.first() else {
+        return Vec::new();
+    };
+    let cycle = trajectory
+        .windows(2)
+        .map(|w| w[1].0.saturating_sub(w[0].0))
+        .filter(|gap| *gap > 0)
+        .min()
+        .unwrap_or(0);
+
+    let mut filter = ConstantVelocityXYAHModel2::new(first, update_factor);
+    let mut filtered = vec![(filter.mean, filter.covariance)];
+    // Predicted state of each following measurement and the transition
+    // from the previous one.
+    let mut predicted = Vec::with_capacity(trajectory.len());
+    for w in trajectory.windows(2) {
+        let ((prev, _), (timestamp, measurement)) = (w[0], w[1]);
+        let mut transition = SMatrix::<f32, 8, 8>::identity();
+        for _ in 0..=missed_frames(timestamp.saturating_sub(prev), cycle) {
+            filter.predict();
+            transition = filter.motion_matrix() * transition;
+        }
+        predicted.push((filter.mean, filter.covariance, transition));
+        filter.update(&measurement);
+        filtered.push((filter.mean, filter.covariance));
+    }
+
+    let mut smoothed = filtered.clone();
+    for k in (0..predicted.len()).rev() {
+        let (mean, covariance) = filtered[k];
+        let (predicted_mean, predicted_covariance, transition) = predicted[k];
+        let Some(inverse) = predicted_covariance.try_inverse() else {
+            continue;
+        };
+        let gain = covariance * transition.transpose() * inverse;
+        let (next_mean, next_covariance) = smoothed[k + 1];
+        smoothed[k] = (
+            mean + gain * (next_mean - predicted_mean),
+            covariance + gain * (next_covariance - predicted_covariance) * gain.transpose(),
+        );
+    }
+
+    smoothed
+        .iter()
+        .map(|(mean, _)| [mean[0], mean[1], mean[2], mean[3]])
+        .collect()
+}
+
+/// Davies-Bouldin index of a clustering, a measure of cluster separation.
+///
+/// For every cluster the scatter is the average distance of its points to
+/// the cluster centroid.  The similarity of two clusters is the sum of their
+/// scatters divided by the distance between their centroids, and the index
+/// is the mean over all clusters of the similarity to their most similar
+/// other cluster.
+///
+/// Lower values indicate compact, well separated clusters.  Values well
+/// below 1 mean the clusters are further apart than they are wide, values
+/// around 1 or above mean neighbouring clusters touch or overlap, which
+/// suggests `clustering_eps` is too large and is merging objects, or too
+/// small and is splitting single objects into adjacent clusters.  Compare
+/// values for the same scene, the index depends on the scene and the
+/// parameter scaling.
+///
+/// # Arguments
+/// * `points` - Points as [x, y, z, speed] in the scaled clustering space
+/// * `labels` - Cluster id of each point, 0 is noise and is ignored
+///
+/// # Returns
+/// The Davies-Bouldin index, or 0 if there are fewer than two clusters
+pub fn davies_bouldin_index(points: &[[f32; 4]], labels: &[usize]) -> f32 {
+    let mut clusters = BTreeMap::<usize, Vec<[f32; 4]>>::new();
+    for (point, label) in points.iter().zip(labels.iter()) {
+        if *label != 0 {
+            clusters.entry(*label).or_default().push(*point);
+        }
+    }
+    if clusters.len() < 2 {
+        return 0.0;
+    }
+
+    let distance = |a: &[f32; 4], b: &[f32; 4]| {
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>()
+            .sqrt()
+    };
+
+    let stats: Vec<_> = clusters
+        .values()
+        .map(|cluster| {
+            let n = cluster.len() as f32;
+            let mut centroid = [0.0; 4];
+            for p in cluster {
+                for (c, v) in centroid.iter_mut().zip(p.iter()) {
+                    *c += v / n;
+                }
+            }
+            let scatter = cluster.iter().map(|p| distance(p, &centroid)).sum::<f32>() / n;
+            (centroid, scatter)
+        })
+        .collect();
+
+    let worst = stats.iter().enumerate().map(|(i, (ci, si))| {
+        stats
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, (cj, sj))| (si + sj) / distance(ci, cj))
+            .fold(0.0, f32::max)
+    });
+    worst.sum::<f32>() / stats.len() as f32
+}
+
+/// Centroid and mean velocity of one cluster of a clustering cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ClusterSummary {
+    /// Cluster id, kept across cycles by the tracker
+    pub id: usize,
+    /// Mean [x, y, z] of the cluster points in meters
+    pub centroid: [f32; 3],
+    /// Mean radial speed of the cluster points in m/s
+    pub velocity: f32,
+    /// Mean radar cross-section of the cluster points in dBsm
+    pub rcs: f32,
+    /// Number of points in the cluster
+    pub points: usize,
+}
+
+/// Summarize the clusters of a clustering cycle.
+///
+/// # Arguments
+/// * `points` - Position in meters, speed in m/s, radar cross-section in
+///   dBsm and cluster id of each point, 0 is noise and is ignored
+///
+/// # Returns
+/// One summary per cluster ordered by cluster id
+pub fn cluster_summaries(
+    points: impl IntoIterator<Item = ([f32; 3], f32, f32, usize)>,
+) -> Vec<ClusterSummary> {
+    let mut clusters = BTreeMap::<usize, ClusterSummary>::new();
+    for (xyz, speed, rcs, id) in points {
+        if id == 0 {
+            continue;
+        }
+        let summary = clusters.entry(id).or_insert(ClusterSummary {
+            id,
+            ..Default::default()
+        });
+        for (c, v) in summary.centroid.iter_mut().zip(xyz) {
+            *c += v;
+        }
+        summary.velocity += speed;
+        summary.rcs += rcs;
+        summary.points += 1;
+    }
+
+    clusters
+        .into_values()
+        .map(|mut summary| {
+            let n = summary.points as f32;
+            summary.centroid = summary.centroid.map(|c| c / n);
+            summary.velocity /= n;
+            summary.rcs /= n;
+            summary
+        })
+        .collect()
+}
+
+/// Returns false when the mean velocity of a cluster changed by more than
+/// `max_accel * dt_sec` since the previous cycle, which suggests the cluster
+/// was assigned to a different object, for example DBSCAN merging two
+/// objects.
+///
+/// # Arguments
+/// * `prev_summary` - The cluster in the previous cycle
+/// * `curr_summary` - The cluster with the same id in the current cycle
+/// * `dt_sec` - Time between the cycles in seconds
+/// * `max_accel` - Largest plausible acceleration in m/s²
+pub fn velocity_coherence_check(
+    prev_summary: &ClusterSummary,
+    curr_summary: &ClusterSummary,
+    dt_sec: f32,
+    max_accel: f32,
+) -> bool {
+    (curr_summary.velocity - prev_summary.velocity).abs() <= max_accel * dt_sec
+}
+
+/// Points of a cluster for the full point count term of
+/// [`detection_score`].
+pub const DETECTION_SCORE_POINTS: usize = 10;
+
+/// Tracked cycles for the full persistence term of [`detection_score`].
+pub const DETECTION_SCORE_CYCLES: i32 = 10;
+
+/// Confidence of a tracked cluster as a detection in [0, 1].
+///
+/// Half the score grows with the number of points up to
+/// [`DETECTION_SCORE_POINTS`], the other half with the number of cycles the
+/// track was updated up to [`DETECTION_SCORE_CYCLES`].  A sparse cluster
+/// seen once scores low, a dense cluster tracked for a second scores 1.
+pub fn detection_score(points: usize, count: i32) -> f32 {
+    let points = (points as f32 / DETECTION_SCORE_POINTS as f32).min(1.0);
+    let count = (count.max(0) as f32 / DETECTION_SCORE_CYCLES as f32).min(1.0);
+    0.5 * points + 0.5 * count
+}
+
+/// Predicted box and lifetime of a track which has been assigned a cluster
+/// id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterTrack {
+    /// Cluster id of the track
+    pub cluster_id: usize,
+    /// Track id, stable for the lifetime of the track
+    pub uuid: Uuid,
+    /// Predicted [xmin, ymin, xmax, ymax] box in the scaled clustering
+    /// coordinates
+    pub bbox: [f32; 4],
+    /// Number of cycles the track was updated
+    pub count: i32,
+    /// Timestamp in nanoseconds of the cycle which created the track
+    pub created: u64,
+}
+
+/// Default of [`Clustering::set_track_history_max_points`].
+pub const TRACK_HISTORY_MAX_POINTS: usize = 256;
+
+/// Default of [`Clustering::set_track_history_max_tracks`].
+pub const TRACK_HISTORY_MAX_TRACKS: usize = 10000;
+
+/// Summary of the lifetime of a confirmed track, see
+/// [`Clustering::record_history`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackRecord {
+    /// Cluster id of the track
+    pub cluster_id: usize,
+    /// Track id
+    pub uuid: Uuid,
+    /// Timestamp in nanoseconds of the first cycle the track was recorded
+    pub first_seen: u64,
+    /// Timestamp in nanoseconds of the last cycle the track had points
+    pub last_seen: u64,
+    /// Decimated [x, y] cluster centroids in meters, oldest first
+    pub path: Vec<[f32; 2]>,
+    /// Largest absolute mean radial speed of the cluster in m/s
+    pub max_speed: f32,
+    /// Class of the track in its last recorded cycle
+    pub class: ObjectClass,
+}
+
+/// History of a live track.
+#[derive(Debug, Clone)]
+struct LiveTrack {
+    record: TrackRecord,
+    /// Cycles the track was recorded
+    cycles: usize,
+    /// Recorded cycles between path points
+    stride: usize,
+}
+
+/// Fewest cluster points for a class other than [`ObjectClass::Unknown`].
+pub const CLASSIFIER_MIN_POINTS: usize = 3;
+
+/// Largest speed in m/s of a pedestrian, a brisk run is faster but is rare
+/// in traffic.
+pub const PEDESTRIAN_MAX_SPEED: f32 = 3.0;
+
+/// Largest box extent in meters of a pedestrian.
+pub const PEDESTRIAN_MAX_EXTENT: f32 = 1.2;
+
+/// Largest mean radar cross-section in dBsm of a pedestrian, about -8 dBsm
+/// for an adult.
+pub const PEDESTRIAN_MAX_RCS: f32 = -3.0;
+
+/// Speed in m/s from which a cluster is a vehicle, 43 km/h is beyond most
+/// bicycles.
+pub const VEHICLE_MIN_SPEED: f32 = 12.0;
+
+/// Box extent in meters from which a cluster is a vehicle.
+pub const VEHICLE_MIN_EXTENT: f32 = 2.5;
+
+/// Mean radar cross-section in dBsm from which a cluster is a vehicle, a
+/// passenger car is about 10 dBsm.
+pub const VEHICLE_MIN_RCS: f32 = 5.0;
+
+/// Speed in m/s below which a small cluster is stationary and as likely a
+/// pole as a standing pedestrian.
+pub const MOVING_SPEED: f32 = 0.5;
+
+/// Consecutive cycles a different class has to be observed before a
+/// classified track changes class.
+#[allow(dead_code)]
+pub const CLASSIFIER_HYSTERESIS: u32 = 3;
+
+/// Coarse class of a tracked cluster, see [`classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObjectClass {
+    /// Too few points or no rule matched
+    #[default]
+    Unknown,
+    /// Slow, small and weak reflector
+    Pedestrian,
+    /// Faster or larger than a pedestrian but not a vehicle
+    Bicycle,
+    /// Fast, large or strong reflector
+    Vehicle,
+}
+
+impl ObjectClass {
+    /// Lower case name of the class used as the Detect label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ObjectClass::Unknown => "unknown",
+            ObjectClass::Pedestrian => "pedestrian",
+            ObjectClass::Bicycle => "bicycle",
+            ObjectClass::Vehicle => "vehicle",
+        }
+    }
+}
+
+/// Thresholds of [`classify`], the defaults are the documented constants.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassifierThresholds {
+    /// See [`CLASSIFIER_MIN_POINTS`]
+    pub min_points: usize,
+    /// See [`PEDESTRIAN_MAX_SPEED`]
+    pub pedestrian_max_speed: f32,
+    /// See [`PEDESTRIAN_MAX_EXTENT`]
+    pub pedestrian_max_extent: f32,
+    /// See [`PEDESTRIAN_MAX_RCS`]
+    pub pedestrian_max_rcs: f32,
+    /// See [`VEHICLE_MIN_SPEED`]
+    pub vehicle_min_speed: f32,
+    /// See [`VEHICLE_MIN_EXTENT`]
+    pub vehicle_min_extent: f32,
+    /// See [`VEHICLE_MIN_RCS`]
+    pub vehicle_min_rcs: f32,
+}
+
+impl Default for ClassifierThresholds {
+    fn default() -> Self {
+        Self {
+            min_points: CLASSIFIER_MIN_POINTS,
+            pedestrian_max_speed: PEDESTRIAN_MAX_SPEED,
+            pedestrian_max_extent: PEDESTRIAN_MAX_EXTENT,
+            pedestrian_max_rcs: PEDESTRIAN_MAX_RCS,
+            vehicle_min_speed: VEHICLE_MIN_SPEED,
+            vehicle_min_extent: VEHICLE_MIN_EXTENT,
+            vehicle_min_rcs: VEHICLE_MIN_RCS,
+        }
+    }
+}
+
+/// Kinematic and reflection features of a tracked cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ClusterFeatures {
+    /// Absolute mean radial speed in m/s
+    pub speed: f32,
+    /// Larger side of the track box in meters
+    pub extent: f32,
+    /// Number of cluster points
+    pub points: usize,
+    /// Mean radar cross-section in dBsm
+    pub rcs: f32,
+}
+
+impl ClusterFeatures {
+    /// Features of a track and its cluster in this cycle.  A track without
+    /// points in this cycle has the default summary.
+    ///
+    /// # Arguments
+    /// * `track` - The tracked box in the scaled clustering coordinates
+    /// * `summary` - The cluster of the track
+    /// * `param_scale` - The clustering scale of x and y, removed from the
+    ///   box
+    pub fn new(track: &ClusterTrack, summary: &ClusterSummary, param_scale: &[f32]) -> Self {
+        let [xmin, ymin, xmax, ymax] = track.bbox;
+        let width = (xmax - xmin) / param_scale[0];
+        let height = (ymax - ymin) / param_scale[1];
+        Self {
+            speed: summary.velocity.abs(),
+            extent: width.max(height),
+            points: summary.points,
+            rcs: summary.rcs,
+        }
+    }
+}
+
+/// Assign a coarse class and a confidence in [0.5, 1] to a cluster.
+///
+/// The rules are checked in order:
+/// 1. Fewer than `min_points` points is unknown.
+/// 2. Any of the vehicle speed, extent or RCS cues makes a vehicle.
+/// 3. All of the pedestrian speed, extent and RCS limits make a pedestrian.
+/// 4. A speed or extent above the pedestrian limits makes a bicycle.
+/// 5. Anything else, a small slow strong reflector, is unknown.
+///
+/// The confidence of a vehicle or bicycle grows from 0.5 with each further
+/// cue which agrees, a pedestrian is 1 when moving and 0.5 when stationary.
+/// Unknown is always 0.5.  The speed is radial so a crossing object is
+/// slower than it is.
+pub fn classify(
+    features: &ClusterFeatures,
+    thresholds: &ClassifierThresholds,
+) -> (ObjectClass, f32) {
+    let t = thresholds;
+    let f = features;
+    // 0.5 for the single cue deciding the class, 1 when all three agree.
+    let agreement = |cues: [bool; 3]| 0.25 + 0.25 * cues.iter().filter(|c| **c).count() as f32;
+
+    if f.points < t.min_points {
+        return (ObjectClass::Unknown, 0.5);
+    }
+
+    let vehicle = [
+        f.speed >= t.vehicle_min_speed,
+        f.extent >= t.vehicle_min_extent,
+        f.rcs >= t.vehicle_min_rcs,
+    ];
+    if vehicle.contains(&true) {
+        return (ObjectClass::Vehicle, agreement(vehicle));
+    }
+
+    let bicycle = [
+        f.speed > t.pedestrian_max_speed,
+        f.extent > t.pedestrian_max_extent,
+        f.rcs > t.pedestrian_max_rcs,
+    ];
+    match bicycle {
+        [false, false, false] => match f.speed >= MOVING_SPEED {
+            true => (ObjectClass::Pedestrian, 1.0),
+            false => (ObjectClass::Pedestrian, 0.5),
+        },
+        [false, false, true] => (ObjectClass::Unknown, 0.5),
+        _ => (ObjectClass::Bicycle, agreement(bicycle)),
+    }
+}
+
+/// Sticky class of a track.
+#[derive(Debug, Clone, Copy)]
+struct StickyClass {
+    class: ObjectClass,
+    confidence: f32,
+    candidate: ObjectClass,
+    seen: u32,
+}
+
+/// Classifies tracks with hysteresis.
+///
+/// An unknown track takes the first class it is assigned, a classified
+/// track only changes class once a different class is assigned on the
+/// configured number of consecutive cycles, so a cluster near a threshold
+/// does not flap between classes from frame to frame.
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    thresholds: ClassifierThresholds,
+    cycles: u32,
+    tracks: HashMap<Uuid, StickyClass>,
+}
+
+impl Classifier {
+    /// Create a classifier changing the class of a track after `cycles`
+    /// consecutive cycles of a different class, 1 disables the hysteresis.
+    pub fn new(thresholds: ClassifierThresholds, cycles: u32) -> Self {
+        Self {
+            thresholds,
+            cycles: cycles.max(1),
+            tracks: HashMap::new(),
+        }
+    }
+
+    /// Classify the track in this cycle.
+    ///
+    /// # Returns
+    /// The sticky class of the track and its confidence
+    pub fn update(&mut self, uuid: Uuid, features: &ClusterFeatures) -> (ObjectClass, f32) {
+        let (class, confidence) = classify(features, &self.thresholds);
+        let sticky = self.tracks.entry(uuid).or_insert(StickyClass {
+            class,
+            confidence,
+            candidate: class,
+            seen: 0,
+        });
+
+        if class == sticky.class || sticky.class == ObjectClass::Unknown {
+            sticky.class = class;
+            sticky.confidence = confidence;
+            sticky.seen = 0;
+        } else {
+            sticky.seen = match class == sticky.candidate {
+                true => sticky.seen + 1,
+                false => 1,
+            };
+            sticky.candidate = class;
+            if sticky.seen >= self.cycles {
+                sticky.class = class;
+                sticky.confidence = confidence;
+                sticky.seen = 0;
+            }
+        }
+
+        (sticky.class, sticky.confidence)
+    }
+
+    /// Classify the tracks of a clustering cycle and forget the tracks which
+    /// ended.
+    ///
+    /// # Arguments
+    /// * `tracks` - The tracks of the cycle, see [`Clustering::tracks`]
+    /// * `summaries` - The clusters of the cycle ordered by id, see
+    ///   [`cluster_summaries`]
+    /// * `param_scale` - The clustering scale of the track boxes
+    ///
+    /// # Returns
+    /// The class and confidence of each track
+    pub fn classify_tracks(
+        &mut self,
+        tracks: &[ClusterTrack],
+        summaries: &[ClusterSummary],
+        param_scale: &[f32],
+    ) -> Vec<(ObjectClass, f32)> {
+        let classes = tracks
+            .iter()
+            .map(|track| {
+                let summary = summaries
+                    .binary_search_by_key(&track.cluster_id, |s| s.id)
+                    .map(|i| summaries[i])
+                    .unwrap_or_default();
+                let features = ClusterFeatures::new(track, &summary, param_scale);
+                self.update(track.uuid, &features)
+            })
+            .collect();
+
+        let live: HashSet<_> = tracks.iter().map(|t| t.uuid).collect();
+        self.tracks.retain(|uuid, _| live.contains(uuid));
+        classes
+    }
+}
+
+/// How targets are grouped before tracking.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClusteringMode {
+    /// A single DBSCAN pass over all targets.
+    #[default]
+    Standard,
+
+    /// Partition targets into approaching, stationary and receding velocity
+    /// bands before DBSCAN, see [`Clustering::cluster_velocity_gated`].
+    VelocityGated {
+        /// Radial speed in m/s separating the stationary band from the
+        /// approaching and receding bands
+        separation_threshold_mps: f32,
+    },
+
+    /// Skip DBSCAN and put every target into cluster 1, the tracker follows
+    /// the bounding box of the whole frame.
+    Single,
+}
+
+/// Runtime of the stages of a clustering cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClusterRuntime {
+    /// DBSCAN, including the velocity band and sector splits
+    pub dbscan: Duration,
+    /// Box suppression and tracker update
+    pub tracker: Duration,
+}
+
+/// DBSCAN role of a clustered point, published as the UINT8 point_role field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum PointRole {
+    /// Not part of any cluster
+    #[default]
+    Noise = 0,
+    /// Within eps of a core point without enough neighbours of its own,
+    /// often a multipath ghost
+    Border = 1,
+    /// Point with at least the point limit of neighbours within eps
+    Core = 2,
+}
+
+impl From<Classification> for PointRole {
+    fn from(class: Classification) -> Self {
+        match class {
+            Classification::Core(_) => PointRole::Core,
+            Classification::Edge(_) => PointRole::Border,
+            Classification::Noise => PointRole::Noise,
+        }
+    }
+}
+
+/// Builder for [`Clustering`] with the same defaults as the radarpub
+/// command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusteringBuilder {
+    eps: f64,
+    param_scale: Vec<f32>,
+    point_limit: usize,
+    threads: usize,
+    mode: ClusteringMode,
+    nms_iou_threshold: f32,
+    min_cluster_size: usize,
+    parallel_sectors: usize,
+    track_min_hits: usize,
+    track_max_coast: Option<usize>,
+    provisional_ids: bool,
+    cluster_id_cooldown: Duration,
+    cluster_id_wrap: Option<usize>,
+    track_history: usize,
+    track_history_max_points: usize,
+    track_history_max_tracks: usize,
+}
+
+impl Default for ClusteringBuilder {
+    fn default() -> Self {
+        ClusteringBuilder {
+            eps: 1.0,
+            param_scale: vec![1.0, 1.0, 0.0, 0.0],
+            point_limit: 5,
+            threads: 0,
+            mode: ClusteringMode::Standard,
+            nms_iou_threshold: 1.0,
+            min_cluster_size: 1,
+            parallel_sectors: 1,
+            track_min_hits: 1,
+            track_max_coast: None,
+            provisional_ids: false,
+            cluster_id_cooldown: Duration::from_secs(5),
+            cluster_id_wrap: None,
+            track_history: 0,
+            track_history_max_points: TRACK_HISTORY_MAX_POINTS,
+            track_history_max_tracks: TRACK_HISTORY_MAX_TRACKS,
+        }
+    }
+}
+
+impl ClusteringBuilder {
+    /// Create a builder with the default clustering parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// DBSCAN epsilon (maximum distance between points in cluster).
+    pub fn eps(mut self, eps: f64) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    /// Scaling factors for [x, y, z, speed] axes (0 to ignore axis).
+    pub fn param_scale(mut self, param_scale: &[f32]) -> Self {
+        self.param_scale = param_scale.to_vec();
+        self
+    }
+
+    /// Minimum points to form cluster (minimum 3).
+    pub fn point_limit(mut self, point_limit: usize) -> Self {
+        self.point_limit = point_limit;
+        self
+    }
+
+    /// Worker threads, see [`Clustering::set_threads`].
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Clustering mode, see [`ClusteringMode`].
+    pub fn mode(mut self, mode: ClusteringMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Cluster box suppression, see [`Clustering::set_nms_iou_threshold`].
+    pub fn nms_iou_threshold(mut self, nms_iou_threshold: f32) -> Self {
+        self.nms_iou_threshold = nms_iou_threshold;
+        self
+    }
+
+    /// Minimum tracked cluster size, see [`Clustering::set_min_cluster_size`].
+    pub fn min_cluster_size(mut self, min_cluster_size: usize) -> Self {
+        self.min_cluster_size = min_cluster_size;
+        self
+    }
+
+    /// See [`Clustering::set_track_min_hits`].
+    pub fn track_min_hits(mut self, track_min_hits: usize) -> Self {
+        self.track_min_hits = track_min_hits;
+        self
+    }
+
+    /// See [`Clustering::set_track_max_coast`].
+    pub fn track_max_coast(mut self, track_max_coast: Option<usize>) -> Self {
+        self.track_max_coast = track_max_coast;
+        self
+    }
+
+    /// See [`Clustering::set_provisional_ids`].
+    pub fn provisional_ids(mut self, provisional_ids: bool) -> Self {
+        self.provisional_ids = provisional_ids;
+        self
+    }
+
+    /// See [`Clustering::set_cluster_id_cooldown`].
+    pub fn cluster_id_cooldown(mut self, cluster_id_cooldown: Duration) -> Self {
+        self.cluster_id_cooldown = cluster_id_cooldown;
+        self
+    }
+
+    /// See [`Clustering::set_cluster_id_wrap`].
+    pub fn cluster_id_wrap(mut self, cluster_id_wrap: Option<usize>) -> Self {
+        self.cluster_id_wrap = cluster_id_wrap;
+        self
+    }
+
+    /// See [`Clustering::set_track_history`].
+    pub fn track_history(mut self, decimation: usize) -> Self {
+        self.track_history = decimation;
+        self
+    }
+
+    /// See [`Clustering::set_track_history_max_points`].
+    pub fn track_history_max_points(mut self, max_points: usize) -> Self {
+        self.track_history_max_points = max_points;
+        self
+    }
+
+    /// See [`Clustering::set_track_history_max_tracks`].
+    pub fn track_history_max_tracks(mut self, max_tracks: usize) -> Self {
+        self.track_history_max_tracks = max_tracks;
+        self
+    }
+
+    /// Azimuth sectors, see [`Clustering::set_parallel_sectors`].
+    pub fn parallel_sectors(mut self, parallel_sectors: usize) -> Self {
+        self.parallel_sectors = parallel_sectors;
+        self
+    }
+
+    /// Create the clustering instance.
+    pub fn build(&self) -> Clustering {
+        let mut clustering = Clustering::new(self.eps, &self.param_scale, self.point_limit);
+        clustering.set_threads(self.threads);
+        clustering.set_mode(self.mode);
+        clustering.set_nms_iou_threshold(self.nms_iou_threshold);
+        clustering.set_min_cluster_size(self.min_cluster_size);
+        clustering.set_parallel_sectors(self.parallel_sectors);
+        clustering.set_track_min_hits(self.track_min_hits);
+        clustering.set_track_max_coast(self.track_max_coast);
+        clustering.set_provisional_ids(self.provisional_ids);
+        clustering.set_cluster_id_cooldown(self.cluster_id_cooldown);
+        clustering.set_cluster_id_wrap(self.cluster_id_wrap);
+        clustering.set_track_history(self.track_history);
+        clustering.set_track_history_max_points(self.track_history_max_points);
+        clustering.set_track_history_max_tracks(self.track_history_max_tracks);
+        clustering
+    }
+}
+
 /// DBSCAN-based spatial clustering with ByteTrack multi-object tracking.
 ///
 /// Clusters radar targets using DBSCAN algorithm and tracks objects across
@@ -25,6 +867,9 @@ pub struct Clustering {
     /// Clustering DBSCAN point limit. Minimum 3
     clustering_point_limit: usize,
 
+    /// Clustering mode
+    mode: ClusteringMode,
+
     /// Tracker
     tracker: ByteTrack,
 
@@ -34,11 +879,63 @@ pub struct Clustering {
     /// track id to cluster id
     track_id_to_cluster_id: HashMap<Uuid, usize>,
 
-    /// available cluster ids
-    cluster_id_queue: VecDeque<usize>,
+    /// freed cluster ids with the timestamp they were freed, oldest first
+    cluster_id_queue: VecDeque<(u64, usize)>,
 
     /// max_cluster_id
     cluster_id_max: usize,
+
+    /// Nanoseconds a freed cluster id is held back before it is reused
+    cluster_id_cooldown: u64,
+
+    /// Largest cluster id when ids are not recycled, None recycles freed ids
+    cluster_id_wrap: Option<usize>,
+
+    /// Associations before the cluster id of a track is published
+    track_min_hits: usize,
+
+    /// Missed frames a confirmed track coasts before it is dropped
+    track_max_coast: Option<usize>,
+
+    /// Publish the negated cluster id of unconfirmed tracks instead of 0
+    provisional_ids: bool,
+
+    /// Association count and missed frames of each track
+    coast: HashMap<Uuid, (i32, usize)>,
+
+    /// Worker pool for the per-point clustering work
+    pool: Pool,
+
+    /// Azimuth sectors clustered independently, 1 clusters all targets at once
+    parallel_sectors: usize,
+
+    /// Role of each point of the last clustering cycle
+    roles: Vec<PointRole>,
+
+    /// Runtime of the last clustering cycle
+    runtime: ClusterRuntime,
+
+    /// Recorded cycles between the path points of a track, 0 disables the
+    /// track history
+    track_history: usize,
+
+    /// Largest number of path points of a track
+    track_history_max_points: usize,
+
+    /// Largest number of completed tracks kept
+    track_history_max_tracks: usize,
+
+    /// History of the live confirmed tracks
+    live_tracks: HashMap<Uuid, LiveTrack>,
+
+    /// Tracks which ended in the last clustering cycle
+    ended_tracks: Vec<TrackRecord>,
+
+    /// Completed tracks not yet drained, oldest first
+    completed_tracks: VecDeque<TrackRecord>,
+
+    /// Completed tracks dropped from a full completed_tracks
+    dropped_tracks: u64,
 }
 
 impl Clustering {
@@ -66,14 +963,175 @@ impl Clustering {
             clustering_eps,
             clustering_param_scale,
             clustering_point_limit,
+            mode: ClusteringMode::Standard,
             tracker: ByteTrack::new(),
             track_settings: TrackSettings::default(),
             track_id_to_cluster_id: HashMap::new(),
             cluster_id_queue: VecDeque::new(),
             cluster_id_max: 0,
+            cluster_id_cooldown: 0,
+            cluster_id_wrap: None,
+            track_min_hits: 1,
+            track_max_coast: None,
+            provisional_ids: false,
+            coast: HashMap::new(),
+            pool: Pool::default(),
+            parallel_sectors: 1,
+            roles: Vec::new(),
+            runtime: ClusterRuntime::default(),
+            track_history: 0,
+            track_history_max_points: TRACK_HISTORY_MAX_POINTS,
+            track_history_max_tracks: TRACK_HISTORY_MAX_TRACKS,
+            live_tracks: HashMap::new(),
+            ended_tracks: Vec::new(),
+            completed_tracks: VecDeque::new(),
+            dropped_tracks: 0,
+        }
+    }
+
+    /// Set the number of worker threads used for clustering.
+    ///
+    /// Requires the `rayon` feature, otherwise clustering always runs on the
+    /// calling thread.  A value of 0 uses one thread per CPU core and 1
+    /// disables parallelism.  The output is identical for any thread count.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.pool = Pool::new(threads);
+    }
+
+    /// Set the clustering mode used by [`Clustering::cluster`].
+    pub fn set_mode(&mut self, mode: ClusteringMode) {
+        self.mode = mode;
+    }
+
+    /// DBSCAN epsilon of the next clustering cycles.
+    pub fn eps(&self) -> f64 {
+        self.clustering_eps
+    }
+
+    /// Set the DBSCAN epsilon, which can change between clustering cycles
+    /// such as for the range resolution of the current frequency sweep.
+    pub fn set_eps(&mut self, eps: f64) {
+        self.clustering_eps = eps;
+    }
+
+    /// Set the non-maximum suppression threshold of the cluster boxes.
+    ///
+    /// When two objects separate DBSCAN briefly produces overlapping cluster
+    /// boxes for what the tracker follows as a single object.  Boxes which
+    /// overlap a kept box by more than this iou are merged into its cluster
+    /// before tracking so no duplicate tracks are created.  A value of 1.0,
+    /// the default, disables suppression.
+    pub fn set_nms_iou_threshold(&mut self, nms_iou_threshold: f32) {
+        self.track_settings.nms_iou_threshold = nms_iou_threshold;
+    }
+
+    /// Set the minimum number of points for a cluster to be tracked.
+    ///
+    /// DBSCAN only requires the core points to have `point_limit`
+    /// neighbours, so a cluster can be as small as a handful of noisy
+    /// targets.  Clusters with fewer points are not passed to the tracker and
+    /// their points are reported as noise (cluster id 0).  The default of 1
+    /// tracks every cluster.
+    pub fn set_min_cluster_size(&mut self, min_cluster_size: usize) {
+        self.track_settings.min_cluster_size_after_tracking = min_cluster_size;
+    }
+
+    /// Set the number of associations before the cluster id of a track is
+    /// published.
+    ///
+    /// A specular flicker forms a cluster for a frame or two, the default of
+    /// 1 publishes it under a fresh cluster id which downstream treats as a
+    /// new object.  The points of an unconfirmed track get cluster id 0, or
+    /// the negated cluster id with [`Clustering::set_provisional_ids`], and
+    /// the track is left out of [`Clustering::associate`] and
+    /// [`Clustering::tracks`].  A track is confirmed on the frame of its
+    /// `track_min_hits` association and stays confirmed.
+    pub fn set_track_min_hits(&mut self, track_min_hits: usize) {
+        self.track_min_hits = track_min_hits;
+    }
+
+    /// Set the number of consecutive missed frames a confirmed track may
+    /// coast on its prediction before it is dropped, None keeps it for the
+    /// track lifespan.  A dropped object which reappears starts a new track
+    /// which has to be confirmed again.
+    pub fn set_track_max_coast(&mut self, track_max_coast: Option<usize>) {
+        self.track_max_coast = track_max_coast;
+    }
+
+    /// Publish the points of unconfirmed tracks with the negated cluster id
+    /// the track will keep once confirmed, instead of 0.
+    pub fn set_provisional_ids(&mut self, provisional_ids: bool) {
+        self.provisional_ids = provisional_ids;
+    }
+
+    /// Set how long a freed cluster id is held back before it is assigned
+    /// to a new track.
+    ///
+    /// The cluster id of a track is freed when the track ends.  Reassigning
+    /// it on the next frame hands a different object the id downstream
+    /// consumers keyed their state by, so freed ids are quarantined for the
+    /// cooldown, measured on the clustering timestamps.  A zero cooldown,
+    /// the [`Clustering::new`] default, reuses ids as soon as they are freed.
+    pub fn set_cluster_id_cooldown(&mut self, cooldown: Duration) {
+        self.cluster_id_cooldown = cooldown.as_nanos() as u64;
+    }
+
+    /// Assign monotonically increasing cluster ids which wrap around to 1
+    /// after `wrap` instead of recycling freed ids, None recycles.  After
+    /// wrapping the ids still held by a track are skipped.
+    pub fn set_cluster_id_wrap(&mut self, wrap: Option<usize>) {
+        self.cluster_id_wrap = wrap;
+    }
+
+    /// Record a [`TrackRecord`] of every confirmed track through
+    /// [`Clustering::record_history`], with a path point every `decimation`
+    /// recorded cycles.  0, the default, disables the track history.
+    pub fn set_track_history(&mut self, decimation: usize) {
+        self.track_history = decimation;
+        if decimation == 0 {
+            self.live_tracks.clear();
         }
     }
 
+    /// Set the largest number of path points of a track.
+    ///
+    /// A path reaching the limit drops every other point and doubles the
+    /// decimation of the track, so a long track keeps a coarser polyline of
+    /// its whole route rather than only its most recent part.
+    pub fn set_track_history_max_points(&mut self, max_points: usize) {
+        self.track_history_max_points = max_points.max(2);
+    }
+
+    /// Set the largest number of completed tracks kept until they are
+    /// drained, the oldest are dropped first.
+    pub fn set_track_history_max_tracks(&mut self, max_tracks: usize) {
+        self.track_history_max_tracks = max_tracks;
+    }
+
+    /// Set the number of azimuth sectors clustered independently.
+    ///
+    /// Large clustering windows make a single DBSCAN pass slow enough to
+    /// back up the clustering channel.  With more than one sector the
+    /// azimuth span of the targets is split into equal sectors which run
+    /// DBSCAN in parallel on the worker pool, and clusters with points within
+    /// eps of each other across a sector boundary are merged before tracking.
+    /// A point which is only dense enough with the neighbours across the
+    /// boundary can end up as noise.  Values of 0 and 1 disable the split.
+    pub fn set_parallel_sectors(&mut self, parallel_sectors: usize) {
+        self.parallel_sectors = parallel_sectors;
+    }
+
+    /// Map every item through `f` on the clustering worker pool, preserving
+    /// the input order.
+    pub fn map<T, U, F>(&self, items: &[T], f: F) -> Vec<U>
+    where
+        T: Sync,
+        U: Send,
+        F: Fn(&T) -> U + Sync + Send,
+    {
+        self.pool.map(items, f)
+    }
+
     /// Clusters radar points. Radar points should be given as a list of tuples
     /// of 4 elements [(x, y, z, speed), (x, y, z, speed), ...]
     ///
@@ -81,42 +1139,179 @@ impl Clustering {
     /// [(x, y, z, speed, cluster_id), (x, y, z, speed, cluster_id), ...]
     /// Points with a cluster_id = 0 are noise. Otherwise points with the same
     /// cluster_id are in the same cluster
+    ///
+    /// In [`ClusteringMode::VelocityGated`] mode this runs
+    /// [`Clustering::cluster_velocity_gated`], in [`ClusteringMode::Single`]
+    /// mode all targets are labelled cluster 1 before tracking.
     pub fn cluster(&mut self, targets: Vec<[f32; 4]>, timestamp: u64) -> Vec<[f32; 5]> {
-        let dbscantargets: Vec<Vec<f32>> = targets
-            .iter()
-            .map(|t| {
-                let mut v = Vec::from(t);
-                for (i, val) in v.iter_mut().enumerate() {
-                    *val *= self.clustering_param_scale[i];
-                }
-                v
-            })
-            .collect();
-        let dbscan_clusters =
-            Model::new(self.clustering_eps, self.clustering_point_limit).run(&dbscantargets);
-        // do some tracking to keep cluster_ids consistent across different runs
+        let start = Instant::now();
+        let (labels, roles) = match self.mode {
+            ClusteringMode::Standard => self.dbscan(&targets),
+            ClusteringMode::VelocityGated {
+                separation_threshold_mps,
+            } => return self.cluster_velocity_gated(targets, separation_threshold_mps, timestamp),
+            ClusteringMode::Single => {
+                (vec![1; targets.len()], vec![PointRole::Core; targets.len()])
+            }
+        };
+        self.runtime.dbscan = start.elapsed();
+        self.track(&targets, &labels, roles, timestamp)
+    }
 
-        let mut data: Vec<_> = targets
-            .iter()
-            .zip(dbscan_clusters.iter())
-            .map(|(target, cluster)| {
-                let cluster_id = match cluster {
+    /// Roles of the points returned by the last clustering cycle, in the same
+    /// order.  Points of clusters the tracker reported as noise are
+    /// [`PointRole::Noise`] so the role is only 0 when the cluster_id is 0,
+    /// in [`ClusteringMode::Single`] mode every clustered point is
+    /// [`PointRole::Core`].
+    pub fn point_roles(&self) -> &[PointRole] {
+        &self.roles
+    }
+
+    /// Runtime of the stages of the last clustering cycle.
+    pub fn runtime(&self) -> ClusterRuntime {
+        self.runtime
+    }
+
+    /// Number of active tracklets, including tracks coasting without a
+    /// cluster.
+    pub fn tracklet_count(&self) -> usize {
+        self.tracker.get_tracklets().len()
+    }
+
+    /// Clusters radar points separately per velocity band so approaching and
+    /// receding targets are never merged into one cluster, even when they are
+    /// spatially close.
+    ///
+    /// Targets slower than `separation_threshold_mps` in either direction form
+    /// the stationary band, faster targets are split by the sign of their
+    /// radial speed.  The speed of the targets must be in m/s, the speed scale
+    /// is only applied for DBSCAN.  The clusters of all bands share the
+    /// tracker, which re-merges the per band cluster ids into the persistent
+    /// cluster ids so an object keeps its id when it moves between bands.
+    ///
+    /// The input and output formats are the same as [`Clustering::cluster`].
+    pub fn cluster_velocity_gated(
+        &mut self,
+        targets: Vec<[f32; 4]>,
+        separation_threshold_mps: f32,
+        timestamp: u64,
+    ) -> Vec<[f32; 5]> {
+        let band = |speed: f32| {
+            if speed < -separation_threshold_mps {
+                0
+            } else if speed > separation_threshold_mps {
+                2
+            } else {
+                1
+            }
+        };
+
+        let start = Instant::now();
+        let mut labels = vec![0; targets.len()];
+        let mut roles = vec![PointRole::Noise; targets.len()];
+        let mut offset = 0;
+        for b in 0..3 {
+            let indices: Vec<_> = (0..targets.len())
+                .filter(|i| band(targets[*i][3]) == b)
+                .collect();
+            let subset: Vec<_> = indices.iter().map(|i| targets[*i]).collect();
+            let (band_labels, band_roles) = self.dbscan(&subset);
+
+            for ((i, label), role) in indices.iter().zip(band_labels.iter()).zip(band_roles) {
+                if *label != 0 {
+                    labels[*i] = label + offset;
+                }
+                roles[*i] = role;
+            }
+            offset += band_labels.iter().max().copied().unwrap_or(0);
+        }
+
+        self.runtime.dbscan = start.elapsed();
+        self.track(&targets, &labels, roles, timestamp)
+    }
+
+    /// Davies-Bouldin index of a single DBSCAN pass over the targets for each
+    /// eps, for tuning `clustering_eps`.
+    ///
+    /// Neither the tracker nor the clustering mode are involved, the targets
+    /// are in the same format as for [`Clustering::cluster`].  The eps
+    /// giving the lowest index, or the elbow of the curve, separates the
+    /// objects of the scene best.
+    pub fn eps_sweep(&self, targets: &[[f32; 4]], eps: &[f64]) -> Vec<f32> {
+        eps.iter()
+            .map(|&eps| {
+                let (labels, _) = self.dbscan_eps(targets, eps);
+                davies_bouldin_index(targets, &labels)
+            })
+            .collect()
+    }
+
+    /// Run DBSCAN on the scaled targets.
+    ///
+    /// # Returns
+    /// The DBSCAN cluster label of each target starting at 1, 0 is noise, and
+    /// the role of each target.
+    fn dbscan(&self, targets: &[[f32; 4]]) -> (Vec<usize>, Vec<PointRole>) {
+        self.dbscan_eps(targets, self.clustering_eps)
+    }
+
+    fn dbscan_eps(&self, targets: &[[f32; 4]], eps: f64) -> (Vec<usize>, Vec<PointRole>) {
+        let dbscantargets = self.pool.map(targets, |t| {
+            let mut v = *t;
+            for (i, val) in v.iter_mut().enumerate() {
+                *val *= self.clustering_param_scale[i];
+            }
+            v
+        });
+        let dbscan = Dbscan::new(eps, self.clustering_point_limit);
+        let classes = match self.parallel_sectors > 1 {
+            true => {
+                let azimuths: Vec<_> = targets.iter().map(|t| t[1].atan2(t[0])).collect();
+                dbscan.run_sectors(&dbscantargets, &azimuths, self.parallel_sectors, &self.pool)
+            }
+            false => dbscan.run(&dbscantargets, &self.pool),
+        };
+        classes
+            .into_iter()
+            .map(|cluster| {
+                let label = match cluster {
                     Classification::Core(i) => i + 1,
                     Classification::Edge(i) => i + 1,
                     Classification::Noise => 0,
                 };
+                (label, PointRole::from(cluster))
+            })
+            .unzip()
+    }
+
+    /// Track the DBSCAN clusters to keep cluster_ids consistent across
+    /// different runs.
+    fn track(
+        &mut self,
+        targets: &[[f32; 4]],
+        labels: &[usize],
+        mut roles: Vec<PointRole>,
+        timestamp: u64,
+    ) -> Vec<[f32; 5]> {
+        let start = Instant::now();
+        let mut data: Vec<_> = targets
+            .iter()
+            .zip(labels.iter())
+            .map(|(target, cluster_id)| {
                 [
                     target[0],
                     target[1],
                     target[2],
                     target[3],
-                    cluster_id as f32,
+                    *cluster_id as f32,
                 ]
             })
             .collect();
 
         let mut boxes = Vec::new();
-        let mut clusters = HashMap::new();
+        let mut discarded = HashSet::new();
+        // Ordered by DBSCAN cluster so new track ids are assigned deterministically.
+        let mut clusters = BTreeMap::new();
         for p in data.iter() {
             let id = p[4] as usize;
             clusters.entry(id).or_insert_with(Vec::new);
@@ -129,6 +1324,10 @@ impl Clustering {
             if cluster.is_empty() {
                 continue;
             }
+            if cluster.len() < self.track_settings.min_cluster_size_after_tracking {
+                discarded.insert(id);
+                continue;
+            }
             let mut xmin = 9999999.9;
             let mut xmax = -9999999.9;
             let mut ymin = 9999999.9;
@@ -171,9 +1370,29 @@ impl Clustering {
             //     label: id as i32,
             // });
         }
+
+        // DBSCAN cluster id of every suppressed box to the cluster id of the
+        // kept box it overlaps the most.
+        let mut suppressed = Vec::new();
+        if self.track_settings.nms_iou_threshold < 1.0 {
+            let candidates = boxes.clone();
+            let kept = non_maximum_suppression(&mut boxes, self.track_settings.nms_iou_threshold);
+            for (i, candidate) in candidates.iter().enumerate() {
+                if kept.binary_search(&i).is_ok() {
+                    continue;
+                }
+                let merged = boxes
+                    .iter()
+                    .max_by(|a, b| iou(candidate, a).total_cmp(&iou(candidate, b)))
+                    .unwrap();
+                suppressed.push((candidate.label, merged.label));
+            }
+        }
+
         let trackinfo = self
             .tracker
             .update(&self.track_settings, &mut boxes, timestamp);
+        self.coast();
         let mut old_to_new = HashMap::new();
         for (ind, info) in trackinfo.into_iter().enumerate() {
             if info.is_none() {
@@ -183,44 +1402,358 @@ impl Clustering {
             let old_cluster_id = boxes[ind].label;
             let new_cluster_id = match self.track_id_to_cluster_id.get(&info.uuid) {
                 None => {
-                    let new_id = self.get_new_cluster_id();
+                    let new_id = self.get_new_cluster_id(timestamp);
                     self.track_id_to_cluster_id.insert(info.uuid, new_id);
                     new_id
                 }
                 Some(v) => *v,
             };
             // let new_cluster_id = (info.uuid.as_u128() % 32) as i32;
+            let new_cluster_id = match info.count as usize >= self.track_min_hits {
+                true => new_cluster_id as f32,
+                false if self.provisional_ids => -(new_cluster_id as f32),
+                false => 0.0,
+            };
             old_to_new.insert(old_cluster_id, new_cluster_id);
         }
+        for (old_cluster_id, merged_cluster_id) in suppressed {
+            old_to_new.insert(old_cluster_id, old_to_new[&merged_cluster_id]);
+        }
         for d in data.iter_mut() {
             if d[4] == 0.0 {
                 continue;
             }
-            d[4] = old_to_new[&(d[4] as i32)] as f32;
+            if discarded.contains(&(d[4] as usize)) {
+                d[4] = 0.0;
+                continue;
+            }
+            d[4] = old_to_new[&(d[4] as i32)];
         }
 
         let mut remove_track: HashSet<_> = self.track_id_to_cluster_id.keys().cloned().collect();
         for tracklet in self.tracker.get_tracklets() {
             let _ = remove_track.remove(&tracklet.id);
         }
-        for track_id in remove_track {
-            let cluster_id = self.track_id_to_cluster_id.remove(&track_id);
-            if let Some(v) = cluster_id {
-                self.cluster_id_queue.push_back(v);
+        self.ended_tracks.clear();
+        let mut ended: Vec<_> = remove_track
+            .iter()
+            .filter_map(|track_id| self.live_tracks.remove(track_id))
+            .map(|track| track.record)
+            .collect();
+        ended.sort_by_key(|record| record.cluster_id);
+        for record in ended {
+            self.complete_track(record);
+        }
+        // Recycle in cluster id order so reuse does not depend on hash order.
+        let mut freed: Vec<_> = remove_track
+            .iter()
+            .filter_map(|track_id| self.track_id_to_cluster_id.remove(track_id))
+            .collect();
+        freed.sort_unstable();
+        if self.cluster_id_wrap.is_none() {
+            self.cluster_id_queue
+                .extend(freed.into_iter().map(|id| (timestamp, id)));
+        }
+
+        for (role, d) in roles.iter_mut().zip(data.iter()) {
+            if d[4] == 0.0 {
+                *role = PointRole::Noise;
             }
         }
+        self.roles = roles;
+        self.runtime.tracker = start.elapsed();
         data
     }
 
-    fn get_new_cluster_id(&mut self) -> usize {
-        if self.cluster_id_queue.is_empty() {
-            self.cluster_id_max += 1;
-            self.cluster_id_max
-        } else {
-            self.cluster_id_queue.pop_front().unwrap()
+    /// Count the consecutive frames each track was not associated and drop
+    /// the confirmed tracks which coasted longer than the track_max_coast.
+    fn coast(&mut self) {
+        let coast = self
+            .tracker
+            .get_tracklets()
+            .iter()
+            .map(|t| {
+                let missed = match self.coast.get(&t.id) {
+                    Some((count, missed)) if *count == t.count => missed + 1,
+                    _ => 0,
+                };
+                (t.id, (t.count, missed))
+            })
+            .collect();
+        self.coast = coast;
+
+        if let Some(max_coast) = self.track_max_coast {
+            let coast = &self.coast;
+            let min_hits = self.track_min_hits;
+            self.tracker
+                .tracklets
+                .retain(|t| (t.count as usize) < min_hits || coast[&t.id].1 <= max_coast);
+        }
+    }
+
+    /// Returns true once the track has been associated track_min_hits times.
+    fn confirmed(&self, tracklet: &Tracklet) -> bool {
+        tracklet.count as usize >= self.track_min_hits
+    }
+
+    fn get_new_cluster_id(&mut self, timestamp: u64) -> usize {
+        if let Some(wrap) = self.cluster_id_wrap {
+            return self.get_wrapped_cluster_id(wrap);
+        }
+        match self.cluster_id_queue.front() {
+            Some((freed, _)) if timestamp >= freed.saturating_add(self.cluster_id_cooldown) => {
+                self.cluster_id_queue.pop_front().unwrap().1
+            }
+            _ => {
+                self.cluster_id_max += 1;
+                self.cluster_id_max
+            }
+        }
+    }
+
+    fn get_wrapped_cluster_id(&mut self, wrap: usize) -> usize {
+        let in_use: HashSet<_> = self.track_id_to_cluster_id.values().copied().collect();
+        for _ in 0..wrap.max(1) {
+            self.cluster_id_max = match self.cluster_id_max >= wrap {
+                true => 1,
+                false => self.cluster_id_max + 1,
+            };
+            if !in_use.contains(&self.cluster_id_max) {
+                break;
+            }
+        }
+        self.cluster_id_max
+    }
+
+    /// Predict the tracks over radar frames which were never clustered, see
+    /// [`missed_frames`].
+    pub fn advance_missing_frames(&mut self, frames: usize) {
+        for (_, missed) in self.coast.values_mut() {
+            *missed += frames;
+        }
+        for _ in 0..frames {
+            ByteTrack::advance_missing_frame(&mut self.tracker.tracklets);
+        }
+    }
+
+    /// JSON object of the tracker state and the cluster id of every track,
+    /// see [`Clustering::restore_tracker_state`].
+    pub fn tracker_state(&self) -> Value {
+        let cluster_ids: serde_json::Map<_, _> = self
+            .track_id_to_cluster_id
+            .iter()
+            .map(|(uuid, id)| (uuid.to_string(), json!(id)))
+            .collect();
+        json!({
+            "tracker": self.tracker.serialize_state(),
+            "cluster_ids": cluster_ids,
+            "cluster_id_max": self.cluster_id_max,
+        })
+    }
+
+    /// Replace the tracks with those of a [`Clustering::tracker_state`],
+    /// keeping their cluster ids.
+    ///
+    /// The tracks continue as if the last saved cycle had been clustered at
+    /// `timestamp`, so the following cycle associates them as usual.  The
+    /// track history starts over with the restored tracks.
+    ///
+    /// # Returns
+    /// False, leaving the tracks untouched, if the state is malformed
+    pub fn restore_tracker_state(&mut self, state: &Value, timestamp: u64) -> bool {
+        let Some(mut tracker) = ByteTrack::restore_state(&state["tracker"]) else {
+            return false;
+        };
+        let Some(cluster_ids) = state["cluster_ids"].as_object() else {
+            return false;
+        };
+        let mut track_id_to_cluster_id = HashMap::new();
+        for (uuid, id) in cluster_ids {
+            match (Uuid::parse_str(uuid), id.as_u64()) {
+                (Ok(uuid), Some(id)) => {
+                    track_id_to_cluster_id.insert(uuid, id as usize);
+                }
+                _ => return false,
+            }
+        }
+        // A cluster id without its track could never be freed.
+        track_id_to_cluster_id.retain(|uuid, _| tracker.tracklets.iter().any(|t| t.id == *uuid));
+        let Some(cluster_id_max) = state["cluster_id_max"].as_u64() else {
+            return false;
+        };
+
+        tracker.rebase(timestamp);
+        self.cluster_id_max = track_id_to_cluster_id
+            .values()
+            .copied()
+            .fold(cluster_id_max as usize, usize::max);
+        self.tracker = tracker;
+        self.track_id_to_cluster_id = track_id_to_cluster_id;
+        self.cluster_id_queue.clear();
+        self.coast.clear();
+        self.live_tracks.clear();
+        self.ended_tracks.clear();
+        true
+    }
+
+    /// Capture the predicted box of every confirmed track which has been
+    /// assigned a cluster id.
+    ///
+    /// # Arguments
+    /// * `cycle_counter` - Radar cycle counter of the frame just clustered
+    ///
+    /// # Returns
+    /// Track association used to annotate subsequent radar frames.
+    pub fn associate(&self, cycle_counter: u32) -> TrackAssociation {
+        let boxes = self
+            .tracker
+            .get_tracklets()
+            .iter()
+            .filter(|t| self.confirmed(t))
+            .filter_map(|t| {
+                let id = self.track_id_to_cluster_id.get(&t.id)?;
+                let b = t.get_predicted_location();
+                Some(([b.xmin, b.ymin, b.xmax, b.ymax], *id))
+            })
+            .collect();
+
+        TrackAssociation {
+            cycle_counter,
+            boxes,
+        }
+    }
+
+    /// Confirmed tracks which have been assigned a cluster id, ordered by
+    /// cluster id.
+    pub fn tracks(&self) -> Vec<ClusterTrack> {
+        let mut tracks: Vec<_> = self
+            .tracker
+            .get_tracklets()
+            .iter()
+            .filter(|t| self.confirmed(t))
+            .filter_map(|t| {
+                let cluster_id = *self.track_id_to_cluster_id.get(&t.id)?;
+                let b = t.get_predicted_location();
+                Some(ClusterTrack {
+                    cluster_id,
+                    uuid: t.id,
+                    bbox: [b.xmin, b.ymin, b.xmax, b.ymax],
+                    count: t.count,
+                    created: t.created,
+                })
+            })
+            .collect();
+        tracks.sort_by_key(|t| t.cluster_id);
+        tracks
+    }
+
+    /// Record the tracks of the last clustering cycle in the track history,
+    /// see [`Clustering::set_track_history`].
+    ///
+    /// Only tracks with points in the cycle are recorded.  The path point of
+    /// a cycle is the cluster centroid, the first cycle of a track always
+    /// adds one.  A track is completed when it ends, see
+    /// [`Clustering::ended_tracks`].
+    ///
+    /// # Arguments
+    /// * `timestamp` - Timestamp in nanoseconds of the clustering cycle
+    /// * `tracks` - The tracks of the cycle, see [`Clustering::tracks`]
+    /// * `summaries` - The clusters of the cycle ordered by id, see
+    ///   [`cluster_summaries`]
+    /// * `classes` - The class of each track, unknown when None
+    pub fn record_history(
+        &mut self,
+        timestamp: u64,
+        tracks: &[ClusterTrack],
+        summaries: &[ClusterSummary],
+        classes: Option<&[(ObjectClass, f32)]>,
+    ) {
+        if self.track_history == 0 {
+            return;
+        }
+        for (i, track) in tracks.iter().enumerate() {
+            let summary = match summaries.binary_search_by_key(&track.cluster_id, |s| s.id) {
+                Ok(j) => &summaries[j],
+                Err(_) => continue,
+            };
+            let live = self.live_tracks.entry(track.uuid).or_insert(LiveTrack {
+                record: TrackRecord {
+                    cluster_id: track.cluster_id,
+                    uuid: track.uuid,
+                    first_seen: timestamp,
+                    last_seen: timestamp,
+                    path: Vec::new(),
+                    max_speed: 0.0,
+                    class: ObjectClass::Unknown,
+                },
+                cycles: 0,
+                stride: self.track_history,
+            });
+
+            let record = &mut live.record;
+            record.last_seen = timestamp;
+            record.max_speed = record.max_speed.max(summary.velocity.abs());
+            if let Some(classes) = classes {
+                record.class = classes[i].0;
+            }
+            if live.cycles % live.stride == 0 {
+                if record.path.len() >= self.track_history_max_points {
+                    let mut j = 0;
+                    record.path.retain(|_| {
+                        j += 1;
+                        j % 2 == 1
+                    });
+                    live.stride *= 2;
+                }
+                let [x, y, _] = summary.centroid;
+                record.path.push([x, y]);
+            }
+            live.cycles += 1;
+        }
+    }
+
+    /// Tracks of the track history completed by the last clustering cycle,
+    /// or by [`Clustering::complete_live_tracks`] if it was called since.
+    pub fn ended_tracks(&self) -> &[TrackRecord] {
+        &self.ended_tracks
+    }
+
+    /// Remove and return the completed tracks, oldest first.
+    pub fn drain_completed_tracks(&mut self) -> Vec<TrackRecord> {
+        self.completed_tracks.drain(..).collect()
+    }
+
+    /// Number of completed tracks dropped because more than the
+    /// [`Clustering::set_track_history_max_tracks`] were not drained.
+    pub fn dropped_tracks(&self) -> u64 {
+        self.dropped_tracks
+    }
+
+    /// Complete the history of every live track, such as at shutdown.  The
+    /// tracks themselves are kept, one which is recorded again starts a new
+    /// record.
+    pub fn complete_live_tracks(&mut self) {
+        self.ended_tracks.clear();
+        let mut live: Vec<_> = self.live_tracks.drain().map(|(_, t)| t.record).collect();
+        live.sort_by_key(|record| record.first_seen);
+        for record in live {
+            self.complete_track(record);
         }
     }
 
+    fn complete_track(&mut self, record: TrackRecord) {
+        self.ended_tracks.push(record.clone());
+        if self.track_history_max_tracks == 0 {
+            self.dropped_tracks += 1;
+            return;
+        }
+        if self.completed_tracks.len() >= self.track_history_max_tracks {
+            self.completed_tracks.pop_front();
+            self.dropped_tracks += 1;
+        }
+        self.completed_tracks.push_back(record);
+    }
+
     /// Retrieve current tracked object locations in bounding box format.
     ///
     /// Used for debugging and visualization. Converts internal Kalman filter
@@ -239,3 +1772,1258 @@ impl Clustering {
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob(x: f32, y: f32) -> Vec<[f32; 4]> {
+        (0..6)
+            .map(|i| [x + 0.1 * i as f32, y + 0.05 * i as f32, 0.0, 0.0])
+            .collect()
+    }
+
+    /// Dense pseudo-random scene of clumped points with a fixed seed.
+    fn scene(n: usize, seed: u64) -> Vec<[f32; 4]> {
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 40) as f32 / (1u64 << 24) as f32
+        };
+
+        (0..n)
+            .map(|_| {
+                let cx = (next() * 10.0).floor() * 4.0;
+                let cy = (next() * 10.0).floor() * 4.0 - 20.0;
+                [cx + next(), cy + next(), next(), next() * 2.0 - 1.0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_threads_deterministic() {
+        let mut serial = Clustering::new(0.5, &[1.0, 1.0, 0.0, 0.0], 3);
+        serial.set_threads(1);
+        let mut parallel = Clustering::new(0.5, &[1.0, 1.0, 0.0, 0.0], 3);
+        parallel.set_threads(4);
+
+        for frame in 0..5 {
+            let points = scene(600, frame);
+            let timestamp = frame * 55_000_000;
+            assert_eq!(
+                serial.cluster(points.clone(), timestamp),
+                parallel.cluster(points, timestamp)
+            );
+        }
+    }
+
+    /// Replays testdata/clustering_replay.log through the CAN parser and the
+    /// clustering pipeline and compares the cluster ids and centroids of every
+    /// frame against testdata/clustering_golden.json.  Set RADARPUB_BLESS=1
+    /// to regenerate the golden file after an intentional behaviour change.
+    #[cfg(feature = "can")]
+    #[test]
+    fn test_golden_replay() {
+        use crate::{
+            can::{read_message, CanIds, CanInjector},
+            common::transform_xyz,
+        };
+        use serde_json::{json, Value};
+        use std::{fs::File, io::BufReader};
+
+        let log = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/clustering_replay.log"
+        );
+        let golden = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/clustering_golden.json"
+        );
+
+        let can = CanInjector::from_candump(BufReader::new(File::open(log).unwrap())).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        clustering.set_threads(1);
+
+        let round = |v: f32| (v as f64 * 1e4).round() / 1e4;
+        let mut frames = Vec::new();
+        while can.pending() > 0 {
+            let frame = runtime
+                .block_on(read_message(&can, &CanIds::default()))
+                .unwrap();
+            let points = frame.targets[..frame.header.n_targets]
+                .iter()
+                .map(|t| {
+                    let [x, y, z] =
+                        transform_xyz(t.range as f32, t.azimuth as f32, t.elevation as f32, false);
+                    [x, y, z, t.speed as f32]
+                })
+                .collect();
+
+            let timestamp = 1_000_000_000 + frame.header.cycle_counter as u64 * 55_000_000;
+            let clusters = clustering.cluster(points, timestamp);
+
+            let mut centroids = BTreeMap::<usize, (f32, f32, f32)>::new();
+            for p in clusters.iter().filter(|p| p[4] != 0.0) {
+                let c = centroids.entry(p[4] as usize).or_default();
+                *c = (c.0 + p[0], c.1 + p[1], c.2 + 1.0);
+            }
+
+            frames.push(json!({
+                "cycle_counter": frame.header.cycle_counter,
+                "cluster_ids": clusters.iter().map(|p| p[4] as usize).collect::<Vec<_>>(),
+                "centroids": centroids
+                    .iter()
+                    .map(|(id, (x, y, n))| (id.to_string(), json!([round(x / n), round(y / n)])))
+                    .collect::<serde_json::Map<_, _>>(),
+            }));
+        }
+
+        if std::env::var("RADARPUB_BLESS").is_ok() {
+            let text = serde_json::to_string_pretty(&json!({ "frames": frames })).unwrap();
+            std::fs::write(golden, text + "\n").unwrap();
+            return;
+        }
+
+        let expected: Value = serde_json::from_reader(File::open(golden).unwrap()).unwrap();
+        let expected = expected["frames"].as_array().unwrap();
+        assert_eq!(frames.len(), expected.len());
+
+        for (actual, expected) in frames.iter().zip(expected) {
+            let cycle = &expected["cycle_counter"];
+            assert_eq!(actual["cycle_counter"], *cycle);
+            assert_eq!(
+                actual["cluster_ids"], expected["cluster_ids"],
+                "cluster ids of frame {}",
+                cycle
+            );
+
+            let actual = actual["centroids"].as_object().unwrap();
+            let expected = expected["centroids"].as_object().unwrap();
+            assert_eq!(
+                actual.keys().collect::<Vec<_>>(),
+                expected.keys().collect::<Vec<_>>(),
+                "clusters of frame {}",
+                cycle
+            );
+            for (id, centroid) in expected {
+                for axis in 0..2 {
+                    let a = actual[id][axis].as_f64().unwrap();
+                    let e = centroid[axis].as_f64().unwrap();
+                    assert!(
+                        (a - e).abs() < 1e-3,
+                        "centroid {} of frame {} drifted: {} != {}",
+                        id,
+                        cycle,
+                        a,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_vertical_separation() {
+        // A vehicle and a person on a platform 2 m above it at the same
+        // ground position.
+        let mut points = blob(10.0, 2.0);
+        points.extend(
+            blob(10.0, 2.0)
+                .into_iter()
+                .map(|[x, y, _, speed]| [x, y, 2.0, speed]),
+        );
+
+        let ids = |clusters: &[[f32; 5]]| {
+            clusters
+                .iter()
+                .map(|p| p[4] as usize)
+                .collect::<HashSet<_>>()
+        };
+
+        // Ignoring z merges the stacked objects.
+        let mut flat = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let clusters = flat.cluster(points.clone(), 1_000_000_000);
+        assert_eq!(ids(&clusters).len(), 1);
+        assert_ne!(clusters[0][4], 0.0);
+
+        // With z every level is its own cluster.
+        let mut stacked = Clustering::new(1.0, &[1.0, 1.0, 1.0, 0.0], 3);
+        let clusters = stacked.cluster(points.clone(), 1_000_000_000);
+        let (ground, platform) = clusters.split_at(6);
+        assert_ne!(ground[0][4], 0.0);
+        assert_ne!(platform[0][4], 0.0);
+        assert_ne!(ground[0][4], platform[0][4]);
+        assert!(ground.iter().all(|p| p[4] == ground[0][4]));
+        assert!(platform.iter().all(|p| p[4] == platform[0][4]));
+
+        // Scaled down below eps the levels merge again.
+        let mut squashed = Clustering::new(1.0, &[1.0, 1.0, 0.25, 0.0], 3);
+        let clusters = squashed.cluster(points.clone(), 1_000_000_000);
+        assert_eq!(ids(&clusters).len(), 1);
+
+        // The tracker boxes are in the ground plane, both levels keep a
+        // track on the next frame.
+        let first = ids(&stacked.cluster(points.clone(), 1_055_000_000));
+        let next = ids(&stacked.cluster(points, 1_110_000_000));
+        assert_eq!(first.len(), 2);
+        assert_eq!(next, first);
+    }
+
+    #[test]
+    fn test_velocity_gated() {
+        // Approaching and receding groups within eps of each other.
+        let mut points: Vec<_> = blob(10.0, 2.0)
+            .into_iter()
+            .map(|[x, y, z, _]| [x, y, z, -3.0])
+            .collect();
+        points.extend(
+            blob(10.0, 2.8)
+                .into_iter()
+                .map(|[x, y, z, _]| [x, y, z, 3.0]),
+        );
+
+        let mut standard = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let clusters = standard.cluster(points.clone(), 1_000_000_000);
+        assert!(clusters.iter().all(|p| p[4] == clusters[0][4]));
+
+        let mut gated = ClusteringBuilder::new()
+            .point_limit(3)
+            .threads(1)
+            .mode(ClusteringMode::VelocityGated {
+                separation_threshold_mps: 0.5,
+            })
+            .build();
+        let clusters = gated.cluster(points.clone(), 1_000_000_000);
+        let approaching = clusters[0][4];
+        let receding = clusters[6][4];
+        assert_ne!(approaching, 0.0);
+        assert_ne!(receding, 0.0);
+        assert_ne!(approaching, receding);
+        for p in clusters.iter() {
+            let expected = if p[3] < 0.0 { approaching } else { receding };
+            assert_eq!(p[4], expected);
+        }
+
+        // The tracker keeps the ids stable on the following frame.
+        let next = gated.cluster(points, 1_055_000_000);
+        assert_eq!(next, clusters);
+    }
+
+    #[test]
+    fn test_velocity_gated_stationary_band() {
+        let mut points = blob(10.0, 2.0);
+        points.extend(
+            blob(20.0, -3.0)
+                .into_iter()
+                .map(|[x, y, z, _]| [x, y, z, 5.0]),
+        );
+
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let standard = clustering.cluster(points.clone(), 1_000_000_000);
+
+        // Well separated clusters are not affected by the gating.
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let gated = clustering.cluster_velocity_gated(points, 0.5, 1_000_000_000);
+        assert_eq!(gated, standard);
+    }
+
+    #[test]
+    fn test_single_mode() {
+        // Well separated groups are one cluster, noise included.
+        let mut points = blob(10.0, 2.0);
+        points.extend(blob(20.0, -3.0));
+        points.push([40.0, 10.0, 0.0, 0.0]);
+
+        let mut single = ClusteringBuilder::new()
+            .point_limit(3)
+            .threads(1)
+            .mode(ClusteringMode::Single)
+            .build();
+        let clusters = single.cluster(points.clone(), 1_000_000_000);
+        assert_eq!(clusters.len(), points.len());
+        assert_ne!(clusters[0][4], 0.0);
+        for (p, c) in points.iter().zip(clusters.iter()) {
+            assert_eq!(p[..], c[..4]);
+            assert_eq!(c[4], clusters[0][4]);
+        }
+
+        let next = single.cluster(points, 1_055_000_000);
+        assert_eq!(next, clusters);
+        assert!(single.cluster(Vec::new(), 1_110_000_000).is_empty());
+    }
+
+    #[test]
+    fn test_point_roles() {
+        let points = vec![
+            [0.0, 0.0, 0.0, 0.0],
+            [0.5, 0.0, 0.0, 0.0],
+            [0.0, 0.5, 0.0, 0.0],
+            [0.5, 0.5, 0.0, 0.0],
+            // Only within eps of a single core point.
+            [1.4, 0.5, 0.0, 0.0],
+            [10.0, 10.0, 0.0, 0.0],
+            [10.5, 10.0, 0.0, 0.0],
+            [10.0, 10.5, 0.0, 0.0],
+            [20.0, 20.0, 0.0, 0.0],
+        ];
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let clusters = clustering.cluster(points.clone(), 1_000_000_000);
+
+        use PointRole::*;
+        let expected = [Core, Core, Core, Core, Border, Core, Core, Core, Noise];
+        assert_eq!(clustering.point_roles(), expected);
+        // The border point keeps the cluster id of its core points.
+        assert_eq!(clusters[4][4], clusters[0][4]);
+        assert_eq!(clusters[8][4], 0.0);
+
+        // A cluster the tracker discards is noise in both fields.
+        let mut clustering = ClusteringBuilder::new()
+            .point_limit(3)
+            .threads(1)
+            .min_cluster_size(4)
+            .build();
+        let clusters = clustering.cluster(points, 1_000_000_000);
+        assert_eq!(clusters[5][4], 0.0);
+        assert_eq!(
+            clustering.point_roles(),
+            [Core, Core, Core, Core, Border, Noise, Noise, Noise, Noise]
+        );
+    }
+
+    #[test]
+    fn test_parallel_sectors() {
+        // Separated clusters across the field of view and one on the
+        // boresight, which straddles the boundary of an even sector count.
+        let mut points = blob(10.0, -6.0);
+        for i in 0..3 {
+            let d = 0.1 * i as f32;
+            points.push([12.0 + d, -0.3 + d / 2.0, 0.0, 0.0]);
+            points.push([12.0 + d, 0.2 + d / 2.0, 0.0, 0.0]);
+        }
+        points.extend(blob(10.0, 6.0));
+        points.extend(blob(25.0, 3.0));
+
+        let mut standard = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let expected = standard.cluster(points.clone(), 1_000_000_000);
+
+        for sectors in [2, 3, 4] {
+            let mut sectored = ClusteringBuilder::new()
+                .point_limit(3)
+                .threads(2)
+                .parallel_sectors(sectors)
+                .build();
+            let clusters = sectored.cluster(points.clone(), 1_000_000_000);
+            assert_eq!(clusters, expected, "{} sectors", sectors);
+        }
+    }
+
+    #[test]
+    fn test_eps_sweep() {
+        let mut points = blob(10.0, 2.0);
+        points.extend(blob(10.0, 4.0));
+        points.extend(blob(20.0, -3.0));
+
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let sweep = clustering.eps_sweep(&points, &[0.2, 1.0, 2.5, 20.0]);
+        assert_eq!(sweep.len(), 4);
+        // Three well separated blobs at 0.2 and 1.0, the two close blobs
+        // merge at 2.5 and every blob merges into one cluster at 20.
+        assert_eq!(sweep[0], sweep[1]);
+        assert!(sweep[1] > 0.0);
+        assert!(sweep[2] > 0.0 && sweep[2] != sweep[1]);
+        assert_eq!(sweep[3], 0.0);
+
+        // The sweep does not touch the tracker or the configured eps.
+        assert_eq!(clustering.eps(), 1.0);
+        let tracked = clustering.cluster(points.clone(), 1_000_000_000);
+        let mut fresh = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        assert_eq!(tracked, fresh.cluster(points, 1_000_000_000));
+    }
+
+    #[test]
+    fn test_nms_merges_overlapping_boxes() {
+        // An L-shaped cluster with a second cluster inside its bounding box,
+        // every point of the square is further than eps from the L.
+        let mut points = Vec::new();
+        for i in 0..5 {
+            points.push([0.8 * i as f32, 0.0, 0.0, 0.0]);
+        }
+        for i in 1..5 {
+            points.push([0.0, 0.8 * i as f32, 0.0, 0.0]);
+        }
+        for x in [1.4, 2.2, 3.0] {
+            for y in [1.4, 2.2, 3.0] {
+                points.push([x, y, 0.0, 0.0]);
+            }
+        }
+
+        let ids = |clusters: &[[f32; 5]]| {
+            clusters
+                .iter()
+                .map(|p| p[4] as usize)
+                .collect::<HashSet<_>>()
+        };
+
+        let mut clustering = ClusteringBuilder::new().point_limit(3).threads(1).build();
+        let clusters = clustering.cluster(points.clone(), 1_000_000_000);
+        assert_eq!(ids(&clusters).len(), 2);
+        assert!(!ids(&clusters).contains(&0));
+
+        let mut clustering = ClusteringBuilder::new()
+            .point_limit(3)
+            .threads(1)
+            .nms_iou_threshold(0.1)
+            .build();
+        let clusters = clustering.cluster(points.clone(), 1_000_000_000);
+        assert_eq!(ids(&clusters).len(), 1);
+        assert_ne!(clusters[0][4], 0.0);
+        assert_eq!(clustering.tracker.get_tracklets().len(), 1);
+
+        let next = clustering.cluster(points, 1_055_000_000);
+        assert_eq!(next, clusters);
+    }
+
+    #[test]
+    fn test_min_cluster_size() {
+        let mut points = blob(10.0, 2.0);
+        points.extend(blob(20.0, -3.0).into_iter().take(3));
+
+        let mut clustering = ClusteringBuilder::new().point_limit(3).threads(1).build();
+        let clusters = clustering.cluster(points.clone(), 1_000_000_000);
+        assert!(clusters.iter().all(|p| p[4] != 0.0));
+        assert_eq!(clustering.tracker.get_tracklets().len(), 2);
+
+        // The three point cluster is reported as noise and not tracked.
+        let mut clustering = ClusteringBuilder::new()
+            .point_limit(3)
+            .threads(1)
+            .min_cluster_size(4)
+            .build();
+        let clusters = clustering.cluster(points, 1_000_000_000);
+        assert!(clusters[..6].iter().all(|p| p[4] != 0.0));
+        assert!(clusters[6..].iter().all(|p| p[4] == 0.0));
+        assert_eq!(clustering.tracker.get_tracklets().len(), 1);
+    }
+
+    #[test]
+    fn test_missed_frames() {
+        let cycle = 55_000_000;
+        assert_eq!(missed_frames(0, cycle), 0);
+        assert_eq!(missed_frames(cycle, cycle), 0);
+        assert_eq!(missed_frames(cycle * 3 / 2, cycle), 0);
+        assert_eq!(missed_frames(cycle * 3 / 2 + 1, cycle), 1);
+        assert_eq!(missed_frames(2 * cycle, cycle), 1);
+        assert_eq!(missed_frames(4 * cycle + cycle / 3, cycle), 3);
+        assert_eq!(missed_frames(u64::MAX, 0), 0);
+    }
+
+    #[test]
+    fn test_advance_missing_frames() {
+        let mut clustering = ClusteringBuilder::new().point_limit(3).threads(1).build();
+        for frame in 0..5u64 {
+            let points = blob(10.0 + 0.1 * frame as f32, 2.0);
+            clustering.cluster(points, 1_000_000_000 + frame * 55_000_000);
+        }
+        let uncertainty = |clustering: &Clustering| {
+            let tracklets = clustering.tracker.get_tracklets();
+            assert_eq!(tracklets.len(), 1);
+            tracklets[0].filter.covariance.trace()
+        };
+        let tracked = clustering.get_tracklets();
+        let before = uncertainty(&clustering);
+
+        // Every missed frame is one more prediction step.
+        clustering.advance_missing_frames(0);
+        assert_eq!(uncertainty(&clustering), before);
+        clustering.advance_missing_frames(1);
+        let once = uncertainty(&clustering);
+        assert!(once > before);
+        clustering.advance_missing_frames(2);
+        assert!(uncertainty(&clustering) > once);
+        assert_eq!(clustering.get_tracklets().len(), tracked.len());
+    }
+
+    #[test]
+    fn test_cluster_summaries() {
+        let summaries = cluster_summaries([
+            ([1.0, 0.0, 0.0], 2.0, -4.0, 3),
+            ([5.0, 5.0, 0.0], -1.0, 20.0, 0),
+            ([3.0, 2.0, 1.0], 4.0, 2.0, 3),
+            ([10.0, 0.0, 0.0], -5.0, 12.0, 1),
+        ]);
+        assert_eq!(
+            summaries,
+            vec![
+                ClusterSummary {
+                    id: 1,
+                    centroid: [10.0, 0.0, 0.0],
+                    velocity: -5.0,
+                    rcs: 12.0,
+                    points: 1,
+                },
+                ClusterSummary {
+                    id: 3,
+                    centroid: [2.0, 1.0, 0.5],
+                    velocity: 3.0,
+                    rcs: -1.0,
+                    points: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_velocity_coherence_check() {
+        let prev = ClusterSummary {
+            id: 1,
+            velocity: 10.0,
+            ..Default::default()
+        };
+        let curr = ClusterSummary {
+            velocity: 10.4,
+            ..prev
+        };
+
+        // 0.4 m/s over one 55 ms radar cycle is about 7.3 m/s².
+        assert!(velocity_coherence_check(&prev, &curr, 0.055, 10.0));
+        assert!(!velocity_coherence_check(&prev, &curr, 0.055, 5.0));
+        // Braking is checked the same way.
+        assert!(!velocity_coherence_check(&curr, &prev, 0.055, 5.0));
+        // The default limit never fails.
+        assert!(velocity_coherence_check(
+            &prev,
+            &ClusterSummary {
+                velocity: -30.0,
+                ..prev
+            },
+            0.055,
+            f32::INFINITY
+        ));
+    }
+
+    #[test]
+    fn test_davies_bouldin_index() {
+        let points = [
+            [0.0, 0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0, 0.0],
+            [10.0, 0.0, 0.0, 0.0],
+            [12.0, 0.0, 0.0, 0.0],
+            [50.0, 50.0, 0.0, 0.0],
+        ];
+        // Both scatters are 1 and the centroids are 10 apart, noise ignored.
+        let index = davies_bouldin_index(&points, &[1, 1, 2, 2, 0]);
+        assert!((index - 0.2).abs() < 1e-6);
+
+        // Moving the clusters closer together is worse.
+        let closer = points.map(|[x, y, z, s]| [if x > 5.0 { x - 6.0 } else { x }, y, z, s]);
+        assert!(davies_bouldin_index(&closer, &[1, 1, 2, 2, 0]) > index);
+
+        // Undefined for fewer than two clusters.
+        assert_eq!(davies_bouldin_index(&points, &[1, 1, 1, 1, 0]), 0.0);
+        assert_eq!(davies_bouldin_index(&[], &[]), 0.0);
+
+        // Well separated blobs from the clustering score below 1.
+        let mut points = blob(10.0, 2.0);
+        points.extend(blob(20.0, -3.0));
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let clusters = clustering.cluster(points, 1_000_000_000);
+        let (points, labels): (Vec<_>, Vec<_>) = clusters
+            .iter()
+            .map(|p| ([p[0], p[1], p[2], p[3]], p[4] as usize))
+            .unzip();
+        assert!(davies_bouldin_index(&points, &labels) < 0.1);
+    }
+
+    #[test]
+    fn test_associate() {
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let mut points = blob(10.0, 2.0);
+        points.extend(blob(20.0, -3.0));
+        points.push([40.0, 10.0, 0.0, 0.0]);
+
+        let clusters = clustering.cluster(points, 1_000_000_000);
+        let association = clustering.associate(7);
+        assert_eq!(association.cycle_counter, 7);
+        assert_eq!(association.boxes.len(), 2);
+
+        let a = clusters[0][4] as usize;
+        let b = clusters[6][4] as usize;
+        assert_ne!(a, 0);
+        assert_ne!(b, 0);
+        assert_ne!(a, b);
+        assert_eq!(clusters[12][4], 0.0);
+
+        assert_eq!(association.track_id(10.2, 2.1), a);
+        assert_eq!(association.track_id(20.2, -2.9), b);
+        assert_eq!(association.track_id(40.0, 10.0), 0);
+        assert_eq!(association.track_id(0.0, 0.0), 0);
+    }
+
+    #[test]
+    fn test_tracks() {
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let mut points = blob(20.0, -3.0);
+        points.extend(blob(10.0, 2.0));
+
+        clustering.cluster(points.clone(), 1_000_000_000);
+        let clusters = clustering.cluster(points, 1_055_000_000);
+        let tracks = clustering.tracks();
+        assert_eq!(tracks.len(), 2);
+        assert!(tracks[0].cluster_id < tracks[1].cluster_id);
+        assert_ne!(tracks[0].uuid, tracks[1].uuid);
+
+        // The boxes contain the centroid of their cluster and the tracks
+        // keep the time of their creation.
+        for track in &tracks {
+            assert_eq!(track.created, 1_000_000_000);
+            assert_eq!(track.count, 2);
+            let summary = cluster_summaries(
+                clusters
+                    .iter()
+                    .map(|v| ([v[0], v[1], v[2]], v[3], 0.0, v[4] as usize)),
+            )
+            .into_iter()
+            .find(|s| s.id == track.cluster_id)
+            .unwrap();
+            let [x, y, _] = summary.centroid;
+            let [xmin, ymin, xmax, ymax] = track.bbox;
+            assert!(x > xmin && x < xmax && y > ymin && y < ymax, "{:?}", track);
+        }
+    }
+
+    #[test]
+    fn test_detection_score() {
+        assert_eq!(detection_score(0, 0), 0.0);
+        assert!((detection_score(5, 1) - 0.3).abs() < 1e-6);
+        // Each term saturates.
+        assert_eq!(detection_score(10, 5), 0.75);
+        assert_eq!(detection_score(100, 0), 0.5);
+        assert_eq!(detection_score(100, 100), 1.0);
+        // The score grows with the points and the persistence.
+        assert!(detection_score(6, 3) > detection_score(5, 3));
+        assert!(detection_score(5, 4) > detection_score(5, 3));
+    }
+
+    #[test]
+    fn test_classify() {
+        let t = ClassifierThresholds::default();
+        let walker = ClusterFeatures {
+            speed: 1.4,
+            extent: 0.6,
+            points: 4,
+            rcs: -8.0,
+        };
+        let class = |f: ClusterFeatures| classify(&f, &t).0;
+
+        assert_eq!(classify(&walker, &t), (ObjectClass::Pedestrian, 1.0));
+        // A standing pedestrian is as likely a pole.
+        let standing = ClusterFeatures {
+            speed: 0.2,
+            ..walker
+        };
+        assert_eq!(classify(&standing, &t), (ObjectClass::Pedestrian, 0.5));
+        // Too few points.
+        assert_eq!(
+            class(ClusterFeatures {
+                points: CLASSIFIER_MIN_POINTS - 1,
+                ..walker
+            }),
+            ObjectClass::Unknown
+        );
+
+        // The pedestrian limits are inclusive, beyond them is a bicycle.
+        for (at, beyond) in [
+            (
+                ClusterFeatures {
+                    speed: PEDESTRIAN_MAX_SPEED,
+                    ..walker
+                },
+                ClusterFeatures {
+                    speed: PEDESTRIAN_MAX_SPEED + 0.1,
+                    ..walker
+                },
+            ),
+            (
+                ClusterFeatures {
+                    extent: PEDESTRIAN_MAX_EXTENT,
+                    ..walker
+                },
+                ClusterFeatures {
+                    extent: PEDESTRIAN_MAX_EXTENT + 0.1,
+                    ..walker
+                },
+            ),
+        ] {
+            assert_eq!(class(at), ObjectClass::Pedestrian);
+            assert_eq!(class(beyond), ObjectClass::Bicycle);
+        }
+        // A small slow strong reflector matches no class.
+        assert_eq!(
+            class(ClusterFeatures {
+                rcs: PEDESTRIAN_MAX_RCS + 0.1,
+                ..walker
+            }),
+            ObjectClass::Unknown
+        );
+
+        // A bicycle gains confidence with each agreeing cue.
+        let cyclist = ClusterFeatures {
+            speed: 6.0,
+            extent: 1.0,
+            points: 5,
+            rcs: -5.0,
+        };
+        assert_eq!(classify(&cyclist, &t), (ObjectClass::Bicycle, 0.5));
+        assert_eq!(
+            classify(
+                &ClusterFeatures {
+                    extent: 1.8,
+                    rcs: 0.0,
+                    ..cyclist
+                },
+                &t
+            ),
+            (ObjectClass::Bicycle, 1.0)
+        );
+
+        // Each vehicle cue alone makes a vehicle from its threshold on.
+        for vehicle in [
+            ClusterFeatures {
+                speed: VEHICLE_MIN_SPEED,
+                ..cyclist
+            },
+            ClusterFeatures {
+                extent: VEHICLE_MIN_EXTENT,
+                ..cyclist
+            },
+            ClusterFeatures {
+                rcs: VEHICLE_MIN_RCS,
+                ..cyclist
+            },
+        ] {
+            assert_eq!(classify(&vehicle, &t), (ObjectClass::Vehicle, 0.5));
+        }
+        assert_eq!(
+            class(ClusterFeatures {
+                speed: VEHICLE_MIN_SPEED - 0.1,
+                extent: VEHICLE_MIN_EXTENT - 0.1,
+                rcs: VEHICLE_MIN_RCS - 0.1,
+                ..cyclist
+            }),
+            ObjectClass::Bicycle
+        );
+        let car = ClusterFeatures {
+            speed: 15.0,
+            extent: 4.5,
+            points: 12,
+            rcs: 10.0,
+        };
+        assert_eq!(classify(&car, &t), (ObjectClass::Vehicle, 1.0));
+        assert_eq!(ObjectClass::Vehicle.label(), "vehicle");
+    }
+
+    #[test]
+    fn test_classifier_hysteresis() {
+        let mut classifier = Classifier::new(ClassifierThresholds::default(), 3);
+        let uuid = Uuid::new_v4();
+        let pedestrian = ClusterFeatures {
+            speed: 2.9,
+            extent: 0.8,
+            points: 4,
+            rcs: -8.0,
+        };
+        let bicycle = ClusterFeatures {
+            speed: 3.1,
+            ..pedestrian
+        };
+
+        // An unknown track takes the first class at once.
+        let sparse = ClusterFeatures {
+            points: 1,
+            ..pedestrian
+        };
+        assert_eq!(classifier.update(uuid, &sparse).0, ObjectClass::Unknown);
+        assert_eq!(
+            classifier.update(uuid, &pedestrian).0,
+            ObjectClass::Pedestrian
+        );
+
+        // A speed jittering around the pedestrian limit does not flap.
+        for features in [bicycle, bicycle, pedestrian, bicycle, sparse, bicycle] {
+            assert_eq!(
+                classifier.update(uuid, &features),
+                (ObjectClass::Pedestrian, 1.0)
+            );
+        }
+        // Three consecutive cycles change the class.
+        assert_eq!(classifier.update(uuid, &bicycle).0, ObjectClass::Pedestrian);
+        assert_eq!(
+            classifier.update(uuid, &bicycle),
+            (ObjectClass::Bicycle, 0.5)
+        );
+
+        // Without hysteresis the class follows every cycle.
+        let mut classifier = Classifier::new(ClassifierThresholds::default(), 1);
+        for features in [pedestrian, bicycle, pedestrian] {
+            assert_eq!(
+                classifier.update(uuid, &features).0,
+                classify(&features, &ClassifierThresholds::default()).0
+            );
+        }
+    }
+
+    #[test]
+    fn test_classify_tracks() {
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        clustering.cluster(blob(10.0, 2.0), 1_000_000_000);
+        let tracks = clustering.tracks();
+        let summaries = [ClusterSummary {
+            id: tracks[0].cluster_id,
+            velocity: -1.5,
+            rcs: -9.0,
+            points: 6,
+            ..Default::default()
+        }];
+
+        let mut classifier = Classifier::new(ClassifierThresholds::default(), 3);
+        let classes = classifier.classify_tracks(&tracks, &summaries, &[1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(classes, [(ObjectClass::Pedestrian, 1.0)]);
+        // The blob spans 0.5 m, scaled to 5 m it is a vehicle but the class
+        // sticks.
+        let classes = classifier.classify_tracks(&tracks, &summaries, &[0.1, 0.1, 0.0, 0.0]);
+        assert_eq!(classes, [(ObjectClass::Pedestrian, 1.0)]);
+
+        // Ended tracks are forgotten.
+        classifier.classify_tracks(&[], &[], &[1.0, 1.0, 0.0, 0.0]);
+        assert!(classifier.tracks.is_empty());
+    }
+
+    /// Cluster id of the first point of each of the frames, 55 ms apart
+    /// starting from frame `first`.
+    fn cluster_ids(
+        clustering: &mut Clustering,
+        frames: &[Vec<[f32; 4]>],
+        first: usize,
+    ) -> Vec<f32> {
+        frames
+            .iter()
+            .enumerate()
+            .map(|(i, points)| {
+                let frame = (first + i) as u64;
+                let clusters =
+                    clustering.cluster(points.clone(), 1_000_000_000 + frame * 55_000_000);
+                clusters.first().map(|v| v[4]).unwrap_or(0.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_track_min_hits() {
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_min_hits(3)
+            .build();
+
+        // A flicker over two frames is never published.
+        let flicker = [blob(10.0, 2.0), blob(10.0, 2.0), Vec::new(), Vec::new()];
+        assert_eq!(cluster_ids(&mut clustering, &flicker, 0), [0.0; 4]);
+        assert!(clustering.tracks().is_empty());
+        assert!(clustering.associate(1).boxes.is_empty());
+
+        // A persistent object is confirmed on its third frame and keeps the
+        // id.
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_min_hits(3)
+            .build();
+        let ids = cluster_ids(&mut clustering, &[0; 5].map(|_| blob(20.0, -3.0)), 0);
+        assert_eq!(ids[..2], [0.0, 0.0]);
+        assert_ne!(ids[2], 0.0);
+        assert_eq!(ids[3..], [ids[2], ids[2]]);
+        assert_eq!(clustering.tracks().len(), 1);
+        assert_eq!(clustering.associate(1).boxes.len(), 1);
+
+        // The unconfirmed points are noise.
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_min_hits(2)
+            .build();
+        clustering.cluster(blob(20.0, -3.0), 1_000_000_000);
+        assert!(clustering
+            .point_roles()
+            .iter()
+            .all(|role| *role == PointRole::Noise));
+    }
+
+    #[test]
+    fn test_provisional_ids() {
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_min_hits(3)
+            .provisional_ids(true)
+            .build();
+
+        // The negated id turns positive once the track is confirmed.
+        let ids = cluster_ids(&mut clustering, &[0; 4].map(|_| blob(20.0, -3.0)), 0);
+        assert!(ids[0] < 0.0);
+        assert_eq!(ids[1], ids[0]);
+        assert_eq!(ids[2], -ids[0]);
+        assert_eq!(ids[3], ids[2]);
+    }
+
+    #[test]
+    fn test_track_max_coast() {
+        let frames = [
+            blob(10.0, 2.0),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            blob(10.0, 2.0),
+        ];
+
+        // Coasting over three missed frames keeps the track and its id.
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_max_coast(Some(3))
+            .build();
+        let ids = cluster_ids(&mut clustering, &frames, 0);
+        assert_ne!(ids[0], 0.0);
+        assert_eq!(ids[4], ids[0]);
+
+        // Beyond the coast limit the track is dropped and the object starts
+        // a new track.
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_max_coast(Some(2))
+            .build();
+        let ids = cluster_ids(&mut clustering, &frames[..4], 0);
+        assert_ne!(ids[0], 0.0);
+        assert!(clustering.tracks().is_empty());
+        let id = cluster_ids(&mut clustering, &frames[4..], 4);
+        assert_ne!(id[0], 0.0);
+        assert_eq!(clustering.tracks().len(), 1);
+        assert_eq!(clustering.tracks()[0].count, 1);
+
+        // Missed frames which were never clustered count as well.
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_max_coast(Some(2))
+            .build();
+        cluster_ids(&mut clustering, &frames[..1], 0);
+        clustering.advance_missing_frames(2);
+        cluster_ids(&mut clustering, &frames[1..2], 3);
+        assert!(clustering.tracks().is_empty());
+    }
+
+    #[test]
+    fn test_track_history() {
+        let mut clustering = ClusteringBuilder::new()
+            .eps(1.0)
+            .point_limit(3)
+            .track_max_coast(Some(1))
+            .track_history(2)
+            .track_history_max_points(3)
+            .track_history_max_tracks(1)
+            .build();
+        // A blob moving 0.2 along x every 55 ms, then gone.
+        let frames = (0..8).map(|k| {
+            let speed = match k {
+                5 => -4.0,
+                _ => 2.0,
+            };
+            blob(10.0 + 0.2 * k as f32, 2.0)
+                .into_iter()
+                .map(|[x, y, z, _]| [x, y, z, speed])
+                .collect::<Vec<_>>()
+        });
+        let frames: Vec<_> = frames.chain((0..4).map(|_| Vec::new())).collect();
+
+        let mut ended = Vec::new();
+        for (k, frame) in frames.into_iter().enumerate() {
+            let timestamp = 1_000_000_000 + k as u64 * 55_000_000;
+            let clusters = clustering.cluster(frame, timestamp);
+            ended.extend_from_slice(clustering.ended_tracks());
+            let summaries = cluster_summaries(
+                clusters
+                    .iter()
+                    .map(|v| ([v[0], v[1], v[2]], v[3], 0.0, v[4] as usize)),
+            );
+            let tracks = clustering.tracks();
+            let classes = vec![(ObjectClass::Bicycle, 0.5); tracks.len()];
+            clustering.record_history(timestamp, &tracks, &summaries, Some(&classes));
+        }
+
+        // The track ended once it coasted beyond the limit.
+        assert_eq!(ended.len(), 1);
+        let record = &ended[0];
+        assert_ne!(record.cluster_id, 0);
+        assert_eq!(record.first_seen, 1_000_000_000);
+        assert_eq!(record.last_seen, 1_385_000_000);
+        assert_eq!(record.max_speed, 4.0);
+        assert_eq!(record.class, ObjectClass::Bicycle);
+        // Points of cycles 0, 2 and 4, then the full path is thinned to
+        // cycles 0 and 4 before cycle 6 is added.
+        let centroid = |k: usize| [10.25 + 0.2 * k as f32, 2.125];
+        assert_eq!(record.path.len(), 3);
+        for (point, k) in record.path.iter().zip([0, 4, 6]) {
+            assert!(
+                (point[0] - centroid(k)[0]).abs() < 1e-4,
+                "{:?}",
+                record.path
+            );
+            assert!(
+                (point[1] - centroid(k)[1]).abs() < 1e-4,
+                "{:?}",
+                record.path
+            );
+        }
+        assert_eq!(clustering.drain_completed_tracks(), ended);
+        assert!(clustering.drain_completed_tracks().is_empty());
+
+        // At shutdown the live tracks are completed, beyond the kept tracks
+        // the oldest are dropped.
+        let mut points = blob(20.0, -3.0);
+        points.extend(blob(10.0, 2.0));
+        let clusters = clustering.cluster(points, 2_000_000_000);
+        let summaries = cluster_summaries(
+            clusters
+                .iter()
+                .map(|v| ([v[0], v[1], v[2]], v[3], 0.0, v[4] as usize)),
+        );
+        clustering.record_history(2_000_000_000, &clustering.tracks(), &summaries, None);
+        clustering.complete_live_tracks();
+        assert_eq!(clustering.ended_tracks().len(), 2);
+        assert_eq!(clustering.dropped_tracks(), 1);
+        let completed = clustering.drain_completed_tracks();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].class, ObjectClass::Unknown);
+        assert_eq!(completed[0].path.len(), 1);
+
+        // The history is disabled by default.
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let clusters = clustering.cluster(blob(10.0, 2.0), 0);
+        let summaries = cluster_summaries(
+            clusters
+                .iter()
+                .map(|v| ([v[0], v[1], v[2]], v[3], 0.0, v[4] as usize)),
+        );
+        clustering.record_history(0, &clustering.tracks(), &summaries, None);
+        clustering.complete_live_tracks();
+        assert!(clustering.drain_completed_tracks().is_empty());
+    }
+
+    /// Cluster id of each blob of a single frame at `timestamp`.
+    fn blob_ids(clustering: &mut Clustering, blobs: &[(f32, f32)], timestamp: u64) -> Vec<f32> {
+        let points = blobs.iter().flat_map(|(x, y)| blob(*x, *y)).collect();
+        let clusters = clustering.cluster(points, timestamp);
+        clusters.chunks(6).map(|c| c[0][4]).collect()
+    }
+
+    #[test]
+    fn test_cluster_id_cooldown() {
+        let mut clustering = ClusteringBuilder::new()
+            .point_limit(3)
+            .threads(1)
+            .cluster_id_cooldown(Duration::from_secs(1))
+            .build();
+
+        assert_eq!(
+            blob_ids(&mut clustering, &[(10.0, 2.0)], 1_000_000_000),
+            [1.0]
+        );
+        // The track expires 1.5 s after its last association, freeing id 1
+        // at 3 s.
+        assert!(blob_ids(&mut clustering, &[], 3_000_000_000).is_empty());
+        assert!(clustering.tracks().is_empty());
+
+        // Within the cooldown a new object gets a fresh id, once the
+        // cooldown has passed the freed id is reused.
+        assert_eq!(
+            blob_ids(&mut clustering, &[(20.0, -3.0)], 3_500_000_000),
+            [2.0]
+        );
+        assert_eq!(
+            blob_ids(&mut clustering, &[(20.0, -3.0), (10.0, 6.0)], 4_000_000_000),
+            [2.0, 1.0]
+        );
+
+        // Without a cooldown the freed id is reused on the next frame.
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        assert_eq!(
+            blob_ids(&mut clustering, &[(10.0, 2.0)], 1_000_000_000),
+            [1.0]
+        );
+        blob_ids(&mut clustering, &[], 3_000_000_000);
+        assert_eq!(
+            blob_ids(&mut clustering, &[(20.0, -3.0)], 3_500_000_000),
+            [1.0]
+        );
+    }
+
+    #[test]
+    fn test_restore_tracker_state() {
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        let cycle = 55_000_000;
+        for i in 0..5 {
+            let x = 10.0 + 0.2 * i as f32;
+            let ids = blob_ids(&mut clustering, &[(x, 2.0), (20.0, -3.0)], (i + 1) * cycle);
+            assert_eq!(ids, [1.0, 2.0]);
+        }
+        let state: Value = serde_json::from_str(&clustering.tracker_state().to_string()).unwrap();
+
+        // A new process continues the moving objects with their cluster ids
+        // on its own clock and a new object gets a fresh id.
+        let mut restored = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        assert!(!restored.restore_tracker_state(&json!({}), 0));
+        let start = 7_000_000_000;
+        assert!(restored.restore_tracker_state(&state, start));
+        let uuids: Vec<_> = clustering.tracks().iter().map(|t| t.uuid).collect();
+        for i in 5..10 {
+            let x = 10.0 + 0.2 * i as f32;
+            let ids = blob_ids(
+                &mut restored,
+                &[(x, 2.0), (20.0, -3.0)],
+                start + (i - 4) * cycle,
+            );
+            assert_eq!(ids, [1.0, 2.0], "frame {}", i);
+        }
+        let tracks = restored.tracks();
+        assert_eq!(tracks.iter().map(|t| t.uuid).collect::<Vec<_>>(), uuids);
+        assert_eq!(tracks[0].count, 10);
+        assert_eq!(
+            blob_ids(
+                &mut restored,
+                &[(12.0, 2.0), (20.0, -3.0), (30.0, 6.0)],
+                start + 6 * cycle
+            ),
+            [1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_cluster_id_wrap() {
+        let mut clustering = ClusteringBuilder::new()
+            .point_limit(3)
+            .threads(1)
+            .cluster_id_cooldown(Duration::ZERO)
+            .cluster_id_wrap(Some(3))
+            .build();
+
+        let a = (10.0, 2.0);
+        let b = (20.0, -3.0);
+        let c = (10.0, 6.0);
+        assert_eq!(
+            blob_ids(&mut clustering, &[a, b, c], 1_000_000_000),
+            [1.0, 2.0, 3.0]
+        );
+        // Only b is still seen when a and c expire, their ids are not
+        // recycled.
+        blob_ids(&mut clustering, &[b], 2_000_000_000);
+        assert_eq!(blob_ids(&mut clustering, &[b], 3_000_000_000), [2.0]);
+        assert_eq!(clustering.tracks().len(), 1);
+
+        // The ids wrap around after 3 and skip id 2 held by b.
+        let d = (30.0, 5.0);
+        let e = (30.0, -8.0);
+        assert_eq!(
+            blob_ids(&mut clustering, &[b, d], 3_055_000_000),
+            [2.0, 1.0]
+        );
+        assert_eq!(
+            blob_ids(&mut clustering, &[b, d, e], 3_110_000_000),
+            [2.0, 1.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_smooth_trajectory() {
+        // A box moving 0.2 per frame along x measured with up to 0.1 of
+        // noise.
+        let mut state = 0x9e37_79b9_u32;
+        let mut noise = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state as f32 / u32::MAX as f32 - 0.5) * 0.2
+        };
+        let truth: Vec<[f32; 4]> = (0..60)
+            .map(|k| [10.0 + 0.2 * k as f32, 5.0, 1.0, 1.0])
+            .collect();
+        let trajectory: Vec<_> = truth
+            .iter()
+            .enumerate()
+            .map(|(k, [x, y, a, h])| {
+                let timestamp = 1_000_000_000 + k as u64 * 55_000_000;
+                (timestamp, [x + noise(), y + noise(), *a, *h])
+            })
+            .collect();
+
+        let mut filter = ConstantVelocityXYAHModel2::new(&trajectory[0].1, 1.0);
+        let mut filtered = vec![trajectory[0].1];
+        for (_, measurement) in &trajectory[1..] {
+            filter.predict();
+            filter.update(measurement);
+            filtered.push([
+                filter.mean[0],
+                filter.mean[1],
+                filter.mean[2],
+                filter.mean[3],
+            ]);
+        }
+        let smoothed = smooth_trajectory(&trajectory, 1.0);
+        assert_eq!(smoothed.len(), trajectory.len());
+
+        let rms = |estimate: &[[f32; 4]]| {
+            let sum: f32 = estimate
+                .iter()
+                .zip(&truth)
+                .map(|(e, t)| (e[0] - t[0]).powi(2) + (e[1] - t[1]).powi(2))
+                .sum();
+            (sum / truth.len() as f32).sqrt()
+        };
+        assert!(
+            rms(&smoothed) < rms(&filtered),
+            "smoothed {} filtered {}",
+            rms(&smoothed),
+            rms(&filtered)
+        );
+
+        // A single measurement is its own smoothed state.
+        assert_eq!(smooth_trajectory(&trajectory[..1], 1.0), [trajectory[0].1]);
+        assert!(smooth_trajectory(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_association_age() {
+        let mut clustering = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3);
+        clustering.cluster(blob(10.0, 2.0), 1_000_000_000);
+
+        // The association produced by frame N is applied to frame N + 1.
+        let association = clustering.associate(41);
+        assert_eq!(association.age(42), 1);
+        assert_eq!(association.age(41), 0);
+
+        // The radar cycle counter wraps around.
+        let association = clustering.associate(u32::MAX);
+        assert_eq!(association.age(0), 1);
+
+        // Before the first clustering cycle completes nothing is tracked.
+        let empty = Clustering::new(1.0, &[1.0, 1.0, 0.0, 0.0], 3).associate(0);
+        assert!(empty.boxes.is_empty());
+        assert_eq!(empty.track_id(10.2, 2.1), 0);
+    }
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use std::sync::Arc;
+
+/// Worker pool used to spread the per-point clustering work across threads.
+///
+/// Without the `rayon` feature every operation runs on the calling thread.
+/// Results are always returned in input order so the clustering output does
+/// not depend on the number of threads.
+#[derive(Debug, Clone, Default)]
+pub struct Pool {
+    #[cfg(feature = "rayon")]
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl Pool {
+    /// Create a pool with the given number of threads, 0 uses one thread per
+    /// CPU core and 1 disables parallelism.
+    pub fn new(threads: usize) -> Self {
+        #[cfg(feature = "rayon")]
+        {
+            if threads == 1 {
+                return Pool { pool: None };
+            }
+
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|i| format!("cluster{}", i))
+                .build()
+                .map_err(|e| tracing::warn!("unable to create clustering pool: {:?}", e))
+                .ok()
+                .map(Arc::new);
+            Pool { pool }
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let _ = threads;
+            Pool {}
+        }
+    }
+
+    /// Map every item through `f`, preserving the input order.
+    pub fn map<T, U, F>(&self, items: &[T], f: F) -> Vec<U>
+    where
+        T: Sync,
+        U: Send,
+        F: Fn(&T) -> U + Sync + Send,
+    {
+        #[cfg(feature = "rayon")]
+        if let Some(pool) = &self.pool {
+            return pool.install(|| items.par_iter().map(&f).collect());
+        }
+
+        items.iter().map(f).collect()
+    }
+}
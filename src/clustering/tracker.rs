@@ -3,6 +3,7 @@
 
 use lapjv::{lapjv, Matrix};
 use nalgebra::{Dyn, OMatrix, U4};
+use serde_json::{json, Value};
 use uuid::Uuid;
 
 use super::kalman::ConstantVelocityXYAHModel2;
@@ -31,6 +32,7 @@ pub struct ByteTrack {
     pub lost_tracks: Vec<Tracklet>,
     pub removed_tracks: Vec<Tracklet>,
     pub frame_count: i32,
+    /// Timestamp in nanoseconds of the last update
     pub timestamp: u64,
 }
 
@@ -50,6 +52,16 @@ pub struct TrackSettings {
     /// tracking update factor. Higher update factor will also mean
     /// less smoothing but more rapid response to change (0.0 to 1.0)
     pub track_update: f32,
+
+    /// non-maximum suppression iou threshold for the cluster boxes. Boxes
+    /// overlapping a higher scoring box by more than this iou are merged into
+    /// it before tracking, 1.0 disables suppression.
+    pub nms_iou_threshold: f32,
+
+    /// minimum number of points in a cluster for it to be tracked. Smaller
+    /// clusters are discarded before tracking and their points reported as
+    /// noise, 1 tracks every cluster.
+    pub min_cluster_size_after_tracking: usize,
 }
 
 impl Default for TrackSettings {
@@ -59,6 +71,8 @@ impl Default for TrackSettings {
             track_high_conf: 0.5,
             track_iou: 0.01,
             track_update: 1.0,
+            nms_iou_threshold: 1.0,
+            min_cluster_size_after_tracking: 1,
         }
     }
 }
@@ -102,6 +116,62 @@ impl Tracklet {
         xyah_to_vaalbox(predicted_xyah, &mut expected);
         expected
     }
+
+    /// JSON object of the tracklet with the mean and the column major
+    /// covariance of its Kalman filter.
+    fn to_json(&self) -> Value {
+        let b = &self.prev_boxes;
+        json!({
+            "id": self.id.to_string(),
+            "box": [b.xmin, b.ymin, b.xmax, b.ymax, b.score],
+            "label": b.label,
+            "mean": self.filter.mean.as_slice(),
+            "covariance": self.filter.covariance.as_slice(),
+            "update_factor": self.filter.update_factor,
+            "expiry": self.expiry,
+            "count": self.count,
+            "created": self.created,
+        })
+    }
+
+    /// Parse a [`Tracklet::to_json`] object, None if malformed.
+    fn from_json(value: &Value) -> Option<Self> {
+        let id = Uuid::parse_str(value["id"].as_str()?).ok()?;
+        let b = f32_array(&value["box"], 5)?;
+        let mean = f32_array(&value["mean"], 8)?;
+        let covariance = f32_array(&value["covariance"], 64)?;
+        let update_factor = value["update_factor"].as_f64()? as f32;
+
+        let mut filter =
+            ConstantVelocityXYAHModel2::new(&[mean[0], mean[1], mean[2], mean[3]], update_factor);
+        filter.mean.copy_from_slice(&mean);
+        filter.covariance.copy_from_slice(&covariance);
+        Some(Tracklet {
+            id,
+            prev_boxes: VAALBox {
+                xmin: b[0],
+                ymin: b[1],
+                xmax: b[2],
+                ymax: b[3],
+                score: b[4],
+                label: value["label"].as_i64()?.try_into().ok()?,
+            },
+            filter,
+            expiry: value["expiry"].as_u64()?,
+            count: value["count"].as_i64()?.try_into().ok()?,
+            created: value["created"].as_u64()?,
+        })
+    }
+}
+
+/// The `len` finite numbers of a JSON array, None otherwise.
+fn f32_array(value: &Value, len: usize) -> Option<Vec<f32>> {
+    let values = value
+        .as_array()?
+        .iter()
+        .map(|v| v.as_f64().map(|v| v as f32))
+        .collect::<Option<Vec<_>>>()?;
+    (values.len() == len && values.iter().all(|v| v.is_finite())).then_some(values)
 }
 
 fn vaalbox_to_xyah(vaal_box: &VAALBox) -> [f32; 4] {
@@ -139,7 +209,7 @@ pub struct TrackInfo {
 const INVALID_MATCH: f32 = 1000000.0;
 const EPSILON: f32 = 0.00001;
 
-fn iou(box1: &VAALBox, box2: &VAALBox) -> f32 {
+pub fn iou(box1: &VAALBox, box2: &VAALBox) -> f32 {
     let intersection = (box1.xmax.min(box2.xmax) - box1.xmin.max(box2.xmin)).max(0.0)
         * (box1.ymax.min(box2.ymax) - box1.ymin.max(box2.ymin)).max(0.0);
 
@@ -158,6 +228,38 @@ fn iou(box1: &VAALBox, box2: &VAALBox) -> f32 {
     intersection / union
 }
 
+/// Standard greedy non-maximum suppression.  Boxes are visited in order of
+/// decreasing score, ties keeping their input order, and every box whose iou
+/// with an already kept box exceeds `iou_threshold` is removed from `boxes`.
+///
+/// Returns the indices into the original `boxes` of the kept boxes, in input
+/// order which is also the order of the remaining `boxes`.
+pub fn non_maximum_suppression(boxes: &mut Vec<VAALBox>, iou_threshold: f32) -> Vec<usize> {
+    let mut order = (0..boxes.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| boxes[*b].score.total_cmp(&boxes[*a].score));
+
+    let mut kept: Vec<usize> = Vec::with_capacity(boxes.len());
+    for i in order {
+        if kept
+            .iter()
+            .all(|k| iou(&boxes[*k], &boxes[i]) <= iou_threshold)
+        {
+            kept.push(i);
+        }
+    }
+    kept.sort_unstable();
+
+    let mut keep = vec![false; boxes.len()];
+    kept.iter().for_each(|k| keep[*k] = true);
+    let mut i = 0;
+    boxes.retain(|_| {
+        i += 1;
+        keep[i - 1]
+    });
+
+    kept
+}
+
 fn box_cost(
     track: &Tracklet,
     new_box: &VAALBox,
@@ -245,6 +347,7 @@ impl ByteTrack {
         timestamp: u64,
     ) -> Vec<Option<TrackInfo>> {
         self.frame_count += 1;
+        self.timestamp = timestamp;
         let high_conf_ind = (0..boxes.len())
             .filter(|x| boxes[*x].score >= s.track_high_conf)
             .collect::<Vec<usize>>();
@@ -273,11 +376,6 @@ impl ByteTrack {
                         continue;
                     }
                     matched[i] = true;
-                    matched_info[i] = Some(TrackInfo {
-                        uuid: self.tracklets[x].id,
-                        count: self.tracklets[x].count,
-                        created: self.tracklets[x].created,
-                    });
                     assert!(!tracked[x]);
                     tracked[x] = true;
 
@@ -286,6 +384,12 @@ impl ByteTrack {
                     let predicted_xyah = self.tracklets[x].filter.mean.as_slice();
                     xyah_to_vaalbox(predicted_xyah, &mut boxes[i]);
                     self.tracklets[x].update(&observed_box, s, timestamp);
+                    // The count includes this association.
+                    matched_info[i] = Some(TrackInfo {
+                        uuid: self.tracklets[x].id,
+                        count: self.tracklets[x].count,
+                        created: self.tracklets[x].created,
+                    });
                 }
             }
         }
@@ -303,11 +407,6 @@ impl ByteTrack {
                         continue;
                     }
                     matched[i] = true;
-                    matched_info[i] = Some(TrackInfo {
-                        uuid: self.tracklets[x].id,
-                        count: self.tracklets[x].count,
-                        created: self.tracklets[x].created,
-                    });
                     assert!(!tracked[x]);
                     tracked[x] = true;
                     let predicted_xyah = self.tracklets[x].filter.mean.as_slice();
@@ -317,6 +416,11 @@ impl ByteTrack {
                     let h_ = predicted_xyah[3];
 
                     self.tracklets[x].update(&boxes[i], s, timestamp);
+                    matched_info[i] = Some(TrackInfo {
+                        uuid: self.tracklets[x].id,
+                        count: self.tracklets[x].count,
+                        created: self.tracklets[x].created,
+                    });
 
                     let w_ = h_ * a_;
                     boxes[i].xmin = x_ - w_ / 2.0;
@@ -363,6 +467,71 @@ impl ByteTrack {
     pub fn get_tracklets(&self) -> &Vec<Tracklet> {
         &self.tracklets
     }
+
+    /// Drop every tracklet and restart the frame count, the next update
+    /// starts new tracks for all its boxes.
+    #[allow(dead_code)]
+    pub fn clear(&mut self) {
+        self.tracklets.clear();
+        self.lost_tracks.clear();
+        self.removed_tracks.clear();
+        self.frame_count = 0;
+        self.timestamp = 0;
+    }
+
+    /// JSON object of the tracklets, with their Kalman filter state, ids,
+    /// association counts and expiries, for [`ByteTrack::restore_state`].
+    pub fn serialize_state(&self) -> Value {
+        json!({
+            "frame_count": self.frame_count,
+            "timestamp": self.timestamp,
+            "tracklets": self.tracklets.iter().map(Tracklet::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Parse a [`ByteTrack::serialize_state`] object, None if malformed.
+    ///
+    /// The timestamps are those of the saved tracker, see
+    /// [`ByteTrack::rebase`] to continue on a different clock.
+    pub fn restore_state(value: &Value) -> Option<ByteTrack> {
+        let tracklets = value["tracklets"]
+            .as_array()?
+            .iter()
+            .map(Tracklet::from_json)
+            .collect::<Option<Vec<_>>>()?;
+        Some(ByteTrack {
+            tracklets,
+            lost_tracks: vec![],
+            removed_tracks: vec![],
+            frame_count: value["frame_count"].as_i64()?.try_into().ok()?,
+            timestamp: value["timestamp"].as_u64()?,
+        })
+    }
+
+    /// Shift the creation and expiry times of the tracklets so the last
+    /// update happened at `timestamp`.
+    ///
+    /// A restored tracker continues as if the frames had not been
+    /// interrupted, the frame timestamps of a new process need not share
+    /// the clock of the saved state.
+    pub fn rebase(&mut self, timestamp: u64) {
+        let offset = timestamp as i128 - self.timestamp as i128;
+        let shift = |t: u64| (t as i128 + offset).clamp(0, u64::MAX as i128) as u64;
+        for track in &mut self.tracklets {
+            track.expiry = shift(track.expiry);
+            track.created = shift(track.created);
+        }
+        self.timestamp = timestamp;
+    }
+
+    /// Advance the tracklets by one frame without an assignment step, for a
+    /// radar frame which never reached the tracker, so the Kalman filters go
+    /// through the same prediction steps as if the frame had been processed.
+    pub fn advance_missing_frame(tracklets: &mut [Tracklet]) {
+        for track in tracklets {
+            track.filter.predict();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -370,7 +539,9 @@ mod tests {
 
     use crate::clustering::tracker::VAALBox;
 
-    use super::{vaalbox_to_xyah, xyah_to_vaalbox};
+    use super::{
+        non_maximum_suppression, vaalbox_to_xyah, xyah_to_vaalbox, ByteTrack, TrackSettings,
+    };
 
     #[test]
     fn filter() {
@@ -398,4 +569,87 @@ mod tests {
         assert!((box1.xmin - box2.xmin).abs() < f32::EPSILON);
         assert!((box1.ymin - box2.ymin).abs() < f32::EPSILON);
     }
+
+    fn unit_box(x: f32, y: f32, score: f32) -> VAALBox {
+        VAALBox {
+            xmin: x,
+            xmax: x + 1.0,
+            ymin: y,
+            ymax: y + 1.0,
+            score,
+            label: 0,
+        }
+    }
+
+    #[test]
+    fn nms() {
+        let boxes = vec![
+            unit_box(0.0, 0.0, 0.5),
+            // Overlaps the first box with an iou of 0.6
+            unit_box(0.25, 0.0, 0.9),
+            unit_box(5.0, 5.0, 0.3),
+            // Overlaps the third box with an iou of 0.33
+            unit_box(5.5, 5.0, 0.3),
+        ];
+
+        let mut kept = boxes.clone();
+        assert_eq!(non_maximum_suppression(&mut kept, 0.5), vec![1, 2, 3]);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].score, 0.9);
+
+        // Equal scores keep the first box.
+        let mut kept = boxes.clone();
+        assert_eq!(non_maximum_suppression(&mut kept, 0.3), vec![1, 2]);
+
+        let mut kept = boxes.clone();
+        assert_eq!(non_maximum_suppression(&mut kept, 1.0), vec![0, 1, 2, 3]);
+        assert_eq!(kept.len(), 4);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let s = TrackSettings::default();
+        let cycle = 55_000_000;
+        let mut tracker = ByteTrack::new();
+        let mut boxes = [unit_box(0.0, 0.0, 0.9)];
+        let id = tracker.update(&s, &mut boxes, cycle)[0]
+            .as_ref()
+            .unwrap()
+            .uuid;
+        for i in 1..10 {
+            let mut boxes = [unit_box(0.2 * i as f32, 0.0, 0.9)];
+            let info = tracker.update(&s, &mut boxes, (i + 1) * cycle);
+            assert_eq!(info[0].as_ref().unwrap().uuid, id);
+        }
+
+        let text = tracker.serialize_state().to_string();
+        let mut restored = ByteTrack::restore_state(&serde_json::from_str(&text).unwrap()).unwrap();
+        assert_eq!(restored.serialize_state(), tracker.serialize_state());
+        let (saved, track) = (&tracker.tracklets[0], &restored.tracklets[0]);
+        assert_eq!(track.id, id);
+        assert_eq!(track.count, 10);
+        assert_eq!(track.filter.mean, saved.filter.mean);
+        assert_eq!(track.filter.covariance, saved.filter.covariance);
+
+        // The restored track continues on the clock of a new process.
+        let start = 3_000_000_000;
+        restored.rebase(start);
+        assert_eq!(restored.tracklets[0].expiry, start + 1_500_000_000);
+        for i in 10..20 {
+            let mut boxes = [unit_box(0.2 * i as f32, 0.0, 0.9)];
+            let info = restored.update(&s, &mut boxes, start + (i - 9) * cycle);
+            let info = info[0].as_ref().unwrap();
+            assert_eq!(info.uuid, id, "frame {}", i);
+            assert_eq!(info.count, i as i32 + 1);
+        }
+        assert_eq!(restored.tracklets.len(), 1);
+
+        assert!(ByteTrack::restore_state(&serde_json::json!({ "tracklets": [{}] })).is_none());
+
+        restored.clear();
+        assert!(restored.tracklets.is_empty());
+        let mut boxes = [unit_box(4.0, 0.0, 0.9)];
+        let info = restored.update(&s, &mut boxes, start + 11 * cycle);
+        assert_ne!(info[0].as_ref().unwrap().uuid, id);
+    }
 }
@@ -1,63 +1,613 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
-use std::net::UdpSocket;
-use tracing::warn;
+use std::{collections::VecDeque, f32::consts::PI, fmt, net::UdpSocket, str::FromStr};
+use tracing::{debug, info, warn};
 
-/// Set real-time FIFO scheduler priority for current thread.
+/// Drop rate above which dropped frames are logged as a warning.
+pub const DROP_RATE_WARNING: f64 = 0.025;
+
+/// Drop rate above which dropped frames are logged as an error.
+pub const DROP_RATE_ERROR: f64 = 0.05;
+
+/// Convert a radar target from spherical to Cartesian coordinates.
 ///
-/// Configures SCHED_FIFO with priority 10 on Linux for low-latency processing.
-/// No-op on non-Linux platforms.
+/// # Arguments
+/// * `range` - Target range in meters
+/// * `azimuth` - Target azimuth in degrees
+/// * `elevation` - Target elevation in degrees
+/// * `mirror` - Mirror the y axis to correct for an upside-down mounting
+///
+/// # Returns
+/// Target position [x, y, z] in meters in the radar frame
+pub fn transform_xyz(range: f32, azimuth: f32, elevation: f32, mirror: bool) -> [f32; 3] {
+    let azi = azimuth / 180.0 * PI;
+    let ele = elevation / 180.0 * PI;
+    let x = range * ele.cos() * azi.cos();
+    let y = range * ele.cos() * azi.sin();
+    let z = range * ele.sin();
+    if mirror {
+        [x, -y, z]
+    } else {
+        [x, y, z]
+    }
+}
+
+/// Scheduling policy of a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedPolicy {
+    /// Normal time-sharing scheduler
+    Other,
+    /// Real-time first in, first out
+    Fifo,
+    /// Real-time round robin
+    RoundRobin,
+}
+
+impl fmt::Display for SchedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchedPolicy::Other => write!(f, "SCHED_OTHER"),
+            SchedPolicy::Fifo => write!(f, "SCHED_FIFO"),
+            SchedPolicy::RoundRobin => write!(f, "SCHED_RR"),
+        }
+    }
+}
+
+/// Set the real-time scheduling policy and priority of the current thread.
+///
+/// A priority of 0 leaves the thread on its current scheduler.  Real-time
+/// scheduling requires root or CAP_SYS_NICE, without it a warning is logged
+/// and the thread keeps running at normal priority.  The effective policy is
+/// logged with the thread `name`.  No-op on non-Linux platforms.
+///
+/// # Returns
+/// The effective scheduling policy of the thread
 #[cfg(target_os = "linux")]
-pub fn set_process_priority() {
-    let mut param = libc::sched_param { sched_priority: 10 };
-    let pid = unsafe { libc::pthread_self() };
-    let err = unsafe {
-        libc::pthread_setschedparam(pid, libc::SCHED_FIFO, &mut param as *mut libc::sched_param)
-    };
-    if err != 0 {
-        let err = std::io::Error::last_os_error();
-        warn!("unable to set udp_read real-time fifo scheduler: {}", err);
+pub fn set_thread_priority(name: &str, policy: SchedPolicy, priority: i32) -> SchedPolicy {
+    let thread = unsafe { libc::pthread_self() };
+
+    if priority > 0 && policy != SchedPolicy::Other {
+        let raw_policy = match policy {
+            SchedPolicy::Fifo => libc::SCHED_FIFO,
+            _ => libc::SCHED_RR,
+        };
+        let param = libc::sched_param {
+            sched_priority: priority,
+        };
+        // Returns the error number rather than setting errno.
+        let err = unsafe { libc::pthread_setschedparam(thread, raw_policy, &param) };
+        if err != 0 {
+            warn!(
+                "unable to set {} thread {} priority {}, running at normal priority \
+                 (requires CAP_SYS_NICE): {}",
+                name,
+                policy,
+                priority,
+                std::io::Error::from_raw_os_error(err)
+            );
+        }
+    }
+
+    let mut raw_policy = 0;
+    let mut param = libc::sched_param { sched_priority: 0 };
+    let effective =
+        match unsafe { libc::pthread_getschedparam(thread, &mut raw_policy, &mut param) } {
+            0 => match raw_policy {
+                libc::SCHED_FIFO => SchedPolicy::Fifo,
+                libc::SCHED_RR => SchedPolicy::RoundRobin,
+                _ => SchedPolicy::Other,
+            },
+            _ => SchedPolicy::Other,
+        };
+    match priority > 0 {
+        true => info!(
+            "{} thread scheduler {} priority {}",
+            name, effective, param.sched_priority
+        ),
+        false => debug!("{} thread scheduler {}", name, effective),
     }
+    effective
 }
 
 #[cfg(not(target_os = "linux"))]
-pub fn set_process_priority() {}
+pub fn set_thread_priority(_name: &str, _policy: SchedPolicy, _priority: i32) -> SchedPolicy {
+    SchedPolicy::Other
+}
 
-/// Configure UDP socket receive buffer size.
+/// Highest number of CPU cores a [`CpuList`] may name, the size of the
+/// kernel cpu_set_t.
+pub const MAX_CPUS: usize = 1024;
+
+/// CPU cores in the kernel cpulist format, for example `0,2-3`, sorted
+/// without duplicates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuList(pub Vec<usize>);
+
+impl FromStr for CpuList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a cpu list such as 0,2-3, got '{}'", s);
+        let core = |core: &str| match core.trim().parse::<usize>() {
+            Ok(core) if core < MAX_CPUS => Ok(core),
+            _ => Err(invalid()),
+        };
+
+        let mut cpus = Vec::new();
+        for part in s.split(',') {
+            match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (core(first)?, core(last)?);
+                    if first > last {
+                        return Err(invalid());
+                    }
+                    cpus.extend(first..=last);
+                }
+                None => cpus.push(core(part)?),
+            }
+        }
+        cpus.sort_unstable();
+        cpus.dedup();
+        Ok(CpuList(cpus))
+    }
+}
+
+impl fmt::Display for CpuList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cpus: Vec<_> = self.0.iter().map(usize::to_string).collect();
+        write!(f, "{}", cpus.join(","))
+    }
+}
+
+/// Pin the current thread to the CPU cores.
 ///
-/// # Arguments
-/// * `socket` - UDP socket to configure
-/// * `size` - Buffer size in bytes
+/// Cores not present on the system are skipped with a warning, should no
+/// core remain or the system call fail the thread is left unpinned.  The
+/// pipeline threads each run a current_thread tokio runtime so pinning the
+/// thread pins every task of its runtime, and threads spawned afterwards
+/// from the pinned thread inherit its cores.  No-op on non-Linux platforms.
 ///
 /// # Returns
-/// Configured socket
+/// The cores the thread was pinned to, empty if it was not pinned
 #[cfg(target_os = "linux")]
-pub fn set_socket_bufsize(socket: UdpSocket, size: usize) -> UdpSocket {
-    use std::os::fd::{FromRawFd, IntoRawFd};
+pub fn set_thread_affinity(name: &str, cpus: &CpuList) -> Vec<usize> {
+    let present = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) }.max(1) as usize;
+    let (cores, missing): (Vec<usize>, Vec<usize>) =
+        cpus.0.iter().partition(|core| **core < present);
+    if !missing.is_empty() {
+        warn!(
+            "{} thread affinity skips cores {:?} not present on this {} core system",
+            name, missing, present
+        );
+    }
+    if cores.is_empty() {
+        warn!("{} thread not pinned, no core of {} is present", name, cpus);
+        return Vec::new();
+    }
 
-    let fd = socket.into_raw_fd();
-    let size = size as libc::c_int;
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for core in &cores {
+        unsafe { libc::CPU_SET(*core, &mut set) };
+    }
+    // A pid of 0 sets the affinity of the calling thread.
+    let err = unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+    if err != 0 {
+        warn!(
+            "unable to pin {} thread to cores {:?}: {}",
+            name,
+            cores,
+            std::io::Error::last_os_error()
+        );
+        return Vec::new();
+    }
+
+    info!("{} thread pinned to cores {:?}", name, cores);
+    cores
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_thread_affinity(_name: &str, _cpus: &CpuList) -> Vec<usize> {
+    Vec::new()
+}
+
+/// Set a socket option of type int at the SOL_SOCKET level.
+#[cfg(target_os = "linux")]
+fn set_socket_option(socket: &UdpSocket, option: libc::c_int, value: usize) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let value = value.min(libc::c_int::MAX as usize) as libc::c_int;
     let err = unsafe {
         libc::setsockopt(
-            fd,
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            option,
+            &value as *const _ as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    match err {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Returns the receive buffer size of the socket as reported by the kernel,
+/// which doubles the requested size to account for its bookkeeping.
+#[cfg(target_os = "linux")]
+pub fn socket_bufsize(socket: &UdpSocket) -> std::io::Result<usize> {
+    use std::os::fd::AsRawFd;
+
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of_val(&value) as libc::socklen_t;
+    let err = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
             libc::SOL_SOCKET,
             libc::SO_RCVBUF,
-            &size as *const _ as *const libc::c_void,
-            std::mem::size_of_val(&size) as libc::socklen_t,
+            &mut value as *mut _ as *mut libc::c_void,
+            &mut len,
         )
     };
-    if err != 0 {
-        warn!(
-            "setsockopt SO_RCVBUF failed: {}",
-            std::io::Error::last_os_error()
-        );
+    match err {
+        0 => Ok(value.max(0) as usize),
+        _ => Err(std::io::Error::last_os_error()),
     }
+}
 
-    unsafe { UdpSocket::from_raw_fd(fd) }
+/// Configure UDP socket receive buffer size.
+///
+/// SO_RCVBUFFORCE is tried first as it ignores the net.core.rmem_max limit
+/// but requires CAP_NET_ADMIN, otherwise SO_RCVBUF is used and the kernel
+/// silently clamps the size to rmem_max.  A warning naming both the limit
+/// and the capability is logged when the effective size falls short.
+///
+/// # Arguments
+/// * `name` - Socket name used in the log messages
+/// * `socket` - UDP socket to configure
+/// * `size` - Buffer size in bytes
+///
+/// # Returns
+/// The effective buffer size read back from the socket, as reported by
+/// [`socket_bufsize`], or 0 if it could not be read
+#[cfg(target_os = "linux")]
+pub fn set_socket_bufsize(name: &str, socket: &UdpSocket, size: usize) -> usize {
+    if let Err(err) = set_socket_option(socket, libc::SO_RCVBUFFORCE, size) {
+        debug!("{} SO_RCVBUFFORCE failed, using SO_RCVBUF: {}", name, err);
+        if let Err(err) = set_socket_option(socket, libc::SO_RCVBUF, size) {
+            warn!("{} setsockopt SO_RCVBUF failed: {}", name, err);
+        }
+    }
+
+    let effective = match socket_bufsize(socket) {
+        Ok(effective) => effective,
+        Err(err) => {
+            warn!("{} getsockopt SO_RCVBUF failed: {}", name, err);
+            return 0;
+        }
+    };
+    match receive_buffer_granted(effective, size) {
+        true => info!(
+            "{} receive buffer requested {} bytes, got {}",
+            name, size, effective
+        ),
+        false => warn!(
+            "{} receive buffer requested {} bytes, got {}, raise net.core.rmem_max \
+             or grant CAP_NET_ADMIN to avoid dropped packets",
+            name, size, effective
+        ),
+    }
+    effective
+}
+
+/// Whether the receive buffer read back from the socket covers the requested
+/// size.  The kernel reports twice the buffer it grants, a request clamped to
+/// half its size would otherwise pass.
+#[cfg(target_os = "linux")]
+fn receive_buffer_granted(effective: usize, size: usize) -> bool {
+    effective / 2 >= size
 }
 
 #[cfg(not(target_os = "linux"))]
-pub fn set_socket_bufsize(socket: UdpSocket, _size: usize) -> UdpSocket {
-    socket
+pub fn set_socket_bufsize(_name: &str, _socket: &UdpSocket, _size: usize) -> usize {
+    0
+}
+
+/// Statistics over a rolling window of the most recent samples.
+///
+/// The samples are kept in a ring buffer, once `capacity` samples have been
+/// pushed every new sample replaces the oldest one.  Frame rates are tracked
+/// by pushing the interval between frames in seconds, see
+/// [`RollingStats::rate`], and drop rates by pushing 1.0 for every dropped
+/// frame and 0.0 otherwise so the mean is the fraction of dropped frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollingStats {
+    samples: VecDeque<f64>,
+    capacity: usize,
+}
+
+impl RollingStats {
+    /// Create an empty window holding at most `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        RollingStats {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add a sample, replacing the oldest sample once the window is full.
+    pub fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Returns the number of samples in the window.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns true if no samples have been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the mean of the samples, None if empty.
+    pub fn mean(&self) -> Option<f64> {
+        match self.samples.len() {
+            0 => None,
+            n => Some(self.samples.iter().sum::<f64>() / n as f64),
+        }
+    }
+
+    /// Returns the smallest sample, None if empty.
+    pub fn min(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::min)
+    }
+
+    /// Returns the largest sample, None if empty.
+    pub fn max(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::max)
+    }
+
+    /// Returns the (mean, min, max) rate in Hz for a window of intervals in
+    /// seconds.  The slowest rate comes from the longest interval.
+    pub fn rate(&self) -> Option<(f64, f64, f64)> {
+        match (self.mean(), self.min(), self.max()) {
+            (Some(mean), Some(min), Some(max)) if min > 0.0 => {
+                Some((1.0 / mean, 1.0 / max, 1.0 / min))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Normalize the values to [0, 1] by the range of their finite values.
+///
+/// Non-finite values map to 0 and don't take part in the range, a constant
+/// frame maps every finite value to 0.5 rather than dividing by a zero span.
+pub fn normalize(values: &[f32]) -> Vec<f32> {
+    let (min, max) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+    let span = max - min;
+
+    values
+        .iter()
+        .map(|&v| match (v.is_finite(), span > f32::EPSILON) {
+            (false, _) => 0.0,
+            (true, true) => ((v - min) / span).clamp(0.0, 1.0),
+            (true, false) => 0.5,
+        })
+        .collect()
+}
+
+/// Logging severity of a drop rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DropSeverity {
+    /// Below [`DROP_RATE_WARNING`]
+    Normal,
+
+    /// At or above [`DROP_RATE_WARNING`]
+    Warning,
+
+    /// At or above [`DROP_RATE_ERROR`]
+    Error,
+}
+
+impl DropSeverity {
+    /// Classify a drop rate given as a fraction of frames.
+    pub fn from_rate(rate: f64) -> Self {
+        if rate >= DROP_RATE_ERROR {
+            DropSeverity::Error
+        } else if rate >= DROP_RATE_WARNING {
+            DropSeverity::Warning
+        } else {
+            DropSeverity::Normal
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_thread_priority() {
+        // A fresh thread so the test runner thread keeps its scheduler.
+        let (disabled, effective) = std::thread::spawn(|| {
+            let disabled = set_thread_priority("test", SchedPolicy::Fifo, 0);
+            (disabled, set_thread_priority("test", SchedPolicy::Fifo, 10))
+        })
+        .join()
+        .unwrap();
+        assert_eq!(disabled, SchedPolicy::Other);
+        // Unprivileged the thread stays on the normal scheduler.
+        assert!(matches!(effective, SchedPolicy::Fifo | SchedPolicy::Other));
+        assert_eq!(SchedPolicy::RoundRobin.to_string(), "SCHED_RR");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_socket_bufsize() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        // Below the default rmem_max the kernel doubles the request.
+        let effective = set_socket_bufsize("test", &socket, 32 * 1024);
+        assert_eq!(effective, socket_bufsize(&socket).unwrap());
+        assert!(effective >= 2 * 32 * 1024);
+
+        // Unprivileged a large request is clamped rather than failing, the
+        // read back value is what the socket actually got.
+        let effective = set_socket_bufsize("test", &socket, 64 * 1024 * 1024);
+        assert_eq!(effective, socket_bufsize(&socket).unwrap());
+        assert!(effective > 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_receive_buffer_granted() {
+        // A granted request is read back doubled.
+        assert!(receive_buffer_granted(2 * 4096, 4096));
+        assert!(receive_buffer_granted(3 * 4096, 4096));
+        // A request clamped to half its size reads back as the request.
+        assert!(!receive_buffer_granted(4096, 4096));
+        assert!(!receive_buffer_granted(2 * 4096 - 1, 4096));
+    }
+
+    #[test]
+    fn test_cpu_list() {
+        assert_eq!("2-3".parse(), Ok(CpuList(vec![2, 3])));
+        assert_eq!("5, 0,2-3,3".parse(), Ok(CpuList(vec![0, 2, 3, 5])));
+        assert_eq!("1-1".parse::<CpuList>().unwrap().to_string(), "1");
+        assert_eq!(CpuList(vec![0, 2, 3]).to_string(), "0,2,3");
+
+        for invalid in [
+            "", "a", "3-2", "1-", "-1", "0,,1", "1-2-3", "1024", "0-4096",
+        ] {
+            assert!(invalid.parse::<CpuList>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_thread_affinity() {
+        // Pin a fresh thread to a core this process may already run on.
+        let mut current: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<libc::cpu_set_t>();
+        assert_eq!(unsafe { libc::sched_getaffinity(0, size, &mut current) }, 0);
+        let core = (0..MAX_CPUS)
+            .find(|core| unsafe { libc::CPU_ISSET(*core, &current) })
+            .unwrap();
+
+        let (pinned, missing) = std::thread::spawn(move || {
+            let pinned = set_thread_affinity("test", &CpuList(vec![core, MAX_CPUS - 1]));
+            let missing = set_thread_affinity("test", &CpuList(vec![MAX_CPUS - 1]));
+            (pinned, missing)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(pinned, [core]);
+        // A core which does not exist leaves the thread unpinned.
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_rolling_stats() {
+        let mut stats = RollingStats::new(4);
+        assert!(stats.is_empty());
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.rate(), None);
+
+        for sample in [2.0, 4.0, 6.0] {
+            stats.push(sample);
+        }
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.mean(), Some(4.0));
+        assert_eq!(stats.min(), Some(2.0));
+        assert_eq!(stats.max(), Some(6.0));
+
+        // The oldest samples roll out of the window.
+        for sample in [8.0, 1.0, 3.0] {
+            stats.push(sample);
+        }
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats.mean(), Some(4.5));
+        assert_eq!(stats.min(), Some(1.0));
+        assert_eq!(stats.max(), Some(8.0));
+
+        let mut intervals = RollingStats::new(3);
+        for interval in [0.05, 0.1, 0.25] {
+            intervals.push(interval);
+        }
+        let (mean, min, max) = intervals.rate().unwrap();
+        assert!((mean - 7.5).abs() < 1e-9);
+        assert!((min - 4.0).abs() < 1e-9);
+        assert!((max - 20.0).abs() < 1e-9);
+
+        intervals.push(0.0);
+        assert_eq!(intervals.rate(), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert!(normalize(&[]).is_empty());
+        assert_eq!(normalize(&[-80.0, -70.0, -60.0]), vec![0.0, 0.5, 1.0]);
+
+        // A single value or all equal powers have no span.
+        assert_eq!(normalize(&[-42.0]), vec![0.5]);
+        assert_eq!(normalize(&[-55.5; 4]), vec![0.5; 4]);
+
+        // Non-finite values are left out of the range.
+        let values = normalize(&[f32::NAN, 10.0, f32::INFINITY, 20.0, f32::NEG_INFINITY]);
+        assert_eq!(values, vec![0.0, 0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(normalize(&[f32::NAN, f32::NAN]), vec![0.0, 0.0]);
+
+        // Rounding on a large offset never leaves [0, 1].
+        let values = normalize(&[1e7, 1e7 + 1.0, 1e7 + 3.0]);
+        assert!(values.iter().all(|v| (0.0..=1.0).contains(v)));
+    }
+
+    #[test]
+    fn test_drop_severity() {
+        assert_eq!(DropSeverity::from_rate(0.0), DropSeverity::Normal);
+        assert_eq!(DropSeverity::from_rate(0.024), DropSeverity::Normal);
+        assert_eq!(DropSeverity::from_rate(0.025), DropSeverity::Warning);
+        assert_eq!(DropSeverity::from_rate(0.049), DropSeverity::Warning);
+        assert_eq!(DropSeverity::from_rate(0.05), DropSeverity::Error);
+        assert_eq!(DropSeverity::from_rate(1.0), DropSeverity::Error);
+
+        // A burst of drops raises the severity as it enters the 100 frame
+        // window and clears it again as it rolls out.
+        let mut drops = RollingStats::new(100);
+        let severity = |drops: &mut RollingStats, dropped: bool| {
+            drops.push(if dropped { 1.0 } else { 0.0 });
+            DropSeverity::from_rate(drops.mean().unwrap())
+        };
+        let mut transitions = vec![];
+        let mut last = DropSeverity::Normal;
+        for frame in 0..300 {
+            let current = severity(&mut drops, (100..106).contains(&frame));
+            if current != last {
+                transitions.push((frame, current));
+                last = current;
+            }
+        }
+        assert_eq!(
+            transitions,
+            [
+                (102, DropSeverity::Warning),
+                (104, DropSeverity::Error),
+                (201, DropSeverity::Warning),
+                (203, DropSeverity::Normal),
+            ]
+        );
+    }
 }
@@ -0,0 +1,588 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::can::{
+    self, read_parameter, read_status, send_command, write_parameter, CanReader, CanWriter,
+    Command, Parameter, RecvResponseSettings, Status,
+};
+use clap::ValueEnum;
+use log::{debug, warn};
+use serde_json::{json, Map, Value};
+use std::{fmt, time::Duration};
+use zenoh::{
+    bytes::Encoding,
+    handlers::FifoChannelHandler,
+    query::{Query, Queryable},
+    Session,
+};
+
+/// Default key expression of the radarpub control queryable.
+pub const CONTROL_TOPIC: &str = "rt/radar/control";
+
+/// Default key expression of the radarpub parameter dump queryable.
+pub const PARAM_DUMP_TOPIC: &str = "rt/radar/dump_params";
+
+/// Time to wait for the control queryable to reply.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time allowed to read the full sensor state for a parameter dump.
+pub const PARAM_DUMP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Radar control errors.
+#[derive(Debug)]
+pub enum Error {
+    /// CAN error on the radarpub side
+    Can(can::Error),
+    /// Zenoh session or query error
+    Zenoh(zenoh::Error),
+    /// Malformed request or reply
+    Protocol(String),
+    /// Error reported by the remote radarpub
+    Remote(String),
+    /// No reply before the request timed out
+    NoReply,
+    /// The sensor did not respond within the given time
+    Timeout(Duration),
+}
+
+impl std::error::Error for Error {}
+
+impl From<can::Error> for Error {
+    fn from(err: can::Error) -> Error {
+        Error::Can(err)
+    }
+}
+
+impl From<zenoh::Error> for Error {
+    fn from(err: zenoh::Error) -> Error {
+        Error::Zenoh(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Can(err) => write!(f, "can error: {}", err),
+            Error::Zenoh(err) => write!(f, "zenoh error: {}", err),
+            Error::Protocol(msg) => write!(f, "protocol error: {}", msg),
+            Error::Remote(msg) => write!(f, "remote error: {}", msg),
+            Error::NoReply => write!(f, "no reply from control queryable"),
+            Error::Timeout(timeout) => write!(f, "no sensor response within {:?}", timeout),
+        }
+    }
+}
+
+/// Sensor request forwarded by the control queryable.  The JSON encoding
+/// uses the same value names as the drvegrdctl command line, for example
+/// `{"parameter": "center_frequency", "value": 1}`.
+#[derive(Copy, Clone, Debug)]
+pub enum Request {
+    /// Read a status field
+    ReadStatus(Status),
+    /// Read a parameter
+    ReadParameter(Parameter),
+    /// Write a parameter, the sensor replies with the confirmed value
+    WriteParameter(Parameter, u32),
+    /// Send a command with its argument
+    SendCommand(Command, u32),
+}
+
+fn value_name<T: ValueEnum>(value: &T) -> Result<String, Error> {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .ok_or_else(|| Error::Protocol("value has no name".to_string()))
+}
+
+fn parse_name<T: ValueEnum>(value: &Value) -> Result<T, Error> {
+    let name = value
+        .as_str()
+        .ok_or_else(|| Error::Protocol(format!("expected a name: {}", value)))?;
+    T::from_str(name, false).map_err(Error::Protocol)
+}
+
+fn parse_value(value: &Value) -> Result<u32, Error> {
+    value
+        .as_u64()
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| Error::Protocol(format!("invalid value: {}", value)))
+}
+
+impl Request {
+    /// Encode the request as JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        let msg = match self {
+            Request::ReadStatus(status) => json!({ "status": value_name(status)? }),
+            Request::ReadParameter(param) => json!({ "parameter": value_name(param)? }),
+            Request::WriteParameter(param, value) => {
+                json!({ "parameter": value_name(param)?, "value": value })
+            }
+            Request::SendCommand(command, value) => {
+                json!({ "command": value_name(command)?, "value": value })
+            }
+        };
+
+        Ok(msg.to_string())
+    }
+
+    /// Decode a request from JSON.
+    pub fn from_json(msg: &str) -> Result<Request, Error> {
+        let msg: Value =
+            serde_json::from_str(msg).map_err(|err| Error::Protocol(err.to_string()))?;
+        let value = msg.get("value");
+
+        if let Some(status) = msg.get("status") {
+            Ok(Request::ReadStatus(parse_name(status)?))
+        } else if let Some(command) = msg.get("command") {
+            let value = value.ok_or_else(|| Error::Protocol("command requires a value".into()))?;
+            Ok(Request::SendCommand(
+                parse_name(command)?,
+                parse_value(value)?,
+            ))
+        } else if let Some(param) = msg.get("parameter") {
+            match value {
+                Some(value) => Ok(Request::WriteParameter(
+                    parse_name(param)?,
+                    parse_value(value)?,
+                )),
+                None => Ok(Request::ReadParameter(parse_name(param)?)),
+            }
+        } else {
+            Err(Error::Protocol(format!("unknown request: {}", msg)))
+        }
+    }
+
+    /// Execute the request on the CAN bus and return the sensor response.
+    pub async fn execute(
+        &self,
+        can: &(impl CanReader + CanWriter),
+        settings: &RecvResponseSettings,
+    ) -> Result<u32, Error> {
+        Ok(match *self {
+            Request::ReadStatus(status) => read_status(can, status, settings).await?,
+            Request::ReadParameter(param) => read_parameter(can, param, settings).await?,
+            Request::WriteParameter(param, value) => {
+                write_parameter(can, param, value, settings).await?
+            }
+            Request::SendCommand(command, value) => {
+                send_command(can, command, value, settings).await?
+            }
+        })
+    }
+}
+
+fn encode_reply(result: &Result<u32, Error>) -> String {
+    match result {
+        Ok(value) => json!({ "value": value }),
+        Err(err) => json!({ "error": err.to_string() }),
+    }
+    .to_string()
+}
+
+fn decode_reply(msg: &str) -> Result<u32, Error> {
+    let msg: Value = serde_json::from_str(msg).map_err(|err| Error::Protocol(err.to_string()))?;
+    if let Some(err) = msg.get("error") {
+        return Err(Error::Remote(err.as_str().unwrap_or_default().to_string()));
+    }
+
+    match msg.get("value") {
+        Some(value) => parse_value(value),
+        None => Err(Error::Protocol(format!("invalid reply: {}", msg))),
+    }
+}
+
+/// Read every named parameter and status field of the sensor.
+///
+/// The JSON object uses the drvegrdctl value names as keys, for example
+/// `{"center_frequency": 1, ..., "serial_number": 12345, "version": "3.2.1"}`,
+/// with the firmware version composed from the version status fields.
+///
+/// # Errors
+/// Returns Error if CAN communication fails or sensor reports error
+pub async fn dump_params(
+    can: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+) -> Result<String, Error> {
+    let mut dump = Map::new();
+    for param in Parameter::value_variants() {
+        let value = read_parameter(can, *param, settings).await?;
+        dump.insert(value_name(param)?, value.into());
+    }
+    for status in Status::value_variants() {
+        let value = read_status(can, *status, settings).await?;
+        dump.insert(value_name(status)?, value.into());
+    }
+
+    let field = |name: &str| dump.get(name).cloned().unwrap_or_default();
+    let version = format!(
+        "{}.{}.{}",
+        field("major_version"),
+        field("minor_version"),
+        field("patch_version")
+    );
+    dump.insert("version".to_string(), version.into());
+
+    Ok(Value::Object(dump).to_string())
+}
+
+/// Execute a control request and reply with the sensor response.
+async fn reply_request(
+    query: &Query,
+    can: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+    on_write: &mut impl FnMut(Parameter, u32, u32),
+) {
+    let request = match query.payload().map(|payload| payload.try_to_string()) {
+        Some(Ok(payload)) => Request::from_json(&payload),
+        _ => Err(Error::Protocol("missing request payload".to_string())),
+    };
+
+    let result = match request {
+        Ok(request) => {
+            debug!("control request {:?}", request);
+            let result = request.execute(can, settings).await;
+            if let (Request::WriteParameter(param, requested), Ok(confirmed)) = (request, &result) {
+                on_write(param, requested, *confirmed);
+            }
+            result
+        }
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = &result {
+        warn!("control request failed: {}", err);
+    }
+
+    if let Err(err) = query
+        .reply(query.key_expr().clone(), encode_reply(&result))
+        .encoding(Encoding::APPLICATION_JSON)
+        .await
+    {
+        warn!("control reply error: {}", err);
+    }
+}
+
+/// Dump the sensor state and reply with the JSON object, or with an error
+/// reply if the sensor does not respond within [`PARAM_DUMP_TIMEOUT`].
+async fn reply_dump(
+    query: &Query,
+    can: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+) {
+    debug!("parameter dump request");
+    let result = match tokio::time::timeout(PARAM_DUMP_TIMEOUT, dump_params(can, settings)).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::Timeout(PARAM_DUMP_TIMEOUT)),
+    };
+
+    let reply = match result {
+        Ok(dump) => {
+            query
+                .reply(query.key_expr().clone(), dump)
+                .encoding(Encoding::APPLICATION_JSON)
+                .await
+        }
+        Err(err) => {
+            warn!("parameter dump failed: {}", err);
+            query
+                .reply_err(json!({ "error": err.to_string() }).to_string())
+                .encoding(Encoding::APPLICATION_JSON)
+                .await
+        }
+    };
+    if let Err(err) = reply {
+        warn!("parameter dump reply error: {}", err);
+    }
+}
+
+/// Wait for the next query, never completes without a queryable.
+async fn next_query(queryable: &Option<Queryable<FifoChannelHandler<Query>>>) -> Option<Query> {
+    match queryable {
+        Some(queryable) => queryable.recv_async().await.ok(),
+        None => std::future::pending().await,
+    }
+}
+
+/// Serve sensor requests on the control queryable and parameter dumps on
+/// the dump queryable, each only declared when its topic is given.
+///
+/// Queries are executed one at a time on the CAN bus, `can` should only
+/// receive instruction responses, see [`can::split_responses`].  Every
+/// successful parameter write is reported through `on_write` with the value
+/// confirmed by the sensor so the caller can update the published
+/// configuration.
+///
+/// # Errors
+/// Returns an error if a queryable cannot be declared
+pub async fn serve(
+    session: &Session,
+    topic: Option<&str>,
+    dump_topic: Option<&str>,
+    can: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+    mut on_write: impl FnMut(Parameter, u32, u32),
+) -> Result<(), Error> {
+    let control = match topic {
+        Some(topic) => Some(session.declare_queryable(topic).await?),
+        None => None,
+    };
+    let dump = match dump_topic {
+        Some(topic) => Some(session.declare_queryable(topic).await?),
+        None => None,
+    };
+
+    loop {
+        tokio::select! {
+            query = next_query(&control) => match query {
+                Some(query) => reply_request(&query, can, settings, &mut on_write).await,
+                None => break,
+            },
+            query = next_query(&dump) => match query {
+                Some(query) => reply_dump(&query, can, settings).await,
+                None => break,
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a request to the control queryable and wait for the sensor response.
+///
+/// # Errors
+/// Returns an error if no radarpub replies or the request fails remotely
+pub async fn request(session: &Session, topic: &str, request: &Request) -> Result<u32, Error> {
+    let replies = session
+        .get(topic)
+        .payload(request.to_json()?)
+        .encoding(Encoding::APPLICATION_JSON)
+        .timeout(REQUEST_TIMEOUT)
+        .await?;
+
+    let reply = replies.recv_async().await.map_err(|_| Error::NoReply)?;
+    match reply.result() {
+        Ok(sample) => {
+            let payload = sample
+                .payload()
+                .try_to_string()
+                .map_err(|err| Error::Protocol(err.to_string()))?;
+            decode_reply(&payload)
+        }
+        Err(err) => Err(Error::Remote(format!("{:?}", err))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can::{split_responses, CanInjector, Packet, RESPONSE_ID};
+    use std::sync::{Arc, Mutex};
+
+    /// Response to an instruction with result 0 and the given value.
+    fn response(value: u32) -> [Packet; 4] {
+        let value = value.to_le_bytes();
+        [
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 5, 0, 0, 1, 0, 0]),
+            },
+            Packet { id: 0x700, data: 0 },
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 2, 0, value[0], value[1], value[2], value[3]]),
+            },
+            Packet { id: 0x700, data: 0 },
+        ]
+    }
+
+    #[test]
+    fn test_request_json() {
+        let requests = [
+            Request::ReadStatus(Status::SerialNumber),
+            Request::ReadParameter(Parameter::RangeToggle),
+            Request::WriteParameter(Parameter::CenterFrequency, 2),
+            Request::SendCommand(Command::SensorReset, 1),
+        ];
+
+        for request in requests {
+            let json = request.to_json().unwrap();
+            let decoded = Request::from_json(&json).unwrap();
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", request));
+        }
+
+        assert_eq!(
+            Request::WriteParameter(Parameter::CenterFrequency, 2)
+                .to_json()
+                .unwrap(),
+            r#"{"parameter":"center_frequency","value":2}"#
+        );
+        assert!(Request::from_json(r#"{"command":"sensor_reset"}"#).is_err());
+        assert!(Request::from_json(r#"{"parameter":"unknown"}"#).is_err());
+        assert!(Request::from_json(r#"{"value":1}"#).is_err());
+    }
+
+    #[test]
+    fn test_reply_json() {
+        assert_eq!(decode_reply(&encode_reply(&Ok(7))).unwrap(), 7);
+        assert!(matches!(
+            decode_reply(&encode_reply(&Err(Error::NoReply))),
+            Err(Error::Remote(_))
+        ));
+    }
+
+    /// Runs the radarpub control queryable on a mock CAN bus and drives it
+    /// through the drvegrdctl client over a local zenoh session.
+    #[test]
+    fn test_control_end_to_end() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let mut config = zenoh::Config::default();
+            config
+                .insert_json5("scouting/multicast/enabled", "false")
+                .unwrap();
+            config.insert_json5("listen/endpoints", "[]").unwrap();
+            let session = zenoh::open(config).await.unwrap();
+
+            let can = CanInjector::new();
+            // Target traffic on the bus must not disturb the responses.
+            can.push(Packet { id: 0x400, data: 0 });
+            can.reply(response(123456));
+            can.reply(response(1));
+
+            let (frames, responses) = split_responses(can.clone(), RESPONSE_ID);
+            let writes = Arc::new(Mutex::new(Vec::new()));
+
+            let server_session = session.clone();
+            let server_writes = writes.clone();
+            tokio::spawn(async move {
+                serve(
+                    &server_session,
+                    Some(CONTROL_TOPIC),
+                    None,
+                    &responses,
+                    &RecvResponseSettings::default(),
+                    move |param, requested, confirmed| {
+                        server_writes
+                            .lock()
+                            .unwrap()
+                            .push((param as u16, requested, confirmed))
+                    },
+                )
+                .await
+                .unwrap()
+            });
+            // Give the queryable time to be declared.
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let serial = request(
+                &session,
+                CONTROL_TOPIC,
+                &Request::ReadStatus(Status::SerialNumber),
+            )
+            .await
+            .unwrap();
+            assert_eq!(serial, 123456);
+
+            // The sensor clamps the requested value.
+            let confirmed = request(
+                &session,
+                CONTROL_TOPIC,
+                &Request::WriteParameter(Parameter::DetectionSensitivity, 5),
+            )
+            .await
+            .unwrap();
+            assert_eq!(confirmed, 1);
+            assert_eq!(
+                *writes.lock().unwrap(),
+                vec![(Parameter::DetectionSensitivity as u16, 5, 1)]
+            );
+
+            assert_eq!(frames.read_frame().await.unwrap().id, 0x400);
+            assert_eq!(can.sent().len(), 6);
+
+            // Without a radarpub serving the key the client gets no reply.
+            let err = request(
+                &session,
+                "rt/radar/unknown",
+                &Request::ReadStatus(Status::MajorVersion),
+            )
+            .await;
+            assert!(matches!(err, Err(Error::NoReply)));
+        });
+    }
+
+    #[test]
+    fn test_param_dump() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let mut config = zenoh::Config::default();
+            config
+                .insert_json5("scouting/multicast/enabled", "false")
+                .unwrap();
+            config.insert_json5("listen/endpoints", "[]").unwrap();
+            let session = zenoh::open(config).await.unwrap();
+
+            // The parameters followed by the status fields.
+            let can = CanInjector::new();
+            for value in [1, 2, 0, 3, 1, 4, 3, 2, 1, 12345] {
+                can.reply(response(value));
+            }
+
+            let (_frames, responses) = split_responses(can.clone(), RESPONSE_ID);
+            let server_session = session.clone();
+            tokio::spawn(async move {
+                // A read timeout beyond the dump timeout, a silent sensor
+                // fails the dump rather than the CAN read.
+                let settings = RecvResponseSettings {
+                    max_retries: 1,
+                    read_timeout: Duration::from_secs(5),
+                    ..Default::default()
+                };
+                serve(
+                    &server_session,
+                    None,
+                    Some(PARAM_DUMP_TOPIC),
+                    &responses,
+                    &settings,
+                    |_, _, _| (),
+                )
+                .await
+                .unwrap()
+            });
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let replies = session.get(PARAM_DUMP_TOPIC).await.unwrap();
+            let reply = replies.recv_async().await.unwrap();
+            let payload = reply.result().unwrap().payload().try_to_string().unwrap();
+            let dump: Value = serde_json::from_str(&payload).unwrap();
+            assert_eq!(dump["center_frequency"], 1);
+            assert_eq!(dump["frequency_sweep"], 2);
+            assert_eq!(dump["range_toggle"], 0);
+            assert_eq!(dump["detection_sensitivity"], 3);
+            assert_eq!(dump["enable_target_list"], 1);
+            assert_eq!(dump["software_generation"], 4);
+            assert_eq!(dump["serial_number"], 12345);
+            assert_eq!(dump["version"], "3.2.1");
+            assert_eq!(can.sent().len(), 30);
+
+            // The sensor no longer responds, the dump times out.
+            let replies = session.get(PARAM_DUMP_TOPIC).await.unwrap();
+            let reply = replies.recv_async().await.unwrap();
+            let err = reply
+                .result()
+                .unwrap_err()
+                .payload()
+                .try_to_string()
+                .unwrap();
+            assert!(err.contains("no sensor response within 2s"), "{}", err);
+        });
+    }
+}
@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Window the thread utilisation is averaged over.
+pub const CPU_WINDOW: Duration = Duration::from_secs(5);
+
+/// Utilisation of one core, in percent, above which a thread is reported.
+pub const CPU_WARN_PERCENT: f32 = 80.0;
+
+/// CPU utilisation of a thread over the [`CPU_WINDOW`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadUsage {
+    /// Kernel thread id
+    pub tid: u32,
+    /// Thread name, truncated to 15 characters by the kernel
+    pub name: String,
+    /// Utilisation in percent of one core
+    pub percent: f32,
+}
+
+/// Per-thread CPU utilisation of the process.
+///
+/// Every [`CpuMonitor::sample`] reads the CPU time of each thread from
+/// `/proc/self/task/{tid}/stat` and averages it over the [`CPU_WINDOW`],
+/// warning once when a thread rises above [`CPU_WARN_PERCENT`].  Other
+/// platforms than Linux report no threads.
+#[derive(Debug, Default)]
+pub struct CpuMonitor {
+    /// CPU time samples in clock ticks of every thread, oldest first
+    samples: HashMap<u32, VecDeque<(Instant, u64)>>,
+    /// Threads above CPU_WARN_PERCENT which have been warned about
+    overloaded: HashSet<u32>,
+}
+
+impl CpuMonitor {
+    /// Create a monitor without samples.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sample the threads of the process and return their utilisation
+    /// ordered by thread id, 0 for threads sampled for the first time.
+    pub fn sample(&mut self) -> Vec<ThreadUsage> {
+        self.update(Instant::now(), read_threads(), clock_ticks())
+    }
+
+    fn update(
+        &mut self,
+        now: Instant,
+        threads: Vec<(u32, String, u64)>,
+        ticks_per_sec: u64,
+    ) -> Vec<ThreadUsage> {
+        // Forget the threads which have exited.
+        let tids: HashSet<_> = threads.iter().map(|(tid, _, _)| *tid).collect();
+        self.samples.retain(|tid, _| tids.contains(tid));
+        self.overloaded.retain(|tid| tids.contains(tid));
+
+        let mut usage = Vec::with_capacity(threads.len());
+        for (tid, name, ticks) in threads {
+            let samples = self.samples.entry(tid).or_default();
+            samples.push_back((now, ticks));
+            // Keep the newest sample at least a window old as the start.
+            while samples.len() > 2 && now.duration_since(samples[1].0) >= CPU_WINDOW {
+                samples.pop_front();
+            }
+
+            let (start, start_ticks) = samples[0];
+            let elapsed = now.duration_since(start).as_secs_f32();
+            let percent = match elapsed > 0.0 {
+                true => {
+                    let busy = ticks.saturating_sub(start_ticks) as f32 / ticks_per_sec as f32;
+                    100.0 * busy / elapsed
+                }
+                false => 0.0,
+            };
+
+            if percent > CPU_WARN_PERCENT {
+                if self.overloaded.insert(tid) {
+                    warn!(
+                        "thread {} ({}) at {:.0}% cpu over {:?}",
+                        name, tid, percent, CPU_WINDOW
+                    );
+                }
+            } else if self.overloaded.remove(&tid) {
+                info!("thread {} ({}) back at {:.0}% cpu", name, tid, percent);
+            }
+
+            usage.push(ThreadUsage { tid, name, percent });
+        }
+
+        usage.sort_by_key(|thread| thread.tid);
+        usage
+    }
+}
+
+/// Thread name and CPU time in clock ticks, user plus system, of a
+/// `/proc/[pid]/task/[tid]/stat` line.
+#[cfg(target_os = "linux")]
+fn parse_stat(stat: &str) -> Option<(String, u64)> {
+    // The name is in parentheses and may itself contain spaces or ')'.
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat.get(open + 1..close)?.to_string();
+
+    // The fields after the name start at field 3 (state), utime and stime
+    // are fields 14 and 15.
+    let fields: Vec<_> = stat.get(close + 1..)?.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, utime + stime))
+}
+
+#[cfg(target_os = "linux")]
+fn read_threads() -> Vec<(u32, String, u64)> {
+    let tasks = match std::fs::read_dir("/proc/self/task") {
+        Ok(tasks) => tasks,
+        Err(_) => return Vec::new(),
+    };
+
+    // Threads may exit between listing and reading their stat.
+    tasks
+        .filter_map(|entry| {
+            let tid: u32 = entry.ok()?.file_name().to_str()?.parse().ok()?;
+            let stat = std::fs::read_to_string(format!("/proc/self/task/{}/stat", tid)).ok()?;
+            let (name, ticks) = parse_stat(&stat)?;
+            Some((tid, name, ticks))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks() -> u64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    ticks.max(1) as u64
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_threads() -> Vec<(u32, String, u64)> {
+    Vec::new()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clock_ticks() -> u64 {
+    100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_stat() {
+        let stat = "4242 (tokio-runtime-w) S 1 4240 4240 0 -1 4194368 310 0 0 0 \
+                    1200 34 0 0 20 0 9 0 4415 1095344128 2967 18446744073709551615";
+        assert_eq!(
+            parse_stat(stat),
+            Some(("tokio-runtime-w".to_string(), 1234))
+        );
+        let stat = "7 (odd) name)) R 1 7 7 0 -1 0 0 0 0 0 5 6 0 0 20 0 1 0 1 0 0 0";
+        assert_eq!(parse_stat(stat), Some(("odd) name)".to_string(), 11)));
+        assert_eq!(parse_stat("7 (short) R 1"), None);
+
+        // The monitor finds at least this thread.
+        let usage = CpuMonitor::new().sample();
+        assert!(!usage.is_empty());
+        assert!(usage.iter().all(|thread| thread.percent == 0.0));
+    }
+
+    #[test]
+    fn test_cpu_monitor_window() {
+        let mut monitor = CpuMonitor::new();
+        let start = Instant::now();
+        let threads = |cluster: u64, cube: u64| {
+            vec![
+                (20, "cluster".to_string(), cluster),
+                (10, "cube".to_string(), cube),
+            ]
+        };
+
+        let usage = monitor.update(start, threads(0, 0), 100);
+        assert_eq!(usage[0].name, "cube");
+        assert_eq!(usage[1].name, "cluster");
+        assert!(usage.iter().all(|thread| thread.percent == 0.0));
+
+        // One second later cluster used 90 ticks of 100, cube 10.
+        let usage = monitor.update(start + Duration::from_secs(1), threads(90, 10), 100);
+        assert!((usage[0].percent - 10.0).abs() < 1e-3);
+        assert!((usage[1].percent - 90.0).abs() < 1e-3);
+        assert!(monitor.overloaded.contains(&20));
+
+        // The average covers the last five seconds, cluster went idle.
+        for secs in 2..=6 {
+            monitor.update(
+                start + Duration::from_secs(secs),
+                threads(90, 10 * secs),
+                100,
+            );
+        }
+        let usage = monitor.update(start + Duration::from_secs(7), threads(90, 70), 100);
+        assert!((usage[0].percent - 10.0).abs() < 1e-3);
+        assert_eq!(usage[1].percent, 0.0);
+        assert!(monitor.overloaded.is_empty());
+
+        // Exited threads are forgotten.
+        let cube = vec![(10, "cube".to_string(), 80)];
+        let usage = monitor.update(start + Duration::from_secs(8), cube, 100);
+        assert_eq!(usage.len(), 1);
+        assert_eq!(monitor.samples.len(), 1);
+    }
+}
@@ -1,11 +1,30 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
+// The target confidence and status streams are used by radarpub.
+#[allow(dead_code)]
 mod can;
+// The queryable half is served by radarpub.
+#[cfg(feature = "zenoh")]
+#[allow(dead_code)]
+mod control;
+// The echo server half is run by radarpub.
+#[cfg(feature = "zenoh")]
+#[allow(dead_code)]
+mod echo;
+mod profile;
 
-use can::{read_parameter, read_status, send_command, write_parameter, Command, Parameter, Status};
+use can::{
+    parse_can_id, parse_can_id_base, read_parameter, read_status, send_command, write_parameter,
+    CanIds, Command, Parameter, RecvResponseSettings, Status,
+};
 use clap::Parser;
+#[cfg(feature = "zenoh")]
+use control::Request;
 use log::debug;
+use profile::{load_profile, profiles_path, save_profile, Profile};
+use socketcan::tokio::CanSocket;
+use std::{error::Error, path::PathBuf};
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
@@ -14,6 +33,35 @@ struct Args {
     #[arg(short, long)]
     device: Option<String>,
 
+    /// Send requests to the radarpub control queryable through this Zenoh
+    /// endpoint (e.g. tcp/192.168.1.10:7447) instead of opening a CAN device.
+    /// Requires radarpub to run with --control.
+    #[cfg(feature = "zenoh")]
+    #[arg(long, value_name = "ENDPOINT", conflicts_with = "device")]
+    via_zenoh: Option<String>,
+
+    /// Radar control queryable key expression
+    #[cfg(feature = "zenoh")]
+    #[arg(long, default_value = control::CONTROL_TOPIC)]
+    control_topic: String,
+
+    /// Verify the Zenoh connectivity to radarpub through this endpoint with
+    /// an echo round trip, then exit. Requires radarpub to run with
+    /// --echo-server.
+    #[cfg(feature = "zenoh")]
+    #[arg(long, value_name = "ENDPOINT", conflicts_with_all = ["device", "via_zenoh"])]
+    zenoh_test: Option<String>,
+
+    /// Echo request key expression
+    #[cfg(feature = "zenoh")]
+    #[arg(long, default_value = echo::ECHO_REQUEST_TOPIC)]
+    echo_request_topic: String,
+
+    /// Echo reply key expression
+    #[cfg(feature = "zenoh")]
+    #[arg(long, default_value = echo::ECHO_REPLY_TOPIC)]
+    echo_reply_topic: String,
+
     /// Monitor the CAN bus and print target lists.
     #[arg(short, long)]
     monitor: bool,
@@ -30,9 +78,174 @@ struct Args {
     #[arg(short, long, value_enum)]
     parameter: Option<Parameter>,
 
+    /// Apply a named parameter profile (parking, highway, indoor or one
+    /// stored with --save-profile), then save the parameters to the sensor
+    /// and reset it.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["parameter", "command", "save_profile"]
+    )]
+    apply_profile: Option<String>,
+
+    /// Read the current parameters and store them as a named profile in
+    /// ~/.config/drvegrdctl/profiles.toml.
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["parameter", "command"])]
+    save_profile: Option<String>,
+
     /// Parameter value to set
     #[arg()]
     value: Option<u32>,
+
+    /// Directory of the CAN interface locks taken by radarpub
+    #[arg(long, default_value = can::CAN_LOCK_DIR)]
+    can_lock_dir: PathBuf,
+
+    /// CAN identifier of the radar frame header, in decimal or 0x prefixed
+    /// hexadecimal. The targets, instruction and response identifiers are
+    /// shifted along with it, as configured on the sensor.
+    #[arg(long, default_value = "0x400", value_parser = parse_can_id_base)]
+    can_id_base: u32,
+
+    /// CAN identifier of the instruction requests, by default 0x3FB shifted
+    /// along with can_id_base.
+    #[arg(long, value_parser = parse_can_id)]
+    can_instruction_id: Option<u32>,
+}
+
+impl Args {
+    /// Returns true if the arguments send instructions to the sensor.
+    fn sends_instructions(&self) -> bool {
+        self.status
+            || self.parameter.is_some()
+            || self.command.is_some()
+            || self.apply_profile.is_some()
+            || self.save_profile.is_some()
+    }
+
+    /// CAN identifiers of the sensor from can_id_base and
+    /// can_instruction_id.
+    fn can_ids(&self) -> CanIds {
+        let ids = CanIds::new(self.can_id_base).expect("can_id_base is checked by its parser");
+        match self.can_instruction_id {
+            Some(id) => ids.with_instruction(id),
+            None => ids,
+        }
+    }
+}
+
+/// Zenoh endpoint of a radarpub running on this host, used for its control
+/// queryable when radarpub holds the CAN interface.
+#[cfg(feature = "zenoh")]
+const LOCAL_ENDPOINT: &str = "tcp/127.0.0.1:7447";
+
+/// Sensor access either through a local CAN socket or through the radarpub
+/// control queryable.
+enum Device {
+    Can(CanSocket, RecvResponseSettings),
+    #[cfg(feature = "zenoh")]
+    Zenoh(zenoh::Session, String),
+}
+
+impl Device {
+    async fn read_status(&self, status: Status) -> Result<u32, Box<dyn Error>> {
+        match self {
+            Device::Can(sock, settings) => Ok(read_status(sock, status, settings).await?),
+            #[cfg(feature = "zenoh")]
+            Device::Zenoh(session, topic) => {
+                Ok(control::request(session, topic, &Request::ReadStatus(status)).await?)
+            }
+        }
+    }
+
+    async fn read_parameter(&self, param: Parameter) -> Result<u32, Box<dyn Error>> {
+        match self {
+            Device::Can(sock, settings) => Ok(read_parameter(sock, param, settings).await?),
+            #[cfg(feature = "zenoh")]
+            Device::Zenoh(session, topic) => {
+                Ok(control::request(session, topic, &Request::ReadParameter(param)).await?)
+            }
+        }
+    }
+
+    async fn write_parameter(&self, param: Parameter, value: u32) -> Result<u32, Box<dyn Error>> {
+        match self {
+            Device::Can(sock, settings) => Ok(write_parameter(sock, param, value, settings).await?),
+            #[cfg(feature = "zenoh")]
+            Device::Zenoh(session, topic) => {
+                let request = Request::WriteParameter(param, value);
+                Ok(control::request(session, topic, &request).await?)
+            }
+        }
+    }
+
+    async fn send_command(&self, command: Command, value: u32) -> Result<u32, Box<dyn Error>> {
+        match self {
+            Device::Can(sock, settings) => Ok(send_command(sock, command, value, settings).await?),
+            #[cfg(feature = "zenoh")]
+            Device::Zenoh(session, topic) => {
+                let request = Request::SendCommand(command, value);
+                Ok(control::request(session, topic, &request).await?)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "zenoh")]
+async fn open_zenoh(endpoint: &str) -> Result<zenoh::Session, zenoh::Error> {
+    let mut config = zenoh::Config::default();
+    config.insert_json5("mode", "\"client\"")?;
+    config.insert_json5(
+        "connect/endpoints",
+        &serde_json::json!([endpoint]).to_string(),
+    )?;
+    config.insert_json5("scouting/multicast/enabled", "false")?;
+    zenoh::open(config).await
+}
+
+/// Sensor access when radarpub holds the lock of the CAN interface, through
+/// the control queryable of the local radarpub when Zenoh is enabled.
+#[cfg(feature = "zenoh")]
+async fn locked_device(args: &Args, device: &str) -> Device {
+    let err = can::Error::InterfaceLocked(
+        device.to_string(),
+        can::CanLock::holder(&args.can_lock_dir, device),
+    );
+    println!(
+        "{}, sending the requests through its control queryable at {}",
+        err, LOCAL_ENDPOINT
+    );
+    match open_zenoh(LOCAL_ENDPOINT).await {
+        Ok(session) => Device::Zenoh(session, args.control_topic.clone()),
+        Err(err) => {
+            println!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Refuses to send instructions when radarpub holds the lock of the CAN
+/// interface, its responses would be read by radarpub.
+#[cfg(not(feature = "zenoh"))]
+async fn locked_device(args: &Args, device: &str) -> Device {
+    let err = can::Error::InterfaceLocked(
+        device.to_string(),
+        can::CanLock::holder(&args.can_lock_dir, device),
+    );
+    println!("Error: {}, stop it before sending instructions", err);
+    std::process::exit(1);
+}
+
+/// Returns the value of a sensor request, or prints the error, including
+/// the decoded UAT error of a failed instruction, and exits.
+fn or_exit<T>(result: Result<T, Box<dyn Error>>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => {
+            println!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
 }
 
 #[tokio::main]
@@ -40,18 +253,74 @@ async fn main() {
     env_logger::init();
     let args = Args::parse();
 
-    let device = args.device.unwrap_or("can0".to_string());
-    debug!("opening can interface {}", device);
-    let sock = socketcan::tokio::CanSocket::open(&device).unwrap();
+    #[cfg(feature = "zenoh")]
+    if let Some(endpoint) = &args.zenoh_test {
+        debug!("testing zenoh connectivity through {}", endpoint);
+        let session = open_zenoh(endpoint).await.unwrap();
+        let rtt = echo::echo(
+            &session,
+            &args.echo_request_topic,
+            &args.echo_reply_topic,
+            echo::ECHO_TIMEOUT,
+        )
+        .await;
+        match rtt {
+            Ok(Some(rtt)) => println!("Echo round trip: {:?}", rtt),
+            Ok(None) => {
+                println!(
+                    "No echo reply within {:?}, check radarpub runs with --echo-server",
+                    echo::ECHO_TIMEOUT
+                );
+                std::process::exit(1);
+            }
+            Err(err) => {
+                println!("Echo error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    #[cfg(feature = "zenoh")]
+    let remote = match &args.via_zenoh {
+        Some(endpoint) => {
+            debug!("connecting to radarpub control through {}", endpoint);
+            let session = open_zenoh(endpoint).await.unwrap();
+            Some(Device::Zenoh(session, args.control_topic.clone()))
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "zenoh"))]
+    let remote = None;
+
+    let sock = match remote {
+        Some(remote) => remote,
+        None => {
+            let device = args.device.clone().unwrap_or("can0".to_string());
+            // Instructions sent while radarpub streams from the interface
+            // would interleave with its own, monitoring is harmless.
+            let locked = args.sends_instructions()
+                && can::CanLock::is_locked(&args.can_lock_dir, &device).unwrap_or(false);
+            match locked {
+                true => locked_device(&args, &device).await,
+                false => {
+                    debug!("opening can interface {}", device);
+                    let settings = RecvResponseSettings {
+                        ids: args.can_ids(),
+                        ..Default::default()
+                    };
+                    Device::Can(CanSocket::open(&device).unwrap(), settings)
+                }
+            }
+        }
+    };
 
     if args.status {
-        let software_generation = read_status(&sock, Status::SoftwareGeneration)
-            .await
-            .unwrap();
-        let major_version = read_status(&sock, Status::MajorVersion).await.unwrap();
-        let minor_version = read_status(&sock, Status::MinorVersion).await.unwrap();
-        let patch_version = read_status(&sock, Status::PatchVersion).await.unwrap();
-        let serial_number = read_status(&sock, Status::SerialNumber).await.unwrap();
+        let software_generation = or_exit(sock.read_status(Status::SoftwareGeneration).await);
+        let major_version = or_exit(sock.read_status(Status::MajorVersion).await);
+        let minor_version = or_exit(sock.read_status(Status::MinorVersion).await);
+        let patch_version = or_exit(sock.read_status(Status::PatchVersion).await);
+        let serial_number = or_exit(sock.read_status(Status::SerialNumber).await);
         println!("Software Generation: {}", software_generation);
         println!(
             "Version: {}.{}.{}",
@@ -60,19 +329,49 @@ async fn main() {
         println!("Serial Number: {}", serial_number);
     }
 
+    if let Some(name) = &args.save_profile {
+        let mut profile = Profile::new();
+        for param in profile::profile_parameters() {
+            let value = or_exit(sock.read_parameter(*param).await);
+            println!("{:?}: {}", param, value);
+            profile.push((*param, value));
+        }
+        let path = profiles_path().unwrap();
+        save_profile(&path, name, &profile).unwrap();
+        println!("Saved profile {} to {}", name, path.display());
+    }
+
+    if let Some(name) = &args.apply_profile {
+        let profile = match profiles_path().and_then(|path| load_profile(&path, name)) {
+            Ok(profile) => profile,
+            Err(err) => {
+                println!("Profile error: {}", err);
+                std::process::exit(1);
+            }
+        };
+        for (param, value) in profile {
+            let value = or_exit(sock.write_parameter(param, value).await);
+            println!("{:?}: {}", param, value);
+        }
+        for command in [Command::SaveParameters, Command::SensorReset] {
+            let value = or_exit(sock.send_command(command, 0).await);
+            println!("{:?}: {}", command, value);
+        }
+    }
+
     if let Some(parameter) = args.parameter {
         if let Some(value) = args.value {
-            let value = write_parameter(&sock, parameter, value).await.unwrap();
+            let value = or_exit(sock.write_parameter(parameter, value).await);
             println!("{:?}: {}", args.parameter, value);
         } else {
-            let value = read_parameter(&sock, parameter).await.unwrap();
+            let value = or_exit(sock.read_parameter(parameter).await);
             println!("{:?}: {}", args.parameter, value);
         }
     }
 
     if let Some(command) = args.command {
         if let Some(value) = args.value {
-            let value = send_command(&sock, command, value).await.unwrap();
+            let value = or_exit(sock.send_command(command, value).await);
             println!("{:?}: {}", args.command, value);
         } else {
             println!("Command {:?} requires a value", args.command);
@@ -81,8 +380,17 @@ async fn main() {
     }
 
     if args.monitor {
+        let (sock, ids) = match &sock {
+            Device::Can(sock, settings) => (sock, settings.ids),
+            #[cfg(feature = "zenoh")]
+            Device::Zenoh(..) => {
+                println!("Monitor requires a local CAN device");
+                return;
+            }
+        };
+
         loop {
-            match can::read_message(&sock).await {
+            match can::read_message(sock, &ids).await {
                 Err(err) => println!("Error: {:?}", err),
                 Ok(msg) => {
                     println!("{:?}", msg);
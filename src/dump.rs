@@ -0,0 +1,641 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::eth;
+use edgefirst_schemas::{edgefirst_msgs::RadarCube, serde_cdr};
+use ndarray::{arr0, arr1, Array1, Array4};
+use ndarray_npy::{write_npy, NpzWriter};
+use num::complex::Complex32;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, SyncSender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::error;
+
+/// File format of a [`CubeWriter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFileFormat {
+    /// One `cube_{frame_counter}.npy` complex64 array per frame in a
+    /// directory
+    Npy,
+
+    /// CDR encoded `edgefirst_msgs/RadarCube` records appended to a single
+    /// file, each prefixed by its length as a little endian u32, see
+    /// [`cube_dump`].  The file is rotated by a [`RotatingWriter`] when
+    /// started with [`CubeWriter::with_rotation`].
+    Raw,
+}
+
+/// Writes radar cubes to disk on a dedicated thread.
+///
+/// Cubes are handed over through a bounded queue so slow storage never
+/// stalls the cube reader, a cube arriving while the queue is full is
+/// dropped and counted.  Dropping the writer flushes the queued cubes and
+/// waits for the thread to exit.
+#[derive(Debug)]
+pub struct CubeWriter {
+    tx: Option<SyncSender<(u32, RadarCube)>>,
+    handle: Option<JoinHandle<()>>,
+    written: Arc<AtomicU64>,
+    dropped: u64,
+}
+
+impl CubeWriter {
+    /// Start the writer thread.
+    ///
+    /// # Arguments
+    /// * `format` - File format
+    /// * `path` - Target directory for [`CubeFileFormat::Npy`], created if
+    ///   missing, or the file to append to for [`CubeFileFormat::Raw`]
+    /// * `queue` - Cubes waiting to be written before new cubes are dropped
+    ///
+    /// # Errors
+    /// Returns the error creating the directory or opening the file
+    pub fn new<P: AsRef<Path>>(
+        format: CubeFileFormat,
+        path: P,
+        queue: usize,
+    ) -> Result<Self, io::Error> {
+        Self::with_rotation(format, path, queue, u64::MAX, usize::MAX)
+    }
+
+    /// Start the writer thread, rotating the [`CubeFileFormat::Raw`] file
+    /// once it reaches `max_size` bytes, see [`RotatingWriter`].
+    ///
+    /// # Arguments
+    /// * `format` - File format
+    /// * `path` - Target directory for [`CubeFileFormat::Npy`], created if
+    ///   missing, or the file to append to for [`CubeFileFormat::Raw`]
+    /// * `queue` - Cubes waiting to be written before new cubes are dropped
+    /// * `max_size` - Largest raw file in bytes
+    /// * `max_files` - Rotated raw files kept besides the current file
+    ///
+    /// # Errors
+    /// Returns the error creating the directory or opening the file
+    pub fn with_rotation<P: AsRef<Path>>(
+        format: CubeFileFormat,
+        path: P,
+        queue: usize,
+        max_size: u64,
+        max_files: usize,
+    ) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut raw = match format {
+            CubeFileFormat::Npy => {
+                fs::create_dir_all(&path)?;
+                None
+            }
+            CubeFileFormat::Raw => Some(RotatingWriter::new(&path, max_size, max_files)?),
+        };
+
+        let (tx, rx) = mpsc::sync_channel::<(u32, RadarCube)>(queue.max(1));
+        let written = Arc::new(AtomicU64::new(0));
+        let counter = written.clone();
+        let handle = thread::Builder::new()
+            .name("cube_writer".to_string())
+            .spawn(move || {
+                for (frame_counter, cube) in rx {
+                    let result = match &mut raw {
+                        Some(file) => encode_record(&cube).and_then(|record| file.write(&record)),
+                        None => write_npy_frame(&path, frame_counter, &cube).map(|_| ()),
+                    };
+                    match result {
+                        Ok(()) => {
+                            counter.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => error!("cube {} write error: {:?}", frame_counter, e),
+                    }
+                }
+
+                if let Some(Err(e)) = raw.as_mut().map(|file| file.flush()) {
+                    error!("cube file flush error: {:?}", e);
+                }
+            })?;
+
+        Ok(CubeWriter {
+            tx: Some(tx),
+            handle: Some(handle),
+            written,
+            dropped: 0,
+        })
+    }
+
+    /// Queue a cube for writing.
+    ///
+    /// # Returns
+    /// False if the queue was full or the writer thread has exited, the cube
+    /// is then dropped and counted in [`CubeWriter::dropped`]
+    pub fn write(&mut self, frame_counter: u32, cube: RadarCube) -> bool {
+        match self
+            .tx
+            .as_ref()
+            .map(|tx| tx.try_send((frame_counter, cube)))
+        {
+            Some(Ok(())) => true,
+            _ => {
+                self.dropped += 1;
+                false
+            }
+        }
+    }
+
+    /// Returns the number of cubes written to disk.
+    pub fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of cubes dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl Drop for CubeWriter {
+    fn drop(&mut self) {
+        self.tx = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Convert the interleaved i16 samples of a complex radar cube message to a
+/// complex64 array, numpy only supports complex float arrays.
+///
+/// # Errors
+/// Returns an `InvalidData` error if the message is not a complex four
+/// dimensional cube or the data does not match the shape
+pub fn cube_array(cube: &RadarCube) -> Result<Array4<Complex32>, io::Error> {
+    let invalid = |msg: &str| io::Error::new(ErrorKind::InvalidData, msg.to_string());
+    let shape = match (cube.is_complex, &cube.shape[..]) {
+        (true, &[a, b, c, d]) if d % 2 == 0 => (a as usize, b as usize, c as usize, d as usize / 2),
+        _ => return Err(invalid("expected a complex 4D radar cube")),
+    };
+
+    let data = cube
+        .cube
+        .chunks_exact(2)
+        .map(|x| Complex32::new(x[0] as f32, x[1] as f32))
+        .collect();
+    Array4::from_shape_vec(shape, data).map_err(|_| invalid("cube data does not match the shape"))
+}
+
+/// Write the cube as `cube_{frame_counter}.npy` in the directory.
+///
+/// # Returns
+/// Path of the written file
+pub fn write_npy_frame(
+    dir: &Path,
+    frame_counter: u32,
+    cube: &RadarCube,
+) -> Result<PathBuf, io::Error> {
+    let path = dir.join(format!("cube_{}.npy", frame_counter));
+    write_npy(&path, &cube_array(cube)?).map_err(io::Error::other)?;
+    Ok(path)
+}
+
+/// Append the cube as a length prefixed CDR record.
+#[allow(dead_code)]
+pub fn write_record<W: Write>(writer: &mut W, cube: &RadarCube) -> Result<(), io::Error> {
+    writer.write_all(&encode_record(cube)?)
+}
+
+/// Encode the cube as a length prefixed CDR record, written at once so a
+/// [`RotatingWriter`] never splits a record across files.
+fn encode_record(cube: &RadarCube) -> Result<Vec<u8>, io::Error> {
+    let cdr = serde_cdr::serialize(cube).map_err(io::Error::other)?;
+    let len = u32::try_from(cdr.len()).map_err(io::Error::other)?;
+    let mut record = Vec::with_capacity(4 + cdr.len());
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(&cdr);
+    Ok(record)
+}
+
+/// Appends to a file which is rotated before it grows above a size limit.
+///
+/// On rotation the file is closed and renamed with the Unix time in
+/// microseconds as suffix, `cubes.raw` becomes `cubes.raw.1760700000123456`,
+/// and a new file is opened at the base path.  Only the newest `max_files`
+/// rotated files are kept, older ones are deleted.  Each write goes to a
+/// single file, a file exceeds the limit only when a single write is larger
+/// than the limit.
+#[derive(Debug)]
+pub struct RotatingWriter {
+    base_path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    current: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingWriter {
+    /// Open the file at the base path for appending.
+    ///
+    /// # Arguments
+    /// * `base_path` - File written to, an existing file is appended to
+    /// * `max_size` - Largest file size in bytes
+    /// * `max_files` - Rotated files kept besides the current file
+    pub fn new<P: AsRef<Path>>(
+        base_path: P,
+        max_size: u64,
+        max_files: usize,
+    ) -> Result<Self, io::Error> {
+        let base_path = base_path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&base_path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingWriter {
+            base_path,
+            max_size,
+            max_files,
+            current: BufWriter::new(file),
+            size,
+        })
+    }
+
+    /// Write all the data to the current file, rotating it first if the data
+    /// would take it above the size limit.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size.saturating_add(data.len() as u64) > self.max_size {
+            self.rotate()?;
+        }
+        self.current.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    /// Flush the buffered data to the current file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.current.flush()
+    }
+
+    /// Returns the rotated files, oldest first.
+    pub fn rotated_files(&self) -> io::Result<Vec<PathBuf>> {
+        let (dir, prefix) = self.rotation_prefix();
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name();
+            let suffix = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|suffix| suffix.parse::<u64>().ok());
+            if let Some(suffix) = suffix {
+                files.push((suffix, dir.join(name)));
+            }
+        }
+        files.sort();
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.current.flush()?;
+        let mut micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let (dir, prefix) = self.rotation_prefix();
+        // Never replace a file rotated within the same microsecond.
+        while dir.join(format!("{}{}", prefix, micros)).exists() {
+            micros += 1;
+        }
+        fs::rename(&self.base_path, dir.join(format!("{}{}", prefix, micros)))?;
+
+        self.current = BufWriter::new(File::create(&self.base_path)?);
+        self.size = 0;
+
+        let rotated = self.rotated_files()?;
+        let expired = rotated.len().saturating_sub(self.max_files);
+        for path in &rotated[..expired] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the directory of the rotated files and their file name prefix.
+    fn rotation_prefix(&self) -> (&Path, String) {
+        let dir = match self.base_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = self.base_path.file_name().unwrap_or_default();
+        (dir, format!("{}.", name.to_string_lossy()))
+    }
+}
+
+/// Exports every Nth radar cube as a `cube_{frame_counter}.npz` archive.
+///
+/// The archive holds the named arrays `data` (complex64 cube), `scales`
+/// (`[range_per_bin, speed_per_bin, bin_per_speed]`), `shape`, `timestamp`
+/// (microseconds), `frame_counter` and `packets_skipped`, so the range and
+/// speed scaling and the frame timing survive offline analysis.
+#[derive(Debug)]
+pub struct NpzExporter {
+    dir: PathBuf,
+    every: u64,
+    cubes: u64,
+}
+
+impl NpzExporter {
+    /// Create the exporter, creating the directory if missing.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory receiving the archives
+    /// * `every` - Export one cube out of every `every` received, 0 is
+    ///   treated as 1
+    pub fn new<P: AsRef<Path>>(dir: P, every: u64) -> Result<Self, io::Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(NpzExporter {
+            dir,
+            every: every.max(1),
+            cubes: 0,
+        })
+    }
+
+    /// Count the cube and export it if it is due.
+    ///
+    /// # Returns
+    /// Path of the written archive, or None if the cube was decimated
+    pub fn export(&mut self, cube: &eth::RadarCube) -> Result<Option<PathBuf>, io::Error> {
+        let due = self.cubes % self.every == 0;
+        self.cubes += 1;
+        if !due {
+            return Ok(None);
+        }
+
+        let path = self.dir.join(format!("cube_{}.npz", cube.frame_counter));
+        write_npz(&path, cube)?;
+        Ok(Some(path))
+    }
+}
+
+/// Write the cube and its metadata as an uncompressed `.npz` archive, see
+/// [`NpzExporter`] for the array names.
+pub fn write_npz<P: AsRef<Path>>(path: P, cube: &eth::RadarCube) -> Result<(), io::Error> {
+    // Numpy only supports complex float arrays.
+    let data = cube.data.mapv(|x| Complex32::new(x.re as f32, x.im as f32));
+    let bins = &cube.bin_properties;
+    let scales = arr1(&[bins.range_per_bin, bins.speed_per_bin, bins.bin_per_speed]);
+    let shape: Array1<u64> = cube.data.shape().iter().map(|&x| x as u64).collect();
+
+    let mut npz = NpzWriter::new(File::create(path)?);
+    npz.add_array("data", &data).map_err(io::Error::other)?;
+    npz.add_array("scales", &scales).map_err(io::Error::other)?;
+    npz.add_array("shape", &shape).map_err(io::Error::other)?;
+    npz.add_array("timestamp", &arr0(cube.timestamp))
+        .map_err(io::Error::other)?;
+    npz.add_array("frame_counter", &arr0(cube.frame_counter))
+        .map_err(io::Error::other)?;
+    npz.add_array("packets_skipped", &arr0(cube.packets_skipped))
+        .map_err(io::Error::other)?;
+    npz.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Read every radar cube record of a file written with
+/// [`CubeFileFormat::Raw`].
+///
+/// # Errors
+/// Returns an `InvalidData` error for a truncated or undecodable record
+#[allow(dead_code)]
+pub fn cube_dump<P: AsRef<Path>>(path: P) -> Result<Vec<RadarCube>, io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut cubes = Vec::new();
+
+    loop {
+        let mut len = [0u8; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(cubes),
+            Err(e) => return Err(e),
+        }
+
+        let mut cdr = vec![0u8; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut cdr).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => io::Error::new(
+                ErrorKind::InvalidData,
+                format!("truncated record {}", cubes.len()),
+            ),
+            _ => e,
+        })?;
+        let cube = serde_cdr::deserialize(&cdr)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        cubes.push(cube);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edgefirst_schemas::{builtin_interfaces::Time, edgefirst_msgs, std_msgs::Header};
+    use ndarray::Array0;
+    use ndarray_npy::{read_npy, NpzReader};
+    use num::Complex;
+    use std::time::Duration;
+
+    fn radar_cube(timestamp: u64) -> RadarCube {
+        RadarCube {
+            header: Header {
+                stamp: Time { sec: 1, nanosec: 2 },
+                frame_id: "radar".to_string(),
+            },
+            timestamp,
+            layout: vec![
+                edgefirst_msgs::radar_cube_dimension::SEQUENCE,
+                edgefirst_msgs::radar_cube_dimension::RANGE,
+                edgefirst_msgs::radar_cube_dimension::RXCHANNEL,
+                edgefirst_msgs::radar_cube_dimension::DOPPLER,
+            ],
+            shape: vec![1, 3, 2, 4],
+            scales: vec![1.0, 0.5, 1.0, 0.1],
+            cube: (0..24).collect(),
+            is_complex: true,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("radarpub_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_write_raw() {
+        let path = temp_path("cubes.raw");
+        let mut writer = CubeWriter::new(CubeFileFormat::Raw, &path, 4).unwrap();
+        assert!(writer.write(7, radar_cube(100)));
+        assert!(writer.write(8, radar_cube(200)));
+        drop(writer);
+
+        let cubes = cube_dump(&path).unwrap();
+        assert_eq!(cubes.len(), 2);
+        assert_eq!(cubes[0].timestamp, 100);
+        assert_eq!(cubes[1].timestamp, 200);
+        assert_eq!(cubes[1].header.frame_id, "radar");
+        assert_eq!(cubes[1].shape, vec![1, 3, 2, 4]);
+        assert_eq!(cubes[1].cube, (0..24).collect::<Vec<i16>>());
+
+        // A truncated trailing record is reported.
+        let len = fs::metadata(&path).unwrap().len();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+        let err = cube_dump(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rotating_writer() {
+        let dir = temp_path("rotate");
+        fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("targets.csv");
+
+        let mut writer = RotatingWriter::new(&base, 10, 2).unwrap();
+        for i in 0..5 {
+            writer.write(&[i; 6]).unwrap();
+        }
+        writer.flush().unwrap();
+
+        // Every write after the first rotates, only the newest two rotated
+        // files are kept.
+        let rotated = writer.rotated_files().unwrap();
+        assert_eq!(rotated.len(), 2);
+        assert_eq!(fs::read(&rotated[0]).unwrap(), [2; 6]);
+        assert_eq!(fs::read(&rotated[1]).unwrap(), [3; 6]);
+        assert_eq!(fs::read(&base).unwrap(), [4; 6]);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        // An existing file is appended to until it reaches the limit.
+        drop(writer);
+        let mut writer = RotatingWriter::new(&base, 12, 2).unwrap();
+        writer.write(&[5; 6]).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read(&base).unwrap(), [[4; 6], [5; 6]].concat());
+        assert_eq!(writer.rotated_files().unwrap(), rotated);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_raw_rotation() {
+        let dir = temp_path("raw_rotation");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cubes.raw");
+
+        // Every record exceeds the limit and starts a new file.
+        let mut writer = CubeWriter::with_rotation(CubeFileFormat::Raw, &path, 4, 1, 5).unwrap();
+        for timestamp in [100, 200, 300] {
+            assert!(writer.write(0, radar_cube(timestamp)));
+        }
+        drop(writer);
+
+        let rotated = RotatingWriter::new(&path, 1, 5)
+            .unwrap()
+            .rotated_files()
+            .unwrap();
+        let timestamps: Vec<_> = rotated
+            .iter()
+            .chain([&path])
+            .flat_map(|file| cube_dump(file).unwrap())
+            .map(|cube| cube.timestamp)
+            .collect();
+        assert_eq!(timestamps, vec![100, 200, 300]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_npy() {
+        let dir = temp_path("cubes");
+        let mut writer = CubeWriter::new(CubeFileFormat::Npy, &dir, 4).unwrap();
+        assert!(writer.write(42, radar_cube(100)));
+        drop(writer);
+
+        let data: Array4<Complex32> = read_npy(dir.join("cube_42.npy")).unwrap();
+        assert_eq!(data.shape(), &[1, 3, 2, 2]);
+        assert_eq!(data[[0, 0, 0, 0]], Complex32::new(0.0, 1.0));
+        assert_eq!(data[[0, 0, 0, 1]], Complex32::new(2.0, 3.0));
+        assert_eq!(data[[0, 2, 1, 1]], Complex32::new(22.0, 23.0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn eth_cube(frame_counter: u32) -> eth::RadarCube {
+        let mut data = Array4::<Complex<i16>>::zeros((2, 3, 4, 5));
+        for (i, x) in data.iter_mut().enumerate() {
+            *x = Complex::new(i as i16, -(i as i16));
+        }
+        eth::RadarCube {
+            timestamp: 1_700_000_000_123_456,
+            frame_counter,
+            packets_captured: 10,
+            packets_skipped: 2,
+            missing_data: 0,
+            reassembly_time: Duration::from_millis(5),
+            bin_properties: eth::BinProperties {
+                speed_per_bin: 0.25,
+                range_per_bin: 0.5,
+                bin_per_speed: 4.0,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_write_npz() {
+        let dir = temp_path("npz");
+        let mut exporter = NpzExporter::new(&dir, 3).unwrap();
+        let written: Vec<_> = (10..17)
+            .filter_map(|fc| exporter.export(&eth_cube(fc)).unwrap())
+            .collect();
+        assert_eq!(
+            written,
+            vec![
+                dir.join("cube_10.npz"),
+                dir.join("cube_13.npz"),
+                dir.join("cube_16.npz")
+            ]
+        );
+
+        let mut npz = NpzReader::new(File::open(&written[1]).unwrap()).unwrap();
+        let data: Array4<Complex32> = npz.by_name("data").unwrap();
+        assert_eq!(data.shape(), &[2, 3, 4, 5]);
+        assert_eq!(data[[0, 0, 0, 1]], Complex32::new(1.0, -1.0));
+        assert_eq!(data[[1, 2, 3, 4]], Complex32::new(119.0, -119.0));
+        let scales: Array1<f32> = npz.by_name("scales").unwrap();
+        assert_eq!(scales.to_vec(), vec![0.5, 0.25, 4.0]);
+        let shape: Array1<u64> = npz.by_name("shape").unwrap();
+        assert_eq!(shape.to_vec(), vec![2, 3, 4, 5]);
+        let timestamp: Array0<u64> = npz.by_name("timestamp").unwrap();
+        assert_eq!(timestamp.into_scalar(), 1_700_000_000_123_456);
+        let frame_counter: Array0<u32> = npz.by_name("frame_counter").unwrap();
+        assert_eq!(frame_counter.into_scalar(), 13);
+        let skipped: Array0<u16> = npz.by_name("packets_skipped").unwrap();
+        assert_eq!(skipped.into_scalar(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cube_array_invalid() {
+        let mut cube = radar_cube(0);
+        cube.is_complex = false;
+        assert!(cube_array(&cube).is_err());
+
+        let mut cube = radar_cube(0);
+        cube.cube.pop();
+        cube.cube.pop();
+        assert!(cube_array(&cube).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use log::debug;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use zenoh::{bytes::Encoding, Session};
+
+/// Default key expression of the echo requests.
+pub const ECHO_REQUEST_TOPIC: &str = "rt/radar/echo_request";
+
+/// Default key expression of the echo replies.
+pub const ECHO_REPLY_TOPIC: &str = "rt/radar/echo_reply";
+
+/// Time to wait for the echo reply.
+pub const ECHO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Publish every echo request back on the reply topic unchanged.
+///
+/// Runs in radarpub independently of the radar so the Zenoh path between
+/// drvegrdctl and radarpub can be verified without a sensor.
+///
+/// # Errors
+/// Returns an error if the subscriber or publisher cannot be declared or a
+/// reply cannot be published
+pub async fn serve(
+    session: &Session,
+    request_topic: &str,
+    reply_topic: &str,
+) -> Result<(), zenoh::Error> {
+    let subscriber = session.declare_subscriber(request_topic).await?;
+    let publisher = session.declare_publisher(reply_topic.to_string()).await?;
+
+    while let Ok(sample) = subscriber.recv_async().await {
+        debug!("echo request of {} bytes", sample.payload().len());
+        publisher
+            .put(sample.payload().clone())
+            .encoding(sample.encoding().clone())
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Publish a unique echo request and wait for the echo server to reply.
+///
+/// # Returns
+/// The round trip time, or None if no matching reply arrived in time
+///
+/// # Errors
+/// Returns an error if the subscriber cannot be declared or the request
+/// cannot be published
+pub async fn echo(
+    session: &Session,
+    request_topic: &str,
+    reply_topic: &str,
+    timeout: Duration,
+) -> Result<Option<Duration>, zenoh::Error> {
+    let subscriber = session.declare_subscriber(reply_topic).await?;
+    let token = Uuid::new_v4().to_string();
+
+    let start = Instant::now();
+    session
+        .put(request_topic, token.clone())
+        .encoding(Encoding::TEXT_PLAIN)
+        .await?;
+
+    // Replies to other clients testing at the same time are ignored.
+    let reply = async {
+        while let Ok(sample) = subscriber.recv_async().await {
+            if sample
+                .payload()
+                .try_to_string()
+                .is_ok_and(|payload| payload == token.as_str())
+            {
+                return Some(start.elapsed());
+            }
+        }
+        None
+    };
+
+    Ok(tokio::time::timeout(timeout, reply).await.ok().flatten())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_round_trip() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let mut config = zenoh::Config::default();
+            config
+                .insert_json5("scouting/multicast/enabled", "false")
+                .unwrap();
+            config.insert_json5("listen/endpoints", "[]").unwrap();
+            let session = zenoh::open(config).await.unwrap();
+
+            // Without an echo server the request times out.
+            let timeout = Duration::from_millis(100);
+            let rtt = echo(&session, ECHO_REQUEST_TOPIC, ECHO_REPLY_TOPIC, timeout)
+                .await
+                .unwrap();
+            assert_eq!(rtt, None);
+
+            let server_session = session.clone();
+            tokio::spawn(async move {
+                serve(&server_session, ECHO_REQUEST_TOPIC, ECHO_REPLY_TOPIC)
+                    .await
+                    .unwrap()
+            });
+            // Give the subscriber time to be declared.
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let rtt = echo(&session, ECHO_REQUEST_TOPIC, ECHO_REPLY_TOPIC, ECHO_TIMEOUT)
+                .await
+                .unwrap();
+            assert!(rtt.is_some_and(|rtt| rtt < ECHO_TIMEOUT));
+        });
+    }
+}
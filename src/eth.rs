@@ -1,9 +1,17 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
+use edgefirst_schemas::edgefirst_msgs::{self, radar_cube_dimension};
 use ndarray::{Array4, ArrayView4, Axis};
 use num::Complex;
-use std::{cmp::min, fmt, num::Wrapping, vec};
+use serde_json::{json, Value};
+use std::{
+    cmp::min,
+    fmt,
+    num::Wrapping,
+    time::{Duration, Instant},
+    vec,
+};
 use tracing::instrument;
 
 /// Fixed size size of the SMS UDP packets.
@@ -43,14 +51,21 @@ pub enum SMSError {
     BinPropertiesMissing,
     /// Message sequence number gap detected
     MessageSequenceError,
-    /// Frame counter mismatch
-    FrameCounterError,
+    /// Frame counter mismatch which aborted a frame
+    FrameCounterError(FrameCounterMismatch),
     /// Array shape error from ndarray
     ShapeError(ndarray::ShapeError),
     /// Missing radar cube data (received, expected)
     MissingCubeData(usize, usize),
     /// UDP packets dropped
     DroppedMessages(u16),
+    /// Cube header dimensions [chirp_types, range_gates, rx_channels,
+    /// doppler_bins] which are not positive or exceed the dimension limits
+    InvalidCubeShape([i32; 4]),
+    /// Radar cube size in bytes above the limit (size, limit)
+    CubeTooLarge(usize, usize),
+    /// RadarCube message whose layout, shape or scales cannot be restored
+    InvalidCubeMessage(String),
 }
 
 impl std::error::Error for SMSError {}
@@ -107,8 +122,8 @@ impl fmt::Display for SMSError {
             SMSError::MessageSequenceError => {
                 write!(f, "message sequence error")
             }
-            SMSError::FrameCounterError => {
-                write!(f, "frame counter error")
+            SMSError::FrameCounterError(mismatch) => {
+                write!(f, "frame counter error: {}", mismatch)
             }
             SMSError::ShapeError(err) => {
                 write!(f, "shape error: {}", err)
@@ -119,6 +134,15 @@ impl fmt::Display for SMSError {
             SMSError::DroppedMessages(dropped) => {
                 write!(f, "dropped messages: {}", dropped)
             }
+            SMSError::InvalidCubeShape(shape) => {
+                write!(f, "invalid cube shape: {:?}", shape)
+            }
+            SMSError::CubeTooLarge(size, limit) => {
+                write!(f, "cube of {} bytes exceeds the {} byte limit", size, limit)
+            }
+            SMSError::InvalidCubeMessage(err) => {
+                write!(f, "invalid cube message: {}", err)
+            }
         }
     }
 }
@@ -175,6 +199,23 @@ pub struct TransportHeaderSlice<'a> {
 
 impl<'a> TransportHeaderSlice<'a> {
     /// Parse transport header from byte slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use radarpub::eth::TransportHeaderSlice;
+    ///
+    /// // A 14 byte header with the message counter flag, message 7 of
+    /// // application protocol 5, followed by a 4 byte payload and padding.
+    /// let packet = [0x7E, 1, 14, 0, 4, 5, 0, 0, 0, 0x01, 0, 7, 0, 0, 1, 2, 3, 4, 0, 0];
+    /// let header = TransportHeaderSlice::from_slice(&packet).unwrap();
+    /// assert_eq!(header.application_protocol(), 5);
+    /// assert_eq!(header.message_counter().map(|counter| counter.0), Some(7));
+    /// assert_eq!(header.payload(), &[1, 2, 3, 4]);
+    ///
+    /// // The payload must fit in the slice.
+    /// assert!(TransportHeaderSlice::from_slice(&packet[..16]).is_err());
+    /// ```
     pub fn from_slice(slice: &'a [u8]) -> Result<TransportHeaderSlice<'a>, SMSError> {
         if slice.len() < TransportHeader::MIN_LEN {
             return Err(SMSError::UnexpectedEndOfSlice(slice.len()));
@@ -184,19 +225,17 @@ impl<'a> TransportHeaderSlice<'a> {
             return Err(SMSError::StartPattern(slice[0]));
         }
 
-        // Confirm that the slice is large enough to hold the CRC
-        // starting from the offset to account for optional fields.
-        if Self::crc_offset(slice) + TransportHeader::CRC_LEN > slice.len() {
-            return Err(SMSError::UnexpectedEndOfSlice(slice.len()));
-        }
-
-        // Confirm calculated header size matches the reported header size.
-        if Self::crc_offset(slice) + TransportHeader::CRC_LEN != slice[2] as usize {
-            return Err(SMSError::UnexpectedEndOfSlice(slice.len()));
+        // The header length derived from the flags, accounting for the
+        // optional fields, must match the reported header length as the
+        // payload offset is derived from the flags.
+        let header_length = Self::crc_offset(slice) + TransportHeader::CRC_LEN;
+        if header_length != slice[2] as usize {
+            return Err(SMSError::InvalidHeaderLength(slice[2]));
         }
 
         // Confirm that the slice can hold the entire header and payload.
-        if slice.len() < slice[2] as usize + u16::from_be_bytes([slice[3], slice[4]]) as usize {
+        let payload_length = u16::from_be_bytes([slice[3], slice[4]]);
+        if slice.len() < header_length + payload_length as usize {
             return Err(SMSError::UnexpectedEndOfSlice(slice.len()));
         }
 
@@ -239,6 +278,12 @@ impl<'a> TransportHeaderSlice<'a> {
         }
     }
 
+    /// Returns the payload length in bytes reported by the header.
+    #[inline]
+    pub fn payload_length(&self) -> usize {
+        u16::from_be_bytes([self.slice[3], self.slice[4]]) as usize
+    }
+
     /// Returns the application protocol number.
     #[inline]
     pub fn application_protocol(&self) -> u8 {
@@ -355,7 +400,7 @@ impl<'a> TransportHeaderSlice<'a> {
             + Self::segmentation_size(self.slice)
     }
 
-    /// Returns true if the underlyinc slice is empty.
+    /// Returns true if the underlying slice is empty.
     #[inline]
     /// Check if radar cube data buffer is empty.
     /// Used for protocol state validation and debugging.
@@ -417,16 +462,14 @@ impl<'a> TransportHeaderSlice<'a> {
         }
     }
 
-    /// Returns the slice containing the payload.
+    /// Returns the slice containing the payload, bytes following the
+    /// reported payload length such as datagram padding are excluded.
     #[inline]
     pub fn payload(&self) -> &'a [u8] {
         unsafe {
-            // SAFETY: Safe as the slice length was verified
-            // to be at least UdpHeader::LEN by "from_slice".
-            core::slice::from_raw_parts(
-                self.slice.as_ptr().add(self.len()),
-                self.slice.len() - self.len(),
-            )
+            // SAFETY: Safe as "from_slice" verified the header length
+            // matches the flags and the slice holds the header and payload.
+            core::slice::from_raw_parts(self.slice.as_ptr().add(self.len()), self.payload_length())
         }
     }
 }
@@ -511,6 +554,20 @@ impl<'a> DebugHeaderSlice<'a> {
         self.slice[5]
     }
 
+    /// Returns the debug header length in bytes.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        DebugHeader::LEN
+    }
+
+    /// Returns true if the underlying slice is empty.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+
     /// Returns the slice containing the payload.
     #[inline]
     pub fn payload(&self) -> &'a [u8] {
@@ -540,7 +597,8 @@ pub struct PortHeader {
     pub timestamp: u64,
     /// Total data size in bytes
     pub size: u32,
-    /// Byte order (0=little-endian, 1=big-endian)
+    /// Byte order of the payload, 0=little-endian, 1=big-endian, see
+    /// [`PortHeaderSlice::payload_is_little_endian`]
     pub endianess: u8,
     /// Frame index
     pub index: u8,
@@ -624,11 +682,22 @@ impl<'a> PortHeaderSlice<'a> {
         ])
     }
 
+    /// Returns true if the endianess byte is 0, marking the payload, the cube
+    /// header and the cube data, as little-endian.  The port header itself is
+    /// always big-endian.
+    #[inline]
+    pub fn payload_is_little_endian(&self) -> bool {
+        self.slice[20] == 0
+    }
+
     /// Returns the radar cube header slice or an error if not present.
     #[inline]
     pub fn cube_header(&self) -> Result<CubeHeaderSlice<'a>, SMSError> {
         match self.id() {
-            5 => CubeHeaderSlice::from_slize(self.payload()),
+            5 => CubeHeaderSlice::from_slice_with_byte_order(
+                self.payload(),
+                self.payload_is_little_endian(),
+            ),
             _ => Err(SMSError::CubeHeaderMissing),
         }
     }
@@ -637,11 +706,25 @@ impl<'a> PortHeaderSlice<'a> {
     #[inline]
     pub fn bin_properties(&self) -> Result<BinPropertiesSlice<'a>, SMSError> {
         match self.id() {
-            63 => BinPropertiesSlice::from_slize(self.payload()),
+            63 => BinPropertiesSlice::from_slice(self.payload()),
             _ => Err(SMSError::BinPropertiesMissing),
         }
     }
 
+    /// Returns the port header length in bytes.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        PortHeader::LEN
+    }
+
+    /// Returns true if the underlying slice is empty.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+
     /// Returns the slice containing the payload.
     #[inline]
     pub fn payload(&self) -> &'a [u8] {
@@ -704,65 +787,116 @@ impl CubeHeader {
     pub const LEN: usize = 40;
 }
 
+/// Sanity limits of the radar cube described by a start of frame.
+///
+/// The cube is allocated from the header dimensions, a corrupted or crafted
+/// header exceeding the limits is rejected instead of allocating gigabytes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CubeLimits {
+    /// Largest dimensions as [chirp_types, range_gates, rx_channels,
+    /// doppler_bins]
+    pub max_shape: [usize; 4],
+    /// Largest cube size in bytes
+    pub max_bytes: usize,
+}
+
+impl CubeLimits {
+    /// Default largest cube size, 64 MiB.
+    pub const DEFAULT_MAX_BYTES: usize = 64 << 20;
+    /// Default largest dimensions, with headroom above the DRVEGRD cube
+    /// configurations.
+    pub const DEFAULT_MAX_SHAPE: [usize; 4] = [4, 2048, 32, 1024];
+}
+
+impl Default for CubeLimits {
+    fn default() -> Self {
+        CubeLimits {
+            max_shape: Self::DEFAULT_MAX_SHAPE,
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
 /// Zero-copy view of radar cube header bytes.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct CubeHeaderSlice<'a> {
     slice: &'a [u8],
+    little_endian: bool,
 }
 
 impl<'a> CubeHeaderSlice<'a> {
-    /// Parse cube header from byte slice.
-    pub fn from_slize(slice: &'a [u8]) -> Result<CubeHeaderSlice<'a>, SMSError> {
+    /// Parse a big-endian cube header from byte slice.
+    pub fn from_slice(slice: &'a [u8]) -> Result<CubeHeaderSlice<'a>, SMSError> {
+        Self::from_slice_with_byte_order(slice, false)
+    }
+
+    /// Parse a cube header in the byte order given by the port header.
+    pub fn from_slice_with_byte_order(
+        slice: &'a [u8],
+        little_endian: bool,
+    ) -> Result<CubeHeaderSlice<'a>, SMSError> {
         if slice.len() < CubeHeader::LEN {
             return Err(SMSError::UnexpectedEndOfSlice(slice.len()));
         }
 
-        Ok(CubeHeaderSlice { slice })
+        // The padding bytes precede the payload.
+        if slice.len() < CubeHeader::LEN + slice[39] as usize {
+            return Err(SMSError::UnexpectedEndOfSlice(slice.len()));
+        }
+
+        Ok(CubeHeaderSlice {
+            slice,
+            little_endian,
+        })
+    }
+
+    /// Parse cube header from byte slice.
+    #[deprecated(since = "1.7.0", note = "use `from_slice`")]
+    #[allow(dead_code)]
+    pub fn from_slize(slice: &'a [u8]) -> Result<CubeHeaderSlice<'a>, SMSError> {
+        Self::from_slice(slice)
+    }
+
+    /// Returns true if the header and cube data are little-endian.
+    #[inline]
+    pub fn is_little_endian(&self) -> bool {
+        self.little_endian
+    }
+
+    fn i32_at(&self, offset: usize) -> i32 {
+        let bytes = [
+            self.slice[offset],
+            self.slice[offset + 1],
+            self.slice[offset + 2],
+            self.slice[offset + 3],
+        ];
+        match self.little_endian {
+            true => i32::from_le_bytes(bytes),
+            false => i32::from_be_bytes(bytes),
+        }
+    }
+
+    fn i16_at(&self, offset: usize) -> i16 {
+        let bytes = [self.slice[offset], self.slice[offset + 1]];
+        match self.little_endian {
+            true => i16::from_le_bytes(bytes),
+            false => i16::from_be_bytes(bytes),
+        }
     }
 
     #[allow(clippy::wrong_self_convention)]
     /// Convert to owned CubeHeader struct.
     pub fn to_header(&self) -> CubeHeader {
         CubeHeader {
-            imag_offset: i32::from_be_bytes([
-                self.slice[0],
-                self.slice[1],
-                self.slice[2],
-                self.slice[3],
-            ]),
-            real_offset: i32::from_be_bytes([
-                self.slice[4],
-                self.slice[5],
-                self.slice[6],
-                self.slice[7],
-            ]),
-            range_gate_offset: i32::from_be_bytes([
-                self.slice[8],
-                self.slice[9],
-                self.slice[10],
-                self.slice[11],
-            ]),
-            doppler_bin_offset: i32::from_be_bytes([
-                self.slice[12],
-                self.slice[13],
-                self.slice[14],
-                self.slice[15],
-            ]),
-            rx_channel_offset: i32::from_be_bytes([
-                self.slice[16],
-                self.slice[17],
-                self.slice[18],
-                self.slice[19],
-            ]),
-            chirp_type_offset: i32::from_be_bytes([
-                self.slice[20],
-                self.slice[21],
-                self.slice[22],
-                self.slice[23],
-            ]),
-            range_gates: i16::from_be_bytes([self.slice[24], self.slice[25]]),
-            first_range_gate: i16::from_be_bytes([self.slice[26], self.slice[27]]),
-            doppler_bins: i16::from_be_bytes([self.slice[28], self.slice[29]]),
+            imag_offset: self.i32_at(0),
+            real_offset: self.i32_at(4),
+            range_gate_offset: self.i32_at(8),
+            doppler_bin_offset: self.i32_at(12),
+            rx_channel_offset: self.i32_at(16),
+            chirp_type_offset: self.i32_at(20),
+            range_gates: self.i16_at(24),
+            first_range_gate: self.i16_at(26),
+            doppler_bins: self.i16_at(28),
             rx_channels: i8::from_be_bytes([self.slice[30]]),
             chirp_types: i8::from_be_bytes([self.slice[31]]),
             element_size: i8::from_be_bytes([self.slice[32]]),
@@ -778,7 +912,7 @@ impl<'a> CubeHeaderSlice<'a> {
     /// Dimension methods used for cube size validation and analysis.
     #[allow(dead_code)]
     pub fn range_gates(&self) -> i16 {
-        i16::from_be_bytes([self.slice[24], self.slice[25]])
+        self.i16_at(24)
     }
 
     /// Returns the number of doppler bins of the range doppler matrix.
@@ -786,7 +920,7 @@ impl<'a> CubeHeaderSlice<'a> {
     /// Get number of doppler bins in radar cube.
     #[allow(dead_code)]
     pub fn doppler_bins(&self) -> i16 {
-        i16::from_be_bytes([self.slice[28], self.slice[29]])
+        self.i16_at(28)
     }
 
     /// Returns the number of channels (one range doppler matrix is stored for
@@ -812,17 +946,28 @@ impl<'a> CubeHeaderSlice<'a> {
         self.slice[39] as usize
     }
 
+    /// Returns the header length in bytes including the padding bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        CubeHeader::LEN + self.padding_bytes()
+    }
+
+    /// Returns true if the underlying slice is empty.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+
     /// Returns the slice containing the payload.
     #[inline]
     pub fn payload(&self) -> &'a [u8] {
         unsafe {
-            // SAFETY: Safe as the slice length was verified
-            // to be at least UdpHeader::LEN by "from_slice".
+            // SAFETY: Safe as the slice length was verified to hold
+            // the header and padding bytes by "from_slice".
             core::slice::from_raw_parts(
-                self.slice
-                    .as_ptr()
-                    .add(CubeHeader::LEN + self.padding_bytes()),
-                self.slice.len() - (CubeHeader::LEN + self.padding_bytes()),
+                self.slice.as_ptr().add(self.len()),
+                self.slice.len() - self.len(),
             )
         }
     }
@@ -854,7 +999,7 @@ pub struct BinPropertiesSlice<'a> {
 
 impl<'a> BinPropertiesSlice<'a> {
     /// Parse bin properties from byte slice.
-    pub fn from_slize(slice: &'a [u8]) -> Result<BinPropertiesSlice<'a>, SMSError> {
+    pub fn from_slice(slice: &'a [u8]) -> Result<BinPropertiesSlice<'a>, SMSError> {
         if slice.len() < BinProperties::LEN {
             return Err(SMSError::UnexpectedEndOfSlice(slice.len()));
         }
@@ -862,6 +1007,13 @@ impl<'a> BinPropertiesSlice<'a> {
         Ok(BinPropertiesSlice { slice })
     }
 
+    /// Parse bin properties from byte slice.
+    #[deprecated(since = "1.7.0", note = "use `from_slice`")]
+    #[allow(dead_code)]
+    pub fn from_slize(slice: &'a [u8]) -> Result<BinPropertiesSlice<'a>, SMSError> {
+        Self::from_slice(slice)
+    }
+
     #[allow(clippy::wrong_self_convention)]
     /// Convert to owned BinProperties struct.
     pub fn to_header(&self) -> BinProperties {
@@ -886,6 +1038,333 @@ impl<'a> BinPropertiesSlice<'a> {
             ]),
         }
     }
+
+    /// Returns the bin properties length in bytes.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        BinProperties::LEN
+    }
+
+    /// Returns true if the underlying slice is empty.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+
+    /// Returns the slice following the bin properties.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        unsafe {
+            // SAFETY: Safe as the slice length was verified
+            // to be at least BinProperties::LEN by "from_slice".
+            core::slice::from_raw_parts(
+                self.slice.as_ptr().add(BinProperties::LEN),
+                self.slice.len() - BinProperties::LEN,
+            )
+        }
+    }
+}
+
+/// An SMS packet classified by its debug flags and port, with zero-copy
+/// views of its headers.  [`RadarCubeReader::read`] assembles the cubes from
+/// these, [`SmsPacketSlice::to_packet`] copies them into an owned
+/// [`SmsPacket`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SmsPacketSlice<'a> {
+    /// Start of frame with the cube header and the first cube elements
+    CubeStart {
+        /// Transport header
+        transport: TransportHeaderSlice<'a>,
+        /// Debug header
+        debug: DebugHeaderSlice<'a>,
+        /// Port header
+        port: PortHeaderSlice<'a>,
+        /// Cube header, its payload holds the cube elements
+        cube: CubeHeaderSlice<'a>,
+    },
+    /// Cube elements following the start of frame, including the end of
+    /// data message
+    CubeData {
+        /// Transport header
+        transport: TransportHeaderSlice<'a>,
+        /// Debug header, its payload holds the cube elements
+        debug: DebugHeaderSlice<'a>,
+    },
+    /// End of frame, with the bin properties when sent from port 63
+    FrameFooter {
+        /// Transport header
+        transport: TransportHeaderSlice<'a>,
+        /// Debug header
+        debug: DebugHeaderSlice<'a>,
+        /// Bin properties of a port 63 footer
+        bin_properties: Option<BinPropertiesSlice<'a>>,
+    },
+    /// Port 63 bin properties sent without a debug header
+    BinProperties {
+        /// Transport header
+        transport: TransportHeaderSlice<'a>,
+        /// Bin properties
+        bin_properties: BinPropertiesSlice<'a>,
+    },
+    /// Packet of another application protocol or port
+    Unknown {
+        /// Transport header
+        transport: TransportHeaderSlice<'a>,
+    },
+}
+
+impl<'a> SmsPacketSlice<'a> {
+    /// Parse the header chain of a UDP payload.
+    ///
+    /// # Errors
+    /// Returns SMSError if a header is truncated or malformed, or
+    /// InvalidDebugFlags for a debug header of an unknown message type
+    pub fn from_slice(slice: &'a [u8]) -> Result<SmsPacketSlice<'a>, SMSError> {
+        let transport = TransportHeaderSlice::from_slice(slice)?;
+        let debug = match transport.debug_header() {
+            Ok(debug) => debug,
+            Err(SMSError::DebugHeaderMissing) => {
+                return Ok(match transport.bin_properties() {
+                    Ok(bin_properties) => SmsPacketSlice::BinProperties {
+                        transport,
+                        bin_properties,
+                    },
+                    Err(_) => SmsPacketSlice::Unknown { transport },
+                })
+            }
+            Err(err) => return Err(err),
+        };
+
+        match debug.flags() {
+            DebugHeader::START_OF_FRAME => Ok(SmsPacketSlice::CubeStart {
+                transport,
+                debug,
+                port: transport.port_header()?,
+                cube: transport.cube_header()?,
+            }),
+            DebugHeader::FRAME_DATA | DebugHeader::END_OF_DATA => {
+                Ok(SmsPacketSlice::CubeData { transport, debug })
+            }
+            // Port 63 is received independently of the cube data so the
+            // footer may carry another port.
+            DebugHeader::FRAME_FOOTER => Ok(SmsPacketSlice::FrameFooter {
+                transport,
+                debug,
+                bin_properties: transport.bin_properties().ok(),
+            }),
+            flags => Err(SMSError::InvalidDebugFlags(flags)),
+        }
+    }
+
+    /// Returns the transport header of the packet.
+    #[allow(dead_code)]
+    pub fn transport(&self) -> TransportHeaderSlice<'a> {
+        match self {
+            SmsPacketSlice::CubeStart { transport, .. }
+            | SmsPacketSlice::CubeData { transport, .. }
+            | SmsPacketSlice::FrameFooter { transport, .. }
+            | SmsPacketSlice::BinProperties { transport, .. }
+            | SmsPacketSlice::Unknown { transport } => *transport,
+        }
+    }
+
+    /// Copy the headers and payload into an owned packet.
+    #[allow(dead_code)]
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_packet(&self) -> SmsPacket {
+        match self {
+            SmsPacketSlice::CubeStart {
+                transport,
+                debug,
+                port,
+                cube,
+            } => SmsPacket::CubeStart {
+                transport: transport.to_header(),
+                debug: debug.to_header(),
+                port: port.to_header(),
+                cube: cube.to_header(),
+                data: cube.payload().to_vec(),
+            },
+            SmsPacketSlice::CubeData { transport, debug } => SmsPacket::CubeData {
+                transport: transport.to_header(),
+                debug: debug.to_header(),
+                data: debug.payload().to_vec(),
+            },
+            SmsPacketSlice::FrameFooter {
+                transport,
+                debug,
+                bin_properties,
+            } => SmsPacket::FrameFooter {
+                transport: transport.to_header(),
+                debug: debug.to_header(),
+                bin_properties: bin_properties.map(|bin_properties| bin_properties.to_header()),
+            },
+            SmsPacketSlice::BinProperties {
+                transport,
+                bin_properties,
+            } => SmsPacket::BinProperties {
+                transport: transport.to_header(),
+                bin_properties: bin_properties.to_header(),
+            },
+            SmsPacketSlice::Unknown { transport } => SmsPacket::Unknown {
+                transport: transport.to_header(),
+                payload: transport.payload().to_vec(),
+            },
+        }
+    }
+}
+
+/// An SMS packet with owned headers and payload, for tools walking a
+/// capture.  See [`SmsPacketSlice`] for the packet kinds.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum SmsPacket {
+    /// Start of frame with the first cube elements
+    CubeStart {
+        /// Transport header
+        transport: TransportHeader,
+        /// Debug header
+        debug: DebugHeader,
+        /// Port header
+        port: PortHeader,
+        /// Cube header
+        cube: CubeHeader,
+        /// Cube elements in the byte order of the port header
+        data: Vec<u8>,
+    },
+    /// Cube elements following the start of frame
+    CubeData {
+        /// Transport header
+        transport: TransportHeader,
+        /// Debug header
+        debug: DebugHeader,
+        /// Cube elements in the byte order of the start of frame
+        data: Vec<u8>,
+    },
+    /// End of frame, with the bin properties when sent from port 63
+    FrameFooter {
+        /// Transport header
+        transport: TransportHeader,
+        /// Debug header
+        debug: DebugHeader,
+        /// Bin properties of a port 63 footer
+        bin_properties: Option<BinProperties>,
+    },
+    /// Port 63 bin properties sent without a debug header
+    BinProperties {
+        /// Transport header
+        transport: TransportHeader,
+        /// Bin properties
+        bin_properties: BinProperties,
+    },
+    /// Packet of another application protocol or port
+    Unknown {
+        /// Transport header
+        transport: TransportHeader,
+        /// Transport payload
+        payload: Vec<u8>,
+    },
+}
+
+#[allow(dead_code)]
+impl SmsPacket {
+    /// Snake case name of the packet kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SmsPacket::CubeStart { .. } => "cube_start",
+            SmsPacket::CubeData { .. } => "cube_data",
+            SmsPacket::FrameFooter { .. } => "frame_footer",
+            SmsPacket::BinProperties { .. } => "bin_properties",
+            SmsPacket::Unknown { .. } => "unknown",
+        }
+    }
+
+    /// Returns the transport header of the packet.
+    pub fn transport(&self) -> &TransportHeader {
+        match self {
+            SmsPacket::CubeStart { transport, .. }
+            | SmsPacket::CubeData { transport, .. }
+            | SmsPacket::FrameFooter { transport, .. }
+            | SmsPacket::BinProperties { transport, .. }
+            | SmsPacket::Unknown { transport, .. } => transport,
+        }
+    }
+
+    /// JSON object of the packet kind, message counter and typed headers,
+    /// the payloads are reported by length.
+    pub fn to_json(&self) -> Value {
+        let transport = self.transport();
+        let mut msg = json!({
+            "kind": self.kind(),
+            "application_protocol": transport.application_protocol,
+            "message_counter": transport.message_counter.map(|counter| counter.0),
+            "payload_length": transport.payload_length,
+        });
+        let bin_json = |bin: &BinProperties| {
+            json!({
+                "speed_per_bin": bin.speed_per_bin,
+                "range_per_bin": bin.range_per_bin,
+                "bin_per_speed": bin.bin_per_speed,
+            })
+        };
+        match self {
+            SmsPacket::CubeStart {
+                debug,
+                port,
+                cube,
+                data,
+                ..
+            } => {
+                msg["frame_counter"] = json!(debug.frame_counter);
+                msg["port"] = json!({
+                    "id": port.id,
+                    "timestamp": port.timestamp,
+                    "size": port.size,
+                    "little_endian": port.endianess == 0,
+                });
+                msg["cube"] = json!({
+                    "shape": [cube.chirp_types, cube.range_gates, cube.rx_channels, cube.doppler_bins],
+                    "first_range_gate": cube.first_range_gate,
+                    "element_size": cube.element_size,
+                    "element_type": cube.element_type,
+                    "padding_bytes": cube.padding_bytes,
+                });
+                msg["data_length"] = json!(data.len());
+            }
+            SmsPacket::CubeData { debug, data, .. } => {
+                msg["frame_counter"] = json!(debug.frame_counter);
+                msg["end_of_data"] = json!(debug.flags == DebugHeader::END_OF_DATA);
+                msg["data_length"] = json!(data.len());
+            }
+            SmsPacket::FrameFooter {
+                debug,
+                bin_properties,
+                ..
+            } => {
+                msg["frame_counter"] = json!(debug.frame_counter);
+                msg["bin_properties"] = match bin_properties {
+                    Some(bin) => bin_json(bin),
+                    None => Value::Null,
+                };
+            }
+            SmsPacket::BinProperties { bin_properties, .. } => {
+                msg["bin_properties"] = bin_json(bin_properties);
+            }
+            SmsPacket::Unknown { .. } => (),
+        }
+        msg
+    }
+}
+
+/// Parse a UDP payload into an owned [`SmsPacket`].
+///
+/// # Errors
+/// Returns SMSError if a header is truncated or malformed
+#[allow(dead_code)]
+pub fn parse_packet(slice: &[u8]) -> Result<SmsPacket, SMSError> {
+    SmsPacketSlice::from_slice(slice).map(|packet| packet.to_packet())
 }
 
 /// Assembled radar cube with metadata.
@@ -904,6 +1383,8 @@ pub struct RadarCube {
     pub packets_skipped: u16,
     /// Bytes missing from cube data
     pub missing_data: usize,
+    /// Time from the start of frame packet until the cube was assembled
+    pub reassembly_time: Duration,
     /// Bin scaling factors
     pub bin_properties: BinProperties,
     /// 4D radar cube tensor
@@ -922,6 +1403,145 @@ impl fmt::Display for RadarCube {
     }
 }
 
+impl RadarCube {
+    /// Magnitude of every sample, the sample itself for a cube restored from
+    /// a magnitude message.
+    pub fn magnitude(&self) -> Array4<f32> {
+        self.data
+            .mapv(|sample| (sample.re as f32).hypot(sample.im as f32))
+    }
+}
+
+/// Layout of the published radar cubes.
+pub const CUBE_LAYOUT: [u8; 4] = [
+    radar_cube_dimension::SEQUENCE,
+    radar_cube_dimension::RANGE,
+    radar_cube_dimension::RXCHANNEL,
+    radar_cube_dimension::DOPPLER,
+];
+
+/// Rebuild a [`RadarCube`] from an `edgefirst_msgs/RadarCube` message.
+///
+/// The message must have the [`CUBE_LAYOUT`], a finite scale per dimension
+/// and a shape matching its samples.  The interleaved i16 samples of a
+/// complex message, whose last dimension is doubled, are paired back into
+/// complex samples while the samples of a magnitude message become the real
+/// parts, see [`RadarCube::magnitude`].  The bin properties come from the
+/// range and doppler scales, the frame counter and packet statistics are not
+/// part of the message and are left at zero.
+pub fn cube_from_msg(msg: &edgefirst_msgs::RadarCube) -> Result<RadarCube, SMSError> {
+    let invalid = |err: String| Err(SMSError::InvalidCubeMessage(err));
+    if msg.layout != CUBE_LAYOUT {
+        return invalid(format!("unexpected layout {:?}", msg.layout));
+    }
+    if msg.scales.len() != 4 || !msg.scales.iter().all(|scale| scale.is_finite()) {
+        return invalid(format!("unexpected scales {:?}", msg.scales));
+    }
+    let mut shape = match msg.shape[..] {
+        [a, b, c, d] => [a as usize, b as usize, c as usize, d as usize],
+        _ => return invalid(format!("unexpected shape {:?}", msg.shape)),
+    };
+    if msg.is_complex {
+        if shape[3] % 2 != 0 {
+            return invalid(format!("odd complex dimension in shape {:?}", msg.shape));
+        }
+        shape[3] /= 2;
+    }
+
+    let len = shape.iter().product::<usize>() * (1 + msg.is_complex as usize);
+    if msg.cube.len() != len {
+        return Err(SMSError::MissingCubeData(msg.cube.len(), len));
+    }
+    let data = match msg.is_complex {
+        true => msg
+            .cube
+            .chunks_exact(2)
+            .map(|pair| Complex::new(pair[0], pair[1]))
+            .collect(),
+        false => msg.cube.iter().map(|&re| Complex::new(re, 0)).collect(),
+    };
+
+    let speed_per_bin = msg.scales[3];
+    Ok(RadarCube {
+        timestamp: msg.timestamp,
+        frame_counter: 0,
+        packets_captured: 0,
+        packets_skipped: 0,
+        missing_data: 0,
+        reassembly_time: Duration::ZERO,
+        bin_properties: BinProperties {
+            speed_per_bin,
+            range_per_bin: msg.scales[1],
+            bin_per_speed: match speed_per_bin != 0.0 {
+                true => 1.0 / speed_per_bin,
+                false => 0.0,
+            },
+        },
+        data: Array4::from_shape_vec(shape, data)?,
+    })
+}
+
+/// Radar cube elements of a payload as 32-bit words in the given byte order,
+/// the imaginary part in the upper and the real part in the lower half.
+fn cube_elements(payload: &[u8], little_endian: bool) -> Vec<u32> {
+    payload
+        .chunks_exact(4)
+        .map(|chunk| {
+            let word = [chunk[0], chunk[1], chunk[2], chunk[3]];
+            match little_endian {
+                true => u32::from_le_bytes(word),
+                false => u32::from_be_bytes(word),
+            }
+        })
+        .collect()
+}
+
+/// Frame counter mismatch reported by the footer of an aborted frame, with
+/// the aborted frame totals of the reader.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FrameCounterMismatch {
+    /// Frame counter of the start of frame
+    pub expected: u32,
+    /// First frame counter which did not match
+    pub received: u32,
+    /// Data packets discarded from the aborted frame
+    pub packets_discarded: u64,
+    /// Frames aborted by the reader, including this one
+    pub frames_aborted: u64,
+    /// Data packets discarded from every aborted frame
+    pub total_packets_discarded: u64,
+}
+
+impl fmt::Display for FrameCounterMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "frame {} aborted by frame {} with {} packets discarded ({} frames aborted, {} packets discarded in total)",
+            self.expected,
+            self.received,
+            self.packets_discarded,
+            self.frames_aborted,
+            self.total_packets_discarded
+        )
+    }
+}
+
+/// Cumulative statistics of a [`RadarCubeReader`] which, unlike the per frame
+/// state, are kept across frames.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CubeReaderLifetimeStats {
+    /// Frames ended by a frame footer, whether or not a cube was assembled
+    pub total_frames: u64,
+    /// UDP packets dropped within the cube data
+    pub total_drops: u64,
+    /// Cube elements missing from assembled or incomplete cubes
+    pub total_missing_elements: u64,
+    /// Frames aborted by a frame counter mismatch
+    pub frames_aborted: u64,
+    /// Data packets discarded after a frame counter mismatch
+    pub packets_discarded_while_poisoned: u64,
+}
+
 /// Stateful reader for assembling radar cubes from UDP packets.
 ///
 /// Handles SMS protocol parsing, frame assembly, and packet loss detection.
@@ -934,11 +1554,17 @@ pub struct RadarCubeReader {
     received_messages: Wrapping<u16>,
     packets_captured: Wrapping<u16>,
     packets_skipped: Wrapping<u16>,
-    error: Option<SMSError>,
+    poisoned: Option<u32>,
+    packets_discarded: u64,
     cube_header: Option<CubeHeader>,
     cube_index: usize,
     cube_captured: usize,
     cube: Vec<Complex<i16>>,
+    little_endian: bool,
+    bin_properties: Option<BinProperties>,
+    frame_start: Option<Instant>,
+    lifetime: CubeReaderLifetimeStats,
+    limits: CubeLimits,
 }
 
 impl Default for RadarCubeReader {
@@ -958,40 +1584,95 @@ impl RadarCubeReader {
             received_messages: Wrapping(0),
             packets_captured: Wrapping(0),
             packets_skipped: Wrapping(0),
-            error: None,
+            poisoned: None,
+            packets_discarded: 0,
             cube_header: None,
             cube_index: 0,
             cube_captured: 0,
             cube: vec![],
+            little_endian: false,
+            bin_properties: None,
+            frame_start: None,
+            lifetime: CubeReaderLifetimeStats::default(),
+            limits: CubeLimits::default(),
+        }
+    }
+
+    /// Create a radar cube reader rejecting cubes beyond the limits.
+    pub fn with_limits(limits: CubeLimits) -> RadarCubeReader {
+        RadarCubeReader {
+            limits,
+            ..Self::new()
         }
     }
 
+    /// Discard the frame being assembled.  The lifetime statistics, the
+    /// latest bin properties and the cube limits are preserved, use
+    /// [`RadarCubeReader::reset_lifetime_stats`] to clear the statistics.
+    pub fn reset(&mut self) {
+        let lifetime = self.lifetime;
+        let bin_properties = self.bin_properties;
+        let limits = self.limits;
+        *self = Self::default();
+        self.lifetime = lifetime;
+        self.bin_properties = bin_properties;
+        self.limits = limits;
+    }
+
+    /// Returns the most recent bin properties received on port 63.
+    #[allow(dead_code)]
+    pub fn bin_properties(&self) -> Option<BinProperties> {
+        self.bin_properties
+    }
+
+    /// Returns the statistics accumulated since the reader was created or
+    /// the lifetime statistics were last reset.
+    pub fn lifetime_stats(&self) -> CubeReaderLifetimeStats {
+        self.lifetime
+    }
+
+    /// Clear the lifetime statistics.
+    #[allow(dead_code)]
+    pub fn reset_lifetime_stats(&mut self) {
+        self.lifetime = CubeReaderLifetimeStats::default();
+    }
+
     #[instrument(skip_all)]
     fn start_of_frame(
         &mut self,
         transport: &TransportHeaderSlice,
         debug_header: &DebugHeaderSlice,
+        port_header: &PortHeaderSlice,
+        cube_header: &CubeHeaderSlice,
     ) -> Result<Option<RadarCube>, SMSError> {
-        *self = Self::default();
-        self.timestamp = transport.port_header()?.timestamp();
+        self.reset();
+        self.frame_start = Some(Instant::now());
+        self.timestamp = port_header.timestamp();
         self.frame_counter = debug_header.frame_counter();
-        self.first_message = transport.message_counter().unwrap();
+        self.first_message = transport
+            .message_counter()
+            .ok_or(SMSError::MessageCounterMissing)?;
         self.message_counter = self.first_message;
         self.received_messages = Wrapping(1);
-        self.cube_header = Some(transport.cube_header()?.to_header());
-        self.cube = vec![Complex::<i16>::new(32767, 32767); self.volume()?];
+        self.little_endian = cube_header.is_little_endian();
+        self.cube_header = Some(cube_header.to_header());
+        // A malformed header must not leave a cube header without a cube.
+        let volume = match self.volume() {
+            Ok(volume) => volume,
+            Err(err) => {
+                self.reset();
+                return Err(err);
+            }
+        };
+        self.cube = vec![Complex::<i16>::new(32767, 32767); volume];
         // .resize(self.volume()?, Complex::<i16>::new(32767, 32767));
-        let cube: Vec<u32> = transport
-            .cube_header()?
-            .payload()
-            .chunks_exact(4)
-            .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
+        let cube = cube_elements(cube_header.payload(), self.little_endian);
         let cube =
             unsafe { std::slice::from_raw_parts(cube.as_ptr() as *const Complex<i16>, cube.len()) };
-        self.cube[..cube.len()].copy_from_slice(cube);
+        let len = min(cube.len(), self.cube.len());
+        self.cube[..len].copy_from_slice(&cube[..len]);
         self.cube_index = cube.len();
-        self.cube_captured = cube.len();
+        self.cube_captured = len;
         self.packets_captured = Wrapping(1);
 
         Ok(None)
@@ -1000,30 +1681,46 @@ impl RadarCubeReader {
     #[instrument(skip_all)]
     fn frame_footer(
         &mut self,
-        transport: &TransportHeaderSlice,
         debug_header: &DebugHeaderSlice,
     ) -> Result<Option<RadarCube>, SMSError> {
+        self.lifetime.total_frames += 1;
+        self.lifetime.total_drops += self.packets_skipped.0 as u64;
+
         if self.cube_header.is_none() {
-            *self = Self::default();
+            self.reset();
             return Err(SMSError::CubeHeaderMissing);
         }
 
-        if self.frame_counter != debug_header.frame_counter() {
-            *self = Self::default();
-            return Err(SMSError::FrameCounterError);
+        if self.poisoned.is_none() && self.frame_counter != debug_header.frame_counter() {
+            self.poisoned = Some(debug_header.frame_counter());
+            self.lifetime.frames_aborted += 1;
         }
 
-        if self.error.is_some() {
-            let mut error = None;
-            std::mem::swap(&mut self.error, &mut error);
-            *self = Self::default();
-            return Err(error.take().unwrap());
+        if let Some(received) = self.poisoned {
+            let mismatch = FrameCounterMismatch {
+                expected: self.frame_counter,
+                received,
+                packets_discarded: self.packets_discarded,
+                frames_aborted: self.lifetime.frames_aborted,
+                total_packets_discarded: self.lifetime.packets_discarded_while_poisoned,
+            };
+            self.reset();
+            return Err(SMSError::FrameCounterError(mismatch));
         }
 
         if self.cube_index < self.cube.len() {
+            self.lifetime.total_missing_elements += (self.cube.len() - self.cube_captured) as u64;
             return Err(SMSError::MissingCubeData(self.cube_index, self.cube.len()));
         }
 
+        let bin_properties = match self.bin_properties {
+            Some(bin_properties) => bin_properties,
+            None => {
+                self.reset();
+                return Err(SMSError::BinPropertiesMissing);
+            }
+        };
+
         let src = ArrayView4::from_shape(self.shape().unwrap(), &self.cube[..]).unwrap();
         let mut dst = Array4::<Complex<i16>>::zeros(self.shape().unwrap());
         let middle = src.shape()[3] / 2;
@@ -1038,12 +1735,17 @@ impl RadarCubeReader {
             packets_captured: self.packets_captured.0,
             packets_skipped: self.packets_skipped.0,
             frame_counter: self.frame_counter,
-            bin_properties: transport.bin_properties().unwrap().to_header(),
+            bin_properties,
             missing_data: self.volume()? - self.cube_captured,
+            reassembly_time: self
+                .frame_start
+                .map(|start| start.elapsed())
+                .unwrap_or_default(),
             data: dst,
         };
 
-        *self = Self::default();
+        self.lifetime.total_missing_elements += cube.missing_data as u64;
+        self.reset();
 
         Ok(Some(cube))
     }
@@ -1064,18 +1766,6 @@ impl RadarCubeReader {
             return Ok(None);
         }
 
-        // Ignore data messages if the frame counter does not match the
-        // current frame counter.  We also move the index to the end of
-        // the buffer to signal that we no longer want to read into the
-        // now corrupt cube.  An error will be returned once we reach
-        // the frame footer.
-        if self.frame_counter != debug_header.frame_counter() {
-            self.error = Some(SMSError::FrameCounterError);
-            self.cube_index = self.cube.len();
-
-            return Ok(None);
-        }
-
         let message_counter = match transport.message_counter() {
             Some(message_counter) => message_counter,
             None => return Err(SMSError::MessageCounterMissing),
@@ -1107,18 +1797,28 @@ impl RadarCubeReader {
             }
         }
 
+        // A frame counter mismatch aborts the now corrupt cube, the rest of
+        // its data messages are discarded but still advance the index so
+        // the skipped messages are counted.  The error is returned once we
+        // reach the frame footer.
+        if self.poisoned.is_none() && self.frame_counter != debug_header.frame_counter() {
+            self.poisoned = Some(debug_header.frame_counter());
+            self.lifetime.frames_aborted += 1;
+        }
+        if self.poisoned.is_some() {
+            self.packets_discarded += 1;
+            self.lifetime.packets_discarded_while_poisoned += 1;
+            self.cube_index += transport.debug_header()?.payload().len() / 4;
+            return Ok(None);
+        }
+
         // This is a quick check to see if the cube is full. As
         // the DRVEGRD protocol will always transmit the maximum
         // possible cube size we want to ignore the random data
         // transmitted after the cube.
         if self.cube_index < self.cube.len() {
             self.packets_captured += 1;
-            let cube: Vec<u32> = transport
-                .debug_header()?
-                .payload()
-                .chunks_exact(4)
-                .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                .collect();
+            let cube = cube_elements(transport.debug_header()?.payload(), self.little_endian);
             let cube = unsafe {
                 std::slice::from_raw_parts(cube.as_ptr() as *const Complex<i16>, cube.len())
             };
@@ -1141,17 +1841,78 @@ impl RadarCubeReader {
     ///
     /// # Errors
     /// Returns SMSError on protocol violations or missing data
+    ///
+    /// # Examples
+    ///
+    /// A socket loop passes each datagram to the reader, which returns the
+    /// cube once the frame footer arrives.  The fixture holds the start of
+    /// frame, data and footer packets of a small [1, 2, 1, 4] cube, each
+    /// padded to [`SMS_PACKET_SIZE`] as read from the port 50005 socket.
+    ///
+    /// ```
+    /// use radarpub::eth::{RadarCubeReader, SMS_PACKET_SIZE};
+    ///
+    /// let frame = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/cube_frame.bin"));
+    /// let mut reader = RadarCubeReader::new();
+    /// let mut cubes = Vec::new();
+    /// for packet in frame.chunks(SMS_PACKET_SIZE) {
+    ///     match reader.read(packet) {
+    ///         Ok(Some(cube)) => cubes.push(cube),
+    ///         Ok(None) => (),
+    ///         // The frame is dropped, the reader continues with the next.
+    ///         Err(err) => eprintln!("cube error: {}", err),
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(cubes.len(), 1);
+    /// let cube = &cubes[0];
+    /// assert_eq!(cube.frame_counter, 42);
+    /// assert_eq!(cube.data.shape(), &[1, 2, 1, 4]);
+    /// assert_eq!(cube.missing_data, 0);
+    /// assert_eq!(cube.bin_properties.range_per_bin, 0.5);
+    /// assert_eq!(reader.lifetime_stats().total_frames, 1);
+    /// ```
     pub fn read(&mut self, slice: &[u8]) -> Result<Option<RadarCube>, SMSError> {
-        let transport = TransportHeaderSlice::from_slice(slice)?;
-        let debug_header = transport.debug_header()?;
+        let packet = match SmsPacketSlice::from_slice(slice) {
+            Ok(packet) => packet,
+            Err(err) => {
+                // A start of frame with a corrupt port or cube header still
+                // ends the frame in progress.
+                let start_of_frame = TransportHeaderSlice::from_slice(slice)
+                    .and_then(|transport| transport.debug_header())
+                    .is_ok_and(|debug| debug.flags() == DebugHeader::START_OF_FRAME);
+                if start_of_frame {
+                    self.reset();
+                }
+                return Err(err);
+            }
+        };
 
-        match debug_header.flags() {
-            DebugHeader::START_OF_FRAME => self.start_of_frame(&transport, &debug_header),
-            DebugHeader::FRAME_FOOTER => self.frame_footer(&transport, &debug_header),
-            DebugHeader::FRAME_DATA | DebugHeader::END_OF_DATA => {
-                self.frame_data(&transport, &debug_header)
+        // The footer may carry a port other than 63, the latest bin
+        // properties are used for the cube instead.
+        match packet {
+            SmsPacketSlice::CubeStart {
+                transport,
+                debug,
+                port,
+                cube,
+            } => self.start_of_frame(&transport, &debug, &port, &cube),
+            SmsPacketSlice::CubeData { transport, debug } => self.frame_data(&transport, &debug),
+            SmsPacketSlice::FrameFooter {
+                debug,
+                bin_properties,
+                ..
+            } => {
+                if let Some(bin_properties) = bin_properties {
+                    self.bin_properties = Some(bin_properties.to_header());
+                }
+                self.frame_footer(&debug)
             }
-            flags => Err(SMSError::InvalidDebugFlags(flags)),
+            SmsPacketSlice::BinProperties { bin_properties, .. } => {
+                self.bin_properties = Some(bin_properties.to_header());
+                Ok(None)
+            }
+            SmsPacketSlice::Unknown { .. } => Err(SMSError::DebugHeaderMissing),
         }
     }
 
@@ -1159,16 +1920,39 @@ impl RadarCubeReader {
     /// the cube header is not present.  The shape is represented as
     /// [chirp_types, rx_channels, range_gates, doppler_bins] with each value
     /// being a complex 16-bit integer.
+    ///
+    /// # Errors
+    /// Returns InvalidCubeShape if a dimension is not positive or exceeds
+    /// [`CubeLimits::max_shape`], and CubeTooLarge if the cube exceeds
+    /// [`CubeLimits::max_bytes`]
     pub fn shape(&self) -> Result<[usize; 4], SMSError> {
-        match &self.cube_header {
-            Some(header) => Ok([
-                header.chirp_types as usize,
-                header.range_gates as usize,
-                header.rx_channels as usize,
-                header.doppler_bins as usize,
-            ]),
-            None => Err(SMSError::CubeHeaderMissing),
+        let header = match &self.cube_header {
+            Some(header) => header,
+            None => return Err(SMSError::CubeHeaderMissing),
+        };
+
+        let dims = [
+            header.chirp_types as i32,
+            header.range_gates as i32,
+            header.rx_channels as i32,
+            header.doppler_bins as i32,
+        ];
+        if dims
+            .iter()
+            .zip(self.limits.max_shape)
+            .any(|(&dim, max)| dim <= 0 || dim as usize > max)
+        {
+            return Err(SMSError::InvalidCubeShape(dims));
+        }
+
+        // Positive i16 and i8 dimensions cannot overflow the size.
+        let shape = dims.map(|dim| dim as usize);
+        let bytes = shape.iter().product::<usize>() * std::mem::size_of::<Complex<i16>>();
+        if bytes > self.limits.max_bytes {
+            return Err(SMSError::CubeTooLarge(bytes, self.limits.max_bytes));
         }
+
+        Ok(shape)
     }
 
     /// Returns the radar cube volume or the error CubeHeaderMissing if the cube
@@ -1184,76 +1968,1031 @@ mod tests {
     use etherparse::{SlicedPacket, TransportSlice};
     use log::error;
     use pcarp::Capture;
-    use std::fs::File;
+    use std::{collections::HashMap, fs::File};
 
     use super::*;
 
-    #[test]
-    #[ignore = "Requires testdata/office_3.pcapng fixture (TODO: add to repository)"]
-    fn test_pcap() -> Result<(), SMSError> {
-        let mut first_frame = None;
-        let mut last_frame = None;
+    const OFFICE_3: &str = "testdata/office_3.pcapng";
 
-        let office_3_first_frame = 27;
-        let office_3_last_frame = 71;
-        let path = "testdata/office_3.pcapng";
+    /// Returns the UDP payloads of the capture in order.
+    fn udp_payloads(path: &str) -> Vec<Vec<u8>> {
         let file = File::open(path).unwrap();
-
-        let mut reader = RadarCubeReader::default();
+        let mut payloads = Vec::new();
 
         for cap in Capture::new(file) {
             match SlicedPacket::from_ethernet(&cap.unwrap().data) {
                 Err(err) => error!("Err {:?}", err),
                 Ok(pkt) => {
                     if let Some(TransportSlice::Udp(udp)) = pkt.transport {
-                        if let Ok(sms) = TransportHeaderSlice::from_slice(udp.payload()) {
-                            if first_frame.is_none() {
-                                first_frame = sms.frame_counter();
-                            }
+                        payloads.push(udp.payload().to_vec());
+                    }
+                }
+            }
+        }
 
-                            last_frame = sms.frame_counter();
+        payloads
+    }
 
-                            // println!("{:?}", sms.to_header());
+    #[test]
+    #[ignore = "Requires testdata/office_3.pcapng fixture (TODO: add to repository)"]
+    fn test_pcap() -> Result<(), SMSError> {
+        let mut first_frame = None;
+        let mut last_frame = None;
 
-                            // if let Ok(debug) = sms.debug_header() {
-                            //     println!("{:?}", debug.to_header());
-                            // }
+        let office_3_first_frame = 27;
+        let office_3_last_frame = 71;
 
-                            // if let Ok(port) = sms.port_header() {
-                            //     println!("{:?}", port.to_header());
-                            // }
+        let mut reader = RadarCubeReader::default();
+        let mut cubes = Vec::new();
+        // Payload lengths in Complex<i16> elements of the start of frame and
+        // frame data messages, used to derive the expected packets per frame.
+        let mut start_len = None;
+        let mut data_len = None;
+
+        for payload in udp_payloads(OFFICE_3) {
+            if let Ok(sms) = TransportHeaderSlice::from_slice(&payload) {
+                if first_frame.is_none() {
+                    first_frame = sms.frame_counter();
+                }
 
-                            // if let Ok(cube) = sms.cube_header() {
-                            //     println!("{:?}", cube.to_header());
-                            // }
+                last_frame = sms.frame_counter();
 
-                            // if let Ok(bin) = sms.bin_properties() {
-                            //     println!("{:?}", bin.to_header());
-                            // }
+                if let Ok(debug) = sms.debug_header() {
+                    match debug.flags() {
+                        DebugHeader::START_OF_FRAME => {
+                            start_len = Some(sms.cube_header()?.payload().len() / 4)
                         }
-
-                        match reader.read(udp.payload()) {
-                            Ok(Some(cube)) => {
-                                println!(
-                                    "cube shape: {:?} size: {}",
-                                    cube.data.shape(),
-                                    cube.data.len()
-                                )
-                            }
-                            Ok(None) => (),
-                            // Ignore StartPattern errors when reading from pcap which includes
-                            // non-SMS data.
-                            Err(SMSError::StartPattern(_)) => (),
-                            Err(err) => println!("Cube Error: {:?}", err),
+                        DebugHeader::FRAME_DATA if data_len.is_none() => {
+                            data_len = Some(debug.payload().len() / 4)
                         }
+                        _ => (),
                     }
                 }
             }
+
+            match reader.read(&payload) {
+                Ok(Some(cube)) => {
+                    println!(
+                        "cube shape: {:?} size: {}",
+                        cube.data.shape(),
+                        cube.data.len()
+                    );
+                    cubes.push(cube);
+                }
+                Ok(None) => (),
+                // Ignore StartPattern errors when reading from pcap which includes
+                // non-SMS data.
+                Err(SMSError::StartPattern(_)) => (),
+                Err(err) => println!("Cube Error: {:?}", err),
+            }
         }
 
         assert_eq!(first_frame, Some(office_3_first_frame));
         assert_eq!(last_frame, Some(office_3_last_frame));
 
+        assert!(!cubes.is_empty());
+        let complete = cubes.iter().filter(|cube| cube.missing_data == 0).count();
+        assert!(
+            complete * 5 >= cubes.len() * 4,
+            "only {}/{} cubes complete",
+            complete,
+            cubes.len()
+        );
+
+        let shape = cubes[0].data.shape().to_vec();
+        let volume = cubes[0].data.len();
+        let (start_len, data_len) = (start_len.unwrap(), data_len.unwrap());
+        let expected_packets = 1 + (volume - start_len).div_ceil(data_len);
+
+        for cube in &cubes {
+            assert!(cube.bin_properties.speed_per_bin > 0.0);
+            assert!(cube.bin_properties.range_per_bin > 0.0);
+            assert_eq!(cube.data.shape(), &shape[..], "{}", cube);
+            assert_eq!(
+                cube.packets_captured as usize + cube.packets_skipped as usize,
+                expected_packets,
+                "{}",
+                cube
+            );
+        }
+
         Ok(())
     }
+
+    /// Wrap the payload in an SMS transport header with a message counter.
+    fn sms(protocol: u8, counter: u16, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x7E, 1, 14];
+        packet.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[protocol, 0, 0, 0, 0x01]);
+        packet.extend_from_slice(&counter.to_be_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    /// Port header with a big-endian payload.
+    fn port(id: u32, body: &[u8]) -> Vec<u8> {
+        let mut port = id.to_be_bytes().to_vec();
+        port.resize(PortHeader::LEN, 0);
+        port[20] = 1;
+        port.extend_from_slice(body);
+        port
+    }
+
+    fn debug(frame_counter: u32, flags: u8, body: &[u8]) -> Vec<u8> {
+        let mut debug = frame_counter.to_be_bytes().to_vec();
+        debug.extend_from_slice(&[flags, 0, 0, 0]);
+        debug.extend_from_slice(body);
+        debug
+    }
+
+    fn bin_properties(range_per_bin: f32) -> Vec<u8> {
+        [0.1f32, range_per_bin, 10.0]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect()
+    }
+
+    /// Start of frame carrying a complete [1, 2, 1, 4] cube.
+    fn start_of_frame(frame_counter: u32) -> Vec<u8> {
+        let mut cube = vec![0; CubeHeader::LEN];
+        cube[24..26].copy_from_slice(&2i16.to_be_bytes());
+        cube[28..30].copy_from_slice(&4i16.to_be_bytes());
+        cube[30] = 1;
+        cube[31] = 1;
+        cube.extend_from_slice(&[0; 8 * 4]);
+        let body = debug(frame_counter, DebugHeader::START_OF_FRAME, &port(5, &cube));
+        sms(5, 0, &body)
+    }
+
+    /// Frame footer from the given port, only port 63 carries bin properties.
+    fn frame_footer(frame_counter: u32, port_id: u32, range_per_bin: f32) -> Vec<u8> {
+        let body = port(port_id, &bin_properties(range_per_bin));
+        sms(
+            5,
+            1,
+            &debug(frame_counter, DebugHeader::FRAME_FOOTER, &body),
+        )
+    }
+
+    /// Port 63 bin properties sent without a debug header.
+    fn port63(range_per_bin: f32) -> Vec<u8> {
+        sms(8, 0, &port(63, &bin_properties(range_per_bin)))
+    }
+
+    #[test]
+    fn test_bin_properties_before_footer() {
+        let mut reader = RadarCubeReader::new();
+        assert!(matches!(reader.read(&port63(0.25)), Ok(None)));
+        assert!(matches!(reader.read(&start_of_frame(1)), Ok(None)));
+
+        // A stale packet from another port in place of the port 63 footer.
+        let cube = reader.read(&frame_footer(1, 5, 0.5)).unwrap().unwrap();
+        assert_eq!(cube.bin_properties.range_per_bin, 0.25);
+        assert_eq!(cube.missing_data, 0);
+
+        // A port 63 footer replaces the cached bin properties.
+        assert!(matches!(reader.read(&start_of_frame(2)), Ok(None)));
+        let cube = reader.read(&frame_footer(2, 63, 0.5)).unwrap().unwrap();
+        assert_eq!(cube.bin_properties.range_per_bin, 0.5);
+    }
+
+    #[test]
+    fn test_bin_properties_after_footer() {
+        let mut reader = RadarCubeReader::new();
+        assert!(matches!(reader.read(&start_of_frame(1)), Ok(None)));
+        assert!(matches!(
+            reader.read(&frame_footer(1, 5, 0.5)),
+            Err(SMSError::BinPropertiesMissing)
+        ));
+
+        assert!(matches!(reader.read(&port63(0.25)), Ok(None)));
+        assert_eq!(reader.bin_properties().unwrap().range_per_bin, 0.25);
+
+        assert!(matches!(reader.read(&start_of_frame(2)), Ok(None)));
+        let cube = reader.read(&frame_footer(2, 5, 0.5)).unwrap().unwrap();
+        assert_eq!(cube.frame_counter, 2);
+        assert_eq!(cube.bin_properties.range_per_bin, 0.25);
+        assert_eq!(reader.lifetime_stats().total_frames, 2);
+    }
+
+    #[test]
+    fn test_bin_properties_absent() {
+        let mut reader = RadarCubeReader::new();
+        for frame_counter in 1..=3 {
+            assert!(matches!(
+                reader.read(&start_of_frame(frame_counter)),
+                Ok(None)
+            ));
+            assert!(matches!(
+                reader.read(&frame_footer(frame_counter, 5, 0.5)),
+                Err(SMSError::BinPropertiesMissing)
+            ));
+        }
+        assert_eq!(reader.bin_properties(), None);
+        assert_eq!(reader.lifetime_stats().total_frames, 3);
+    }
+
+    /// Frame of a [1, 2, 1, 4] cube with elements re = i and im = -i split
+    /// over the start of frame and a data packet, in the given byte order.
+    fn endian_frame(frame_counter: u32, little_endian: bool) -> Vec<Vec<u8>> {
+        let i16_bytes = |value: i16| match little_endian {
+            true => value.to_le_bytes(),
+            false => value.to_be_bytes(),
+        };
+        let elements = |range: std::ops::Range<i16>| -> Vec<u8> {
+            range
+                .flat_map(|i| {
+                    let word = (i as u16 as u32) | (((-i) as u16 as u32) << 16);
+                    match little_endian {
+                        true => word.to_le_bytes(),
+                        false => word.to_be_bytes(),
+                    }
+                })
+                .collect()
+        };
+
+        let mut cube = vec![0; CubeHeader::LEN];
+        cube[24..26].copy_from_slice(&i16_bytes(2));
+        cube[28..30].copy_from_slice(&i16_bytes(4));
+        cube[30] = 1;
+        cube[31] = 1;
+        cube.extend_from_slice(&elements(1..5));
+        let mut port_header = port(5, &cube);
+        port_header[20] = match little_endian {
+            true => 0,
+            false => 1,
+        };
+        let start = debug(frame_counter, DebugHeader::START_OF_FRAME, &port_header);
+
+        let data = debug(frame_counter, DebugHeader::FRAME_DATA, &elements(5..9));
+        vec![
+            sms(5, 0, &start),
+            sms(5, 1, &data),
+            sms(
+                5,
+                2,
+                &debug(
+                    frame_counter,
+                    DebugHeader::FRAME_FOOTER,
+                    &port(63, &bin_properties(0.5)),
+                ),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_little_endian_cube() {
+        let mut port_header = port(5, &[]);
+        let slice = PortHeaderSlice::from_slice(&port_header).unwrap();
+        assert!(!slice.payload_is_little_endian());
+        assert_eq!(slice.to_header().endianess, 1);
+        port_header[20] = 0;
+        let slice = PortHeaderSlice::from_slice(&port_header).unwrap();
+        assert!(slice.payload_is_little_endian());
+
+        let mut bytes = vec![0; CubeHeader::LEN];
+        bytes[0..4].copy_from_slice(&(-7i32).to_le_bytes());
+        bytes[24..26].copy_from_slice(&128i16.to_le_bytes());
+        bytes[28..30].copy_from_slice(&256i16.to_le_bytes());
+        let cube = CubeHeaderSlice::from_slice_with_byte_order(&bytes, true).unwrap();
+        assert!(cube.is_little_endian());
+        assert_eq!(cube.range_gates(), 128);
+        assert_eq!(cube.doppler_bins(), 256);
+        assert_eq!(cube.to_header().imag_offset, -7);
+
+        // Both byte orders assemble the same cube.
+        let mut cubes = Vec::new();
+        for little_endian in [false, true] {
+            let mut reader = RadarCubeReader::new();
+            let mut cube = None;
+            for packet in endian_frame(1, little_endian) {
+                cube = reader.read(&packet).unwrap();
+            }
+            let cube = cube.unwrap();
+            assert_eq!(cube.data.shape(), &[1, 2, 1, 4]);
+            assert_eq!(cube.missing_data, 0);
+            cubes.push(cube.data);
+        }
+        assert_eq!(cubes[0], cubes[1]);
+        let mut elements: Vec<_> = cubes[1].iter().map(|c| (c.re, c.im)).collect();
+        elements.sort();
+        assert_eq!(elements, (1..9).map(|i| (i, -i)).collect::<Vec<_>>());
+    }
+
+    /// Packets of a [1, 4, 1, 4] cube with two elements in the start of frame
+    /// and in each data message, `(message_counter, frame_counter)`.
+    fn split_frame(frame_counter: u32, data: &[(u16, u32)]) -> Vec<Vec<u8>> {
+        let mut cube = vec![0; CubeHeader::LEN];
+        cube[24..26].copy_from_slice(&4i16.to_be_bytes());
+        cube[28..30].copy_from_slice(&4i16.to_be_bytes());
+        cube[30] = 1;
+        cube[31] = 1;
+        cube.extend_from_slice(&[0; 2 * 4]);
+        let start = debug(frame_counter, DebugHeader::START_OF_FRAME, &port(5, &cube));
+        let mut packets = vec![sms(5, 0, &start)];
+        for &(counter, frame_counter) in data {
+            let body = debug(frame_counter, DebugHeader::FRAME_DATA, &[0x55; 2 * 4]);
+            packets.push(sms(5, counter, &body));
+        }
+        packets
+    }
+
+    #[test]
+    fn test_frame_counter_mismatch() {
+        let mut reader = RadarCubeReader::new();
+        assert!(matches!(reader.read(&port63(0.5)), Ok(None)));
+
+        // Frame 9 interrupts frame 7, and message 3 is lost afterwards.
+        for packet in split_frame(7, &[(1, 7), (2, 9), (4, 7)]) {
+            assert!(matches!(reader.read(&packet), Ok(None)));
+        }
+        let stats = reader.lifetime_stats();
+        assert_eq!(stats.frames_aborted, 1);
+        assert_eq!(stats.packets_discarded_while_poisoned, 2);
+
+        let err = reader.read(&frame_footer(7, 63, 0.5)).unwrap_err();
+        let expected = FrameCounterMismatch {
+            expected: 7,
+            received: 9,
+            packets_discarded: 2,
+            frames_aborted: 1,
+            total_packets_discarded: 2,
+        };
+        assert!(matches!(err, SMSError::FrameCounterError(mismatch) if mismatch == expected));
+        assert_eq!(
+            err.to_string(),
+            "frame counter error: frame 7 aborted by frame 9 with 2 packets discarded \
+             (1 frames aborted, 2 packets discarded in total)"
+        );
+        // The message lost while poisoned is still counted.
+        let stats = reader.lifetime_stats();
+        assert_eq!((stats.total_frames, stats.total_drops), (1, 1));
+
+        // The totals survive the following frames.
+        assert!(matches!(reader.read(&start_of_frame(8)), Ok(None)));
+        assert!(reader.read(&frame_footer(8, 63, 0.5)).unwrap().is_some());
+
+        // A footer of another frame aborts the frame without data messages.
+        assert!(matches!(reader.read(&start_of_frame(10)), Ok(None)));
+        let err = reader.read(&frame_footer(11, 63, 0.5)).unwrap_err();
+        let expected = FrameCounterMismatch {
+            expected: 10,
+            received: 11,
+            packets_discarded: 0,
+            frames_aborted: 2,
+            total_packets_discarded: 2,
+        };
+        assert!(matches!(err, SMSError::FrameCounterError(mismatch) if mismatch == expected));
+        let stats = reader.lifetime_stats();
+        assert_eq!(stats.total_frames, 3);
+        assert_eq!(stats.frames_aborted, 2);
+        assert_eq!(stats.packets_discarded_while_poisoned, 2);
+    }
+
+    #[test]
+    fn test_parse_packet() {
+        let frame = include_bytes!("../testdata/cube_frame.bin");
+        let packets: Vec<_> = frame
+            .chunks(SMS_PACKET_SIZE)
+            .map(|packet| parse_packet(packet).unwrap())
+            .collect();
+        let kinds: Vec<_> = packets.iter().map(|packet| packet.kind()).collect();
+        assert_eq!(kinds, ["cube_start", "cube_data", "frame_footer"]);
+
+        match &packets[0] {
+            SmsPacket::CubeStart {
+                transport,
+                debug,
+                port,
+                cube,
+                data,
+            } => {
+                assert_eq!(transport.message_counter, Some(Wrapping(0)));
+                assert_eq!(debug.frame_counter, 42);
+                assert_eq!(port.id, 5);
+                assert_eq!((cube.range_gates, cube.doppler_bins), (2, 4));
+                assert_eq!(data.len(), 4 * 4);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(&packets[1], SmsPacket::CubeData { data, .. } if data.len() == 4 * 4));
+        assert!(matches!(
+            &packets[2],
+            SmsPacket::FrameFooter { bin_properties: Some(bin), .. } if bin.range_per_bin == 0.5
+        ));
+
+        let msg = packets[0].to_json();
+        assert_eq!(msg["kind"], "cube_start");
+        assert_eq!(msg["frame_counter"], 42);
+        assert_eq!(msg["cube"]["shape"], json!([1, 2, 1, 4]));
+        assert_eq!(msg["data_length"], 16);
+        assert_eq!(packets[2].to_json()["bin_properties"]["range_per_bin"], 0.5);
+
+        // The zero-copy packet is what the reader consumes.
+        let packet = SmsPacketSlice::from_slice(&frame[..SMS_PACKET_SIZE]).unwrap();
+        assert!(matches!(packet, SmsPacketSlice::CubeStart { .. }));
+        assert_eq!(packet.transport().message_counter(), Some(Wrapping(0)));
+        assert_eq!(packet.to_packet(), packets[0]);
+
+        let packet = parse_packet(&port63(0.25)).unwrap();
+        assert_eq!(packet.kind(), "bin_properties");
+        assert_eq!(packet.to_json()["bin_properties"]["range_per_bin"], 0.25);
+
+        // Another application protocol, and an unknown debug message type.
+        let packet = parse_packet(&sms(9, 3, &[1, 2, 3])).unwrap();
+        assert_eq!(
+            packet,
+            SmsPacket::Unknown {
+                transport: TransportHeaderSlice::from_slice(&sms(9, 3, &[1, 2, 3]))
+                    .unwrap()
+                    .to_header(),
+                payload: vec![1, 2, 3],
+            }
+        );
+        assert!(matches!(
+            parse_packet(&sms(5, 0, &debug(1, 7, &[]))),
+            Err(SMSError::InvalidDebugFlags(7))
+        ));
+        let mut reader = RadarCubeReader::new();
+        assert!(matches!(
+            reader.read(&sms(9, 3, &[1, 2, 3])),
+            Err(SMSError::DebugHeaderMissing)
+        ));
+    }
+
+    /// Tallies the packet kinds of the capture, every frame between the
+    /// first and last start of frame has a start, data messages and a
+    /// footer, and the reader ends as many frames as there are footers.
+    #[test]
+    #[ignore = "Requires testdata/office_3.pcapng fixture (TODO: add to repository)"]
+    fn test_pcap_packet_kinds() {
+        let mut kinds = HashMap::<&str, usize>::new();
+        let mut starts = Vec::new();
+        let mut reader = RadarCubeReader::default();
+
+        for payload in udp_payloads(OFFICE_3) {
+            let _ = reader.read(&payload);
+            let packet = match parse_packet(&payload) {
+                Ok(packet) => packet,
+                Err(SMSError::StartPattern(_)) => continue,
+                Err(err) => panic!("unexpected {:?}", err),
+            };
+            if let SmsPacket::CubeStart { debug, .. } = &packet {
+                starts.push(debug.frame_counter);
+            }
+            *kinds.entry(packet.kind()).or_default() += 1;
+        }
+
+        // The capture holds frames 27 to 71, the first and last may be cut.
+        let (first, last) = (starts[0], starts[starts.len() - 1]);
+        assert!((27..=28).contains(&first) && (70..=71).contains(&last));
+        assert_eq!(starts, (first..=last).collect::<Vec<_>>());
+        let footers = kinds["frame_footer"];
+        assert!(footers.abs_diff(starts.len()) <= 1);
+        assert!(kinds["cube_data"] > 10 * starts.len());
+        assert_eq!(reader.lifetime_stats().total_frames, footers as u64);
+    }
+
+    #[test]
+    fn test_reset_preserves_lifetime_stats() {
+        let mut reader = RadarCubeReader::new();
+        reader.frame_counter = 42;
+        reader.packets_skipped = Wrapping(3);
+        reader.lifetime = CubeReaderLifetimeStats {
+            total_frames: 10,
+            total_drops: 4,
+            total_missing_elements: 256,
+            frames_aborted: 2,
+            packets_discarded_while_poisoned: 5,
+        };
+
+        reader.reset();
+        assert_eq!(reader.frame_counter, 0);
+        assert_eq!(reader.packets_skipped, Wrapping(0));
+        assert_eq!(reader.lifetime_stats().total_frames, 10);
+        assert_eq!(reader.lifetime_stats().total_drops, 4);
+        assert_eq!(reader.lifetime_stats().total_missing_elements, 256);
+        assert_eq!(reader.lifetime_stats().frames_aborted, 2);
+        assert_eq!(reader.lifetime_stats().packets_discarded_while_poisoned, 5);
+
+        reader.reset_lifetime_stats();
+        assert_eq!(reader.lifetime_stats(), CubeReaderLifetimeStats::default());
+    }
+
+    /// Drops every 10th UDP payload from the capture and verifies where the
+    /// reader reports the loss.  The reader does not fail a frame for dropped
+    /// data messages, they are counted in packets_skipped and the gap is left
+    /// as missing_data for the client to decide.  Losing the start of frame
+    /// fails the frame at its footer and losing the footer loses the frame.
+    #[test]
+    #[ignore = "Requires testdata/office_3.pcapng fixture (TODO: add to repository)"]
+    fn test_pcap_missing_data() {
+        let mut reader = RadarCubeReader::default();
+
+        // Dropped data messages per frame counter.
+        let mut dropped = HashMap::<u32, u16>::new();
+        let mut dropped_start = None;
+        let mut skipped_total = 0;
+        let mut failed = 0;
+
+        for (i, payload) in udp_payloads(OFFICE_3).iter().enumerate() {
+            let sms = TransportHeaderSlice::from_slice(payload).ok();
+            let frame = sms.as_ref().and_then(|sms| sms.frame_counter());
+            let flags = sms
+                .as_ref()
+                .and_then(|sms| sms.debug_header().ok())
+                .map(|debug| debug.flags());
+
+            if i % 10 == 9 {
+                match (flags, frame) {
+                    (Some(DebugHeader::START_OF_FRAME), frame) => dropped_start = frame,
+                    (Some(DebugHeader::FRAME_DATA | DebugHeader::END_OF_DATA), Some(frame)) => {
+                        *dropped.entry(frame).or_default() += 1
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+
+            let result = reader.read(payload);
+            if flags != Some(DebugHeader::FRAME_FOOTER) {
+                assert!(
+                    matches!(result, Ok(None) | Err(SMSError::StartPattern(_))),
+                    "packet {}: {:?}",
+                    i,
+                    result
+                );
+                continue;
+            }
+
+            let frame = frame.unwrap();
+            match result {
+                Ok(Some(cube)) => {
+                    assert_ne!(dropped_start, Some(frame), "packet {}", i);
+                    assert_eq!(cube.frame_counter, frame);
+
+                    let lost = dropped.get(&frame).copied().unwrap_or_default();
+                    assert!(cube.packets_skipped <= lost, "packet {}: {}", i, cube);
+                    if cube.packets_skipped > 0 {
+                        assert!(cube.missing_data > 0, "packet {}: {}", i, cube);
+                    }
+                    skipped_total += cube.packets_skipped as usize;
+                }
+                Err(SMSError::CubeHeaderMissing) => {
+                    assert_eq!(dropped_start, Some(frame), "packet {}", i);
+                    failed += 1;
+                }
+                other => panic!("packet {}: unexpected {:?}", i, other),
+            }
+        }
+
+        assert!(skipped_total > 0);
+        println!(
+            "skipped {} packets, failed {} frames",
+            skipped_total, failed
+        );
+    }
+
+    #[test]
+    fn test_transport_header_payload_length() {
+        let body = debug(1, DebugHeader::FRAME_DATA, &[1, 2, 3, 4]);
+        let mut packet = sms(5, 7, &body);
+        // Datagram padding after the payload must not reach the parsers.
+        packet.extend_from_slice(&[0xAA; 6]);
+
+        let transport = TransportHeaderSlice::from_slice(&packet).unwrap();
+        assert_eq!(transport.len(), 14);
+        assert_eq!(transport.payload_length(), body.len());
+        assert_eq!(transport.payload(), &body[..]);
+        assert_eq!(transport.message_counter(), Some(Wrapping(7)));
+        assert_eq!(transport.debug_header().unwrap().payload(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_header_slice_len() {
+        let body = debug(
+            1,
+            DebugHeader::START_OF_FRAME,
+            &port(63, &bin_properties(0.25)),
+        );
+        let packet = sms(5, 0, &body);
+        let transport = TransportHeaderSlice::from_slice(&packet).unwrap();
+
+        let debug = transport.debug_header().unwrap();
+        assert_eq!(debug.len(), DebugHeader::LEN);
+        assert!(!debug.is_empty());
+        assert_eq!(debug.len() + debug.payload().len(), body.len());
+
+        let port = transport.port_header().unwrap();
+        assert_eq!(port.len(), PortHeader::LEN);
+        assert!(!port.is_empty());
+        assert_eq!(
+            port.len() + port.payload().len(),
+            body.len() - DebugHeader::LEN
+        );
+
+        let bins = port.bin_properties().unwrap();
+        assert_eq!(bins.len(), BinProperties::LEN);
+        assert!(!bins.is_empty());
+    }
+
+    #[test]
+    fn test_transport_header_malformed() {
+        let packet = sms(5, 0, &debug(1, DebugHeader::FRAME_DATA, &[0; 8]));
+
+        // Header length disagrees with the length derived from the flags.
+        let mut header_length = packet.clone();
+        header_length[2] = 16;
+        assert!(matches!(
+            TransportHeaderSlice::from_slice(&header_length),
+            Err(SMSError::InvalidHeaderLength(16))
+        ));
+
+        // Flags claim a client id which the header length does not hold.
+        let mut flags = packet.clone();
+        flags[9] |= 0x08;
+        assert!(matches!(
+            TransportHeaderSlice::from_slice(&flags),
+            Err(SMSError::InvalidHeaderLength(14))
+        ));
+
+        // Payload length beyond the end of the datagram.
+        let mut payload_length = packet.clone();
+        payload_length[3..5].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert!(matches!(
+            TransportHeaderSlice::from_slice(&payload_length),
+            Err(SMSError::UnexpectedEndOfSlice(_))
+        ));
+
+        for len in 0..packet.len() {
+            assert!(
+                TransportHeaderSlice::from_slice(&packet[..len]).is_err(),
+                "truncated to {}",
+                len
+            );
+        }
+    }
+
+    /// Every single byte mutation of the transport header either fails to
+    /// parse or yields a header and payload bounded by the datagram.
+    #[test]
+    fn test_transport_header_mutations() {
+        let mut packet = sms(5, 0, &debug(1, DebugHeader::FRAME_DATA, &[0x55; 16]));
+        packet.extend_from_slice(&[0xAA; 8]);
+
+        for index in 0..14 {
+            for value in 0..=u8::MAX {
+                let mut mutated = packet.clone();
+                mutated[index] = value;
+                let transport = match TransportHeaderSlice::from_slice(&mutated) {
+                    Ok(transport) => transport,
+                    Err(_) => continue,
+                };
+
+                assert_eq!(transport.len(), mutated[2] as usize);
+                let payload = transport.payload();
+                assert_eq!(payload.len(), transport.payload_length());
+                assert!(transport.len() + payload.len() <= mutated.len());
+                if !(3..5).contains(&index) {
+                    assert!(!payload.contains(&0xAA), "byte {} = {}", index, value);
+                }
+            }
+        }
+    }
+
+    /// Offset of the cube header within a start of frame packet.
+    #[test]
+    fn test_cube_header_slice() {
+        let mut bytes = vec![0; CubeHeader::LEN];
+        bytes[0..4].copy_from_slice(&(-7i32).to_be_bytes());
+        bytes[4..8].copy_from_slice(&3i32.to_be_bytes());
+        bytes[24..26].copy_from_slice(&128i16.to_be_bytes());
+        bytes[26..28].copy_from_slice(&5i16.to_be_bytes());
+        bytes[28..30].copy_from_slice(&256i16.to_be_bytes());
+        bytes[30] = 12;
+        bytes[31] = 2;
+        bytes[32] = 4;
+        bytes[39] = 2;
+        bytes.extend_from_slice(&[0xFF, 0xFF, 1, 2, 3]);
+
+        let cube = CubeHeaderSlice::from_slice(&bytes).unwrap();
+        assert_eq!(cube.range_gates(), 128);
+        assert_eq!(cube.doppler_bins(), 256);
+        assert_eq!(cube.rx_channels(), 12);
+        assert_eq!(cube.chirp_types(), 2);
+        assert_eq!(cube.padding_bytes(), 2);
+        assert_eq!(cube.len(), CubeHeader::LEN + 2);
+        assert!(!cube.is_empty());
+        assert_eq!(cube.payload(), &[1, 2, 3]);
+
+        let header = cube.to_header();
+        assert_eq!(header.imag_offset, -7);
+        assert_eq!(header.real_offset, 3);
+        assert_eq!(header.range_gates, 128);
+        assert_eq!(header.first_range_gate, 5);
+        assert_eq!(header.doppler_bins, 256);
+        assert_eq!(header.rx_channels, 12);
+        assert_eq!(header.chirp_types, 2);
+        assert_eq!(header.element_size, 4);
+        assert_eq!(header.padding_bytes, 2);
+
+        // Header and padding without payload.
+        let cube = CubeHeaderSlice::from_slice(&bytes[..CubeHeader::LEN + 2]).unwrap();
+        assert!(cube.payload().is_empty());
+
+        for len in [0, CubeHeader::LEN - 1, CubeHeader::LEN + 1] {
+            assert!(matches!(
+                CubeHeaderSlice::from_slice(&bytes[..len]),
+                Err(SMSError::UnexpectedEndOfSlice(n)) if n == len
+            ));
+        }
+
+        #[allow(deprecated)]
+        let cube = CubeHeaderSlice::from_slize(&bytes).unwrap();
+        assert_eq!(cube, CubeHeaderSlice::from_slice(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_bin_properties_slice() {
+        let mut bytes = bin_properties(0.25);
+        bytes.extend_from_slice(&[1, 2]);
+
+        let bins = BinPropertiesSlice::from_slice(&bytes).unwrap();
+        assert_eq!(
+            bins.to_header(),
+            BinProperties {
+                speed_per_bin: 0.1,
+                range_per_bin: 0.25,
+                bin_per_speed: 10.0,
+            }
+        );
+        assert_eq!(bins.len(), BinProperties::LEN);
+        assert!(!bins.is_empty());
+        assert_eq!(bins.payload(), &[1, 2]);
+
+        let bins = BinPropertiesSlice::from_slice(&bytes[..BinProperties::LEN]).unwrap();
+        assert!(bins.payload().is_empty());
+
+        assert!(matches!(
+            BinPropertiesSlice::from_slice(&bytes[..BinProperties::LEN - 1]),
+            Err(SMSError::UnexpectedEndOfSlice(11))
+        ));
+
+        #[allow(deprecated)]
+        let bins = BinPropertiesSlice::from_slize(&bytes).unwrap();
+        assert_eq!(bins, BinPropertiesSlice::from_slice(&bytes).unwrap());
+    }
+
+    const CUBE_OFFSET: usize = 14 + DebugHeader::LEN + PortHeader::LEN;
+
+    fn cube_header(
+        chirp_types: i8,
+        range_gates: i16,
+        rx_channels: i8,
+        doppler_bins: i16,
+    ) -> CubeHeader {
+        CubeHeader {
+            imag_offset: 0,
+            real_offset: 0,
+            range_gate_offset: 0,
+            doppler_bin_offset: 0,
+            rx_channel_offset: 0,
+            chirp_type_offset: 0,
+            range_gates,
+            first_range_gate: 0,
+            doppler_bins,
+            rx_channels,
+            chirp_types,
+            element_size: 4,
+            element_type: 0,
+            padding_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn test_cube_shape_validation() {
+        let mut reader = RadarCubeReader::new();
+        assert!(matches!(reader.shape(), Err(SMSError::CubeHeaderMissing)));
+
+        reader.cube_header = Some(cube_header(2, 128, 12, 256));
+        assert_eq!(reader.shape().unwrap(), [2, 128, 12, 256]);
+        assert_eq!(reader.volume().unwrap(), 2 * 128 * 12 * 256);
+
+        for (header, dims) in [
+            (cube_header(2, -128, 12, 256), [2, -128, 12, 256]),
+            (cube_header(-1, 128, 12, 256), [-1, 128, 12, 256]),
+            (cube_header(2, 128, 12, 0), [2, 128, 12, 0]),
+            (cube_header(2, 128, 0, 256), [2, 128, 0, 256]),
+            (
+                cube_header(i8::MAX, i16::MAX, i8::MAX, i16::MAX),
+                [127, 32767, 127, 32767],
+            ),
+        ] {
+            reader.cube_header = Some(header);
+            match reader.volume() {
+                Err(SMSError::InvalidCubeShape(shape)) => assert_eq!(shape, dims),
+                other => panic!("{:?}: unexpected {:?}", dims, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_cube_limits() {
+        // Within the dimension limits but above the 1 MiB size limit.
+        let mut reader = RadarCubeReader::with_limits(CubeLimits {
+            max_bytes: 1 << 20,
+            ..Default::default()
+        });
+        reader.cube_header = Some(cube_header(2, 128, 12, 256));
+        assert!(matches!(
+            reader.volume(),
+            Err(SMSError::CubeTooLarge(3145728, 1048576))
+        ));
+
+        // The limits outlive the reset of each frame.
+        reader.reset();
+        reader.cube_header = Some(cube_header(1, 128, 12, 128));
+        assert_eq!(reader.volume().unwrap(), 128 * 12 * 128);
+        reader.cube_header = Some(cube_header(1, 128, 12, 512));
+        assert!(matches!(
+            reader.volume(),
+            Err(SMSError::CubeTooLarge(3145728, 1048576))
+        ));
+
+        let mut reader = RadarCubeReader::with_limits(CubeLimits {
+            max_shape: [1, 2, 1, 4],
+            ..Default::default()
+        });
+        reader.cube_header = Some(cube_header(1, 2, 1, 8));
+        assert!(matches!(
+            reader.volume(),
+            Err(SMSError::InvalidCubeShape([1, 2, 1, 8]))
+        ));
+    }
+
+    #[test]
+    fn test_start_of_frame_too_large() {
+        let mut reader = RadarCubeReader::with_limits(CubeLimits {
+            max_bytes: 64,
+            ..Default::default()
+        });
+        let mut packet = start_of_frame(1);
+        packet[CUBE_OFFSET + 28..CUBE_OFFSET + 30].copy_from_slice(&64i16.to_be_bytes());
+        assert!(matches!(
+            reader.read(&packet),
+            Err(SMSError::CubeTooLarge(512, 64))
+        ));
+        assert_eq!(reader.cube.capacity(), 0);
+
+        // The reader recovers on the next valid start of frame.
+        assert!(matches!(reader.read(&port63(0.25)), Ok(None)));
+        assert!(matches!(reader.read(&start_of_frame(2)), Ok(None)));
+        let cube = reader.read(&frame_footer(2, 63, 0.25)).unwrap().unwrap();
+        assert_eq!(cube.frame_counter, 2);
+        assert_eq!(cube.data.shape(), &[1, 2, 1, 4]);
+    }
+
+    #[test]
+    fn test_start_of_frame_invalid_shape() {
+        let mut reader = RadarCubeReader::new();
+        let mut packet = start_of_frame(1);
+        packet[CUBE_OFFSET + 24..CUBE_OFFSET + 26].copy_from_slice(&(-2i16).to_be_bytes());
+        assert!(matches!(
+            reader.read(&packet),
+            Err(SMSError::InvalidCubeShape([1, -2, 1, 4]))
+        ));
+
+        // The rejected header is not kept for the following footer.
+        assert!(matches!(reader.read(&port63(0.25)), Ok(None)));
+        assert!(matches!(
+            reader.read(&frame_footer(1, 63, 0.25)),
+            Err(SMSError::CubeHeaderMissing)
+        ));
+    }
+
+    #[test]
+    fn test_start_of_frame_malformed() {
+        let mut reader = RadarCubeReader::new();
+
+        // Padding bytes beyond the end of the packet.
+        let mut packet = start_of_frame(1);
+        packet[CUBE_OFFSET + 39] = 255;
+        assert!(matches!(
+            reader.read(&packet),
+            Err(SMSError::UnexpectedEndOfSlice(_))
+        ));
+
+        // Start of frame without the message counter.
+        let mut packet = start_of_frame(1);
+        packet[2] = 12;
+        packet[9] = 0;
+        packet.drain(10..12);
+        assert!(matches!(
+            reader.read(&packet),
+            Err(SMSError::MessageCounterMissing)
+        ));
+
+        // A start of frame payload larger than the cube fills the cube.
+        let mut packet = start_of_frame(1);
+        packet.extend_from_slice(&[0; 16]);
+        let len = packet.len() - 14;
+        packet[3..5].copy_from_slice(&(len as u16).to_be_bytes());
+        assert!(matches!(reader.read(&packet), Ok(None)));
+        assert!(matches!(reader.read(&port63(0.25)), Ok(None)));
+        let cube = reader.read(&frame_footer(1, 63, 0.25)).unwrap().unwrap();
+        assert_eq!(cube.missing_data, 0);
+        assert_eq!(cube.data.shape(), &[1, 2, 1, 4]);
+    }
+
+    #[test]
+    fn test_start_of_frame_truncated() {
+        let mut reader = RadarCubeReader::new();
+        assert!(matches!(reader.read(&port63(0.25)), Ok(None)));
+        assert!(matches!(reader.read(&start_of_frame(1)), Ok(None)));
+
+        // A start of frame cut off in the cube header ends the frame in
+        // progress, its footer must not complete the cube.
+        let body = debug(2, DebugHeader::START_OF_FRAME, &port(5, &[0; 8]));
+        assert!(matches!(
+            reader.read(&sms(5, 0, &body)),
+            Err(SMSError::UnexpectedEndOfSlice(8))
+        ));
+        assert!(matches!(
+            reader.read(&frame_footer(1, 63, 0.25)),
+            Err(SMSError::CubeHeaderMissing)
+        ));
+    }
+
+    #[test]
+    fn test_cube_from_msg() {
+        use edgefirst_schemas::{builtin_interfaces::Time, std_msgs::Header};
+
+        let msg = || edgefirst_msgs::RadarCube {
+            header: Header {
+                stamp: Time { sec: 0, nanosec: 0 },
+                frame_id: String::from("radar"),
+            },
+            timestamp: 42,
+            layout: CUBE_LAYOUT.to_vec(),
+            shape: vec![1, 2, 1, 4],
+            scales: vec![1.0, 0.5, 1.0, 0.0],
+            cube: vec![3, -4, 0, 5, -6, 8, 1, 0],
+            is_complex: true,
+        };
+        let cube = cube_from_msg(&msg()).unwrap();
+        assert_eq!(cube.timestamp, 42);
+        assert_eq!(cube.data.shape(), &[1, 2, 1, 2]);
+        assert_eq!(cube.data[[0, 1, 0, 0]], Complex::new(-6, 8));
+        assert_eq!(cube.bin_properties.range_per_bin, 0.5);
+        // No doppler scale, no inverse.
+        assert_eq!(cube.bin_properties.bin_per_speed, 0.0);
+        let magnitude = cube.magnitude();
+        assert_eq!(
+            magnitude.iter().copied().collect::<Vec<_>>(),
+            [5.0, 5.0, 10.0, 1.0]
+        );
+
+        let magnitudes = edgefirst_msgs::RadarCube {
+            is_complex: false,
+            shape: vec![1, 2, 1, 4],
+            ..msg()
+        };
+        let cube = cube_from_msg(&magnitudes).unwrap();
+        assert_eq!(cube.data.shape(), &[1, 2, 1, 4]);
+        assert_eq!(cube.magnitude()[[0, 0, 0, 1]], 4.0);
+
+        let invalid = [
+            edgefirst_msgs::RadarCube {
+                layout: CUBE_LAYOUT[..3].to_vec(),
+                ..msg()
+            },
+            edgefirst_msgs::RadarCube {
+                scales: vec![1.0, f32::NAN, 1.0, 0.1],
+                ..msg()
+            },
+            edgefirst_msgs::RadarCube {
+                shape: vec![1, 2, 4],
+                ..msg()
+            },
+            edgefirst_msgs::RadarCube {
+                shape: vec![1, 2, 1, 3],
+                ..msg()
+            },
+        ];
+        for msg in &invalid {
+            assert!(matches!(
+                cube_from_msg(msg),
+                Err(SMSError::InvalidCubeMessage(_))
+            ));
+        }
+        let short = edgefirst_msgs::RadarCube {
+            shape: vec![1, 2, 2, 4],
+            ..msg()
+        };
+        assert!(matches!(
+            cube_from_msg(&short),
+            Err(SMSError::MissingCubeData(8, 16))
+        ));
+    }
 }
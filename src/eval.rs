@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::{clustering::ClusteringBuilder, common::transform_xyz};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+/// Nominal radar cycle time used to derive tracker timestamps from the frame
+/// counter, one frame is 55ms.
+pub const FRAME_PERIOD_NS: u64 = 55_000_000;
+
+/// Targets of one radar frame read from a CSV file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvFrame {
+    /// Radar frame counter
+    pub frame_counter: u32,
+
+    /// Original CSV lines of the frame, one per target
+    pub lines: Vec<String>,
+
+    /// Target [range, azimuth, elevation, speed] in meters, degrees and m/s
+    pub targets: Vec<[f32; 4]>,
+}
+
+/// Parse recorded targets in the CSV format
+/// `frame_counter,range,azimuth,elevation,speed,rcs,power,noise`.
+///
+/// An optional header line is skipped.  Rows are grouped by frame_counter
+/// and frames are returned in order of first appearance.
+///
+/// # Errors
+/// Returns an `InvalidData` error for rows with missing or malformed fields
+pub fn parse_csv<R: BufRead>(reader: R) -> Result<Vec<CsvFrame>, io::Error> {
+    let mut frames: Vec<CsvFrame> = Vec::new();
+    let mut index = HashMap::new();
+
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<_> = line.split(',').map(str::trim).collect();
+        if lineno == 0 && fields[0].parse::<u32>().is_err() {
+            continue;
+        }
+
+        let invalid = |msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", lineno + 1, msg),
+            )
+        };
+
+        if fields.len() < 8 {
+            return Err(invalid("expected 8 fields"));
+        }
+
+        let frame_counter = fields[0]
+            .parse::<u32>()
+            .map_err(|_| invalid("invalid frame_counter"))?;
+        let mut target = [0.0; 4];
+        for (value, field) in target.iter_mut().zip(&fields[1..5]) {
+            *value = field.parse().map_err(|_| invalid("invalid number"))?;
+        }
+
+        let i = *index.entry(frame_counter).or_insert_with(|| {
+            frames.push(CsvFrame {
+                frame_counter,
+                lines: Vec::new(),
+                targets: Vec::new(),
+            });
+            frames.len() - 1
+        });
+        frames[i].lines.push(line);
+        frames[i].targets.push(target);
+    }
+
+    Ok(frames)
+}
+
+/// Cluster every frame in order with a fresh clustering instance.
+///
+/// Targets are converted to Cartesian coordinates (without mirroring) and
+/// the tracker timestamp is derived from the frame counter.
+///
+/// # Returns
+/// One [x, y, z, speed, cluster_id] entry per target for every frame
+pub fn cluster_frames(frames: &[CsvFrame], params: &ClusteringBuilder) -> Vec<Vec<[f32; 5]>> {
+    let mut clustering = params.build();
+
+    frames
+        .iter()
+        .map(|frame| {
+            let points = frame
+                .targets
+                .iter()
+                .map(|[range, azimuth, elevation, speed]| {
+                    let [x, y, z] = transform_xyz(*range, *azimuth, *elevation, false);
+                    [x, y, z, *speed]
+                })
+                .collect();
+            let timestamp = frame.frame_counter as u64 * FRAME_PERIOD_NS;
+            clustering.cluster(points, timestamp)
+        })
+        .collect()
+}
+
+/// Run the clustering algorithm offline on recorded target data.
+///
+/// # Arguments
+/// * `path` - CSV file, see [`parse_csv`] for the format
+/// * `params` - Clustering parameters
+///
+/// # Returns
+/// One [x, y, z, speed, cluster_id] entry per target for every frame
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is malformed
+pub fn cluster_from_csv(
+    path: &Path,
+    params: &ClusteringBuilder,
+) -> Result<Vec<Vec<[f32; 5]>>, io::Error> {
+    let frames = parse_csv(BufReader::new(File::open(path)?))?;
+    Ok(cluster_frames(&frames, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const CSV: &str = "frame_counter,range,azimuth,elevation,speed,rcs,power,noise
+1,10.0,0.0,0.0,0.0,1.0,100,40
+1,10.2,0.5,0.0,0.0,1.0,100,40
+2,10.0,0.0,0.0,0.0,1.0,100,40
+1,10.1,-0.5,0.0,0.0,1.0,100,40
+2,30.0,20.0,0.0,1.0,1.0,100,40
+";
+
+    #[test]
+    fn test_parse_csv() {
+        let frames = parse_csv(Cursor::new(CSV)).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame_counter, 1);
+        assert_eq!(frames[0].targets.len(), 3);
+        assert_eq!(frames[0].targets[2], [10.1, -0.5, 0.0, 0.0]);
+        assert_eq!(frames[0].lines[2], "1,10.1,-0.5,0.0,0.0,1.0,100,40");
+        assert_eq!(frames[1].frame_counter, 2);
+        assert_eq!(frames[1].targets.len(), 2);
+
+        // The header is optional.
+        let frames = parse_csv(Cursor::new("5,1,2,3,4,5,6,7\n")).unwrap();
+        assert_eq!(frames[0].targets[0], [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_parse_csv_invalid() {
+        let err = parse_csv(Cursor::new("1,10.0,0.0\n")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = parse_csv(Cursor::new("1,10.0,x,0,0,0,0,0\n")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_cluster_frames() {
+        let frames = parse_csv(Cursor::new(CSV)).unwrap();
+        let params = ClusteringBuilder::new().point_limit(3).threads(1);
+        let clusters = cluster_frames(&frames, &params);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].len(), 3);
+        assert!(clusters[0].iter().all(|p| p[4] == 1.0));
+        assert!((clusters[0][0][0] - 10.0).abs() < 1e-5);
+        // Two distant points never reach the point limit.
+        assert!(clusters[1].iter().all(|p| p[4] == 0.0));
+    }
+}
@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::{can::Target, common::transform_xyz};
+use nalgebra::{Matrix3, Vector3};
+
+/// Seed of the RANSAC sampler, fixed so a frame always yields the same plane.
+const RANSAC_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Fit the dominant plane through the targets with RANSAC.
+///
+/// Every iteration fits a plane through three random targets and counts the
+/// targets within `inlier_threshold_m` of it.  The plane with the most
+/// inliers is refined with a least squares fit through its inliers, which
+/// are then recounted.  The targets are placed in the radar frame without
+/// mirroring, the normal is oriented towards positive z.
+///
+/// # Arguments
+/// * `targets` - Radar targets
+/// * `iterations` - Number of candidate planes
+/// * `inlier_threshold_m` - Maximum distance of a ground point to the plane
+///
+/// # Returns
+/// The ground point mask, true for targets on the plane, and the plane
+/// `[a, b, c, d]` with `a*x + b*y + c*z + d = 0` and a unit normal, or None
+/// if fewer than three targets span a plane
+pub fn ransac_ground_plane(
+    targets: &[Target],
+    iterations: usize,
+    inlier_threshold_m: f64,
+) -> (Vec<bool>, Option<[f64; 4]>) {
+    let points: Vec<Vector3<f64>> = targets
+        .iter()
+        .map(|t| {
+            let [x, y, z] =
+                transform_xyz(t.range as f32, t.azimuth as f32, t.elevation as f32, false);
+            Vector3::new(x as f64, y as f64, z as f64)
+        })
+        .collect();
+
+    let mut best: Option<(usize, [f64; 4])> = None;
+    if points.len() >= 3 {
+        let mut rng = XorShift(RANSAC_SEED);
+        for _ in 0..iterations {
+            let i = rng.below(points.len());
+            let j = rng.below(points.len());
+            let k = rng.below(points.len());
+            let plane = match plane_from_points(&points[i], &points[j], &points[k]) {
+                Some(plane) => plane,
+                None => continue,
+            };
+
+            let count = inlier_mask(&points, &plane, inlier_threshold_m)
+                .iter()
+                .filter(|&&inlier| inlier)
+                .count();
+            if best.is_none_or(|(best, _)| count > best) {
+                best = Some((count, plane));
+            }
+        }
+    }
+
+    let plane = match best {
+        Some((_, plane)) => {
+            let inliers = inlier_mask(&points, &plane, inlier_threshold_m);
+            refine_plane(&points, &inliers).unwrap_or(plane)
+        }
+        None => return (vec![false; targets.len()], None),
+    };
+
+    (
+        inlier_mask(&points, &plane, inlier_threshold_m),
+        Some(plane),
+    )
+}
+
+/// Plane through three points, None if they are (nearly) collinear.
+fn plane_from_points(p1: &Vector3<f64>, p2: &Vector3<f64>, p3: &Vector3<f64>) -> Option<[f64; 4]> {
+    let (u, v) = (p2 - p1, p3 - p1);
+    let normal = u.cross(&v);
+    let norm = normal.norm();
+    // The norm is |u||v|sin(angle), the f32 target positions are collinear
+    // within rounding for angles this small.
+    if norm <= 1e-3 * u.norm() * v.norm() {
+        return None;
+    }
+    Some(oriented_plane(normal / norm, p1))
+}
+
+/// Least squares plane through the inliers, the normal is the direction of
+/// least variance around their centroid.
+fn refine_plane(points: &[Vector3<f64>], inliers: &[bool]) -> Option<[f64; 4]> {
+    let selected: Vec<_> = points
+        .iter()
+        .zip(inliers)
+        .filter_map(|(p, &inlier)| inlier.then_some(p))
+        .collect();
+    if selected.len() < 3 {
+        return None;
+    }
+
+    let centroid =
+        selected.iter().fold(Vector3::zeros(), |acc, &p| acc + p) / selected.len() as f64;
+    let covariance = selected.iter().fold(Matrix3::zeros(), |acc, &p| {
+        let d = p - centroid;
+        acc + d * d.transpose()
+    });
+
+    let eigen = covariance.symmetric_eigen();
+    let (min, _) = eigen
+        .eigenvalues
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))?;
+    let normal: Vector3<f64> = eigen.eigenvectors.column(min).into();
+    Some(oriented_plane(normal.normalize(), &centroid))
+}
+
+fn oriented_plane(normal: Vector3<f64>, point: &Vector3<f64>) -> [f64; 4] {
+    let normal = if normal.z < 0.0 { -normal } else { normal };
+    [normal.x, normal.y, normal.z, -normal.dot(point)]
+}
+
+fn inlier_mask(points: &[Vector3<f64>], plane: &[f64; 4], threshold: f64) -> Vec<bool> {
+    points
+        .iter()
+        .map(|p| (plane[0] * p.x + plane[1] * p.y + plane[2] * p.z + plane[3]).abs() <= threshold)
+        .collect()
+}
+
+/// Minimal xorshift64 generator for sampling the RANSAC candidates.
+struct XorShift(u64);
+
+impl XorShift {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Target at the Cartesian position in the radar frame.
+    fn target(x: f64, y: f64, z: f64) -> Target {
+        let range = (x * x + y * y + z * z).sqrt();
+        Target {
+            range,
+            azimuth: y.atan2(x).to_degrees(),
+            elevation: (z / range).asin().to_degrees(),
+            speed: 0.0,
+            rcs: 0.0,
+            power: 0.0,
+            noise: 0.0,
+            accuracy: 0,
+        }
+    }
+
+    #[test]
+    fn test_ransac_ground_plane() {
+        // Ground 1.5 m below the radar with a few centimeters of noise, and
+        // objects standing above it.
+        let mut targets = Vec::new();
+        for i in 0..10 {
+            for j in 0..5 {
+                let noise = ((i * 7 + j * 3) % 5) as f64 * 0.01 - 0.02;
+                targets.push(target(
+                    2.0 + i as f64 * 2.0,
+                    j as f64 * 2.0 - 4.0,
+                    -1.5 + noise,
+                ));
+            }
+        }
+        let ground = targets.len();
+        targets.push(target(10.0, 0.0, 0.0));
+        targets.push(target(15.0, 2.0, 1.0));
+        targets.push(target(8.0, -3.0, -0.5));
+
+        let (inliers, plane) = ransac_ground_plane(&targets, 100, 0.1);
+        let [a, b, c, d] = plane.unwrap();
+        assert!(a.abs() < 0.01 && b.abs() < 0.01, "{:?}", plane);
+        assert!((c - 1.0).abs() < 0.001, "{:?}", plane);
+        assert!((d - 1.5).abs() < 0.01, "{:?}", plane);
+
+        assert!(inliers[..ground].iter().all(|&inlier| inlier));
+        assert_eq!(inliers[ground..], [false, false, false]);
+    }
+
+    #[test]
+    fn test_ransac_degenerate() {
+        assert_eq!(ransac_ground_plane(&[], 100, 0.1), (vec![], None));
+
+        let targets = [target(5.0, 0.0, -1.0), target(6.0, 0.0, -1.0)];
+        assert_eq!(
+            ransac_ground_plane(&targets, 100, 0.1),
+            (vec![false, false], None)
+        );
+
+        // Collinear targets never span a plane.
+        let targets: Vec<_> = (1..5).map(|i| target(i as f64, 0.0, -1.0)).collect();
+        assert_eq!(ransac_ground_plane(&targets, 100, 0.1).1, None);
+    }
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::can::Target;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+/// Number of clusters the cluster count has to drop from for a drop to 0 to
+/// be an anomaly, a single object leaving the field of view is not.
+pub const ANOMALY_MIN_DROP: usize = 3;
+
+/// Increase of the cluster count between two cycles which is an anomaly.
+pub const ANOMALY_MAX_JUMP: usize = 10;
+
+/// Ring buffer of the most recent target frames with their timestamps.
+///
+/// The clustering window consumes its frames, this keeps a copy of the last
+/// `capacity` frames so the input of an unexpected clustering result can be
+/// dumped for debugging.
+#[derive(Debug, Clone)]
+pub struct FrameHistory {
+    buffer: VecDeque<(u64, Vec<Target>)>,
+    capacity: usize,
+}
+
+impl FrameHistory {
+    /// Create an empty history keeping up to `capacity` frames.
+    pub fn new(capacity: usize) -> Self {
+        FrameHistory {
+            buffer: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Append a frame, dropping the oldest frame once the history is full.
+    pub fn push(&mut self, timestamp: u64, targets: Vec<Target>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.buffer.len() == self.capacity {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back((timestamp, targets));
+    }
+
+    /// The last `n` frames, oldest first.
+    pub fn iter_recent(&self, n: usize) -> impl Iterator<Item = &(u64, Vec<Target>)> {
+        self.buffer.iter().skip(self.buffer.len().saturating_sub(n))
+    }
+}
+
+/// Describe the change of the cluster count between two clustering cycles
+/// if it is an anomaly: a drop to 0 from at least [`ANOMALY_MIN_DROP`]
+/// clusters or an increase by more than [`ANOMALY_MAX_JUMP`] clusters.
+pub fn cluster_count_anomaly(previous: usize, clusters: usize) -> Option<&'static str> {
+    if clusters == 0 && previous >= ANOMALY_MIN_DROP {
+        Some("cluster count dropped to 0")
+    } else if clusters > previous + ANOMALY_MAX_JUMP {
+        Some("cluster count jumped")
+    } else {
+        None
+    }
+}
+
+/// JSON debug dump of the last `n` frames of the history.
+pub fn debug_dump(
+    history: &FrameHistory,
+    n: usize,
+    reason: &str,
+    previous: usize,
+    clusters: usize,
+) -> Value {
+    let frames = history
+        .iter_recent(n)
+        .map(|(timestamp, targets)| {
+            json!({
+                "timestamp": timestamp,
+                "targets": targets
+                    .iter()
+                    .map(|t| {
+                        json!({
+                            "range": t.range,
+                            "azimuth": t.azimuth,
+                            "elevation": t.elevation,
+                            "speed": t.speed,
+                            "rcs": t.rcs,
+                            "power": t.power,
+                            "noise": t.noise,
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "reason": reason,
+        "previous_clusters": previous,
+        "clusters": clusters,
+        "frames": frames,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(range: f64) -> Vec<Target> {
+        vec![Target {
+            range,
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn test_frame_history() {
+        let mut history = FrameHistory::new(3);
+        assert_eq!(history.iter_recent(10).count(), 0);
+
+        for i in 0..5 {
+            history.push(i * 55, frame(i as f64));
+        }
+
+        // The oldest frames were dropped, the recent frames are oldest first.
+        let recent: Vec<_> = history.iter_recent(10).map(|(ts, _)| *ts).collect();
+        assert_eq!(recent, [110, 165, 220]);
+        let recent: Vec<_> = history
+            .iter_recent(2)
+            .map(|(ts, targets)| (*ts, targets[0].range))
+            .collect();
+        assert_eq!(recent, [(165, 3.0), (220, 4.0)]);
+        assert_eq!(history.iter_recent(0).count(), 0);
+
+        let mut history = FrameHistory::new(0);
+        history.push(0, frame(1.0));
+        assert_eq!(history.iter_recent(1).count(), 0);
+    }
+
+    #[test]
+    fn test_cluster_count_anomaly() {
+        assert!(cluster_count_anomaly(5, 0).is_some());
+        assert!(cluster_count_anomaly(ANOMALY_MIN_DROP, 0).is_some());
+        assert!(cluster_count_anomaly(1, 0).is_none());
+        assert!(cluster_count_anomaly(0, 0).is_none());
+        assert!(cluster_count_anomaly(2, 13).is_some());
+        assert!(cluster_count_anomaly(2, 12).is_none());
+        assert!(cluster_count_anomaly(12, 2).is_none());
+    }
+
+    #[test]
+    fn test_debug_dump() {
+        let mut history = FrameHistory::new(10);
+        for i in 0..4 {
+            history.push(i, frame(i as f64));
+        }
+
+        let dump = debug_dump(&history, 2, "cluster count jumped", 1, 14);
+        assert_eq!(dump["reason"], "cluster count jumped");
+        assert_eq!(dump["previous_clusters"], 1);
+        assert_eq!(dump["clusters"], 14);
+        let frames = dump["frames"].as_array().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0]["timestamp"], 2);
+        assert_eq!(frames[1]["targets"][0]["range"], 3.0);
+    }
+}
@@ -0,0 +1,598 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::memory::ByteGauge;
+use serde_json::json;
+use std::{
+    fmt::{self, Write as _},
+    io::{Read as _, Write as _},
+    net::{TcpListener, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{error, info};
+
+/// Number of histogram buckets.
+pub const LATENCY_BUCKETS: usize = 20;
+
+/// Prometheus metric name of the publish latency histogram.
+pub const LATENCY_METRIC: &str = "radarpub_publish_latency_us_histogram";
+
+/// Prometheus metric name prefix of the clustering task metrics.
+pub const CLUSTERING_METRIC: &str = "radarpub_clustering";
+
+/// Prometheus metric name prefix of the cube publish batch metrics.
+pub const BATCH_METRIC: &str = "radarpub_cube_batch";
+
+/// Read and write timeout of a metrics connection, a client which never sends
+/// its request cannot stall the scrapes of the others.
+const METRICS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Histogram of radar frame to publish latency.
+///
+/// Buckets are logarithmically spaced from 100 µs to 100 ms.  Each bucket
+/// counts the measurements less than or equal to its upper bound and greater
+/// than the previous bound, measurements above 100 ms are counted separately
+/// as overflow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    /// Measurement count per bucket
+    buckets: [u64; LATENCY_BUCKETS],
+
+    /// Inclusive upper bound of each bucket in microseconds
+    bucket_us: [u64; LATENCY_BUCKETS],
+
+    /// Measurements above the last bucket
+    overflow: u64,
+
+    /// Sum of all measurements in microseconds
+    sum_us: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        let mut bucket_us = [0; LATENCY_BUCKETS];
+        for (i, bound) in bucket_us.iter_mut().enumerate() {
+            let exp = i as f64 / (LATENCY_BUCKETS - 1) as f64;
+            *bound = (100.0 * 1000f64.powf(exp)).round() as u64;
+        }
+
+        LatencyHistogram {
+            buckets: [0; LATENCY_BUCKETS],
+            bucket_us,
+            overflow: 0,
+            sum_us: 0,
+        }
+    }
+
+    /// Record a latency measurement.
+    pub fn record(&mut self, latency: Duration) {
+        let us = latency.as_micros() as u64;
+        self.sum_us += us;
+        match self.bucket_us.iter().position(|bound| us <= *bound) {
+            Some(i) => self.buckets[i] += 1,
+            None => self.overflow += 1,
+        }
+    }
+
+    /// Returns the measurement count per bucket.
+    #[allow(dead_code)]
+    pub fn buckets(&self) -> &[u64; LATENCY_BUCKETS] {
+        &self.buckets
+    }
+
+    /// Returns the upper bound of each bucket in microseconds.
+    #[allow(dead_code)]
+    pub fn bucket_us(&self) -> &[u64; LATENCY_BUCKETS] {
+        &self.bucket_us
+    }
+
+    /// Returns the total number of measurements.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum::<u64>() + self.overflow
+    }
+
+    /// Encode the histogram as JSON for publishing.
+    pub fn to_json(&self) -> String {
+        json!({
+            "bucket_us": self.bucket_us,
+            "buckets": self.buckets,
+            "overflow": self.overflow,
+            "count": self.count(),
+            "sum_us": self.sum_us,
+        })
+        .to_string()
+    }
+
+    /// Encode the histogram in the Prometheus text exposition format with
+    /// cumulative `le` buckets.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP {LATENCY_METRIC} Radar frame receipt to targets publish latency in microseconds."
+        );
+        let _ = writeln!(out, "# TYPE {LATENCY_METRIC} histogram");
+
+        let mut cumulative = 0;
+        for (bound, count) in self.bucket_us.iter().zip(self.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{LATENCY_METRIC}_bucket{{le=\"{bound}\"}} {cumulative}"
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "{LATENCY_METRIC}_bucket{{le=\"+Inf\"}} {}",
+            self.count()
+        );
+        let _ = writeln!(out, "{LATENCY_METRIC}_sum {}", self.sum_us);
+        let _ = writeln!(out, "{LATENCY_METRIC}_count {}", self.count());
+        out
+    }
+}
+
+/// Measurements of a single clustering cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClusteringCycle {
+    /// Targets in the clustering window
+    pub points: usize,
+    /// Tracked clusters in the output
+    pub clusters: usize,
+    /// Active tracklets after the cycle
+    pub tracklets: usize,
+    /// DBSCAN runtime
+    pub dbscan: Duration,
+    /// Tracker runtime
+    pub tracker: Duration,
+    /// Runtime from taking the frame off the channel to publishing
+    pub total: Duration,
+    /// Frames left waiting in the clustering channel
+    pub backlog: usize,
+    /// Time from the CAN frame receipt to publishing its clusters
+    pub receipt_to_publish: Duration,
+    /// Frames evicted early from the window to stay below its point limit
+    pub evicted: usize,
+}
+
+/// Clustering task metrics accumulated over every cycle.
+///
+/// Counts and durations are summed so the Prometheus rates and the logged
+/// means cover any interval, the last cycle is kept for the gauges.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClusteringMetrics {
+    cycles: u64,
+    last: ClusteringCycle,
+    points: u64,
+    clusters: u64,
+    dbscan_us: u64,
+    tracker_us: u64,
+    total_us: u64,
+    total_max_us: u64,
+    receipt_to_publish_us: u64,
+    backlog_max: usize,
+    evicted: u64,
+}
+
+impl ClusteringMetrics {
+    /// Create metrics without any cycles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulate the measurements of a clustering cycle.
+    pub fn record(&mut self, cycle: ClusteringCycle) {
+        let total_us = cycle.total.as_micros() as u64;
+        self.cycles += 1;
+        self.points += cycle.points as u64;
+        self.clusters += cycle.clusters as u64;
+        self.dbscan_us += cycle.dbscan.as_micros() as u64;
+        self.tracker_us += cycle.tracker.as_micros() as u64;
+        self.total_us += total_us;
+        self.total_max_us = self.total_max_us.max(total_us);
+        self.receipt_to_publish_us += cycle.receipt_to_publish.as_micros() as u64;
+        self.backlog_max = self.backlog_max.max(cycle.backlog);
+        self.evicted += cycle.evicted as u64;
+        self.last = cycle;
+    }
+
+    /// Returns the number of recorded cycles.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Returns the measurements of the last cycle.
+    #[allow(dead_code)]
+    pub fn last(&self) -> &ClusteringCycle {
+        &self.last
+    }
+
+    /// Encode the metrics in the Prometheus text exposition format, the sums
+    /// as counters and the last cycle and maxima as gauges.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {CLUSTERING_METRIC}_{name} {help}");
+            let _ = writeln!(out, "# TYPE {CLUSTERING_METRIC}_{name} {kind}");
+            let _ = writeln!(out, "{CLUSTERING_METRIC}_{name} {value}");
+        };
+
+        metric("cycles_total", "counter", "Clustering cycles.", self.cycles);
+        metric("points_total", "counter", "Targets clustered.", self.points);
+        metric(
+            "clusters_total",
+            "counter",
+            "Tracked clusters published.",
+            self.clusters,
+        );
+        metric(
+            "dbscan_us_total",
+            "counter",
+            "DBSCAN runtime in microseconds.",
+            self.dbscan_us,
+        );
+        metric(
+            "tracker_us_total",
+            "counter",
+            "Tracker runtime in microseconds.",
+            self.tracker_us,
+        );
+        metric(
+            "runtime_us_total",
+            "counter",
+            "Clustering cycle runtime in microseconds.",
+            self.total_us,
+        );
+        metric(
+            "receipt_to_publish_us_total",
+            "counter",
+            "CAN frame receipt to clusters publish time in microseconds.",
+            self.receipt_to_publish_us,
+        );
+        metric(
+            "window_evicted_total",
+            "counter",
+            "Frames evicted early from the clustering window.",
+            self.evicted,
+        );
+        metric(
+            "runtime_us_max",
+            "gauge",
+            "Longest clustering cycle in microseconds.",
+            self.total_max_us,
+        );
+        metric(
+            "points",
+            "gauge",
+            "Targets in the last clustering window.",
+            self.last.points as u64,
+        );
+        metric(
+            "clusters",
+            "gauge",
+            "Tracked clusters of the last cycle.",
+            self.last.clusters as u64,
+        );
+        metric(
+            "tracklets",
+            "gauge",
+            "Active tracklets after the last cycle.",
+            self.last.tracklets as u64,
+        );
+        metric(
+            "backlog",
+            "gauge",
+            "Frames waiting in the clustering channel.",
+            self.last.backlog as u64,
+        );
+        metric(
+            "backlog_max",
+            "gauge",
+            "Largest clustering channel backlog.",
+            self.backlog_max as u64,
+        );
+        out
+    }
+}
+
+/// Batched publish metrics, the delay is the time a message waited in its
+/// batch which batching adds to the publish latency.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchMetrics {
+    batches: u64,
+    messages: u64,
+    delay_us: u64,
+    delay_max_us: u64,
+}
+
+impl BatchMetrics {
+    /// Create metrics without any batches.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulate a published batch with the delay of each message.
+    pub fn record(&mut self, delays: &[Duration]) {
+        self.batches += 1;
+        self.messages += delays.len() as u64;
+        for delay in delays {
+            let delay_us = delay.as_micros() as u64;
+            self.delay_us += delay_us;
+            self.delay_max_us = self.delay_max_us.max(delay_us);
+        }
+    }
+
+    /// Returns the number of published batches.
+    pub fn batches(&self) -> u64 {
+        self.batches
+    }
+
+    /// Returns the number of messages published in the batches.
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Encode the metrics in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {BATCH_METRIC}_{name} {help}");
+            let _ = writeln!(out, "# TYPE {BATCH_METRIC}_{name} {kind}");
+            let _ = writeln!(out, "{BATCH_METRIC}_{name} {value}");
+        };
+
+        metric(
+            "batches_total",
+            "counter",
+            "Cube batches published.",
+            self.batches,
+        );
+        metric(
+            "messages_total",
+            "counter",
+            "Cubes published in batches.",
+            self.messages,
+        );
+        metric(
+            "delay_us_total",
+            "counter",
+            "Time the cubes waited in their batch in microseconds.",
+            self.delay_us,
+        );
+        metric(
+            "delay_us_max",
+            "gauge",
+            "Longest time a cube waited in its batch in microseconds.",
+            self.delay_max_us,
+        );
+        out
+    }
+}
+
+impl fmt::Display for ClusteringMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mean_ms = |sum_us: u64| sum_us as f64 / self.cycles.max(1) as f64 / 1000.0;
+        write!(
+            f,
+            "clustering cycles={} points={} clusters={} tracklets={} dbscan={:.2}ms tracker={:.2}ms total={:.2}ms max={:.2}ms receipt_to_publish={:.2}ms backlog={} max={}",
+            self.cycles,
+            self.last.points,
+            self.last.clusters,
+            self.last.tracklets,
+            mean_ms(self.dbscan_us),
+            mean_ms(self.tracker_us),
+            mean_ms(self.total_us),
+            self.total_max_us as f64 / 1000.0,
+            mean_ms(self.receipt_to_publish_us),
+            self.last.backlog,
+            self.backlog_max,
+        )
+    }
+}
+
+/// Serve the histogram, clustering, cube channel and cube batch metrics as
+/// Prometheus metrics over plain HTTP.
+///
+/// Every request on the listening address is answered with the current
+/// metrics regardless of the requested path.  This function blocks and
+/// should be run on a dedicated thread.
+///
+/// # Arguments
+/// * `addr` - Address to listen on, for example `0.0.0.0:9100`
+/// * `histogram` - Histogram shared with the publishing task
+/// * `clustering` - Metrics shared with the clustering task
+/// * `cube_channel` - Gauge of the cube packet channel
+/// * `cube_batch` - Metrics shared with the batched cube publisher
+///
+/// # Errors
+/// Returns an error if the address cannot be bound
+pub fn serve_metrics<A: ToSocketAddrs>(
+    addr: A,
+    histogram: Arc<Mutex<LatencyHistogram>>,
+    clustering: Arc<Mutex<ClusteringMetrics>>,
+    cube_channel: Arc<ByteGauge>,
+    cube_batch: Arc<Mutex<BatchMetrics>>,
+) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(addr)?;
+    info!("serving metrics on {}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("metrics accept error: {:?}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = stream
+            .set_read_timeout(Some(METRICS_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(METRICS_TIMEOUT)))
+        {
+            error!("metrics timeout error: {:?}", e);
+            continue;
+        }
+
+        // The request is ignored but must be drained before responding, a
+        // timed out read is answered all the same.
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request);
+
+        let mut body = histogram.lock().unwrap().to_prometheus();
+        body.push_str(&clustering.lock().unwrap().to_prometheus());
+        body.push_str(&cube_channel.to_prometheus());
+        body.push_str(&cube_batch.lock().unwrap().to_prometheus());
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+
+        if let Err(e) = stream.write_all(response.as_bytes()) {
+            error!("metrics write error: {:?}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_bounds() {
+        let histogram = LatencyHistogram::new();
+        let bounds = histogram.bucket_us();
+        assert_eq!(bounds[0], 100);
+        assert_eq!(bounds[LATENCY_BUCKETS - 1], 100_000);
+        assert!(bounds.windows(2).all(|w| w[0] < w[1]));
+
+        // Logarithmic spacing keeps a constant ratio between bounds.
+        let ratio = bounds[1] as f64 / bounds[0] as f64;
+        let last = bounds[19] as f64 / bounds[18] as f64;
+        assert!((ratio - last).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_record() {
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_micros(50));
+        histogram.record(Duration::from_micros(100));
+        histogram.record(Duration::from_micros(101));
+        histogram.record(Duration::from_millis(100));
+        histogram.record(Duration::from_millis(250));
+
+        assert_eq!(histogram.buckets()[0], 2);
+        assert_eq!(histogram.buckets()[1], 1);
+        assert_eq!(histogram.buckets()[LATENCY_BUCKETS - 1], 1);
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.sum_us, 50 + 100 + 101 + 100_000 + 250_000);
+    }
+
+    #[test]
+    fn test_prometheus() {
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_micros(80));
+        histogram.record(Duration::from_micros(1000));
+        histogram.record(Duration::from_secs(1));
+
+        let text = histogram.to_prometheus();
+        assert!(text.contains("# TYPE radarpub_publish_latency_us_histogram histogram"));
+        assert!(text.contains("radarpub_publish_latency_us_histogram_bucket{le=\"100\"} 1\n"));
+        assert!(text.contains("radarpub_publish_latency_us_histogram_bucket{le=\"100000\"} 2\n"));
+        assert!(text.contains("radarpub_publish_latency_us_histogram_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("radarpub_publish_latency_us_histogram_sum 1001080\n"));
+        assert!(text.contains("radarpub_publish_latency_us_histogram_count 3\n"));
+    }
+
+    #[test]
+    fn test_clustering_metrics() {
+        let mut metrics = ClusteringMetrics::new();
+        metrics.record(ClusteringCycle {
+            points: 120,
+            clusters: 3,
+            tracklets: 4,
+            dbscan: Duration::from_micros(1500),
+            tracker: Duration::from_micros(300),
+            total: Duration::from_micros(2000),
+            backlog: 2,
+            receipt_to_publish: Duration::from_micros(2500),
+            evicted: 3,
+        });
+        let last = ClusteringCycle {
+            points: 80,
+            clusters: 1,
+            tracklets: 2,
+            dbscan: Duration::from_micros(500),
+            tracker: Duration::from_micros(100),
+            total: Duration::from_micros(1000),
+            backlog: 0,
+            receipt_to_publish: Duration::from_micros(1500),
+            evicted: 1,
+        };
+        metrics.record(last);
+
+        assert_eq!(metrics.cycles(), 2);
+        assert_eq!(*metrics.last(), last);
+        assert_eq!(metrics.points, 200);
+        assert_eq!(metrics.clusters, 4);
+        assert_eq!(metrics.dbscan_us, 2000);
+        assert_eq!(metrics.tracker_us, 400);
+        assert_eq!(metrics.total_us, 3000);
+        assert_eq!(metrics.total_max_us, 2000);
+        assert_eq!(metrics.receipt_to_publish_us, 4000);
+        assert_eq!(metrics.backlog_max, 2);
+        assert_eq!(metrics.evicted, 4);
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE radarpub_clustering_cycles_total counter\n"));
+        assert!(text.contains("radarpub_clustering_cycles_total 2\n"));
+        assert!(text.contains("radarpub_clustering_dbscan_us_total 2000\n"));
+        assert!(text.contains("radarpub_clustering_runtime_us_max 2000\n"));
+        assert!(text.contains("radarpub_clustering_tracklets 2\n"));
+        assert!(text.contains("radarpub_clustering_backlog_max 2\n"));
+        assert!(text.contains("radarpub_clustering_window_evicted_total 4\n"));
+
+        let summary = metrics.to_string();
+        assert!(summary.contains("dbscan=1.00ms"), "{}", summary);
+        assert!(summary.contains("total=1.50ms max=2.00ms"), "{}", summary);
+        assert!(summary.contains("receipt_to_publish=2.00ms"), "{}", summary);
+    }
+
+    #[test]
+    fn test_batch_metrics() {
+        let mut metrics = BatchMetrics::new();
+        metrics.record(&[Duration::from_micros(1500), Duration::ZERO]);
+        metrics.record(&[Duration::from_micros(800)]);
+        assert_eq!(metrics.batches(), 2);
+        assert_eq!(metrics.messages(), 3);
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE radarpub_cube_batch_batches_total counter\n"));
+        assert!(text.contains("radarpub_cube_batch_messages_total 3\n"));
+        assert!(text.contains("radarpub_cube_batch_delay_us_total 2300\n"));
+        assert!(text.contains("radarpub_cube_batch_delay_us_max 1500\n"));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_micros(120));
+
+        let value: serde_json::Value = serde_json::from_str(&histogram.to_json()).unwrap();
+        assert_eq!(value["count"], 1);
+        assert_eq!(value["sum_us"], 120);
+        assert_eq!(value["bucket_us"][0], 100);
+        assert_eq!(value["buckets"][1], 1);
+    }
+}
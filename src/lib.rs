@@ -13,6 +13,10 @@
 //! - **Ethernet Interface** - Receive 4D radar cube data via UDP
 //! - **Clustering** - DBSCAN spatial clustering for target grouping
 //! - **Tracking** - ByteTrack algorithm with Kalman filtering
+//! - **Track Log** - Per-track summaries of a session in CSV or JSON lines
+//! - **Bird's-Eye View** - Decaying occupancy raster of radar detections
+//! - **Heatmap** - Long horizon detection heatmap for traffic-flow analysis
+//! - **Latency Metrics** - Frame to publish latency histogram with Prometheus export
 //! - **Zenoh Publishing** - ROS2-compatible message publishing
 //!
 //! # Examples
@@ -20,8 +24,30 @@
 //! See the `examples/` directory for complete usage examples:
 //! - `radar_viewer` - Direct radar visualization with Rerun
 //! - `zenoh_viewer` - Subscribe to Zenoh topics and visualize
+//!
+//! [`eth::RadarCubeReader::read`], [`can::read_header`], [`can::read_data`]
+//! and [`schema::layout_mismatches`] have runnable examples of decoding the
+//! sensor data from your own socket loop and of reading the published points.
 
 #![warn(missing_docs)]
+#![warn(clippy::len_without_is_empty)]
+
+/// Long horizon detection heatmap
+pub mod accumulate;
+
+/// Antenna gain pattern power correction
+pub mod antenna;
+
+/// Bird's-eye-view occupancy raster
+pub mod bev;
+
+/// Radome blockage detection from target statistics
+#[cfg(feature = "can")]
+pub mod blockage;
+
+/// Publisher circuit breaker for a dead Zenoh session
+#[cfg(feature = "zenoh")]
+pub mod breaker;
 
 /// CAN interface and DRVEGRD protocol implementation
 #[cfg(feature = "can")]
@@ -30,11 +56,74 @@ pub mod can;
 /// Common types and utilities
 pub mod common;
 
+/// Per-thread CPU utilisation monitor
+pub mod cpu;
+
+/// Remote sensor control over a Zenoh queryable
+#[cfg(all(feature = "can", feature = "zenoh"))]
+pub mod control;
+
+/// Radar cube file writer and reader
+pub mod dump;
+
+/// Zenoh echo server and client for connectivity tests
+#[cfg(feature = "zenoh")]
+pub mod echo;
+
 /// Ethernet/UDP radar cube reception
 pub mod eth;
 
+/// RANSAC ground plane fitting of radar targets
+#[cfg(feature = "can")]
+pub mod ground;
+
+/// Recent target frames for clustering debug dumps
+#[cfg(feature = "can")]
+pub mod history;
+
+/// Publish latency histogram and Prometheus metrics
+pub mod latency;
+
+/// Byte capped cube packet channel
+pub mod memory;
+
 /// Network utilities for UDP communication
 pub mod net;
 
+/// Zenoh connect endpoints reloaded from a file
+#[cfg(feature = "zenoh")]
+pub mod reconnect;
+
+/// Connectivity self test of the CAN, UDP and Zenoh subsystems
+#[cfg(all(feature = "can", feature = "zenoh"))]
+pub mod selftest;
+
+/// Signal processing of the radar cube
+pub mod signal;
+
+/// Point layouts of the published point clouds
+pub mod schema;
+
+/// Publish sinks for serialized messages
+#[cfg(feature = "zenoh")]
+pub mod sink;
+
+/// Completed track log files
+pub mod tracklog;
+
+/// Doppler speed unfolding of range toggled frames
+#[cfg(feature = "can")]
+pub mod unfold;
+
+/// Radar cube slicing and log rate limiting for the viewers
+pub mod viewer;
+
+/// Heartbeat watchdog of an external supervisor
+#[cfg(feature = "zenoh")]
+pub mod watchdog;
+
 /// Clustering and tracking algorithms
 pub mod clustering;
+
+/// Offline clustering evaluation on recorded targets
+pub mod eval;
@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::net::PacketSender;
+use kanal::{AsyncReceiver, AsyncSender, ReceiveError, SendError};
+use std::{
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Prometheus metric name prefix of the cube channel gauges.
+pub const CUBE_CHANNEL_METRIC: &str = "radarpub_cube_channel";
+
+/// Bytes buffered in a [`byte_channel`] and the batches it dropped.
+#[derive(Debug, Default)]
+pub struct ByteGauge {
+    buffered: AtomicUsize,
+    dropped: AtomicU64,
+    max_bytes: usize,
+}
+
+impl ByteGauge {
+    /// Create a gauge for a channel holding up to `max_bytes`.
+    pub fn new(max_bytes: usize) -> Self {
+        ByteGauge {
+            max_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the bytes buffered in the channel.
+    pub fn buffered(&self) -> usize {
+        self.buffered.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of batches dropped to stay below the byte cap.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Encode the gauge in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {CUBE_CHANNEL_METRIC}_{name} {help}");
+            let _ = writeln!(out, "# TYPE {CUBE_CHANNEL_METRIC}_{name} {kind}");
+            let _ = writeln!(out, "{CUBE_CHANNEL_METRIC}_{name} {value}");
+        };
+
+        metric(
+            "bytes",
+            "gauge",
+            "Cube packet bytes waiting in the channel.",
+            self.buffered() as u64,
+        );
+        metric(
+            "max_bytes",
+            "gauge",
+            "Cube channel byte cap.",
+            self.max_bytes as u64,
+        );
+        metric(
+            "dropped_total",
+            "counter",
+            "Packet batches dropped from a full cube channel.",
+            self.dropped(),
+        );
+        out
+    }
+}
+
+/// Create a channel of up to `capacity` packet batches which also holds at
+/// most the `max_bytes` of its gauge.
+///
+/// A batch which would exceed the byte cap drops the oldest batches from the
+/// channel, a single batch larger than the cap is still sent.  Once the
+/// batch count reaches the capacity the senders wait as with a plain bounded
+/// channel.
+pub fn byte_channel(capacity: usize, gauge: Arc<ByteGauge>) -> (ByteSender, ByteReceiver) {
+    let (tx, rx) = kanal::bounded_async(capacity);
+    let sender = ByteSender {
+        tx,
+        rx: rx.clone(),
+        gauge: gauge.clone(),
+    };
+    (sender, ByteReceiver { rx, gauge })
+}
+
+/// Sending half of a [`byte_channel`].
+#[derive(Clone)]
+pub struct ByteSender {
+    tx: AsyncSender<Vec<u8>>,
+    // Used to drop the oldest batches.
+    rx: AsyncReceiver<Vec<u8>>,
+    gauge: Arc<ByteGauge>,
+}
+
+impl ByteSender {
+    /// Send a batch of packets, dropping the oldest batches while the
+    /// channel would exceed its byte cap.
+    pub async fn send(&self, batch: Vec<u8>) -> Result<(), SendError> {
+        let len = batch.len();
+        while self.gauge.buffered() + len > self.gauge.max_bytes {
+            match self.rx.try_recv() {
+                Ok(Some(oldest)) => {
+                    self.gauge
+                        .buffered
+                        .fetch_sub(oldest.len(), Ordering::Relaxed);
+                    self.gauge.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                _ => break,
+            }
+        }
+
+        self.gauge.buffered.fetch_add(len, Ordering::Relaxed);
+        if let Err(e) = self.tx.send(batch).await {
+            self.gauge.buffered.fetch_sub(len, Ordering::Relaxed);
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+impl PacketSender for ByteSender {
+    async fn send_packets(&self, packets: Vec<u8>) -> Result<(), SendError> {
+        self.send(packets).await
+    }
+}
+
+/// Receiving half of a [`byte_channel`].
+pub struct ByteReceiver {
+    rx: AsyncReceiver<Vec<u8>>,
+    gauge: Arc<ByteGauge>,
+}
+
+impl ByteReceiver {
+    /// Receive the oldest batch of packets.
+    pub async fn recv(&self) -> Result<Vec<u8>, ReceiveError> {
+        let batch = self.rx.recv().await?;
+        self.gauge
+            .buffered
+            .fetch_sub(batch.len(), Ordering::Relaxed);
+        Ok(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_byte_channel_cap() {
+        let gauge = Arc::new(ByteGauge::new(1000));
+        let (tx, rx) = byte_channel(128, gauge.clone());
+
+        block_on(async {
+            // A stalled receiver, the oldest batches are dropped to stay
+            // below the cap.
+            for i in 0..20u8 {
+                tx.send(vec![i; 300]).await.unwrap();
+                assert!(gauge.buffered() <= 1000);
+            }
+            assert_eq!(gauge.buffered(), 900);
+            assert_eq!(gauge.dropped(), 17);
+
+            let batch = rx.recv().await.unwrap();
+            assert_eq!(batch[0], 17);
+            assert_eq!(gauge.buffered(), 600);
+
+            // A batch larger than the cap empties the channel but is kept.
+            tx.send(vec![0; 1500]).await.unwrap();
+            assert_eq!(gauge.buffered(), 1500);
+            assert_eq!(gauge.dropped(), 19);
+            assert_eq!(rx.recv().await.unwrap().len(), 1500);
+            assert_eq!(gauge.buffered(), 0);
+        });
+
+        let text = gauge.to_prometheus();
+        assert!(text.contains("radarpub_cube_channel_bytes 0\n"));
+        assert!(text.contains("radarpub_cube_channel_max_bytes 1000\n"));
+        assert!(text.contains("# TYPE radarpub_cube_channel_dropped_total counter\n"));
+        assert!(text.contains("radarpub_cube_channel_dropped_total 19\n"));
+    }
+}
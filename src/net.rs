@@ -2,82 +2,286 @@
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
 use crate::eth::SMS_PACKET_SIZE;
-use kanal::AsyncSender;
+use kanal::{AsyncSender, SendError};
+use std::future::Future;
 use tokio::net::UdpSocket;
 use tracing::error;
 
-/// The port5 implementation on Linux uses the recvmmsg system call to enable
-/// bulk reads of UDP packets.  This is not available on other platforms.
-#[cfg(target_os = "linux")]
-pub async fn port5(tx: AsyncSender<Vec<u8>>) {
-    use std::{os::fd::AsRawFd, thread, time::Duration};
-
-    use crate::common::{set_process_priority, set_socket_bufsize};
+/// Destination of the received packet batches.
+///
+/// Implemented for the bounded channel senders and the byte capped cube
+/// channel of radarpub.
+pub trait PacketSender {
+    /// Send a batch of packets, waiting while the destination is full.
+    fn send_packets(&self, packets: Vec<u8>) -> impl Future<Output = Result<(), SendError>>;
+}
 
-    const VLEN: usize = 64;
-    const RETRY_TIME: Duration = Duration::from_micros(250);
+impl PacketSender for AsyncSender<Vec<u8>> {
+    async fn send_packets(&self, packets: Vec<u8>) -> Result<(), SendError> {
+        self.send(packets).await
+    }
+}
 
-    let mut mmsgs = vec![
-        libc::mmsghdr {
-            msg_hdr: libc::msghdr {
-                msg_name: std::ptr::null_mut(),
-                msg_namelen: 0,
-                msg_iov: std::ptr::null_mut(),
-                msg_iovlen: 0,
-                msg_control: std::ptr::null_mut(),
-                msg_controllen: 0,
-                msg_flags: 0,
-            },
-            msg_len: 0,
-        };
-        VLEN
-    ];
-    let mut iovecs = vec![
-        libc::iovec {
-            iov_base: std::ptr::null_mut(),
-            iov_len: 0,
-        };
-        VLEN
-    ];
-    let mut buf = vec![0; VLEN * SMS_PACKET_SIZE];
+/// Bulk UDP packet reader using the recvmmsg system call.
+///
+/// Every packet is read into its own [`SMS_PACKET_SIZE`] slot of a single
+/// buffer so a batch is handed on as one contiguous block of packets.
+#[cfg(target_os = "linux")]
+pub struct MmsgReader {
+    mmsgs: Vec<libc::mmsghdr>,
+    iovecs: Vec<libc::iovec>,
+    buf: Vec<u8>,
+}
 
-    set_process_priority();
-    let sock = UdpSocket::bind("0.0.0.0:50005").await.unwrap();
-    let sock = set_socket_bufsize(sock.into_std().unwrap(), 2 * 1024 * 1024);
-    let sock = UdpSocket::from_std(sock).unwrap();
+#[cfg(target_os = "linux")]
+impl MmsgReader {
+    /// Create a reader for batches of up to `vlen` packets.
+    pub fn new(vlen: usize) -> Self {
+        MmsgReader {
+            mmsgs: vec![
+                libc::mmsghdr {
+                    msg_hdr: libc::msghdr {
+                        msg_name: std::ptr::null_mut(),
+                        msg_namelen: 0,
+                        msg_iov: std::ptr::null_mut(),
+                        msg_iovlen: 0,
+                        msg_control: std::ptr::null_mut(),
+                        msg_controllen: 0,
+                        msg_flags: 0,
+                    },
+                    msg_len: 0,
+                };
+                vlen
+            ],
+            iovecs: vec![
+                libc::iovec {
+                    iov_base: std::ptr::null_mut(),
+                    iov_len: 0,
+                };
+                vlen
+            ],
+            buf: vec![0; vlen * SMS_PACKET_SIZE],
+        }
+    }
 
-    loop {
-        for i in 0..VLEN {
-            iovecs[i].iov_base = buf[i * SMS_PACKET_SIZE..].as_mut_ptr() as *mut libc::c_void;
-            iovecs[i].iov_len = SMS_PACKET_SIZE;
-            mmsgs[i].msg_hdr.msg_iov = &mut iovecs[i];
-            mmsgs[i].msg_hdr.msg_iovlen = 1;
-            mmsgs[i].msg_hdr.msg_name = std::ptr::null_mut();
-            mmsgs[i].msg_hdr.msg_namelen = 0;
-            mmsgs[i].msg_hdr.msg_control = std::ptr::null_mut();
-            mmsgs[i].msg_hdr.msg_controllen = 0;
-            mmsgs[i].msg_hdr.msg_flags = 0;
-            mmsgs[i].msg_len = 0;
+    /// Read the packets queued on the socket.
+    ///
+    /// # Returns
+    /// The received packets, one [`SMS_PACKET_SIZE`] slot per packet
+    ///
+    /// # Errors
+    /// Returns the recvmmsg error, `WouldBlock` if a non-blocking socket has
+    /// no packets queued
+    pub fn read(&mut self, fd: std::os::fd::RawFd) -> Result<&[u8], std::io::Error> {
+        for i in 0..self.mmsgs.len() {
+            self.iovecs[i].iov_base =
+                self.buf[i * SMS_PACKET_SIZE..].as_mut_ptr() as *mut libc::c_void;
+            self.iovecs[i].iov_len = SMS_PACKET_SIZE;
+            self.mmsgs[i].msg_hdr.msg_iov = &mut self.iovecs[i];
+            self.mmsgs[i].msg_hdr.msg_iovlen = 1;
+            self.mmsgs[i].msg_hdr.msg_name = std::ptr::null_mut();
+            self.mmsgs[i].msg_hdr.msg_namelen = 0;
+            self.mmsgs[i].msg_hdr.msg_control = std::ptr::null_mut();
+            self.mmsgs[i].msg_hdr.msg_controllen = 0;
+            self.mmsgs[i].msg_hdr.msg_flags = 0;
+            self.mmsgs[i].msg_len = 0;
         }
 
         match unsafe {
             libc::recvmmsg(
-                sock.as_raw_fd(),
-                mmsgs.as_mut_ptr(),
-                VLEN as u32,
+                fd,
+                self.mmsgs.as_mut_ptr(),
+                self.mmsgs.len() as u32,
                 0,
                 std::ptr::null_mut(),
             )
         } {
-            -1 => {
-                let err = std::io::Error::last_os_error();
-                match err.kind() {
-                    std::io::ErrorKind::Interrupted => (),
-                    std::io::ErrorKind::WouldBlock => thread::sleep(RETRY_TIME),
-                    _ => error!("port5 error: {:?}", err),
+            -1 => Err(std::io::Error::last_os_error()),
+            n => Ok(&self.buf[..n as usize * SMS_PACKET_SIZE]),
+        }
+    }
+}
+
+/// Batched UDP packet reader on an io_uring.
+///
+/// A `IORING_OP_RECVMSG` request stays queued for every packet slot, each
+/// completed slot is queued again on the next read so a single
+/// `io_uring_enter` both resubmits the slots and waits for completions.
+/// With SQPOLL a kernel thread polls the submission queue, the socket must
+/// be blocking as io_uring reports `EAGAIN` for non-blocking sockets.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub struct UringReader {
+    // Dropped first, tearing down the ring cancels the requests which still
+    // reference the buffers below.
+    ring: io_uring::IoUring,
+    fd: std::os::fd::RawFd,
+    msgs: Vec<libc::msghdr>,
+    iovecs: Vec<libc::iovec>,
+    buf: Vec<u8>,
+    batch: Vec<u8>,
+    pending: Vec<usize>,
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+impl UringReader {
+    /// Idle time in milliseconds before the SQPOLL kernel thread sleeps.
+    const SQPOLL_IDLE_MS: u32 = 1000;
+
+    /// Create the ring and queue a receive request for every packet slot.
+    ///
+    /// # Arguments
+    /// * `fd` - Blocking UDP socket which outlives the reader
+    /// * `depth` - Number of packet slots and queued requests
+    /// * `sqpoll` - Poll the submission queue from a kernel thread
+    ///
+    /// # Errors
+    /// Returns the ring setup error, such as `EPERM` for SQPOLL without the
+    /// required privileges or `ENOSYS` on kernels without io_uring
+    pub fn new(fd: std::os::fd::RawFd, depth: usize, sqpoll: bool) -> Result<Self, std::io::Error> {
+        let mut builder = io_uring::IoUring::builder();
+        if sqpoll {
+            builder.setup_sqpoll(Self::SQPOLL_IDLE_MS);
+        }
+        let ring = builder.build(depth as u32)?;
+
+        let mut reader = UringReader {
+            ring,
+            fd,
+            msgs: Vec::with_capacity(depth),
+            iovecs: Vec::with_capacity(depth),
+            buf: vec![0; depth * SMS_PACKET_SIZE],
+            batch: Vec::with_capacity(depth * SMS_PACKET_SIZE),
+            pending: (0..depth).collect(),
+        };
+
+        // The vectors are never resized so the pointers handed to the kernel
+        // stay valid for the lifetime of the reader.
+        for i in 0..depth {
+            reader.iovecs.push(libc::iovec {
+                iov_base: reader.buf[i * SMS_PACKET_SIZE..].as_mut_ptr() as *mut libc::c_void,
+                iov_len: SMS_PACKET_SIZE,
+            });
+        }
+        for i in 0..depth {
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_iov = &mut reader.iovecs[i];
+            msg.msg_iovlen = 1;
+            reader.msgs.push(msg);
+        }
+
+        reader.queue_pending()?;
+        reader.ring.submit()?;
+        Ok(reader)
+    }
+
+    fn queue_pending(&mut self) -> Result<(), std::io::Error> {
+        let mut submission = self.ring.submission();
+        for &slot in &self.pending {
+            let entry = io_uring::opcode::RecvMsg::new(
+                io_uring::types::Fd(self.fd),
+                &mut self.msgs[slot] as *mut libc::msghdr,
+            )
+            .build()
+            .user_data(slot as u64);
+            // SAFETY: the message header, iovec and buffer of the slot are
+            // owned by the reader and not touched until the completion.
+            unsafe { submission.push(&entry) }
+                .map_err(|_| std::io::Error::other("io_uring submission queue full"))?;
+        }
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Wait for at least one packet.
+    ///
+    /// # Returns
+    /// The received packets in completion order, one [`SMS_PACKET_SIZE`]
+    /// slot per packet
+    ///
+    /// # Errors
+    /// Returns the submission error, or the receive error if no packet was
+    /// received
+    pub fn read(&mut self) -> Result<&[u8], std::io::Error> {
+        self.queue_pending()?;
+        self.ring.submit_and_wait(1)?;
+
+        self.batch.clear();
+        let mut error = None;
+        for cqe in self.ring.completion() {
+            let slot = cqe.user_data() as usize;
+            self.pending.push(slot);
+            match cqe.result() {
+                n if n >= 0 => self
+                    .batch
+                    .extend_from_slice(&self.buf[slot * SMS_PACKET_SIZE..][..SMS_PACKET_SIZE]),
+                n => error = Some(std::io::Error::from_raw_os_error(-n)),
+            }
+        }
+
+        match error {
+            Some(err) if self.batch.is_empty() => Err(err),
+            _ => Ok(&self.batch),
+        }
+    }
+}
+
+/// The port5 implementation on Linux uses the recvmmsg system call to enable
+/// bulk reads of UDP packets.  This is not available on other platforms.
+///
+/// With the io-uring feature the packets are read from an io_uring instead,
+/// polled by a kernel thread with `uring_sqpoll`, falling back to recvmmsg
+/// if the ring cannot be set up.
+///
+/// The reading thread is moved to the SCHED_FIFO scheduler with
+/// `rt_priority`, 0 keeps the normal scheduler.
+#[cfg(target_os = "linux")]
+pub async fn port5(tx: impl PacketSender, uring_sqpoll: bool, rt_priority: i32) {
+    use std::{os::fd::AsRawFd, thread, time::Duration};
+
+    use crate::common::{set_socket_bufsize, set_thread_priority, SchedPolicy};
+
+    const VLEN: usize = 64;
+    const RETRY_TIME: Duration = Duration::from_micros(250);
+
+    set_thread_priority("port5", SchedPolicy::Fifo, rt_priority);
+    let sock = UdpSocket::bind("0.0.0.0:50005").await.unwrap();
+    let sock = sock.into_std().unwrap();
+    set_socket_bufsize("port5", &sock, 2 * 1024 * 1024);
+
+    #[cfg(feature = "io-uring")]
+    {
+        sock.set_nonblocking(false).unwrap();
+        match UringReader::new(sock.as_raw_fd(), VLEN, uring_sqpoll) {
+            Ok(mut reader) => loop {
+                match reader.read() {
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+                    Err(err) => error!("port5 error: {:?}", err),
+                    Ok(packets) => match tx.send_packets(packets.to_vec()).await {
+                        Ok(_) => (),
+                        Err(e) => error!("port5 error: {:?}", e),
+                    },
                 }
+            },
+            Err(err) => {
+                tracing::warn!("port5 io_uring setup error, using recvmmsg: {:?}", err);
+                sock.set_nonblocking(true).unwrap();
             }
-            n => match tx.send(buf[..n as usize * SMS_PACKET_SIZE].to_vec()).await {
+        }
+    }
+    #[cfg(not(feature = "io-uring"))]
+    let _ = uring_sqpoll;
+
+    let sock = UdpSocket::from_std(sock).unwrap();
+    let mut reader = MmsgReader::new(VLEN);
+
+    loop {
+        match reader.read(sock.as_raw_fd()) {
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::Interrupted => (),
+                std::io::ErrorKind::WouldBlock => thread::sleep(RETRY_TIME),
+                _ => error!("port5 error: {:?}", err),
+            },
+            Ok(packets) => match tx.send_packets(packets.to_vec()).await {
                 Ok(_) => (),
                 Err(e) => error!("port5 error: {:?}", e),
             },
@@ -86,13 +290,13 @@ pub async fn port5(tx: AsyncSender<Vec<u8>>) {
 }
 
 #[cfg(not(target_os = "linux"))]
-pub async fn port5(tx: AsyncSender<Vec<u8>>) {
+pub async fn port5(tx: impl PacketSender, _uring_sqpoll: bool, _rt_priority: i32) {
     let sock = UdpSocket::bind("0.0.0.0:50005").await.unwrap();
     let mut buf = [0; SMS_PACKET_SIZE];
 
     loop {
         match sock.recv_from(&mut buf).await {
-            Ok((n, _)) => match tx.send(buf.to_vec()).await {
+            Ok((n, _)) => match tx.send_packets(buf.to_vec()).await {
                 Ok(_) => (),
                 Err(e) => error!("port5 write error: {:?}", e),
             },
@@ -107,14 +311,14 @@ pub async fn port5(tx: AsyncSender<Vec<u8>>) {
 /// channel.
 ///
 /// # Arguments
-/// * `tx` - Async channel sender for received packets
-pub async fn port63(tx: AsyncSender<Vec<u8>>) {
+/// * `tx` - Destination of the received packets
+pub async fn port63(tx: impl PacketSender) {
     let sock = UdpSocket::bind("0.0.0.0:50063").await.unwrap();
     let mut buf = [0; SMS_PACKET_SIZE];
 
     loop {
         match sock.recv_from(&mut buf).await {
-            Ok(_) => match tx.send(buf.to_vec()).await {
+            Ok(_) => match tx.send_packets(buf.to_vec()).await {
                 Ok(_) => (),
                 Err(e) => error!("port63 write error: {:?}", e),
             },
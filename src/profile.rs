@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Named radar parameter profiles for drvegrdctl.
+//!
+//! Profiles are tables of parameter values keyed by the drvegrdctl parameter
+//! names, stored in `~/.config/drvegrdctl/profiles.toml`:
+//!
+//! ```toml
+//! [parking]
+//! center_frequency = 1
+//! frequency_sweep = 3
+//! range_toggle = 0
+//! detection_sensitivity = 2
+//! ```
+//!
+//! The `parking`, `highway` and `indoor` profiles are built in and used when
+//! the profiles file does not define them.
+
+use crate::can::Parameter;
+use clap::ValueEnum;
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// Profiles available without a profiles file, as (center_frequency,
+/// frequency_sweep, range_toggle, detection_sensitivity).
+const BUILTIN_PROFILES: [(&str, [u32; 4]); 3] = [
+    // Ultra short sweep and high sensitivity for close, low objects.
+    ("parking", [1, 3, 0, 2]),
+    // Long sweep alternating with the short sweep for the near field.
+    ("highway", [1, 0, 2, 1]),
+    // Ultra short sweep and low sensitivity against wall reflections.
+    ("indoor", [1, 3, 0, 0]),
+];
+
+/// Profile errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing the profiles file failed
+    Io(io::Error),
+    /// The profiles file is not valid TOML
+    Parse(toml::de::Error),
+    /// A profile entry has an unknown parameter or invalid value
+    Invalid(String),
+    /// No profile with this name
+    Unknown(String),
+    /// Neither XDG_CONFIG_HOME nor HOME is set
+    NoConfigDir,
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Error {
+        Error::Parse(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Parse(err) => write!(f, "profiles parse error: {}", err),
+            Error::Invalid(msg) => write!(f, "invalid profile: {}", msg),
+            Error::Unknown(name) => write!(f, "unknown profile: {}", name),
+            Error::NoConfigDir => write!(f, "neither XDG_CONFIG_HOME nor HOME is set"),
+        }
+    }
+}
+
+/// Parameter values of a profile in the order they are written.
+pub type Profile = Vec<(Parameter, u32)>;
+
+/// Location of the profiles file, `$XDG_CONFIG_HOME/drvegrdctl/profiles.toml`
+/// falling back to `~/.config/drvegrdctl/profiles.toml`.
+pub fn profiles_path() -> Result<PathBuf, Error> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".config"),
+            None => return Err(Error::NoConfigDir),
+        },
+    };
+
+    Ok(config.join("drvegrdctl").join("profiles.toml"))
+}
+
+/// Parameters stored in a profile, every parameter drvegrdctl can name.
+pub fn profile_parameters() -> &'static [Parameter] {
+    Parameter::value_variants()
+}
+
+fn builtin_profile(name: &str) -> Option<Profile> {
+    BUILTIN_PROFILES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, values)| {
+            let params = [
+                Parameter::CenterFrequency,
+                Parameter::FrequencySweep,
+                Parameter::RangeToggle,
+                Parameter::DetectionSensitivity,
+            ];
+            params.into_iter().zip(values.iter().copied()).collect()
+        })
+}
+
+fn read_profiles(path: &Path) -> Result<Table, Error> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text.parse::<Table>()?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Table::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn parse_profile(name: &str, table: &Table) -> Result<Profile, Error> {
+    for key in table.keys() {
+        if Parameter::from_str(key, false).is_err() {
+            return Err(Error::Invalid(format!(
+                "{}: unknown parameter {}",
+                name, key
+            )));
+        }
+    }
+
+    // Write in the drvegrdctl parameter order whatever the file order.
+    let mut profile = Profile::new();
+    for param in profile_parameters() {
+        let key = param.to_possible_value().unwrap();
+        let value = match table.get(key.get_name()) {
+            Some(value) => value,
+            None => continue,
+        };
+        let value = value
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                Error::Invalid(format!("{}: invalid {} {}", name, key.get_name(), value))
+            })?;
+        profile.push((*param, value));
+    }
+
+    Ok(profile)
+}
+
+/// Load the profile `name` from the profiles file at `path`, falling back to
+/// the built in profiles when the file does not define it.
+pub fn load_profile(path: &Path, name: &str) -> Result<Profile, Error> {
+    let profiles = read_profiles(path)?;
+    match profiles.get(name) {
+        Some(Value::Table(table)) => parse_profile(name, table),
+        Some(_) => Err(Error::Invalid(format!("{} is not a table", name))),
+        None => builtin_profile(name).ok_or_else(|| Error::Unknown(name.to_string())),
+    }
+}
+
+/// Store the profile as `name` in the profiles file at `path`, replacing a
+/// profile of the same name and keeping the others.
+pub fn save_profile(path: &Path, name: &str, profile: &[(Parameter, u32)]) -> Result<(), Error> {
+    let mut profiles = read_profiles(path)?;
+    let mut table = Table::new();
+    for (param, value) in profile {
+        let key = param.to_possible_value().unwrap();
+        table.insert(
+            key.get_name().to_string(),
+            Value::Integer(i64::from(*value)),
+        );
+    }
+    profiles.insert(name.to_string(), Value::Table(table));
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, profiles.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("drvegrdctl-{}-{}", name, std::process::id()))
+            .join("profiles.toml")
+    }
+
+    fn names(profile: &[(Parameter, u32)]) -> Vec<String> {
+        profile
+            .iter()
+            .map(|(param, value)| format!("{:?}={}", param, value))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_profiles() {
+        let path = temp_path("builtin");
+        for (name, _) in BUILTIN_PROFILES {
+            let profile = load_profile(&path, name).unwrap();
+            assert_eq!(profile.len(), 4);
+        }
+        assert_eq!(
+            names(&load_profile(&path, "parking").unwrap()),
+            [
+                "CenterFrequency=1",
+                "FrequencySweep=3",
+                "RangeToggle=0",
+                "DetectionSensitivity=2"
+            ]
+        );
+        assert!(matches!(
+            load_profile(&path, "racetrack"),
+            Err(Error::Unknown(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_profile() {
+        let path = temp_path("load");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            "[parking]\ndetection_sensitivity = 1\nfrequency_sweep = 2\n\n\
+             [bad]\ntx_antenna = 1\n\n\
+             [negative]\nrange_toggle = -1\n",
+        )
+        .unwrap();
+
+        // The file overrides the built in profile, in parameter order.
+        assert_eq!(
+            names(&load_profile(&path, "parking").unwrap()),
+            ["FrequencySweep=2", "DetectionSensitivity=1"]
+        );
+        // Profiles missing from the file are still built in.
+        assert_eq!(load_profile(&path, "indoor").unwrap().len(), 4);
+        assert!(matches!(load_profile(&path, "bad"), Err(Error::Invalid(_))));
+        assert!(matches!(
+            load_profile(&path, "negative"),
+            Err(Error::Invalid(_))
+        ));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_save_profile() {
+        let path = temp_path("save");
+        let mine = [
+            (Parameter::CenterFrequency, 2),
+            (Parameter::FrequencySweep, 1),
+            (Parameter::EnableTargetList, 1),
+        ];
+        save_profile(&path, "mine", &mine).unwrap();
+        save_profile(&path, "other", &builtin_profile("indoor").unwrap()).unwrap();
+
+        assert_eq!(names(&load_profile(&path, "mine").unwrap()), names(&mine));
+        assert_eq!(
+            names(&load_profile(&path, "other").unwrap()),
+            names(&builtin_profile("indoor").unwrap())
+        );
+
+        // Saving again replaces only the named profile.
+        save_profile(&path, "mine", &[(Parameter::RangeToggle, 4)]).unwrap();
+        assert_eq!(
+            names(&load_profile(&path, "mine").unwrap()),
+            ["RangeToggle=4"]
+        );
+        assert_eq!(load_profile(&path, "other").unwrap().len(), 4);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,18 +1,66 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
+mod accumulate;
+mod antenna;
 mod args;
+mod bev;
+mod blockage;
+mod breaker;
 mod can;
 mod clustering;
 mod common;
+mod cpu;
+// The client half is used by drvegrdctl.
+#[allow(dead_code)]
+mod control;
+mod dump;
+#[allow(dead_code)]
+mod echo;
 mod eth;
+mod ground;
+mod history;
+mod latency;
+mod memory;
 mod net;
+mod reconnect;
+mod schema;
+mod selftest;
+mod signal;
+mod sink;
+mod tracklog;
+mod unfold;
+mod watchdog;
 
-use args::{Args, CenterFrequency, DetectionSensitivity, FrequencySweep, RangeToggle};
-use can::{read_message, read_status, write_parameter, Parameter, Status, Target};
-use clap::Parser;
-use clustering::Clustering;
+use accumulate::Heatmap;
+use antenna::AntennaPatternCorrection;
+use args::{
+    Args, BevSource, CenterFrequency, ClusteringAlgorithm, CubeSink, DetectionSensitivity,
+    FrequencySweep, RangeToggle, WindowLimit,
+};
+use bev::BevGrid;
+use blockage::{BlockageDetector, FrameStats};
+use breaker::PublishBreaker;
+use can::{
+    read_device_info, read_message, read_parameters, save_parameters, split_responses,
+    split_status, target_confidence, uat_error_counts, write_parameters, BlockageAlarm, CanLock,
+    CanReader, CanWriter, DeviceInfo, Parameter, RadarParameters, RecvResponseSettings,
+    SensorStatus, Target, TargetFrame,
+};
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use clustering::{
+    cluster_summaries, davies_bouldin_index, detection_score, missed_frames,
+    velocity_coherence_check, Classifier, ClassifierThresholds, ClusterSummary, ClusterTrack,
+    Clustering, ClusteringBuilder, ClusteringMode, ObjectClass, PointRole, TrackAssociation,
+    TrackRecord,
+};
+use common::{
+    set_thread_affinity, set_thread_priority, transform_xyz, DropSeverity, RollingStats,
+    SchedPolicy,
+};
 use core::f64;
+use cpu::{CpuMonitor, ThreadUsage};
+use dump::{CubeFileFormat, CubeWriter};
 use edgefirst_schemas::{
     builtin_interfaces::{self, Time},
     edgefirst_msgs::{self, RadarInfo},
@@ -20,22 +68,46 @@ use edgefirst_schemas::{
     sensor_msgs, serde_cdr,
     std_msgs::{self, Header},
 };
-use eth::{RadarCube, RadarCubeReader, SMS_PACKET_SIZE};
+use eth::{CubeLimits, RadarCube, RadarCubeReader, CUBE_LAYOUT, SMS_PACKET_SIZE};
+use ground::ransac_ground_plane;
+use history::{cluster_count_anomaly, debug_dump, FrameHistory};
 use kanal::{AsyncReceiver, AsyncSender};
+use latency::{serve_metrics, BatchMetrics, ClusteringCycle, ClusteringMetrics, LatencyHistogram};
+use memory::{byte_channel, ByteGauge, ByteReceiver};
+use ndarray::Array2;
+use num::Complex;
+use reconnect::{
+    open_with_endpoints, read_endpoints, watch_endpoints, ReconnectingPublisher, SessionWatch,
+    ENDPOINTS_POLL,
+};
+use schema::{schema_json, PointLayout};
+use selftest::{bind_udp, check_can, check_udp, check_zenoh, format_summary, run_check, UDP_PORTS};
+use serde_json::json;
+use signal::beamform_phase_shift;
+use sink::{BatchSink, DiscardSink, Sink};
 use socketcan::tokio::CanSocket;
 use std::{
-    collections::VecDeque,
-    f32::consts::PI,
+    collections::{HashSet, VecDeque},
+    future::Future,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     thread::{self},
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
-use tracing::{error, event, info, info_span, instrument, warn, Instrument, Level};
+use tracing::{debug, error, event, info, info_span, instrument, warn, Instrument, Level};
 use tracing_subscriber::{layer::SubscriberExt as _, Layer as _, Registry};
+use tracklog::{append_tracks, track_json};
 use tracy_client::{frame_mark, plot, secondary_frame_mark};
+use unfold::DopplerUnfolder;
+use watchdog::watchdog;
 use zenoh::{
     bytes::{Encoding, ZBytes},
-    qos::{CongestionControl, Priority},
-    Session,
+    key_expr::KeyExpr,
+    qos::Priority,
+    Config, Session,
 };
 
 #[cfg(feature = "profiling")]
@@ -56,9 +128,176 @@ pub enum PointFieldType {
     FLOAT64 = 8,
 }
 
+/// Clustering cycles between the debug logs of the clustering metrics.
+const CLUSTERING_METRICS_CYCLES: u64 = 100;
+
+/// Number of published frames between latency histogram publications.
+const LATENCY_PUBLISH_FRAMES: u64 = 1000;
+
+/// Radar cube frames in the rolling statistics window, which are reported
+/// once per window.
+const CUBE_STATS_FRAMES: usize = 1080;
+
+/// CAN frames in the rolling frame rate window, which is logged once per
+/// window.
+const CAN_STATS_FRAMES: usize = 1000;
+
+/// Nominal DRVEGRD radar cycle, the targets arrive at about 18 Hz.
+const RADAR_CYCLE: Duration = Duration::from_millis(55);
+
+/// Pause of the radar frames, three nominal cycles, after which the
+/// clustering window is flushed.
+const WINDOW_FLUSH_GAP: Duration = Duration::from_millis(165);
+
+/// Most recent target frames published in a clustering debug dump.
+const DEBUG_DUMP_FRAMES: usize = 10;
+
+/// Frames between ground plane publishes with --remove-ground.
+const GROUND_PLANE_FRAMES: usize = 10;
+
+/// Radar cubes waiting to be written by the cube file sink before new cubes
+/// are dropped.
+const CUBE_WRITER_QUEUE: usize = 16;
+
+/// Consecutive publish failures before a publisher pauses serialization.
+const PUBLISH_FAILURE_THRESHOLD: u32 = 10;
+
+/// Time between probe messages while a publisher is paused.
+const PUBLISH_PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time a track log query waits for the clustering task to write the log.
+const TRACK_LOG_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval between publications of the blockage detector state, a change
+/// of the state is published at once.
+const BLOCKAGE_PUBLISH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interval between saves of the blockage baseline to the state file.
+const BLOCKAGE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Request to append the completed tracks to the track log, answered with
+/// the number of tracks written.
+type TrackLogRequest = tokio::sync::oneshot::Sender<Result<usize, String>>;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    args.resolve_topics();
+    args.resolve_clustering();
+    let eps_sweep = args.eps_sweep();
+    if args.clustering && eps_sweep.is_some() {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the clustering eps sweep is a tuning mode and cannot run with clustering",
+            )
+            .exit();
+    }
+    if args.detect_labels.len() == 2 {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "detect labels take one label for every box or three labels for the approaching, stationary and receding boxes",
+            )
+            .exit();
+    }
+
+    if !(args.cluster_id_cooldown.is_finite() && args.cluster_id_cooldown >= 0.0) {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "the cluster id cooldown must be a non-negative number of seconds",
+            )
+            .exit();
+    }
+
+    if !(args.blockage_fraction > 0.0 && args.blockage_fraction <= 1.0) {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "the blockage fraction must be above 0 and at most 1",
+            )
+            .exit();
+    }
+
+    if !(args.blockage_baseline_hours.is_finite() && args.blockage_baseline_hours > 0.0) {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "the blockage baseline must be a positive number of hours",
+            )
+            .exit();
+    }
+
+    if args.track_log_path().is_some() && !args.clustering {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the track log records the clustering tracks and requires clustering",
+            )
+            .exit();
+    }
+
+    if args.tracker_state_file().is_some() && !args.clustering {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the tracker state keeps the clustering tracks and requires clustering",
+            )
+            .exit();
+    }
+
+    if !(args.tracker_state_max_age.is_finite() && args.tracker_state_max_age >= 0.0) {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "the tracker state max age must be a non-negative number of seconds",
+            )
+            .exit();
+    }
+
+    if args.bev
+        && !(args.bev_size.is_finite()
+            && args.bev_size > 0.0
+            && args.bev_resolution.is_finite()
+            && args.bev_resolution > 0.0
+            && args.bev_size / args.bev_resolution <= bev::MAX_CELLS as f32)
+    {
+        Args::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "the bird's-eye-view size and resolution must be positive with at most {} cells along a side",
+                    bev::MAX_CELLS
+                ),
+            )
+            .exit();
+    }
+
+    if args.heatmap {
+        let extent = &args.heatmap_extent;
+        let resolution = args.heatmap_resolution;
+        let cells = |min: f32, max: f32| (max - min) / resolution;
+        if !(extent.len() == 4
+            && extent.iter().all(|v| v.is_finite())
+            && extent[1] > extent[0]
+            && extent[3] > extent[2]
+            && resolution.is_finite()
+            && resolution > 0.0
+            && cells(extent[0], extent[1]) <= bev::MAX_CELLS as f32
+            && cells(extent[2], extent[3]) <= bev::MAX_CELLS as f32)
+        {
+            Args::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "the heatmap extent must have x_max above x_min and y_max above y_min and the resolution must be positive with at most {} cells along a side",
+                        bev::MAX_CELLS
+                    ),
+                )
+                .exit();
+        }
+    }
 
     args.tracy.then(tracy_client::Client::start);
 
@@ -83,50 +322,122 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
     tracing_log::LogTracer::init()?;
 
-    let session = zenoh::open(args.clone()).await.unwrap();
-    let can = CanSocket::open(&args.can)?;
+    if args.self_test {
+        let passed = self_test(&args).await;
+        std::process::exit(match passed {
+            true => 0,
+            false => 1,
+        });
+    }
 
-    let software_generation = read_status(&can, Status::SoftwareGeneration).await.unwrap();
-    let major_version = read_status(&can, Status::MajorVersion).await.unwrap();
-    let minor_version = read_status(&can, Status::MinorVersion).await.unwrap();
-    let patch_version = read_status(&can, Status::PatchVersion).await.unwrap();
-    let serial_number = read_status(&can, Status::SerialNumber).await.unwrap();
-    info!("Software Generation: {}", software_generation);
-    info!(
-        "Version: {}.{}.{}",
-        major_version, minor_version, patch_version
-    );
-    info!("Serial Number: {}", serial_number);
+    if args.dry_run {
+        return dry_run(args).await;
+    }
 
-    let center_frequency = write_parameter(
-        &can,
-        Parameter::CenterFrequency,
-        args.center_frequency as u32,
-    )
-    .await?;
+    let config = match Config::try_from(args.clone()) {
+        Ok(config) => config,
+        Err(e @ args::Error::ZenohConfig(..)) => {
+            Args::command().error(ErrorKind::InvalidValue, e).exit()
+        }
+        Err(e) => Args::command()
+            .error(ErrorKind::MissingRequiredArgument, e)
+            .exit(),
+    };
+    let zenoh_reconnects = Arc::new(AtomicU64::new(0));
+    let endpoints_file = args
+        .endpoints_file
+        .clone()
+        .filter(|path| !path.as_os_str().is_empty());
+    // The publishers follow the sessions across endpoint reloads, the
+    // subscribers stay on the session opened at startup.
+    let (session, sessions) = match endpoints_file {
+        Some(path) => {
+            let endpoints = match read_endpoints(&path) {
+                Ok(endpoints) => endpoints,
+                Err(e) => {
+                    warn!("{} read error: {:?}", path.display(), e);
+                    Vec::new()
+                }
+            };
+            let session = match endpoints.is_empty() {
+                true => zenoh::open(config.clone()).await.unwrap(),
+                false => open_with_endpoints(&config, &endpoints).await.unwrap(),
+            };
+            let (tx, sessions) = tokio::sync::watch::channel((0, session.clone()));
+            tokio::spawn(watch_endpoints(
+                path,
+                config,
+                ENDPOINTS_POLL,
+                tx,
+                endpoints,
+                zenoh_reconnects.clone(),
+            ));
+            (session, sessions)
+        }
+        None => {
+            let session = zenoh::open(config).await.unwrap();
+            let (_, sessions) = tokio::sync::watch::channel((0, session.clone()));
+            (session, sessions)
+        }
+    };
+
+    // Started before the radar is configured so the echo works without one.
+    if args.echo_server {
+        let echo_session = session.clone();
+        let request_topic = args.topic("echo_request");
+        let reply_topic = args.topic("echo_reply");
+        let echo_task = tokio::spawn(async move {
+            if let Err(e) = echo::serve(&echo_session, &request_topic, &reply_topic).await {
+                error!("{} echo error: {:?}", request_topic, e);
+            }
+        });
+        std::mem::drop(echo_task);
+    }
+
+    let _can_lock = lock_can(&args)?;
+    let can = CanSocket::open(&args.can)?;
 
-    let frequency_sweep =
-        write_parameter(&can, Parameter::FrequencySweep, args.frequency_sweep as u32).await?;
+    let uat = RecvResponseSettings {
+        max_retries: args.uat_max_retries,
+        read_timeout: Duration::from_millis(args.uat_timeout_ms),
+        ids: args.can_ids(),
+    };
 
-    let range_toggle =
-        write_parameter(&can, Parameter::RangeToggle, args.range_toggle as u32).await?;
+    let device = read_device_info(&can, &uat).await.unwrap();
+    info!("Software Generation: {}", device.software_generation);
+    info!("Version: {}", device.version());
+    info!("Serial Number: {}", device.serial_number);
 
-    let detection_sensitivity = write_parameter(
-        &can,
-        Parameter::DetectionSensitivity,
-        args.detection_sensitivity as u32,
-    )
-    .await?;
+    let firmware_accuracy = device.reports_accuracy();
+    if args.include_accuracy && !firmware_accuracy {
+        warn!(
+            "firmware {} does not report target accuracy, publishing 0",
+            device.version()
+        );
+    }
 
+    let (requested, confirmed) = configure_parameters(&can, &args, &uat).await?;
+
+    let radar_state = RadarState {
+        frame_id: args.base_frame_id.clone(),
+        cube: args.cube,
+        device,
+        requested,
+        confirmed,
+        zenoh_reconnects,
+        status: None,
+        blockage_alarm: false,
+        clustering_window: args.clustering.then_some(args.window_limit()),
+    };
     info!(
-        "radar parameters: center_frequency={:?} frequency_sweep={:?} range_toggle={:?} detection_sensitivity={:?}",
-        CenterFrequency::try_from(center_frequency).unwrap(),
-        FrequencySweep::try_from(frequency_sweep).unwrap(),
-        RangeToggle::try_from(range_toggle).unwrap(),
-        DetectionSensitivity::try_from(detection_sensitivity).unwrap()
+        "radar parameters: center_frequency={} frequency_sweep={} range_toggle={} detection_sensitivity={}",
+        radar_state.center_frequency(),
+        radar_state.frequency_sweep(),
+        radar_state.range_toggle(),
+        radar_state.detection_sensitivity()
     );
 
-    let tf_session = session.clone();
+    let tf_sessions = sessions.clone();
     let tf_msg = TransformStamped {
         header: Header {
             frame_id: args.base_frame_id.clone(),
@@ -149,53 +460,137 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
     let tf_msg = ZBytes::from(serde_cdr::serialize(&tf_msg).unwrap());
     let tf_enc = Encoding::APPLICATION_CDR.with_schema("geometry_msgs/msg/TransformStamped");
-    let tf_task = tokio::spawn(async move { tf_static(tf_session, tf_msg, tf_enc).await.unwrap() });
+    let tf_topic = args.global_topic("tf_static");
+    let tf_task = tokio::spawn(async move {
+        publish_static(tf_sessions, tf_topic, tf_msg, tf_enc)
+            .await
+            .unwrap()
+    });
     std::mem::drop(tf_task);
 
-    let info_msg = RadarInfo {
-        header: Header {
-            frame_id: args.base_frame_id.clone(),
-            stamp: timestamp().unwrap_or(Time { sec: 0, nanosec: 0 }),
-        },
-        center_frequency: args.center_frequency.to_string(),
-        frequency_sweep: args.frequency_sweep.to_string(),
-        range_toggle: args.range_toggle.to_string(),
-        detection_sensitivity: args.detection_sensitivity.to_string(),
-        cube: args.cube,
+    // The point layouts follow from the command line, so the schema is
+    // fixed for the lifetime of the process.
+    let schema_sessions = sessions.clone();
+    let schema_msg = ZBytes::from(format_schema(&args).to_string());
+    let schema_topic = args.topic("schema");
+    let schema_task = tokio::spawn(async move {
+        publish_static(
+            schema_sessions,
+            schema_topic,
+            schema_msg,
+            Encoding::APPLICATION_JSON,
+        )
+        .await
+        .unwrap()
+    });
+    std::mem::drop(schema_task);
+
+    let info_sessions = sessions.clone();
+    let radar_state = Arc::new(Mutex::new(radar_state));
+    let info_state = radar_state.clone();
+    let info_topic = args.topic("info");
+    let device_topic = args.topic("device");
+    let targets_topic = args.targets_topic.clone();
+    let info_task = tokio::spawn(async move {
+        radar_info(
+            info_sessions,
+            info_state,
+            info_topic,
+            device_topic,
+            targets_topic,
+        )
+        .await
+        .unwrap()
+    });
+    std::mem::drop(info_task);
+
+    let (association_tx, association_rx) = match args.annotate_targets {
+        true => {
+            let (tx, rx) = kanal::bounded_async(4);
+            (Some(tx), Some(rx))
+        }
+        false => (None, None),
     };
 
-    let info_session = session.clone();
-    let info_msg = ZBytes::from(serde_cdr::serialize(&info_msg).unwrap());
-    let info_enc = Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/RadarInfo");
-    let tf_task =
-        tokio::spawn(async move { radar_info(info_session, info_msg, info_enc).await.unwrap() });
-    std::mem::drop(tf_task);
+    let clustering_metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+
+    let (track_log_tx, track_log_rx) = match args.track_log_path().is_some() {
+        true => {
+            let (tx, rx) = kanal::bounded_async(1);
+            (Some(tx), Some(rx))
+        }
+        false => (None, None),
+    };
 
-    let clustering = if args.clustering {
-        let session = session.clone();
+    let (clustering, clustering_thread) = if args.clustering {
+        let sessions = sessions.clone();
         let args = args.clone();
+        let metrics = clustering_metrics.clone();
         let (tx, rx) = kanal::bounded_async(16);
 
-        thread::Builder::new()
+        let thread = thread::Builder::new()
             .name("cluster".to_string())
             .spawn(move || {
                 tokio::runtime::Builder::new_current_thread()
                     .enable_all()
                     .build()
                     .unwrap()
-                    .block_on(clustering_task(session, args, rx))
+                    .block_on(async {
+                        let sinks = ClusteringSinks::declare(&sessions, &args).await?;
+                        clustering_task(sinks, args, rx, association_tx, metrics, track_log_rx)
+                            .await
+                    })
+                    .unwrap();
+            })?;
+
+        (Some(tx), Some(thread))
+    } else if let Some(eps) = eps_sweep {
+        let sessions = sessions.clone();
+        let args = args.clone();
+        let (tx, rx) = kanal::bounded_async(16);
+
+        thread::Builder::new()
+            .name("eps_sweep".to_string())
+            .spawn(move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+                    .block_on(async {
+                        let sink = declare_sink(
+                            &sessions,
+                            &args.cluster_quality_sweep_topic,
+                            Priority::Background,
+                        )
+                        .await?;
+                        eps_sweep_task(sink, args, eps, rx).await
+                    })
                     .unwrap();
             })?;
 
-        Some(tx)
+        (Some(tx), None)
     } else {
-        None
+        (None, None)
     };
 
+    if let Some(requests) = track_log_tx.clone() {
+        let track_log_session = session.clone();
+        let topic = args.track_log_topic.clone();
+        let track_log_task = tokio::spawn(async move {
+            if let Err(e) = serve_track_log(&track_log_session, &topic, requests).await {
+                error!("{} track log error: {:?}", topic, e);
+            }
+        });
+        std::mem::drop(track_log_task);
+    }
+
+    let cube_channel = Arc::new(ByteGauge::new(args.cube_channel_max_bytes));
+    let cube_batch = Arc::new(Mutex::new(BatchMetrics::new()));
     if args.cube {
-        let session = session.clone();
-        let topic = args.cube_topic.clone();
-        let frame_id = args.radar_frame_id.clone();
+        let sessions = sessions.clone();
+        let args = args.clone();
+        let cube_channel = cube_channel.clone();
+        let cube_batch = cube_batch.clone();
 
         thread::Builder::new()
             .name("cube".to_string())
@@ -204,525 +599,4768 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .enable_all()
                     .build()
                     .unwrap()
-                    .block_on(cube_loop(session, topic, frame_id, args.tracy))
+                    .block_on(cube_loop(sessions, args, cube_channel, cube_batch))
                     .unwrap();
             })?;
     }
 
-    let stream_task = stream(can, session, args, clustering);
-    stream_task.await.unwrap();
-
-    Ok(())
-}
-
-async fn stream(
-    can: CanSocket,
-    session: Session,
-    args: Args,
-    clustering: Option<AsyncSender<Vec<Target>>>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let targets_publisher = session
-        .declare_publisher(args.targets_topic.clone())
-        .priority(Priority::DataHigh)
-        .congestion_control(CongestionControl::Drop)
-        .await
-        .unwrap();
+    let latency = Arc::new(Mutex::new(LatencyHistogram::new()));
 
-    loop {
-        match read_message(&can).await {
-            Err(err) => error!("canbus error: {:?}", err),
-            Ok(frame) => {
-                let targets = &frame.targets[..frame.header.n_targets];
-                args.tracy.then(|| plot!("targets", targets.len() as f64));
+    if let Some(addr) = args.metrics.clone().filter(|addr| !addr.is_empty()) {
+        let latency = latency.clone();
+        let clustering_metrics = clustering_metrics.clone();
+        let cube_channel = cube_channel.clone();
+        let cube_batch = cube_batch.clone();
 
-                if let Some(tx) = &clustering {
-                    tx.send(targets.to_vec()).await.unwrap();
+        thread::Builder::new()
+            .name("metrics".to_string())
+            .spawn(move || {
+                if let Err(e) =
+                    serve_metrics(&addr, latency, clustering_metrics, cube_channel, cube_batch)
+                {
+                    error!("metrics server on {} failed: {:?}", addr, e);
                 }
+            })?;
+    }
 
-                let (msg, enc) = format_targets(targets, args.mirror, &args.radar_frame_id)?;
+    if args.cpu_monitor {
+        let sink = declare_sink(&sessions, &args.cpu_usage_topic, Priority::Background).await?;
+        let topic = args.cpu_usage_topic.clone();
+        let cpu_task = tokio::spawn(async move {
+            if let Err(e) = cpu_monitor(sink, &topic).await {
+                error!("{} cpu monitor error: {:?}", topic, e);
+            }
+        });
+        std::mem::drop(cpu_task);
+    }
 
-                let span = info_span!("targets_publish");
-                async {
-                    match targets_publisher.put(msg).encoding(enc).await {
-                        Ok(_) => {}
-                        Err(e) => error!("{} publish error: {:?}", args.targets_topic, e),
-                    }
-                }
-                .instrument(span)
-                .await;
+    let watchdog_session = session.clone();
+    let watchdog_topic = args
+        .watchdog_subscribe
+        .clone()
+        .filter(|topic| !topic.is_empty());
+    let watchdog_timeout = Duration::from_secs(args.watchdog_timeout_sec);
+    let heartbeat = async move {
+        match watchdog_topic {
+            Some(topic) => watchdog(&watchdog_session, &topic, watchdog_timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    // Without a track log or tracker state the default signal handling exits
+    // at once.
+    let graceful = graceful_shutdown(&args);
+    let shutdown = async move {
+        match graceful {
+            true => shutdown_signal().await,
+            false => std::future::pending().await,
+        }
+    };
 
-                args.tracy.then(frame_mark);
-            }
+    // The periodic status broadcasts of the sensor update the device topic.
+    let (can, statuses) = split_status(can, uat.ids.status);
+    let status_state = radar_state.clone();
+    let blockage_hold = Duration::from_secs(args.blockage_alarm_sec);
+    let status_task = tokio::spawn(async move {
+        let mut alarm = BlockageAlarm::new(blockage_hold);
+        while let Ok(status) = statuses.recv().await {
+            let raised = alarm.update(&status, Instant::now());
+            status_state.lock().unwrap().set_status(status, raised);
         }
-    }
-}
+    });
+    std::mem::drop(status_task);
 
-#[instrument(skip_all)]
-fn format_targets(
-    targets: &[Target],
-    mirror: bool,
-    frame_id: &str,
-) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
-    let n_targets = targets.len() as u32;
-    let data: Vec<_> = targets
-        .iter()
-        .flat_map(|target| {
-            let xyz = transform_xyz(
-                target.range as f32,
-                target.azimuth as f32,
-                target.elevation as f32,
-                mirror,
-            );
-            [
-                xyz[0],
-                xyz[1],
-                xyz[2],
-                target.speed as f32,
-                target.power as f32,
-                target.rcs as f32,
-            ]
-        })
-        .flat_map(|elem| elem.to_ne_bytes())
-        .collect();
+    let result = if args.control || args.param_dump_queryable {
+        let (can, responses) = split_responses(can, uat.ids.response);
 
-    let fields = vec![
-        sensor_msgs::PointField {
-            name: String::from("x"),
-            offset: 0,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("y"),
-            offset: 4,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("z"),
-            offset: 8,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("speed"),
-            offset: 12,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("power"),
-            offset: 16,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("rcs"),
-            offset: 20,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-    ];
+        let control_session = session.clone();
+        let topic = args.control.then(|| args.control_topic.clone());
+        let dump_topic = args
+            .param_dump_queryable
+            .then(|| args.param_dump_topic.clone());
+        let control_task = tokio::spawn(async move {
+            let on_write = |param, requested, confirmed| {
+                radar_state
+                    .lock()
+                    .unwrap()
+                    .set_parameter(param, requested, confirmed)
+            };
+            if let Err(e) = control::serve(
+                &control_session,
+                topic.as_deref(),
+                dump_topic.as_deref(),
+                &responses,
+                &uat,
+                on_write,
+            )
+            .await
+            {
+                error!("control error: {}", e);
+            }
+        });
+        std::mem::drop(control_task);
 
-    let msg = sensor_msgs::PointCloud2 {
-        header: std_msgs::Header {
-            stamp: timestamp()?,
-            frame_id: frame_id.to_string(),
-        },
-        height: 1,
-        width: n_targets,
-        fields,
-        is_bigendian: false,
-        point_step: 24,
-        row_step: 24 * n_targets,
-        data,
-        is_dense: true,
+        let sinks = StreamSinks::declare(&sessions, &args).await?;
+        let stream_task = stream(
+            can,
+            sinks,
+            args,
+            clustering,
+            association_rx,
+            latency,
+            firmware_accuracy,
+        );
+        supervise(stream_task, heartbeat, shutdown).await
+    } else {
+        let sinks = StreamSinks::declare(&sessions, &args).await?;
+        let stream_task = stream(
+            can,
+            sinks,
+            args,
+            clustering,
+            association_rx,
+            latency,
+            firmware_accuracy,
+        );
+        supervise(stream_task, heartbeat, shutdown).await
     };
 
-    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
-    let enc = Encoding::APPLICATION_CDR.with_schema("sensor_msgs/msg/PointCloud2");
+    if let (true, Some(thread)) = (graceful, clustering_thread) {
+        join_clustering(thread).await;
+    }
 
-    Ok((msg, enc))
+    result
 }
 
-async fn clustering_task(
-    session: Session,
-    args: Args,
-    rx: AsyncReceiver<Vec<Target>>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let publisher = session
-        .declare_publisher(&args.clusters_topic)
-        .priority(Priority::DataHigh)
-        .congestion_control(CongestionControl::Drop)
-        .await
-        .unwrap();
-
-    let mut window = VecDeque::<Vec<Target>>::with_capacity(args.window_size);
-    let mut clustering = Clustering::new(
-        args.clustering_eps,
-        &args.clustering_param_scale,
-        args.clustering_point_limit,
-    );
+/// Whether SIGINT and SIGTERM shut radarpub down through [`supervise`] so
+/// the clustering thread can write the track log and tracker state.
+fn graceful_shutdown(args: &Args) -> bool {
+    args.track_log_path().is_some() || args.tracker_state_file().is_some()
+}
 
-    loop {
-        let targets: Vec<Target> = rx.recv().await.unwrap();
-        let time = timestamp()?;
+/// Wait for the clustering thread once the stream task has dropped the
+/// clustering channel, the clustering task completes the live tracks, writes
+/// the track log and saves the tracker state before it exits.
+async fn join_clustering(thread: thread::JoinHandle<()>) {
+    if !matches!(
+        tokio::task::spawn_blocking(move || thread.join()).await,
+        Ok(Ok(()))
+    ) {
+        error!("clustering thread did not exit cleanly");
+    }
+}
 
-        let (targets, clusters) = info_span!("clustering").in_scope(|| {
-            if window.len() == args.window_size {
-                window.pop_front();
-            }
-            window.push_back(targets);
+/// Run the stream task until it returns, the heartbeat watchdog of the
+/// external supervisor expires, which shuts radarpub down with an error, or
+/// the shutdown signal is received.
+async fn supervise(
+    stream_task: impl Future<Output = Result<(), Box<dyn std::error::Error>>>,
+    heartbeat: impl Future<Output = Result<Duration, zenoh::Error>>,
+    shutdown: impl Future<Output = Result<(), std::io::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    tokio::select! {
+        result = stream_task => result.unwrap(),
+        elapsed = heartbeat => {
+            let elapsed = elapsed?;
+            error!("no watchdog heartbeat for {:?}, shutting down", elapsed);
+            return Err("watchdog heartbeat timeout".into());
+        }
+        signal = shutdown => signal?,
+    }
 
-            let targets = window.iter().flat_map(|v| v.iter()).collect::<Vec<_>>();
-            let dbscantargets: Vec<_> = targets
-                .iter()
-                .map(|t| {
-                    let [x, y, z] = transform_xyz(
-                        t.range as f32,
-                        t.azimuth as f32,
-                        t.elevation as f32,
-                        args.mirror,
-                    );
+    Ok(())
+}
 
-                    let mut v = [x, y, z, t.speed as f32];
-                    for (i, val) in v.iter_mut().enumerate() {
-                        *val *= args.clustering_param_scale[i];
-                    }
-                    v
-                })
-                .collect();
-            let clusters = clustering
-                .cluster(dbscantargets, time.to_nanos())
-                .into_iter()
-                .map(|v| v[4]);
+/// Wait for SIGTERM or SIGINT.
+async fn shutdown_signal() -> Result<(), std::io::Error> {
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        _ = terminate.recv() => info!("SIGTERM received, shutting down"),
+        interrupt = tokio::signal::ctrl_c() => {
+            interrupt?;
+            info!("SIGINT received, shutting down");
+        }
+    }
+    Ok(())
+}
 
-            (targets, clusters)
-        });
+/// Run the --self-test checks, logging each result and printing the JSON
+/// summary on stdout.
+///
+/// # Returns
+/// True if every check passed
+async fn self_test(args: &Args) -> bool {
+    let timeout = Duration::from_secs(args.self_test_timeout);
+    let uat = RecvResponseSettings {
+        max_retries: args.uat_max_retries,
+        read_timeout: Duration::from_millis(args.uat_timeout_ms),
+        ids: args.can_ids(),
+    };
 
-        let (msg, enc) = format_clusters(
-            time,
-            &targets,
-            clusters,
-            args.mirror,
-            args.radar_frame_id.clone(),
-        )?;
+    let mut results = Vec::new();
+    let can = run_check("can", timeout, async {
+        match CanSocket::open(&args.can) {
+            Ok(can) => check_can(&can, &uat).await,
+            Err(err) => Err(err.into()),
+        }
+    });
+    results.push(can.await);
 
-        let span = info_span!("clusters_publish");
-        async {
-            match publisher.put(msg).encoding(enc).await {
-                Ok(_) => {}
-                Err(e) => error!("{} message error: {:?}", args.clusters_topic, e),
+    // The cube ports are only bound when the cube is streamed.
+    if args.cube {
+        let udp = run_check("udp", timeout, async {
+            match bind_udp(&UDP_PORTS).await {
+                Ok(sockets) => check_udp(sockets).await,
+                Err(err) => Err(err.into()),
             }
+        });
+        results.push(udp.await);
+    }
+
+    let key = args.topic("self_test");
+    let zenoh = run_check("zenoh", timeout, async {
+        match zenoh::open(args.clone()).await {
+            Ok(session) => check_zenoh(&session, &key).await,
+            Err(err) => Err(err),
         }
-        .instrument(span)
-        .await;
+    });
+    results.push(zenoh.await);
 
-        args.tracy.then(|| secondary_frame_mark!("clustering"));
+    for result in &results {
+        match result.passed {
+            true => info!("{}", result),
+            false => error!("{}", result),
+        }
     }
+    println!("{}", format_summary(&results));
+    results.iter().all(|result| result.passed)
 }
 
-#[instrument(skip_all)]
-fn format_clusters<T: Iterator<Item = f32>>(
-    time: Time,
-    targets: &[&Target],
-    clusters: T,
-    mirror: bool,
-    frame_id: String,
-) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
-    let data: Vec<_> = targets
-        .iter()
-        .zip(clusters)
-        .flat_map(|(target, cluster)| {
-            let xyz = transform_xyz(
-                target.range as f32,
-                target.azimuth as f32,
-                target.elevation as f32,
-                mirror,
+/// Lock the CAN interface for the lifetime of the returned lock.  A lock
+/// directory that cannot be written only disables the lock, another radarpub
+/// holding it is an error.
+fn lock_can(args: &Args) -> Result<Option<CanLock>, can::Error> {
+    let dir = match args.can_lock_dir() {
+        Some(dir) => dir,
+        None => return Ok(None),
+    };
+    match CanLock::acquire(dir, &args.can) {
+        Ok(lock) => Ok(Some(lock)),
+        Err(err @ can::Error::InterfaceLocked(..)) => Err(err),
+        Err(err) => {
+            warn!(
+                "cannot lock {} in {}, drvegrdctl will not detect radarpub: {}",
+                args.can,
+                dir.display(),
+                err
             );
-            [
-                xyz[0],
-                xyz[1],
-                xyz[2],
-                target.speed as f32,
-                target.power as f32,
-                target.rcs as f32,
-                cluster,
-            ]
-        })
-        .flat_map(|elem| elem.to_ne_bytes())
-        .collect();
-    let fields = vec![
-        sensor_msgs::PointField {
-            name: String::from("x"),
-            offset: 0,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("y"),
-            offset: 4,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("z"),
-            offset: 8,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("speed"),
-            offset: 12,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("power"),
-            offset: 16,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("rcs"),
-            offset: 20,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-        sensor_msgs::PointField {
-            name: String::from("cluster_id"),
-            offset: 24,
-            datatype: PointFieldType::FLOAT32 as u8,
-            count: 1,
-        },
-    ];
+            Ok(None)
+        }
+    }
+}
 
-    let msg = sensor_msgs::PointCloud2 {
-        header: std_msgs::Header {
-            stamp: time,
-            frame_id,
-        },
-        height: 1,
-        width: targets.len() as u32,
-        fields,
-        is_bigendian: false,
-        point_step: 28,
-        row_step: 28 * targets.len() as u32,
-        data,
-        is_dense: true,
+/// Run the radar frame and cube pipelines without a Zenoh session for
+/// --dry-run, publishing to [`DiscardSink`]s and printing a summary of every
+/// frame and cube on stdout.
+async fn dry_run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let _can_lock = lock_can(&args)?;
+    let can = CanSocket::open(&args.can)?;
+    let uat = RecvResponseSettings {
+        max_retries: args.uat_max_retries,
+        read_timeout: Duration::from_millis(args.uat_timeout_ms),
+        ids: args.can_ids(),
     };
 
-    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
-    let enc = Encoding::APPLICATION_CDR.with_schema("sensor_msgs/msg/PointCloud2");
+    let device = read_device_info(&can, &uat).await?;
+    info!("dry run of {} {}", device.version(), device.serial_number);
 
-    Ok((msg, enc))
-}
+    if args.cube {
+        let args = args.clone();
+        let beamformed = (!args.beamforming_angles.is_empty()).then_some(DiscardSink);
+        let cube_channel = Arc::new(ByteGauge::new(args.cube_channel_max_bytes));
+        let cube_batch = Arc::new(Mutex::new(BatchMetrics::new()));
 
-async fn cube_loop(
-    session: Session,
-    topic: String,
-    frame_id: String,
-    tracy: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let cube_publisher = match session
-        .declare_publisher(&topic)
-        .priority(Priority::DataHigh)
-        .congestion_control(CongestionControl::Drop)
-        .await
-    {
-        Ok(v) => v,
-        Err(e) => {
-            error!("Failed to create publisher {}: {:?}", topic, e);
-            return Err(e);
+        thread::Builder::new()
+            .name("cube".to_string())
+            .spawn(move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+                    .block_on(cube_pipeline(
+                        DiscardSink,
+                        DiscardSink,
+                        beamformed,
+                        args,
+                        cube_channel,
+                        cube_batch,
+                    ))
+                    .unwrap();
+            })?;
+    }
+
+    let sinks = StreamSinks {
+        targets: DiscardSink,
+        latency: DiscardSink,
+        heatmap: args.heatmap.then_some(DiscardSink),
+        ground_plane: args.remove_ground.then_some(DiscardSink),
+        blockage: args.blockage_detect.then_some(DiscardSink),
+    };
+    let latency = Arc::new(Mutex::new(LatencyHistogram::new()));
+    stream(
+        can,
+        sinks,
+        args,
+        None,
+        None,
+        latency,
+        device.reports_accuracy(),
+    )
+    .await
+}
+
+/// Concise summary of a radar frame printed by --dry-run.
+fn format_frame_summary(header: &can::Header, targets: &[Target]) -> String {
+    if targets.is_empty() {
+        return format!("frame {}: 0 targets", header.cycle_counter);
+    }
+
+    let (min, max) = targets.iter().fold((f64::MAX, f64::MIN), |(min, max), t| {
+        (min.min(t.range), max.max(t.range))
+    });
+    let power = targets.iter().map(|t| t.power).sum::<f64>() / targets.len() as f64;
+    format!(
+        "frame {}: {} targets range {:.2}..{:.2} m mean power {:.1} dBm",
+        header.cycle_counter,
+        targets.len(),
+        min,
+        max,
+        power
+    )
+}
+
+/// Concise summary of a radar cube printed by --dry-run.
+fn format_cube_summary(cube: &RadarCube) -> String {
+    format!(
+        "cube {}: shape {:?} missing {} skipped {}",
+        cube.frame_counter,
+        cube.data.shape(),
+        cube.missing_data,
+        cube.packets_skipped
+    )
+}
+
+/// Write the radar parameters requested on the command line, or only read
+/// them with --read-only-params when the sensor is configured by another
+/// system.
+///
+/// # Returns
+/// The requested parameters and the values confirmed by the sensor, which
+/// are the values read back in read-only mode.
+async fn configure_parameters(
+    can: &(impl CanReader + CanWriter),
+    args: &Args,
+    uat: &RecvResponseSettings,
+) -> Result<(RadarParameters, RadarParameters), can::Error> {
+    let requested = RadarParameters {
+        center_frequency: args.center_frequency as u32,
+        frequency_sweep: args.frequency_sweep as u32,
+        range_toggle: args.range_toggle as u32,
+        detection_sensitivity: args.detection_sensitivity as u32,
+    };
+
+    if args.read_only_params {
+        let confirmed = read_parameters(can, uat).await?;
+        if confirmed != requested {
+            warn!(
+                "read-only radar parameters differ from the command line: requested {:?} actual {:?}",
+                requested, confirmed
+            );
         }
+        return Ok((requested, confirmed));
+    }
+
+    // Read the current values first so the save can be skipped when nothing
+    // changes, sparing the sensor's non-volatile memory.
+    let current = match args.save_params_to_sensor {
+        true => Some(read_parameters(can, uat).await?),
+        false => None,
     };
 
-    let (tx5, rx) = kanal::bounded_async(128);
-    let tx63 = tx5.clone();
+    let confirmed = write_parameters(can, &requested, uat).await?;
+    if confirmed != requested {
+        warn!(
+            "radar adjusted parameters: requested {:?} confirmed {:?}",
+            requested, confirmed
+        );
+    }
 
-    thread::Builder::new()
-        .name("port5".to_string())
-        .spawn(move || {
-            tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .unwrap()
-                .block_on(net::port5(tx5));
-        })?;
+    if let Some(current) = current {
+        if confirmed != requested {
+            warn!("radar parameters not saved to sensor as the writes were not confirmed");
+        } else if current == requested {
+            info!("radar parameters unchanged, skipping save to sensor");
+        } else {
+            match save_parameters(can, uat).await {
+                Ok(()) => info!("radar parameters saved to sensor: {:?}", requested),
+                Err(e) => error!("failed to save radar parameters to sensor: {}", e),
+            }
+        }
+    }
 
-    thread::Builder::new()
-        .name("port63".to_string())
-        .spawn(move || {
-            tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .unwrap()
-                .block_on(net::port63(tx63));
-        })?;
+    Ok((requested, confirmed))
+}
 
-    let mut reader = RadarCubeReader::default();
+/// Declare a publisher which drops messages when the network is congested
+/// and follows the session when the endpoints file changes.
+async fn declare_sink(
+    sessions: &SessionWatch,
+    topic: &str,
+    priority: Priority,
+) -> Result<ReconnectingPublisher, zenoh::Error> {
+    ReconnectingPublisher::declare(sessions.clone(), topic, priority).await
+}
 
-    loop {
-        let msg = match rx.recv().await {
-            Ok(msg) => msg,
+/// Publishers of the [`stream`] task.
+struct StreamSinks<S> {
+    targets: S,
+    latency: S,
+    heatmap: Option<S>,
+    ground_plane: Option<S>,
+    blockage: Option<S>,
+}
+
+impl StreamSinks<ReconnectingPublisher> {
+    async fn declare(sessions: &SessionWatch, args: &Args) -> Result<Self, zenoh::Error> {
+        Ok(StreamSinks {
+            targets: declare_sink(sessions, &args.targets_topic, Priority::DataHigh).await?,
+            latency: declare_sink(sessions, &args.latency_topic, Priority::Background).await?,
+            heatmap: match args.heatmap {
+                true => {
+                    Some(declare_sink(sessions, &args.heatmap_topic, Priority::Background).await?)
+                }
+                false => None,
+            },
+            ground_plane: match args.remove_ground {
+                true => Some(
+                    declare_sink(sessions, &args.ground_plane_topic, Priority::Background).await?,
+                ),
+                false => None,
+            },
+            blockage: match args.blockage_detect {
+                true => Some(
+                    declare_sink(sessions, &args.topic("blockage"), Priority::Background).await?,
+                ),
+                false => None,
+            },
+        })
+    }
+}
+
+async fn stream<S: Sink>(
+    can: impl CanReader,
+    sinks: StreamSinks<S>,
+    args: Args,
+    clustering: Option<AsyncSender<TargetFrame>>,
+    associations: Option<AsyncReceiver<TrackAssociation>>,
+    latency: Arc<Mutex<LatencyHistogram>>,
+    firmware_accuracy: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    set_thread_priority("stream", SchedPolicy::Fifo, args.rt_priority_can);
+    if let Some(cpus) = &args.cpu_affinity_can {
+        set_thread_affinity("stream", cpus);
+    }
+
+    let mut targets_breaker = PublishBreaker::new(
+        &args.targets_topic,
+        PUBLISH_FAILURE_THRESHOLD,
+        PUBLISH_PROBE_INTERVAL,
+    );
+
+    let antenna = match args
+        .antenna_pattern_file
+        .as_ref()
+        .filter(|path| !path.as_os_str().is_empty())
+    {
+        Some(path) => match AntennaPatternCorrection::load(path) {
+            Ok(antenna) => Some(antenna),
             Err(e) => {
-                error!("recv error: {:?}", e);
-                continue;
+                error!("antenna pattern {} error: {:?}", path.display(), e);
+                return Err(e.into());
             }
-        };
+        },
+        None => None,
+    };
 
-        let n_msg = msg.len() / SMS_PACKET_SIZE;
+    let mut unfolder = args.doppler_unfold.then(DopplerUnfolder::new);
 
-        event!(Level::TRACE, event = "port5", n_msg = n_msg);
+    let mut heatmap = args.heatmap.then(|| {
+        Heatmap::new(
+            &args.heatmap_extent,
+            args.heatmap_resolution,
+            args.heatmap_decay,
+            args.heatmap_interval,
+        )
+    });
 
-        for i in 0..n_msg {
-            let begin = i * SMS_PACKET_SIZE;
-            let end = begin + SMS_PACKET_SIZE;
-            let cubemsg = reader.read(&msg[begin..end]);
+    // Latest track association from the clustering task, always at least one
+    // radar cycle behind the frame being published.
+    let mut association: Option<TrackAssociation> = None;
 
-            match cubemsg {
-                Ok(Some(cubemsg)) => {
-                    tracy.then(|| {
-                        plot!("cube captured data", cubemsg.data.len() as f64);
-                        plot!("cube missing data", cubemsg.missing_data as f64);
+    let mut blockage = args.blockage_detect.then(|| BlockageMonitor::new(&args));
+
+    let mut frame_intervals = RollingStats::new(CAN_STATS_FRAMES);
+    let mut last_frame: Option<Instant> = None;
+    let mut frames = 0;
+    let ids = args.can_ids();
+
+    loop {
+        match read_message(&can, &ids).await {
+            Err(err) => error!("canbus error: {:?}", err),
+            Ok(mut frame) => {
+                let received = Instant::now();
+                if !firmware_accuracy {
+                    for target in frame.targets.iter_mut() {
+                        target.accuracy = 0;
+                    }
+                }
+                if let Some(last) = last_frame.replace(received) {
+                    frame_intervals.push(received.duration_since(last).as_secs_f64());
+                }
+                frames += 1;
+                if args.dry_run {
+                    let targets = &frame.targets[..frame.header.n_targets];
+                    println!("{}", format_frame_summary(&frame.header, targets));
+                }
+                if frames % CAN_STATS_FRAMES == 0 {
+                    if let Some((mean, min, max)) = frame_intervals.rate() {
+                        info!(
+                            "can frame rate: mean={:.1} min={:.1} max={:.1} Hz",
+                            mean, min, max
+                        );
+                        args.tracy.then(|| plot!("can fps", mean));
+                    }
+                }
+
+                let mut targets = &frame.targets[..frame.header.n_targets];
+                let remaining;
+                if args.remove_ground {
+                    let (ground, plane) = info_span!("ground_plane").in_scope(|| {
+                        ransac_ground_plane(targets, args.ground_iterations, args.ground_threshold)
                     });
+                    remaining = targets
+                        .iter()
+                        .zip(&ground)
+                        .filter_map(|(t, &ground)| (!ground).then_some(*t))
+                        .collect::<Vec<_>>();
+                    args.tracy
+                        .then(|| plot!("ground targets", (targets.len() - remaining.len()) as f64));
+                    targets = &remaining;
 
-                    if cubemsg.missing_data == 0 {
-                        let (msg, enc) = format_cube(cubemsg, &frame_id).unwrap();
-                        let span = info_span!("cube_publish");
-                        async {
-                            match cube_publisher.put(msg).encoding(enc).await {
-                                Ok(_) => {}
-                                Err(e) => error!("publish cube error: {:?}", e),
+                    if let (Some(plane), Some(sink)) = (plane, &sinks.ground_plane) {
+                        if frames % GROUND_PLANE_FRAMES == 0 {
+                            let (msg, enc) = format_ground_plane(plane, args.mirror)?;
+                            if let Err(e) = sink.publish(msg, enc).await {
+                                error!("{} publish error: {:?}", args.ground_plane_topic, e);
                             }
                         }
-                        .instrument(span)
-                        .await;
+                    }
+                }
+                args.tracy.then(|| plot!("targets", targets.len() as f64));
 
-                        tracy.then(|| secondary_frame_mark!("cube"));
-                    } else {
-                        warn!("dropping cube with {} missing data", cubemsg.missing_data);
+                if let Some(rx) = &associations {
+                    while let Ok(Some(latest)) = rx.try_recv() {
+                        association = Some(latest);
                     }
                 }
-                Ok(None) => (),
-                Err(err) => {
-                    error!("capture cube error: {}", err);
+
+                let unfolded = unfolder
+                    .as_mut()
+                    .map(|unfolder| unfolder.unfold(&frame.header, targets));
+
+                let annotation = associations.as_ref().map(|_| {
+                    annotate_targets(
+                        targets,
+                        association.as_ref(),
+                        frame.header.cycle_counter,
+                        args.mirror,
+                        &args.clustering_param_scale,
+                    )
+                });
+
+                if let Some(tx) = &clustering {
+                    tx.send(TargetFrame {
+                        header: frame.header,
+                        received_at: received,
+                        stamp: timestamp()?,
+                        targets: targets.to_vec(),
+                    })
+                    .await
+                    .unwrap();
+                }
+
+                if targets_breaker.ready() {
+                    let (msg, enc) = format_targets(
+                        targets,
+                        args.mirror,
+                        args.include_confidence,
+                        args.include_accuracy,
+                        unfolded.as_deref(),
+                        annotation.as_ref(),
+                        antenna.as_ref(),
+                        args.organised_grid(),
+                        &args.radar_frame_id,
+                    )?;
+
+                    let span = info_span!("targets_publish");
+                    async {
+                        if let Err(e) = targets_breaker.publish(&sinks.targets, msg, enc).await {
+                            error!("{} publish error: {:?}", args.targets_topic, e);
+                        }
+                    }
+                    .instrument(span)
+                    .await;
+                }
+
+                let histogram = {
+                    let mut latency = latency.lock().unwrap();
+                    latency.record(received.elapsed());
+                    (latency.count() % LATENCY_PUBLISH_FRAMES == 0).then(|| latency.to_json())
+                };
+
+                if let (Some(heatmap), Some(sink)) = (&mut heatmap, &sinks.heatmap) {
+                    publish_heatmap(heatmap, sink, targets, &args).await;
+                }
+
+                if let (Some(blockage), Some(sink)) = (&mut blockage, &sinks.blockage) {
+                    let targets = &frame.targets[..frame.header.n_targets];
+                    blockage.update(sink, targets, received, &args).await;
+                }
+
+                if let Some(histogram) = histogram {
+                    match sinks
+                        .latency
+                        .publish(ZBytes::from(histogram), Encoding::APPLICATION_JSON)
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(e) => error!("{} publish error: {:?}", args.latency_topic, e),
+                    }
                 }
+
+                args.tracy.then(frame_mark);
             }
         }
     }
 }
 
-#[instrument(skip_all, fields(shape = cubemsg.data.shape().iter().map(|s| s.to_string()).collect::<Vec<_>>().join(" ")))]
-fn format_cube(
-    cubemsg: RadarCube,
-    frame_id: &str,
+/// Serialize the ground plane `a*x + b*y + c*z + d = 0` as a Quaternion
+/// holding the unit normal in x, y, z and the distance term d in w.
+fn format_ground_plane(
+    plane: [f64; 4],
+    mirror: bool,
 ) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
-    let layout = vec![
-        edgefirst_msgs::radar_cube_dimension::SEQUENCE,
-        edgefirst_msgs::radar_cube_dimension::RANGE,
-        edgefirst_msgs::radar_cube_dimension::RXCHANNEL,
-        edgefirst_msgs::radar_cube_dimension::DOPPLER,
-    ];
+    let [a, b, c, d] = plane;
+    let msg = Quaternion {
+        x: a,
+        y: if mirror { -b } else { b },
+        z: c,
+        w: d,
+    };
 
-    // Double the final dimension to account for complex data.
-    let shape = cubemsg.data.shape();
-    let shape = vec![
-        shape[0] as u16,
-        shape[1] as u16,
-        shape[2] as u16,
-        shape[3] as u16 * 2,
-    ];
+    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("geometry_msgs/msg/Quaternion");
 
-    // Cast the Complex<i16> vector to a i16 vector.
-    let data = cubemsg.data.into_raw_vec_and_offset().0;
-    let data2 =
-        unsafe { Vec::from_raw_parts(data.as_ptr() as *mut i16, data.len() * 2, data.len() * 2) };
-    std::mem::forget(data);
+    Ok((msg, enc))
+}
 
-    let msg = edgefirst_msgs::RadarCube {
+/// Per-target track annotation for the raw targets point cloud.
+struct TargetAnnotation {
+    /// Cluster id of the track containing each target, 0 if none
+    track_ids: Vec<f32>,
+
+    /// Radar cycles since the association was computed, -1 if no association
+    /// has been received yet
+    age: f32,
+}
+
+/// Annotate the targets of the current frame with the cluster id of the
+/// tracked box they fall inside.  The association comes from a previous
+/// clustering cycle so the annotation is at least one radar cycle late.
+#[instrument(skip_all)]
+fn annotate_targets(
+    targets: &[Target],
+    association: Option<&TrackAssociation>,
+    cycle_counter: u32,
+    mirror: bool,
+    param_scale: &[f32],
+) -> TargetAnnotation {
+    let association = match association {
+        Some(association) => association,
+        None => {
+            return TargetAnnotation {
+                track_ids: vec![0.0; targets.len()],
+                age: -1.0,
+            }
+        }
+    };
+
+    let track_ids = targets
+        .iter()
+        .map(|t| {
+            let [x, y, _, _] = clustering_point(t, mirror, param_scale);
+            association.track_id(x, y) as f32
+        })
+        .collect();
+
+    TargetAnnotation {
+        track_ids,
+        age: association.age(cycle_counter) as f32,
+    }
+}
+
+/// Convert a target into the scaled [x, y, z, speed] clustering parameters.
+fn clustering_point(t: &Target, mirror: bool, param_scale: &[f32]) -> [f32; 4] {
+    let [x, y, z] = transform_xyz(t.range as f32, t.azimuth as f32, t.elevation as f32, mirror);
+
+    let mut v = [x, y, z, t.speed as f32];
+    for (i, val) in v.iter_mut().enumerate() {
+        *val *= param_scale[i];
+    }
+    v
+}
+
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip_all)]
+fn format_targets(
+    targets: &[Target],
+    mirror: bool,
+    include_confidence: bool,
+    include_accuracy: bool,
+    unfolded: Option<&[f64]>,
+    annotation: Option<&TargetAnnotation>,
+    antenna: Option<&AntennaPatternCorrection>,
+    organised: Option<(u32, u32, &[f32])>,
+    frame_id: &str,
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let n_targets = targets.len() as u32;
+    let layout = PointLayout::targets(
+        include_confidence,
+        include_accuracy,
+        unfolded.is_some(),
+        annotation.is_some(),
+    );
+    let point_step = layout.point_step;
+    let mut data = Vec::with_capacity((point_step * n_targets) as usize);
+    for (i, target) in targets.iter().enumerate() {
+        let xyz = transform_xyz(
+            target.range as f32,
+            target.azimuth as f32,
+            target.elevation as f32,
+            mirror,
+        );
+        let power = match antenna {
+            Some(antenna) => antenna.correct_power(target.power, target.azimuth),
+            None => target.power,
+        };
+        for elem in [
+            xyz[0],
+            xyz[1],
+            xyz[2],
+            target.speed as f32,
+            power as f32,
+            target.rcs as f32,
+        ] {
+            data.extend_from_slice(&elem.to_ne_bytes());
+        }
+        if include_confidence {
+            data.extend_from_slice(&target_confidence(target).to_ne_bytes());
+        }
+        if include_accuracy {
+            // Packed as FLOAT32 like every other field for ROS 2 tools.
+            data.extend_from_slice(&(target.accuracy as f32).to_ne_bytes());
+        }
+        if let Some(unfolded) = unfolded {
+            data.extend_from_slice(&(unfolded[i] as f32).to_ne_bytes());
+        }
+        if let Some(annotation) = annotation {
+            data.extend_from_slice(&annotation.track_ids[i].to_ne_bytes());
+            data.extend_from_slice(&annotation.age.to_ne_bytes());
+        }
+    }
+
+    let msg = sensor_msgs::PointCloud2 {
         header: std_msgs::Header {
             stamp: timestamp()?,
             frame_id: frame_id.to_string(),
         },
-        timestamp: cubemsg.timestamp,
-        layout,
-        shape,
-        scales: vec![
-            1.0,
-            cubemsg.bin_properties.range_per_bin,
-            1.0,
-            cubemsg.bin_properties.speed_per_bin,
-        ],
-        cube: data2,
-        is_complex: true,
+        height: 1,
+        width: n_targets,
+        fields: layout.point_fields(),
+        is_bigendian: false,
+        point_step,
+        row_step: point_step * n_targets,
+        data,
+        is_dense: true,
+    };
+    let msg = match organised {
+        Some((range_bins, azimuth_bins, extent)) => {
+            to_organised_pointcloud2(targets, msg, range_bins, azimuth_bins, extent, mirror)
+        }
+        None => msg,
     };
 
     let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
-    let enc = Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/RadarCube");
+    let enc = Encoding::APPLICATION_CDR.with_schema("sensor_msgs/msg/PointCloud2");
 
     Ok((msg, enc))
 }
 
-fn transform_xyz(range: f32, azimuth: f32, elevation: f32, mirror: bool) -> [f32; 3] {
-    let azi = azimuth / 180.0 * PI;
-    let ele = elevation / 180.0 * PI;
-    let x = range * ele.cos() * azi.cos();
-    let y = range * ele.cos() * azi.sin();
-    let z = range * ele.sin();
-    if mirror {
-        [x, -y, z]
-    } else {
-        [x, y, z]
+/// Schema message with the point layouts of the targets and, with
+/// clustering, the clusters topics.
+fn format_schema(args: &Args) -> serde_json::Value {
+    let targets = PointLayout::targets(
+        args.include_confidence,
+        args.include_accuracy,
+        args.doppler_unfold,
+        args.annotate_targets,
+    );
+    let clusters = PointLayout::clusters(args.include_confidence, args.cluster_roles);
+    let mut topics = vec![(args.targets_topic.as_str(), &targets)];
+    if args.clustering {
+        topics.push((args.clusters_topic.as_str(), &clusters));
     }
+    schema_json(&topics)
 }
 
-async fn tf_static(
-    session: Session,
-    msg: ZBytes,
-    enc: Encoding,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let topic = "rt/tf_static".to_string();
-    let mut interval = tokio::time::interval(Duration::from_secs(1));
+/// Reshape the unorganised cloud of the targets into an organised cloud of
+/// `range_bins` rows and `azimuth_bins` columns.
+///
+/// The `extent` is [range_min, range_max, azimuth_min, azimuth_max] in
+/// meters and degrees, the azimuth is mirrored with the y axis.  Each
+/// target fills the cell of its range and azimuth, the target with the
+/// highest power is kept when several share a cell and targets outside the
+/// extent are dropped.  Every field of an unoccupied cell is NaN so the
+/// cloud is not dense.
+fn to_organised_pointcloud2(
+    targets: &[Target],
+    cloud: sensor_msgs::PointCloud2,
+    range_bins: u32,
+    azimuth_bins: u32,
+    extent: &[f32],
+    mirror: bool,
+) -> sensor_msgs::PointCloud2 {
+    let step = cloud.point_step as usize;
+    let cells = (range_bins * azimuth_bins) as usize;
+    // Every target field is FLOAT32.
+    let mut data = f32::NAN.to_ne_bytes().repeat(cells * step / 4);
+    let mut power = vec![f64::NEG_INFINITY; cells];
 
-    loop {
-        interval.tick().await;
-        let span = info_span!("tf_static_publish");
-        async { session.put(&topic, msg.clone()).encoding(enc.clone()).await }
-            .instrument(span)
-            .await?;
+    let [range_min, range_max, azimuth_min, azimuth_max] =
+        [extent[0], extent[1], extent[2], extent[3]];
+    for (i, target) in targets.iter().enumerate() {
+        let azimuth = match mirror {
+            true => -target.azimuth as f32,
+            false => target.azimuth as f32,
+        };
+        let row = (target.range as f32 - range_min) / (range_max - range_min) * range_bins as f32;
+        let col = (azimuth - azimuth_min) / (azimuth_max - azimuth_min) * azimuth_bins as f32;
+        // Also rejects a NaN row or column.
+        if !((0.0..range_bins as f32).contains(&row) && (0.0..azimuth_bins as f32).contains(&col)) {
+            continue;
+        }
+        let cell = row as usize * azimuth_bins as usize + col as usize;
+        if target.power <= power[cell] {
+            continue;
+        }
+        power[cell] = target.power;
+        data[cell * step..][..step].copy_from_slice(&cloud.data[i * step..][..step]);
+    }
+
+    sensor_msgs::PointCloud2 {
+        height: range_bins,
+        width: azimuth_bins,
+        row_step: cloud.point_step * azimuth_bins,
+        data,
+        is_dense: false,
+        ..cloud
     }
 }
 
-async fn radar_info(
-    session: Session,
-    msg: ZBytes,
-    enc: Encoding,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let topic = "rt/radar/info".to_string();
-    let mut interval = tokio::time::interval(Duration::from_secs(1));
+/// Publishers of the [`clustering_task`].
+struct ClusteringSinks<S> {
+    clusters: S,
+    quality: S,
+    bev: Option<S>,
+    detect: Option<S>,
+    debug_dump: Option<S>,
+    track_events: Option<S>,
+}
 
-    loop {
-        interval.tick().await;
-        let span = info_span!("radar_info_publish");
-        async { session.put(&topic, msg.clone()).encoding(enc.clone()).await }
-            .instrument(span)
-            .await?;
+impl ClusteringSinks<ReconnectingPublisher> {
+    async fn declare(sessions: &SessionWatch, args: &Args) -> Result<Self, zenoh::Error> {
+        Ok(ClusteringSinks {
+            clusters: declare_sink(sessions, &args.clusters_topic, Priority::DataHigh).await?,
+            quality: declare_sink(sessions, &args.cluster_quality_topic, Priority::Background)
+                .await?,
+            bev: match args.bev {
+                true => Some(declare_sink(sessions, &args.bev_topic, Priority::DataHigh).await?),
+                false => None,
+            },
+            detect: match args.detect {
+                true => Some(declare_sink(sessions, &args.detect_topic, Priority::DataHigh).await?),
+                false => None,
+            },
+            debug_dump: match args.enable_cluster_debug_dump {
+                true => Some(
+                    declare_sink(sessions, &args.debug_dump_topic, Priority::Background).await?,
+                ),
+                false => None,
+            },
+            track_events: match args.track_events {
+                true => Some(
+                    declare_sink(sessions, &args.track_events_topic, Priority::Background).await?,
+                ),
+                false => None,
+            },
+        })
     }
 }
 
-fn timestamp() -> Result<builtin_interfaces::Time, std::io::Error> {
-    let mut tp = libc::timespec {
-        tv_sec: 0,
-        tv_nsec: 0,
+/// Publish the JSON summary of each completed track.
+async fn publish_track_events<S: Sink>(sink: &S, topic: &str, tracks: &[TrackRecord]) {
+    for record in tracks {
+        if let Err(e) = sink
+            .publish(
+                ZBytes::from(track_json(record).to_string()),
+                Encoding::APPLICATION_JSON,
+            )
+            .await
+        {
+            error!("{} publish error: {:?}", topic, e);
+        }
+    }
+}
+
+/// Append the completed tracks of the clustering to the track log.
+///
+/// # Returns
+/// The number of tracks written
+fn write_track_log(clustering: &mut Clustering, path: &Path) -> Result<usize, String> {
+    let tracks = clustering.drain_completed_tracks();
+    match append_tracks(path, &tracks) {
+        Ok(()) => {
+            info!(
+                "wrote {} tracks to {}, {} dropped since startup",
+                tracks.len(),
+                path.display(),
+                clustering.dropped_tracks()
+            );
+            Ok(tracks.len())
+        }
+        Err(e) => {
+            error!("{} write error: {:?}", path.display(), e);
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Save the tracks to the tracker state file, replacing the previous state
+/// at once.  The state records the wall clock time of the save, the frame
+/// stamps restart with the system.
+fn save_tracker_state(clustering: &Clustering, path: &Path) -> std::io::Result<()> {
+    let saved_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut state = clustering.tracker_state();
+    state["saved_at_ms"] = json!(saved_at.as_millis() as u64);
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, state.to_string())?;
+    std::fs::rename(&tmp, path)
+}
+
+/// The tracker state saved at `path`, None if missing, malformed or saved
+/// more than `max_age` ago.
+fn load_tracker_state(path: &Path, max_age: Duration) -> Option<serde_json::Value> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            warn!("tracker state {} error: {}", path.display(), err);
+            return None;
+        }
     };
-    let err = unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_RAW, &mut tp) };
-    if err != 0 {
-        return Err(std::io::Error::last_os_error());
+    let state: Option<serde_json::Value> = serde_json::from_str(&text).ok();
+    let Some(saved_at) = state
+        .as_ref()
+        .and_then(|state| state["saved_at_ms"].as_u64())
+    else {
+        warn!("ignoring malformed tracker state {}", path.display());
+        return None;
+    };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let age = now.saturating_sub(Duration::from_millis(saved_at));
+    if age > max_age {
+        info!(
+            "discarding tracker state {} saved {:.1} s ago",
+            path.display(),
+            age.as_secs_f64()
+        );
+        return None;
     }
+    state
+}
 
-    Ok(builtin_interfaces::Time {
-        sec: tp.tv_sec as i32,
-        nanosec: tp.tv_nsec as u32,
-    })
+/// Wait for the next track log request, never completes without a track
+/// log or once the queryable has exited.
+async fn next_track_log_request(
+    requests: Option<&AsyncReceiver<TrackLogRequest>>,
+) -> TrackLogRequest {
+    match requests {
+        Some(requests) => match requests.recv().await {
+            Ok(request) => request,
+            Err(_) => std::future::pending().await,
+        },
+        None => std::future::pending().await,
+    }
+}
+
+/// Write the track log for every query of the track log queryable and reply
+/// with the number of tracks written, or an error reply.
+async fn serve_track_log(
+    session: &Session,
+    topic: &str,
+    requests: AsyncSender<TrackLogRequest>,
+) -> Result<(), zenoh::Error> {
+    let queryable = session.declare_queryable(topic).await?;
+    while let Ok(query) = queryable.recv_async().await {
+        debug!("track log request");
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let written = match requests.send(tx).await {
+            Ok(()) => match tokio::time::timeout(TRACK_LOG_TIMEOUT, rx).await {
+                Ok(Ok(written)) => written,
+                Ok(Err(_)) => Err("clustering has exited".to_string()),
+                Err(_) => Err(format!(
+                    "no reply from clustering in {:?}",
+                    TRACK_LOG_TIMEOUT
+                )),
+            },
+            Err(_) => Err("clustering has exited".to_string()),
+        };
+
+        let reply = match written {
+            Ok(tracks) => {
+                query
+                    .reply(
+                        query.key_expr().clone(),
+                        json!({ "tracks": tracks }).to_string(),
+                    )
+                    .encoding(Encoding::APPLICATION_JSON)
+                    .await
+            }
+            Err(err) => {
+                query
+                    .reply_err(json!({ "error": err }).to_string())
+                    .encoding(Encoding::APPLICATION_JSON)
+                    .await
+            }
+        };
+        if let Err(err) = reply {
+            warn!("track log reply error: {}", err);
+        }
+    }
+    Ok(())
+}
+
+async fn clustering_task<S: Sink>(
+    sinks: ClusteringSinks<S>,
+    args: Args,
+    rx: AsyncReceiver<TargetFrame>,
+    associations: Option<AsyncSender<TrackAssociation>>,
+    metrics: Arc<Mutex<ClusteringMetrics>>,
+    track_log: Option<AsyncReceiver<TrackLogRequest>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    set_thread_priority("cluster", SchedPolicy::Fifo, args.rt_priority_can);
+    if let Some(cpus) = &args.cpu_affinity_cluster {
+        set_thread_affinity("cluster", cpus);
+    }
+
+    let mut breaker = PublishBreaker::new(
+        &args.clusters_topic,
+        PUBLISH_FAILURE_THRESHOLD,
+        PUBLISH_PROBE_INTERVAL,
+    );
+
+    // The single mode ignores the velocity gating.
+    let velocity_gated =
+        args.clustering_velocity_gated && args.clustering_mode != Some(ClusteringAlgorithm::Single);
+
+    let track_log_path = args.track_log_path().map(Path::to_path_buf);
+    let track_history = track_log_path.is_some() || args.track_events;
+
+    // Restored by the first clustering cycle, on the clock of its frame.
+    let tracker_state_path = args.tracker_state_file().map(Path::to_path_buf);
+    let mut tracker_state = tracker_state_path.as_deref().and_then(|path| {
+        load_tracker_state(path, Duration::from_secs_f32(args.tracker_state_max_age))
+    });
+
+    let window_limit = args.window_limit();
+    let mut window = VecDeque::<TargetFrame>::with_capacity(args.window_size);
+    let mut clustering = ClusteringBuilder::new()
+        .eps(args.clustering_eps)
+        .param_scale(&args.clustering_param_scale)
+        .point_limit(args.clustering_point_limit)
+        .threads(args.clustering_threads)
+        .parallel_sectors(args.clustering_parallel_sectors)
+        .mode(match (args.clustering_mode, velocity_gated) {
+            (Some(ClusteringAlgorithm::Single), _) => ClusteringMode::Single,
+            (_, true) => ClusteringMode::VelocityGated {
+                separation_threshold_mps: args.clustering_velocity_separation_mps,
+            },
+            (_, false) => ClusteringMode::Standard,
+        })
+        .nms_iou_threshold(args.clustering_nms_iou)
+        .min_cluster_size(args.track_min_cluster_size)
+        .track_min_hits(args.track_min_hits as usize)
+        .track_max_coast(args.track_max_coast)
+        .provisional_ids(args.track_provisional_ids)
+        .cluster_id_cooldown(Duration::from_secs_f32(args.cluster_id_cooldown))
+        .cluster_id_wrap(args.no_id_recycling.then_some(args.cluster_id_max as usize))
+        .track_history(match track_history {
+            true => args.track_history_decimation,
+            false => 0,
+        })
+        .track_history_max_points(args.track_history_max_points)
+        .track_history_max_tracks(args.track_history_max_tracks)
+        .build();
+
+    let mut bev = args.bev.then(|| {
+        BevGrid::new(
+            args.bev_size,
+            args.bev_resolution,
+            args.bev_decay,
+            &args.radar_tf_vec,
+            &args.radar_tf_quat,
+        )
+    });
+
+    let mut classifier = args
+        .classify
+        .then(|| Classifier::new(classifier_thresholds(&args), args.classifier_hysteresis));
+
+    let mut history = sinks
+        .debug_dump
+        .is_some()
+        .then(|| FrameHistory::new(DEBUG_DUMP_FRAMES));
+    let mut last_clusters: Option<usize> = None;
+
+    let mut last_time: Option<u64> = None;
+    let mut last_summaries: Option<(u64, Vec<ClusterSummary>)> = None;
+
+    loop {
+        let frame = tokio::select! {
+            frame = rx.recv() => match frame {
+                Ok(frame) => frame,
+                // The stream task has exited once the channel is closed.
+                Err(_) => {
+                    if let Some(path) = &tracker_state_path {
+                        match save_tracker_state(&clustering, path) {
+                            Ok(()) => info!(
+                                "saved {} tracks to {}",
+                                clustering.tracklet_count(),
+                                path.display()
+                            ),
+                            Err(e) => error!("{} write error: {:?}", path.display(), e),
+                        }
+                    }
+                    if let Some(path) = &track_log_path {
+                        clustering.complete_live_tracks();
+                        if let Some(sink) = &sinks.track_events {
+                            publish_track_events(
+                                sink,
+                                &args.track_events_topic,
+                                clustering.ended_tracks(),
+                            )
+                            .await;
+                        }
+                        let _ = write_track_log(&mut clustering, path);
+                    }
+                    return Ok(());
+                }
+            },
+            reply = next_track_log_request(track_log.as_ref()) => {
+                let written = match &track_log_path {
+                    Some(path) => write_track_log(&mut clustering, path),
+                    None => Err("no track log".to_string()),
+                };
+                let _ = reply.send(written);
+                continue;
+            }
+        };
+        let start = Instant::now();
+        let (header, received) = (frame.header, frame.received_at);
+        // The clusters carry the stamp of the newest frame in the window.
+        let time = Time {
+            sec: frame.stamp.sec,
+            nanosec: frame.stamp.nanosec,
+        };
+
+        if let (Some(state), Some(path)) = (tracker_state.take(), &tracker_state_path) {
+            // The saved tracks continue as if clustered on the previous cycle.
+            let last = time
+                .to_nanos()
+                .saturating_sub(RADAR_CYCLE.as_nanos() as u64);
+            match clustering.restore_tracker_state(&state, last) {
+                true => info!(
+                    "restored {} tracks from {}",
+                    clustering.tracklet_count(),
+                    path.display()
+                ),
+                false => warn!("ignoring malformed tracker state {}", path.display()),
+            }
+        }
+
+        if let Some(last) = last_time.replace(time.to_nanos()) {
+            let gap = time.to_nanos().saturating_sub(last);
+            let missed = missed_frames(gap, RADAR_CYCLE.as_nanos() as u64);
+            if missed > 0 {
+                debug!("advancing tracks over {} missed frames", missed);
+                clustering.advance_missing_frames(missed);
+            }
+
+            // The frames before a pause of the stream, such as a sensor
+            // reset, would be clustered into ghosts at their old positions.
+            // The tracks have already been advanced over the pause.
+            if gap > WINDOW_FLUSH_GAP.as_nanos() as u64 && !window.is_empty() {
+                info!(
+                    "flushed {} frames from the clustering window after a {:?} pause of the radar frames",
+                    window.len(),
+                    Duration::from_nanos(gap)
+                );
+                window.clear();
+            }
+        }
+
+        // The range resolution, and with it the point spacing, follows the
+        // frequency sweep so the eps of a range toggled frame is scaled.
+        let eps = args.sweep_eps(header.frequency_sweep);
+        if eps != clustering.eps() {
+            debug!(
+                "clustering eps {} for frequency sweep {}",
+                eps,
+                parameter_name::<FrequencySweep>(header.frequency_sweep as u32)
+            );
+            clustering.set_eps(eps);
+        }
+
+        if let Some(history) = &mut history {
+            history.push(time.to_nanos(), frame.targets.clone());
+        }
+
+        let (targets, clusters, roles, quality, n_clusters, evicted) = info_span!("clustering")
+            .in_scope(|| {
+                push_window(&mut window, frame, window_limit);
+                let evicted = args
+                    .window_max_points
+                    .map_or(0, |max_points| evict_window(&mut window, max_points));
+
+                let targets = window
+                    .iter()
+                    .flat_map(|frame| frame.targets.iter())
+                    .collect::<Vec<_>>();
+                let dbscantargets = clustering.map(&targets, |t| {
+                    let mut v = clustering_point(t, args.mirror, &args.clustering_param_scale);
+                    if velocity_gated {
+                        // The velocity bands are in m/s, the clustering still
+                        // applies the speed scale before DBSCAN.
+                        v[3] = t.speed as f32;
+                    }
+                    v
+                });
+                let output = clustering.cluster(dbscantargets, time.to_nanos());
+
+                // Measure the quality in the scaled clustering space, the gated
+                // mode passes the speed unscaled.
+                let speed_scale = match velocity_gated {
+                    true => args.clustering_param_scale[3],
+                    false => 1.0,
+                };
+                let (points, labels): (Vec<_>, Vec<_>) = output
+                    .iter()
+                    .map(|v| ([v[0], v[1], v[2], v[3] * speed_scale], v[4] as usize))
+                    .unzip();
+                let n_clusters = labels
+                    .iter()
+                    .filter(|id| **id != 0)
+                    .collect::<HashSet<_>>()
+                    .len();
+                let quality = json!({
+                    "davies_bouldin_index": davies_bouldin_index(&points, &labels),
+                    "clusters": n_clusters,
+                    "eps": eps,
+                    "window_frames": window.len(),
+                    "window_ms": window_span(&window).as_secs_f64() * 1000.0,
+                });
+
+                let clusters: Vec<f32> = output.into_iter().map(|v| v[4]).collect();
+                let roles = args
+                    .cluster_roles
+                    .then(|| clustering.point_roles().to_vec());
+                (targets, clusters, roles, quality, n_clusters, evicted)
+            });
+        if evicted > 0 {
+            debug!(
+                "evicted {} frames from the clustering window over {} points",
+                evicted,
+                args.window_max_points.unwrap_or_default()
+            );
+        }
+
+        if let Err(e) = sinks
+            .quality
+            .publish(
+                ZBytes::from(quality.to_string()),
+                Encoding::APPLICATION_JSON,
+            )
+            .await
+        {
+            error!("{} publish error: {:?}", args.cluster_quality_topic, e);
+        }
+
+        let previous = last_clusters.replace(n_clusters);
+        if let (Some(history), Some(debug_sink), Some(previous)) =
+            (&history, &sinks.debug_dump, previous)
+        {
+            if let Some(reason) = cluster_count_anomaly(previous, n_clusters) {
+                warn!(
+                    "{} from {} to {}, publishing the last {} frames on {}",
+                    reason, previous, n_clusters, DEBUG_DUMP_FRAMES, args.debug_dump_topic
+                );
+                let dump = debug_dump(history, DEBUG_DUMP_FRAMES, reason, previous, n_clusters);
+                if let Err(e) = debug_sink
+                    .publish(ZBytes::from(dump.to_string()), Encoding::APPLICATION_JSON)
+                    .await
+                {
+                    error!("{} publish error: {:?}", args.debug_dump_topic, e);
+                }
+            }
+        }
+
+        let summaries = (args.cluster_max_acceleration_mps2.is_finite()
+            || args.detect
+            || args.classify
+            || track_history)
+            .then(|| {
+                cluster_summaries(targets.iter().zip(clusters.iter()).map(|(t, id)| {
+                    (
+                        transform_xyz(
+                            t.range as f32,
+                            t.azimuth as f32,
+                            t.elevation as f32,
+                            args.mirror,
+                        ),
+                        t.speed as f32,
+                        t.rcs as f32,
+                        *id as usize,
+                    )
+                }))
+            })
+            .unwrap_or_default();
+
+        let tracks = match args.detect || args.classify || track_history {
+            true => clustering.tracks(),
+            false => Vec::new(),
+        };
+        let classes = classifier.as_mut().map(|classifier| {
+            classifier.classify_tracks(&tracks, &summaries, &args.clustering_param_scale)
+        });
+
+        clustering.record_history(time.to_nanos(), &tracks, &summaries, classes.as_deref());
+        if let Some(sink) = &sinks.track_events {
+            publish_track_events(sink, &args.track_events_topic, clustering.ended_tracks()).await;
+        }
+
+        if let Some(detect_sink) = &sinks.detect {
+            let (msg, enc) = info_span!("detect").in_scope(|| {
+                format_detect(
+                    Time {
+                        sec: time.sec,
+                        nanosec: time.nanosec,
+                    },
+                    &tracks,
+                    &summaries,
+                    classes.as_deref(),
+                    &args,
+                )
+            })?;
+            if let Err(e) = detect_sink.publish(msg, enc).await {
+                error!("{} publish error: {:?}", args.detect_topic, e);
+            }
+        }
+
+        if args.cluster_max_acceleration_mps2.is_finite() {
+            if let Some((last, prev)) = &last_summaries {
+                let dt = time.to_nanos().saturating_sub(*last) as f32 * 1e-9;
+                // Cluster ids are kept by the tracker, a new id has no
+                // previous velocity to compare against.
+                for (curr, i) in summaries.iter().filter_map(|curr| {
+                    let i = prev.binary_search_by_key(&curr.id, |s| s.id).ok()?;
+                    Some((curr, i))
+                }) {
+                    if dt > 0.0
+                        && !velocity_coherence_check(
+                            &prev[i],
+                            curr,
+                            dt,
+                            args.cluster_max_acceleration_mps2,
+                        )
+                    {
+                        warn!(
+                            "cluster {} velocity changed from {:.2} to {:.2} m/s, {:.1} m/s² exceeds {} m/s²",
+                            curr.id,
+                            prev[i].velocity,
+                            curr.velocity,
+                            (curr.velocity - prev[i].velocity).abs() / dt,
+                            args.cluster_max_acceleration_mps2
+                        );
+                    }
+                }
+            }
+            last_summaries = Some((time.to_nanos(), summaries));
+        }
+
+        if let Some(tx) = &associations {
+            // Never block clustering on the stream task, a dropped association
+            // is replaced by the next cycle.
+            let _ = tx.try_send(clustering.associate(header.cycle_counter));
+        }
+
+        if let (Some(bev), Some(bev_sink)) = (&mut bev, &sinks.bev) {
+            let bev_time = Time {
+                sec: time.sec,
+                nanosec: time.nanosec,
+            };
+
+            let (msg, enc) = info_span!("bev").in_scope(|| {
+                bev.decay(time.to_nanos());
+                bev.accumulate(
+                    targets
+                        .iter()
+                        .zip(clusters.iter())
+                        .filter(|(_, cluster)| match args.bev_source {
+                            BevSource::Clusters => **cluster != 0.0,
+                            BevSource::Targets => true,
+                        })
+                        .map(|(t, _)| {
+                            transform_xyz(
+                                t.range as f32,
+                                t.azimuth as f32,
+                                t.elevation as f32,
+                                args.mirror,
+                            )
+                        }),
+                );
+                format_bev(bev_time, bev, &args.base_frame_id)
+            })?;
+
+            let span = info_span!("bev_publish");
+            async {
+                match bev_sink.publish(msg, enc).await {
+                    Ok(_) => {}
+                    Err(e) => error!("{} message error: {:?}", args.bev_topic, e),
+                }
+            }
+            .instrument(span)
+            .await;
+        }
+
+        if breaker.ready() {
+            let (msg, enc) = format_clusters(
+                time,
+                &targets,
+                clusters.into_iter(),
+                roles.as_deref(),
+                args.mirror,
+                args.include_confidence,
+                args.radar_frame_id.clone(),
+            )?;
+
+            let span = info_span!("clusters_publish");
+            async {
+                if let Err(e) = breaker.publish(&sinks.clusters, msg, enc).await {
+                    error!("{} message error: {:?}", args.clusters_topic, e);
+                }
+            }
+            .instrument(span)
+            .await;
+        }
+
+        let runtime = clustering.runtime();
+        let cycle = ClusteringCycle {
+            points: targets.len(),
+            clusters: n_clusters,
+            tracklets: clustering.tracklet_count(),
+            dbscan: runtime.dbscan,
+            tracker: runtime.tracker,
+            total: start.elapsed(),
+            backlog: rx.len(),
+            receipt_to_publish: received.elapsed(),
+            evicted,
+        };
+        {
+            let mut metrics = metrics.lock().unwrap();
+            metrics.record(cycle);
+            if metrics.cycles() % CLUSTERING_METRICS_CYCLES == 0 {
+                debug!("{}", metrics);
+            }
+        }
+
+        args.tracy.then(|| secondary_frame_mark!("clustering"));
+    }
+}
+
+/// Publish the Davies-Bouldin index of every eps of the sweep for each
+/// clustering window, the clusters themselves are not tracked or published.
+async fn eps_sweep_task<S: Sink>(
+    sink: S,
+    args: Args,
+    eps: Vec<f64>,
+    rx: AsyncReceiver<TargetFrame>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "clustering eps sweep from {} to {} in {} steps",
+        eps[0],
+        eps[eps.len() - 1],
+        eps.len()
+    );
+
+    let clustering = ClusteringBuilder::new()
+        .param_scale(&args.clustering_param_scale)
+        .point_limit(args.clustering_point_limit)
+        .threads(args.clustering_threads)
+        .parallel_sectors(args.clustering_parallel_sectors)
+        .build();
+    let window_limit = args.window_limit();
+    let mut window = VecDeque::<TargetFrame>::with_capacity(args.window_size);
+
+    loop {
+        let frame = match rx.recv().await {
+            Ok(frame) => frame,
+            Err(_) => return Ok(()),
+        };
+        push_window(&mut window, frame, window_limit);
+
+        let (msg, enc) = info_span!("eps_sweep").in_scope(|| {
+            let points: Vec<_> = window
+                .iter()
+                .flat_map(|frame| frame.targets.iter())
+                .map(|t| clustering_point(t, args.mirror, &args.clustering_param_scale))
+                .collect();
+            format_eps_sweep(&clustering.eps_sweep(&points, &eps))
+        })?;
+
+        if let Err(e) = sink.publish(msg, enc).await {
+            error!(
+                "{} publish error: {:?}",
+                args.cluster_quality_sweep_topic, e
+            );
+        }
+    }
+}
+
+/// Append the newest frame to the clustering window and evict the frames
+/// beyond the window limit.  A duration limit evicts the frames received
+/// longer than the duration before the newest frame, so the span of the
+/// window does not change with the radar cycle duration.
+fn push_window(window: &mut VecDeque<TargetFrame>, frame: TargetFrame, limit: WindowLimit) {
+    window.push_back(frame);
+    match limit {
+        WindowLimit::Frames(frames) => {
+            while window.len() > frames.max(1) {
+                window.pop_front();
+            }
+        }
+        WindowLimit::Duration(duration) => {
+            if let Some(newest) = window.back().map(|frame| frame.received_at) {
+                while window
+                    .front()
+                    .is_some_and(|oldest| newest.duration_since(oldest.received_at) > duration)
+                {
+                    window.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Time between the reception of the oldest and the newest frame of the
+/// clustering window.
+fn window_span(window: &VecDeque<TargetFrame>) -> Duration {
+    match (window.front(), window.back()) {
+        (Some(oldest), Some(newest)) => newest.received_at.duration_since(oldest.received_at),
+        _ => Duration::ZERO,
+    }
+}
+
+/// Evict the oldest frames of the clustering window while it holds more than
+/// `max_points` targets, the newest frame is always kept.
+///
+/// # Returns
+/// The number of evicted frames
+fn evict_window(window: &mut VecDeque<TargetFrame>, max_points: usize) -> usize {
+    let mut points: usize = window.iter().map(|frame| frame.targets.len()).sum();
+    let mut evicted = 0;
+    while points > max_points && window.len() > 1 {
+        points -= window.pop_front().map_or(0, |frame| frame.targets.len());
+        evicted += 1;
+    }
+    evicted
+}
+
+/// Format the eps sweep as a std_msgs/Float32MultiArray with a single eps
+/// dimension.
+fn format_eps_sweep(indices: &[f32]) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let n = indices.len() as u32;
+    // MultiArrayLayout of (label, size, stride) dimensions and data_offset.
+    let layout = (vec![(String::from("eps"), n, n)], 0u32);
+    let msg = ZBytes::from(serde_cdr::serialize(&(layout, indices))?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("std_msgs/msg/Float32MultiArray");
+
+    Ok((msg, enc))
+}
+
+/// Sample the thread CPU utilisation every second and publish it.
+async fn cpu_monitor<S: Sink>(
+    sink: S,
+    topic: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut monitor = CpuMonitor::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+        let (msg, enc) = format_cpu_usage(&monitor.sample())?;
+        if let Err(e) = sink.publish(msg, enc).await {
+            error!("{} publish error: {:?}", topic, e);
+        }
+    }
+}
+
+/// Format the thread utilisation in percent as a std_msgs/Float32MultiArray
+/// with a single dimension labelled with the comma separated thread names.
+fn format_cpu_usage(
+    usage: &[ThreadUsage],
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error + Send + Sync>> {
+    let n = usage.len() as u32;
+    let names: Vec<_> = usage.iter().map(|thread| thread.name.as_str()).collect();
+    let percent: Vec<_> = usage.iter().map(|thread| thread.percent).collect();
+    let layout = (vec![(names.join(","), n, n)], 0u32);
+    let msg = ZBytes::from(serde_cdr::serialize(&(layout, percent))?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("std_msgs/msg/Float32MultiArray");
+
+    Ok((msg, enc))
+}
+
+#[instrument(skip_all)]
+fn format_clusters<T: Iterator<Item = f32>>(
+    time: Time,
+    targets: &[&Target],
+    clusters: T,
+    roles: Option<&[PointRole]>,
+    mirror: bool,
+    include_confidence: bool,
+    frame_id: String,
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let n_targets = targets.len() as u32;
+    let layout = PointLayout::clusters(include_confidence, roles.is_some());
+    let point_step = layout.point_step;
+    let mut data = Vec::with_capacity((point_step * n_targets) as usize);
+    for (i, (target, cluster)) in targets.iter().zip(clusters).enumerate() {
+        let xyz = transform_xyz(
+            target.range as f32,
+            target.azimuth as f32,
+            target.elevation as f32,
+            mirror,
+        );
+        for elem in [
+            xyz[0],
+            xyz[1],
+            xyz[2],
+            target.speed as f32,
+            target.power as f32,
+            target.rcs as f32,
+        ] {
+            data.extend_from_slice(&elem.to_ne_bytes());
+        }
+        if include_confidence {
+            data.extend_from_slice(&target_confidence(target).to_ne_bytes());
+        }
+        data.extend_from_slice(&cluster.to_ne_bytes());
+        if let Some(roles) = roles {
+            data.extend_from_slice(&[roles[i] as u8, 0, 0, 0]);
+        }
+    }
+
+    let msg = sensor_msgs::PointCloud2 {
+        header: std_msgs::Header {
+            stamp: time,
+            frame_id,
+        },
+        height: 1,
+        width: n_targets,
+        fields: layout.point_fields(),
+        is_bigendian: false,
+        point_step,
+        row_step: point_step * n_targets,
+        data,
+        is_dense: true,
+    };
+
+    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("sensor_msgs/msg/PointCloud2");
+
+    Ok((msg, enc))
+}
+
+/// Classifier thresholds from the classifier arguments.
+fn classifier_thresholds(args: &Args) -> ClassifierThresholds {
+    ClassifierThresholds {
+        min_points: args.classifier_min_points,
+        pedestrian_max_speed: args.classifier_pedestrian_max_speed,
+        pedestrian_max_extent: args.classifier_pedestrian_max_extent,
+        pedestrian_max_rcs: args.classifier_pedestrian_max_rcs,
+        vehicle_min_speed: args.classifier_vehicle_min_speed,
+        vehicle_min_extent: args.classifier_vehicle_min_extent,
+        vehicle_min_rcs: args.classifier_vehicle_min_rcs,
+    }
+}
+
+/// Label of a Detect box, three labels split the boxes by the mean radial
+/// speed of the cluster into approaching, stationary and receding.
+fn detect_label<'a>(labels: &'a [String], speed: f32, separation: f32) -> &'a str {
+    match labels {
+        [approaching, stationary, receding] => match speed {
+            s if s < -separation => approaching,
+            s if s > separation => receding,
+            _ => stationary,
+        },
+        [label, ..] => label,
+        [] => "",
+    }
+}
+
+/// Format the tracked clusters as an edgefirst_msgs/Detect message.
+///
+/// The boxes are in meters of the radar frame rather than normalized image
+/// coordinates, the center and size are taken from the predicted track box
+/// with the clustering scale removed.  The distance and speed are the
+/// centroid range and mean radial speed of the cluster points, a track
+/// without points in this cycle keeps its box with the distance of the box
+/// center and no speed.  With classes the label is the class of the track
+/// and the score is weighted by the class confidence.
+#[instrument(skip_all)]
+fn format_detect(
+    time: Time,
+    tracks: &[ClusterTrack],
+    summaries: &[ClusterSummary],
+    classes: Option<&[(ObjectClass, f32)]>,
+    args: &Args,
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let boxes = tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| {
+            let [xmin, ymin, xmax, ymax] = track.bbox;
+            let (sx, sy) = (
+                args.clustering_param_scale[0],
+                args.clustering_param_scale[1],
+            );
+            let (center_x, center_y) = ((xmin + xmax) / (2.0 * sx), (ymin + ymax) / (2.0 * sy));
+            let summary = summaries
+                .binary_search_by_key(&track.cluster_id, |s| s.id)
+                .ok()
+                .map(|i| summaries[i])
+                .unwrap_or_default();
+            let distance = match summary.points {
+                0 => center_x.hypot(center_y),
+                _ => summary.centroid.iter().map(|c| c * c).sum::<f32>().sqrt(),
+            };
+
+            let score = detection_score(summary.points, track.count);
+            let (label, score) = match classes {
+                Some(classes) => (classes[i].0.label(), score * classes[i].1),
+                None => (
+                    detect_label(
+                        &args.detect_labels,
+                        summary.velocity,
+                        args.clustering_velocity_separation_mps,
+                    ),
+                    score,
+                ),
+            };
+
+            edgefirst_msgs::DetectBox2D {
+                center_x,
+                center_y,
+                width: (xmax - xmin) / sx,
+                height: (ymax - ymin) / sy,
+                label: label.to_string(),
+                score,
+                distance,
+                speed: summary.velocity,
+                track: edgefirst_msgs::DetectTrack {
+                    id: track.uuid.to_string(),
+                    lifetime: track.count,
+                    created: Time {
+                        sec: (track.created / 1_000_000_000) as i32,
+                        nanosec: (track.created % 1_000_000_000) as u32,
+                    },
+                },
+            }
+        })
+        .collect();
+
+    let msg = edgefirst_msgs::Detect {
+        header: std_msgs::Header {
+            stamp: Time {
+                sec: time.sec,
+                nanosec: time.nanosec,
+            },
+            frame_id: args.radar_frame_id.clone(),
+        },
+        input_timestamp: Time {
+            sec: time.sec,
+            nanosec: time.nanosec,
+        },
+        model_time: Time { sec: 0, nanosec: 0 },
+        output_time: time,
+        boxes,
+    };
+
+    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/Detect");
+
+    Ok((msg, enc))
+}
+
+#[instrument(skip_all)]
+fn format_bev(
+    time: Time,
+    bev: &BevGrid,
+    frame_id: &str,
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let cells = bev.cells() as u32;
+    format_mono8(time, cells, cells, bev.to_mono8(), frame_id)
+}
+
+/// Blockage detector of the stream task and the times of its last
+/// publication and save.
+struct BlockageMonitor {
+    detector: BlockageDetector,
+    published: Option<Instant>,
+    saved: Instant,
+}
+
+impl BlockageMonitor {
+    /// Create the detector, continuing from the saved baseline if any.
+    fn new(args: &Args) -> Self {
+        let settings = args.blockage_settings();
+        let detector = match args.blockage_state_file() {
+            Some(path) => BlockageDetector::load(settings, path),
+            None => BlockageDetector::new(settings, None),
+        };
+        BlockageMonitor {
+            detector,
+            published: None,
+            saved: Instant::now(),
+        }
+    }
+
+    /// Update the detector with the targets of a frame, publish its state
+    /// when it changes or once per [`BLOCKAGE_PUBLISH_INTERVAL`] and save the
+    /// baseline once per [`BLOCKAGE_SAVE_INTERVAL`].
+    async fn update(&mut self, sink: &impl Sink, targets: &[Target], now: Instant, args: &Args) {
+        let was_blocked = self.detector.blocked();
+        let blocked = self
+            .detector
+            .update(&FrameStats::from_targets(targets), now);
+
+        let due = match self.published {
+            Some(last) => now.duration_since(last) >= BLOCKAGE_PUBLISH_INTERVAL,
+            None => true,
+        };
+        if due || blocked != was_blocked {
+            self.published = Some(now);
+            let msg = ZBytes::from(self.detector.to_json().to_string());
+            if let Err(e) = sink.publish(msg, Encoding::APPLICATION_JSON).await {
+                error!("{} publish error: {:?}", args.topic("blockage"), e);
+            }
+        }
+
+        if let Some(path) = args.blockage_state_file() {
+            if now.duration_since(self.saved) >= BLOCKAGE_SAVE_INTERVAL {
+                self.saved = now;
+                if let Err(e) = self.detector.save(path) {
+                    warn!("blockage state {} error: {:?}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Accumulate the published targets into the heatmap and publish it once
+/// per heatmap interval.
+async fn publish_heatmap(heatmap: &mut Heatmap, sink: &impl Sink, targets: &[Target], args: &Args) {
+    let time = timestamp().unwrap_or(Time { sec: 0, nanosec: 0 });
+    let now = time.to_nanos();
+
+    heatmap.decay(now);
+    heatmap.accumulate(targets.iter().map(|t| {
+        let [x, y, _] = transform_xyz(
+            t.range as f32,
+            t.azimuth as f32,
+            t.elevation as f32,
+            args.mirror,
+        );
+        [x, y]
+    }));
+
+    if !heatmap.publish_due(now) {
+        return;
+    }
+
+    debug!(
+        "heatmap publish, {} targets out of range",
+        heatmap.out_of_range()
+    );
+
+    if let Some(path) = args
+        .heatmap_png
+        .clone()
+        .filter(|path| !path.as_os_str().is_empty())
+    {
+        // The file write must not hold up the CAN frames.
+        let png = heatmap.to_png();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = std::fs::write(&path, png) {
+                warn!("heatmap png {} error: {:?}", path.display(), e);
+            }
+        });
+    }
+
+    let (rows, cols) = (heatmap.rows() as u32, heatmap.cols() as u32);
+    let (msg, enc) = match format_mono8(time, cols, rows, heatmap.to_mono8(), &args.radar_frame_id)
+    {
+        Ok(msg) => msg,
+        Err(e) => {
+            error!("heatmap format error: {:?}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = sink.publish(msg, enc).await {
+        error!("{} publish error: {:?}", args.heatmap_topic, e);
+    }
+}
+
+fn format_mono8(
+    time: Time,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    frame_id: &str,
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let msg = sensor_msgs::Image {
+        header: std_msgs::Header {
+            stamp: time,
+            frame_id: frame_id.to_string(),
+        },
+        height,
+        width,
+        encoding: String::from("mono8"),
+        is_bigendian: 0,
+        step: width,
+        data,
+    };
+
+    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("sensor_msgs/msg/Image");
+
+    Ok((msg, enc))
+}
+
+/// Publishers of the [`cube_task`].
+struct CubeSinks<S> {
+    cube: S,
+    stats: S,
+    beamformed: Option<S>,
+
+    /// Writes the cubes to disk instead of publishing them on `cube`
+    file: Option<CubeWriter>,
+}
+
+async fn cube_loop(
+    sessions: SessionWatch,
+    args: Args,
+    cube_channel: Arc<ByteGauge>,
+    cube_batch: Arc<Mutex<BatchMetrics>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cube = match declare_sink(&sessions, &args.cube_topic, Priority::DataHigh).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed to create publisher {}: {:?}", args.cube_topic, e);
+            return Err(e);
+        }
+    };
+
+    let stats = match declare_sink(&sessions, &args.cube_stats_topic, Priority::Background).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!(
+                "Failed to create publisher {}: {:?}",
+                args.cube_stats_topic, e
+            );
+            return Err(e);
+        }
+    };
+
+    let beamformed = match args.beamforming_angles.is_empty() {
+        true => None,
+        false => match declare_sink(&sessions, &args.beamformed_topic, Priority::DataHigh).await {
+            Ok(v) => Some(v),
+            Err(e) => {
+                error!(
+                    "Failed to create publisher {}: {:?}",
+                    args.beamformed_topic, e
+                );
+                return Err(e);
+            }
+        },
+    };
+
+    cube_pipeline(cube, stats, beamformed, args, cube_channel, cube_batch).await
+}
+
+/// Receive the cube packets on the UDP ports and publish the assembled cubes
+/// to the sinks, or write them to disk with the npy and raw cube sinks.  The
+/// packets wait for the cube task in a channel capped at the bytes of the
+/// `cube_channel` gauge.
+async fn cube_pipeline<S: Sink>(
+    cube: S,
+    stats: S,
+    beamformed: Option<S>,
+    args: Args,
+    cube_channel: Arc<ByteGauge>,
+    cube_batch: Arc<Mutex<BatchMetrics>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The port threads spawned below inherit the scheduler and cores of the
+    // cube thread, port5 then sets its own priority.
+    set_thread_priority("cube", SchedPolicy::Fifo, args.rt_priority_udp);
+    if let Some(cpus) = &args.cpu_affinity_cube {
+        set_thread_affinity("cube", cpus);
+    }
+
+    let format = match args.cube_sink {
+        CubeSink::Zenoh => None,
+        CubeSink::Npy => Some(CubeFileFormat::Npy),
+        CubeSink::Raw => Some(CubeFileFormat::Raw),
+    };
+    let file = match format {
+        Some(format) => match CubeWriter::with_rotation(
+            format,
+            &args.cube_sink_path,
+            CUBE_WRITER_QUEUE,
+            args.log_max_size_mb
+                .map_or(u64::MAX, |mb| (mb * (1 << 20) as f64).max(1.0) as u64),
+            args.log_max_files,
+        ) {
+            Ok(writer) => Some(writer),
+            Err(e) => {
+                error!("cube sink {} error: {:?}", args.cube_sink_path.display(), e);
+                return Err(e.into());
+            }
+        },
+        None => None,
+    };
+
+    let (tx5, rx) = byte_channel(128, cube_channel);
+    let tx63 = tx5.clone();
+
+    #[cfg(feature = "io-uring")]
+    let uring_sqpoll = args.uring_sqpoll;
+    #[cfg(not(feature = "io-uring"))]
+    let uring_sqpoll = false;
+    let rt_priority = args.rt_priority_udp;
+
+    thread::Builder::new()
+        .name("port5".to_string())
+        .spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(net::port5(tx5, uring_sqpoll, rt_priority));
+        })?;
+
+    thread::Builder::new()
+        .name("port63".to_string())
+        .spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(net::port63(tx63));
+        })?;
+
+    let sinks = CubeSinks {
+        cube,
+        stats,
+        beamformed,
+        file,
+    };
+    cube_task(rx, sinks, &args, cube_batch).await
+}
+
+/// Rolling frame rate, drop rate and reassembly time of the radar cubes.
+struct CubeRollingStats {
+    /// Seconds between complete cubes
+    intervals: RollingStats,
+
+    /// 1.0 for frames which ended without a complete cube, 0.0 otherwise
+    drops: RollingStats,
+
+    /// Reassembly time of the complete cubes in milliseconds
+    reassembly_ms: RollingStats,
+
+    /// Time the last complete cube was assembled
+    last_cube: Option<Instant>,
+
+    /// Frames recorded since the last report
+    unreported: usize,
+}
+
+impl CubeRollingStats {
+    fn new(window: usize) -> Self {
+        CubeRollingStats {
+            intervals: RollingStats::new(window),
+            drops: RollingStats::new(window),
+            reassembly_ms: RollingStats::new(window),
+            last_cube: None,
+            unreported: 0,
+        }
+    }
+
+    /// Record a frame assembled into a complete cube.
+    fn complete(&mut self, cube: &RadarCube) {
+        let now = Instant::now();
+        if let Some(last) = self.last_cube.replace(now) {
+            self.intervals.push(now.duration_since(last).as_secs_f64());
+        }
+        self.reassembly_ms
+            .push(cube.reassembly_time.as_secs_f64() * 1000.0);
+        self.drops.push(0.0);
+        self.unreported += 1;
+    }
+
+    /// Record a frame which ended without a complete cube.
+    fn dropped(&mut self) {
+        self.drops.push(1.0);
+        self.unreported += 1;
+    }
+
+    /// Returns true once per window of recorded frames.
+    fn report_due(&mut self, window: usize) -> bool {
+        if self.unreported < window {
+            return false;
+        }
+        self.unreported = 0;
+        true
+    }
+}
+
+/// Assemble radar cubes from the batches of SMS packets received on the
+/// channel and publish every complete cube.
+///
+/// The cubes are published in batches of `cube_batch` cubes, a partial batch
+/// is published once its first cube has waited `cube_batch_timeout_ms` or
+/// when the channel closes.
+async fn cube_task<S: Sink>(
+    rx: ByteReceiver,
+    mut sinks: CubeSinks<S>,
+    args: &Args,
+    cube_batch: Arc<Mutex<BatchMetrics>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tracy = args.tracy;
+    let frame_id = &args.radar_frame_id;
+    let mut cube_breaker = PublishBreaker::new(
+        &args.cube_topic,
+        PUBLISH_FAILURE_THRESHOLD,
+        PUBLISH_PROBE_INTERVAL,
+    );
+    let mut reader = RadarCubeReader::with_limits(CubeLimits {
+        max_bytes: args.max_cube_bytes,
+        ..Default::default()
+    });
+    let mut rolling = CubeRollingStats::new(CUBE_STATS_FRAMES);
+    let cube_sink = BatchSink::new(
+        sinks.cube,
+        args.cube_batch,
+        Duration::from_millis(args.cube_batch_timeout_ms),
+        cube_batch,
+    );
+
+    loop {
+        let msg = match cube_sink.deadline() {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), rx.recv()).await {
+                Ok(msg) => msg,
+                Err(_) => {
+                    if let Err(e) = cube_sink.flush().await {
+                        cube_breaker.record(false);
+                        error!("publish cube error: {:?}", e);
+                    }
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        // The UDP receivers have exited once the channel is closed.
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(_) => {
+                if let Err(e) = cube_sink.flush().await {
+                    cube_breaker.record(false);
+                    error!("publish cube error: {:?}", e);
+                }
+                return Ok(());
+            }
+        };
+
+        let n_msg = msg.len() / SMS_PACKET_SIZE;
+
+        event!(Level::TRACE, event = "port5", n_msg = n_msg);
+
+        for i in 0..n_msg {
+            let begin = i * SMS_PACKET_SIZE;
+            let end = begin + SMS_PACKET_SIZE;
+            let frames = reader.lifetime_stats().total_frames;
+            let cubemsg = reader.read(&msg[begin..end]);
+
+            match &cubemsg {
+                Ok(Some(cube)) if cube.missing_data == 0 => rolling.complete(cube),
+                _ if reader.lifetime_stats().total_frames > frames => rolling.dropped(),
+                _ => (),
+            }
+
+            match cubemsg {
+                Ok(Some(cubemsg)) => {
+                    if args.dry_run {
+                        println!("{}", format_cube_summary(&cubemsg));
+                    }
+                    tracy.then(|| {
+                        plot!("cube captured data", cubemsg.data.len() as f64);
+                        plot!("cube missing data", cubemsg.missing_data as f64);
+                    });
+
+                    if cubemsg.missing_data == 0 {
+                        if let Some(sink) = &sinks.beamformed {
+                            let (msg, enc) = info_span!("beamforming").in_scope(|| {
+                                let beams =
+                                    beamform_phase_shift(&cubemsg.data, &args.beamforming_angles);
+                                format_beamformed(&cubemsg, &beams, frame_id)
+                            })?;
+                            if let Err(e) = sink.publish(msg, enc).await {
+                                error!("{} publish error: {:?}", args.beamformed_topic, e);
+                            }
+                        }
+
+                        if let Some(writer) = &mut sinks.file {
+                            let frame_counter = cubemsg.frame_counter;
+                            if !writer.write(frame_counter, cube_msg(cubemsg, frame_id)?) {
+                                debug!("cube sink queue full, dropped frame {}", frame_counter);
+                            }
+                        } else if cube_breaker.ready() {
+                            let (msg, enc) = format_cube(cubemsg, frame_id).unwrap();
+                            let span = info_span!("cube_publish");
+                            async {
+                                if let Err(e) = cube_breaker.publish(&cube_sink, msg, enc).await {
+                                    error!("publish cube error: {:?}", e);
+                                }
+                            }
+                            .instrument(span)
+                            .await;
+                        }
+
+                        tracy.then(|| secondary_frame_mark!("cube"));
+                    } else {
+                        warn!("dropping cube with {} missing data", cubemsg.missing_data);
+                    }
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    error!("capture cube error: {}", err);
+                }
+            }
+        }
+
+        if rolling.report_due(CUBE_STATS_FRAMES) {
+            let stats = reader.lifetime_stats();
+            let drop_rate = rolling.drops.mean().unwrap_or_default();
+            let (fps, fps_min, fps_max) = rolling.intervals.rate().unwrap_or_default();
+            let reassembly_ms = rolling.reassembly_ms.mean().unwrap_or_default();
+            let summary = format!(
+                "cube stats: fps={:.1} min={:.1} max={:.1} drop_rate={:.1}% reassembly={:.2}ms lifetime frames={} drops={} missing_elements={} aborted={} discarded={}",
+                fps,
+                fps_min,
+                fps_max,
+                drop_rate * 100.0,
+                reassembly_ms,
+                stats.total_frames,
+                stats.total_drops,
+                stats.total_missing_elements,
+                stats.frames_aborted,
+                stats.packets_discarded_while_poisoned
+            );
+            match DropSeverity::from_rate(drop_rate) {
+                DropSeverity::Normal => info!("{}", summary),
+                DropSeverity::Warning => warn!("{}", summary),
+                DropSeverity::Error => error!("{}", summary),
+            }
+
+            let publish = cube_breaker.counters();
+            let msg = json!({
+                "total_frames": stats.total_frames,
+                "total_drops": stats.total_drops,
+                "total_missing_elements": stats.total_missing_elements,
+                "frames_aborted": stats.frames_aborted,
+                "packets_discarded_while_poisoned": stats.packets_discarded_while_poisoned,
+                "rolling": {
+                    "frames": rolling.drops.len(),
+                    "drop_rate": drop_rate,
+                    "fps": fps,
+                    "fps_min": fps_min,
+                    "fps_max": fps_max,
+                    "reassembly_ms": reassembly_ms,
+                    "reassembly_ms_min": rolling.reassembly_ms.min(),
+                    "reassembly_ms_max": rolling.reassembly_ms.max(),
+                },
+                "publish": {
+                    "published": publish.published,
+                    "failed": publish.failed,
+                    "skipped": publish.skipped,
+                    "trips": publish.trips,
+                },
+                "file": sinks.file.as_ref().map(|writer| json!({
+                    "written": writer.written(),
+                    "dropped": writer.dropped(),
+                })),
+            });
+            if let Err(e) = sinks
+                .stats
+                .publish(ZBytes::from(msg.to_string()), Encoding::APPLICATION_JSON)
+                .await
+            {
+                error!("{} publish error: {:?}", args.cube_stats_topic, e);
+            }
+        }
+    }
+}
+
+#[instrument(skip_all, fields(shape = cubemsg.data.shape().iter().map(|s| s.to_string()).collect::<Vec<_>>().join(" ")))]
+fn format_cube(
+    cubemsg: RadarCube,
+    frame_id: &str,
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let msg = cube_msg(cubemsg, frame_id)?;
+    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/RadarCube");
+
+    Ok((msg, enc))
+}
+
+/// Convert the assembled cube into a RadarCube message.
+fn cube_msg(
+    cubemsg: RadarCube,
+    frame_id: &str,
+) -> Result<edgefirst_msgs::RadarCube, Box<dyn std::error::Error>> {
+    // Double the final dimension to account for complex data.
+    let shape = cubemsg.data.shape();
+    let shape = vec![
+        shape[0] as u16,
+        shape[1] as u16,
+        shape[2] as u16,
+        shape[3] as u16 * 2,
+    ];
+
+    // Cast the Complex<i16> vector to a i16 vector.
+    let data = cubemsg.data.into_raw_vec_and_offset().0;
+    let data2 =
+        unsafe { Vec::from_raw_parts(data.as_ptr() as *mut i16, data.len() * 2, data.len() * 2) };
+    std::mem::forget(data);
+
+    let msg = edgefirst_msgs::RadarCube {
+        header: std_msgs::Header {
+            stamp: timestamp()?,
+            frame_id: frame_id.to_string(),
+        },
+        timestamp: cubemsg.timestamp,
+        layout: CUBE_LAYOUT.to_vec(),
+        shape,
+        scales: vec![
+            1.0,
+            cubemsg.bin_properties.range_per_bin,
+            1.0,
+            cubemsg.bin_properties.speed_per_bin,
+        ],
+        cube: data2,
+        is_complex: true,
+    };
+
+    Ok(msg)
+}
+
+/// Format the beamformed range-Doppler maps as a RadarCube with the
+/// [azimuth, range, doppler] layout, one azimuth per steering angle.
+fn format_beamformed(
+    cubemsg: &RadarCube,
+    beams: &[Array2<Complex<f32>>],
+    frame_id: &str,
+) -> Result<(ZBytes, Encoding), Box<dyn std::error::Error>> {
+    let layout = vec![
+        edgefirst_msgs::radar_cube_dimension::AZIMUTH,
+        edgefirst_msgs::radar_cube_dimension::RANGE,
+        edgefirst_msgs::radar_cube_dimension::DOPPLER,
+    ];
+
+    let (range_gates, doppler_bins) = beams.first().map_or((0, 0), |beam| beam.dim());
+    let shape = vec![
+        beams.len() as u16,
+        range_gates as u16,
+        doppler_bins as u16 * 2,
+    ];
+
+    // The beamformer averages the channels so the samples stay in i16 range.
+    let cube = beams
+        .iter()
+        .flat_map(|beam| beam.iter())
+        .flat_map(|sample| [sample.re, sample.im])
+        .map(|value| value.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+        .collect();
+
+    let msg = edgefirst_msgs::RadarCube {
+        header: std_msgs::Header {
+            stamp: timestamp()?,
+            frame_id: frame_id.to_string(),
+        },
+        timestamp: cubemsg.timestamp,
+        layout,
+        shape,
+        scales: vec![
+            1.0,
+            cubemsg.bin_properties.range_per_bin,
+            cubemsg.bin_properties.speed_per_bin,
+        ],
+        cube,
+        is_complex: true,
+    };
+
+    let msg = ZBytes::from(serde_cdr::serialize(&msg)?);
+    let enc = Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/RadarCube");
+
+    Ok((msg, enc))
+}
+
+/// Publish a message which never changes every second, for subscribers
+/// joining late.
+async fn publish_static(
+    sessions: SessionWatch,
+    topic: String,
+    msg: ZBytes,
+    enc: Encoding,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+        let session = sessions.borrow().1.clone();
+        let span = info_span!("static_publish");
+        async { session.put(&topic, msg.clone()).encoding(enc.clone()).await }
+            .instrument(span)
+            .await?;
+    }
+}
+
+/// Radar configuration and identification published on the info and device
+/// topics, `rt/radar/info` and `rt/radar/device` by default.  The published messages are regenerated from this state
+/// on every publication so parameter changes are reflected immediately.
+#[derive(Clone, Debug)]
+struct RadarState {
+    frame_id: String,
+    cube: bool,
+    device: DeviceInfo,
+    requested: RadarParameters,
+    confirmed: RadarParameters,
+    /// Zenoh reconnects after changes of the endpoints file
+    zenoh_reconnects: Arc<AtomicU64>,
+    /// Latest status broadcast of the sensor
+    status: Option<SensorStatus>,
+    /// Blockage reported for longer than blockage_alarm_sec
+    blockage_alarm: bool,
+    /// Clustering window limit, None without the clustering task
+    clustering_window: Option<WindowLimit>,
+}
+
+/// Name of a parameter value, or the raw value if the sensor confirmed a
+/// value which radarpub does not know.
+fn parameter_name<T: TryFrom<u32> + std::fmt::Display>(value: u32) -> String {
+    match T::try_from(value) {
+        Ok(value) => value.to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+impl RadarState {
+    fn center_frequency(&self) -> String {
+        parameter_name::<CenterFrequency>(self.confirmed.center_frequency)
+    }
+
+    fn frequency_sweep(&self) -> String {
+        parameter_name::<FrequencySweep>(self.confirmed.frequency_sweep)
+    }
+
+    fn range_toggle(&self) -> String {
+        parameter_name::<RangeToggle>(self.confirmed.range_toggle)
+    }
+
+    fn detection_sensitivity(&self) -> String {
+        parameter_name::<DetectionSensitivity>(self.confirmed.detection_sensitivity)
+    }
+
+    /// Record a parameter reconfigured at runtime.
+    fn set_parameter(&mut self, param: Parameter, requested: u32, confirmed: u32) {
+        if requested != confirmed {
+            warn!(
+                "radar adjusted {:?}: requested {} confirmed {}",
+                param, requested, confirmed
+            );
+        }
+        self.requested.set(param, requested);
+        self.confirmed.set(param, confirmed);
+    }
+
+    /// Record a status broadcast of the sensor and the blockage alarm.
+    fn set_status(&mut self, status: SensorStatus, blockage_alarm: bool) {
+        self.status = Some(status);
+        self.blockage_alarm = blockage_alarm;
+    }
+
+    /// RadarInfo built from the values confirmed by the sensor.
+    fn format_info(&self) -> (ZBytes, Encoding) {
+        let msg = RadarInfo {
+            header: Header {
+                frame_id: self.frame_id.clone(),
+                stamp: timestamp().unwrap_or(Time { sec: 0, nanosec: 0 }),
+            },
+            center_frequency: self.center_frequency(),
+            frequency_sweep: self.frequency_sweep(),
+            range_toggle: self.range_toggle(),
+            detection_sensitivity: self.detection_sensitivity(),
+            cube: self.cube,
+        };
+
+        let msg = ZBytes::from(serde_cdr::serialize(&msg).unwrap());
+        let enc = Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/RadarInfo");
+
+        (msg, enc)
+    }
+
+    /// Sensor identification and the requested and confirmed parameters,
+    /// which RadarInfo has no fields for.
+    fn format_device(&self) -> (ZBytes, Encoding) {
+        let parameters = |p: &RadarParameters| {
+            json!({
+                "center_frequency": p.center_frequency,
+                "frequency_sweep": p.frequency_sweep,
+                "range_toggle": p.range_toggle,
+                "detection_sensitivity": p.detection_sensitivity,
+            })
+        };
+
+        // Failed instruction responses by result code, such as "busy".
+        let uat_errors: serde_json::Map<_, _> = uat_error_counts()
+            .into_iter()
+            .map(|(code, count)| (code.name(), json!(count)))
+            .collect();
+
+        let status = self.status.map(|status| {
+            json!({
+                "temperature": status.temperature,
+                "supply_voltage": status.supply_voltage,
+                "blocked": status.blocked,
+            })
+        });
+
+        let msg = json!({
+            "frame_id": self.frame_id,
+            "software_generation": self.device.software_generation,
+            "version": self.device.version(),
+            "serial_number": self.device.serial_number.to_string(),
+            "requested": parameters(&self.requested),
+            "confirmed": parameters(&self.confirmed),
+            "zenoh_reconnects": self.zenoh_reconnects.load(Ordering::Relaxed),
+            "uat_errors": uat_errors,
+            "status": status,
+            "blockage_alarm": self.blockage_alarm,
+            "clustering_window": self.clustering_window.map(|limit| limit.to_string()),
+        });
+
+        (ZBytes::from(msg.to_string()), Encoding::APPLICATION_JSON)
+    }
+}
+
+/// Interval between checks of the targets topic subscribers, in radar info
+/// publish periods (one second).
+const HEALTH_CHECK_TICKS: u32 = 5;
+
+/// Time without any targets subscriber before warning about it.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Count the subscribers matching a topic through the Zenoh admin space.
+///
+/// Every router and peer with the admin space enabled reports its
+/// subscriptions as `@/<zid>/<whatami>/subscriber/<key_expr>`, the count is
+/// the number of reported subscriptions which intersect the topic, including
+/// wildcard subscriptions.  A subscriber reached through several routers may
+/// be counted more than once.
+pub async fn check_topic_subscriber_count(
+    session: &Session,
+    topic: &str,
+) -> Result<usize, zenoh::Error> {
+    let topic = KeyExpr::try_from(topic)?;
+    let replies = session
+        .get("@/*/*/subscriber/**")
+        .timeout(Duration::from_secs(1))
+        .await?;
+
+    let mut count = 0;
+    while let Ok(reply) = replies.recv_async().await {
+        if let Ok(sample) = reply.result() {
+            let key = sample.key_expr().as_str().splitn(5, '/').nth(4);
+            if let Some(Ok(key)) = key.map(KeyExpr::try_from) {
+                if key.intersects(&topic) {
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+async fn radar_info(
+    sessions: SessionWatch,
+    state: Arc<Mutex<RadarState>>,
+    topic: String,
+    device_topic: String,
+    targets_topic: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    let mut ticks = 0u32;
+    let mut unconsumed_since: Option<Instant> = None;
+    let mut warned = false;
+
+    loop {
+        interval.tick().await;
+
+        let session = sessions.borrow().1.clone();
+        ticks = ticks.wrapping_add(1);
+        if ticks % HEALTH_CHECK_TICKS == 0 {
+            match check_topic_subscriber_count(&session, &targets_topic).await {
+                Ok(0) => {
+                    let since = *unconsumed_since.get_or_insert_with(Instant::now);
+                    if !warned && since.elapsed() > HEALTH_CHECK_TIMEOUT {
+                        warn!(
+                            "no subscribers on {} for {:?}, check the topic name of the consumers",
+                            targets_topic,
+                            since.elapsed()
+                        );
+                        warned = true;
+                    }
+                }
+                Ok(count) => {
+                    if warned {
+                        info!("{} has {} subscribers", targets_topic, count);
+                    }
+                    unconsumed_since = None;
+                    warned = false;
+                }
+                Err(e) => debug!("{} subscriber check error: {:?}", targets_topic, e),
+            }
+        }
+        let ((msg, enc), (device_msg, device_enc)) = {
+            let state = state.lock().unwrap();
+            (state.format_info(), state.format_device())
+        };
+
+        let span = info_span!("radar_info_publish");
+        async {
+            session.put(&topic, msg).encoding(enc).await?;
+            session
+                .put(&device_topic, device_msg)
+                .encoding(device_enc)
+                .await
+        }
+        .instrument(span)
+        .await?;
+    }
+}
+
+fn timestamp() -> Result<builtin_interfaces::Time, std::io::Error> {
+    let mut tp = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let err = unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_RAW, &mut tp) };
+    if err != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(builtin_interfaces::Time {
+        sec: tp.tv_sec as i32,
+        nanosec: tp.tv_nsec as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use can::{CanIds, CanInjector};
+    use eth::DebugHeader;
+    use sink::VecSink;
+    use std::{fs::File, future::Future, io::BufReader};
+    use uuid::Uuid;
+
+    const REPLAY: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testdata/clustering_replay.log"
+    );
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn replay() -> CanInjector {
+        CanInjector::from_candump(BufReader::new(File::open(REPLAY).unwrap())).unwrap()
+    }
+
+    /// Targets of every frame in the replay log.
+    fn replay_frames() -> Vec<TargetFrame> {
+        let can = replay();
+        let mut frames = Vec::new();
+        while can.pending() > 0 {
+            let frame = block_on(read_message(&can, &CanIds::default())).unwrap();
+            frames.push(TargetFrame {
+                header: frame.header,
+                received_at: Instant::now(),
+                stamp: timestamp().unwrap(),
+                targets: frame.targets[..frame.header.n_targets].to_vec(),
+            });
+        }
+        frames
+    }
+
+    fn pointcloud(payload: &ZBytes) -> sensor_msgs::PointCloud2 {
+        serde_cdr::deserialize(&payload.to_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_stream_publishes_targets() {
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        let frames = replay_frames();
+        let sinks = StreamSinks {
+            targets: VecSink::new(),
+            latency: VecSink::new(),
+            heatmap: None,
+            ground_plane: None,
+            blockage: None,
+        };
+        let (targets, latency) = (sinks.targets.clone(), sinks.latency.clone());
+        let histogram = Arc::new(Mutex::new(LatencyHistogram::new()));
+
+        // The stream never returns, it idles once the replay is exhausted.
+        let task = stream(replay(), sinks, args, None, None, histogram.clone(), true);
+        let _ = block_on(async { tokio::time::timeout(Duration::from_millis(500), task).await });
+
+        let messages = targets.messages();
+        assert_eq!(messages.len(), frames.len());
+        for ((payload, encoding), frame) in messages.iter().zip(frames.iter()) {
+            assert_eq!(
+                *encoding,
+                Encoding::APPLICATION_CDR.with_schema("sensor_msgs/msg/PointCloud2")
+            );
+            let cloud = pointcloud(payload);
+            assert_eq!(cloud.width as usize, frame.targets.len());
+            assert_eq!(cloud.header.frame_id, "radar");
+        }
+
+        assert_eq!(histogram.lock().unwrap().count(), frames.len() as u64);
+        assert!(latency.is_empty());
+    }
+
+    #[test]
+    fn test_format_targets_accuracy() {
+        let targets = [
+            Target {
+                range: 10.0,
+                accuracy: 3,
+                ..Default::default()
+            },
+            Target {
+                range: 20.0,
+                accuracy: 1,
+                ..Default::default()
+            },
+        ];
+        for (include_confidence, offset) in [(false, 24), (true, 28)] {
+            let (msg, _) = format_targets(
+                &targets,
+                false,
+                include_confidence,
+                true,
+                None,
+                None,
+                None,
+                None,
+                "radar",
+            )
+            .unwrap();
+            let cloud = pointcloud(&msg);
+            let field = cloud.fields.last().unwrap();
+            assert_eq!(field.name, "accuracy");
+            assert_eq!(field.offset, offset);
+            assert_eq!(field.datatype, PointFieldType::FLOAT32 as u8);
+            assert_eq!(cloud.point_step, offset + 4);
+
+            let accuracy: Vec<_> = cloud
+                .data
+                .chunks_exact(cloud.point_step as usize)
+                .map(|p| f32::from_ne_bytes(p[offset as usize..][..4].try_into().unwrap()))
+                .collect();
+            assert_eq!(accuracy, [3.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn test_format_targets_speed_unfolded() {
+        let targets = [
+            Target {
+                range: 10.0,
+                speed: 4.4,
+                ..Default::default()
+            },
+            Target {
+                range: 20.0,
+                speed: -1.5,
+                ..Default::default()
+            },
+        ];
+        let (msg, _) = format_targets(
+            &targets,
+            false,
+            false,
+            true,
+            Some(&[-35.0, -1.5]),
+            None,
+            None,
+            None,
+            "radar",
+        )
+        .unwrap();
+        let cloud = pointcloud(&msg);
+        let names: Vec<_> = cloud.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(&names[6..], ["accuracy", "speed_unfolded"]);
+        assert_eq!(cloud.fields[7].offset, 28);
+        assert_eq!(cloud.point_step, 32);
+
+        let speeds: Vec<_> = cloud
+            .data
+            .chunks_exact(32)
+            .map(|p| {
+                let speed = f32::from_ne_bytes(p[12..16].try_into().unwrap());
+                let unfolded = f32::from_ne_bytes(p[28..32].try_into().unwrap());
+                (speed, unfolded)
+            })
+            .collect();
+        assert_eq!(speeds, [(4.4, -35.0), (-1.5, -1.5)]);
+    }
+
+    #[test]
+    fn test_format_schema() {
+        let mut args = Args::parse_from(["edgefirst-radarpub"]);
+        args.resolve_topics();
+        let (version, topics) = schema::parse_schema(&format_schema(&args)).unwrap();
+        assert_eq!(version, schema::SCHEMA_VERSION);
+        assert_eq!(topics.len(), 1);
+        assert_eq!(topics[0].0, "rt/radar/targets");
+        assert_eq!(topics[0].1.point_step, 24);
+
+        // Every optional field, the schema matches the published messages.
+        let mut args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering",
+            "--include-confidence",
+            "--include-accuracy",
+            "--doppler-unfold",
+            "--annotate-targets",
+            "--cluster-roles",
+        ]);
+        args.resolve_topics();
+        let (_, topics) = schema::parse_schema(&format_schema(&args)).unwrap();
+        let layout = |topic: &str| &topics.iter().find(|(t, _)| t == topic).unwrap().1;
+
+        let targets = [Target::default(); 2];
+        let annotation = annotate_targets(&targets, None, 0, false, &[1.0; 4]);
+        let (msg, _) = format_targets(
+            &targets,
+            false,
+            true,
+            true,
+            Some(&[0.0, 0.0]),
+            Some(&annotation),
+            None,
+            None,
+            "radar",
+        )
+        .unwrap();
+        let cloud = pointcloud(&msg);
+        assert_eq!(
+            *layout("rt/radar/targets"),
+            PointLayout::from_point_fields(&cloud.fields, cloud.point_step)
+        );
+
+        let refs: Vec<_> = targets.iter().collect();
+        let (msg, _) = format_clusters(
+            Time { sec: 0, nanosec: 0 },
+            &refs,
+            [1.0, 2.0].into_iter(),
+            Some(&[PointRole::Core, PointRole::Border]),
+            false,
+            true,
+            "radar".to_string(),
+        )
+        .unwrap();
+        let cloud = pointcloud(&msg);
+        assert_eq!(
+            *layout("rt/radar/clusters"),
+            PointLayout::from_point_fields(&cloud.fields, cloud.point_step)
+        );
+        assert_eq!(cloud.point_step, 36);
+    }
+
+    #[test]
+    fn test_format_targets_organised() {
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--organised-cloud",
+            "--organised-cloud-range-bins",
+            "4",
+            "--organised-cloud-azimuth-bins",
+            "4",
+            "--organised-cloud-extent",
+            "0 40 -40 40",
+        ]);
+        let target = |range, azimuth, speed, power| Target {
+            range,
+            azimuth,
+            speed,
+            power,
+            ..Default::default()
+        };
+        // Cells of 10 m by 20 degrees, the first two targets share a cell
+        // and the last two are beyond the range and azimuth extent.
+        let targets = [
+            target(5.0, -30.0, 1.0, -60.0),
+            target(6.0, -25.0, 2.0, -50.0),
+            target(35.0, 30.0, 3.0, -70.0),
+            target(45.0, 0.0, 4.0, -40.0),
+            target(10.0, 40.0, 5.0, -40.0),
+        ];
+        let speeds = |mirror| {
+            let (msg, _) = format_targets(
+                &targets,
+                mirror,
+                false,
+                false,
+                None,
+                None,
+                None,
+                args.organised_grid(),
+                "radar",
+            )
+            .unwrap();
+            let cloud = pointcloud(&msg);
+            assert_eq!((cloud.height, cloud.width), (4, 4));
+            assert_eq!(cloud.point_step, 24);
+            assert_eq!(cloud.row_step, 96);
+            assert_eq!(cloud.data.len(), 16 * 24);
+            assert!(!cloud.is_dense);
+            cloud
+                .data
+                .chunks_exact(24)
+                .map(|p| f32::from_ne_bytes(p[12..16].try_into().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        let cells = speeds(false);
+        assert_eq!(cells[0], 2.0);
+        assert_eq!(cells[15], 3.0);
+        assert_eq!(cells.iter().filter(|speed| !speed.is_nan()).count(), 2);
+
+        // Mirrored the azimuth columns follow the y axis.
+        let cells = speeds(true);
+        assert_eq!(cells[3], 2.0);
+        assert_eq!(cells[12], 3.0);
+
+        // Without the flag the cloud stays unorganised.
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        assert!(args.organised_grid().is_none());
+    }
+
+    #[test]
+    fn test_stream_removes_ground() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--remove-ground"]);
+        let frames = replay_frames();
+        let sinks = StreamSinks {
+            targets: VecSink::new(),
+            latency: VecSink::new(),
+            heatmap: None,
+            ground_plane: Some(VecSink::new()),
+            blockage: None,
+        };
+        let (targets, ground_plane) = (sinks.targets.clone(), sinks.ground_plane.clone().unwrap());
+        let histogram = Arc::new(Mutex::new(LatencyHistogram::new()));
+
+        let task = stream(replay(), sinks, args.clone(), None, None, histogram, true);
+        let _ = block_on(async { tokio::time::timeout(Duration::from_millis(500), task).await });
+
+        let messages = targets.messages();
+        assert_eq!(messages.len(), frames.len());
+        let mut planes = Vec::new();
+        for (i, ((payload, _), frame)) in messages.iter().zip(frames.iter()).enumerate() {
+            let (ground, plane) = ransac_ground_plane(
+                &frame.targets,
+                args.ground_iterations,
+                args.ground_threshold,
+            );
+            let remaining = ground.iter().filter(|&&ground| !ground).count();
+            assert_eq!(pointcloud(payload).width as usize, remaining);
+            if (i + 1) % GROUND_PLANE_FRAMES == 0 {
+                planes.extend(plane);
+            }
+        }
+
+        let published = ground_plane.messages();
+        assert_eq!(published.len(), planes.len());
+        for ((payload, encoding), [a, b, c, d]) in published.iter().zip(planes) {
+            assert_eq!(
+                *encoding,
+                Encoding::APPLICATION_CDR.with_schema("geometry_msgs/msg/Quaternion")
+            );
+            let msg: Quaternion = serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+            assert_eq!([msg.x, msg.y, msg.z, msg.w], [a, b, c, d]);
+        }
+    }
+
+    #[test]
+    fn test_clustering_task_publishes_clusters() {
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        let frames = replay_frames();
+        let sinks = ClusteringSinks {
+            clusters: VecSink::new(),
+            quality: VecSink::new(),
+            bev: None,
+            detect: None,
+            debug_dump: None,
+            track_events: None,
+        };
+        let (clusters, quality) = (sinks.clusters.clone(), sinks.quality.clone());
+
+        let (tx, rx) = kanal::bounded_async(frames.len());
+        for frame in frames.iter().cloned() {
+            block_on(tx.send(frame)).unwrap();
+        }
+        // The task returns once the stream side of the channel is closed.
+        drop(tx);
+        let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+        block_on(clustering_task(
+            sinks,
+            args.clone(),
+            rx,
+            None,
+            metrics.clone(),
+            None,
+        ))
+        .unwrap();
+        assert_eq!(metrics.lock().unwrap().cycles(), frames.len() as u64);
+
+        let messages = clusters.messages();
+        assert_eq!(messages.len(), frames.len());
+        for (i, (payload, _)) in messages.iter().enumerate() {
+            let window = &frames[(i + 1).saturating_sub(args.window_size)..=i];
+            let expected: usize = window.iter().map(|frame| frame.targets.len()).sum();
+            assert_eq!(pointcloud(payload).width as usize, expected);
+        }
+
+        let reports = quality.messages();
+        assert_eq!(reports.len(), frames.len());
+        let last: serde_json::Value =
+            serde_json::from_slice(&reports.last().unwrap().0.to_bytes()).unwrap();
+        assert!(last["clusters"].as_u64().unwrap() > 0);
+        assert!(last["davies_bouldin_index"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn test_clustering_task_flushes_window() {
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        let mut frames = replay_frames();
+        frames.retain(|frame| !frame.targets.is_empty());
+        // Frames every 55 ms with a 3 s pause before the fifth frame.
+        let pause = 4;
+        let mut at = 1_000_000_000u64;
+        for (i, frame) in frames.iter_mut().enumerate() {
+            at += match i == pause {
+                true => 3_000_000_000,
+                false => RADAR_CYCLE.as_nanos() as u64,
+            };
+            frame.stamp = Time {
+                sec: (at / 1_000_000_000) as i32,
+                nanosec: (at % 1_000_000_000) as u32,
+            };
+        }
+        let sinks = ClusteringSinks {
+            clusters: VecSink::new(),
+            quality: VecSink::new(),
+            bev: None,
+            detect: None,
+            debug_dump: None,
+            track_events: None,
+        };
+        let (clusters, quality) = (sinks.clusters.clone(), sinks.quality.clone());
+
+        let (tx, rx) = kanal::bounded_async(frames.len());
+        for frame in frames.iter().cloned() {
+            block_on(tx.send(frame)).unwrap();
+        }
+        drop(tx);
+        let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+        block_on(clustering_task(
+            sinks,
+            args.clone(),
+            rx,
+            None,
+            metrics,
+            None,
+        ))
+        .unwrap();
+
+        // The window restarts with the first frame after the pause.
+        let messages = clusters.messages();
+        for (i, (payload, _)) in messages.iter().enumerate() {
+            let oldest = match i >= pause {
+                true => pause.max((i + 1).saturating_sub(args.window_size)),
+                false => (i + 1).saturating_sub(args.window_size),
+            };
+            let expected: usize = frames[oldest..=i].iter().map(|f| f.targets.len()).sum();
+            assert_eq!(pointcloud(payload).width as usize, expected, "frame {}", i);
+        }
+        let cloud = pointcloud(&messages[pause].0);
+        assert_eq!(cloud.width as usize, frames[pause].targets.len());
+
+        let report: serde_json::Value =
+            serde_json::from_slice(&quality.messages()[pause].0.to_bytes()).unwrap();
+        assert_eq!(report["window_frames"], 1);
+        let report: serde_json::Value =
+            serde_json::from_slice(&quality.messages()[pause - 1].0.to_bytes()).unwrap();
+        assert_eq!(report["window_frames"], pause);
+    }
+
+    #[test]
+    fn test_clustering_task_track_log() {
+        let dir = std::env::temp_dir().join(format!("radarpub-track-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tracks.jsonl");
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering",
+            "--track-events",
+            "--track-history-decimation",
+            "1",
+            "--track-log",
+            path.to_str().unwrap(),
+        ]);
+        let frames = replay_frames();
+        let sinks = ClusteringSinks {
+            clusters: VecSink::new(),
+            quality: VecSink::new(),
+            bev: None,
+            detect: None,
+            debug_dump: None,
+            track_events: Some(VecSink::new()),
+        };
+        let events = sinks.track_events.clone().unwrap();
+
+        let (tx, rx) = kanal::bounded_async(frames.len());
+        let (requests, track_log) = kanal::bounded_async(1);
+        let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+        let (result, written) = block_on(async {
+            let task = clustering_task(sinks, args, rx, None, metrics, Some(track_log));
+            let replay = async {
+                for frame in frames {
+                    tx.send(frame).await.unwrap();
+                }
+                // A request writes the tracks completed so far, the live
+                // tracks are written once the stream side has closed.
+                let (reply, written) = tokio::sync::oneshot::channel();
+                requests.send(reply).await.unwrap();
+                let written = written.await.unwrap().unwrap();
+                drop(tx);
+                written
+            };
+            tokio::join!(task, replay)
+        });
+        result.unwrap();
+
+        // Every completed track was published and written once, in order.
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let events: Vec<serde_json::Value> = events
+            .messages()
+            .iter()
+            .map(|(payload, _)| serde_json::from_slice(&payload.to_bytes()).unwrap())
+            .collect();
+        assert!(!lines.is_empty());
+        assert!(written <= lines.len());
+        assert_eq!(lines, events);
+        for track in &lines {
+            assert!(track["cluster_id"].as_u64().unwrap() > 0);
+            assert!(track["first_seen"].as_u64() <= track["last_seen"].as_u64());
+            assert!(!track["path"].as_array().unwrap().is_empty());
+            assert_eq!(track["class"], "unknown");
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_clustering_task_tracker_state() {
+        let dir =
+            std::env::temp_dir().join(format!("radarpub-tracker-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tracker.json");
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering",
+            "--tracker-state-file",
+            path.to_str().unwrap(),
+        ]);
+        let run = |frames: &[TargetFrame]| {
+            let sinks = ClusteringSinks {
+                clusters: VecSink::new(),
+                quality: VecSink::new(),
+                bev: None,
+                detect: None,
+                debug_dump: None,
+                track_events: None,
+            };
+            let (tx, rx) = kanal::bounded_async(frames.len());
+            for frame in frames.iter().cloned() {
+                block_on(tx.send(frame)).unwrap();
+            }
+            drop(tx);
+            let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+            block_on(clustering_task(
+                sinks,
+                args.clone(),
+                rx,
+                None,
+                metrics,
+                None,
+            ))
+            .unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+            serde_json::from_str::<serde_json::Value>(&text).unwrap()
+        };
+        let tracks = |state: &serde_json::Value| -> std::collections::HashMap<String, i64> {
+            state["tracker"]["tracklets"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| {
+                    (
+                        t["id"].as_str().unwrap().to_string(),
+                        t["count"].as_i64().unwrap(),
+                    )
+                })
+                .collect()
+        };
+
+        // The tracks saved at shutdown are continued by the next run.
+        let frames = replay_frames();
+        let (first, second) = frames.split_at(frames.len() / 2);
+        let saved = run(first);
+        let saved_tracks = tracks(&saved);
+        assert!(!saved_tracks.is_empty());
+        assert!(saved["saved_at_ms"].as_u64().unwrap() > 0);
+        let continued = tracks(&run(second));
+        assert!(saved_tracks
+            .iter()
+            .any(|(id, count)| continued.get(id).is_some_and(|c| c > count)));
+        let max_age = Duration::from_secs(5);
+        assert!(load_tracker_state(&path, max_age).is_some());
+
+        // A stale state is discarded.
+        let mut stale = saved.clone();
+        stale["saved_at_ms"] = json!(0);
+        std::fs::write(&path, stale.to_string()).unwrap();
+        assert!(load_tracker_state(&path, max_age).is_none());
+        std::fs::write(&path, "{}").unwrap();
+        assert!(load_tracker_state(&path, max_age).is_none());
+        assert!(load_tracker_state(&dir.join("missing.json"), max_age).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_tracker_state() {
+        let dir = std::env::temp_dir().join(format!("radarpub-shutdown-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tracker.json");
+        let args = Args::parse_from(["edgefirst-radarpub", "--clustering"]);
+        assert!(!graceful_shutdown(&args));
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering",
+            "--tracker-state-file",
+            path.to_str().unwrap(),
+        ]);
+        assert!(graceful_shutdown(&args));
+
+        let (tx, rx) = kanal::bounded_async(16);
+        let clustering_args = args.clone();
+        let thread = thread::spawn(move || {
+            let sinks = ClusteringSinks {
+                clusters: VecSink::new(),
+                quality: VecSink::new(),
+                bev: None,
+                detect: None,
+                debug_dump: None,
+                track_events: None,
+            };
+            let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+            block_on(clustering_task(
+                sinks,
+                clustering_args,
+                rx,
+                None,
+                metrics,
+                None,
+            ))
+            .unwrap();
+        });
+
+        // The signal arrives while the stream task still holds the
+        // clustering channel, the tracker state is saved once it is dropped.
+        let frames = replay_frames();
+        let (signal_tx, signal_rx) = kanal::bounded_async(1);
+        let stream_task = async move {
+            for frame in frames {
+                tx.send(frame).await.unwrap();
+            }
+            signal_tx.send(()).await.unwrap();
+            std::future::pending::<()>().await;
+            Ok::<(), Box<dyn std::error::Error>>(())
+        };
+        let heartbeat = std::future::pending::<Result<Duration, zenoh::Error>>();
+        let shutdown = async move {
+            signal_rx.recv().await.unwrap();
+            Ok::<(), std::io::Error>(())
+        };
+        block_on(async {
+            supervise(stream_task, heartbeat, shutdown).await.unwrap();
+            join_clustering(thread).await;
+        });
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let state: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert!(!state["tracker"]["tracklets"].as_array().unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_evict_window() {
+        // A burst of frames, each with ten copies of the replayed targets.
+        let mut frames = replay_frames();
+        frames.retain(|frame| !frame.targets.is_empty());
+        for frame in frames.iter_mut() {
+            frame.targets = frame.targets.repeat(10);
+        }
+        let points = |window: &VecDeque<TargetFrame>| -> usize {
+            window.iter().map(|frame| frame.targets.len()).sum()
+        };
+
+        let mut window = VecDeque::new();
+        let mut evicted = 0;
+        for frame in frames.iter().cloned() {
+            let newest = frame.targets.len();
+            window.push_back(frame);
+            evicted += evict_window(&mut window, 100);
+            assert!(points(&window) <= 100 || window.len() == 1);
+            assert_eq!(window.back().unwrap().targets.len(), newest);
+        }
+        assert!(evicted > 0);
+        assert_eq!(evicted + window.len(), frames.len());
+        assert_eq!(evict_window(&mut window, usize::MAX), 0);
+
+        // A cap below a single frame keeps only the newest frame.
+        let mut window: VecDeque<_> = frames.iter().take(3).cloned().collect();
+        assert_eq!(evict_window(&mut window, 0), 2);
+        assert_eq!(window.len(), 1);
+        assert_eq!(window[0].header, frames[2].header);
+    }
+
+    #[test]
+    fn test_window_duration() {
+        // Frames of a 50 ms cycle, then of a 100 ms cycle after the sweep
+        // settings changed.
+        let frame = replay_frames().remove(0);
+        let start = frame.received_at;
+        let mut at = Duration::ZERO;
+        let mut frames = Vec::new();
+        for cycle_ms in [50u64; 12].into_iter().chain([100; 8]) {
+            at += Duration::from_millis(cycle_ms);
+            frames.push(TargetFrame {
+                received_at: start + at,
+                ..frame.clone()
+            });
+        }
+
+        let limit = WindowLimit::Duration(Duration::from_millis(300));
+        let mut window = VecDeque::new();
+        let mut lengths = Vec::new();
+        for (i, frame) in frames.iter().cloned().enumerate() {
+            push_window(&mut window, frame, limit);
+            assert!(window_span(&window) <= Duration::from_millis(300));
+            // The last evicted frame was older than the duration.
+            if let Some(evicted) = i.checked_sub(window.len()) {
+                let age = frames[i].received_at - frames[evicted].received_at;
+                assert!(age > Duration::from_millis(300), "frame {}", i);
+            }
+            lengths.push(window.len());
+        }
+        // 7 frames span 300 ms at 50 ms, 4 frames at 100 ms.
+        assert_eq!(lengths[11], 7);
+        assert_eq!(lengths[19], 4);
+        assert_eq!(window_span(&window), Duration::from_millis(300));
+
+        // The same frames in a frame count window span twice the time once
+        // the cycle slows down.
+        let mut window = VecDeque::new();
+        for frame in frames.iter().cloned() {
+            push_window(&mut window, frame, WindowLimit::Frames(6));
+            assert!(window.len() <= 6);
+        }
+        assert_eq!(window_span(&window), Duration::from_millis(500));
+
+        // A single frame is never evicted.
+        let mut window = VecDeque::new();
+        push_window(
+            &mut window,
+            frames[0].clone(),
+            WindowLimit::Duration(Duration::ZERO),
+        );
+        push_window(
+            &mut window,
+            frames[1].clone(),
+            WindowLimit::Duration(Duration::ZERO),
+        );
+        assert_eq!(window.len(), 1);
+        assert_eq!(window[0].received_at, frames[1].received_at);
+    }
+
+    #[test]
+    fn test_window_limit_args() {
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        assert_eq!(args.window_limit(), WindowLimit::Frames(6));
+        assert_eq!(args.window_limit().to_string(), "6 frames");
+
+        let args = Args::parse_from(["edgefirst-radarpub", "--window-duration", "330"]);
+        assert_eq!(
+            args.window_limit(),
+            WindowLimit::Duration(Duration::from_millis(330))
+        );
+        assert_eq!(args.window_limit().to_string(), "330 ms");
+
+        assert!(Args::try_parse_from([
+            "edgefirst-radarpub",
+            "--window-size",
+            "3",
+            "--window-duration",
+            "330"
+        ])
+        .is_err());
+        assert!(Args::try_parse_from(["edgefirst-radarpub", "--window-duration", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_clustering_frame_stamp() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--window-size", "3"]);
+        let mut frames = replay_frames();
+        let start = timestamp().unwrap().to_nanos();
+        for (i, frame) in frames.iter_mut().enumerate() {
+            let nanos = start + i as u64 * RADAR_CYCLE.as_nanos() as u64;
+            frame.stamp = Time {
+                sec: (nanos / 1_000_000_000) as i32,
+                nanosec: (nanos % 1_000_000_000) as u32,
+            };
+        }
+        let stamps: Vec<_> = frames
+            .iter()
+            .map(|frame| (frame.stamp.sec, frame.stamp.nanosec))
+            .collect();
+
+        // Each window is stamped with the reception of its newest frame,
+        // not with the time it was clustered.
+        let (clouds, _) = replay_clusters(args, frames);
+        assert_eq!(clouds.len(), stamps.len());
+        for (cloud, stamp) in clouds.iter().zip(&stamps) {
+            assert_eq!((cloud.header.stamp.sec, cloud.header.stamp.nanosec), *stamp);
+        }
+    }
+
+    #[test]
+    fn test_clustering_detect() {
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering",
+            "--detect",
+            "--window-size",
+            "3",
+        ]);
+        assert_eq!(args.detect_labels, ["object"]);
+        let frames = replay_frames();
+        let n_frames = frames.len();
+        let sinks = ClusteringSinks {
+            clusters: VecSink::new(),
+            quality: VecSink::new(),
+            bev: None,
+            detect: Some(VecSink::new()),
+            debug_dump: None,
+            track_events: None,
+        };
+        let (clusters, detect) = (sinks.clusters.clone(), sinks.detect.clone().unwrap());
+
+        let (tx, rx) = kanal::bounded_async(n_frames);
+        for frame in frames {
+            block_on(tx.send(frame)).unwrap();
+        }
+        drop(tx);
+        let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+        block_on(clustering_task(sinks, args, rx, None, metrics, None)).unwrap();
+
+        let messages = detect.messages();
+        assert_eq!(messages.len(), n_frames);
+        let mut tracks = HashSet::new();
+        for ((payload, encoding), (cloud, _)) in messages.iter().zip(clusters.messages().iter()) {
+            assert_eq!(
+                *encoding,
+                Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/Detect")
+            );
+            let msg: edgefirst_msgs::Detect = serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+            let cloud = pointcloud(cloud);
+            // The boxes carry the stamp of the clusters they were tracked from.
+            assert_eq!(
+                (msg.header.stamp.sec, msg.header.stamp.nanosec),
+                (cloud.header.stamp.sec, cloud.header.stamp.nanosec)
+            );
+            for b in &msg.boxes {
+                assert_eq!(b.label, "object");
+                assert!((0.0..=1.0).contains(&b.score), "{}", b.score);
+                assert!(b.width >= 0.0 && b.height >= 0.0);
+                assert!(b.track.lifetime >= 1);
+                assert!(Uuid::parse_str(&b.track.id).is_ok());
+                tracks.insert(b.track.id.clone());
+            }
+        }
+        assert!(!tracks.is_empty());
+    }
+
+    #[test]
+    fn test_clustering_debug_dump() {
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering",
+            "--enable-cluster-debug-dump",
+            "--window-size",
+            "1",
+        ]);
+        let mut frames = replay_frames();
+        // An empty frame drops the cluster count to 0.
+        let mut empty = frames[frames.len() - 1].clone();
+        empty.targets.clear();
+        frames.push(empty);
+        let sinks = ClusteringSinks {
+            clusters: VecSink::new(),
+            quality: VecSink::new(),
+            bev: None,
+            detect: None,
+            debug_dump: Some(VecSink::new()),
+            track_events: None,
+        };
+        let (quality, dumps) = (sinks.quality.clone(), sinks.debug_dump.clone().unwrap());
+
+        let (tx, rx) = kanal::bounded_async(frames.len());
+        for frame in frames.iter().cloned() {
+            block_on(tx.send(frame)).unwrap();
+        }
+        drop(tx);
+        let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+        block_on(clustering_task(sinks, args, rx, None, metrics, None)).unwrap();
+
+        // A dump for every anomalous change of the published cluster count.
+        let counts: Vec<usize> = quality
+            .messages()
+            .iter()
+            .map(|(payload, _)| {
+                let quality: serde_json::Value =
+                    serde_json::from_slice(&payload.to_bytes()).unwrap();
+                quality["clusters"].as_u64().unwrap() as usize
+            })
+            .collect();
+        let anomalies: Vec<_> = counts
+            .windows(2)
+            .enumerate()
+            .filter(|(_, w)| history::cluster_count_anomaly(w[0], w[1]).is_some())
+            .map(|(i, w)| (i + 1, w[0], w[1]))
+            .collect();
+
+        let messages = dumps.messages();
+        assert_eq!(messages.len(), anomalies.len());
+        for ((payload, encoding), (cycle, previous, clusters)) in messages.iter().zip(anomalies) {
+            assert_eq!(*encoding, Encoding::APPLICATION_JSON);
+            let dump: serde_json::Value = serde_json::from_slice(&payload.to_bytes()).unwrap();
+            assert_eq!(dump["previous_clusters"], previous);
+            assert_eq!(dump["clusters"], clusters);
+            // The dumped frames end with the frame of the anomaly.
+            let dumped = dump["frames"].as_array().unwrap();
+            assert_eq!(dumped.len(), (cycle + 1).min(DEBUG_DUMP_FRAMES));
+            let last = &frames[cycle];
+            assert_eq!(
+                dumped[dumped.len() - 1]["targets"]
+                    .as_array()
+                    .unwrap()
+                    .len(),
+                last.targets.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_classifier_thresholds() {
+        // The argument defaults are the documented constants.
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        assert_eq!(
+            classifier_thresholds(&args),
+            ClassifierThresholds::default()
+        );
+        assert_eq!(
+            args.classifier_hysteresis,
+            clustering::CLASSIFIER_HYSTERESIS
+        );
+
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--classifier-pedestrian-max-rcs",
+            "-6",
+            "--classifier-vehicle-min-speed",
+            "15",
+        ]);
+        let thresholds = classifier_thresholds(&args);
+        assert_eq!(thresholds.pedestrian_max_rcs, -6.0);
+        assert_eq!(thresholds.vehicle_min_speed, 15.0);
+    }
+
+    #[test]
+    fn test_format_detect_classes() {
+        let track = ClusterTrack {
+            cluster_id: 4,
+            uuid: Uuid::new_v4(),
+            bbox: [9.0, -1.0, 13.0, 1.0],
+            count: 10,
+            created: 1_500_000_000,
+        };
+        let summary = ClusterSummary {
+            id: 4,
+            centroid: [11.0, 0.0, 0.0],
+            velocity: -13.0,
+            rcs: 10.0,
+            points: 10,
+        };
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        let time = || Time { sec: 2, nanosec: 0 };
+
+        let (msg, _) = format_detect(
+            time(),
+            &[track.clone()],
+            &[summary],
+            Some(&[(ObjectClass::Vehicle, 0.5)]),
+            &args,
+        )
+        .unwrap();
+        let msg: edgefirst_msgs::Detect = serde_cdr::deserialize(&msg.to_bytes()).unwrap();
+        let b = &msg.boxes[0];
+        assert_eq!(b.label, "vehicle");
+        assert_eq!(b.score, 0.5);
+        assert_eq!(
+            (b.center_x, b.center_y, b.width, b.height),
+            (11.0, 0.0, 4.0, 2.0)
+        );
+        assert_eq!(b.track.id, track.uuid.to_string());
+        assert_eq!(
+            (b.track.created.sec, b.track.created.nanosec),
+            (1, 500_000_000)
+        );
+
+        // Without classes the configured label and the detection score.
+        let (msg, _) = format_detect(time(), &[track], &[summary], None, &args).unwrap();
+        let msg: edgefirst_msgs::Detect = serde_cdr::deserialize(&msg.to_bytes()).unwrap();
+        assert_eq!(msg.boxes[0].label, "object");
+        assert_eq!(msg.boxes[0].score, 1.0);
+    }
+
+    #[test]
+    fn test_detect_label() {
+        let single = [String::from("object")];
+        assert_eq!(detect_label(&single, -10.0, 0.5), "object");
+        assert_eq!(detect_label(&single, 10.0, 0.5), "object");
+
+        let bands = ["approaching", "stationary", "receding"].map(String::from);
+        assert_eq!(detect_label(&bands, -0.6, 0.5), "approaching");
+        assert_eq!(detect_label(&bands, -0.5, 0.5), "stationary");
+        assert_eq!(detect_label(&bands, 0.2, 0.5), "stationary");
+        assert_eq!(detect_label(&bands, 0.6, 0.5), "receding");
+    }
+
+    /// Run the clustering task over the frames and return the published
+    /// cluster point clouds and quality reports.
+    fn replay_clusters(
+        args: Args,
+        frames: Vec<TargetFrame>,
+    ) -> (Vec<sensor_msgs::PointCloud2>, Vec<serde_json::Value>) {
+        let sinks = ClusteringSinks {
+            clusters: VecSink::new(),
+            quality: VecSink::new(),
+            bev: None,
+            detect: None,
+            debug_dump: None,
+            track_events: None,
+        };
+        let (clusters, quality) = (sinks.clusters.clone(), sinks.quality.clone());
+
+        let (tx, rx) = kanal::bounded_async(frames.len());
+        for frame in frames {
+            block_on(tx.send(frame)).unwrap();
+        }
+        drop(tx);
+        let metrics = Arc::new(Mutex::new(ClusteringMetrics::new()));
+        block_on(clustering_task(sinks, args, rx, None, metrics, None)).unwrap();
+
+        let clouds = clusters
+            .messages()
+            .iter()
+            .map(|(payload, _)| pointcloud(payload))
+            .collect();
+        let reports = quality
+            .messages()
+            .iter()
+            .map(|(payload, _)| serde_json::from_slice(&payload.to_bytes()).unwrap())
+            .collect();
+        (clouds, reports)
+    }
+
+    #[test]
+    fn test_clustering_mode_message_layout() {
+        let layout = |msg: &sensor_msgs::PointCloud2| {
+            let fields: Vec<_> = msg
+                .fields
+                .iter()
+                .map(|f| (f.name.clone(), f.offset, f.datatype, f.count))
+                .collect();
+            (fields, msg.height, msg.width, msg.point_step, msg.row_step)
+        };
+        let cluster_ids = |msg: &sensor_msgs::PointCloud2| {
+            let step = msg.point_step as usize;
+            msg.data
+                .chunks_exact(step)
+                .map(|p| f32::from_le_bytes(p[step - 4..].try_into().unwrap()))
+                .collect::<HashSet<_>>()
+        };
+
+        let mut args = Args::parse_from(["edgefirst-radarpub", "--clustering-mode", "dbscan"]);
+        args.resolve_clustering();
+        let dbscan = replay_clusters(args, replay_frames()).0;
+
+        let mut args = Args::parse_from(["edgefirst-radarpub", "--clustering-mode", "single"]);
+        args.resolve_clustering();
+        let single = replay_clusters(args, replay_frames()).0;
+
+        assert_eq!(dbscan.len(), single.len());
+        for (dbscan, single) in dbscan.iter().zip(single.iter()) {
+            assert_eq!(layout(dbscan), layout(single));
+            // Every target of the window is in the one tracked cluster.
+            let ids = cluster_ids(single);
+            assert_eq!(ids.len(), (single.width > 0) as usize, "{:?}", ids);
+            assert!(!ids.contains(&0.0));
+        }
+    }
+
+    #[test]
+    fn test_cluster_roles_field() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--cluster-roles"]);
+        let messages = replay_clusters(args, replay_frames()).0;
+        assert!(!messages.is_empty());
+
+        let mut roles = HashSet::new();
+        for msg in messages.iter() {
+            let names: Vec<_> = msg.fields.iter().map(|f| f.name.as_str()).collect();
+            assert_eq!(names.last(), Some(&"point_role"));
+            assert_eq!(msg.fields.last().unwrap().offset, 28);
+            assert_eq!(msg.point_step, 32);
+
+            for p in msg.data.chunks_exact(32) {
+                let cluster_id = f32::from_ne_bytes(p[24..28].try_into().unwrap());
+                assert_eq!(p[28] == PointRole::Noise as u8, cluster_id == 0.0);
+                roles.insert(p[28]);
+            }
+        }
+        assert!(roles.contains(&(PointRole::Core as u8)));
+    }
+
+    #[test]
+    fn test_clustering_sweep_eps() {
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering-eps",
+            "0.8",
+            "--eps-scale-long",
+            "2.5",
+            "--eps-scale-ultra-short",
+            "0.5",
+        ]);
+        assert_eq!(args.sweep_eps(FrequencySweep::Long as u8), 2.0);
+        assert_eq!(args.sweep_eps(FrequencySweep::Medium as u8), 0.8);
+        assert_eq!(args.sweep_eps(FrequencySweep::UltraShort as u8), 0.4);
+        assert_eq!(args.sweep_eps(7), 0.8);
+
+        // Range toggling between the long and short sweeps.
+        let mut toggled = replay_frames();
+        for (i, frame) in toggled.iter_mut().enumerate() {
+            frame.header.frequency_sweep = match i % 2 {
+                0 => FrequencySweep::Long as u8,
+                _ => FrequencySweep::Short as u8,
+            };
+        }
+        let (_, reports) = replay_clusters(args.clone(), toggled);
+        for (i, report) in reports.iter().enumerate() {
+            let expected = if i % 2 == 0 { 2.0 } else { 0.8 };
+            assert_eq!(report["eps"].as_f64(), Some(expected));
+        }
+
+        // A single short sweep is clustered as without the eps table.
+        let mut single = replay_frames();
+        for frame in single.iter_mut() {
+            frame.header.frequency_sweep = FrequencySweep::Short as u8;
+        }
+        let (clusters, reports) = replay_clusters(args, single.clone());
+        let baseline = Args::parse_from(["edgefirst-radarpub", "--clustering-eps", "0.8"]);
+        let (expected, _) = replay_clusters(baseline, single);
+        assert!(reports.iter().all(|r| r["eps"].as_f64() == Some(0.8)));
+        assert_eq!(clusters.len(), expected.len());
+        for (cloud, expected) in clusters.iter().zip(expected.iter()) {
+            assert_eq!(cloud.data, expected.data);
+        }
+    }
+
+    #[test]
+    fn test_dry_run_summary() {
+        let header = can::Header {
+            seconds: 0,
+            nanoseconds: 0,
+            cycle_duration: 0.055,
+            cycle_counter: 42,
+            n_targets: 3,
+            tx_antenna: 0,
+            frequency_sweep: 0,
+            center_frequency: 1,
+        };
+        let targets = [(12.5, -80.0), (3.25, -70.0), (40.0, -90.0)].map(|(range, power)| Target {
+            range,
+            power,
+            ..Default::default()
+        });
+        assert_eq!(
+            format_frame_summary(&header, &targets),
+            "frame 42: 3 targets range 3.25..40.00 m mean power -80.0 dBm"
+        );
+        assert_eq!(format_frame_summary(&header, &[]), "frame 42: 0 targets");
+
+        let cube = RadarCube {
+            timestamp: 0,
+            frame_counter: 7,
+            packets_captured: 10,
+            packets_skipped: 2,
+            missing_data: 1024,
+            reassembly_time: Duration::from_millis(5),
+            bin_properties: eth::BinProperties {
+                speed_per_bin: 0.25,
+                range_per_bin: 0.5,
+                bin_per_speed: 4.0,
+            },
+            data: ndarray::Array4::zeros((2, 3, 4, 5)),
+        };
+        assert_eq!(
+            format_cube_summary(&cube),
+            "cube 7: shape [2, 3, 4, 5] missing 1024 skipped 2"
+        );
+    }
+
+    #[test]
+    fn test_format_cpu_usage() {
+        let usage = [
+            ThreadUsage {
+                tid: 10,
+                name: "cube".to_string(),
+                percent: 12.5,
+            },
+            ThreadUsage {
+                tid: 11,
+                name: "cluster".to_string(),
+                percent: 85.0,
+            },
+        ];
+        let (payload, encoding) = format_cpu_usage(&usage).unwrap();
+        assert_eq!(
+            encoding,
+            Encoding::APPLICATION_CDR.with_schema("std_msgs/msg/Float32MultiArray")
+        );
+        type MultiArray = ((Vec<(String, u32, u32)>, u32), Vec<f32>);
+        let ((dims, offset), percent): MultiArray =
+            serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+        assert_eq!(dims, [(String::from("cube,cluster"), 2, 2)]);
+        assert_eq!(offset, 0);
+        assert_eq!(percent, [12.5, 85.0]);
+    }
+
+    #[test]
+    fn test_eps_sweep_task() {
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering-eps-sweep-start",
+            "0.5",
+            "--clustering-eps-sweep-end",
+            "2.5",
+            "--clustering-eps-sweep-steps",
+            "5",
+        ]);
+        let eps = args.eps_sweep().unwrap();
+        assert_eq!(eps, [0.5, 1.0, 1.5, 2.0, 2.5]);
+
+        let frames = replay_frames();
+        let sink = VecSink::new();
+        let (tx, rx) = kanal::bounded_async(frames.len());
+        for frame in frames.iter().cloned() {
+            block_on(tx.send(frame)).unwrap();
+        }
+        drop(tx);
+        block_on(eps_sweep_task(sink.clone(), args.clone(), eps, rx)).unwrap();
+
+        let messages = sink.messages();
+        assert_eq!(messages.len(), frames.len());
+        let (payload, encoding) = messages.last().unwrap();
+        assert_eq!(
+            *encoding,
+            Encoding::APPLICATION_CDR.with_schema("std_msgs/msg/Float32MultiArray")
+        );
+        type MultiArray = ((Vec<(String, u32, u32)>, u32), Vec<f32>);
+        let ((dims, offset), indices): MultiArray =
+            serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+        assert_eq!(dims, [(String::from("eps"), 5, 5)]);
+        assert_eq!(offset, 0);
+        assert_eq!(indices.len(), 5);
+        assert!(indices.iter().all(|i| *i >= 0.0));
+
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering-eps-sweep-start",
+            "1",
+            "--clustering-eps-sweep-end",
+            "3",
+            "--clustering-eps-sweep-steps",
+            "1",
+        ]);
+        assert_eq!(args.eps_sweep(), Some(vec![1.0]));
+        assert_eq!(Args::parse_from(["edgefirst-radarpub"]).eps_sweep(), None);
+        let incomplete = ["edgefirst-radarpub", "--clustering-eps-sweep-start", "1"];
+        assert!(Args::try_parse_from(incomplete).is_err());
+    }
+
+    #[test]
+    fn test_resolve_clustering() {
+        let resolve = |argv: &[&str]| {
+            let mut args = Args::parse_from(argv);
+            args.resolve_clustering();
+            (args.clustering, args.clustering_mode.unwrap())
+        };
+
+        let bin = "edgefirst-radarpub";
+        assert_eq!(resolve(&[bin]), (false, ClusteringAlgorithm::None));
+        assert_eq!(
+            resolve(&[bin, "--clustering"]),
+            (true, ClusteringAlgorithm::Dbscan)
+        );
+        assert_eq!(
+            resolve(&[bin, "--clustering-mode", "single"]),
+            (true, ClusteringAlgorithm::Single)
+        );
+        assert_eq!(
+            resolve(&[bin, "--clustering", "--clustering-mode", "none"]),
+            (false, ClusteringAlgorithm::None)
+        );
+    }
+
+    /// Shape of the synthetic cube as [chirp_types, range_gates, rx_channels,
+    /// doppler_bins].
+    const CUBE_SHAPE: [usize; 4] = [1, 16, 2, 32];
+
+    /// Build a padded SMS debug port packet with a message counter.
+    fn sms_packet(frame_counter: u32, message_counter: u16, flags: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x7E, 1, 14];
+        packet.extend_from_slice(&((SMS_PACKET_SIZE - 14) as u16).to_be_bytes());
+        // Debug application protocol, flags with the message counter present.
+        packet.extend_from_slice(&[5, 0, 0, 0, 0x01]);
+        packet.extend_from_slice(&message_counter.to_be_bytes());
+        packet.extend_from_slice(&[0, 0]);
+
+        packet.extend_from_slice(&frame_counter.to_be_bytes());
+        packet.extend_from_slice(&[flags, 0, 0, 0]);
+        packet.extend_from_slice(body);
+        packet.resize(SMS_PACKET_SIZE, 0);
+        packet
+    }
+
+    fn port_header(id: u32) -> Vec<u8> {
+        let mut header = id.to_be_bytes().to_vec();
+        header.resize(8, 0);
+        header.extend_from_slice(&1_000_000u64.to_be_bytes());
+        header.resize(24, 0);
+        // Big-endian payload.
+        header[20] = 1;
+        header
+    }
+
+    /// Packets of one radar cube frame, optionally without the data packet
+    /// with the given message counter.
+    fn cube_frame(frame_counter: u32, drop: Option<u16>) -> Vec<Vec<u8>> {
+        let [chirp_types, range_gates, rx_channels, doppler_bins] = CUBE_SHAPE;
+        let mut cube_header = vec![0; 40];
+        cube_header[24..26].copy_from_slice(&(range_gates as i16).to_be_bytes());
+        cube_header[28..30].copy_from_slice(&(doppler_bins as i16).to_be_bytes());
+        cube_header[30] = rx_channels as u8;
+        cube_header[31] = chirp_types as u8;
+
+        let mut start = port_header(5);
+        start.extend_from_slice(&cube_header);
+        let mut packets = vec![sms_packet(
+            frame_counter,
+            0,
+            DebugHeader::START_OF_FRAME,
+            &start,
+        )];
+
+        // The start of frame carries 343 elements and every data packet 359.
+        for counter in 1..=2 {
+            if drop != Some(counter) {
+                packets.push(sms_packet(
+                    frame_counter,
+                    counter,
+                    DebugHeader::FRAME_DATA,
+                    &[],
+                ));
+            }
+        }
+
+        let mut footer = port_header(63);
+        for value in [0.1f32, 0.5, 10.0] {
+            footer.extend_from_slice(&value.to_be_bytes());
+        }
+        packets.push(sms_packet(
+            frame_counter,
+            3,
+            DebugHeader::FRAME_FOOTER,
+            &footer,
+        ));
+        packets
+    }
+
+    /// Run the cube task over three frames where the second frame is missing
+    /// a data packet, returning the cube batch metrics.
+    fn run_cube_task(args: &Args, sinks: CubeSinks<VecSink>) -> BatchMetrics {
+        let mut packets = cube_frame(1, None);
+        packets.extend(cube_frame(2, Some(2)));
+        packets.extend(cube_frame(3, None));
+
+        let (tx, rx) = byte_channel(packets.len(), Arc::new(ByteGauge::new(usize::MAX)));
+        for packet in packets {
+            block_on(tx.send(packet)).unwrap();
+        }
+        drop(tx);
+        let cube_batch = Arc::new(Mutex::new(BatchMetrics::new()));
+        block_on(cube_task(rx, sinks, args, cube_batch.clone())).unwrap();
+        let metrics = cube_batch.lock().unwrap().clone();
+        metrics
+    }
+
+    #[test]
+    fn test_cube_task_publishes_cubes() {
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        let sinks = CubeSinks {
+            cube: VecSink::new(),
+            stats: VecSink::new(),
+            beamformed: None,
+            file: None,
+        };
+        let (cubes, stats) = (sinks.cube.clone(), sinks.stats.clone());
+        run_cube_task(&args, sinks);
+
+        // The frame with a dropped data packet is incomplete and skipped.
+        let messages = cubes.messages();
+        assert_eq!(messages.len(), 2);
+        for (payload, encoding) in &messages {
+            assert_eq!(
+                *encoding,
+                Encoding::APPLICATION_CDR.with_schema("edgefirst_msgs/msg/RadarCube")
+            );
+            let cube: edgefirst_msgs::RadarCube =
+                serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+            assert_eq!(cube.header.frame_id, "radar");
+            assert_eq!(cube.timestamp, 1_000_000);
+            assert_eq!(cube.shape, vec![1, 16, 2, 64]);
+            assert_eq!(cube.cube.len(), CUBE_SHAPE.iter().product::<usize>() * 2);
+            assert_eq!(cube.scales, vec![1.0, 0.5, 1.0, 0.1]);
+            assert!(cube.is_complex);
+        }
+        assert!(stats.is_empty());
+    }
+
+    #[test]
+    fn test_format_cube_round_trip() {
+        let data = ndarray::Array4::from_shape_fn((2, 3, 4, 5), |(s, r, c, d)| {
+            let i = (s * 60 + r * 20 + c * 5 + d) as i16;
+            Complex::new(i * 7 - 200, 1000 - i * 3)
+        });
+        let cube = RadarCube {
+            timestamp: 1_700_000_000_123_456,
+            frame_counter: 0,
+            packets_captured: 0,
+            packets_skipped: 0,
+            missing_data: 0,
+            reassembly_time: Duration::ZERO,
+            bin_properties: eth::BinProperties {
+                speed_per_bin: 0.25,
+                range_per_bin: 0.5,
+                bin_per_speed: 4.0,
+            },
+            data,
+        };
+
+        let (payload, _) = format_cube(cube.clone(), "radar").unwrap();
+        let msg: edgefirst_msgs::RadarCube = serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+        assert_eq!(eth::cube_from_msg(&msg).unwrap(), cube);
+
+        // A magnitude message keeps its samples as the real parts.
+        let magnitude = cube.magnitude();
+        let msg = edgefirst_msgs::RadarCube {
+            shape: vec![2, 3, 4, 5],
+            cube: magnitude.iter().map(|m| m.round() as i16).collect(),
+            is_complex: false,
+            ..msg
+        };
+        let restored = eth::cube_from_msg(&msg).unwrap();
+        assert_eq!(restored.data.shape(), cube.data.shape());
+        assert!(restored.data.iter().all(|sample| sample.im == 0));
+        assert!(restored
+            .magnitude()
+            .iter()
+            .zip(magnitude.iter())
+            .all(|(a, b)| (a - b).abs() <= 0.5));
+    }
+
+    #[test]
+    fn test_cube_task_batches_cubes() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--cube-batch", "4"]);
+        let sinks = CubeSinks {
+            cube: VecSink::new(),
+            stats: VecSink::new(),
+            beamformed: None,
+            file: None,
+        };
+        let cubes = sinks.cube.clone();
+        let metrics = run_cube_task(&args, sinks);
+
+        // The partial batch is published in order when the channel closes.
+        let messages = cubes.messages();
+        assert_eq!(messages.len(), 2);
+        let stamps: Vec<_> = messages
+            .iter()
+            .map(|(payload, _)| {
+                let cube: edgefirst_msgs::RadarCube =
+                    serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+                assert_eq!(cube.timestamp, 1_000_000);
+                (cube.header.stamp.sec, cube.header.stamp.nanosec)
+            })
+            .collect();
+        assert!(stamps[0] <= stamps[1]);
+        assert_eq!(metrics.batches(), 1);
+        assert_eq!(metrics.messages(), 2);
+
+        // Without batching every cube is its own batch.
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        assert_eq!(args.cube_batch, 1);
+        let sinks = CubeSinks {
+            cube: VecSink::new(),
+            stats: VecSink::new(),
+            beamformed: None,
+            file: None,
+        };
+        let metrics = run_cube_task(&args, sinks);
+        assert_eq!(metrics.batches(), 2);
+    }
+
+    #[test]
+    fn test_cube_task_writes_raw_file() {
+        let path = std::env::temp_dir().join(format!("radarpub_cubes_{}.raw", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--cube-sink",
+            "raw",
+            "--cube-sink-path",
+            path.to_str().unwrap(),
+        ]);
+        let sinks = CubeSinks {
+            cube: VecSink::new(),
+            stats: VecSink::new(),
+            beamformed: None,
+            file: Some(CubeWriter::new(CubeFileFormat::Raw, &args.cube_sink_path, 4).unwrap()),
+        };
+        let cubes = sinks.cube.clone();
+        // The writer is flushed when the task exits and drops the sinks.
+        run_cube_task(&args, sinks);
+        assert!(cubes.is_empty());
+
+        let written = dump::cube_dump(&path).unwrap();
+        assert_eq!(written.len(), 2);
+        for cube in &written {
+            assert_eq!(cube.header.frame_id, "radar");
+            assert_eq!(cube.shape, vec![1, 16, 2, 64]);
+            assert_eq!(cube.cube.len(), CUBE_SHAPE.iter().product::<usize>() * 2);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_cube_task_publishes_rolling_stats() {
+        // One frame in 40 loses a data packet, a 2.5% drop rate.
+        let packets: Vec<_> = (1..=CUBE_STATS_FRAMES as u32)
+            .flat_map(|frame| cube_frame(frame, (frame % 40 == 0).then_some(1)))
+            .collect();
+        let (tx, rx) = byte_channel(packets.len(), Arc::new(ByteGauge::new(usize::MAX)));
+        for packet in packets {
+            block_on(tx.send(packet)).unwrap();
+        }
+        drop(tx);
+
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        let sinks = CubeSinks {
+            cube: VecSink::new(),
+            stats: VecSink::new(),
+            beamformed: None,
+            file: None,
+        };
+        let stats = sinks.stats.clone();
+        let cube_batch = Arc::new(Mutex::new(BatchMetrics::new()));
+        block_on(cube_task(rx, sinks, &args, cube_batch)).unwrap();
+
+        let messages = stats.messages();
+        assert_eq!(messages.len(), 1);
+        let (payload, encoding) = &messages[0];
+        assert_eq!(*encoding, Encoding::APPLICATION_JSON);
+        let stats: serde_json::Value = serde_json::from_slice(&payload.to_bytes()).unwrap();
+        assert_eq!(stats["total_frames"], CUBE_STATS_FRAMES as u64);
+        assert_eq!(stats["rolling"]["frames"], CUBE_STATS_FRAMES as u64);
+        assert_eq!(stats["rolling"]["drop_rate"], 0.025);
+        assert_eq!(stats["publish"]["published"], 1053);
+        assert!(stats["rolling"]["fps"].is_number());
+        assert!(stats["rolling"]["reassembly_ms_max"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn test_cube_task_publishes_beamformed() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--beamforming-angles", "-30,0,30"]);
+        assert_eq!(args.beamforming_angles, vec![-30.0, 0.0, 30.0]);
+        let sinks = CubeSinks {
+            cube: VecSink::new(),
+            stats: VecSink::new(),
+            beamformed: Some(VecSink::new()),
+            file: None,
+        };
+        let beamformed = sinks.beamformed.clone().unwrap();
+        run_cube_task(&args, sinks);
+
+        let messages = beamformed.messages();
+        assert_eq!(messages.len(), 2);
+        for (payload, _) in &messages {
+            let cube: edgefirst_msgs::RadarCube =
+                serde_cdr::deserialize(&payload.to_bytes()).unwrap();
+            assert_eq!(cube.shape, vec![3, 16, 64]);
+            assert_eq!(cube.cube.len(), 3 * 16 * 64);
+            assert_eq!(cube.scales, vec![1.0, 0.5, 0.1]);
+            assert_eq!(
+                cube.layout,
+                vec![
+                    edgefirst_msgs::radar_cube_dimension::AZIMUTH,
+                    edgefirst_msgs::radar_cube_dimension::RANGE,
+                    edgefirst_msgs::radar_cube_dimension::DOPPLER,
+                ]
+            );
+        }
+    }
+
+    /// Sensor response to an instruction with result 0 and the given value.
+    fn response(value: u32) -> [can::Packet; 4] {
+        let value = value.to_le_bytes();
+        [
+            can::Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 5, 0, 0, 1, 0, 0]),
+            },
+            can::Packet { id: 0x700, data: 0 },
+            can::Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 2, 0, value[0], value[1], value[2], value[3]]),
+            },
+            can::Packet { id: 0x700, data: 0 },
+        ]
+    }
+
+    #[test]
+    fn test_topic_prefix() {
+        let mut args = Args::parse_from(["edgefirst-radarpub"]);
+        args.resolve_topics();
+        assert_eq!(args.targets_topic, "rt/radar/targets");
+        assert_eq!(args.cube_stats_topic, "rt/radar/cube_stats");
+        assert_eq!(args.topic("info"), "rt/radar/info");
+        assert_eq!(args.global_topic("tf_static"), "rt/tf_static");
+
+        let mut args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--topic-prefix",
+            "robot1",
+            "--node-name",
+            "front_radar",
+            "--clusters-topic",
+            "tracks",
+            "--cube-topic",
+            "lab/cube",
+        ]);
+        args.resolve_topics();
+        assert_eq!(args.targets_topic, "robot1/front_radar/targets");
+        assert_eq!(args.clusters_topic, "robot1/front_radar/tracks");
+        assert_eq!(args.cube_topic, "lab/cube");
+        assert_eq!(args.topic("device"), "robot1/front_radar/device");
+        assert_eq!(args.global_topic("tf_static"), "robot1/tf_static");
+
+        let args = Args::parse_from(["edgefirst-radarpub", "--topic-prefix", ""]);
+        assert_eq!(args.topic("info"), "radar/info");
+    }
+
+    #[test]
+    fn test_zenoh_config() {
+        let config = |argv: &[&str]| {
+            let args = Args::parse_from(
+                ["edgefirst-radarpub"]
+                    .into_iter()
+                    .chain(argv.iter().copied()),
+            );
+            Config::try_from(args)
+        };
+        let interface = |config: &Config| config.get_json("scouting/multicast/interface").unwrap();
+
+        // Zenoh picks the scouting interface unless one is given.
+        let default = config(&[]).unwrap();
+        assert_ne!(interface(&default), "\"lo\"");
+        let eth0 = config(&["--scout-interface", "eth0"]).unwrap();
+        assert_eq!(interface(&eth0), "\"eth0\"");
+        assert_eq!(
+            interface(&config(&["--scout-interface", ""]).unwrap()),
+            interface(&default)
+        );
+
+        let disabled = config(&["--no-multicast-scouting", "--scout-interface", "lo"]).unwrap();
+        assert_eq!(
+            disabled.get_json("scouting/multicast/enabled").unwrap(),
+            "false"
+        );
+        assert_eq!(interface(&disabled), "\"lo\"");
+
+        // A client needs a router to connect to.
+        assert!(matches!(
+            config(&["--mode", "client"]),
+            Err(args::Error::ClientWithoutEndpoints)
+        ));
+        let client = config(&["--mode", "client", "--connect", "tcp/10.0.0.1:7447"]).unwrap();
+        assert!(client
+            .get_json("connect/endpoints")
+            .unwrap()
+            .contains("tcp/10.0.0.1:7447"));
+        assert!(config(&["--mode", "client", "--endpoints-file", "/tmp/endpoints"]).is_ok());
+        assert!(config(&["--mode", "client", "--endpoints-file", ""]).is_err());
+    }
+
+    #[test]
+    fn test_zenoh_config_table() {
+        const TLS: &[&str] = &[
+            "--tls-ca",
+            "/etc/radarpub/ca.pem",
+            "--tls-cert",
+            "/etc/radarpub/cert.pem",
+            "--tls-key",
+            "/etc/radarpub/key.pem",
+        ];
+        // Arguments and the expected JSON of the config keys, None for an
+        // unset key.
+        let cases: &[(&[&str], &[(&str, Option<&str>)])] = &[
+            (
+                &[],
+                &[
+                    ("mode", Some(r#""peer""#)),
+                    ("transport/link/tls/root_ca_certificate", None),
+                    ("transport/link/tls/listen_certificate", None),
+                ],
+            ),
+            (
+                &["--mode", "client", "--connect", "tcp/10.0.0.1:7447"],
+                &[
+                    ("mode", Some(r#""client""#)),
+                    ("connect/endpoints", Some(r#"["tcp/10.0.0.1:7447"]"#)),
+                ],
+            ),
+            (
+                &[
+                    "--connect",
+                    "tcp/10.0.0.1:7447",
+                    "--connect",
+                    "udp/10.0.0.2:7447",
+                    "--listen",
+                    "tcp/0.0.0.0:7447",
+                    "--listen",
+                    "udp/0.0.0.0:7447",
+                ],
+                &[
+                    (
+                        "connect/endpoints",
+                        Some(r#"["tcp/10.0.0.1:7447","udp/10.0.0.2:7447"]"#),
+                    ),
+                    (
+                        "listen/endpoints",
+                        Some(r#"["tcp/0.0.0.0:7447","udp/0.0.0.0:7447"]"#),
+                    ),
+                ],
+            ),
+            (&["--mode", "router", "--listen", "tls/0.0.0.0:7447"], &[]),
+            (
+                &[
+                    "--tls-ca",
+                    "/etc/radarpub/ca.pem",
+                    "--connect",
+                    "quic/10.0.0.1:7447",
+                ],
+                &[
+                    (
+                        "transport/link/tls/root_ca_certificate",
+                        Some(r#""/etc/radarpub/ca.pem""#),
+                    ),
+                    ("transport/link/tls/connect_certificate", None),
+                ],
+            ),
+            (
+                &[TLS, &["--listen", "quic/0.0.0.0:7447"]].concat(),
+                &[
+                    ("listen/endpoints", Some(r#"["quic/0.0.0.0:7447"]"#)),
+                    (
+                        "transport/link/tls/root_ca_certificate",
+                        Some(r#""/etc/radarpub/ca.pem""#),
+                    ),
+                    (
+                        "transport/link/tls/listen_certificate",
+                        Some(r#""/etc/radarpub/cert.pem""#),
+                    ),
+                    (
+                        "transport/link/tls/listen_private_key",
+                        Some(r#""/etc/radarpub/key.pem""#),
+                    ),
+                    (
+                        "transport/link/tls/connect_certificate",
+                        Some(r#""/etc/radarpub/cert.pem""#),
+                    ),
+                    (
+                        "transport/link/tls/connect_private_key",
+                        Some(r#""/etc/radarpub/key.pem""#),
+                    ),
+                ],
+            ),
+        ];
+
+        for (argv, keys) in cases {
+            let args = Args::parse_from(
+                ["edgefirst-radarpub"]
+                    .into_iter()
+                    .chain(argv.iter().copied()),
+            );
+            match Config::try_from(args) {
+                Ok(config) => {
+                    for (key, expected) in keys.iter() {
+                        let value = config.get_json(key).unwrap();
+                        assert_eq!(value, expected.unwrap_or("null"), "{} with {:?}", key, argv);
+                    }
+                }
+                // Listening on TLS without a certificate.
+                Err(e) => {
+                    assert!(keys.is_empty(), "{:?}: {}", argv, e);
+                    assert!(matches!(
+                        e,
+                        args::Error::TlsListenWithoutCertificate(endpoint)
+                            if endpoint == "tls/0.0.0.0:7447"
+                    ));
+                }
+            }
+        }
+
+        // The certificate and key go together.
+        assert!(Args::try_parse_from(["edgefirst-radarpub", "--tls-cert", "cert.pem"]).is_err());
+        assert!(Args::try_parse_from(["edgefirst-radarpub", "--tls-key", "key.pem"]).is_err());
+    }
+
+    #[test]
+    fn test_targets_include_track_id() {
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--clustering",
+            "--targets-include-track-id",
+        ]);
+        assert!(args.annotate_targets);
+        assert!(
+            Args::try_parse_from(["edgefirst-radarpub", "--targets-include-track-id"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_rt_priority_args() {
+        // The UDP port keeps its former priority, the CAN stream is opt-in.
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        assert_eq!(args.rt_priority_can, 0);
+        assert_eq!(args.rt_priority_udp, 10);
+
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--rt-priority-can",
+            "20",
+            "--rt-priority-udp",
+            "0",
+        ]);
+        assert_eq!(args.rt_priority_can, 20);
+        assert_eq!(args.rt_priority_udp, 0);
+
+        assert!(Args::try_parse_from(["edgefirst-radarpub", "--rt-priority-can", "100"]).is_err());
+        assert!(Args::try_parse_from(["edgefirst-radarpub", "--rt-priority-udp", "-1"]).is_err());
+
+        // A disabled priority leaves the thread on the normal scheduler.
+        let policy = thread::spawn(|| set_thread_priority("stream", SchedPolicy::Fifo, 0))
+            .join()
+            .unwrap();
+        assert_eq!(policy, SchedPolicy::Other);
+    }
+
+    #[test]
+    fn test_read_only_params() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--read-only-params"]);
+        let can = CanInjector::new();
+        for value in [0, 1, 3, 2] {
+            can.extend(response(value));
+        }
+
+        let (requested, confirmed) = block_on(configure_parameters(
+            &can,
+            &args,
+            &RecvResponseSettings::default(),
+        ))
+        .unwrap();
+        assert_eq!(can.pending(), 0);
+        assert_eq!(requested.frequency_sweep, FrequencySweep::Short as u32);
+
+        // Four parameter reads (message type 3) and no writes.
+        let sent = can.sent();
+        assert_eq!(sent.len(), 12);
+        for message1 in sent.iter().skip(1).step_by(3) {
+            assert_eq!(message1.data.to_le_bytes()[3], 3);
+        }
+
+        let state = RadarState {
+            frame_id: "base_link".to_string(),
+            cube: false,
+            device: DeviceInfo::default(),
+            requested,
+            confirmed,
+            zenoh_reconnects: Arc::default(),
+            status: None,
+            blockage_alarm: false,
+        };
+        let (msg, _) = state.format_info();
+        let info: RadarInfo = serde_cdr::deserialize(&msg.to_bytes()).unwrap();
+        assert_eq!(info.center_frequency, "low");
+        assert_eq!(info.frequency_sweep, "medium");
+        assert_eq!(info.range_toggle, "medium-long");
+        assert_eq!(info.detection_sensitivity, "high");
+
+        let (msg, _) = state.format_device();
+        let device: serde_json::Value =
+            serde_json::from_str(&msg.try_to_string().unwrap()).unwrap();
+        assert!(device["uat_errors"].is_object());
+        assert!(device["status"].is_null());
+
+        let mut state = state;
+        let status = SensorStatus {
+            temperature: 41.0,
+            supply_voltage: 12.5,
+            blocked: true,
+        };
+        state.set_status(status, true);
+        let (msg, _) = state.format_device();
+        let device: serde_json::Value =
+            serde_json::from_str(&msg.try_to_string().unwrap()).unwrap();
+        assert_eq!(device["status"]["temperature"], 41.0);
+        assert_eq!(device["status"]["supply_voltage"], 12.5);
+        assert_eq!(device["status"]["blocked"], true);
+        assert_eq!(device["blockage_alarm"], true);
+    }
+
+    fn requested_values(args: &Args) -> [u32; 4] {
+        [
+            args.center_frequency as u32,
+            args.frequency_sweep as u32,
+            args.range_toggle as u32,
+            args.detection_sensitivity as u32,
+        ]
+    }
+
+    #[test]
+    fn test_save_params_skipped_when_unchanged() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--save-params-to-sensor"]);
+
+        // The sensor already holds the requested values, reads then writes.
+        let can = CanInjector::new();
+        for _ in 0..2 {
+            for value in requested_values(&args) {
+                can.extend(response(value));
+            }
+        }
+        let (requested, confirmed) = block_on(configure_parameters(
+            &can,
+            &args,
+            &RecvResponseSettings::default(),
+        ))
+        .unwrap();
+        assert_eq!(confirmed, requested);
+        assert_eq!(can.pending(), 0);
+
+        // Four reads, four writes and no save command.
+        let sent = can.sent();
+        assert_eq!(sent.len(), 24);
+        let types: Vec<_> = sent
+            .iter()
+            .skip(1)
+            .step_by(3)
+            .map(|message1| message1.data.to_le_bytes()[3])
+            .collect();
+        assert_eq!(types, [3, 3, 3, 3, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_save_params_after_change() {
+        let args = Args::parse_from(["edgefirst-radarpub", "--save-params-to-sensor"]);
+        let mut current = requested_values(&args);
+        current[3] = (current[3] + 1) % 3;
+
+        let can = CanInjector::new();
+        for value in current.into_iter().chain(requested_values(&args)) {
+            can.extend(response(value));
+        }
+        can.extend(response(0));
+        block_on(configure_parameters(
+            &can,
+            &args,
+            &RecvResponseSettings::default(),
+        ))
+        .unwrap();
+        assert_eq!(can.pending(), 0);
+
+        // The save command follows the verified writes.
+        let sent = can.sent();
+        assert_eq!(sent.len(), 27);
+        let save = sent[25].data.to_le_bytes();
+        assert_eq!(save[3], 0);
+        assert_eq!(u16::from_le_bytes([save[4], save[5]]), 344);
+    }
+
+    #[test]
+    fn test_can_id_args() {
+        let args = Args::parse_from(["edgefirst-radarpub"]);
+        assert_eq!(args.can_ids(), CanIds::default());
+
+        let args = Args::parse_from([
+            "edgefirst-radarpub",
+            "--can-id-base",
+            "0x18FF0000",
+            "--can-instruction-id",
+            "419368960",
+        ]);
+        let ids = args.can_ids();
+        assert_eq!(ids.base, 0x18FF_0000);
+        assert_eq!(ids.instruction, 0x18FF_1000);
+        assert_eq!(ids.response, 0x18FF_0300);
+
+        for base in ["4", "0x1FFFFFFF", "0x400g"] {
+            assert!(Args::try_parse_from(["edgefirst-radarpub", "--can-id-base", base]).is_err());
+        }
+        assert!(
+            Args::try_parse_from(["edgefirst-radarpub", "--can-instruction-id", "0x20000000"])
+                .is_err()
+        );
+    }
 }
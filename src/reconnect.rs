@@ -0,0 +1,295 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Zenoh connect endpoints reloaded from a file.
+//!
+//! The endpoints file lists one Zenoh endpoint per line, blank lines and
+//! lines starting with `#` are ignored.  Zenoh cannot change the connect
+//! endpoints of an open session, so when the file changes a new session is
+//! opened and published through a [`watch`] channel.  Publishers declared
+//! with [`ReconnectingPublisher`] move to the new session on their next
+//! message while the CAN and UDP pipelines keep running.
+
+use crate::sink::Sink;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{watch, Mutex};
+use tracing::{debug, info, warn};
+use zenoh::{
+    bytes::{Encoding, ZBytes},
+    pubsub::Publisher,
+    qos::{CongestionControl, Priority},
+    Config, Session,
+};
+
+/// Interval between reads of the endpoints file.
+pub const ENDPOINTS_POLL: Duration = Duration::from_secs(1);
+
+/// The current Zenoh session and its generation, the number of reconnects
+/// before it was opened.
+pub type SessionWatch = watch::Receiver<(u64, Session)>;
+
+/// Endpoints listed in the text of an endpoints file.
+pub fn parse_endpoints(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read the endpoints file at `path`.
+pub fn read_endpoints(path: &Path) -> Result<Vec<String>, io::Error> {
+    Ok(parse_endpoints(&fs::read_to_string(path)?))
+}
+
+/// Open a session with the configuration connecting to `endpoints` instead
+/// of its own connect endpoints.
+pub async fn open_with_endpoints(
+    config: &Config,
+    endpoints: &[String],
+) -> Result<Session, zenoh::Error> {
+    let mut config = config.clone();
+    config.insert_json5(
+        "connect/endpoints",
+        &serde_json::to_string(endpoints).unwrap(),
+    )?;
+    zenoh::open(config).await
+}
+
+/// Poll the endpoints file and reconnect when its endpoints differ from
+/// `endpoints`, the endpoints of the current session.
+///
+/// An empty or unreadable file keeps the current session.  Should the new
+/// session fail to open the current one is kept and the reconnect retried
+/// on the next poll.  The old session is not closed as subscribers declared
+/// on it at startup may still use it.
+pub async fn watch_endpoints(
+    path: PathBuf,
+    config: Config,
+    poll: Duration,
+    sessions: watch::Sender<(u64, Session)>,
+    mut endpoints: Vec<String>,
+    reconnects: Arc<AtomicU64>,
+) {
+    let mut interval = tokio::time::interval(poll);
+
+    loop {
+        interval.tick().await;
+
+        let next = match read_endpoints(&path) {
+            Ok(next) => next,
+            Err(e) => {
+                debug!("{} read error: {:?}", path.display(), e);
+                continue;
+            }
+        };
+        if next.is_empty() || next == endpoints {
+            continue;
+        }
+
+        match open_with_endpoints(&config, &next).await {
+            Ok(session) => {
+                let generation = reconnects.fetch_add(1, Ordering::Relaxed) + 1;
+                info!(
+                    "zenoh endpoints changed to {:?}, reconnected ({} reconnects)",
+                    next, generation
+                );
+                sessions.send_replace((generation, session));
+                endpoints = next;
+            }
+            Err(e) => warn!("zenoh endpoints {:?} open error: {:?}", next, e),
+        }
+    }
+}
+
+/// Declare a publisher which drops messages when the network is congested.
+async fn declare_publisher(
+    session: &Session,
+    topic: &str,
+    priority: Priority,
+) -> Result<Publisher<'static>, zenoh::Error> {
+    session
+        .declare_publisher(topic.to_string())
+        .priority(priority)
+        .congestion_control(CongestionControl::Drop)
+        .await
+}
+
+/// Publisher which is declared again on the new session after a reconnect.
+///
+/// Messages published while the publisher is declared again wait for it,
+/// the bounded channels in front of the publishing tasks buffer the radar
+/// data meanwhile.
+pub struct ReconnectingPublisher {
+    sessions: SessionWatch,
+    topic: String,
+    priority: Priority,
+    publisher: Mutex<(u64, Publisher<'static>)>,
+}
+
+impl ReconnectingPublisher {
+    /// Declare the publisher on the current session.
+    pub async fn declare(
+        sessions: SessionWatch,
+        topic: &str,
+        priority: Priority,
+    ) -> Result<Self, zenoh::Error> {
+        let (generation, session) = sessions.borrow().clone();
+        let publisher = declare_publisher(&session, topic, priority).await?;
+
+        Ok(ReconnectingPublisher {
+            sessions,
+            topic: topic.to_string(),
+            priority,
+            publisher: Mutex::new((generation, publisher)),
+        })
+    }
+}
+
+impl Sink for ReconnectingPublisher {
+    async fn publish(&self, payload: ZBytes, encoding: Encoding) -> Result<(), zenoh::Error> {
+        let (generation, session) = self.sessions.borrow().clone();
+        let mut publisher = self.publisher.lock().await;
+        if publisher.0 != generation {
+            debug!("{} declared on session {}", self.topic, generation);
+            *publisher = (
+                generation,
+                declare_publisher(&session, &self.topic, self.priority).await?,
+            );
+        }
+        publisher.1.put(payload).encoding(encoding).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use zenoh::{handlers::FifoChannelHandler, pubsub::Subscriber, sample::Sample};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    fn local_config(listen: &[String]) -> Config {
+        let mut config = Config::default();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        config
+            .insert_json5("listen/endpoints", &serde_json::to_string(listen).unwrap())
+            .unwrap();
+        config
+    }
+
+    async fn listener(port: u16) -> Session {
+        let endpoint = format!("tcp/127.0.0.1:{}", port);
+        zenoh::open(local_config(&[endpoint])).await.unwrap()
+    }
+
+    /// Publish until the subscriber receives a message, the subscription
+    /// takes a moment to reach the publishing session.
+    async fn publish_until_received(
+        publisher: &ReconnectingPublisher,
+        subscriber: &Subscriber<FifoChannelHandler<Sample>>,
+    ) -> bool {
+        for _ in 0..50 {
+            publisher
+                .publish(ZBytes::from("target"), Encoding::TEXT_PLAIN)
+                .await
+                .unwrap();
+            let received =
+                tokio::time::timeout(Duration::from_millis(100), subscriber.recv_async()).await;
+            if matches!(received, Ok(Ok(_))) {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn test_parse_endpoints() {
+        let text = "# radar router\ntcp/192.168.1.1:7447\n\n  udp/10.0.0.2:7447  \n";
+        assert_eq!(
+            parse_endpoints(text),
+            ["tcp/192.168.1.1:7447", "udp/10.0.0.2:7447"]
+        );
+        assert!(parse_endpoints("# none yet\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_reconnect_endpoints_file() {
+        block_on(async {
+            let dir =
+                std::env::temp_dir().join(format!("radarpub-reconnect-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("endpoints");
+
+            let first_port = free_port();
+            fs::write(&path, format!("tcp/127.0.0.1:{}\n", first_port)).unwrap();
+            let first = listener(first_port).await;
+            let first_sub = first.declare_subscriber("rt/radar/targets").await.unwrap();
+
+            let config = local_config(&[]);
+            let endpoints = read_endpoints(&path).unwrap();
+            let session = open_with_endpoints(&config, &endpoints).await.unwrap();
+            let (tx, sessions) = watch::channel((0, session));
+            let reconnects = Arc::new(AtomicU64::new(0));
+            tokio::spawn(watch_endpoints(
+                path.clone(),
+                config,
+                Duration::from_millis(50),
+                tx,
+                endpoints,
+                reconnects.clone(),
+            ));
+
+            let publisher = ReconnectingPublisher::declare(
+                sessions.clone(),
+                "rt/radar/targets",
+                Priority::DataHigh,
+            )
+            .await
+            .unwrap();
+            assert!(publish_until_received(&publisher, &first_sub).await);
+
+            // Move to a listener started after radarpub.
+            let second_port = free_port();
+            let second = listener(second_port).await;
+            let second_sub = second.declare_subscriber("rt/radar/targets").await.unwrap();
+            fs::write(&path, format!("# moved\ntcp/127.0.0.1:{}\n", second_port)).unwrap();
+
+            for _ in 0..100 {
+                if reconnects.load(Ordering::Relaxed) > 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            assert_eq!(reconnects.load(Ordering::Relaxed), 1);
+            assert_eq!(sessions.borrow().0, 1);
+            assert!(publish_until_received(&publisher, &second_sub).await);
+
+            fs::remove_dir_all(&dir).unwrap();
+        });
+    }
+}
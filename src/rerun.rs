@@ -2,17 +2,22 @@
 // Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
 
 mod can;
+#[allow(dead_code)]
+mod dump;
 mod eth;
 mod net;
+mod viewer;
 
 use clap::Parser;
-use eth::{RadarCube, RadarCubeReader, SMSError, TransportHeaderSlice, SMS_PACKET_SIZE};
+use dump::NpzExporter;
+use eth::{RadarCubeReader, SMSError, TransportHeaderSlice, SMS_PACKET_SIZE};
 use log::{debug, error, trace};
-use ndarray::{s, Array2};
-use ndarray_npy::write_npy;
-use num::complex::Complex32;
 use rerun::RecordingStream;
 use std::{fs::File, net::Ipv4Addr, thread};
+use viewer::{
+    CubeSlice, CubeSlicer, PowerNormaliser, RateLimiter, DEFAULT_COLORMAP_EMA_ALPHA,
+    DEFAULT_CUBE_RATE, RADAR_TIMELINE,
+};
 
 mod common;
 
@@ -35,10 +40,25 @@ struct Args {
     #[arg(short, long)]
     port: Option<u16>,
 
-    /// Save Numpy files to this directory, one per frame.
+    /// Save Numpy archives with the cube and its metadata to this directory,
+    /// one per exported frame.
     #[arg(short, long)]
     numpy: Option<String>,
 
+    /// Export only every Nth frame with --numpy.
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    numpy_every: u64,
+
+    /// Maximum rate in Hz of the cube tensor logs, the bin properties and
+    /// stats are still logged for every cube. Defaults to 5 Hz for a viewer
+    /// and to every cube when recording to a file, 0 logs every cube.
+    #[arg(long, value_name = "HZ")]
+    rerun_cube_rate: Option<f64>,
+
+    /// Sequence and rx antenna of the displayed cube slice.
+    #[arg(long, value_name = "SEQ,RX", default_value = "0,0")]
+    rerun_slice: CubeSlice,
+
     /// Read from a pcapng file instead of a live interface.
     #[arg()]
     pcap: Option<String>,
@@ -51,12 +71,31 @@ struct Args {
     #[cfg(feature = "can")]
     #[arg(long)]
     device: Option<String>,
+
+    /// Mirror the y axis of the targets like radarpub --mirror.
+    #[cfg(feature = "can")]
+    #[arg(long)]
+    mirror: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
     let args = Args::parse();
 
+    let mut exporter = args
+        .numpy
+        .as_ref()
+        .map(|dir| NpzExporter::new(dir, args.numpy_every))
+        .transpose()?;
+
+    // Recordings keep every cube unless a rate is given.
+    let cube_rate = args.rerun_cube_rate.unwrap_or(match args.record {
+        Some(_) => 0.0,
+        None => DEFAULT_CUBE_RATE,
+    });
+    let mut limiter = RateLimiter::new(cube_rate);
+    let mut slicer = CubeSlicer::new(args.rerun_slice);
+
     let rr = if let Some(addr) = args.connect {
         let port = args.port.unwrap_or(9876);
         Some(
@@ -74,11 +113,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     if let Some(pcap) = args.pcap {
-        pcap_loop(&rr, &pcap, &args.numpy)?;
+        pcap_loop(&rr, &pcap, &mut exporter, &mut limiter, &mut slicer)?;
     } else {
         #[cfg(feature = "can")]
         if let Some(device) = args.device {
             let rr2 = rr.clone();
+            let mirror = args.mirror;
 
             if args.cube {
                 let cube_thread =
@@ -89,7 +129,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .enable_all()
                                 .build()
                                 .unwrap()
-                                .block_on(udp_loop(&rr, &args.numpy))
+                                .block_on(udp_loop(&rr, &mut exporter, &mut limiter, &mut slicer))
                                 .unwrap();
                         })?;
                 cube_thread.join().unwrap();
@@ -102,7 +142,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .enable_all()
                                 .build()
                                 .unwrap()
-                                .block_on(can_loop(&rr2, Some(device)));
+                                .block_on(can_loop(&rr2, Some(device), mirror));
                         })?;
                 can_thread.join().unwrap();
             }
@@ -118,7 +158,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .enable_all()
                         .build()
                         .unwrap()
-                        .block_on(udp_loop(&rr, &args.numpy))
+                        .block_on(udp_loop(&rr, &mut exporter, &mut limiter, &mut slicer))
                         .unwrap();
                 })?;
             cube_thread.join().unwrap();
@@ -130,47 +170,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn format_cube(
-    cube: &RadarCube,
-    numpy: &Option<String>,
-) -> Result<Array2<i16>, Box<dyn std::error::Error>> {
-    if let Some(numpy) = numpy {
-        // Numpy requires complex arrays to be either f32 or f64.
-        let npdata = cube.data.mapv(|x| Complex32::new(x.re as f32, x.im as f32));
-        write_npy(
-            format!("{}/cube_{}.npy", numpy, cube.frame_counter),
-            &npdata,
-        )?;
-    }
-
-    // The radar cube shape is (sequence, range, rx antenna, doppler, complex).
-    // When saving the cube this shape should be maintained (possibly shuffled)
-    // but for display purposes we take the first sequence, first rx antenna,
-    // and the real portion of the signal (note drvegrd does imaginary first).
-    let data = cube.data.slice(s![1, .., 0, ..]);
-
-    // Convert the cube to real absolute values for display as rerun cannot
-    // handle complex numbers.  The absolute value is to ensure a constant
-    // background.
-    let data = data.mapv(|x| x.re.abs());
-
-    trace!(
-        "format_cube shape {:?} -> {:?}",
-        cube.data.shape(),
-        data.shape()
-    );
-
-    Ok(data)
-}
-
 async fn udp_loop(
     rr: &Option<RecordingStream>,
-    numpy: &Option<String>,
+    exporter: &mut Option<NpzExporter>,
+    limiter: &mut RateLimiter,
+    slicer: &mut CubeSlicer,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(numpy) = numpy {
-        std::fs::create_dir_all(numpy)?;
-    }
-
     let (tx5, rx) = kanal::bounded_async(128);
     let tx63 = tx5.clone();
 
@@ -181,7 +186,7 @@ async fn udp_loop(
                 .enable_all()
                 .build()
                 .unwrap()
-                .block_on(net::port5(tx5));
+                .block_on(net::port5(tx5, false, 10));
         })?;
 
     thread::Builder::new()
@@ -233,11 +238,22 @@ async fn udp_loop(
                         error!("dropped {} packets", cubemsg.packets_skipped);
                     }
 
-                    let cube = format_cube(&cubemsg, numpy)?;
+                    if let Some(exporter) = exporter {
+                        exporter.export(&cubemsg)?;
+                    }
 
                     if let Some(rr) = rr {
-                        let tensor = rerun::Tensor::try_from(cube)?;
-                        rr.log("cube", &tensor)?;
+                        rr.set_time(
+                            RADAR_TIMELINE,
+                            rerun::TimeCell::from_timestamp_nanos_since_epoch(
+                                cubemsg.timestamp as i64 * 1000,
+                            ),
+                        );
+                        if limiter.ready(cubemsg.timestamp) {
+                            let cube = slicer.format_cube(&cubemsg.data);
+                            let tensor = rerun::Tensor::try_from(cube)?;
+                            rr.log("cube", &tensor)?;
+                        }
 
                         rr.log(
                             "cube/speed_per_bin",
@@ -278,12 +294,10 @@ async fn udp_loop(
 fn pcap_loop(
     rr: &Option<RecordingStream>,
     path: &String,
-    numpy: &Option<String>,
+    exporter: &mut Option<NpzExporter>,
+    limiter: &mut RateLimiter,
+    slicer: &mut CubeSlicer,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(numpy) = numpy {
-        std::fs::create_dir_all(numpy)?;
-    }
-
     let file = File::open(path)?;
     let mut reader = RadarCubeReader::default();
     let mut frame_num = 0;
@@ -302,12 +316,23 @@ fn pcap_loop(
                             Ok(Some(cubemsg)) => {
                                 frame_num += 1;
                                 let time = frame_num as f32 * 0.055;
-                                let cube = format_cube(&cubemsg, numpy)?;
+                                if let Some(exporter) = exporter {
+                                    exporter.export(&cubemsg)?;
+                                }
 
                                 if let Some(rr) = rr {
                                     rr.set_time_secs("stable_time", time as f64);
-                                    let tensor = rerun::Tensor::try_from(cube)?;
-                                    rr.log("cube", &tensor)?;
+                                    rr.set_time(
+                                        RADAR_TIMELINE,
+                                        rerun::TimeCell::from_timestamp_nanos_since_epoch(
+                                            cubemsg.timestamp as i64 * 1000,
+                                        ),
+                                    );
+                                    if limiter.ready(cubemsg.timestamp) {
+                                        let cube = slicer.format_cube(&cubemsg.data);
+                                        let tensor = rerun::Tensor::try_from(cube)?;
+                                        rr.log("cube", &tensor)?;
+                                    }
                                 }
                             }
                             Ok(None) => (),
@@ -326,7 +351,7 @@ fn pcap_loop(
 }
 
 #[cfg(feature = "can")]
-async fn can_loop(rr: &Option<RecordingStream>, device: Option<String>) {
+async fn can_loop(rr: &Option<RecordingStream>, device: Option<String>, mirror: bool) {
     use can;
     use rerun::Points3D;
     use tokio::task::yield_now;
@@ -340,30 +365,32 @@ async fn can_loop(rr: &Option<RecordingStream>, device: Option<String>) {
 
     debug!("opening can interface {}", iface);
     let sock = socketcan::tokio::CanSocket::open(&iface).unwrap();
+    let mut normaliser = PowerNormaliser::new(DEFAULT_COLORMAP_EMA_ALPHA);
 
     loop {
-        match can::read_message(&sock).await {
+        match can::read_message(&sock, &can::CanIds::default()).await {
             Err(err) => println!("Error: {:?}", err),
             Ok(msg) => {
                 trace!("radar can header {:?}", msg.header);
+                let targets = &msg.targets[..msg.header.n_targets];
+                normaliser.update(targets.iter().map(|tgt| tgt.power));
 
                 if let Some(rr) = rr {
+                    let colors = targets
+                        .iter()
+                        .map(|tgt| colormap_viridis_srgb(normaliser.normalize(tgt.power)));
                     rr.log(
                         "radar",
-                        &Points3D::new((0..msg.header.n_targets).map(|idx| {
-                            let tgt = &msg.targets[idx];
-                            transform_xyz(
+                        &Points3D::new(targets.iter().map(|tgt| {
+                            common::transform_xyz(
                                 tgt.range as f32,
                                 tgt.azimuth as f32,
                                 tgt.elevation as f32,
-                                false,
+                                mirror,
                             )
                         }))
                         .with_radii([0.5])
-                        .with_colors(
-                            msg.targets
-                                .map(|tgt| colormap_viridis_srgb(tgt.power as f32)),
-                        ),
+                        .with_colors(colors),
                     )
                     .unwrap()
                 }
@@ -372,22 +399,6 @@ async fn can_loop(rr: &Option<RecordingStream>, device: Option<String>) {
     }
 }
 
-#[cfg(feature = "can")]
-fn transform_xyz(range: f32, azimuth: f32, elevation: f32, mirror: bool) -> [f32; 3] {
-    use core::f32::consts::PI;
-
-    let azi = azimuth / 180.0 * PI;
-    let ele = elevation / 180.0 * PI;
-    let x = range * ele.cos() * azi.cos();
-    let y = range * ele.cos() * azi.sin();
-    let z = range * ele.sin();
-    if mirror {
-        [x, -y, z]
-    } else {
-        [x, y, z]
-    }
-}
-
 #[cfg(feature = "can")]
 fn colormap_viridis_srgb(t: f32) -> [u8; 4] {
     use rerun::external::glam::Vec3A;
@@ -0,0 +1,379 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Point layouts of the published point clouds.
+//!
+//! The fields of the targets and clusters PointCloud2 messages depend on the
+//! command line, and have changed between releases.  radarpub builds the
+//! messages from a [`PointLayout`] and publishes the layouts of its point
+//! cloud topics in a [`schema_json`] message, `rt/radar/schema` by default,
+//! so a subscriber can check its expectations with [`layout_mismatches`]
+//! instead of silently misreading the points.
+
+use edgefirst_schemas::sensor_msgs::PointField;
+use serde_json::{json, Map, Value};
+
+/// Version of the schema message, raised when its structure changes.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// sensor_msgs/PointField UINT8 datatype.
+pub const UINT8: u8 = 2;
+
+/// sensor_msgs/PointField UINT32 datatype.
+pub const UINT32: u8 = 6;
+
+/// sensor_msgs/PointField FLOAT32 datatype.
+pub const FLOAT32: u8 = 7;
+
+/// A field of a point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// Field name
+    pub name: String,
+    /// Byte offset in the point
+    pub offset: u32,
+    /// sensor_msgs/PointField datatype
+    pub datatype: u8,
+}
+
+/// Fields and size of the points of a PointCloud2 topic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PointLayout {
+    /// Fields in offset order
+    pub fields: Vec<FieldLayout>,
+    /// Bytes per point
+    pub point_step: u32,
+}
+
+impl PointLayout {
+    /// Append a field taking `size` bytes, including any padding.
+    fn push(&mut self, name: &str, datatype: u8, size: u32) {
+        self.fields.push(FieldLayout {
+            name: name.to_string(),
+            offset: self.point_step,
+            datatype,
+        });
+        self.point_step += size;
+    }
+
+    /// The x, y, z, speed, power and rcs fields common to the targets and
+    /// clusters, followed by the optional confidence at offset 24.
+    fn base(include_confidence: bool) -> Self {
+        let mut layout = PointLayout::default();
+        for name in ["x", "y", "z", "speed", "power", "rcs"] {
+            layout.push(name, FLOAT32, 4);
+        }
+        if include_confidence {
+            layout.push("confidence", FLOAT32, 4);
+        }
+        layout
+    }
+
+    /// Layout of the targets topic.
+    ///
+    /// # Arguments
+    /// * `include_confidence` - Target confidence field
+    /// * `include_accuracy` - Firmware accuracy field
+    /// * `unfolded` - Unfolded Doppler speed field
+    /// * `annotated` - Track id and age fields of the annotated targets
+    pub fn targets(
+        include_confidence: bool,
+        include_accuracy: bool,
+        unfolded: bool,
+        annotated: bool,
+    ) -> Self {
+        let mut layout = PointLayout::base(include_confidence);
+        if include_accuracy {
+            layout.push("accuracy", FLOAT32, 4);
+        }
+        if unfolded {
+            layout.push("speed_unfolded", FLOAT32, 4);
+        }
+        if annotated {
+            layout.push("track_id", FLOAT32, 4);
+            layout.push("track_age", FLOAT32, 4);
+        }
+        layout
+    }
+
+    /// Layout of the clusters topic, the point role is padded to keep the
+    /// points 4 byte aligned.  The cluster_id follows the rcs at offset 24,
+    /// or the confidence at offset 28 when it is included.
+    pub fn clusters(include_confidence: bool, roles: bool) -> Self {
+        let mut layout = PointLayout::base(include_confidence);
+        layout.push("cluster_id", FLOAT32, 4);
+        if roles {
+            layout.push("point_role", UINT8, 4);
+        }
+        layout
+    }
+
+    /// Returns the field with the name, if any.
+    pub fn field(&self, name: &str) -> Option<&FieldLayout> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// The fields of a PointCloud2 message with this layout.
+    pub fn point_fields(&self) -> Vec<PointField> {
+        self.fields
+            .iter()
+            .map(|field| PointField {
+                name: field.name.clone(),
+                offset: field.offset,
+                datatype: field.datatype,
+                count: 1,
+            })
+            .collect()
+    }
+
+    /// The layout of the fields of a PointCloud2 message.
+    pub fn from_point_fields(fields: &[PointField], point_step: u32) -> Self {
+        PointLayout {
+            fields: fields
+                .iter()
+                .map(|field| FieldLayout {
+                    name: field.name.clone(),
+                    offset: field.offset,
+                    datatype: field.datatype,
+                })
+                .collect(),
+            point_step,
+        }
+    }
+
+    /// JSON object of the point step and the fields.
+    pub fn to_json(&self) -> Value {
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|field| {
+                json!({
+                    "name": field.name,
+                    "offset": field.offset,
+                    "datatype": field.datatype,
+                })
+            })
+            .collect();
+        json!({
+            "point_step": self.point_step,
+            "fields": fields,
+        })
+    }
+
+    /// Parse a [`PointLayout::to_json`] object, None if malformed.
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let fields = value["fields"]
+            .as_array()?
+            .iter()
+            .map(|field| {
+                Some(FieldLayout {
+                    name: field["name"].as_str()?.to_string(),
+                    offset: u32::try_from(field["offset"].as_u64()?).ok()?,
+                    datatype: u8::try_from(field["datatype"].as_u64()?).ok()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(PointLayout {
+            fields,
+            point_step: u32::try_from(value["point_step"].as_u64()?).ok()?,
+        })
+    }
+}
+
+/// Schema message with the [`SCHEMA_VERSION`] and the layout of every point
+/// cloud topic.
+pub fn schema_json(topics: &[(&str, &PointLayout)]) -> Value {
+    let topics: Map<_, _> = topics
+        .iter()
+        .map(|(topic, layout)| (topic.to_string(), layout.to_json()))
+        .collect();
+    json!({
+        "version": SCHEMA_VERSION,
+        "topics": topics,
+    })
+}
+
+/// Parse a [`schema_json`] message into its version and the layout of every
+/// topic ordered by topic, None if malformed.
+pub fn parse_schema(value: &Value) -> Option<(u64, Vec<(String, PointLayout)>)> {
+    let version = value["version"].as_u64()?;
+    let mut topics = value["topics"]
+        .as_object()?
+        .iter()
+        .map(|(topic, layout)| Some((topic.clone(), PointLayout::from_json(layout)?)))
+        .collect::<Option<Vec<_>>>()?;
+    topics.sort_by(|a, b| a.0.cmp(&b.0));
+    Some((version, topics))
+}
+
+/// Name of a sensor_msgs/PointField datatype.
+pub fn datatype_name(datatype: u8) -> &'static str {
+    match datatype {
+        1 => "INT8",
+        UINT8 => "UINT8",
+        3 => "INT16",
+        4 => "UINT16",
+        5 => "INT32",
+        UINT32 => "UINT32",
+        FLOAT32 => "FLOAT32",
+        8 => "FLOAT64",
+        _ => "unknown",
+    }
+}
+
+/// Differences between the layout and the fields a subscriber expects, as
+/// (name, datatype) pairs, empty when every expected field is present with
+/// the expected datatype and within the point step.
+///
+/// # Examples
+///
+/// A subscriber checks the fields of a received PointCloud2 before reading
+/// the points at the field offsets.
+///
+/// ```
+/// use radarpub::schema::{layout_mismatches, PointLayout, FLOAT32};
+///
+/// // The fields, point step and data of a received targets message.
+/// let published = PointLayout::targets(false, false, false, false);
+/// let (fields, point_step) = (published.point_fields(), published.point_step);
+/// let data: Vec<u8> = [10.0f32, 2.0, 0.5, -3.0, 80.0, 4.5]
+///     .iter()
+///     .flat_map(|value| value.to_le_bytes())
+///     .collect();
+///
+/// let layout = PointLayout::from_point_fields(&fields, point_step);
+/// let expected = [("x", FLOAT32), ("speed", FLOAT32)];
+/// assert!(layout_mismatches(&layout, &expected).is_empty());
+///
+/// let offset = layout.field("speed").unwrap().offset as usize;
+/// for point in data.chunks_exact(layout.point_step as usize) {
+///     let speed = f32::from_le_bytes(point[offset..offset + 4].try_into().unwrap());
+///     assert_eq!(speed, -3.0);
+/// }
+///
+/// let mismatches = layout_mismatches(&layout, &[("track_id", FLOAT32)]);
+/// assert_eq!(mismatches, ["missing field track_id"]);
+/// ```
+pub fn layout_mismatches(layout: &PointLayout, expected: &[(&str, u8)]) -> Vec<String> {
+    expected
+        .iter()
+        .filter_map(|&(name, datatype)| match layout.field(name) {
+            None => Some(format!("missing field {}", name)),
+            Some(field) if field.datatype != datatype => Some(format!(
+                "field {} is {} instead of {}",
+                name,
+                datatype_name(field.datatype),
+                datatype_name(datatype)
+            )),
+            Some(field) if field.offset + 4 > layout.point_step => Some(format!(
+                "field {} at offset {} is beyond the point step of {}",
+                name, field.offset, layout.point_step
+            )),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(layout: &PointLayout) -> Vec<&str> {
+        layout.fields.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_point_layouts() {
+        let targets = PointLayout::targets(false, false, false, false);
+        assert_eq!(names(&targets), ["x", "y", "z", "speed", "power", "rcs"]);
+        assert_eq!(targets.point_step, 24);
+        assert_eq!(targets.field("rcs").unwrap().offset, 20);
+
+        let targets = PointLayout::targets(true, true, true, true);
+        assert_eq!(targets.point_step, 44);
+        assert_eq!(targets.field("confidence").unwrap().offset, 24);
+        assert_eq!(targets.field("accuracy").unwrap().offset, 28);
+        assert_eq!(targets.field("speed_unfolded").unwrap().offset, 32);
+        assert_eq!(targets.field("track_id").unwrap().offset, 36);
+        assert_eq!(targets.field("track_age").unwrap().offset, 40);
+
+        let clusters = PointLayout::clusters(false, false);
+        assert_eq!(clusters.point_step, 28);
+        assert_eq!(clusters.field("cluster_id").unwrap().offset, 24);
+        let clusters = PointLayout::clusters(true, true);
+        assert_eq!(clusters.point_step, 36);
+        assert_eq!(clusters.field("confidence").unwrap().offset, 24);
+        assert_eq!(clusters.field("cluster_id").unwrap().offset, 28);
+        let role = clusters.field("point_role").unwrap();
+        assert_eq!((role.offset, role.datatype), (32, UINT8));
+
+        let fields = clusters.point_fields();
+        assert_eq!(fields.len(), 9);
+        assert_eq!(
+            PointLayout::from_point_fields(&fields, clusters.point_step),
+            clusters
+        );
+    }
+
+    #[test]
+    fn test_schema_json() {
+        let targets = PointLayout::targets(true, false, false, true);
+        let clusters = PointLayout::clusters(false, false);
+        let schema = schema_json(&[
+            ("rt/radar/targets", &targets),
+            ("rt/radar/clusters", &clusters),
+        ]);
+        assert_eq!(schema["version"], SCHEMA_VERSION);
+        assert_eq!(schema["topics"]["rt/radar/targets"]["point_step"], 36);
+        assert_eq!(
+            schema["topics"]["rt/radar/targets"]["fields"][7],
+            json!({"name": "track_id", "offset": 28, "datatype": FLOAT32})
+        );
+
+        // The message survives the JSON text of the publication.
+        let schema: Value = serde_json::from_str(&schema.to_string()).unwrap();
+        let (version, topics) = parse_schema(&schema).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        assert_eq!(
+            topics,
+            vec![
+                ("rt/radar/clusters".to_string(), clusters),
+                ("rt/radar/targets".to_string(), targets),
+            ]
+        );
+        assert_eq!(parse_schema(&json!({"version": 1})), None);
+        assert_eq!(
+            parse_schema(&json!({"version": 1, "topics": {"t": {"fields": []}}})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_layout_mismatches() {
+        let expected = [("x", FLOAT32), ("power", FLOAT32), ("track_id", UINT32)];
+        let layout = PointLayout::targets(false, false, false, true);
+        assert_eq!(
+            layout_mismatches(&layout, &expected),
+            vec!["field track_id is FLOAT32 instead of UINT32"]
+        );
+        assert_eq!(
+            layout_mismatches(&layout, &expected[..2]),
+            Vec::<String>::new()
+        );
+
+        let layout = PointLayout::clusters(false, false);
+        assert_eq!(
+            layout_mismatches(&layout, &expected),
+            vec!["missing field track_id"]
+        );
+
+        let truncated = PointLayout {
+            point_step: 16,
+            ..layout
+        };
+        assert_eq!(
+            layout_mismatches(&truncated, &expected[..2]),
+            vec!["field power at offset 16 is beyond the point step of 16"]
+        );
+    }
+}
@@ -0,0 +1,306 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::{
+    can::{read_status, CanReader, CanWriter, RecvResponseSettings, Status},
+    eth::SMS_PACKET_SIZE,
+};
+use serde_json::json;
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    io,
+    time::{Duration, Instant},
+};
+use tokio::{net::UdpSocket, task::JoinSet};
+use uuid::Uuid;
+use zenoh::{bytes::Encoding, Session};
+
+/// UDP ports the radar streams the SMS cube packets to.
+pub const UDP_PORTS: [u16; 2] = [50005, 50063];
+
+/// Outcome of a single self-test check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    /// Subsystem which was checked
+    pub name: &'static str,
+    /// True if the check passed before the timeout
+    pub passed: bool,
+    /// What the check found, or why it failed
+    pub detail: String,
+    /// Time the check took
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self.passed {
+            true => "PASS",
+            false => "FAIL",
+        };
+        write!(f, "{} {}: {}", status, self.name, self.detail)
+    }
+}
+
+/// Run a check, failing it if it does not complete within the timeout.
+pub async fn run_check<F, E>(name: &'static str, timeout: Duration, check: F) -> CheckResult
+where
+    F: Future<Output = Result<String, E>>,
+    E: fmt::Display,
+{
+    let start = Instant::now();
+    let (passed, detail) = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(detail)) => (true, detail),
+        Ok(Err(err)) => (false, err.to_string()),
+        Err(_) => (false, format!("no response within {:?}", timeout)),
+    };
+
+    CheckResult {
+        name,
+        passed,
+        detail,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Read the sensor serial number over CAN.
+pub async fn check_can(
+    can: &(impl CanReader + CanWriter),
+    settings: &RecvResponseSettings,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let serial_number = read_status(can, Status::SerialNumber, settings).await?;
+    Ok(format!("serial number {}", serial_number))
+}
+
+/// Bind every port on all interfaces, failing if any port is in use such as
+/// by a running radarpub.
+pub async fn bind_udp(ports: &[u16]) -> Result<Vec<UdpSocket>, io::Error> {
+    let mut sockets = Vec::with_capacity(ports.len());
+    for port in ports {
+        sockets.push(UdpSocket::bind(("0.0.0.0", *port)).await?);
+    }
+    Ok(sockets)
+}
+
+/// Wait for the first packet on any of the sockets and report which port
+/// produced it.
+pub async fn check_udp(sockets: Vec<UdpSocket>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut receivers = JoinSet::new();
+    for sock in sockets {
+        receivers.spawn(async move {
+            let port = sock.local_addr()?.port();
+            let mut buf = [0; SMS_PACKET_SIZE];
+            let (len, _) = sock.recv_from(&mut buf).await?;
+            Ok::<_, io::Error>((port, len))
+        });
+    }
+
+    // The remaining receivers are aborted when the set is dropped.
+    match receivers.join_next().await {
+        Some(result) => {
+            let (port, len) = result??;
+            Ok(format!("{} byte packet on port {}", len, port))
+        }
+        None => Err("no udp ports to check".into()),
+    }
+}
+
+/// Publish a unique message on a scratch key and wait to receive it back
+/// through the session.
+pub async fn check_zenoh(
+    session: &Session,
+    key: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let subscriber = session.declare_subscriber(key).await?;
+    let token = Uuid::new_v4().to_string();
+    session
+        .put(key, token.clone())
+        .encoding(Encoding::TEXT_PLAIN)
+        .await?;
+
+    while let Ok(sample) = subscriber.recv_async().await {
+        if sample
+            .payload()
+            .try_to_string()
+            .is_ok_and(|payload| payload == token.as_str())
+        {
+            return Ok(format!("loopback on {}", key));
+        }
+    }
+
+    Err("subscriber closed".into())
+}
+
+/// Machine readable summary of the checks as a single JSON line.
+pub fn format_summary(results: &[CheckResult]) -> String {
+    let checks = results
+        .iter()
+        .map(|result| {
+            json!({
+                "name": result.name,
+                "passed": result.passed,
+                "detail": result.detail,
+                "elapsed_ms": result.elapsed.as_millis() as u64,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "passed": results.iter().all(|result| result.passed),
+        "checks": checks,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::can::{CanInjector, Packet};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_run_check() {
+        let timeout = Duration::from_millis(50);
+        let passed = block_on(run_check("can", timeout, async {
+            Ok::<_, io::Error>("serial number 7".to_string())
+        }));
+        assert!(passed.passed);
+        assert_eq!(passed.detail, "serial number 7");
+        assert_eq!(passed.to_string(), "PASS can: serial number 7");
+
+        let failed = block_on(run_check("udp", timeout, async {
+            Err::<String, _>(io::Error::new(io::ErrorKind::AddrInUse, "port in use"))
+        }));
+        assert!(!failed.passed);
+        assert_eq!(failed.detail, "port in use");
+
+        // A check which never completes fails at the timeout.
+        let hung = block_on(run_check(
+            "zenoh",
+            timeout,
+            std::future::pending::<Result<String, io::Error>>(),
+        ));
+        assert!(!hung.passed);
+        assert!(hung.detail.starts_with("no response within"));
+        assert!(hung.elapsed >= timeout);
+    }
+
+    #[test]
+    fn test_check_can() {
+        let can = CanInjector::new();
+        let value = 123456u32.to_le_bytes();
+        can.extend([
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 5, 0, 0, 1, 0, 0]),
+            },
+            Packet { id: 0x700, data: 0 },
+            Packet {
+                id: 0x700,
+                data: u64::from_le_bytes([0, 0, 2, 0, value[0], value[1], value[2], value[3]]),
+            },
+            Packet { id: 0x700, data: 0 },
+        ]);
+        let settings = RecvResponseSettings {
+            max_retries: 1,
+            read_timeout: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(1);
+
+        let result = block_on(run_check("can", timeout, check_can(&can, &settings)));
+        assert!(result.passed, "{}", result);
+        assert_eq!(result.detail, "serial number 123456");
+
+        // A silent sensor fails the check.
+        let result = block_on(run_check("can", timeout, check_can(&can, &settings)));
+        assert!(!result.passed);
+    }
+
+    #[test]
+    fn test_check_udp() {
+        block_on(async {
+            let quiet = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let active = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let addr = active.local_addr().unwrap();
+
+            let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            sender.send_to(&[0; 32], addr).await.unwrap();
+
+            let result = run_check(
+                "udp",
+                Duration::from_secs(1),
+                check_udp(vec![quiet, active]),
+            )
+            .await;
+            assert!(result.passed, "{}", result);
+            assert_eq!(
+                result.detail,
+                format!("32 byte packet on port {}", addr.port())
+            );
+
+            // Without packets the check times out.
+            let quiet = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let result = run_check("udp", Duration::from_millis(50), check_udp(vec![quiet])).await;
+            assert!(!result.passed);
+        });
+    }
+
+    #[test]
+    fn test_check_zenoh() {
+        block_on(async {
+            let mut config = zenoh::Config::default();
+            config
+                .insert_json5("scouting/multicast/enabled", "false")
+                .unwrap();
+            config.insert_json5("listen/endpoints", "[]").unwrap();
+            let session = zenoh::open(config).await.unwrap();
+
+            let result = run_check(
+                "zenoh",
+                Duration::from_secs(1),
+                check_zenoh(&session, "rt/radar/self_test"),
+            )
+            .await;
+            assert!(result.passed, "{}", result);
+        });
+    }
+
+    #[test]
+    fn test_format_summary() {
+        let results = [
+            CheckResult {
+                name: "can",
+                passed: true,
+                detail: "serial number 7".to_string(),
+                elapsed: Duration::from_millis(12),
+            },
+            CheckResult {
+                name: "udp",
+                passed: false,
+                detail: "no response within 5s".to_string(),
+                elapsed: Duration::from_secs(5),
+            },
+        ];
+
+        let summary: serde_json::Value = serde_json::from_str(&format_summary(&results)).unwrap();
+        assert_eq!(summary["passed"], false);
+        assert_eq!(summary["checks"][0]["name"], "can");
+        assert_eq!(summary["checks"][0]["passed"], true);
+        assert_eq!(summary["checks"][0]["elapsed_ms"], 12);
+        assert_eq!(summary["checks"][1]["detail"], "no response within 5s");
+        assert!(!format_summary(&results).contains('\n'));
+
+        let summary: serde_json::Value =
+            serde_json::from_str(&format_summary(&results[..1])).unwrap();
+        assert_eq!(summary["passed"], true);
+    }
+}
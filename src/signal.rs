@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use ndarray::{Array2, Array4, Axis};
+use num::Complex;
+use rustfft::FftPlanner;
+use std::f32::consts::PI;
+
+/// Phase shift (delay-and-sum) beamforming across the receive channels of a
+/// radar cube.
+///
+/// The cube has the shape [chirp_types, range_gates, rx_channels,
+/// doppler_bins] as assembled by [`crate::eth::RadarCubeReader`].  The
+/// receive channels are treated as a uniform linear array with half
+/// wavelength spacing, so a target at azimuth θ advances the phase of every
+/// following channel by π·sin(θ).  For every steering angle the channels are
+/// rotated back by that phase and averaged, which keeps the output in the
+/// range of the input samples.  Only the first chirp type is beamformed as
+/// the chirp types of a range toggle cover different ranges.
+///
+/// # Arguments
+/// * `cube` - Radar cube of complex samples
+/// * `steering_angles_deg` - Steering angles in degrees, 0 is boresight
+///
+/// # Returns
+/// One [range_gates, doppler_bins] map per steering angle
+pub fn beamform_phase_shift(
+    cube: &Array4<Complex<i16>>,
+    steering_angles_deg: &[f32],
+) -> Vec<Array2<Complex<f32>>> {
+    let (range_gates, rx_channels, doppler_bins) =
+        (cube.shape()[1], cube.shape()[2], cube.shape()[3]);
+    if cube.is_empty() {
+        return steering_angles_deg
+            .iter()
+            .map(|_| Array2::zeros((range_gates, doppler_bins)))
+            .collect();
+    }
+
+    let chirp = cube.index_axis(Axis(0), 0);
+    steering_angles_deg
+        .iter()
+        .map(|angle| {
+            let phase = PI * (angle * PI / 180.0).sin();
+            let weights: Vec<_> = (0..rx_channels)
+                .map(|n| Complex::from_polar(1.0 / rx_channels as f32, -phase * n as f32))
+                .collect();
+
+            let mut beam = Array2::<Complex<f32>>::zeros((range_gates, doppler_bins));
+            for (n, weight) in weights.iter().enumerate() {
+                let channel = chirp.index_axis(Axis(1), n);
+                beam.zip_mut_with(&channel, |out, sample| {
+                    *out += Complex::new(sample.re as f32, sample.im as f32) * weight;
+                });
+            }
+            beam
+        })
+        .collect()
+}
+
+/// Doppler FFT of a radar cube of raw chirp samples.
+///
+/// Some sensor configurations output the time domain chirp samples instead
+/// of the range doppler matrix.  A Hanning window is applied to the samples
+/// of every (chirp_type, range_gate, rx_channel) before the FFT along the
+/// last axis to reduce the spectral leakage into neighbouring bins.  The
+/// output bins are in FFT order, bin 0 is zero Doppler and the bins above
+/// half of the doppler_bins are the negative Doppler frequencies.
+///
+/// # Arguments
+/// * `cube` - Radar cube of complex samples with the shape [chirp_types,
+///   range_gates, rx_channels, doppler_bins]
+///
+/// # Returns
+/// The unnormalized Doppler spectrum with the shape of the cube
+#[allow(dead_code)]
+pub fn apply_doppler_fft(cube: &Array4<Complex<i16>>) -> Array4<Complex<f32>> {
+    let mut spectrum = cube.mapv(|sample| Complex::new(sample.re as f32, sample.im as f32));
+    let doppler_bins = cube.shape()[3];
+    if spectrum.is_empty() {
+        return spectrum;
+    }
+
+    let window = hanning_window(doppler_bins);
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(doppler_bins);
+    let mut buffer = vec![Complex::default(); doppler_bins];
+    let mut scratch = vec![Complex::default(); fft.get_inplace_scratch_len()];
+
+    for mut lane in spectrum.lanes_mut(Axis(3)) {
+        for ((out, sample), weight) in buffer.iter_mut().zip(lane.iter()).zip(&window) {
+            *out = *sample * *weight;
+        }
+        fft.process_with_scratch(&mut buffer, &mut scratch);
+        for (out, bin) in lane.iter_mut().zip(&buffer) {
+            *out = *bin;
+        }
+    }
+
+    spectrum
+}
+
+/// Periodic Hanning window of the given length, which has no leakage beyond
+/// the neighbouring bins for frequencies on a bin.
+fn hanning_window(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / len as f32).cos())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beamform_plane_wave() {
+        // A plane wave from 30° on four channels, the phase advances by
+        // π·sin(30°) = π/2 per channel.
+        let channels = [(1000, 0), (0, 1000), (-1000, 0), (0, -1000)];
+        let mut cube = Array4::<Complex<i16>>::zeros((1, 3, 4, 2));
+        for ((_, _, n, _), sample) in cube.indexed_iter_mut() {
+            *sample = Complex::new(channels[n].0, channels[n].1);
+        }
+
+        let beams = beamform_phase_shift(&cube, &[30.0, 0.0, -30.0]);
+        assert_eq!(beams.len(), 3);
+        for beam in &beams {
+            assert_eq!(beam.shape(), &[3, 2]);
+        }
+
+        // The matching beam sums coherently, the others cancel out.
+        for sample in beams[0].iter() {
+            assert!((sample - Complex::new(1000.0, 0.0)).norm() < 0.1);
+        }
+        assert!(beams[1].iter().all(|sample| sample.norm() < 0.1));
+        assert!(beams[2].iter().all(|sample| sample.norm() < 0.1));
+    }
+
+    #[test]
+    fn test_beamform_boresight() {
+        let mut cube = Array4::<Complex<i16>>::zeros((2, 2, 3, 4));
+        cube.fill(Complex::new(-300, 600));
+        // Only the first chirp type is beamformed.
+        cube.index_axis_mut(Axis(0), 1)
+            .fill(Complex::new(32767, 32767));
+
+        let beams = beamform_phase_shift(&cube, &[0.0]);
+        for sample in beams[0].iter() {
+            assert!((sample - Complex::new(-300.0, 600.0)).norm() < 0.01);
+        }
+
+        assert!(beamform_phase_shift(&cube, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_doppler_fft_sinusoid() {
+        // A sinusoid completing 5 cycles over the 64 chirps of every lane.
+        let (doppler_bins, frequency) = (64, 5);
+        let mut cube = Array4::<Complex<i16>>::zeros((2, 3, 2, doppler_bins));
+        for ((_, _, _, n), sample) in cube.indexed_iter_mut() {
+            let phase = 2.0 * PI * (frequency * n) as f32 / doppler_bins as f32;
+            *sample = Complex::new(
+                (1000.0 * phase.cos()).round() as i16,
+                (1000.0 * phase.sin()).round() as i16,
+            );
+        }
+
+        let spectrum = apply_doppler_fft(&cube);
+        assert_eq!(spectrum.shape(), cube.shape());
+        for lane in spectrum.lanes(Axis(3)) {
+            let (peak, max) = lane
+                .iter()
+                .map(|bin| bin.norm())
+                .enumerate()
+                .fold((0, 0.0), |a, b| if b.1 > a.1 { b } else { a });
+            assert_eq!(peak, frequency);
+            // The window spreads the peak into the neighbouring bins only.
+            for (bin, value) in lane.iter().enumerate() {
+                if bin.abs_diff(frequency) > 1 {
+                    assert!(value.norm() < max * 0.01, "bin {}: {}", bin, value);
+                }
+            }
+        }
+
+        let empty = Array4::<Complex<i16>>::zeros((1, 0, 2, doppler_bins));
+        assert_eq!(apply_doppler_fft(&empty).shape(), &[1, 0, 2, doppler_bins]);
+    }
+}
@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::latency::BatchMetrics;
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use zenoh::{
+    bytes::{Encoding, ZBytes},
+    pubsub::Publisher,
+};
+
+/// Destination for serialized messages.
+///
+/// Implemented for the Zenoh [`Publisher`], the reconnecting publisher of
+/// the `reconnect` module, [`BatchSink`] and [`DiscardSink`] as well as
+/// [`VecSink`] which captures the messages in memory so the publishing
+/// pipeline can be tested without a Zenoh session.
+pub trait Sink: Send + Sync {
+    /// Publish the payload with the given encoding.
+    fn publish(
+        &self,
+        payload: ZBytes,
+        encoding: Encoding,
+    ) -> impl Future<Output = Result<(), zenoh::Error>> + Send;
+}
+
+impl Sink for Publisher<'_> {
+    async fn publish(&self, payload: ZBytes, encoding: Encoding) -> Result<(), zenoh::Error> {
+        self.put(payload).encoding(encoding).await
+    }
+}
+
+/// Sink which drops every message, the messages are still serialized by
+/// the publishing pipeline which makes it an honest dry run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiscardSink;
+
+impl Sink for DiscardSink {
+    async fn publish(&self, _payload: ZBytes, _encoding: Encoding) -> Result<(), zenoh::Error> {
+        Ok(())
+    }
+}
+
+/// Sink which holds messages until a batch is full and then publishes them
+/// in order, one put after the other.
+///
+/// Consecutive puts share the wakeups and system calls of the Zenoh link,
+/// trading publish latency for throughput.  A batch of 1 publishes every
+/// message immediately.  The owner calls [`BatchSink::flush`] once the
+/// [`BatchSink::deadline`] has passed so a partial batch is not held
+/// indefinitely, the time each message waited is recorded in the metrics.
+pub struct BatchSink<S> {
+    sink: S,
+    size: usize,
+    timeout: Duration,
+    pending: Mutex<Vec<(ZBytes, Encoding, Instant)>>,
+    metrics: Arc<Mutex<BatchMetrics>>,
+}
+
+impl<S: Sink> BatchSink<S> {
+    /// Create a sink publishing batches of `size` messages to `sink`, a
+    /// partial batch is due `timeout` after its first message.
+    pub fn new(sink: S, size: usize, timeout: Duration, metrics: Arc<Mutex<BatchMetrics>>) -> Self {
+        BatchSink {
+            sink,
+            size: size.max(1),
+            timeout,
+            pending: Mutex::new(Vec::with_capacity(size)),
+            metrics,
+        }
+    }
+
+    /// Returns the time the pending batch is due, None if nothing is pending.
+    pub fn deadline(&self) -> Option<Instant> {
+        let pending = self.pending.lock().unwrap();
+        pending.first().map(|(_, _, queued)| *queued + self.timeout)
+    }
+
+    /// Publish the pending messages in the order they were queued.
+    ///
+    /// Every message is published even if an earlier one fails, the first
+    /// error is returned.
+    pub async fn flush(&self) -> Result<(), zenoh::Error> {
+        let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let delays: Vec<_> = batch
+            .iter()
+            .map(|(_, _, queued)| now.duration_since(*queued))
+            .collect();
+        self.metrics.lock().unwrap().record(&delays);
+
+        let mut result = Ok(());
+        for (payload, encoding, _) in batch {
+            if let Err(e) = self.sink.publish(payload, encoding).await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<S: Sink> Sink for BatchSink<S> {
+    async fn publish(&self, payload: ZBytes, encoding: Encoding) -> Result<(), zenoh::Error> {
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push((payload, encoding, Instant::now()));
+            pending.len() >= self.size
+        };
+        match full {
+            true => self.flush().await,
+            false => Ok(()),
+        }
+    }
+}
+
+/// In-memory sink for unit testing.
+///
+/// Every published message is recorded in order and can be inspected with
+/// [`VecSink::messages`].  Clones share the same messages so a test can keep
+/// a handle while the code under test owns another.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct VecSink {
+    messages: Arc<Mutex<Vec<(ZBytes, Encoding)>>>,
+}
+
+#[allow(dead_code)]
+impl VecSink {
+    /// Create a new empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of messages published to the sink.
+    pub fn len(&self) -> usize {
+        self.messages.lock().unwrap().len()
+    }
+
+    /// Returns true if nothing has been published to the sink.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drain and return the messages published to the sink.
+    pub fn messages(&self) -> Vec<(ZBytes, Encoding)> {
+        std::mem::take(&mut *self.messages.lock().unwrap())
+    }
+}
+
+impl Sink for VecSink {
+    async fn publish(&self, payload: ZBytes, encoding: Encoding) -> Result<(), zenoh::Error> {
+        self.messages.lock().unwrap().push((payload, encoding));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_batch_sink() {
+        let metrics = Arc::new(Mutex::new(BatchMetrics::new()));
+        let inner = VecSink::new();
+        let sink = BatchSink::new(inner.clone(), 3, Duration::from_millis(50), metrics.clone());
+        let payloads = |messages: Vec<(ZBytes, Encoding)>| -> Vec<String> {
+            messages
+                .iter()
+                .map(|(payload, _)| payload.try_to_string().unwrap().to_string())
+                .collect()
+        };
+
+        block_on(async {
+            assert_eq!(sink.deadline(), None);
+            for i in 0..5 {
+                sink.publish(ZBytes::from(i.to_string()), Encoding::TEXT_PLAIN)
+                    .await
+                    .unwrap();
+            }
+            // The first batch is published in order, the rest is held.
+            assert_eq!(payloads(inner.messages()), ["0", "1", "2"]);
+            assert!(sink.deadline().unwrap() > Instant::now());
+
+            sink.flush().await.unwrap();
+            assert_eq!(payloads(inner.messages()), ["3", "4"]);
+            assert_eq!(sink.deadline(), None);
+            sink.flush().await.unwrap();
+            assert!(inner.is_empty());
+        });
+
+        let metrics = metrics.lock().unwrap();
+        assert_eq!(metrics.batches(), 2);
+        assert_eq!(metrics.messages(), 5);
+    }
+}
@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+//! Completed track log files.
+//!
+//! The [`TrackRecord`]s of the clustering track history are appended to a
+//! CSV file when the path ends in `.csv` and to a JSON lines file
+//! otherwise.  The JSON object of a record is also the payload of the live
+//! track events.
+
+use crate::clustering::TrackRecord;
+use serde_json::{json, Value};
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+};
+
+/// Header line of a CSV track log.
+pub const CSV_HEADER: &str = "cluster_id,uuid,first_seen,last_seen,max_speed,class,path";
+
+/// File format of a track log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackLogFormat {
+    /// One line per track after a [`CSV_HEADER`] line
+    Csv,
+    /// One [`track_json`] object per line
+    Jsonl,
+}
+
+impl TrackLogFormat {
+    /// CSV for a `.csv` path, in any case, JSON lines otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TrackLogFormat::Csv,
+            _ => TrackLogFormat::Jsonl,
+        }
+    }
+}
+
+/// JSON object of a track with the timestamps in nanoseconds, the max
+/// speed in m/s and the path as [x, y] points in meters.
+pub fn track_json(record: &TrackRecord) -> Value {
+    json!({
+        "cluster_id": record.cluster_id,
+        "uuid": record.uuid.to_string(),
+        "first_seen": record.first_seen,
+        "last_seen": record.last_seen,
+        "max_speed": record.max_speed,
+        "class": record.class.label(),
+        "path": record.path,
+    })
+}
+
+/// CSV line of a track, without the line break.  The path is a single
+/// column of space separated x and y pairs separated by semicolons.
+pub fn track_csv(record: &TrackRecord) -> String {
+    let path: Vec<_> = record
+        .path
+        .iter()
+        .map(|[x, y]| format!("{:.3} {:.3}", x, y))
+        .collect();
+    format!(
+        "{},{},{},{},{:.3},{},{}",
+        record.cluster_id,
+        record.uuid,
+        record.first_seen,
+        record.last_seen,
+        record.max_speed,
+        record.class.label(),
+        path.join(";")
+    )
+}
+
+/// Append the tracks to the log at `path`, creating it if needed.  A new or
+/// empty CSV log starts with the [`CSV_HEADER`].
+pub fn append_tracks(path: &Path, records: &[TrackRecord]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut out = String::new();
+    match TrackLogFormat::from_path(path) {
+        TrackLogFormat::Csv => {
+            if file.metadata()?.len() == 0 {
+                out.push_str(CSV_HEADER);
+                out.push('\n');
+            }
+            for record in records {
+                out.push_str(&track_csv(record));
+                out.push('\n');
+            }
+        }
+        TrackLogFormat::Jsonl => {
+            for record in records {
+                out.push_str(&track_json(record).to_string());
+                out.push('\n');
+            }
+        }
+    }
+    file.write_all(out.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::ObjectClass;
+    use std::fs;
+    use uuid::Uuid;
+
+    fn record(cluster_id: usize) -> TrackRecord {
+        TrackRecord {
+            cluster_id,
+            uuid: Uuid::from_u128(0x1234),
+            first_seen: 1_000_000_000,
+            last_seen: 3_500_000_000,
+            path: vec![[10.25, 2.125], [11.5, 2.0]],
+            max_speed: 4.0,
+            class: ObjectClass::Vehicle,
+        }
+    }
+
+    #[test]
+    fn test_append_tracks() {
+        let dir = std::env::temp_dir().join(format!("radarpub-tracklog-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let csv = dir.join("tracks.CSV");
+        assert_eq!(TrackLogFormat::from_path(&csv), TrackLogFormat::Csv);
+        append_tracks(&csv, &[record(3)]).unwrap();
+        append_tracks(&csv, &[record(4)]).unwrap();
+        let text = fs::read_to_string(&csv).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "3,00000000-0000-0000-0000-000000001234,1000000000,3500000000,4.000,vehicle,\
+             10.250 2.125;11.500 2.000"
+        );
+        assert!(lines[2].starts_with("4,"));
+
+        let jsonl = dir.join("tracks.jsonl");
+        assert_eq!(TrackLogFormat::from_path(&jsonl), TrackLogFormat::Jsonl);
+        append_tracks(&jsonl, &[record(3), record(4)]).unwrap();
+        let text = fs::read_to_string(&jsonl).unwrap();
+        let tracks: Vec<Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0], track_json(&record(3)));
+        assert_eq!(tracks[1]["cluster_id"], 4);
+        assert_eq!(tracks[0]["class"], "vehicle");
+        assert_eq!(tracks[0]["last_seen"], 3_500_000_000u64);
+        assert_eq!(tracks[0]["path"][1][0], 11.5);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use crate::{
+    can::{Header, Target},
+    common::transform_xyz,
+};
+
+/// Unambiguous radial speed interval in m/s of each frequency sweep, indexed
+/// by the frequency_sweep of the CAN header: long, medium, short and ultra
+/// short.
+///
+/// A speed measured by a sweep stands for any speed v + k·interval.  The
+/// sweeps use different chirp repetition intervals, so with range toggling
+/// consecutive frames measure the same speed modulo different intervals.
+/// The values are the Doppler bin count times the speed per bin of the radar
+/// cube bin properties, rounded to 0.1 m/s.  No two multiples of the
+/// intervals up to twice [`MAX_UNFOLDED_SPEED`] are closer than 2 m/s, which
+/// keeps the unfolding within [`SPEED_TOLERANCE`] unique.
+pub const SWEEP_SPEED_INTERVALS: [f64; 4] = [19.7, 25.3, 31.1, 43.3];
+
+/// Largest unfolded speed in m/s, 252 km/h.
+pub const MAX_UNFOLDED_SPEED: f64 = 70.0;
+
+/// Largest difference in m/s between the unfolded measurements of the two
+/// sweeps.
+pub const SPEED_TOLERANCE: f64 = 0.5;
+
+/// Largest distance in meters between the positions of a target in two
+/// consecutive frames, a target at [`MAX_UNFOLDED_SPEED`] moves 3.9 m in a
+/// 55 ms radar cycle.
+pub const MAX_PAIR_DISTANCE: f32 = 4.0;
+
+/// Returns the unambiguous speed interval of the frequency sweep, None for
+/// an unknown sweep.
+pub fn speed_interval(frequency_sweep: u8) -> Option<f64> {
+    SWEEP_SPEED_INTERVALS.get(frequency_sweep as usize).copied()
+}
+
+/// Unfold two ambiguous measurements of the same radial speed.
+///
+/// Following the Chinese remainder theorem every unfolding of the first
+/// measurement up to `max_speed` is compared with the nearest unfolding of
+/// the second measurement, the speed is the mean of the single pair which
+/// agrees within the tolerance.
+///
+/// # Arguments
+/// * `first` - Speed in m/s measured with the first interval
+/// * `first_interval` - Unambiguous speed interval of the first measurement
+/// * `second` - Speed in m/s measured with the second interval
+/// * `second_interval` - Unambiguous speed interval of the second
+///   measurement
+/// * `max_speed` - Largest absolute unfolded speed in m/s
+/// * `tolerance` - Largest difference in m/s of the unfolded measurements
+///
+/// # Returns
+/// The unfolded speed, or None if no unfolding or more than one agrees
+pub fn unfold_speed(
+    first: f64,
+    first_interval: f64,
+    second: f64,
+    second_interval: f64,
+    max_speed: f64,
+    tolerance: f64,
+) -> Option<f64> {
+    let folds = (max_speed / first_interval).ceil() as i64 + 1;
+    let mut unfolded = None;
+
+    for k in -folds..=folds {
+        let candidate = first + k as f64 * first_interval;
+        if candidate.abs() > max_speed {
+            continue;
+        }
+        let other = second + ((candidate - second) / second_interval).round() * second_interval;
+        if (candidate - other).abs() <= tolerance {
+            if unfolded.is_some() {
+                return None;
+            }
+            unfolded = Some((candidate + other) / 2.0);
+        }
+    }
+
+    unfolded
+}
+
+/// Targets of the previous frame kept for the pairing.
+#[derive(Debug, Clone)]
+struct PairFrame {
+    cycle_counter: u32,
+    frequency_sweep: u8,
+    points: Vec<([f32; 3], f64)>,
+}
+
+/// Unfolds the target speeds of range toggled frames.
+///
+/// Each frame is paired with the previous frame if the cycle counters are
+/// consecutive and the frequency sweeps differ.  A target is associated
+/// with the nearest target of the previous frame within
+/// [`MAX_PAIR_DISTANCE`] and its speed unfolded against the speed of that
+/// target, see [`unfold_speed`].
+#[derive(Debug, Clone, Default)]
+pub struct DopplerUnfolder {
+    previous: Option<PairFrame>,
+}
+
+impl DopplerUnfolder {
+    /// Create an unfolder without a previous frame.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unfold the target speeds of the frame and keep the frame for pairing
+    /// with the next frame.
+    ///
+    /// # Returns
+    /// The unfolded speed of each target, the raw speed if the frame is not
+    /// paired or the target speeds of the pair do not agree
+    pub fn unfold(&mut self, header: &Header, targets: &[Target]) -> Vec<f64> {
+        let points: Vec<_> = targets
+            .iter()
+            .map(|t| {
+                let xyz =
+                    transform_xyz(t.range as f32, t.azimuth as f32, t.elevation as f32, false);
+                (xyz, t.speed)
+            })
+            .collect();
+
+        let pair = self
+            .previous
+            .as_ref()
+            .filter(|prev| {
+                prev.cycle_counter.wrapping_add(1) == header.cycle_counter
+                    && prev.frequency_sweep != header.frequency_sweep
+            })
+            .and_then(|prev| {
+                let prev_interval = speed_interval(prev.frequency_sweep)?;
+                Some((prev, prev_interval, speed_interval(header.frequency_sweep)?))
+            });
+
+        let speeds = points
+            .iter()
+            .map(|(xyz, speed)| {
+                let Some((prev, prev_interval, interval)) = pair else {
+                    return *speed;
+                };
+                prev.points
+                    .iter()
+                    .map(|(p, prev_speed)| (distance(xyz, p), *prev_speed))
+                    .filter(|(d, _)| *d <= MAX_PAIR_DISTANCE)
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .and_then(|(_, prev_speed)| {
+                        unfold_speed(
+                            *speed,
+                            interval,
+                            prev_speed,
+                            prev_interval,
+                            MAX_UNFOLDED_SPEED,
+                            SPEED_TOLERANCE,
+                        )
+                    })
+                    .unwrap_or(*speed)
+            })
+            .collect();
+
+        self.previous = Some(PairFrame {
+            cycle_counter: header.cycle_counter,
+            frequency_sweep: header.frequency_sweep,
+            points,
+        });
+        speeds
+    }
+}
+
+fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Speed as measured with the unambiguous interval.
+    fn fold(speed: f64, interval: f64) -> f64 {
+        speed - (speed / interval).round() * interval
+    }
+
+    fn header(cycle_counter: u32, frequency_sweep: u8) -> Header {
+        Header {
+            seconds: 0,
+            nanoseconds: 0,
+            cycle_duration: 0.055,
+            cycle_counter,
+            n_targets: 0,
+            tx_antenna: 0,
+            frequency_sweep,
+            center_frequency: 0,
+        }
+    }
+
+    #[test]
+    fn test_unfold_speed() {
+        for (a, b) in [(0, 2), (1, 2), (0, 3), (1, 3), (2, 3), (0, 1)] {
+            let (first, second) = (SWEEP_SPEED_INTERVALS[a], SWEEP_SPEED_INTERVALS[b]);
+            for i in -139..=139 {
+                let speed = i as f64 * 0.5;
+                // Measurement noise within the tolerance.
+                let noise = if i % 2 == 0 { 0.2 } else { -0.2 };
+                let unfolded = unfold_speed(
+                    fold(speed, first),
+                    first,
+                    fold(speed + noise, second),
+                    second,
+                    MAX_UNFOLDED_SPEED,
+                    SPEED_TOLERANCE,
+                )
+                .unwrap_or_else(|| panic!("{} m/s with {} and {}", speed, first, second));
+                assert!(
+                    (unfolded - speed - noise / 2.0).abs() < 1e-9,
+                    "{}",
+                    unfolded
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_unfold_speed_inconsistent() {
+        // 40 m/s folds to 8.9 m/s by the short sweep, the medium sweep
+        // measuring 4 m/s instead of -10.6 m/s agrees with no unfolding.
+        let (medium, short) = (SWEEP_SPEED_INTERVALS[1], SWEEP_SPEED_INTERVALS[2]);
+        assert!((fold(40.0, short) - 8.9).abs() < 1e-9);
+        assert_eq!(
+            unfold_speed(8.9, short, 4.0, medium, MAX_UNFOLDED_SPEED, 0.5),
+            None
+        );
+
+        // Equal intervals never resolve the ambiguity.
+        assert_eq!(unfold_speed(5.0, 20.0, 5.0, 20.0, 70.0, 0.5), None);
+    }
+
+    #[test]
+    fn test_doppler_unfolder() {
+        let (long, short) = (SWEEP_SPEED_INTERVALS[0], SWEEP_SPEED_INTERVALS[2]);
+        let target = |range: f64, speed: f64| Target {
+            range,
+            speed,
+            ..Default::default()
+        };
+        let mut unfolder = DopplerUnfolder::new();
+
+        // The first frame has no pair.
+        let first = [target(50.0, fold(-35.0, short)), target(80.0, 3.0)];
+        assert_eq!(
+            unfolder.unfold(&header(10, 2), &first),
+            [fold(-35.0, short), 3.0]
+        );
+
+        // The long sweep frame pairs with the short sweep frame, the second
+        // target is too far from any target of the previous frame.
+        let second = [target(48.1, fold(-35.0, long)), target(120.0, 4.0)];
+        let speeds = unfolder.unfold(&header(11, 0), &second);
+        assert!((speeds[0] + 35.0).abs() < 1e-9, "{:?}", speeds);
+        assert_eq!(speeds[1], 4.0);
+
+        // A missed frame or a repeated sweep breaks the pairing.
+        let third = [target(46.2, fold(-35.0, short))];
+        assert_eq!(
+            unfolder.unfold(&header(13, 2), &third),
+            [fold(-35.0, short)]
+        );
+        assert_eq!(
+            unfolder.unfold(&header(14, 2), &third),
+            [fold(-35.0, short)]
+        );
+    }
+}
@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use log::warn;
+use ndarray::{s, Array2, Array4};
+use num::Complex;
+use std::{fmt, str::FromStr};
+
+/// Cube log rate of a live viewer when no rate is given, the full 18 Hz
+/// tensor stream overwhelms a remote viewer.
+pub const DEFAULT_CUBE_RATE: f64 = 5.0;
+
+/// Rerun timeline of the radar cube timestamps from the port header, the
+/// sensor clock can differ from the host clock by seconds so recordings are
+/// aligned on this timeline rather than the wall clock.
+pub const RADAR_TIMELINE: &str = "radar_timestamp";
+
+/// Smoothing factor of the target power range when none is given, the range
+/// follows a changed scene within about 20 frames.
+pub const DEFAULT_COLORMAP_EMA_ALPHA: f64 = 0.05;
+
+/// Sequence and receive antenna of the radar cube slice to display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CubeSlice {
+    /// Sequence (chirp type) index
+    pub sequence: usize,
+    /// Receive antenna index
+    pub rx: usize,
+}
+
+impl fmt::Display for CubeSlice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.sequence, self.rx)
+    }
+}
+
+impl FromStr for CubeSlice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected seq,rx indices, got '{}'", s);
+        match s.split(',').map(str::trim).collect::<Vec<_>>()[..] {
+            [sequence, rx] => Ok(CubeSlice {
+                sequence: sequence.parse().map_err(|_| invalid())?,
+                rx: rx.parse().map_err(|_| invalid())?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl CubeSlice {
+    /// Clamp the indices to a cube of the shape [sequence, range, rx,
+    /// doppler], a single chirp type configuration only has sequence 0.
+    pub fn clamp(&self, shape: &[usize]) -> CubeSlice {
+        CubeSlice {
+            sequence: self.sequence.min(shape[0].saturating_sub(1)),
+            rx: self.rx.min(shape[2].saturating_sub(1)),
+        }
+    }
+}
+
+/// Extracts the [range, doppler] display slice of radar cubes.
+///
+/// A requested slice outside the cube is clamped to the last sequence or
+/// antenna with a warning whenever the clamped slice changes, as the shape
+/// changes with the radar configuration.
+#[derive(Debug, Clone)]
+pub struct CubeSlicer {
+    slice: CubeSlice,
+    clamped: Option<CubeSlice>,
+}
+
+impl CubeSlicer {
+    /// Create a slicer for the requested sequence and antenna.
+    pub fn new(slice: CubeSlice) -> Self {
+        CubeSlicer {
+            slice,
+            clamped: None,
+        }
+    }
+
+    /// Format the radar cube for display.
+    ///
+    /// Rerun cannot display complex numbers so the absolute real part of the
+    /// slice is returned, the absolute value keeps the background constant.
+    pub fn format_cube(&mut self, data: &Array4<Complex<i16>>) -> Array2<i16> {
+        let shape = data.shape();
+        if shape[0] == 0 || shape[2] == 0 {
+            return Array2::zeros((shape[1], shape[3]));
+        }
+
+        let slice = self.slice.clamp(shape);
+        if slice != self.slice && self.clamped != Some(slice) {
+            warn!(
+                "cube slice {} out of range for {} sequences and {} rx antennas, showing {}",
+                self.slice, shape[0], shape[2], slice
+            );
+        }
+        self.clamped = (slice != self.slice).then_some(slice);
+
+        data.slice(s![slice.sequence, .., slice.rx, ..])
+            .mapv(|x| x.re.saturating_abs())
+    }
+}
+
+/// Drops cube logs beyond a maximum rate.
+///
+/// The rate is measured on the cube timestamps rather than the wall clock so
+/// a pcap replayed faster than real time is decimated the same way as the
+/// live stream.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    interval_us: u64,
+    last: Option<u64>,
+}
+
+impl RateLimiter {
+    /// Create a limiter passing at most `rate_hz` cubes per second, a rate of
+    /// 0 passes every cube.
+    pub fn new(rate_hz: f64) -> Self {
+        let interval_us = match rate_hz > 0.0 {
+            true => (1e6 / rate_hz) as u64,
+            false => 0,
+        };
+        RateLimiter {
+            interval_us,
+            last: None,
+        }
+    }
+
+    /// Returns true if the cube with the timestamp in microseconds should be
+    /// logged.
+    ///
+    /// The passed cubes are scheduled on the interval grid so the jitter of
+    /// the cube timestamps does not lower the rate, after a gap of more than
+    /// one interval the schedule restarts.  A timestamp going backwards, such
+    /// as a radar restart or a looping replay, also restarts it.
+    pub fn ready(&mut self, timestamp_us: u64) -> bool {
+        match self.last {
+            Some(last) if timestamp_us >= last && timestamp_us - last < self.interval_us => false,
+            Some(last) if timestamp_us >= last && timestamp_us - last < 2 * self.interval_us => {
+                self.last = Some(last + self.interval_us);
+                true
+            }
+            _ => {
+                self.last = Some(timestamp_us);
+                true
+            }
+        }
+    }
+}
+
+/// Normalises target powers for the colormap over the power range of the
+/// scene.
+///
+/// The frame minimum and maximum power are tracked with an exponential
+/// moving average so a single strong or weak target does not change the
+/// colour of every other target from one frame to the next.
+#[derive(Debug, Clone)]
+pub struct PowerNormaliser {
+    ema_min: f64,
+    ema_max: f64,
+    alpha: f64,
+}
+
+impl PowerNormaliser {
+    /// Create a normaliser moving the tracked range by `alpha` of the
+    /// difference to every frame range, 1 follows each frame.
+    pub fn new(alpha: f64) -> Self {
+        PowerNormaliser {
+            ema_min: f64::NAN,
+            ema_max: f64::NAN,
+            alpha: alpha.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Move the tracked range towards the power range of a frame, the first
+    /// frame sets the range.  Frames without finite powers are ignored.
+    pub fn update(&mut self, powers: impl IntoIterator<Item = f64>) {
+        let (min, max) = powers
+            .into_iter()
+            .filter(|power| power.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), power| {
+                (min.min(power), max.max(power))
+            });
+        if min > max {
+            return;
+        }
+
+        match self.ema_min.is_nan() {
+            true => (self.ema_min, self.ema_max) = (min, max),
+            false => {
+                self.ema_min += self.alpha * (min - self.ema_min);
+                self.ema_max += self.alpha * (max - self.ema_max);
+            }
+        }
+    }
+
+    /// Normalise a power in dBm to 0..=1 over the tracked range, powers
+    /// outside the range are clamped.  Before the first frame the power
+    /// starts the range.
+    pub fn normalize(&mut self, power: f64) -> f32 {
+        if !power.is_finite() {
+            return 0.0;
+        }
+        if self.ema_min.is_nan() {
+            self.update([power]);
+        }
+
+        let span = self.ema_max - self.ema_min;
+        match span > f64::EPSILON {
+            true => ((power - self.ema_min) / span).clamp(0.0, 1.0) as f32,
+            false => 0.5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        // 18 Hz cubes limited to 5 Hz.
+        let mut limiter = RateLimiter::new(5.0);
+        let passed: Vec<_> = (0..36u64)
+            .filter(|i| limiter.ready(1_000_000 + i * 55_555))
+            .collect();
+        assert_eq!(passed, vec![0, 4, 8, 11, 15, 19, 22, 26, 29, 33]);
+
+        // A timestamp going backwards restarts the limiter.
+        let mut limiter = RateLimiter::new(1.0);
+        assert!(limiter.ready(5_000_000));
+        assert!(!limiter.ready(5_500_000));
+        assert!(limiter.ready(1_000_000));
+        assert!(!limiter.ready(1_999_999));
+        assert!(limiter.ready(2_000_000));
+
+        let mut unlimited = RateLimiter::new(0.0);
+        assert!((0..10).all(|_| unlimited.ready(42)));
+    }
+
+    #[test]
+    fn test_power_normaliser() {
+        // Before the first frame a power starts the range.
+        let mut normaliser = PowerNormaliser::new(0.5);
+        assert_eq!(normaliser.normalize(-40.0), 0.5);
+
+        let mut normaliser = PowerNormaliser::new(0.5);
+        normaliser.update([-60.0, -80.0, -40.0]);
+        assert_eq!(normaliser.normalize(-60.0), 0.5);
+        assert_eq!(normaliser.normalize(-80.0), 0.0);
+        assert_eq!(normaliser.normalize(-10.0), 1.0);
+        assert_eq!(normaliser.normalize(f64::NAN), 0.0);
+
+        // A frame with one strong target moves the range by alpha.
+        normaliser.update([-80.0, 0.0]);
+        assert_eq!(normaliser.normalize(-40.0), 2.0 / 3.0);
+        normaliser.update([]);
+        normaliser.update([f64::NAN]);
+        assert_eq!(normaliser.normalize(-40.0), 2.0 / 3.0);
+
+        // An alpha of 1 follows every frame.
+        let mut normaliser = PowerNormaliser::new(1.0);
+        normaliser.update([-80.0, -40.0]);
+        normaliser.update([-20.0, -10.0]);
+        assert_eq!(normaliser.normalize(-15.0), 0.5);
+    }
+
+    #[test]
+    fn test_power_normaliser_colormap_range() {
+        // Raw powers well outside 0..=1 map into the colormap input range
+        // for every frame, including a frame beyond the smoothed range.
+        let mut normaliser = PowerNormaliser::new(DEFAULT_COLORMAP_EMA_ALPHA);
+        let frames: [&[f64]; 3] = [&[-10.0, 5.0, 30.0], &[-60.0, -45.0], &[42.0, 80.0, 12.5]];
+        for frame in frames {
+            normaliser.update(frame.iter().copied());
+            for power in frame {
+                let t = normaliser.normalize(*power);
+                assert!((0.0..=1.0).contains(&t), "{} -> {}", power, t);
+            }
+        }
+
+        // An alpha of 1 is the per-frame minimum and maximum.
+        let mut normaliser = PowerNormaliser::new(1.0);
+        for frame in frames {
+            normaliser.update(frame.iter().copied());
+            let min = frame.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = frame.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            assert_eq!(normaliser.normalize(min), 0.0);
+            assert_eq!(normaliser.normalize(max), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_cube_slice() {
+        assert_eq!("1,0".parse(), Ok(CubeSlice { sequence: 1, rx: 0 }));
+        assert_eq!(" 0 , 3 ".parse(), Ok(CubeSlice { sequence: 0, rx: 3 }));
+        for invalid in ["", "1", "1,2,3", "a,0", "-1,0"] {
+            assert!(invalid.parse::<CubeSlice>().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_format_cube_single_chirp_type() {
+        // Shape [1, R, A, D], only sequence 0 exists.
+        let mut data = Array4::<Complex<i16>>::zeros((1, 3, 4, 5));
+        data[[0, 2, 1, 4]] = Complex::new(-7, 9);
+        data[[0, 0, 3, 0]] = Complex::new(i16::MIN, 0);
+
+        let request = CubeSlice { sequence: 1, rx: 1 };
+        assert_eq!(
+            request.clamp(data.shape()),
+            CubeSlice { sequence: 0, rx: 1 }
+        );
+        let mut slicer = CubeSlicer::new(request);
+        let slice = slicer.format_cube(&data);
+        assert_eq!(slice.shape(), &[3, 5]);
+        assert_eq!(slice[[2, 4]], 7);
+
+        // Antennas beyond the cube are clamped to the last one.
+        let mut slicer = CubeSlicer::new(CubeSlice { sequence: 0, rx: 9 });
+        assert_eq!(slicer.format_cube(&data)[[0, 0]], i16::MAX);
+
+        let empty = Array4::<Complex<i16>>::zeros((0, 3, 4, 5));
+        assert_eq!(slicer.format_cube(&empty), Array2::zeros((3, 5)));
+    }
+
+    #[test]
+    fn test_format_cube_range_toggle() {
+        // Shape [2, R, A, D], both chirp types are selectable.
+        let mut data = Array4::<Complex<i16>>::zeros((2, 3, 4, 5));
+        data.index_axis_mut(ndarray::Axis(0), 0)
+            .fill(Complex::new(1, 0));
+        data.index_axis_mut(ndarray::Axis(0), 1)
+            .fill(Complex::new(-2, 0));
+
+        let first = CubeSlicer::new(CubeSlice { sequence: 0, rx: 3 }).format_cube(&data);
+        assert!(first.iter().all(|&x| x == 1));
+        let second = CubeSlicer::new(CubeSlice { sequence: 1, rx: 3 }).format_cube(&data);
+        assert!(second.iter().all(|&x| x == 2));
+        let clamped = CubeSlicer::new(CubeSlice { sequence: 5, rx: 0 }).format_cube(&data);
+        assert!(clamped.iter().all(|&x| x == 2));
+    }
+}
@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright (c) 2025 Au-Zone Technologies. All Rights Reserved.
+
+use log::debug;
+use std::time::{Duration, Instant};
+use tokio::{sync::watch, time::MissedTickBehavior};
+use zenoh::Session;
+
+/// Heartbeat checks per watchdog timeout, the watchdog expires at most a
+/// quarter of the timeout late.
+const WATCHDOG_CHECKS: u32 = 4;
+
+/// Subscribe to the heartbeat topic of an external supervisor and wait for
+/// the heartbeat to stop.
+///
+/// Any message on the topic counts as a heartbeat.  The timeout starts when
+/// the subscriber is declared so a supervisor which never publishes also
+/// expires the watchdog.
+///
+/// # Returns
+/// The time since the last heartbeat once it exceeds the timeout
+///
+/// # Errors
+/// Returns an error if the subscriber cannot be declared
+pub async fn watchdog(
+    session: &Session,
+    topic: &str,
+    timeout: Duration,
+) -> Result<Duration, zenoh::Error> {
+    let (heartbeat, receiver) = watch::channel(Instant::now());
+    // Undeclared when dropped on return.
+    let _subscriber = session
+        .declare_subscriber(topic)
+        .callback(move |_| {
+            heartbeat.send_replace(Instant::now());
+        })
+        .await?;
+    debug!(
+        "watchdog subscribed to {} with timeout {:?}",
+        topic, timeout
+    );
+
+    Ok(expired(receiver, timeout).await)
+}
+
+/// Wait until the latest heartbeat is older than the timeout.
+///
+/// # Returns
+/// The time since the latest heartbeat
+pub async fn expired(heartbeat: watch::Receiver<Instant>, timeout: Duration) -> Duration {
+    let mut interval = tokio::time::interval(timeout / WATCHDOG_CHECKS);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        let elapsed = heartbeat.borrow().elapsed();
+        if elapsed > timeout {
+            return elapsed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_watchdog_expires() {
+        let timeout = Duration::from_millis(100);
+        let (heartbeat, receiver) = watch::channel(Instant::now());
+
+        let start = Instant::now();
+        block_on(async {
+            let beats = async {
+                for _ in 0..5 {
+                    tokio::time::sleep(timeout / 2).await;
+                    heartbeat.send_replace(Instant::now());
+                }
+            };
+            // The heartbeats keep the watchdog alive.
+            tokio::select! {
+                _ = beats => {}
+                _ = expired(receiver.clone(), timeout) => panic!("watchdog expired"),
+            }
+
+            // Once the heartbeats stop the watchdog expires.
+            let elapsed = expired(receiver, timeout).await;
+            assert!(elapsed > timeout);
+            assert!(elapsed < timeout * 2);
+        });
+        assert!(start.elapsed() >= timeout * 5 / 2 + timeout);
+    }
+
+    #[test]
+    fn test_watchdog_subscription() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let mut config = zenoh::Config::default();
+            config
+                .insert_json5("scouting/multicast/enabled", "false")
+                .unwrap();
+            config.insert_json5("listen/endpoints", "[]").unwrap();
+            let session = zenoh::open(config).await.unwrap();
+
+            let topic = "test/watchdog/heartbeat";
+            let timeout = Duration::from_millis(200);
+            let publisher = session.clone();
+            let beats = tokio::spawn(async move {
+                for _ in 0..10 {
+                    publisher.put(topic, "alive").await.unwrap();
+                    tokio::time::sleep(timeout / 4).await;
+                }
+            });
+
+            let start = Instant::now();
+            let elapsed = watchdog(&session, topic, timeout).await.unwrap();
+            assert!(elapsed > timeout);
+            // The watchdog outlives the heartbeats of about 500ms.
+            assert!(start.elapsed() >= timeout * 2);
+            beats.await.unwrap();
+        });
+    }
+}